@@ -0,0 +1,7 @@
+// Licensed under the Open Software License version 3.0
+fn main() {
+    println!("cargo:rerun-if-changed=proto");
+    // Generate gRPC service only when it's going to be compiled
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/universal_data_source.proto").unwrap();
+}
@@ -0,0 +1,39 @@
+// Licensed under the Open Software License version 3.0
+//! Print every temperature sensor and UPS of a running instance
+//!
+//! `cargo run --example print_temperatures --features client -- http://localhost:63623`
+use universal_data_source::client::{types::SCHEMA_VERSION, Client};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let url = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| String::from("http://localhost:63623"));
+    let client = Client::new(&url);
+
+    let version = client.version().await?;
+    println!("{} {} ({})", url, version.version, version.instance_id);
+    if version.schema_version != SCHEMA_VERSION {
+        eprintln!(
+            "Server uses schema {}, this client was written for {}",
+            version.schema_version, SCHEMA_VERSION
+        );
+    }
+
+    for sensor in client.temperatures().await? {
+        let name = sensor.meta.hw.name.unwrap_or(sensor.meta.hw.id);
+        match sensor.temperature {
+            Some(temperature) => println!("{}: {:.1} °C", name, temperature),
+            None => println!("{}: unreadable", name),
+        }
+    }
+    for ups in client.upses().await? {
+        let status = ups.variables.get("ups.status").map_or("?", String::as_str);
+        let charge = ups
+            .variables
+            .get("battery.charge")
+            .map_or("?", String::as_str);
+        println!("{}: {} ({}%)", ups.meta.hw.id, status, charge);
+    }
+    Ok(())
+}
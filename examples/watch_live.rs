@@ -0,0 +1,31 @@
+// Licensed under the Open Software License version 3.0
+//! Print temperature and UPS updates as soon as they're pushed by `/ws`
+//!
+//! `cargo run --example watch_live --features client -- http://localhost:63623`
+use universal_data_source::client::{types::LiveEvent, Client};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let url = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| String::from("http://localhost:63623"));
+    let mut live = Client::new(&url).subscribe().await?;
+    println!("Watching {}", url);
+
+    while let Some(event) = live.next().await {
+        match event? {
+            LiveEvent::Temperature(sensors) => {
+                for sensor in sensors {
+                    println!("{}: {:?} °C", sensor.meta.hw.id, sensor.temperature);
+                }
+            }
+            LiveEvent::Ups(upses) => {
+                for ups in upses {
+                    println!("{}: {:?}", ups.meta.hw.id, ups.variables.get("ups.status"));
+                }
+            }
+        }
+    }
+    println!("Server closed the connection");
+    Ok(())
+}
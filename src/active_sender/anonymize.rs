@@ -0,0 +1,118 @@
+// Licensed under the Open Software License version 3.0
+use super::receiver::DataToSend;
+use crate::hardware::types::HardwareMetadata;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+// NUT variables holding serial numbers, ex. ups.serial and device.serial
+const SERIAL_SUFFIX: &str = ".serial";
+
+/// HMAC-SHA256 of `id` as lowercase hex, stable for the same `secret`
+pub fn hash_id(secret: &str, id: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(id.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn hash_meta(meta: &mut HardwareMetadata, secret: &str) {
+    meta.hw.id = hash_id(secret, &meta.hw.id);
+    // Friendly names often name a place or a person
+    meta.hw.name = None;
+    // Related ids are hw.ids too, hashed the same way so links still match
+    for relation in meta.relations.iter_mut().flatten() {
+        relation.id = hash_id(secret, &relation.id);
+    }
+}
+
+/// Replace every hw.id and related id with its hash, drop names and serial numbers
+pub fn anonymize_ids(data: &DataToSend, secret: &str) -> DataToSend {
+    let mut data = data.clone();
+    for sensor in data.sensors.iter_mut() {
+        hash_meta(&mut sensor.meta, secret);
+    }
+    for ups in data.upses.iter_mut() {
+        hash_meta(&mut ups.meta, secret);
+        ups.variables
+            .retain(|variable, _| !variable.ends_with(SERIAL_SUFFIX));
+    }
+    for reading in data.readings.iter_mut() {
+        hash_meta(&mut reading.meta, secret);
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::types::Example, hardware::types::HardwareRelation,
+        nut::sender::UninterruptiblePowerSupplyData, one_wire::sender::MeasuredTemperature,
+    };
+
+    #[test]
+    fn test_hash_id() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            hash_id("Jefe", "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_ne!(hash_id("a", "id"), hash_id("b", "id"));
+    }
+
+    #[test]
+    fn test_anonymize_ids() {
+        let data = DataToSend::new(
+            vec![MeasuredTemperature::example()],
+            vec![UninterruptiblePowerSupplyData::example()],
+            String::from("instance"),
+        );
+        let anonymized = anonymize_ids(&data, "secret");
+        let expected = hash_id("secret", "fake_hw_id");
+        assert_eq!(anonymized.sensors[0].meta.hw.id, expected);
+        assert_eq!(anonymized.upses[0].meta.hw.id, expected);
+        assert_eq!(
+            anonymized.sensors[0].temperature,
+            data.sensors[0].temperature
+        );
+        // Local copy is untouched
+        assert_eq!(data.sensors[0].meta.hw.id, "fake_hw_id");
+    }
+
+    #[test]
+    fn test_anonymize_names_and_serials() {
+        let mut ups = UninterruptiblePowerSupplyData::example();
+        ups.meta.hw.name = Some(String::from("John's office"));
+        ups.variables
+            .insert(String::from("ups.serial"), String::from("AS1234567890"));
+        ups.variables
+            .insert(String::from("device.serial"), String::from("AS1234567890"));
+        let data = DataToSend::new(vec![], vec![ups], String::from("instance"));
+        let anonymized = anonymize_ids(&data, "secret");
+        assert_eq!(anonymized.upses[0].meta.hw.name, None);
+        assert!(!anonymized.upses[0].variables.contains_key("ups.serial"));
+        assert!(!anonymized.upses[0].variables.contains_key("device.serial"));
+        assert_eq!(
+            anonymized.upses[0].variables.len(),
+            data.upses[0].variables.len() - 2
+        );
+    }
+
+    #[test]
+    fn test_anonymize_relations() {
+        let mut sensor = MeasuredTemperature::example();
+        sensor.meta.relations = Some(vec![HardwareRelation {
+            id: String::from("rack_1"),
+            kind: String::from("located_in"),
+        }]);
+        let data = DataToSend::new(vec![sensor], vec![], String::from("instance"));
+        let anonymized = anonymize_ids(&data, "secret");
+        let relations = anonymized.sensors[0].meta.relations.as_ref().unwrap();
+        assert_eq!(relations[0].id, hash_id("secret", "rack_1"));
+        assert_eq!(relations[0].kind, "located_in");
+    }
+}
@@ -0,0 +1,365 @@
+// Licensed under the Open Software License version 3.0
+//! Key layout of JSON payloads
+//!
+//! home-panel only accepts `sensors` and `upses`, so that layout stays the default and is pinned
+//! by contract tests below, independently of golden files. Other consumers may opt into canonical
+//! names matching the passive endpoint routes. Endpoints with a `category` get a single key and
+//! nothing else, for consumers that reject unknown fields.
+//!
+//! Third-party ingestion APIs may get a flat array of items (or NDJSON, an item per line) instead
+//! of an object, optionally nested under a single key.
+use super::{
+    config::{Endpoint, PayloadCategory, PayloadFormat, PayloadKeys},
+    receiver::DataToSend,
+};
+use crate::{
+    hardware::reading::Reading, nut::sender::UninterruptiblePowerSupplyData,
+    one_wire::sender::MeasuredTemperature,
+};
+use serde::{ser::SerializeMap, Serialize, Serializer};
+
+/// Same data as `DataToSend`, with keys of `/temperature`, `/ups` and `/readings`
+#[derive(Debug, Serialize)]
+pub(super) struct CanonicalPayload<'a> {
+    temperature: &'a [MeasuredTemperature],
+    ups: &'a [UninterruptiblePowerSupplyData],
+    readings: &'a [Reading],
+    instance_id: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum CategoryItems<'a> {
+    Sensors(&'a [MeasuredTemperature]),
+    Upses(&'a [UninterruptiblePowerSupplyData]),
+    Readings(&'a [Reading]),
+}
+
+/// Items of a single category under `key`
+#[derive(Debug)]
+pub(super) struct CategoryPayload<'a> {
+    key: String,
+    items: CategoryItems<'a>,
+}
+
+impl Serialize for CategoryPayload<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(&self.key, &self.items)?;
+        map.end()
+    }
+}
+
+/// Single item of an array payload, told apart by `meta.hw.hardware_type`
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub(super) enum Item<'a> {
+    Sensor(&'a MeasuredTemperature),
+    Ups(&'a UninterruptiblePowerSupplyData),
+    Reading(&'a Reading),
+}
+
+/// Another payload under `key`
+#[derive(Debug)]
+pub(super) struct WrappedPayload<'a> {
+    key: String,
+    payload: Box<Payload<'a>>,
+}
+
+impl Serialize for WrappedPayload<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(&self.key, &self.payload)?;
+        map.end()
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub(super) enum Payload<'a> {
+    HomePanel(&'a DataToSend),
+    Canonical(CanonicalPayload<'a>),
+    Category(CategoryPayload<'a>),
+    Items(Vec<Item<'a>>),
+    Wrapped(WrappedPayload<'a>),
+}
+
+impl<'a> Payload<'a> {
+    pub fn new(data: &'a DataToSend, keys: PayloadKeys) -> Self {
+        match keys {
+            PayloadKeys::HomePanel => Self::HomePanel(data),
+            PayloadKeys::Canonical => Self::Canonical(CanonicalPayload {
+                temperature: &data.sensors,
+                ups: &data.upses,
+                readings: &data.readings,
+                instance_id: &data.instance_id,
+            }),
+        }
+    }
+
+    /// Layout configured for `endpoint`
+    pub fn for_endpoint(data: &'a DataToSend, endpoint: &Endpoint) -> Self {
+        let format = endpoint.get_payload_format();
+        let payload = match format {
+            PayloadFormat::Object => Self::object(data, endpoint),
+            // Other categories are already removed by keep_categories
+            PayloadFormat::Array | PayloadFormat::Ndjson => Self::Items(
+                data.sensors
+                    .iter()
+                    .map(Item::Sensor)
+                    .chain(data.upses.iter().map(Item::Ups))
+                    .chain(data.readings.iter().map(Item::Reading))
+                    .collect(),
+            ),
+        };
+        match (&endpoint.wrap_key, format) {
+            (Some(key), PayloadFormat::Object | PayloadFormat::Array) => {
+                Self::Wrapped(WrappedPayload {
+                    key: key.clone(),
+                    payload: Box::new(payload),
+                })
+            }
+            _ => payload,
+        }
+    }
+
+    fn object(data: &'a DataToSend, endpoint: &Endpoint) -> Self {
+        let (category, key) = match (endpoint.category, endpoint.get_category_key()) {
+            (Some(category), Some(key)) => (category, key),
+            _ => return Self::new(data, endpoint.get_payload_keys()),
+        };
+        let items = match category {
+            PayloadCategory::Sensors => CategoryItems::Sensors(&data.sensors),
+            PayloadCategory::Upses => CategoryItems::Upses(&data.upses),
+            PayloadCategory::Readings => CategoryItems::Readings(&data.readings),
+        };
+        Self::Category(CategoryPayload { key, items })
+    }
+}
+
+/// Serialized body and its content type, NDJSON has every item of an array on its own line
+pub(super) fn encode_body<T: ?Sized + Serialize>(
+    json: &T,
+    format: PayloadFormat,
+) -> serde_json::Result<(Vec<u8>, &'static str)> {
+    if format != PayloadFormat::Ndjson {
+        return Ok((serde_json::to_vec(json)?, "application/json"));
+    }
+    let items = match serde_json::to_value(json)? {
+        serde_json::Value::Array(items) => items,
+        value => vec![value],
+    };
+    let mut body = Vec::new();
+    for item in items {
+        serde_json::to_writer(&mut body, &item)?;
+        body.push(b'\n');
+    }
+    Ok((body, "application/x-ndjson"))
+}
+
+/// Drop other categories, so parts are split and sized by what's actually sent
+pub(super) fn keep_categories(data: &mut DataToSend, categories: &[PayloadCategory]) {
+    if !categories.contains(&PayloadCategory::Sensors) {
+        data.sensors.clear();
+    }
+    if !categories.contains(&PayloadCategory::Upses) {
+        data.upses.clear();
+    }
+    if !categories.contains(&PayloadCategory::Readings) {
+        data.readings.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{assert_matches_golden_file, fixtures};
+    use serde_json::Value;
+
+    fn data() -> DataToSend {
+        let mut data = DataToSend::new(
+            vec![fixtures::sensor()],
+            vec![fixtures::ups()],
+            String::from(fixtures::INSTANCE_ID),
+        );
+        data.readings = vec![fixtures::reading()];
+        data
+    }
+
+    fn keys(value: &Value) -> Vec<&str> {
+        let mut keys: Vec<&str> = value
+            .as_object()
+            .expect("expected an object")
+            .keys()
+            .map(String::as_str)
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    /// Fields home-panel reads, extra fields are ignored by it
+    fn assert_home_panel_layout(payload: &Value) {
+        assert_eq!(
+            keys(payload),
+            vec!["instance_id", "readings", "sensors", "upses"]
+        );
+        assert!(payload["instance_id"].is_string());
+        for item in payload["sensors"]
+            .as_array()
+            .into_iter()
+            .chain(payload["upses"].as_array())
+            .flatten()
+        {
+            let meta = &item["meta"];
+            assert!(meta["hw"]["id"].is_string());
+            assert!(meta["hw"]["hardware_type"].is_string());
+            assert!(meta["source"]["source_type"].is_string());
+        }
+        let sensor = &payload["sensors"][0];
+        assert!(sensor["temperature"].is_number());
+        assert!(sensor["resolution"].is_number());
+        let ups = &payload["upses"][0];
+        assert!(ups["variables"]["ups.status"].is_string());
+    }
+
+    #[test]
+    fn test_home_panel_contract() {
+        let payload = serde_json::to_value(Payload::new(&data(), PayloadKeys::HomePanel)).unwrap();
+        assert_home_panel_layout(&payload);
+        // Default layout is identical to the internal representation
+        assert_eq!(payload, serde_json::to_value(data()).unwrap());
+    }
+
+    #[test]
+    fn test_golden_file_keeps_home_panel_layout() {
+        // Golden files can be regenerated, this contract can't
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/golden/active_sender_payload.json");
+        let golden: Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        assert_home_panel_layout(&golden);
+    }
+
+    #[test]
+    fn test_canonical_payload() {
+        let data = data();
+        let payload = serde_json::to_value(Payload::new(&data, PayloadKeys::Canonical)).unwrap();
+        assert_eq!(
+            keys(&payload),
+            vec!["instance_id", "readings", "temperature", "ups"]
+        );
+        let home_panel = serde_json::to_value(&data).unwrap();
+        assert_eq!(payload["temperature"], home_panel["sensors"]);
+        assert_eq!(payload["ups"], home_panel["upses"]);
+        assert_matches_golden_file("active_sender_payload_canonical", &payload);
+    }
+
+    #[test]
+    fn test_category_payload() {
+        let data = data();
+        let mut endpoint = Endpoint {
+            category: Some(PayloadCategory::Upses),
+            ..Default::default()
+        };
+        let payload = serde_json::to_value(Payload::for_endpoint(&data, &endpoint)).unwrap();
+        assert_eq!(keys(&payload), vec!["upses"]);
+        assert_eq!(
+            payload["upses"],
+            serde_json::to_value(&data).unwrap()["upses"]
+        );
+
+        endpoint.payload_keys = Some(PayloadKeys::Canonical);
+        let payload = serde_json::to_value(Payload::for_endpoint(&data, &endpoint)).unwrap();
+        assert_eq!(keys(&payload), vec!["ups"]);
+
+        endpoint.category = Some(PayloadCategory::Sensors);
+        endpoint.category_key = Some(String::from("temperatures"));
+        let payload = serde_json::to_value(Payload::for_endpoint(&data, &endpoint)).unwrap();
+        assert_eq!(keys(&payload), vec!["temperatures"]);
+        assert_eq!(payload["temperatures"][0]["temperature"], 21.5);
+    }
+
+    #[test]
+    fn test_array_payload() {
+        let mut data = data();
+        let mut endpoint = Endpoint {
+            payload_format: Some(PayloadFormat::Array),
+            ..Default::default()
+        };
+        let payload = serde_json::to_value(Payload::for_endpoint(&data, &endpoint)).unwrap();
+        let home_panel = serde_json::to_value(&data).unwrap();
+        assert_eq!(
+            payload,
+            serde_json::json!([
+                home_panel["sensors"][0],
+                home_panel["upses"][0],
+                home_panel["readings"][0]
+            ])
+        );
+
+        // Raw sensors only
+        endpoint.category = Some(PayloadCategory::Sensors);
+        keep_categories(&mut data, &endpoint.get_categories().unwrap());
+        let payload = serde_json::to_value(Payload::for_endpoint(&data, &endpoint)).unwrap();
+        assert_eq!(payload, home_panel["sensors"]);
+
+        endpoint.wrap_key = Some(String::from("data"));
+        let payload = serde_json::to_value(Payload::for_endpoint(&data, &endpoint)).unwrap();
+        assert_eq!(keys(&payload), vec!["data"]);
+        assert_eq!(payload["data"], home_panel["sensors"]);
+    }
+
+    #[test]
+    fn test_wrapped_object() {
+        let data = data();
+        let endpoint = Endpoint {
+            wrap_key: Some(String::from("data")),
+            ..Default::default()
+        };
+        let payload = serde_json::to_value(Payload::for_endpoint(&data, &endpoint)).unwrap();
+        assert_eq!(payload["data"], serde_json::to_value(&data).unwrap());
+    }
+
+    #[test]
+    fn test_encode_ndjson() {
+        let data = data();
+        let endpoint = Endpoint {
+            payload_format: Some(PayloadFormat::Ndjson),
+            // Ignored, lines have to be items
+            wrap_key: Some(String::from("data")),
+            ..Default::default()
+        };
+        let payload = Payload::for_endpoint(&data, &endpoint);
+        let (body, content_type) = encode_body(&payload, PayloadFormat::Ndjson).unwrap();
+        assert_eq!(content_type, "application/x-ndjson");
+        let body = String::from_utf8(body).unwrap();
+        let lines: Vec<Value> = body
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["meta"]["hw"]["hardware_type"], "TemperatureSensor");
+        assert!(body.ends_with('\n'));
+
+        let (body, content_type) = encode_body(&payload, PayloadFormat::Array).unwrap();
+        assert_eq!(content_type, "application/json");
+        assert!(serde_json::from_slice::<Value>(&body).unwrap().is_array());
+    }
+
+    #[test]
+    fn test_keep_categories() {
+        let mut data = data();
+        keep_categories(&mut data, &[PayloadCategory::Sensors]);
+        assert_eq!(data.sensors.len(), 1);
+        assert!(data.upses.is_empty());
+        assert!(data.readings.is_empty());
+
+        let mut data = self::data();
+        keep_categories(
+            &mut data,
+            &[PayloadCategory::Upses, PayloadCategory::Readings],
+        );
+        assert!(data.sensors.is_empty());
+        assert_eq!(data.upses.len(), 1);
+        assert_eq!(data.readings.len(), 1);
+    }
+}
@@ -0,0 +1,91 @@
+// Licensed under the Open Software License version 3.0
+//! Request body compression, signatures and `max_payload_size` still use uncompressed bodies
+use super::config::Compression;
+use crate::dedup_log::warn_deduplicated;
+use std::io::{self, Write};
+
+fn gzip(body: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(body)?;
+    encoder.finish()
+}
+
+#[cfg(feature = "zstd")]
+fn zstd(body: &[u8]) -> io::Result<Vec<u8>> {
+    zstd::encode_all(body, zstd::DEFAULT_COMPRESSION_LEVEL)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd(_body: &[u8]) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "this binary was built without zstd feature",
+    ))
+}
+
+/// Compressed body with its `Content-Encoding`, the original one if it can't be compressed
+pub fn compress(
+    body: Vec<u8>,
+    compression: Compression,
+    url: &str,
+) -> (Vec<u8>, Option<&'static str>) {
+    let (compressed, encoding) = match compression {
+        Compression::None => return (body, None),
+        Compression::Gzip => (gzip(&body), "gzip"),
+        Compression::Zstd => (zstd(&body), "zstd"),
+    };
+    match compressed {
+        Ok(compressed) => (compressed, Some(encoding)),
+        Err(error) => {
+            warn_deduplicated!(
+                format!("active_sender:compression:{}", url),
+                "Sending uncompressed data to {}, {} failed: {}",
+                url,
+                encoding,
+                error
+            );
+            (body, None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_gzip() {
+        let body = br#"{"sensors": [], "upses": [], "readings": []}"#.repeat(100);
+        let (compressed, encoding) = compress(body.clone(), Compression::Gzip, "test");
+        assert_eq!(encoding, Some("gzip"));
+        assert!(compressed.len() < body.len());
+        let mut decompressed = Vec::new();
+        flate2::read::GzDecoder::new(compressed.as_slice())
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, body);
+    }
+
+    #[test]
+    fn test_zstd() {
+        let body = b"1234".repeat(100);
+        let (compressed, encoding) = compress(body.clone(), Compression::Zstd, "test");
+        #[cfg(feature = "zstd")]
+        {
+            assert_eq!(encoding, Some("zstd"));
+            assert_eq!(zstd::decode_all(compressed.as_slice()).unwrap(), body);
+        }
+        #[cfg(not(feature = "zstd"))]
+        {
+            assert_eq!(encoding, None);
+            assert_eq!(compressed, body);
+        }
+    }
+
+    #[test]
+    fn test_none() {
+        let (body, encoding) = compress(b"[1]".to_vec(), Compression::None, "test");
+        assert_eq!((body.as_slice(), encoding), (b"[1]".as_slice(), None));
+    }
+}
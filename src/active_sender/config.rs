@@ -1,20 +1,317 @@
 // Licensed under the Open Software License version 3.0
 use crate::config::types::Example;
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::{collections::HashMap, time::Duration};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StartupCheckMethod {
+    Head,
+    Options,
+    Post,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OAuth2ClientCredentials {
+    pub token_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub scope: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HttpVersion {
+    // HTTP/2 only if negotiated with ALPN
+    #[default]
+    Auto,
+    // Never use HTTP/2, ex. for proxies with broken h2 support
+    Http1,
+    // HTTP/2 without negotiation, server must support it
+    Http2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadKeys {
+    // "sensors" and "upses", required by home-panel
+    #[default]
+    HomePanel,
+    // "temperature" and "ups", same as routes of the passive endpoint
+    Canonical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadCategory {
+    Sensors,
+    Upses,
+    Readings,
+}
+
+impl PayloadCategory {
+    /// Key used when `category_key` isn't set
+    pub fn default_key(&self, keys: PayloadKeys) -> &'static str {
+        match (self, keys) {
+            (Self::Sensors, PayloadKeys::HomePanel) => "sensors",
+            (Self::Sensors, PayloadKeys::Canonical) => "temperature",
+            (Self::Upses, PayloadKeys::HomePanel) => "upses",
+            (Self::Upses, PayloadKeys::Canonical) => "ups",
+            (Self::Readings, _) => "readings",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SendMethod {
+    #[default]
+    Post,
+    Put,
+    Patch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadFormat {
+    // Object with a key per category, see payload_keys and category
+    #[default]
+    Object,
+    // Items of every sent category in a single array, without keys and instance_id
+    Array,
+    // Same items as array, one per line (application/x-ndjson)
+    Ndjson,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    // Requires zstd feature, data is sent uncompressed without it
+    Zstd,
+}
+
+// Local time window in HH:MM format, end is exclusive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActiveHours {
+    pub start: String,
+    pub end: String,
+}
+
+// Send XML rendered from a template instead of JSON
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct XmlOutput {
+    pub template_file: String,
+    pub content_type: Option<String>,
+    // Required by most SOAP 1.1 services
+    pub soap_action: Option<String>,
+}
+
+impl XmlOutput {
+    pub fn get_content_type(&self) -> String {
+        self.content_type
+            .clone()
+            .unwrap_or_else(|| String::from("application/xml; charset=utf-8"))
+    }
+}
+
+// Response checks for endpoints that respond with 200 to rejected data
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct SuccessCriteria {
+    // Accepted statuses, any 2xx if not set
+    pub statuses: Option<Vec<u16>>,
+    // JSONPath of a value that has to be truthy, ex. $.ok
+    pub json_path: Option<String>,
+}
+
+impl SuccessCriteria {
+    pub fn is_accepted_status(&self, status: u16) -> bool {
+        match &self.statuses {
+            Some(statuses) => statuses.contains(&status),
+            None => (200..300).contains(&status),
+        }
+    }
+}
+
+// Payloads that failed with a connection error, timeout, 408, 429 or 5xx are sent again
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct RetryConfig {
+    // Attempts after the first failed one, then the payload is dropped
+    pub max_retries: Option<u32>,
+    // Queued payloads per endpoint, the oldest one is dropped when full
+    pub queue_size: Option<usize>,
+    // Doubled after every failed retry, up to max_backoff
+    pub initial_backoff: Option<Duration>,
+    pub max_backoff: Option<Duration>,
+}
+
+impl RetryConfig {
+    pub fn get_max_retries(&self) -> u32 {
+        self.max_retries.unwrap_or(5)
+    }
+
+    pub fn get_queue_size(&self) -> usize {
+        self.queue_size.unwrap_or(100).max(1)
+    }
+
+    pub fn get_initial_backoff(&self) -> Duration {
+        self.initial_backoff
+            .unwrap_or(Duration::from_secs(5))
+            .max(Duration::from_secs(1))
+    }
+
+    pub fn get_max_backoff(&self) -> Duration {
+        self.max_backoff
+            .unwrap_or(Duration::from_secs(5 * 60))
+            .max(self.get_initial_backoff())
+    }
+}
+
+// Payloads that would be dropped by the retry queue are written to disk instead
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpoolConfig {
+    // Every endpoint gets its own subdirectory
+    pub directory: String,
+    // Bytes per endpoint, the oldest payloads are removed when full
+    pub max_size: Option<u64>,
+}
+
+impl SpoolConfig {
+    pub fn get_max_size(&self) -> u64 {
+        self.max_size.unwrap_or(64 * 1024 * 1024)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub struct Endpoint {
     pub url: String,
     pub bearer_token: Option<String>,
+    // Re-read before each send, takes precedence over bearer_token
+    pub bearer_token_file: Option<String>,
+    // Takes precedence over both bearer_token and bearer_token_file
+    pub oauth2: Option<OAuth2ClientCredentials>,
+    // UPS variables that won't be sent to this endpoint
+    pub redacted_variables: Option<Vec<String>>,
+    // Hash hw.ids before sending, requires id_hash_secret
+    pub untrusted: Option<bool>,
+    // Only send within this local time window
+    pub active_hours: Option<ActiveHours>,
+    // Skip sending after this many requests in the last hour
+    pub max_sends_per_hour: Option<u32>,
+    // Payload format for legacy systems, max_payload_size doesn't apply
+    pub xml: Option<XmlOutput>,
+    pub http_version: Option<HttpVersion>,
+    // Respect cooldown and pause_until returned in JSON responses
+    pub accept_control: Option<bool>,
+    // Key names of JSON payloads, doesn't apply to xml
+    pub payload_keys: Option<PayloadKeys>,
+    // Send only this category, without instance_id
+    pub category: Option<PayloadCategory>,
+    // Top-level key of the category, overrides payload_keys
+    pub category_key: Option<String>,
+    // Override settings of ActiveSenderConfig for this endpoint
+    pub cooldown: Option<Duration>,
+    pub timeout: Option<Duration>,
+    pub ignore_connection_errors: Option<bool>,
+    pub heartbeat: Option<Duration>,
+    // Send only these categories, keeps the usual payload shape unlike category
+    pub categories: Option<Vec<PayloadCategory>>,
+    // Sent with every request, ex. X-Api-Key, Authorization replaces bearer_token
+    pub headers: Option<HashMap<String, String>>,
+    // HTTP method of data requests, the startup check uses its own one
+    pub method: Option<SendMethod>,
+    // Shape of JSON payloads, doesn't apply to xml
+    pub payload_format: Option<PayloadFormat>,
+    // Nest the whole payload under this key, ex. {"data": ...}, doesn't apply to ndjson
+    pub wrap_key: Option<String>,
+    // Content-Encoding of request bodies, the endpoint has to support it
+    pub compression: Option<Compression>,
+    // Response checks, any 2xx is a success if not set
+    pub success: Option<SuccessCriteria>,
+}
+
+impl Endpoint {
+    pub fn is_untrusted(&self) -> bool {
+        self.untrusted.unwrap_or_default()
+    }
+
+    pub fn get_http_version(&self) -> HttpVersion {
+        self.http_version.unwrap_or_default()
+    }
+
+    pub fn get_accept_control(&self) -> bool {
+        self.accept_control.unwrap_or_default()
+    }
+
+    pub fn get_payload_keys(&self) -> PayloadKeys {
+        self.payload_keys.unwrap_or_default()
+    }
+
+    pub fn get_method(&self) -> SendMethod {
+        self.method.unwrap_or_default()
+    }
+
+    pub fn get_payload_format(&self) -> PayloadFormat {
+        self.payload_format.unwrap_or_default()
+    }
+
+    pub fn get_compression(&self) -> Compression {
+        self.compression.unwrap_or_default()
+    }
+
+    pub fn get_success(&self) -> SuccessCriteria {
+        self.success.clone().unwrap_or_default()
+    }
+
+    /// Categories kept in payloads, `None` if everything is sent
+    pub fn get_categories(&self) -> Option<Vec<PayloadCategory>> {
+        self.category
+            .map(|category| vec![category])
+            .or_else(|| self.categories.clone())
+    }
+
+    pub fn get_headers(&self) -> HashMap<String, String> {
+        self.headers.clone().unwrap_or_default()
+    }
+
+    pub fn get_category_key(&self) -> Option<String> {
+        let category = self.category?;
+        Some(
+            self.category_key
+                .clone()
+                .unwrap_or_else(|| String::from(category.default_key(self.get_payload_keys()))),
+        )
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ActiveSenderConfig {
     enabled: Option<bool>,
     cooldown: Option<Duration>,
+    // Of a single request
+    timeout: Option<Duration>,
     ignore_connection_errors: Option<bool>,
     endpoints: Option<Vec<Endpoint>>,
+    // Max bytes of response body to log
+    response_preview_limit: Option<usize>,
+    // Check every endpoint once before sending any data
+    startup_check: Option<StartupCheckMethod>,
+    // Split snapshots larger than this many bytes into multiple requests
+    max_payload_size: Option<usize>,
+    // Local secret used to hash hw.ids for untrusted endpoints
+    id_hash_secret: Option<String>,
+    // Add X-Signature header with Ed25519 signature of every body
+    sign_payloads: Option<bool>,
+    // Queue failed payloads instead of dropping them
+    retry: Option<RetryConfig>,
+    // Keep payloads that failed too many times on disk, implies retry
+    spool: Option<SpoolConfig>,
+    // Send the latest data this long after the last send even if nothing changed
+    heartbeat: Option<Duration>,
+    // Don't wake endpoints for updates that didn't change any data
+    skip_unchanged: Option<bool>,
 }
 
 impl Default for ActiveSenderConfig {
@@ -22,8 +319,18 @@ impl Default for ActiveSenderConfig {
         Self {
             enabled: Some(false),
             cooldown: Some(Duration::from_secs(10)),
+            timeout: Some(Duration::from_secs(5)),
             ignore_connection_errors: Some(false),
             endpoints: None,
+            response_preview_limit: Some(4096),
+            startup_check: None,
+            max_payload_size: None,
+            id_hash_secret: None,
+            sign_payloads: Some(false),
+            retry: None,
+            spool: None,
+            heartbeat: None,
+            skip_unchanged: Some(false),
         }
     }
 }
@@ -33,17 +340,92 @@ impl Example for ActiveSenderConfig {
         Self {
             enabled: Some(true),
             cooldown: Some(Duration::from_secs(10)),
+            timeout: Some(Duration::from_secs(5)),
             ignore_connection_errors: Some(true),
             endpoints: Some(vec![
                 Endpoint {
                     url: String::from("http://localhost:3001/anything/status/200"),
                     bearer_token: None,
+                    redacted_variables: None,
+                    ..Default::default()
                 },
                 Endpoint {
                     url: String::from("https://home-panel.lan/api/trpc/m2m.storeUniversalData"),
                     bearer_token: Some(String::from("EXAMPLE_TOKEN")),
+                    redacted_variables: Some(vec![
+                        String::from("ups.serial"),
+                        String::from("battery.date"),
+                        String::from("driver.parameter.port"),
+                    ]),
+                    accept_control: Some(true),
+                    heartbeat: Some(Duration::from_secs(5 * 60)),
+                    ..Default::default()
+                },
+                Endpoint {
+                    url: String::from("https://aggregator.example.com/api/upload"),
+                    untrusted: Some(true),
+                    active_hours: Some(ActiveHours {
+                        start: String::from("06:00"),
+                        end: String::from("23:00"),
+                    }),
+                    max_sends_per_hour: Some(60),
+                    http_version: Some(HttpVersion::Http1),
+                    payload_keys: Some(PayloadKeys::Canonical),
+                    categories: Some(vec![PayloadCategory::Sensors, PayloadCategory::Readings]),
+                    ..Default::default()
+                },
+                Endpoint {
+                    url: String::from("http://power.lan/api/ups"),
+                    category: Some(PayloadCategory::Upses),
+                    category_key: Some(String::from("devices")),
+                    headers: Some(HashMap::from([
+                        (String::from("X-Api-Key"), String::from("EXAMPLE_KEY")),
+                        (String::from("X-Tenant"), String::from("home")),
+                    ])),
+                    cooldown: Some(Duration::from_secs(60)),
+                    timeout: Some(Duration::from_secs(15)),
+                    ignore_connection_errors: Some(false),
+                    ..Default::default()
+                },
+                Endpoint {
+                    url: String::from("https://ingest.example.com/v1/points"),
+                    method: Some(SendMethod::Put),
+                    payload_format: Some(PayloadFormat::Array),
+                    wrap_key: Some(String::from("data")),
+                    compression: Some(Compression::Gzip),
+                    success: Some(SuccessCriteria {
+                        statuses: Some(vec![200, 202]),
+                        json_path: Some(String::from("$.ok")),
+                    }),
+                    ..Default::default()
+                },
+                Endpoint {
+                    url: String::from("http://bms.lan/services/DataPoints"),
+                    xml: Some(XmlOutput {
+                        template_file: String::from("/etc/universal-data-source/bms.xml"),
+                        content_type: Some(String::from("text/xml; charset=utf-8")),
+                        soap_action: Some(String::from("urn:WriteDataPoints")),
+                    }),
+                    ..Default::default()
                 },
             ]),
+            response_preview_limit: Some(4096),
+            startup_check: Some(StartupCheckMethod::Head),
+            max_payload_size: Some(1024 * 1024),
+            id_hash_secret: Some(String::from("EXAMPLE_SECRET")),
+            sign_payloads: Some(false),
+            retry: Some(RetryConfig {
+                max_retries: Some(5),
+                queue_size: Some(100),
+                initial_backoff: Some(Duration::from_secs(5)),
+                max_backoff: Some(Duration::from_secs(5 * 60)),
+            }),
+            spool: Some(SpoolConfig {
+                directory: String::from("/var/spool/universal-data-source"),
+                max_size: Some(64 * 1024 * 1024),
+            }),
+            heartbeat: None,
+            skip_unchanged: Some(false),
         }
     }
 }
@@ -61,7 +443,69 @@ impl ActiveSenderConfig {
         self.endpoints.clone().unwrap_or_default()
     }
 
+    pub fn get_timeout(&self) -> Duration {
+        self.timeout.unwrap_or(Duration::from_secs(5))
+    }
+
     pub fn get_ignore_connection_errors(&self) -> bool {
         self.ignore_connection_errors.unwrap_or_default()
     }
+
+    pub fn get_skip_unchanged(&self) -> bool {
+        self.skip_unchanged.unwrap_or_default()
+    }
+
+    /// Cooldown of `endpoint`, its own one takes precedence
+    pub fn get_endpoint_cooldown(&self, endpoint: &Endpoint) -> Duration {
+        endpoint.cooldown.unwrap_or_else(|| self.get_cooldown())
+    }
+
+    /// Heartbeat interval of `endpoint`, its own one takes precedence and zero disables it
+    pub fn get_endpoint_heartbeat(&self, endpoint: &Endpoint) -> Option<Duration> {
+        endpoint
+            .heartbeat
+            .or(self.heartbeat)
+            .filter(|heartbeat| !heartbeat.is_zero())
+    }
+
+    pub fn get_endpoint_timeout(&self, endpoint: &Endpoint) -> Duration {
+        endpoint.timeout.unwrap_or_else(|| self.get_timeout())
+    }
+
+    pub fn get_endpoint_ignore_connection_errors(&self, endpoint: &Endpoint) -> bool {
+        endpoint
+            .ignore_connection_errors
+            .unwrap_or_else(|| self.get_ignore_connection_errors())
+    }
+
+    pub fn get_response_preview_limit(&self) -> usize {
+        self.response_preview_limit.unwrap_or(4096)
+    }
+
+    pub fn get_startup_check(&self) -> Option<StartupCheckMethod> {
+        self.startup_check.clone()
+    }
+
+    pub fn get_max_payload_size(&self) -> Option<usize> {
+        self.max_payload_size
+    }
+
+    pub fn get_id_hash_secret(&self) -> Option<String> {
+        self.id_hash_secret.clone()
+    }
+
+    pub fn get_sign_payloads(&self) -> bool {
+        self.sign_payloads.unwrap_or_default()
+    }
+
+    /// `None` if failed payloads are dropped, spool uses the default retry config
+    pub fn get_retry(&self) -> Option<RetryConfig> {
+        self.retry
+            .clone()
+            .or_else(|| self.spool.as_ref().map(|_| RetryConfig::default()))
+    }
+
+    pub fn get_spool(&self) -> Option<SpoolConfig> {
+        self.spool.clone()
+    }
 }
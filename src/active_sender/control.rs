@@ -0,0 +1,191 @@
+// Licensed under the Open Software License version 3.0
+//! Throttling requested by an endpoint in its response, ex. during backend maintenance
+use super::policy::SkipReason;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+/// Misbehaving endpoints can't silence a device for longer than this
+const MAX_THROTTLE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// JSON document an endpoint may return with a successful response
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ControlDocument {
+    /// Seconds between sends, only used if longer than configured `cooldown`
+    pub cooldown: Option<u64>,
+    /// RFC 3339 time until which nothing is sent
+    pub pause_until: Option<String>,
+}
+
+/// `None` if the body isn't a control document
+pub fn parse_control_document(body: &str) -> Option<ControlDocument> {
+    if body.trim().is_empty() {
+        return None;
+    }
+    serde_json::from_str(body).ok()
+}
+
+/// Latest control document of a single endpoint, replaced by every new one
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerControl {
+    cooldown: Option<Duration>,
+    pause_until: Option<DateTime<Utc>>,
+    // Monotonic equivalent of pause_until, so clock steps don't shorten or extend the pause
+    resume_at: Option<Instant>,
+}
+
+impl ServerControl {
+    /// Replace state with `document`, received at `now` (wall clock) and `instant`
+    pub fn apply(
+        &mut self,
+        document: ControlDocument,
+        now: DateTime<Utc>,
+        instant: Instant,
+        url: &str,
+    ) {
+        let cooldown = document
+            .cooldown
+            .map(|cooldown| Duration::from_secs(cooldown).min(MAX_THROTTLE));
+        let pause_until = document.pause_until.and_then(|pause_until| {
+            match DateTime::parse_from_rfc3339(&pause_until) {
+                Ok(pause_until) => Some(pause_until.with_timezone(&Utc)),
+                Err(error) => {
+                    tracing::warn!("Ignoring invalid pause_until from {}: {}", url, error);
+                    None
+                }
+            }
+        });
+        let max_pause_until = now + chrono::Duration::from_std(MAX_THROTTLE).unwrap();
+        let pause_until = pause_until
+            .filter(|pause_until| *pause_until > now)
+            .map(|pause_until| pause_until.min(max_pause_until));
+        let resume_at = pause_until
+            .map(|pause_until| instant + (pause_until - now).to_std().unwrap_or_default());
+        let control = Self {
+            cooldown,
+            pause_until,
+            resume_at,
+        };
+        if (control.cooldown, control.pause_until) != (self.cooldown, self.pause_until) {
+            tracing::info!(
+                "{} requested cooldown {:?} and pause until {:?}",
+                url,
+                control.cooldown,
+                control
+                    .pause_until
+                    .map(|pause_until| pause_until.to_rfc3339())
+            );
+        }
+        *self = control;
+    }
+
+    /// Configured `cooldown`, unless the endpoint asked for a longer one
+    pub fn get_cooldown(&self, configured: Duration) -> Duration {
+        self.cooldown
+            .map_or(configured, |cooldown| cooldown.max(configured))
+    }
+
+    /// `now` is monotonic, the wall clock is only used to report when sending resumes
+    pub fn check(&self, now: Instant) -> Result<(), SkipReason> {
+        match (self.pause_until, self.resume_at) {
+            (Some(pause_until), Some(resume_at)) if now < resume_at => {
+                Err(SkipReason::PausedByEndpoint(pause_until.to_rfc3339()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_server_control() {
+        let now = time("2023-01-01T12:00:00Z");
+        let instant = Instant::now();
+        let mut control = ServerControl::default();
+        let document =
+            parse_control_document(r#"{"cooldown": 30, "pause_until": "2023-01-01T13:00:00Z"}"#)
+                .unwrap();
+        control.apply(document, now, instant, "http://a");
+        assert_eq!(
+            control.get_cooldown(Duration::from_secs(10)),
+            Duration::from_secs(30)
+        );
+        // Can't make sending more frequent than configured
+        assert_eq!(
+            control.get_cooldown(Duration::from_secs(60)),
+            Duration::from_secs(60)
+        );
+        assert!(control.check(instant).is_err());
+        assert!(control
+            .check(instant + Duration::from_secs(60 * 60))
+            .is_ok());
+        // Empty document restores config
+        control.apply(
+            parse_control_document("{}").unwrap(),
+            now,
+            instant,
+            "http://a",
+        );
+        assert_eq!(control, ServerControl::default());
+    }
+
+    #[test]
+    fn test_pause_is_limited() {
+        let now = time("2023-01-01T12:00:00Z");
+        let mut control = ServerControl::default();
+        let document = ControlDocument {
+            cooldown: None,
+            pause_until: Some(String::from("2999-01-01T00:00:00Z")),
+        };
+        let instant = Instant::now();
+        control.apply(document, now, instant, "http://a");
+        assert!(control
+            .check(instant + MAX_THROTTLE - Duration::from_secs(1))
+            .is_err());
+        assert!(control.check(instant + MAX_THROTTLE).is_ok());
+    }
+
+    #[test]
+    fn test_pause_ignores_clock_steps() {
+        let now = time("2023-01-01T12:00:00Z");
+        let instant = Instant::now();
+        let mut control = ServerControl::default();
+        let document = ControlDocument {
+            cooldown: None,
+            pause_until: Some(String::from("2023-01-01T12:10:00Z")),
+        };
+        control.apply(document, now, instant, "http://a");
+        // Clock stepped 2 hours back after the pause was requested, still resumes after 10 minutes
+        assert!(control
+            .check(instant + Duration::from_secs(9 * 60))
+            .is_err());
+        assert!(control
+            .check(instant + Duration::from_secs(10 * 60))
+            .is_ok());
+        assert_eq!(
+            control.check(instant),
+            Err(SkipReason::PausedByEndpoint(String::from(
+                "2023-01-01T12:10:00+00:00"
+            )))
+        );
+    }
+
+    #[test]
+    fn test_parse_control_document() {
+        assert_eq!(parse_control_document(""), None);
+        assert_eq!(parse_control_document("OK"), None);
+        assert_eq!(
+            parse_control_document(r#"{"status": "stored"}"#),
+            Some(ControlDocument::default())
+        );
+    }
+}
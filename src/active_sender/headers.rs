@@ -0,0 +1,10 @@
+// Licensed under the Open Software License version 3.0
+//! Headers of multipart delivery, also read by the fleet head that reassembles the parts
+
+pub const PART_HEADER: &str = "X-Part";
+pub const TOTAL_PARTS_HEADER: &str = "X-Total-Parts";
+
+/// Part number and total number of parts, `None` if the snapshot wasn't split
+pub fn parse_part(part: Option<&str>, total: Option<&str>) -> Option<(usize, usize)> {
+    Some((part?.parse().ok()?, total?.parse().ok()?))
+}
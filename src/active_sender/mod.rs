@@ -1,3 +1,71 @@
 // Licensed under the Open Software License version 3.0
+#[cfg(feature = "active-sender")]
+mod anonymize;
+#[cfg(feature = "active-sender")]
+mod compat;
+#[cfg(feature = "active-sender")]
+mod compression;
+#[cfg_attr(not(feature = "active-sender"), allow(dead_code))]
 pub mod config;
+#[cfg(feature = "active-sender")]
+mod control;
+// Read by the fleet head of passive endpoint too
+#[cfg_attr(
+    not(any(
+        feature = "active-sender",
+        feature = "passive-endpoint",
+        feature = "axum"
+    )),
+    allow(dead_code)
+)]
+pub mod headers;
+#[cfg(feature = "active-sender")]
+mod multipart;
+#[cfg(feature = "active-sender")]
+mod policy;
+#[cfg(feature = "active-sender")]
+mod preview;
+#[cfg(feature = "active-sender")]
 pub mod receiver;
+#[cfg(feature = "active-sender")]
+mod retry;
+#[cfg(feature = "active-sender")]
+mod spool;
+#[cfg(feature = "active-sender")]
+mod startup_check;
+#[cfg(feature = "active-sender")]
+mod state;
+#[cfg(feature = "active-sender")]
+mod success;
+#[cfg(feature = "active-sender")]
+mod token;
+#[cfg(feature = "active-sender")]
+mod xml;
+
+#[cfg(feature = "active-sender")]
+pub use receiver::start_active_sender_loop;
+#[cfg(feature = "active-sender")]
+pub use state::ActiveSenderState;
+
+/// Stand-in for [`state::ActiveSenderState`] when built without the active-sender feature
+#[cfg(not(feature = "active-sender"))]
+#[derive(Debug, Clone, Default)]
+pub struct ActiveSenderState;
+
+/// Stand-in for [`receiver::start_active_sender_loop`] when built without the active-sender feature
+#[cfg(not(feature = "active-sender"))]
+pub async fn start_active_sender_loop(
+    _shutdown_rx: tokio::sync::broadcast::Receiver<()>,
+    config: config::ActiveSenderConfig,
+    _one_wire_rx: tokio::sync::broadcast::Receiver<
+        Vec<crate::one_wire::sender::MeasuredTemperature>,
+    >,
+    _ups_monitoring_rx: tokio::sync::broadcast::Receiver<crate::nut::sender::UpsUpdate>,
+    _readings_rx: tokio::sync::broadcast::Receiver<crate::hardware::reading::ReadingsUpdate>,
+    _instance_id: String,
+    _state: ActiveSenderState,
+) {
+    if config.is_enabled() {
+        tracing::error!("Active sender is enabled but the active-sender feature is disabled");
+    }
+}
@@ -0,0 +1,117 @@
+// Licensed under the Open Software License version 3.0
+use super::receiver::DataToSend;
+
+fn serialized_size(data: &DataToSend) -> usize {
+    serde_json::to_vec(data).map(|json| json.len()).unwrap_or(0)
+}
+
+fn split_in_half<T: Clone>(items: &[T]) -> (Vec<T>, Vec<T>) {
+    let (left, right) = items.split_at(items.len() / 2);
+    (left.to_vec(), right.to_vec())
+}
+
+/// Split `data` into parts that serialize to at most `max_size` bytes
+///
+/// Splits by category first, then by hw.id ranges within a category.
+/// A single item larger than `max_size` is still sent as its own part.
+pub fn split_data(data: &DataToSend, max_size: usize) -> Vec<DataToSend> {
+    let items = data.sensors.len() + data.upses.len() + data.readings.len();
+    if items <= 1 || serialized_size(data) <= max_size {
+        return vec![data.clone()];
+    }
+    let empty = DataToSend::new(vec![], vec![], data.instance_id.clone());
+    let categories = [
+        !data.sensors.is_empty(),
+        !data.upses.is_empty(),
+        !data.readings.is_empty(),
+    ];
+    let (left, right) = if categories.iter().filter(|present| **present).count() > 1 {
+        // Sensors in one part, everything else in the other
+        let mut left = empty.clone();
+        let mut right = data.clone();
+        if data.sensors.is_empty() {
+            left.upses = std::mem::take(&mut right.upses);
+        } else {
+            left.sensors = std::mem::take(&mut right.sensors);
+        }
+        (left, right)
+    } else {
+        let mut left = empty.clone();
+        let mut right = empty;
+        let mut sensors = data.sensors.clone();
+        sensors.sort_by(|a, b| a.meta.hw.id.cmp(&b.meta.hw.id));
+        (left.sensors, right.sensors) = split_in_half(&sensors);
+        let mut upses = data.upses.clone();
+        upses.sort_by(|a, b| a.meta.hw.id.cmp(&b.meta.hw.id));
+        (left.upses, right.upses) = split_in_half(&upses);
+        let mut readings = data.readings.clone();
+        readings.sort_by(|a, b| a.meta.hw.id.cmp(&b.meta.hw.id));
+        (left.readings, right.readings) = split_in_half(&readings);
+        (left, right)
+    };
+    let mut parts = split_data(&left, max_size);
+    parts.extend(split_data(&right, max_size));
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::types::Example, hardware::reading::Reading,
+        nut::sender::UninterruptiblePowerSupplyData, one_wire::sender::MeasuredTemperature,
+    };
+
+    fn sensor(id: &str) -> MeasuredTemperature {
+        let mut sensor = MeasuredTemperature::example();
+        sensor.meta.hw.id = String::from(id);
+        sensor
+    }
+
+    fn data(sensors: usize) -> DataToSend {
+        let sensors = (0..sensors).map(|i| sensor(&format!("{:02}", i))).collect();
+        let mut data = DataToSend::new(
+            sensors,
+            vec![UninterruptiblePowerSupplyData::example()],
+            String::from("instance"),
+        );
+        data.readings = vec![Reading::example()];
+        data
+    }
+
+    #[test]
+    fn test_small_payload_is_not_split() {
+        let data = data(3);
+        assert_eq!(split_data(&data, usize::MAX), vec![data]);
+    }
+
+    #[test]
+    fn test_parts_fit_and_keep_everything() {
+        let data = data(10);
+        let max_size = serialized_size(&data) / 4;
+        let parts = split_data(&data, max_size);
+        assert!(parts.len() > 2);
+        for part in &parts {
+            assert!(serialized_size(part) <= max_size);
+            assert_eq!(part.instance_id, "instance");
+        }
+        let sensors: Vec<String> = parts
+            .iter()
+            .flat_map(|part| part.sensors.iter().map(|sensor| sensor.meta.hw.id.clone()))
+            .collect();
+        let expected: Vec<String> = (0..10).map(|i| format!("{:02}", i)).collect();
+        assert_eq!(sensors, expected);
+        assert_eq!(parts.iter().map(|part| part.upses.len()).sum::<usize>(), 1);
+        assert_eq!(
+            parts.iter().map(|part| part.readings.len()).sum::<usize>(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_oversized_item_is_sent_alone() {
+        let data = data(2);
+        let parts = split_data(&data, 1);
+        assert_eq!(parts.len(), 4);
+    }
+}
@@ -0,0 +1,187 @@
+// Licensed under the Open Software License version 3.0
+use super::config::Endpoint;
+use chrono::NaiveTime;
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    OutsideActiveHours,
+    BudgetExhausted(u32),
+    // RFC 3339 time requested by the endpoint
+    PausedByEndpoint(String),
+}
+
+impl Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkipReason::OutsideActiveHours => write!(f, "outside active hours"),
+            SkipReason::BudgetExhausted(limit) => {
+                write!(f, "already sent {} times in the last hour", limit)
+            }
+            SkipReason::PausedByEndpoint(until) => write!(f, "paused by endpoint until {}", until),
+        }
+    }
+}
+
+fn parse_time(time: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(time, "%H:%M").ok()
+}
+
+/// Whether `now` is still within `cooldown` of the last send
+pub fn is_cooling_down(last_sent: Option<Instant>, now: Instant, cooldown: Duration) -> bool {
+    last_sent.map_or(false, |last_sent| {
+        now.saturating_duration_since(last_sent) <= cooldown
+    })
+}
+
+/// Enforces active hours and sends-per-hour budget of a single endpoint
+#[derive(Debug, Clone, Default)]
+pub struct SendPolicy {
+    active_hours: Option<(NaiveTime, NaiveTime)>,
+    max_sends_per_hour: Option<u32>,
+    sent: VecDeque<Instant>,
+}
+
+impl SendPolicy {
+    pub fn new(endpoint: &Endpoint) -> Self {
+        let active_hours = endpoint.active_hours.as_ref().and_then(|hours| {
+            match (parse_time(&hours.start), parse_time(&hours.end)) {
+                (Some(start), Some(end)) => Some((start, end)),
+                _ => {
+                    tracing::error!(
+                        "Invalid active_hours of {}, expected HH:MM, ignoring",
+                        endpoint.url
+                    );
+                    None
+                }
+            }
+        });
+        Self {
+            active_hours,
+            max_sends_per_hour: endpoint.max_sends_per_hour,
+            sent: VecDeque::new(),
+        }
+    }
+
+    fn is_active(&self, time: NaiveTime) -> bool {
+        match self.active_hours {
+            None => true,
+            Some((start, end)) if start <= end => start <= time && time < end,
+            // Window spans midnight, ex. 22:00-06:00
+            Some((start, end)) => time >= start || time < end,
+        }
+    }
+
+    /// Check whether sending is allowed at local `time` and monotonic `now`
+    pub fn check(&mut self, time: NaiveTime, now: Instant) -> Result<(), SkipReason> {
+        if !self.is_active(time) {
+            return Err(SkipReason::OutsideActiveHours);
+        }
+        if let Some(limit) = self.max_sends_per_hour {
+            while let Some(sent) = self.sent.front() {
+                if now.duration_since(*sent) < HOUR {
+                    break;
+                }
+                self.sent.pop_front();
+            }
+            if self.sent.len() >= limit as usize {
+                return Err(SkipReason::BudgetExhausted(limit));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn record_send(&mut self, now: Instant) {
+        if self.max_sends_per_hour.is_some() {
+            self.sent.push_back(now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{active_sender::config::ActiveHours, clock::MockClock};
+
+    fn endpoint(start: &str, end: &str, max_sends_per_hour: Option<u32>) -> Endpoint {
+        Endpoint {
+            active_hours: Some(ActiveHours {
+                start: String::from(start),
+                end: String::from(end),
+            }),
+            max_sends_per_hour,
+            ..Default::default()
+        }
+    }
+
+    fn time(time: &str) -> NaiveTime {
+        parse_time(time).unwrap()
+    }
+
+    #[test]
+    fn test_active_hours() {
+        let mut policy = SendPolicy::new(&endpoint("06:00", "23:00", None));
+        let now = Instant::now();
+        assert_eq!(policy.check(time("12:00"), now), Ok(()));
+        assert_eq!(
+            policy.check(time("23:30"), now),
+            Err(SkipReason::OutsideActiveHours)
+        );
+        assert_eq!(
+            policy.check(time("05:59"), now),
+            Err(SkipReason::OutsideActiveHours)
+        );
+    }
+
+    #[test]
+    fn test_active_hours_spanning_midnight() {
+        let mut policy = SendPolicy::new(&endpoint("22:00", "06:00", None));
+        let now = Instant::now();
+        assert_eq!(policy.check(time("23:00"), now), Ok(()));
+        assert_eq!(policy.check(time("01:00"), now), Ok(()));
+        assert!(policy.check(time("12:00"), now).is_err());
+    }
+
+    #[test]
+    fn test_invalid_active_hours_are_ignored() {
+        let mut policy = SendPolicy::new(&endpoint("6am", "23:00", None));
+        assert_eq!(policy.check(time("03:00"), Instant::now()), Ok(()));
+    }
+
+    #[test]
+    fn test_budget() {
+        let mut policy = SendPolicy::new(&Endpoint {
+            max_sends_per_hour: Some(2),
+            ..Default::default()
+        });
+        let start = Instant::now();
+        for _ in 0..2 {
+            assert_eq!(policy.check(time("12:00"), start), Ok(()));
+            policy.record_send(start);
+        }
+        assert_eq!(
+            policy.check(time("12:00"), start + Duration::from_secs(60)),
+            Err(SkipReason::BudgetExhausted(2))
+        );
+        assert_eq!(policy.check(time("12:00"), start + HOUR), Ok(()));
+    }
+
+    #[test]
+    fn test_cooldown() {
+        let mock = MockClock::default();
+        let clock = mock.shared();
+        let cooldown = Duration::from_secs(10);
+        assert!(!is_cooling_down(None, clock.now(), cooldown));
+        let last_sent = Some(clock.now());
+        mock.advance(cooldown);
+        assert!(is_cooling_down(last_sent, clock.now(), cooldown));
+        mock.advance(Duration::from_millis(1));
+        assert!(!is_cooling_down(last_sent, clock.now(), cooldown));
+    }
+}
@@ -0,0 +1,77 @@
+// Licensed under the Open Software License version 3.0
+use reqwest::{header::CONTENT_TYPE, Response};
+
+/// Read at most `limit` bytes of response body for logging
+///
+/// JSON bodies that fit within the limit are pretty-printed,
+/// everything else is returned as lossy UTF-8 text
+pub async fn read_response_preview(mut response: Response, limit: usize) -> String {
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map_or(false, |value| value.contains("json"));
+    let mut body: Vec<u8> = Vec::new();
+    let mut truncated = false;
+    // Stream chunks instead of buffering the whole body
+    loop {
+        match response.chunk().await {
+            Ok(Some(chunk)) => {
+                let remaining = limit.saturating_sub(body.len());
+                if chunk.len() > remaining {
+                    body.extend_from_slice(&chunk[..remaining]);
+                    truncated = true;
+                    break;
+                }
+                body.extend_from_slice(&chunk);
+            }
+            Ok(None) => break,
+            Err(error) => return format!("<failed to read body: {}>", error),
+        }
+    }
+    if is_json && !truncated {
+        if let Ok(json) = serde_json::from_slice::<serde_json::Value>(&body) {
+            return serde_json::to_string_pretty(&json).unwrap_or_default();
+        }
+    }
+    let mut preview = String::from_utf8_lossy(&body).into_owned();
+    if truncated {
+        preview.push_str(&format!("... <truncated to {} bytes>", limit));
+    }
+    preview
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Server;
+
+    async fn get_preview(content_type: &str, body: &str, limit: usize) -> String {
+        let mut server = Server::new();
+        let _mock = server
+            .mock("GET", "/")
+            .with_header("content-type", content_type)
+            .with_body(body)
+            .create();
+        let response = reqwest::get(server.url()).await.unwrap();
+        read_response_preview(response, limit).await
+    }
+
+    #[tokio::test]
+    async fn test_json_preview() {
+        let preview = get_preview("application/json", r#"{"a":1}"#, 1024).await;
+        assert_eq!(preview, "{\n  \"a\": 1\n}");
+    }
+
+    #[tokio::test]
+    async fn test_truncated_preview() {
+        let preview = get_preview("application/json", r#"{"a":1}"#, 3).await;
+        assert_eq!(preview, "{\"a... <truncated to 3 bytes>");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_json_preview() {
+        let preview = get_preview("application/json", "<html>", 1024).await;
+        assert_eq!(preview, "<html>");
+    }
+}
@@ -1,121 +1,767 @@
 // Licensed under the Open Software License version 3.0
-use super::config::{ActiveSenderConfig, Endpoint};
-use crate::{nut::sender::UninterruptiblePowerSupplyData, one_wire::sender::MeasuredTemperature};
+use super::{
+    anonymize::anonymize_ids,
+    compat::{encode_body, keep_categories, Payload},
+    compression::compress,
+    config::{ActiveSenderConfig, Endpoint, HttpVersion, SendMethod, XmlOutput},
+    control::{parse_control_document, ControlDocument, ServerControl},
+    headers::{PART_HEADER, TOTAL_PARTS_HEADER},
+    multipart::split_data,
+    policy::{is_cooling_down, SendPolicy, SkipReason},
+    preview::read_response_preview,
+    retry::{RetryQueue, SendFailure},
+    spool::{directory_name, Spool},
+    startup_check::{check_endpoint, report_endpoint_check},
+    state::{ActiveSenderState, EndpointState, MergedData},
+    success::is_accepted,
+    token::TokenProvider,
+    xml::XmlTemplate,
+};
+use crate::{
+    bandwidth,
+    clock::SharedClock,
+    config::file::get_config_file_path,
+    dedup_log::{info_resolved, warn_deduplicated},
+    hardware::reading::{Reading, ReadingsByPublisher, ReadingsUpdate},
+    introspection,
+    nut::sender::{redact_upses, UninterruptiblePowerSupplyData, UpsUpdate, UpsesByPublisher},
+    one_wire::sender::MeasuredTemperature,
+    self_metrics::lag::recv_counting_lag,
+    signing::key::{read_or_create_signer, Signer, SIGNATURE_HEADER},
+};
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
-use std::{cmp::max, time::Duration};
-use tokio::{
-    sync::{broadcast, watch},
-    time::Instant,
+use std::{
+    cmp::max,
+    path::Path,
+    time::{Duration, Instant},
 };
+use tokio::sync::{broadcast, watch};
 use tokio_stream::StreamExt;
 
+// Name of this sink in task introspection and bandwidth priorities
+const SINK: &str = "active_sender";
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-struct DataToSend {
-    // Has to remain "sensors" for compatibility with home-panel
-    sensors: Vec<MeasuredTemperature>,
-    upses: Vec<UninterruptiblePowerSupplyData>,
+pub(super) struct DataToSend {
+    // Has to remain "sensors" for compatibility with home-panel, see compat
+    pub(super) sensors: Vec<MeasuredTemperature>,
+    pub(super) upses: Vec<UninterruptiblePowerSupplyData>,
+    // Generic sources, ex. self metrics
+    pub(super) readings: Vec<Reading>,
+    // Stable across restarts, lets backends track a device across IP changes
+    pub(super) instance_id: String,
 }
 
 impl DataToSend {
     pub fn new(
         sensors: Vec<MeasuredTemperature>,
         upses: Vec<UninterruptiblePowerSupplyData>,
+        instance_id: String,
     ) -> Self {
-        Self { sensors, upses }
+        Self {
+            sensors,
+            upses,
+            readings: vec![],
+            instance_id,
+        }
+    }
+}
+
+fn request_method(method: SendMethod) -> reqwest::Method {
+    match method {
+        SendMethod::Post => reqwest::Method::POST,
+        SendMethod::Put => reqwest::Method::PUT,
+        SendMethod::Patch => reqwest::Method::PATCH,
     }
 }
 
+/// Request with bearer token and custom headers of `endpoint`
+pub fn build_request(
+    client: &reqwest::Client,
+    method: reqwest::Method,
+    endpoint: &Endpoint,
+) -> reqwest::RequestBuilder {
+    let headers = endpoint.get_headers();
+    let mut request = client.request(method, &endpoint.url);
+    // Custom Authorization header replaces the bearer token instead of being sent twice
+    if !headers
+        .keys()
+        .any(|name| name.eq_ignore_ascii_case(reqwest::header::AUTHORIZATION.as_str()))
+    {
+        // With bearer token if available (use empty string if not)
+        request = request.bearer_auth(endpoint.bearer_token.as_deref().unwrap_or(""));
+    }
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+    request
+}
+
+/// Returns control document of the response if the endpoint accepts control
 pub async fn send_data<T>(
     client: &reqwest::Client,
     json: &T,
     endpoint: &Endpoint,
+    sink: &str,
+    timeout: &Duration,
+    ignore_connection_errors: &bool,
+    response_preview_limit: &usize,
+) -> Option<ControlDocument>
+where
+    T: ?Sized + Serialize,
+{
+    send_data_part(
+        client,
+        json,
+        endpoint,
+        sink,
+        None,
+        None,
+        timeout,
+        ignore_connection_errors,
+        response_preview_limit,
+    )
+    .await
+    .unwrap_or_default()
+}
+
+/// Same as `send_data`, but with part number and total number of parts (both 1-based)
+/// and signature of the serialized body
+#[allow(clippy::too_many_arguments)]
+pub async fn send_data_part<T>(
+    client: &reqwest::Client,
+    json: &T,
+    endpoint: &Endpoint,
+    sink: &str,
+    part: Option<(usize, usize)>,
+    signer: Option<&Signer>,
     timeout: &Duration,
     ignore_connection_errors: &bool,
-) where
+    response_preview_limit: &usize,
+) -> Result<Option<ControlDocument>, SendFailure>
+where
     T: ?Sized + Serialize,
 {
     // Enter send_data span
     // Send json to endpoint
-    // With bearer token if available (use empty string if not)
-    let result = client
-        .post(&endpoint.url)
-        .bearer_auth(endpoint.bearer_token.as_deref().unwrap_or(""))
-        .json(json)
+    let method = request_method(endpoint.get_method());
+    let mut request = build_request(client, method, endpoint);
+    if let Some((part, total)) = part {
+        request = request
+            .header(PART_HEADER, part)
+            .header(TOTAL_PARTS_HEADER, total);
+    }
+    // Serialized here instead of by reqwest, signature has to cover the exact bytes
+    let (body, content_type) = match encode_body(json, endpoint.get_payload_format()) {
+        Ok(encoded) => encoded,
+        Err(error) => {
+            tracing::error!("Failed to serialize data for {}: {}", endpoint.url, error);
+            return Err(SendFailure::Permanent);
+        }
+    };
+    if let Some(signer) = signer {
+        request = request.header(SIGNATURE_HEADER, signer.sign(&body));
+    }
+    let (body, encoding) = compress(body, endpoint.get_compression(), &endpoint.url);
+    if let Some(encoding) = encoding {
+        request = request.header(reqwest::header::CONTENT_ENCODING, encoding);
+    }
+    bandwidth::limiter::acquire(sink, body.len()).await;
+    let result = request
+        .header(reqwest::header::CONTENT_TYPE, content_type)
+        .body(body)
         .timeout(*timeout)
         .send()
         .await;
+    handle_send_result(
+        result,
+        endpoint,
+        ignore_connection_errors,
+        response_preview_limit,
+    )
+    .await
+}
+
+/// Same as `send_data`, but with an already rendered XML body
+#[allow(clippy::too_many_arguments)]
+pub async fn send_xml(
+    client: &reqwest::Client,
+    body: String,
+    xml: &XmlOutput,
+    endpoint: &Endpoint,
+    sink: &str,
+    signer: Option<&Signer>,
+    timeout: &Duration,
+    ignore_connection_errors: &bool,
+    response_preview_limit: &usize,
+) -> Result<Option<ControlDocument>, SendFailure> {
+    let method = request_method(endpoint.get_method());
+    let mut request = build_request(client, method, endpoint)
+        .header(reqwest::header::CONTENT_TYPE, xml.get_content_type());
+    if let Some(soap_action) = &xml.soap_action {
+        request = request.header("SOAPAction", soap_action);
+    }
+    if let Some(signer) = signer {
+        request = request.header(SIGNATURE_HEADER, signer.sign(body.as_bytes()));
+    }
+    let (body, encoding) = compress(body.into_bytes(), endpoint.get_compression(), &endpoint.url);
+    if let Some(encoding) = encoding {
+        request = request.header(reqwest::header::CONTENT_ENCODING, encoding);
+    }
+    bandwidth::limiter::acquire(sink, body.len()).await;
+    let result = request.body(body).timeout(*timeout).send().await;
+    handle_send_result(
+        result,
+        endpoint,
+        ignore_connection_errors,
+        response_preview_limit,
+    )
+    .await
+}
+
+async fn handle_send_result(
+    result: reqwest::Result<reqwest::Response>,
+    endpoint: &Endpoint,
+    ignore_connection_errors: &bool,
+    response_preview_limit: &usize,
+) -> Result<Option<ControlDocument>, SendFailure> {
+    // Same endpoint being down shouldn't flood the journal every cooldown
+    let key = format!("active_sender:{}", endpoint.url);
+    let version = result
+        .as_ref()
+        .ok()
+        .map(|response| format!("{:?}", response.version()));
+    introspection::record_endpoint_request(&endpoint.url, version);
     match result {
         Ok(response) => {
-            if response.status().is_success() {
-                // Pretty-print response object but only in debug mode
+            let success = endpoint.get_success();
+            if success.is_accepted_status(response.status().as_u16()) {
+                if endpoint.get_accept_control() || success.json_path.is_some() {
+                    let preview = read_response_preview(response, *response_preview_limit).await;
+                    tracing::trace!(%preview, ?endpoint.url);
+                    if let Some(json_path) = &success.json_path {
+                        if !is_accepted(&preview, json_path) {
+                            // Ex. {"ok": false}, retried like a server error
+                            warn_deduplicated!(
+                                key,
+                                "{} rejected data, {} of the response isn't truthy",
+                                endpoint.url,
+                                json_path
+                            );
+                            tracing::debug!(%preview, ?endpoint.url);
+                            return Err(SendFailure::Transient);
+                        }
+                    }
+                    info_resolved!(key, "{} is accepting data again", endpoint.url);
+                    return Ok(endpoint
+                        .get_accept_control()
+                        .then(|| parse_control_document(&preview))
+                        .flatten());
+                }
+                info_resolved!(key, "{} is accepting data again", endpoint.url);
+                // Pretty-print bounded response preview but only in debug mode
                 // Used with httpbin to test the request
                 #[cfg(debug_assertions)]
                 {
-                    let json: serde_json::Value = response.json().await.unwrap();
-                    tracing::trace!(?json, ?endpoint.url);
+                    let preview = read_response_preview(response, *response_preview_limit).await;
+                    tracing::trace!(%preview, ?endpoint.url);
                 }
+                Ok(None)
             } else {
                 // Print response error with endpoint url
-                tracing::warn!("Got {} response from {}", response.status(), endpoint.url);
+                let status = response.status();
+                warn_deduplicated!(key, "Got {} response from {}", status, endpoint.url);
+                let preview = read_response_preview(response, *response_preview_limit).await;
+                tracing::debug!(%preview, ?endpoint.url);
+                if status.is_server_error()
+                    || status == StatusCode::REQUEST_TIMEOUT
+                    || status == StatusCode::TOO_MANY_REQUESTS
+                {
+                    Err(SendFailure::Transient)
+                } else if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+                    Err(SendFailure::Unauthorized)
+                } else {
+                    Err(SendFailure::Permanent)
+                }
             }
         }
+        Err(error) if error.is_builder() => {
+            // Ex. invalid header name, every request would fail the same way
+            warn_deduplicated!(key, "Invalid request to {}: {}", endpoint.url, error);
+            Err(SendFailure::Permanent)
+        }
         Err(error) => {
-            // Ignore connection errors if specified
-            if *ignore_connection_errors && error.is_connect() {
-                return;
+            // Ignore connection errors if specified, they're still retried
+            if !(*ignore_connection_errors && error.is_connect()) {
+                warn_deduplicated!(key, "Connection failed: {}", error);
             }
-            tracing::warn!("Connection failed: {}", error);
+            Err(SendFailure::Transient)
         }
     }
 }
 
+/// Redact and anonymize `data_to_send` as configured for `endpoint`, before any request
+fn prepare_for_endpoint(
+    mut data_to_send: DataToSend,
+    endpoint: &Endpoint,
+    id_hash_secret: Option<&str>,
+) -> DataToSend {
+    if let Some(redacted_variables) = &endpoint.redacted_variables {
+        data_to_send.upses = redact_upses(&data_to_send.upses, redacted_variables);
+    }
+    if let (true, Some(secret)) = (endpoint.is_untrusted(), id_hash_secret) {
+        data_to_send = anonymize_ids(&data_to_send, secret);
+    }
+    data_to_send
+}
+
+/// `None` if failed payloads are dropped, spool is skipped if its directory can't be used
+async fn create_retry_queue(
+    config: &ActiveSenderConfig,
+    endpoint: &Endpoint,
+) -> Option<RetryQueue<DataToSend>> {
+    let retries = RetryQueue::new(config.get_retry()?);
+    let spool_config = match config.get_spool() {
+        Some(spool_config) => spool_config,
+        None => return Some(retries),
+    };
+    let directory = Path::new(&spool_config.directory).join(directory_name(&endpoint.url));
+    match Spool::open(&directory, spool_config.get_max_size()).await {
+        Ok(spool) => {
+            if !spool.is_empty() {
+                tracing::info!(
+                    "{} payloads for {} are waiting in spool",
+                    spool.len(),
+                    endpoint.url
+                );
+            }
+            Some(retries.with_spool(spool))
+        }
+        Err(error) => {
+            tracing::error!(
+                "Failed to open spool {}: {}, keeping payloads only in memory",
+                directory.display(),
+                error
+            );
+            Some(retries)
+        }
+    }
+}
+
+/// Send `data_to_send` in the format of `endpoint`, split into parts if configured
+///
+/// Every request, including every part, is recorded in `send_policy`
+#[allow(clippy::too_many_arguments)]
+async fn send_snapshot(
+    client: &reqwest::Client,
+    config: &ActiveSenderConfig,
+    endpoint: &Endpoint,
+    xml_template: Option<&XmlTemplate>,
+    signer: Option<&Signer>,
+    data_to_send: &DataToSend,
+    send_policy: &mut SendPolicy,
+    clock: &SharedClock,
+) -> Result<Option<ControlDocument>, SendFailure> {
+    match (&endpoint.xml, xml_template, config.get_max_payload_size()) {
+        (Some(xml), Some(xml_template), _) => {
+            let timestamp = clock.utc_now().to_rfc3339();
+            let result = send_xml(
+                client,
+                xml_template.render(data_to_send, &timestamp),
+                xml,
+                endpoint,
+                SINK,
+                signer,
+                &config.get_endpoint_timeout(endpoint),
+                &config.get_endpoint_ignore_connection_errors(endpoint),
+                &config.get_response_preview_limit(),
+            )
+            .await;
+            send_policy.record_send(clock.now());
+            result
+        }
+        (_, _, Some(max_payload_size)) => {
+            let parts = split_data(data_to_send, max_payload_size);
+            let total = parts.len();
+            if total > 1 {
+                tracing::debug!("Sending {} parts to {}", total, endpoint.url);
+            }
+            let mut result = Ok(None);
+            for (index, part) in parts.iter().enumerate() {
+                let part_result = send_data_part(
+                    client,
+                    &Payload::for_endpoint(part, endpoint),
+                    endpoint,
+                    SINK,
+                    Some((index + 1, total)),
+                    signer,
+                    &config.get_endpoint_timeout(endpoint),
+                    &config.get_endpoint_ignore_connection_errors(endpoint),
+                    &config.get_response_preview_limit(),
+                )
+                .await;
+                send_policy.record_send(clock.now());
+                result = match (result, part_result) {
+                    // Latest control document wins
+                    (Ok(control_document), Ok(part_control_document)) => {
+                        Ok(part_control_document.or(control_document))
+                    }
+                    // Retrying the whole snapshot can't help if any part was rejected
+                    (Err(SendFailure::Permanent), _) | (_, Err(SendFailure::Permanent)) => {
+                        Err(SendFailure::Permanent)
+                    }
+                    (Err(SendFailure::Unauthorized), _) | (_, Err(SendFailure::Unauthorized)) => {
+                        Err(SendFailure::Unauthorized)
+                    }
+                    _ => Err(SendFailure::Transient),
+                };
+            }
+            result
+        }
+        _ => {
+            let result = send_data_part(
+                client,
+                &Payload::for_endpoint(data_to_send, endpoint),
+                endpoint,
+                SINK,
+                None,
+                signer,
+                &config.get_endpoint_timeout(endpoint),
+                &config.get_endpoint_ignore_connection_errors(endpoint),
+                &config.get_response_preview_limit(),
+            )
+            .await;
+            send_policy.record_send(clock.now());
+            result
+        }
+    }
+}
+
+/// Persistent client for `endpoint`, reused between sends to keep connections alive
+fn build_client(endpoint: &Endpoint) -> reqwest::Result<reqwest::Client> {
+    let builder = reqwest::Client::builder();
+    let builder = match endpoint.get_http_version() {
+        HttpVersion::Auto => builder,
+        HttpVersion::Http1 => builder.http1_only(),
+        HttpVersion::Http2 => builder.http2_prior_knowledge(),
+    };
+    builder.build()
+}
+
+async fn load_xml_template(xml: &XmlOutput) -> Result<XmlTemplate, String> {
+    let template = tokio::fs::read_to_string(&xml.template_file)
+        .await
+        .map_err(|error| error.to_string())?;
+    XmlTemplate::parse(&template)
+}
+
 async fn start_active_sender_client_loop(
     mut shutdown_rx: broadcast::Receiver<()>,
     config: ActiveSenderConfig,
     endpoint: Endpoint,
     mut data_to_send_rx: watch::Receiver<DataToSend>,
+    signer: Option<Signer>,
+    state: ActiveSenderState,
+    clock: SharedClock,
 ) {
     // Create a persistent reqwest client
-    let client = reqwest::Client::new();
-    let cooldown = max(config.get_cooldown(), Duration::from_secs(1));
-    // Create in instant at 0 to start sending immediately
-    let mut last_sent: Option<Instant> = None;
+    let client = match build_client(&endpoint) {
+        Ok(client) => client,
+        Err(error) => {
+            tracing::error!(
+                "Failed to create HTTP client for {}: {}",
+                endpoint.url,
+                error
+            );
+            return;
+        }
+    };
+    let cooldown = max(
+        config.get_endpoint_cooldown(&endpoint),
+        Duration::from_secs(1),
+    );
+    let heartbeat = config.get_endpoint_heartbeat(&endpoint);
+    let mut token_provider = TokenProvider::default();
+    let mut endpoint_with_token = endpoint.clone();
+    let id_hash_secret = config.get_id_hash_secret();
+    let mut send_policy = SendPolicy::new(&endpoint);
+    let mut server_control = ServerControl::default();
+    let mut last_skip_reason: Option<SkipReason> = None;
+    // Last accepted payload with only the categories of this endpoint
+    let mut last_filtered: Option<DataToSend> = None;
+    let mut retries = create_retry_queue(&config, &endpoint).await;
+    let retry_key = format!("active_sender:{}", endpoint.url);
+    if let Some(retries) = &retries {
+        introspection::set_retries(&retry_key, retries.len() as u32);
+    }
+    if endpoint.is_untrusted() && id_hash_secret.is_none() {
+        // Never send raw ids to an untrusted endpoint
+        tracing::error!(
+            "{} is untrusted but id_hash_secret is not set, not sending any data",
+            endpoint.url
+        );
+        return;
+    }
+    let xml_template = match &endpoint.xml {
+        Some(xml) => match load_xml_template(xml).await {
+            Ok(template) => Some(template),
+            Err(error) => {
+                tracing::error!(
+                    "Failed to load XML template {} for {}: {}",
+                    xml.template_file,
+                    endpoint.url,
+                    error
+                );
+                return;
+            }
+        },
+        None => None,
+    };
+
+    // Surface misconfiguration before the first real payload
+    if let Some(method) = config.get_startup_check() {
+        endpoint_with_token.bearer_token = token_provider.get_token(&client, &endpoint, SINK).await;
+        let tiny_payload = prepare_for_endpoint(
+            data_to_send_rx.borrow().clone(),
+            &endpoint,
+            id_hash_secret.as_deref(),
+        );
+        let tiny_payload = Payload::for_endpoint(&tiny_payload, &endpoint);
+        let result =
+            check_endpoint(&client, &endpoint_with_token, SINK, &method, &tiny_payload).await;
+        report_endpoint_check(&endpoint, &result);
+    }
 
+    // Continue where the previous run stopped, ex. before a hot reload
+    let restored = state.take_endpoint(&endpoint.url);
+    // Nothing sent yet on the first run, so the first update is sent immediately
+    let mut last_sent: Option<Instant> = restored.last_sent;
+    if let Some(retries) = &mut retries {
+        for payload in restored.queued {
+            retries.push(payload, clock.now()).await;
+        }
+        introspection::set_retries(&retry_key, retries.len() as u32);
+    }
+
+    let _task = introspection::task_started(SINK);
+    // Armed by the first payload, the initial data is empty until sources publish
+    let mut heartbeat_due = last_sent
+        .and(heartbeat)
+        .map(|heartbeat| clock.now() + heartbeat);
     loop {
-        tokio::select! {
+        introspection::mark_iteration("active_sender");
+        let retry_delay = retries
+            .as_ref()
+            .and_then(|retries| retries.delay(clock.now()));
+        let heartbeat_delay = heartbeat_due.map(|due| due.saturating_duration_since(clock.now()));
+        let is_heartbeat = tokio::select! {
             data_to_send_changed = data_to_send_rx.changed() => {
                 if data_to_send_changed.is_err() {
                     tracing::trace!("Shutting down active sender loop for {}", endpoint.url);
                     break;
                 }
-                if last_sent.is_some() && last_sent.unwrap().elapsed() <= cooldown {
+                if is_cooling_down(last_sent, clock.now(), server_control.get_cooldown(cooldown)) {
                     tracing::trace!("Skipping because of cooldown: {}", endpoint.url);
                     continue;
                 }
-                let data_to_send = data_to_send_rx.borrow().clone();
-                send_data(
+                false
+            }
+            _ = clock.sleep(heartbeat_delay.unwrap_or_default()), if heartbeat_delay.is_some() => {
+                tracing::trace!("Sending heartbeat to {}", endpoint.url);
+                true
+            }
+            _ = clock.sleep(retry_delay.unwrap_or_default()), if retry_delay.is_some() => {
+                let Some(retries) = &mut retries else {
+                    continue;
+                };
+                let Some(queued) = retries.pop().await else {
+                    continue;
+                };
+                // Pauses and budgets apply to retries too
+                let local_time = clock.utc_now().with_timezone(&chrono::Local).time();
+                let check = server_control
+                    .check(clock.now())
+                    .and_then(|()| send_policy.check(local_time, clock.now()));
+                if check.is_err() {
+                    retries.postpone(queued, clock.now());
+                    continue;
+                }
+                endpoint_with_token.bearer_token =
+                    token_provider.get_token(&client, &endpoint, SINK).await;
+                let result = send_snapshot(
                     &client,
-                    &data_to_send,
-                    &endpoint,
-                    &Duration::from_secs(5),
-                    &config.get_ignore_connection_errors(),
+                    &config,
+                    &endpoint_with_token,
+                    xml_template.as_ref(),
+                    signer.as_ref(),
+                    &queued.payload,
+                    &mut send_policy,
+                    &clock,
                 )
                 .await;
-                last_sent = Some(Instant::now());
+                if result == Err(SendFailure::Unauthorized) {
+                    token_provider.invalidate();
+                }
+                match result {
+                    Ok(control_document) => {
+                        if let Some(control_document) = control_document {
+                            server_control.apply(
+                                control_document,
+                                clock.utc_now(),
+                                clock.now(),
+                                &endpoint.url,
+                            );
+                        }
+                        retries.delivered(queued, clock.now()).await;
+                    }
+                    Err(SendFailure::Transient | SendFailure::Unauthorized) => {
+                        if !retries.failed(queued, clock.now()).await {
+                            tracing::warn!(
+                                "Dropped payload for {} after too many retries",
+                                endpoint.url
+                            );
+                        }
+                    }
+                    // Already logged, sending it again wouldn't help
+                    Err(SendFailure::Permanent) => retries.rejected(queued).await,
+                }
+                introspection::set_retries(&retry_key, retries.len() as u32);
+                continue;
             }
             _ = shutdown_rx.recv() => {
                 tracing::trace!("Shutting down active sender loop for {}", endpoint.url);
                 break;
             }
+        };
+        // Skipped heartbeats aren't tried again until the next interval
+        heartbeat_due = heartbeat.map(|heartbeat| clock.now() + heartbeat);
+        let check = server_control.check(clock.now()).and_then(|()| {
+            let local_time = clock.utc_now().with_timezone(&chrono::Local).time();
+            send_policy.check(local_time, clock.now())
+        });
+        match check {
+            Ok(()) => {
+                if last_skip_reason.take().is_some() {
+                    tracing::info!("Resuming sending to {}", endpoint.url);
+                }
+            }
+            Err(reason) => {
+                // Log once per reason to avoid flooding logs on every update
+                if last_skip_reason.as_ref() != Some(&reason) {
+                    tracing::info!("Skipping {}: {}", endpoint.url, reason);
+                } else {
+                    tracing::trace!("Skipping {}: {}", endpoint.url, reason);
+                }
+                last_skip_reason = Some(reason);
+                continue;
+            }
+        }
+        let mut data_to_send = prepare_for_endpoint(
+            data_to_send_rx.borrow().clone(),
+            &endpoint,
+            id_hash_secret.as_deref(),
+        );
+        if let Some(categories) = endpoint.get_categories() {
+            keep_categories(&mut data_to_send, &categories);
+            // Updates of other categories don't concern this endpoint, heartbeats are sent anyway
+            if !is_heartbeat && last_filtered.as_ref() == Some(&data_to_send) {
+                tracing::trace!("Skipping unchanged categories: {}", endpoint.url);
+                continue;
+            }
+        }
+        endpoint_with_token.bearer_token = token_provider.get_token(&client, &endpoint, SINK).await;
+        let result = send_snapshot(
+            &client,
+            &config,
+            &endpoint_with_token,
+            xml_template.as_ref(),
+            signer.as_ref(),
+            &data_to_send,
+            &mut send_policy,
+            &clock,
+        )
+        .await;
+        // Ex. revoked before its expiry, the next send requests a new one
+        if result == Err(SendFailure::Unauthorized) {
+            token_provider.invalidate();
+        }
+        match result {
+            Ok(control_document) => {
+                if let Some(control_document) = control_document {
+                    server_control.apply(
+                        control_document,
+                        clock.utc_now(),
+                        clock.now(),
+                        &endpoint.url,
+                    );
+                }
+                if let Some(retries) = &mut retries {
+                    retries.succeeded(clock.now());
+                    introspection::set_retries(&retry_key, retries.len() as u32);
+                }
+                // Failed payloads have to be sent again even if they don't change
+                if endpoint.get_categories().is_some() {
+                    last_filtered = Some(data_to_send);
+                }
+            }
+            Err(SendFailure::Transient | SendFailure::Unauthorized) => {
+                if let Some(retries) = &mut retries {
+                    if retries.push(data_to_send, clock.now()).await {
+                        tracing::warn!(
+                            "Retry queue of {} is full, dropped the oldest payload",
+                            endpoint.url
+                        );
+                    }
+                    introspection::set_retries(&retry_key, retries.len() as u32);
+                }
+            }
+            Err(SendFailure::Permanent) => {}
         }
+        last_sent = Some(clock.now());
     }
+    // Queued payloads would be lost on shutdown, restart or reload
+    if let Some(retries) = &mut retries {
+        let spooled = retries.flush_to_spool().await;
+        if spooled > 0 {
+            tracing::info!("Spooled {} queued payloads for {}", spooled, endpoint.url);
+        }
+    }
+    state.save_endpoint(
+        &endpoint.url,
+        EndpointState {
+            last_sent,
+            queued: retries
+                .as_mut()
+                .map(RetryQueue::take_queued)
+                .unwrap_or_default(),
+        },
+    );
+}
+
+/// Wake endpoint loops, with `skip_unchanged` only if merged data differs from the last one
+///
+/// Data is always replaced, so `measured_at` stays fresh even if values didn't change
+fn publish(
+    data_to_send_tx: &watch::Sender<DataToSend>,
+    data_to_send: &DataToSend,
+    skip_unchanged: bool,
+) {
+    data_to_send_tx.send_if_modified(|current| {
+        let changed = current != data_to_send;
+        *current = data_to_send.clone();
+        changed || !skip_unchanged
+    });
 }
 
 pub async fn start_active_sender_loop(
     mut shutdown_rx: broadcast::Receiver<()>,
     config: ActiveSenderConfig,
     mut one_wire_rx: broadcast::Receiver<Vec<MeasuredTemperature>>,
-    mut ups_monitoring_rx: broadcast::Receiver<Vec<UninterruptiblePowerSupplyData>>,
+    mut ups_monitoring_rx: broadcast::Receiver<UpsUpdate>,
+    mut readings_rx: broadcast::Receiver<ReadingsUpdate>,
+    instance_id: String,
+    state: ActiveSenderState,
 ) {
     // Check if module is enabled
     if !config.is_enabled() {
@@ -123,16 +769,46 @@ pub async fn start_active_sender_loop(
         return;
     }
 
-    // Prepare channel with merged data
-    let (data_to_send_tx, data_to_send_rx) = watch::channel::<DataToSend>(DataToSend {
-        sensors: vec![],
-        upses: vec![],
-    });
+    // Same key for every endpoint, archives need only one public key
+    let signer = if config.get_sign_payloads() {
+        match read_or_create_signer(&get_config_file_path()) {
+            Ok(signer) => {
+                tracing::info!("Signing payloads with public key {}", signer.public_key());
+                Some(signer)
+            }
+            Err(error) => {
+                // Unsigned payloads would look tampered with to archives expecting signatures
+                tracing::error!(
+                    "Failed to load signing key: {}, not sending any data",
+                    error
+                );
+                return;
+            }
+        }
+    } else {
+        None
+    };
+
+    // Prepare channel with merged data, starting from the previous run if there was one
+    let merged = match state.take_merged() {
+        Some(mut merged) => {
+            merged.data_to_send.instance_id = instance_id;
+            merged
+        }
+        None => MergedData {
+            data_to_send: DataToSend::new(vec![], vec![], instance_id),
+            upses: UpsesByPublisher::default(),
+            readings: ReadingsByPublisher::default(),
+        },
+    };
+    let (data_to_send_tx, data_to_send_rx) =
+        watch::channel::<DataToSend>(merged.data_to_send.clone());
 
     // Spawn task for each endpoint
     tracing::trace!("Starting active sender loop");
     let endpoints = config.get_endpoints();
     let mut endpoints = tokio_stream::iter(endpoints);
+    let clock = SharedClock::default();
 
     // Make sure all tasks are spawned
     let mut tasks = Vec::new();
@@ -141,26 +817,49 @@ pub async fn start_active_sender_loop(
         let shutdown_rx_clone = shutdown_rx.resubscribe();
         let data_to_send_rx = data_to_send_rx.clone();
         let config = config.clone();
+        let signer = signer.clone();
+        let state = state.clone();
+        let clock = clock.clone();
         let task = tokio::spawn(async move {
-            start_active_sender_client_loop(shutdown_rx_clone, config, endpoint, data_to_send_rx)
-                .await
+            start_active_sender_client_loop(
+                shutdown_rx_clone,
+                config,
+                endpoint,
+                data_to_send_rx,
+                signer,
+                state,
+                clock,
+            )
+            .await
         });
         tasks.push(task);
     }
 
+    let skip_unchanged = config.get_skip_unchanged();
     let data_merger_task = tokio::spawn(async move {
-        let mut data_to_send = DataToSend::new(vec![], vec![]);
+        let MergedData {
+            mut data_to_send,
+            mut upses,
+            mut readings,
+        } = merged;
         loop {
             tokio::select! {
-                Ok(value) = one_wire_rx.recv() => {
+                Ok(value) = recv_counting_lag(&mut one_wire_rx) => {
                     tracing::trace!("one_wire_changed");
                     data_to_send.sensors = value;
-                    data_to_send_tx.send(data_to_send.clone()).unwrap();
+                    publish(&data_to_send_tx, &data_to_send, skip_unchanged);
                 }
-                Ok(value) = ups_monitoring_rx.recv() => {
+                Ok(value) = recv_counting_lag(&mut ups_monitoring_rx) => {
                     tracing::trace!("ups_monitoring_received");
-                    data_to_send.upses = value;
-                    data_to_send_tx.send(data_to_send.clone()).unwrap();
+                    upses.update(value);
+                    data_to_send.upses = upses.all();
+                    publish(&data_to_send_tx, &data_to_send, skip_unchanged);
+                }
+                Ok(value) = recv_counting_lag(&mut readings_rx) => {
+                    tracing::trace!("readings_received");
+                    readings.update(value);
+                    data_to_send.readings = readings.all();
+                    publish(&data_to_send_tx, &data_to_send, skip_unchanged);
                 }
                 _ = shutdown_rx.recv() => {
                     tracing::trace!("Shutting down data merger task");
@@ -168,6 +867,11 @@ pub async fn start_active_sender_loop(
                 }
             }
         }
+        state.save_merged(MergedData {
+            data_to_send,
+            upses,
+            readings,
+        });
     });
     tasks.push(data_merger_task);
 
@@ -180,7 +884,14 @@ pub async fn start_active_sender_loop(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use mockito::{Matcher::JsonString, Server};
+    use crate::active_sender::config::{Compression, PayloadFormat, SuccessCriteria};
+    use crate::clock::MockClock;
+    use crate::config::types::Example;
+    use crate::schema::{assert_matches_golden_file, fixtures};
+    use mockito::{
+        Matcher::{JsonString, PartialJsonString},
+        Server,
+    };
     use reqwest::Client;
     use std::time::Duration;
 
@@ -201,14 +912,41 @@ mod tests {
         let endpoint = Endpoint {
             url: format!("{}{}", server.url(), "/post-data"),
             bearer_token: None,
+            ..Default::default()
         };
         let timeout = Duration::from_secs(5);
         let data = vec![1, 2, 3, 4, 5];
-        send_data(&client, &data, &endpoint, &timeout, &false).await;
+        send_data(&client, &data, &endpoint, "test", &timeout, &false, &1024).await;
         // Assert that mock was called
         mock.assert();
     }
 
+    #[tokio::test]
+    async fn test_send_data_returns_control_document() {
+        let mut server = Server::new();
+        let _mock = server
+            .mock("POST", "/post-data")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"cooldown": 30}"#)
+            .create();
+        let client = Client::new();
+        let mut endpoint = Endpoint {
+            url: format!("{}{}", server.url(), "/post-data"),
+            ..Default::default()
+        };
+        let timeout = Duration::from_secs(5);
+        let data = vec![1, 2, 3];
+        // Ignored unless the endpoint accepts control
+        let control_document =
+            send_data(&client, &data, &endpoint, "test", &timeout, &false, &1024).await;
+        assert_eq!(control_document, None);
+        endpoint.accept_control = Some(true);
+        let control_document =
+            send_data(&client, &data, &endpoint, "test", &timeout, &false, &1024).await;
+        assert_eq!(control_document.unwrap().cooldown, Some(30));
+    }
+
     #[tokio::test]
     async fn test_send_data_with_bearer_token() {
         let mut server = Server::new();
@@ -225,10 +963,567 @@ mod tests {
         let endpoint = Endpoint {
             url: format!("{}{}", server.url(), "/post-data"),
             bearer_token,
+            ..Default::default()
         };
         let timeout = Duration::from_secs(5);
         let data = vec![1, 2, 3, 4, 5];
-        send_data(&client, &data, &endpoint, &timeout, &false).await;
+        send_data(&client, &data, &endpoint, "test", &timeout, &false, &1024).await;
         mock.assert();
     }
+
+    #[tokio::test]
+    async fn test_send_data_with_custom_headers() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("POST", "/post-data")
+            .match_header("X-Api-Key", "key")
+            .match_header("Authorization", "ApiKey key")
+            .with_status(200)
+            .create();
+        let client = Client::new();
+        let endpoint = Endpoint {
+            url: format!("{}{}", server.url(), "/post-data"),
+            bearer_token: Some(String::from("ignored")),
+            headers: Some(std::collections::HashMap::from([
+                (String::from("X-Api-Key"), String::from("key")),
+                (String::from("authorization"), String::from("ApiKey key")),
+            ])),
+            ..Default::default()
+        };
+        let timeout = Duration::from_secs(5);
+        send_data(&client, &[1], &endpoint, "test", &timeout, &false, &1024).await;
+        mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_send_data_with_method_and_format() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("PUT", "/put-data")
+            .match_header("Content-Type", "application/x-ndjson")
+            .match_body("1\n2\n3\n")
+            .with_status(200)
+            .create();
+        let client = Client::new();
+        let endpoint = Endpoint {
+            url: format!("{}{}", server.url(), "/put-data"),
+            method: Some(SendMethod::Put),
+            payload_format: Some(PayloadFormat::Ndjson),
+            ..Default::default()
+        };
+        let timeout = Duration::from_secs(5);
+        send_data(
+            &client,
+            &[1, 2, 3],
+            &endpoint,
+            "test",
+            &timeout,
+            &false,
+            &1024,
+        )
+        .await;
+        mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_send_compressed_data() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("POST", "/post-data")
+            .match_header("Content-Encoding", "gzip")
+            .match_header("Content-Type", "application/json")
+            .with_status(200)
+            .create();
+        let client = Client::new();
+        let endpoint = Endpoint {
+            url: format!("{}{}", server.url(), "/post-data"),
+            compression: Some(Compression::Gzip),
+            ..Default::default()
+        };
+        let timeout = Duration::from_secs(5);
+        send_data(
+            &client,
+            &[1, 2, 3],
+            &endpoint,
+            "test",
+            &timeout,
+            &false,
+            &1024,
+        )
+        .await;
+        mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_success_criteria() {
+        let mut server = Server::new();
+        let _rejected = server
+            .mock("POST", "/rejected")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"ok": false}"#)
+            .create();
+        let _accepted = server
+            .mock("POST", "/accepted")
+            .with_status(202)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"ok": true}"#)
+            .create();
+        let _created = server
+            .mock("POST", "/created")
+            .with_status(201)
+            .with_body(r#"{"ok": true}"#)
+            .create();
+        let client = Client::new();
+        let timeout = Duration::from_secs(5);
+        let send = |path: &str| {
+            let endpoint = Endpoint {
+                url: format!("{}{}", server.url(), path),
+                success: Some(SuccessCriteria {
+                    statuses: Some(vec![200, 202]),
+                    json_path: Some(String::from("$.ok")),
+                }),
+                ..Default::default()
+            };
+            let client = client.clone();
+            async move {
+                send_data_part(
+                    &client,
+                    &[1],
+                    &endpoint,
+                    "test",
+                    None,
+                    None,
+                    &timeout,
+                    &false,
+                    &1024,
+                )
+                .await
+            }
+        };
+        assert_eq!(send("/rejected").await, Err(SendFailure::Transient));
+        assert_eq!(send("/accepted").await, Ok(None));
+        // Not in statuses
+        assert_eq!(send("/created").await, Err(SendFailure::Permanent));
+    }
+
+    #[tokio::test]
+    async fn test_send_data_part_headers() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("POST", "/post-data")
+            .match_header(PART_HEADER, "2")
+            .match_header(TOTAL_PARTS_HEADER, "3")
+            .with_status(200)
+            .create();
+        let client = Client::new();
+        let endpoint = Endpoint {
+            url: format!("{}{}", server.url(), "/post-data"),
+            ..Default::default()
+        };
+        let timeout = Duration::from_secs(5);
+        let data = vec![1, 2, 3];
+        send_data_part(
+            &client,
+            &data,
+            &endpoint,
+            "test",
+            Some((2, 3)),
+            None,
+            &timeout,
+            &false,
+            &1024,
+        )
+        .await
+        .unwrap();
+        mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_every_part_counts_as_send() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("POST", "/data")
+            .with_status(200)
+            .expect(2)
+            .create();
+        let endpoint = Endpoint {
+            url: format!("{}/data", server.url()),
+            max_sends_per_hour: Some(2),
+            ..Default::default()
+        };
+        let config: ActiveSenderConfig = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "max_payload_size": 1,
+        }))
+        .unwrap();
+        let data = DataToSend::new(
+            vec![MeasuredTemperature::example(); 2],
+            vec![],
+            String::from("test"),
+        );
+        let clock = MockClock::default();
+        let mut send_policy = SendPolicy::new(&endpoint);
+        let now = clock.shared().now();
+        let time = clock.shared().utc_now().time();
+        assert_eq!(send_policy.check(time, now), Ok(()));
+        send_snapshot(
+            &Client::new(),
+            &config,
+            &endpoint,
+            None,
+            None,
+            &data,
+            &mut send_policy,
+            &clock.shared(),
+        )
+        .await
+        .unwrap();
+        mock.assert();
+        assert_eq!(
+            send_policy.check(time, now),
+            Err(SkipReason::BudgetExhausted(2))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_failures() {
+        let mut server = Server::new();
+        let unavailable = server
+            .mock("POST", "/unavailable")
+            .with_status(503)
+            .create();
+        let rejected = server.mock("POST", "/rejected").with_status(400).create();
+        let forbidden = server.mock("POST", "/forbidden").with_status(403).create();
+        let timeout = Duration::from_secs(5);
+        for (path, expected) in [
+            ("/unavailable", SendFailure::Transient),
+            ("/rejected", SendFailure::Permanent),
+            ("/forbidden", SendFailure::Unauthorized),
+        ] {
+            let endpoint = Endpoint {
+                url: format!("{}{}", server.url(), path),
+                ..Default::default()
+            };
+            let result = send_data_part(
+                &Client::new(),
+                &[1],
+                &endpoint,
+                "test",
+                None,
+                None,
+                &timeout,
+                &false,
+                &1024,
+            )
+            .await;
+            assert_eq!(result, Err(expected));
+        }
+        unavailable.assert();
+        rejected.assert();
+        forbidden.assert();
+        // Nothing is listening, so it may work later
+        let endpoint = Endpoint {
+            url: String::from("http://127.0.0.1:9/closed"),
+            ..Default::default()
+        };
+        let result = send_data_part(
+            &Client::new(),
+            &[1],
+            &endpoint,
+            "test",
+            None,
+            None,
+            &timeout,
+            &true,
+            &1024,
+        )
+        .await;
+        assert_eq!(result, Err(SendFailure::Transient));
+    }
+
+    #[tokio::test]
+    async fn test_send_data_part_signature() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let signer = read_or_create_signer(&temp_dir.path().join("config.json")).unwrap();
+        let mut server = Server::new();
+        let mock = server
+            .mock("POST", "/post-data")
+            .match_header(SIGNATURE_HEADER, signer.sign(b"[1,2,3]").as_str())
+            .match_header("content-type", "application/json")
+            .match_body("[1,2,3]")
+            .with_status(200)
+            .create();
+        let endpoint = Endpoint {
+            url: format!("{}{}", server.url(), "/post-data"),
+            ..Default::default()
+        };
+        let timeout = Duration::from_secs(5);
+        let data = vec![1, 2, 3];
+        send_data_part(
+            &Client::new(),
+            &data,
+            &endpoint,
+            "test",
+            None,
+            Some(&signer),
+            &timeout,
+            &false,
+            &1024,
+        )
+        .await
+        .unwrap();
+        mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("POST", "/heartbeat")
+            .with_status(200)
+            .expect_at_least(3)
+            .create();
+        let endpoint = Endpoint {
+            url: format!("{}{}", server.url(), "/heartbeat"),
+            heartbeat: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        let config: ActiveSenderConfig =
+            serde_json::from_value(serde_json::json!({"enabled": true})).unwrap();
+        let clock = MockClock::default();
+        let (data_to_send_tx, data_to_send_rx) =
+            watch::channel(DataToSend::new(vec![], vec![], String::from("test")));
+        let (shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let sender = tokio::spawn(start_active_sender_client_loop(
+            shutdown_rx,
+            config,
+            endpoint,
+            data_to_send_rx,
+            None,
+            ActiveSenderState::default(),
+            clock.shared(),
+        ));
+        // Nothing was published yet, so there's nothing to send as a heartbeat
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        assert!(clock.sleeps().is_empty());
+        data_to_send_tx.send_replace(DataToSend::new(
+            vec![MeasuredTemperature::example()],
+            vec![],
+            String::from("test"),
+        ));
+        // Nothing changes, but the latest data is sent anyway
+        wait_until_matched(&mock).await;
+        shutdown_tx.send(()).unwrap();
+        sender.await.unwrap();
+        assert!(clock
+            .sleeps()
+            .iter()
+            .all(|sleep| *sleep <= Duration::from_secs(60)));
+        mock.assert();
+    }
+
+    async fn wait_until_matched(mock: &mockito::Mock) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !mock.matched() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cooldown_skips_updates() {
+        let mut server = Server::new();
+        let mock_with_instance = |server: &mut mockito::ServerGuard, instance_id: &str| {
+            server
+                .mock("POST", "/data")
+                .match_body(PartialJsonString(format!(
+                    r#"{{"instance_id": "{}"}}"#,
+                    instance_id
+                )))
+                .with_status(200)
+        };
+        let first = mock_with_instance(&mut server, "first").create();
+        let skipped = mock_with_instance(&mut server, "skipped")
+            .expect(0)
+            .create();
+        let after_cooldown = mock_with_instance(&mut server, "after_cooldown").create();
+        let endpoint = Endpoint {
+            url: format!("{}/data", server.url()),
+            ..Default::default()
+        };
+        let config: ActiveSenderConfig = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "cooldown": { "secs": 10, "nanos": 0 },
+        }))
+        .unwrap();
+        let mock = MockClock::default();
+        let data = |instance_id: &str| DataToSend::new(vec![], vec![], String::from(instance_id));
+        let (data_to_send_tx, data_to_send_rx) = watch::channel(data("initial"));
+        let (shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let sender = tokio::spawn(start_active_sender_client_loop(
+            shutdown_rx,
+            config,
+            endpoint,
+            data_to_send_rx,
+            None,
+            ActiveSenderState::default(),
+            mock.shared(),
+        ));
+        data_to_send_tx.send_replace(data("first"));
+        wait_until_matched(&first).await;
+        // Still within cooldown of the loop's clock
+        mock.advance(Duration::from_secs(5));
+        data_to_send_tx.send_replace(data("skipped"));
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        mock.advance(Duration::from_secs(6));
+        data_to_send_tx.send_replace(data("after_cooldown"));
+        wait_until_matched(&after_cooldown).await;
+        shutdown_tx.send(()).unwrap();
+        sender.await.unwrap();
+        first.assert();
+        skipped.assert();
+        after_cooldown.assert();
+    }
+
+    #[tokio::test]
+    async fn test_merged_data_survives_restart() {
+        let config: ActiveSenderConfig =
+            serde_json::from_value(serde_json::json!({"enabled": true})).unwrap();
+        let state = ActiveSenderState::default();
+        let (one_wire_tx, _) = broadcast::channel(1);
+        let (ups_monitoring_tx, _) = broadcast::channel(1);
+        let (readings_tx, _) = broadcast::channel(1);
+        let run = |shutdown_rx| {
+            tokio::spawn(start_active_sender_loop(
+                shutdown_rx,
+                config.clone(),
+                one_wire_tx.subscribe(),
+                ups_monitoring_tx.subscribe(),
+                readings_tx.subscribe(),
+                String::from("instance"),
+                state.clone(),
+            ))
+        };
+        let (shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let sender = run(shutdown_rx);
+        let mut other = UninterruptiblePowerSupplyData::example();
+        other.meta.hw.id = String::from("other_hw_id");
+        ups_monitoring_tx
+            .send(UpsUpdate::new(
+                "nut",
+                vec![UninterruptiblePowerSupplyData::example()],
+            ))
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        shutdown_tx.send(()).unwrap();
+        sender.await.unwrap();
+
+        // Restarted, ex. by hot reload, only 1-Wire and another UPS source publish again
+        let (shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let sender = run(shutdown_rx);
+        one_wire_tx
+            .send(vec![MeasuredTemperature::example()])
+            .unwrap();
+        ups_monitoring_tx
+            .send(UpsUpdate::new("apcupsd", vec![other]))
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        shutdown_tx.send(()).unwrap();
+        sender.await.unwrap();
+        let merged = state.take_merged().unwrap();
+        assert_eq!(merged.data_to_send.sensors.len(), 1);
+        // UPSes of one source don't replace those of another
+        assert_eq!(merged.data_to_send.upses.len(), 2);
+    }
+
+    #[test]
+    fn test_publish() {
+        let data_to_send = DataToSend::new(vec![], vec![], String::from("instance"));
+        let (tx, mut rx) = watch::channel(data_to_send.clone());
+        rx.borrow_and_update();
+        // Every update wakes receivers by default
+        publish(&tx, &data_to_send, false);
+        assert!(rx.has_changed().unwrap());
+        rx.borrow_and_update();
+        // Same data doesn't wake receivers with skip_unchanged
+        publish(&tx, &data_to_send, true);
+        assert!(!rx.has_changed().unwrap());
+        let mut changed = data_to_send.clone();
+        changed.sensors = vec![MeasuredTemperature::example()];
+        publish(&tx, &changed, true);
+        assert!(rx.has_changed().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_send_xml() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("POST", "/soap")
+            .match_header("content-type", "text/xml; charset=utf-8")
+            .match_header("soapaction", "urn:Write")
+            .match_body("<Value>1 &lt; 2</Value>")
+            .with_status(200)
+            .create();
+        let endpoint = Endpoint {
+            url: format!("{}/soap", server.url()),
+            ..Default::default()
+        };
+        let xml = XmlOutput {
+            template_file: String::new(),
+            content_type: Some(String::from("text/xml; charset=utf-8")),
+            soap_action: Some(String::from("urn:Write")),
+        };
+        send_xml(
+            &Client::new(),
+            String::from("<Value>1 &lt; 2</Value>"),
+            &xml,
+            &endpoint,
+            "test",
+            None,
+            &Duration::from_secs(5),
+            &false,
+            &1024,
+        )
+        .await
+        .unwrap();
+        mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_http1_only_client() {
+        let mut server = Server::new();
+        let mock = server.mock("POST", "/http1").with_status(200).create();
+        let endpoint = Endpoint {
+            url: format!("{}/http1", server.url()),
+            http_version: Some(HttpVersion::Http1),
+            ..Default::default()
+        };
+        let client = build_client(&endpoint).unwrap();
+        let timeout = Duration::from_secs(5);
+        send_data(&client, &[1], &endpoint, "test", &timeout, &false, &1024).await;
+        send_data(&client, &[2], &endpoint, "test", &timeout, &false, &1024).await;
+        mock.expect(2).assert();
+        let status = introspection::snapshot();
+        let stats = &status.endpoints[&endpoint.url];
+        assert_eq!(stats.requests, 2);
+        assert_eq!(stats.versions["HTTP/1.1"], 2);
+    }
+
+    #[test]
+    fn test_payload_matches_golden_file() {
+        let mut data = DataToSend::new(
+            vec![fixtures::sensor()],
+            vec![fixtures::ups()],
+            String::from(fixtures::INSTANCE_ID),
+        );
+        data.readings = vec![fixtures::reading()];
+        assert_matches_golden_file("active_sender_payload", &data);
+    }
 }
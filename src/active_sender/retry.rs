@@ -0,0 +1,408 @@
+// Licensed under the Open Software License version 3.0
+//! Payloads that failed to reach an endpoint, sent again with exponential backoff
+//!
+//! Once the endpoint accepts any payload again, queued ones are sent right away, oldest first, so
+//! samples from an outage aren't lost. With a spool, payloads that would be dropped are written to
+//! disk instead. They're older than everything in memory, so they're sent first, but only after
+//! the endpoint accepts data again.
+use super::{config::RetryConfig, spool::Spool};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// Why a payload wasn't delivered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendFailure {
+    /// Connection error, timeout, 408, 429 or 5xx, may succeed later
+    Transient,
+    /// 401 or 403, may succeed with a new access token
+    Unauthorized,
+    /// Rejected by the endpoint or not serializable, would fail the same way again
+    Permanent,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Queued<T> {
+    pub payload: T,
+    // Failed retries so far
+    retries: u32,
+    // Oldest payload of the spool, removed from disk once delivered
+    spooled: bool,
+}
+
+/// Bounded FIFO of a single endpoint, oldest payloads are sent first
+#[derive(Debug)]
+pub struct RetryQueue<T> {
+    config: RetryConfig,
+    queue: VecDeque<Queued<T>>,
+    backoff: Duration,
+    next_attempt: Option<Instant>,
+    spool: Option<Spool>,
+    // Endpoint accepted data since the spool was last tried, true after a restart
+    resumed: bool,
+}
+
+impl<T: Serialize + DeserializeOwned> RetryQueue<T> {
+    pub fn new(config: RetryConfig) -> Self {
+        Self {
+            backoff: config.get_initial_backoff(),
+            config,
+            queue: VecDeque::new(),
+            next_attempt: None,
+            spool: None,
+            resumed: true,
+        }
+    }
+
+    pub fn with_spool(mut self, spool: Spool) -> Self {
+        self.spool = Some(spool);
+        self
+    }
+
+    /// Payloads waiting in memory and on disk
+    pub fn len(&self) -> usize {
+        self.queue.len() + self.spool.as_ref().map_or(0, Spool::len)
+    }
+
+    fn has_spooled(&self) -> bool {
+        self.spool.as_ref().map_or(false, |spool| !spool.is_empty())
+    }
+
+    /// Time until the next retry is due, `None` if nothing is queued
+    pub fn delay(&self, now: Instant) -> Option<Duration> {
+        if self.queue.is_empty() && !(self.resumed && self.has_spooled()) {
+            return None;
+        }
+        let next_attempt = self.next_attempt.unwrap_or(now);
+        Some(next_attempt.saturating_duration_since(now))
+    }
+
+    /// Move a payload out of memory, `false` if it's lost
+    async fn spill(&mut self, payload: &T) -> bool {
+        let spool = match &mut self.spool {
+            Some(spool) => spool,
+            None => return false,
+        };
+        match spool.push(payload).await {
+            Ok(0) => true,
+            Ok(removed) => {
+                tracing::warn!("Spool is full, removed {} oldest payloads", removed);
+                true
+            }
+            Err(error) => {
+                tracing::warn!("Failed to spool payload: {}", error);
+                false
+            }
+        }
+    }
+
+    /// Nothing is waiting, so the next failure starts a new backoff
+    fn reset_if_drained(&mut self) {
+        if self.queue.is_empty() && !(self.resumed && self.has_spooled()) {
+            self.next_attempt = None;
+        }
+    }
+
+    /// Queue a payload whose first attempt failed, `true` if the oldest one was dropped
+    ///
+    /// Nothing is queued with `max_retries` set to 0.
+    pub async fn push(&mut self, payload: T, now: Instant) -> bool {
+        if self.config.get_max_retries() == 0 {
+            return false;
+        }
+        let mut dropped = false;
+        if self.queue.len() >= self.config.get_queue_size() {
+            if let Some(oldest) = self.queue.pop_front() {
+                dropped = !self.spill(&oldest.payload).await;
+            }
+        }
+        self.queue.push_back(Queued {
+            payload,
+            retries: 0,
+            spooled: false,
+        });
+        self.next_attempt.get_or_insert(now + self.backoff);
+        dropped
+    }
+
+    /// Oldest payload, has to be returned with `delivered`, `failed` or `postpone`
+    pub async fn pop(&mut self) -> Option<Queued<T>> {
+        if self.resumed {
+            let spooled = match &mut self.spool {
+                Some(spool) => spool.peek().await,
+                None => None,
+            };
+            if let Some(payload) = spooled {
+                return Some(Queued {
+                    payload,
+                    retries: 0,
+                    spooled: true,
+                });
+            }
+        }
+        self.queue.pop_front()
+    }
+
+    /// Endpoint accepts data again, queued payloads don't have to wait anymore
+    pub fn succeeded(&mut self, now: Instant) {
+        self.backoff = self.config.get_initial_backoff();
+        self.next_attempt = Some(now);
+        self.resumed = true;
+        self.reset_if_drained();
+    }
+
+    /// Endpoint accepted `queued`
+    pub async fn delivered(&mut self, queued: Queued<T>, now: Instant) {
+        if let Err(error) = self.remove_spooled(&queued).await {
+            tracing::warn!("Failed to remove delivered payload from spool: {}", error);
+        }
+        self.succeeded(now);
+    }
+
+    /// Put back a payload that failed again, `false` if it ran out of retries and was dropped
+    pub async fn failed(&mut self, mut queued: Queued<T>, now: Instant) -> bool {
+        self.backoff = (self.backoff * 2).min(self.config.get_max_backoff());
+        self.next_attempt = Some(now + self.backoff);
+        if queued.spooled {
+            // Stays on disk until the endpoint accepts anything else
+            self.resumed = false;
+            return true;
+        }
+        queued.retries += 1;
+        if queued.retries >= self.config.get_max_retries() {
+            let spilled = self.spill(&queued.payload).await;
+            self.reset_if_drained();
+            return spilled;
+        }
+        self.queue.push_front(queued);
+        true
+    }
+
+    /// Retry later without counting an attempt, ex. while paused by the endpoint
+    pub fn postpone(&mut self, queued: Queued<T>, now: Instant) {
+        self.next_attempt = Some(now + self.backoff);
+        if !queued.spooled {
+            self.queue.push_front(queued);
+        }
+    }
+
+    /// Write payloads waiting in memory to the spool, so they survive a restart
+    ///
+    /// Returns the number of spooled payloads, nothing is written without a spool
+    pub async fn flush_to_spool(&mut self) -> usize {
+        if self.spool.is_none() {
+            return 0;
+        }
+        let mut spooled = 0;
+        // Oldest first, same order as they'd be retried
+        while let Some(queued) = self.queue.pop_front() {
+            if self.spill(&queued.payload).await {
+                spooled += 1;
+            }
+        }
+        spooled
+    }
+
+    /// Payloads waiting in memory, oldest first, ex. to hand them over to a restarted module
+    pub fn take_queued(&mut self) -> Vec<T> {
+        self.queue.drain(..).map(|queued| queued.payload).collect()
+    }
+
+    /// Rejected by the endpoint, sending it again wouldn't help
+    pub async fn rejected(&mut self, queued: Queued<T>) {
+        if let Err(error) = self.remove_spooled(&queued).await {
+            tracing::warn!("Failed to remove rejected payload from spool: {}", error);
+        }
+        self.reset_if_drained();
+    }
+
+    /// Remove `queued` from disk if it came from the spool
+    async fn remove_spooled(&mut self, queued: &Queued<T>) -> std::io::Result<()> {
+        match (&mut self.spool, queued.spooled) {
+            (Some(spool), true) => spool.remove_oldest().await,
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::Path;
+
+    fn queue(max_retries: u32, queue_size: usize) -> RetryQueue<u32> {
+        RetryQueue::new(RetryConfig {
+            max_retries: Some(max_retries),
+            queue_size: Some(queue_size),
+            initial_backoff: Some(Duration::from_secs(5)),
+            max_backoff: Some(Duration::from_secs(15)),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_backoff() {
+        let now = Instant::now();
+        let mut retries = queue(10, 10);
+        assert_eq!(retries.delay(now), None);
+        retries.push(1, now).await;
+        retries.push(2, now).await;
+        assert_eq!(retries.delay(now), Some(Duration::from_secs(5)));
+        let later = now + Duration::from_secs(5);
+        let queued = retries.pop().await.unwrap();
+        assert_eq!(queued.payload, 1);
+        assert!(retries.failed(queued, later).await);
+        assert_eq!(retries.delay(later), Some(Duration::from_secs(10)));
+        let queued = retries.pop().await.unwrap();
+        assert!(retries.failed(queued, later).await);
+        // Limited by max_backoff
+        assert_eq!(retries.delay(later), Some(Duration::from_secs(15)));
+        // Everything else is sent right after a delivery, starting with the oldest payload
+        let queued = retries.pop().await.unwrap();
+        assert_eq!(queued.payload, 1);
+        retries.delivered(queued, later).await;
+        assert_eq!(retries.delay(later), Some(Duration::ZERO));
+        let queued = retries.pop().await.unwrap();
+        assert_eq!(queued.payload, 2);
+        retries.delivered(queued, later).await;
+        assert_eq!(retries.delay(later), None);
+
+        // Drained, so the next failure waits for the initial backoff again
+        retries.push(3, later).await;
+        assert_eq!(retries.delay(later), Some(Duration::from_secs(5)));
+    }
+
+    #[tokio::test]
+    async fn test_fresh_payload_delivers_queued() {
+        let now = Instant::now();
+        let mut retries = queue(10, 10);
+        retries.push(1, now).await;
+        retries.push(2, now).await;
+        // Newer payload was accepted, queued ones are sent right away instead of being lost
+        retries.succeeded(now);
+        assert_eq!(retries.len(), 2);
+        assert_eq!(retries.delay(now), Some(Duration::ZERO));
+        let queued = retries.pop().await.unwrap();
+        assert_eq!(queued.payload, 1);
+        retries.delivered(queued, now).await;
+        let queued = retries.pop().await.unwrap();
+        assert_eq!(queued.payload, 2);
+        retries.delivered(queued, now).await;
+        assert_eq!(retries.len(), 0);
+        assert_eq!(retries.delay(now), None);
+        retries.push(3, now).await;
+        assert_eq!(retries.delay(now), Some(Duration::from_secs(5)));
+    }
+
+    #[tokio::test]
+    async fn test_no_retries() {
+        let now = Instant::now();
+        let mut retries = queue(0, 10);
+        assert!(!retries.push(1, now).await);
+        assert_eq!(retries.len(), 0);
+        assert_eq!(retries.delay(now), None);
+    }
+
+    #[tokio::test]
+    async fn test_limits() {
+        let now = Instant::now();
+        let mut retries = queue(2, 2);
+        assert!(!retries.push(1, now).await);
+        assert!(!retries.push(2, now).await);
+        // Oldest payload makes room for the newest one
+        assert!(retries.push(3, now).await);
+        assert_eq!(retries.len(), 2);
+        let queued = retries.pop().await.unwrap();
+        assert_eq!(queued.payload, 2);
+        assert!(retries.failed(queued, now).await);
+        let queued = retries.pop().await.unwrap();
+        assert!(!retries.failed(queued, now).await);
+        assert_eq!(retries.pop().await.unwrap().payload, 3);
+    }
+
+    #[tokio::test]
+    async fn test_postpone() {
+        let now = Instant::now();
+        let mut retries = queue(1, 10);
+        retries.push(1, now).await;
+        let queued = retries.pop().await.unwrap();
+        retries.postpone(queued, now);
+        let queued = retries.pop().await.unwrap();
+        // Postponing doesn't use up retries
+        assert!(!retries.failed(queued.clone(), now).await);
+        assert_eq!(queued.retries, 0);
+    }
+
+    async fn spooled_queue(directory: &Path) -> RetryQueue<u32> {
+        queue(1, 1).with_spool(Spool::open(directory, 1024).await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_spool() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let now = Instant::now();
+        let mut retries = spooled_queue(temp_dir.path()).await;
+        retries.push(1, now).await;
+        // Doesn't fit in memory, so the oldest one is spooled
+        assert!(!retries.push(2, now).await);
+        assert_eq!(retries.len(), 2);
+        // Spooled payloads are sent first, they're older
+        let queued = retries.pop().await.unwrap();
+        assert_eq!(queued.payload, 1);
+        assert!(retries.failed(queued, now).await);
+        // Kept on disk, but not tried again until the endpoint accepts anything else
+        let queued = retries.pop().await.unwrap();
+        assert_eq!(queued.payload, 2);
+        // Out of retries
+        assert!(retries.failed(queued, now).await);
+        assert_eq!(retries.len(), 2);
+        assert_eq!(retries.delay(now), None);
+        retries.push(3, now).await;
+        let queued = retries.pop().await.unwrap();
+        assert_eq!(queued.payload, 3);
+        retries.delivered(queued, now).await;
+        assert_eq!(retries.delay(now), Some(Duration::ZERO));
+
+        // Picked up after a restart
+        let mut retries = spooled_queue(temp_dir.path()).await;
+        assert_eq!(retries.delay(now), Some(Duration::ZERO));
+        let queued = retries.pop().await.unwrap();
+        assert_eq!(queued.payload, 1);
+        retries.delivered(queued, now).await;
+        let queued = retries.pop().await.unwrap();
+        assert_eq!(queued.payload, 2);
+        retries.rejected(queued).await;
+        assert_eq!(retries.pop().await, None);
+        assert_eq!(retries.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_flush_to_spool() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let now = Instant::now();
+        let mut retries =
+            queue(5, 10).with_spool(Spool::open(temp_dir.path(), 1024).await.unwrap());
+        retries.push(1, now).await;
+        retries.push(2, now).await;
+        assert_eq!(retries.flush_to_spool().await, 2);
+        drop(retries);
+
+        // Picked up after a restart in the same order
+        let mut retries = spooled_queue(temp_dir.path()).await;
+        assert_eq!(retries.len(), 2);
+        let queued = retries.pop().await.unwrap();
+        assert_eq!(queued.payload, 1);
+        retries.delivered(queued, now).await;
+        let queued = retries.pop().await.unwrap();
+        assert_eq!(queued.payload, 2);
+        retries.delivered(queued, now).await;
+        assert_eq!(retries.len(), 0);
+
+        // Nothing to write to without a spool
+        let mut retries = queue(5, 10);
+        retries.push(1, now).await;
+        assert_eq!(retries.flush_to_spool().await, 0);
+    }
+}
@@ -0,0 +1,220 @@
+// Licensed under the Open Software License version 3.0
+//! Payloads that didn't fit in the retry queue, kept on disk until the endpoint accepts them
+//!
+//! Every payload is a separate `<sequence number>.json` file, so the oldest one can be removed
+//! once delivered without rewriting the rest. Files survive restarts and are sent in order.
+//! Files are accessed with `tokio::fs`, so a slow disk doesn't block other tasks.
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::VecDeque,
+    io,
+    path::{Path, PathBuf},
+};
+use tokio::fs;
+
+const EXTENSION: &str = "json";
+// Readable part of directory names, the hash keeps them unique
+const MAX_PREFIX_LENGTH: usize = 48;
+
+/// Directory of a single endpoint
+#[derive(Debug)]
+pub struct Spool {
+    directory: PathBuf,
+    max_size: u64,
+    // Sequence number and size of every file, oldest first
+    files: VecDeque<(u64, u64)>,
+    size: u64,
+}
+
+/// Subdirectory name unique to `url`, without characters that aren't safe in paths
+///
+/// Sanitized start of `url` followed by the first 16 hex digits of its SHA-256, as replacing
+/// characters alone maps different urls (ex. `a-b` and `a.b`) to the same name
+pub fn directory_name(url: &str) -> String {
+    let prefix: String = url
+        .chars()
+        .take(MAX_PREFIX_LENGTH)
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let hash = format!("{:x}", Sha256::digest(url.as_bytes()));
+    format!("{}_{}", prefix, &hash[..16])
+}
+
+impl Spool {
+    /// Create `directory` or pick up files left by previous runs
+    pub async fn open(directory: &Path, max_size: u64) -> io::Result<Self> {
+        fs::create_dir_all(directory).await?;
+        let mut files = Vec::new();
+        let mut entries = fs::read_dir(directory).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some(EXTENSION) {
+                continue;
+            }
+            let sequence = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse::<u64>().ok());
+            if let Some(sequence) = sequence {
+                files.push((sequence, fs::metadata(&path).await?.len()));
+            }
+        }
+        files.sort_unstable();
+        let size = files.iter().map(|(_, size)| size).sum();
+        Ok(Self {
+            directory: directory.to_path_buf(),
+            max_size,
+            files: files.into(),
+            size,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    fn path(&self, sequence: u64) -> PathBuf {
+        self.directory
+            .join(format!("{:020}.{}", sequence, EXTENSION))
+    }
+
+    /// Append `payload`, removing the oldest ones to stay within `max_size`
+    ///
+    /// Returns the number of removed payloads
+    pub async fn push<T: Serialize>(&mut self, payload: &T) -> io::Result<usize> {
+        let json = serde_json::to_vec(payload)?;
+        let length = json.len() as u64;
+        if length > self.max_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("payload of {} bytes is larger than max_size", length),
+            ));
+        }
+        let mut removed = 0;
+        while self.size + length > self.max_size && !self.files.is_empty() {
+            self.remove_oldest().await?;
+            removed += 1;
+        }
+        let sequence = self.files.back().map_or(0, |(sequence, _)| sequence + 1);
+        // Written under a temporary name, so a crash never leaves a truncated payload
+        let path = self.path(sequence);
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, &json).await?;
+        fs::rename(&temporary, &path).await?;
+        self.files.push_back((sequence, length));
+        self.size += length;
+        Ok(removed)
+    }
+
+    /// Oldest payload, unreadable files are removed
+    pub async fn peek<T: DeserializeOwned>(&mut self) -> Option<T> {
+        while let Some((sequence, _)) = self.files.front() {
+            let path = self.path(*sequence);
+            let payload = fs::read(&path)
+                .await
+                .map_err(|error| error.to_string())
+                .and_then(|json| serde_json::from_slice(&json).map_err(|error| error.to_string()));
+            match payload {
+                Ok(payload) => return Some(payload),
+                Err(error) => {
+                    tracing::warn!("Removing unreadable {}: {}", path.display(), error);
+                    if let Err(error) = self.remove_oldest().await {
+                        tracing::warn!("Failed to remove {}: {}", path.display(), error);
+                        return None;
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Forget the oldest payload, ex. once it's delivered
+    pub async fn remove_oldest(&mut self) -> io::Result<()> {
+        if let Some((sequence, size)) = self.files.front().copied() {
+            match fs::remove_file(self.path(sequence)).await {
+                Ok(()) => {}
+                // Already gone, ex. removed by hand
+                Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+                Err(error) => return Err(error),
+            }
+            self.files.pop_front();
+            self.size -= size;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_order_and_restart() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut spool = Spool::open(temp_dir.path(), 1024).await.unwrap();
+        assert_eq!(spool.peek::<u32>().await, None);
+        spool.push(&1).await.unwrap();
+        spool.push(&2).await.unwrap();
+        assert_eq!(spool.peek::<u32>().await, Some(1));
+        spool.remove_oldest().await.unwrap();
+        spool.push(&3).await.unwrap();
+        // Picked up after a restart, still in order
+        let mut spool = Spool::open(temp_dir.path(), 1024).await.unwrap();
+        assert_eq!(spool.len(), 2);
+        assert_eq!(spool.peek::<u32>().await, Some(2));
+        spool.remove_oldest().await.unwrap();
+        assert_eq!(spool.peek::<u32>().await, Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_max_size() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        // Every payload is 4 bytes
+        let mut spool = Spool::open(temp_dir.path(), 10).await.unwrap();
+        assert_eq!(spool.push(&1000).await.unwrap(), 0);
+        assert_eq!(spool.push(&2000).await.unwrap(), 0);
+        assert_eq!(spool.push(&3000).await.unwrap(), 1);
+        assert_eq!(spool.len(), 2);
+        assert_eq!(spool.peek::<u32>().await, Some(2000));
+        assert!(spool.push(&"longer than max_size").await.is_err());
+        assert_eq!(spool.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut spool = Spool::open(temp_dir.path(), 1024).await.unwrap();
+        spool.push(&1).await.unwrap();
+        spool.push(&2).await.unwrap();
+        fs::write(spool.path(0), "{").await.unwrap();
+        assert_eq!(spool.peek::<u32>().await, Some(2));
+        assert_eq!(spool.len(), 1);
+    }
+
+    #[test]
+    fn test_directory_name() {
+        let prefix = "https___example_com_8443_api_a_b_";
+        let name = directory_name("https://example.com:8443/api?a=b");
+        assert!(name.starts_with(prefix));
+        assert_eq!(name.len(), prefix.len() + 16);
+        // Same after sanitizing, still different directories
+        assert_ne!(
+            directory_name("http://a-b/x"),
+            directory_name("http://a.b/x")
+        );
+        // Stable, so spooled payloads are picked up after a restart
+        assert_eq!(
+            directory_name("http://a-b/x"),
+            directory_name("http://a-b/x")
+        );
+        assert_eq!(
+            directory_name(&"x".repeat(1000)).len(),
+            MAX_PREFIX_LENGTH + 17
+        );
+    }
+}
@@ -0,0 +1,150 @@
+// Licensed under the Open Software License version 3.0
+use super::{
+    config::{Endpoint, StartupCheckMethod},
+    receiver::build_request,
+};
+use crate::bandwidth;
+use reqwest::{Method, StatusCode};
+use serde::Serialize;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EndpointCheckResult {
+    Ok(StatusCode),
+    Unauthorized(StatusCode),
+    Rejected(StatusCode),
+    Unreachable(String),
+}
+
+/// Send a single request to check reachability and auth validity
+///
+/// `Post` sends `tiny_payload`, other methods don't send any body
+pub async fn check_endpoint<T>(
+    client: &reqwest::Client,
+    endpoint: &Endpoint,
+    sink: &str,
+    method: &StartupCheckMethod,
+    tiny_payload: &T,
+) -> EndpointCheckResult
+where
+    T: ?Sized + Serialize,
+{
+    let method = match method {
+        StartupCheckMethod::Head => Method::HEAD,
+        StartupCheckMethod::Options => Method::OPTIONS,
+        StartupCheckMethod::Post => Method::POST,
+    };
+    let mut request =
+        build_request(client, method.clone(), endpoint).timeout(Duration::from_secs(5));
+    if method == Method::POST {
+        let body = serde_json::to_vec(tiny_payload).unwrap_or_default();
+        bandwidth::limiter::acquire(sink, body.len()).await;
+        request = request
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body);
+    }
+    match request.send().await {
+        Ok(response) => {
+            let status = response.status();
+            match status {
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                    EndpointCheckResult::Unauthorized(status)
+                }
+                // Endpoint may not implement HEAD or OPTIONS, but it's reachable
+                StatusCode::METHOD_NOT_ALLOWED => EndpointCheckResult::Ok(status),
+                _ if status.is_success() => EndpointCheckResult::Ok(status),
+                _ => EndpointCheckResult::Rejected(status),
+            }
+        }
+        Err(error) => EndpointCheckResult::Unreachable(error.to_string()),
+    }
+}
+
+/// Log result of startup check prominently
+pub fn report_endpoint_check(endpoint: &Endpoint, result: &EndpointCheckResult) {
+    match result {
+        EndpointCheckResult::Ok(status) => {
+            tracing::info!("Endpoint {} is reachable ({})", endpoint.url, status)
+        }
+        EndpointCheckResult::Unauthorized(status) => tracing::error!(
+            "Endpoint {} rejected credentials ({}), check bearer token",
+            endpoint.url,
+            status
+        ),
+        EndpointCheckResult::Rejected(status) => {
+            tracing::error!("Endpoint {} responded with {}", endpoint.url, status)
+        }
+        EndpointCheckResult::Unreachable(error) => {
+            tracing::error!("Endpoint {} is unreachable: {}", endpoint.url, error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Server;
+
+    fn endpoint(server: &Server, bearer_token: Option<&str>) -> Endpoint {
+        Endpoint {
+            url: format!("{}/data", server.url()),
+            bearer_token: bearer_token.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_endpoint_ok() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("HEAD", "/data")
+            .match_header("Authorization", "Bearer token")
+            .with_status(200)
+            .create();
+        let result = check_endpoint(
+            &reqwest::Client::new(),
+            &endpoint(&server, Some("token")),
+            "active_sender",
+            &StartupCheckMethod::Head,
+            &(),
+        )
+        .await;
+        assert_eq!(result, EndpointCheckResult::Ok(StatusCode::OK));
+        mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_check_endpoint_unauthorized() {
+        let mut server = Server::new();
+        let _mock = server.mock("POST", "/data").with_status(401).create();
+        let result = check_endpoint(
+            &reqwest::Client::new(),
+            &endpoint(&server, None),
+            "active_sender",
+            &StartupCheckMethod::Post,
+            &Vec::<u8>::new(),
+        )
+        .await;
+        assert_eq!(
+            result,
+            EndpointCheckResult::Unauthorized(StatusCode::UNAUTHORIZED)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_endpoint_unreachable() {
+        let endpoint = Endpoint {
+            url: String::from("http://127.0.0.1:1/data"),
+            ..Default::default()
+        };
+        let result = check_endpoint(
+            &reqwest::Client::new(),
+            &endpoint,
+            "active_sender",
+            &StartupCheckMethod::Options,
+            &(),
+        )
+        .await;
+        assert!(matches!(result, EndpointCheckResult::Unreachable(_)));
+    }
+}
@@ -0,0 +1,68 @@
+// Licensed under the Open Software License version 3.0
+//! State kept across restarts of the module, ex. by hot reload
+//!
+//! Sources don't publish again just because the sender restarted, so without the merged data the
+//! first payload after a reload would have empty categories and consumers would drop devices.
+use super::receiver::DataToSend;
+use crate::{hardware::reading::ReadingsByPublisher, nut::sender::UpsesByPublisher};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+/// Latest data of every source
+#[derive(Debug, Clone)]
+pub(super) struct MergedData {
+    pub(super) data_to_send: DataToSend,
+    pub(super) upses: UpsesByPublisher,
+    pub(super) readings: ReadingsByPublisher,
+}
+
+/// Left by the loop of a single endpoint
+#[derive(Debug, Default)]
+pub(super) struct EndpointState {
+    // Cooldown continues where the previous run stopped
+    pub(super) last_sent: Option<Instant>,
+    // Retry queue without a spool, oldest first
+    pub(super) queued: Vec<DataToSend>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    merged: Option<MergedData>,
+    // Endpoint url -> its state
+    endpoints: HashMap<String, EndpointState>,
+}
+
+/// Created once and passed to every run of the module
+#[derive(Debug, Clone, Default)]
+pub struct ActiveSenderState(Arc<Mutex<Inner>>);
+
+impl ActiveSenderState {
+    pub(super) fn take_merged(&self) -> Option<MergedData> {
+        self.0.lock().unwrap().merged.take()
+    }
+
+    pub(super) fn save_merged(&self, merged: MergedData) {
+        self.0.lock().unwrap().merged = Some(merged);
+    }
+
+    /// Default state of endpoints that weren't running before
+    pub(super) fn take_endpoint(&self, url: &str) -> EndpointState {
+        self.0
+            .lock()
+            .unwrap()
+            .endpoints
+            .remove(url)
+            .unwrap_or_default()
+    }
+
+    pub(super) fn save_endpoint(&self, url: &str, endpoint: EndpointState) {
+        self.0
+            .lock()
+            .unwrap()
+            .endpoints
+            .insert(String::from(url), endpoint);
+    }
+}
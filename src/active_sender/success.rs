@@ -0,0 +1,76 @@
+// Licensed under the Open Software License version 3.0
+//! Custom success criteria of endpoints that respond with 200 to rejected data
+use serde_json::Value;
+
+/// Value at `path`, a subset of JSONPath: `$`, `.key` and `[index]`, ex. `$.results[0].ok`
+fn select<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    let mut rest = path.trim().strip_prefix('$')?;
+    let mut current = value;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(|c| c == '.' || c == '[').unwrap_or(after.len());
+            let (key, remaining) = after.split_at(end);
+            current = current.get(key)?;
+            rest = remaining;
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let (index, remaining) = after.split_once(']')?;
+            current = current.get(index.trim().parse::<usize>().ok()?)?;
+            rest = remaining;
+        } else {
+            return None;
+        }
+    }
+    Some(current)
+}
+
+/// Same as in JavaScript, missing values are falsy too
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(value) => *value,
+        Value::Number(number) => number.as_f64().map_or(true, |number| number != 0.0),
+        Value::String(value) => !value.is_empty(),
+        Value::Array(_) | Value::Object(_) => true,
+    }
+}
+
+/// `false` if `body` isn't JSON or the value at `path` is falsy
+pub fn is_accepted(body: &str, path: &str) -> bool {
+    serde_json::from_str::<Value>(body)
+        .ok()
+        .as_ref()
+        .and_then(|json| select(json, path))
+        .map_or(false, is_truthy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_select() {
+        let value = json!({"ok": true, "results": [{"status": "stored"}, {"status": null}]});
+        assert_eq!(select(&value, "$"), Some(&value));
+        assert_eq!(select(&value, "$.ok"), Some(&json!(true)));
+        assert_eq!(
+            select(&value, "$.results[0].status"),
+            Some(&json!("stored"))
+        );
+        assert_eq!(select(&value, "$.results[2]"), None);
+        assert_eq!(select(&value, "$.missing.ok"), None);
+        assert_eq!(select(&value, "ok"), None);
+        assert_eq!(select(&value, "$.results[x]"), None);
+    }
+
+    #[test]
+    fn test_is_accepted() {
+        assert!(is_accepted(r#"{"ok": true}"#, "$.ok"));
+        assert!(is_accepted(r#"{"stored": 3}"#, "$.stored"));
+        assert!(!is_accepted(r#"{"ok": false}"#, "$.ok"));
+        assert!(!is_accepted(r#"{"stored": 0}"#, "$.stored"));
+        assert!(!is_accepted(r#"{"error": ""}"#, "$.error"));
+        assert!(!is_accepted(r#"{}"#, "$.ok"));
+        assert!(!is_accepted("OK", "$.ok"));
+    }
+}
@@ -0,0 +1,256 @@
+// Licensed under the Open Software License version 3.0
+use super::config::{Endpoint, OAuth2ClientCredentials};
+use crate::{
+    bandwidth,
+    dedup_log::{info_resolved, warn_deduplicated},
+};
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::time::Instant;
+
+// Refresh access tokens a bit earlier to account for clock skew and latency
+const EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+// Tokens without `expires_in` are requested again after this long, they may still expire
+const DEFAULT_TOKEN_TTL: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Deserialize)]
+struct AccessTokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+}
+
+/// Resolves bearer token of an endpoint before each send
+///
+/// Priority: `oauth2` > `bearer_token_file` > `bearer_token`
+#[derive(Debug, Default)]
+pub struct TokenProvider {
+    cached_access_token: Option<(String, Instant)>,
+}
+
+impl TokenProvider {
+    /// `sink` is charged for token requests, the same as for sends
+    pub async fn get_token(
+        &mut self,
+        client: &reqwest::Client,
+        endpoint: &Endpoint,
+        sink: &str,
+    ) -> Option<String> {
+        if let Some(oauth2) = &endpoint.oauth2 {
+            return self.get_access_token(client, oauth2, sink).await;
+        }
+        if let Some(path) = &endpoint.bearer_token_file {
+            // Re-read file every time, so it can be rotated externally
+            return match tokio::fs::read_to_string(path).await {
+                Ok(token) => {
+                    info_resolved!(format!("token:{}", path), "Read bearer token from {}", path);
+                    Some(String::from(token.trim()))
+                }
+                Err(error) => {
+                    warn_deduplicated!(
+                        format!("token:{}", path),
+                        "Failed to read bearer token from {}: {}",
+                        path,
+                        error
+                    );
+                    None
+                }
+            };
+        }
+        endpoint.bearer_token.clone()
+    }
+
+    async fn get_access_token(
+        &mut self,
+        client: &reqwest::Client,
+        oauth2: &OAuth2ClientCredentials,
+        sink: &str,
+    ) -> Option<String> {
+        if let Some((token, expires_at)) = &self.cached_access_token {
+            if Instant::now() < *expires_at {
+                return Some(token.clone());
+            }
+        }
+        tracing::debug!("Requesting access token from {}", oauth2.token_url);
+        let mut form = vec![
+            ("grant_type", "client_credentials"),
+            ("client_id", oauth2.client_id.as_str()),
+            ("client_secret", oauth2.client_secret.as_str()),
+        ];
+        if let Some(scope) = &oauth2.scope {
+            form.push(("scope", scope.as_str()));
+        }
+        let key = format!("token:{}", oauth2.token_url);
+        let request = match client
+            .post(&oauth2.token_url)
+            .form(&form)
+            .timeout(Duration::from_secs(5))
+            .build()
+        {
+            Ok(request) => request,
+            Err(error) => {
+                warn_deduplicated!(key, "Failed to get access token: {}", error);
+                return None;
+            }
+        };
+        let size = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map_or(0, |body| body.len());
+        bandwidth::limiter::acquire(sink, size).await;
+        let response = client
+            .execute(request)
+            .await
+            .and_then(|response| response.error_for_status());
+        let response: AccessTokenResponse = match response {
+            Ok(response) => match response.json().await {
+                Ok(response) => response,
+                Err(error) => {
+                    warn_deduplicated!(key, "Invalid access token response: {}", error);
+                    return None;
+                }
+            },
+            Err(error) => {
+                warn_deduplicated!(key, "Failed to get access token: {}", error);
+                return None;
+            }
+        };
+        info_resolved!(key, "Got access token from {}", oauth2.token_url);
+        let expires_at = match response.expires_in {
+            Some(expires_in) => {
+                Instant::now() + Duration::from_secs(expires_in).saturating_sub(EXPIRY_MARGIN)
+            }
+            None => Instant::now() + DEFAULT_TOKEN_TTL,
+        };
+        self.cached_access_token = Some((response.access_token.clone(), expires_at));
+        Some(response.access_token)
+    }
+
+    /// Forget the cached access token, ex. after it was rejected before its expiry
+    pub fn invalidate(&mut self) {
+        if self.cached_access_token.take().is_some() {
+            tracing::debug!("Dropped rejected access token");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{Matcher, Server};
+
+    #[tokio::test]
+    async fn test_static_token() {
+        let endpoint = Endpoint {
+            bearer_token: Some(String::from("static")),
+            ..Default::default()
+        };
+        let mut provider = TokenProvider::default();
+        let token = provider
+            .get_token(&reqwest::Client::new(), &endpoint, "active_sender")
+            .await;
+        assert_eq!(token, Some(String::from("static")));
+    }
+
+    #[tokio::test]
+    async fn test_token_file_is_reread() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("token");
+        let endpoint = Endpoint {
+            bearer_token: Some(String::from("ignored")),
+            bearer_token_file: Some(path.to_str().unwrap().to_string()),
+            ..Default::default()
+        };
+        let client = reqwest::Client::new();
+        let mut provider = TokenProvider::default();
+
+        std::fs::write(&path, "first\n").unwrap();
+        let token = provider
+            .get_token(&client, &endpoint, "active_sender")
+            .await;
+        assert_eq!(token, Some(String::from("first")));
+
+        std::fs::write(&path, "second\n").unwrap();
+        let token = provider
+            .get_token(&client, &endpoint, "active_sender")
+            .await;
+        assert_eq!(token, Some(String::from("second")));
+    }
+
+    #[tokio::test]
+    async fn test_oauth2_token_is_cached() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("POST", "/token")
+            .match_body(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("grant_type".into(), "client_credentials".into()),
+                Matcher::UrlEncoded("client_id".into(), "id".into()),
+                Matcher::UrlEncoded("client_secret".into(), "secret".into()),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"access_token": "access", "expires_in": 3600}"#)
+            .expect(1)
+            .create();
+        let endpoint = Endpoint {
+            oauth2: Some(OAuth2ClientCredentials {
+                token_url: format!("{}/token", server.url()),
+                client_id: String::from("id"),
+                client_secret: String::from("secret"),
+                scope: None,
+            }),
+            ..Default::default()
+        };
+        let client = reqwest::Client::new();
+        let mut provider = TokenProvider::default();
+
+        let token = provider
+            .get_token(&client, &endpoint, "active_sender")
+            .await;
+        assert_eq!(token, Some(String::from("access")));
+        let token = provider
+            .get_token(&client, &endpoint, "active_sender")
+            .await;
+        assert_eq!(token, Some(String::from("access")));
+        mock.assert();
+    }
+    #[tokio::test]
+    async fn test_oauth2_token_is_invalidated() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("POST", "/token")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"access_token": "access"}"#)
+            .expect(2)
+            .create();
+        let endpoint = Endpoint {
+            oauth2: Some(OAuth2ClientCredentials {
+                token_url: format!("{}/token", server.url()),
+                client_id: String::from("id"),
+                client_secret: String::from("secret"),
+                scope: None,
+            }),
+            ..Default::default()
+        };
+        let client = reqwest::Client::new();
+        let mut provider = TokenProvider::default();
+
+        provider
+            .get_token(&client, &endpoint, "active_sender")
+            .await;
+        // Cached for a while even without expires_in
+        let (_, expires_at) = provider.cached_access_token.as_ref().unwrap();
+        assert!(*expires_at <= Instant::now() + DEFAULT_TOKEN_TTL);
+        provider
+            .get_token(&client, &endpoint, "active_sender")
+            .await;
+
+        // Rejected by the endpoint, so a new one is requested
+        provider.invalidate();
+        let token = provider
+            .get_token(&client, &endpoint, "active_sender")
+            .await;
+        assert_eq!(token, Some(String::from("access")));
+        mock.assert();
+    }
+}
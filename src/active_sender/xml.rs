@@ -0,0 +1,244 @@
+// Licensed under the Open Software License version 3.0
+//! Rendering snapshots through XML templates for systems that don't accept JSON
+//!
+//! Templates use a small subset of Mustache: `{{name}}` inserts an escaped value and
+//! `{{#name}}...{{/name}}` repeats its content for every item of a list.
+use super::receiver::DataToSend;
+use crate::{
+    hardware::{reading::Reading, types::HardwareMetadata},
+    nut::sender::UninterruptiblePowerSupplyData,
+    one_wire::sender::MeasuredTemperature,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Text(String),
+    Value(String),
+    Section(String, Vec<Node>),
+}
+
+/// Parsed template, ready to render many snapshots
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmlTemplate(Vec<Node>);
+
+/// Escape `value` for use in both text and attribute values
+pub fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for character in value.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+fn parse_nodes<'a>(
+    mut rest: &'a str,
+    section: Option<&str>,
+) -> Result<(Vec<Node>, &'a str), String> {
+    let mut nodes = Vec::new();
+    while let Some(start) = rest.find("{{") {
+        if start > 0 {
+            nodes.push(Node::Text(String::from(&rest[..start])));
+        }
+        let end = rest[start..]
+            .find("}}")
+            .ok_or_else(|| String::from("unclosed {{"))?;
+        let tag = rest[start + 2..start + end].trim();
+        rest = &rest[start + end + 2..];
+        if let Some(name) = tag.strip_prefix('#') {
+            let (children, remaining) = parse_nodes(rest, Some(name.trim()))?;
+            nodes.push(Node::Section(String::from(name.trim()), children));
+            rest = remaining;
+        } else if let Some(name) = tag.strip_prefix('/') {
+            return match section {
+                Some(section) if section == name.trim() => Ok((nodes, rest)),
+                _ => Err(format!("unexpected {{{{/{}}}}}", name.trim())),
+            };
+        } else {
+            nodes.push(Node::Value(String::from(tag)));
+        }
+    }
+    if let Some(section) = section {
+        return Err(format!("unclosed {{{{#{}}}}}", section));
+    }
+    if !rest.is_empty() {
+        nodes.push(Node::Text(String::from(rest)));
+    }
+    Ok((nodes, ""))
+}
+
+/// Anything that can be referenced from a template
+enum Scope<'a> {
+    Root(&'a DataToSend, &'a str),
+    Sensor(&'a MeasuredTemperature),
+    Ups(&'a UninterruptiblePowerSupplyData),
+    Reading(&'a Reading),
+    Entry(String, String),
+}
+
+fn meta_value(meta: &HardwareMetadata, name: &str) -> Option<String> {
+    match name {
+        "id" => Some(meta.hw.id.clone()),
+        "name" => Some(meta.hw.name.clone().unwrap_or_default()),
+        "hardware_type" => Some(format!("{:?}", meta.hw.hardware_type)),
+        "source_type" => Some(format!("{:?}", meta.source.source_type)),
+        _ => None,
+    }
+}
+
+impl<'a> Scope<'a> {
+    fn value(&self, name: &str) -> Option<String> {
+        match self {
+            Scope::Root(data, timestamp) => match name {
+                "instance_id" => Some(data.instance_id.clone()),
+                "timestamp" => Some(String::from(*timestamp)),
+                _ => None,
+            },
+            Scope::Sensor(sensor) => match name {
+                "temperature" => Some(
+                    sensor
+                        .temperature
+                        .map(|t| t.to_string())
+                        .unwrap_or_default(),
+                ),
+                "resolution" => Some(sensor.resolution.map(|r| r.to_string()).unwrap_or_default()),
+                _ => meta_value(&sensor.meta, name),
+            },
+            Scope::Ups(ups) => meta_value(&ups.meta, name),
+            Scope::Reading(reading) => meta_value(&reading.meta, name),
+            Scope::Entry(key, value) => match name {
+                "key" => Some(key.clone()),
+                "value" => Some(value.clone()),
+                _ => None,
+            },
+        }
+    }
+
+    fn section(&self, name: &str) -> Option<Vec<Scope<'a>>> {
+        match (self, name) {
+            (Scope::Root(data, _), "sensors") => {
+                Some(data.sensors.iter().map(Scope::Sensor).collect())
+            }
+            (Scope::Root(data, _), "upses") => Some(data.upses.iter().map(Scope::Ups).collect()),
+            (Scope::Root(data, _), "readings") => {
+                Some(data.readings.iter().map(Scope::Reading).collect())
+            }
+            (Scope::Ups(ups), "variables") => {
+                // Sorted, so output doesn't depend on HashMap order
+                let mut variables: Vec<_> = ups.variables.iter().collect();
+                variables.sort();
+                let entries = variables
+                    .into_iter()
+                    .map(|(key, value)| Scope::Entry(key.clone(), value.clone()))
+                    .collect();
+                Some(entries)
+            }
+            (Scope::Reading(reading), "values") => {
+                let entries = reading
+                    .values
+                    .iter()
+                    .map(|(key, value)| Scope::Entry(key.clone(), value.to_string()))
+                    .collect();
+                Some(entries)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn render_nodes<'a>(nodes: &[Node], scopes: &mut Vec<Scope<'a>>, output: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            // Inner scopes shadow outer ones, ex. `{{instance_id}}` works inside `{{#sensors}}`
+            Node::Value(name) => match scopes.iter().rev().find_map(|scope| scope.value(name)) {
+                Some(value) => output.push_str(&escape(&value)),
+                None => tracing::trace!("Unknown template value {}", name),
+            },
+            Node::Section(name, children) => {
+                let items = scopes.iter().rev().find_map(|scope| scope.section(name));
+                let items = match items {
+                    Some(items) => items,
+                    None => {
+                        tracing::trace!("Unknown template section {}", name);
+                        continue;
+                    }
+                };
+                for item in items {
+                    scopes.push(item);
+                    render_nodes(children, scopes, output);
+                    scopes.pop();
+                }
+            }
+        }
+    }
+}
+
+impl XmlTemplate {
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let (nodes, _) = parse_nodes(template, None)?;
+        Ok(Self(nodes))
+    }
+
+    /// Render `data`, `timestamp` is exposed as `{{timestamp}}`
+    pub fn render(&self, data: &DataToSend, timestamp: &str) -> String {
+        let mut output = String::new();
+        let mut scopes = vec![Scope::Root(data, timestamp)];
+        render_nodes(&self.0, &mut scopes, &mut output);
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::types::Example;
+
+    fn data() -> DataToSend {
+        let mut sensor = MeasuredTemperature::example();
+        sensor.meta.hw.name = Some(String::from("Server room <A&B>"));
+        DataToSend::new(
+            vec![sensor],
+            vec![UninterruptiblePowerSupplyData::example()],
+            String::from("instance"),
+        )
+    }
+
+    #[test]
+    fn test_render_sections_and_escaping() {
+        let template = XmlTemplate::parse(
+            r#"<Report node="{{instance_id}}">{{#sensors}}<Point name="{{name}}" node="{{instance_id}}">{{temperature}}</Point>{{/sensors}}</Report>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            template.render(&data(), "2023-01-01T00:00:00Z"),
+            r#"<Report node="instance"><Point name="Server room &lt;A&amp;B&gt;" node="instance">0</Point></Report>"#
+        );
+    }
+
+    #[test]
+    fn test_render_nested_entries() {
+        let template = XmlTemplate::parse(
+            "{{#upses}}{{#variables}}<{{key}}>{{value}}</{{key}}>{{/variables}}{{/upses}}",
+        )
+        .unwrap();
+        assert_eq!(
+            template.render(&data(), ""),
+            "<battery.charge>100</battery.charge><ups.load>15</ups.load>"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(XmlTemplate::parse("{{#sensors}}").is_err());
+        assert!(XmlTemplate::parse("{{/sensors}}").is_err());
+        assert!(XmlTemplate::parse("{{#sensors}}{{/upses}}").is_err());
+        assert!(XmlTemplate::parse("{{name").is_err());
+    }
+}
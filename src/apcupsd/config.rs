@@ -0,0 +1,91 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Default port of apcupsd Network Information Server
+const DEFAULT_PORT: u16 = 3551;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApcupsdServerConfig {
+    host: String,
+    port: Option<u16>,
+    // hw.id of the UPS, apcupsd@<host>:<port> if not set
+    id: Option<String>,
+}
+
+impl Example for ApcupsdServerConfig {
+    fn example() -> Self {
+        Self {
+            host: String::from("localhost"),
+            port: Some(DEFAULT_PORT),
+            id: Some(String::from("ups-garage")),
+        }
+    }
+}
+
+impl ApcupsdServerConfig {
+    pub fn get_address(&self) -> String {
+        format!("{}:{}", self.host, self.port.unwrap_or(DEFAULT_PORT))
+    }
+
+    pub fn get_id(&self) -> String {
+        self.id
+            .clone()
+            .unwrap_or_else(|| format!("apcupsd@{}", self.get_address()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApcupsdConfig {
+    enabled: Option<bool>,
+    cooldown: Option<Duration>,
+    servers: Option<Vec<ApcupsdServerConfig>>,
+}
+
+impl Default for ApcupsdConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            cooldown: Some(Duration::from_secs(5)),
+            servers: None,
+        }
+    }
+}
+
+impl Example for ApcupsdConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(false),
+            cooldown: Some(Duration::from_secs(5)),
+            servers: Some(vec![ApcupsdServerConfig::example()]),
+        }
+    }
+}
+
+impl ApcupsdConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_cooldown(&self) -> Duration {
+        self.cooldown.unwrap_or(Duration::from_secs(5))
+    }
+
+    pub fn get_servers(&self) -> Vec<ApcupsdServerConfig> {
+        self.servers.clone().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_config_get_id() {
+        assert_eq!(ApcupsdServerConfig::example().get_id(), "ups-garage");
+        let config: ApcupsdServerConfig = serde_json::from_str(r#"{"host": "nas.lan"}"#).unwrap();
+        assert_eq!(config.get_address(), "nas.lan:3551");
+        assert_eq!(config.get_id(), "apcupsd@nas.lan:3551");
+    }
+}
@@ -0,0 +1,5 @@
+// Licensed under the Open Software License version 3.0
+pub mod config;
+mod nis;
+pub mod sender;
+mod status;
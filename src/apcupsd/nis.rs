@@ -0,0 +1,105 @@
+// Licensed under the Open Software License version 3.0
+//! apcupsd Network Information Server protocol, same as `apcaccess`
+//!
+//! Every message is prefixed with its length as big-endian u16, a zero length ends the response
+use std::time::Duration;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    time::timeout,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// STATUS has about 50 lines, anything longer isn't from apcupsd
+const MAX_RECORDS: usize = 512;
+
+pub fn encode_command(command: &str) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(2 + command.len());
+    packet.extend_from_slice(&(command.len() as u16).to_be_bytes());
+    packet.extend_from_slice(command.as_bytes());
+    packet
+}
+
+/// Records of a response up to the zero length terminator
+pub async fn read_records<R: AsyncRead + Unpin>(reader: &mut R) -> std::io::Result<Vec<String>> {
+    let mut records = Vec::new();
+    loop {
+        let length = reader.read_u16().await? as usize;
+        if length == 0 {
+            return Ok(records);
+        }
+        if records.len() >= MAX_RECORDS {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "response is too long",
+            ));
+        }
+        let mut record = vec![0u8; length];
+        reader.read_exact(&mut record).await?;
+        records.push(String::from_utf8_lossy(&record).into_owned());
+    }
+}
+
+async fn exchange(address: &str, command: &str) -> std::io::Result<Vec<String>> {
+    let mut stream = TcpStream::connect(address).await?;
+    stream.write_all(&encode_command(command)).await?;
+    read_records(&mut stream).await
+}
+
+/// Output of `status` command, one `KEY : value` record per line
+pub async fn query_status(address: &str) -> Result<String, String> {
+    timeout(TIMEOUT, exchange(address, "status"))
+        .await
+        .map_err(|_| String::from("timed out"))?
+        .map(|records| records.concat())
+        .map_err(|error| error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    fn encode_response(lines: &[&str]) -> Vec<u8> {
+        let mut packet: Vec<u8> = lines.iter().flat_map(|line| encode_command(line)).collect();
+        packet.extend_from_slice(&[0, 0]);
+        packet
+    }
+
+    #[test]
+    fn test_encode_command() {
+        assert_eq!(encode_command("status"), b"\x00\x06status");
+    }
+
+    #[tokio::test]
+    async fn test_read_records() {
+        let packet = encode_response(&["APC      : 001,036,0879\n", "STATUS   : ONLINE \n"]);
+        let records = read_records(&mut packet.as_slice()).await.unwrap();
+        assert_eq!(
+            records,
+            ["APC      : 001,036,0879\n", "STATUS   : ONLINE \n"]
+        );
+        // Connection closed before the terminator
+        assert!(read_records(&mut &packet[..packet.len() - 2])
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_query_status() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut command = [0u8; 8];
+            stream.read_exact(&mut command).await.unwrap();
+            assert_eq!(&command, b"\x00\x06status");
+            let response = encode_response(&["UPSNAME  : garage\n", "LOADPCT  : 12.0 Percent\n"]);
+            stream.write_all(&response).await.unwrap();
+        });
+        assert_eq!(
+            query_status(&address).await.unwrap(),
+            "UPSNAME  : garage\nLOADPCT  : 12.0 Percent\n"
+        );
+    }
+}
@@ -0,0 +1,130 @@
+// Licensed under the Open Software License version 3.0
+use super::{
+    config::{ApcupsdConfig, ApcupsdServerConfig},
+    nis::query_status,
+    status::{parse_fields, to_variables},
+};
+use crate::{
+    dedup_log::{info_resolved, warn_deduplicated},
+    hardware::types::{HardwareMetadata, HardwareType, SourceType},
+    introspection,
+    nut::sender::{UninterruptiblePowerSupplyData, UpsUpdate},
+    relations::config::RelationsConfig,
+    sampling,
+    scheduler::job::{PollFuture, PollJob},
+};
+use std::{cmp::max, time::Duration};
+use tokio::{sync::broadcast, time::sleep};
+
+const PUBLISHER: &str = "apcupsd";
+
+async fn read_server(
+    server: &ApcupsdServerConfig,
+) -> Result<UninterruptiblePowerSupplyData, String> {
+    let status = query_status(&server.get_address()).await?;
+    let variables = to_variables(&parse_fields(&status))?;
+    let meta = HardwareMetadata::new(
+        server.get_id(),
+        HardwareType::UninterruptiblePowerSupply,
+        SourceType::Apcupsd,
+    )
+    .measured_now();
+    Ok(UninterruptiblePowerSupplyData {
+        meta,
+        variables,
+        clients: None,
+    })
+}
+
+/// UPSes of all reachable servers
+pub async fn read_servers(servers: &[ApcupsdServerConfig]) -> Vec<UninterruptiblePowerSupplyData> {
+    let mut upses = Vec::new();
+    for server in servers {
+        let address = server.get_address();
+        let key = format!("apcupsd:{}", address);
+        match read_server(server).await {
+            Ok(ups) => {
+                info_resolved!(key, "Reading apcupsd at {} again", address);
+                upses.push(ups);
+            }
+            Err(error) => {
+                warn_deduplicated!(key, "Failed to read apcupsd at {}: {}", address, error);
+            }
+        }
+    }
+    upses
+}
+
+pub struct ApcupsdPoller {
+    servers: Vec<ApcupsdServerConfig>,
+    relations_config: RelationsConfig,
+    cooldown: Duration,
+    tx: broadcast::Sender<UpsUpdate>,
+}
+
+impl ApcupsdPoller {
+    pub fn new(
+        config: &ApcupsdConfig,
+        relations_config: RelationsConfig,
+        tx: broadcast::Sender<UpsUpdate>,
+    ) -> Self {
+        Self {
+            servers: config.get_servers(),
+            relations_config,
+            cooldown: max(config.get_cooldown(), Duration::from_secs(1)),
+            tx,
+        }
+    }
+
+    /// Publish all readable UPSes, returns delay until the next poll
+    pub async fn poll_once(&mut self) -> Duration {
+        introspection::mark_iteration("apcupsd");
+        let mut upses = read_servers(&self.servers).await;
+        for ups in &mut upses {
+            self.relations_config.annotate(&mut ups.meta);
+        }
+        tracing::trace!("Sending {:?} to channel", upses);
+        if self.tx.receiver_count() > 0 {
+            self.tx.send(UpsUpdate::new(PUBLISHER, upses)).unwrap();
+            introspection::observe_channel("ups_monitoring", &self.tx);
+        }
+        introspection::mark_idle("apcupsd");
+        self.cooldown
+    }
+}
+
+impl PollJob for ApcupsdPoller {
+    fn name(&self) -> String {
+        String::from("apcupsd")
+    }
+
+    fn poll(&mut self) -> PollFuture<'_> {
+        Box::pin(self.poll_once())
+    }
+}
+
+pub async fn start_apcupsd_loop(
+    mut shutdown_rx: broadcast::Receiver<()>,
+    config: ApcupsdConfig,
+    relations_config: RelationsConfig,
+    tx: broadcast::Sender<UpsUpdate>,
+) {
+    // Check if module is enabled
+    if !config.is_enabled() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+    tracing::debug!("Starting apcupsd loop");
+    let mut poller = ApcupsdPoller::new(&config, relations_config, tx);
+    let _task = introspection::task_started("apcupsd");
+    loop {
+        let delay = poller.poll_once().await;
+        tokio::select! {
+            _ = shutdown_rx.recv() => {
+                tracing::trace!("Shutting down apcupsd loop");
+                break;
+            }
+            _ = sleep(sampling::tick::aligned(delay)) => {}
+        }
+    }
+}
@@ -0,0 +1,161 @@
+// Licensed under the Open Software License version 3.0
+//! Conversion of apcupsd STATUS output to NUT variables
+use std::collections::HashMap;
+
+/// apcupsd fields whose first word is copied to a NUT variable
+const NUMERIC_FIELDS: [(&str, &str); 11] = [
+    ("BCHARGE", "battery.charge"),
+    ("MBATTCHG", "battery.charge.low"),
+    ("LOADPCT", "ups.load"),
+    ("LINEV", "input.voltage"),
+    ("NOMINV", "input.voltage.nominal"),
+    ("LINEFREQ", "input.frequency"),
+    ("OUTPUTV", "output.voltage"),
+    ("BATTV", "battery.voltage"),
+    ("NOMBATTV", "battery.voltage.nominal"),
+    ("NOMPOWER", "ups.realpower.nominal"),
+    ("ITEMP", "ups.temperature"),
+];
+/// apcupsd fields in minutes, NUT uses seconds
+const MINUTES_FIELDS: [(&str, &str); 2] = [
+    ("TIMELEFT", "battery.runtime"),
+    ("MINTIMEL", "battery.runtime.low"),
+];
+/// apcupsd fields copied as they are
+const TEXT_FIELDS: [(&str, &str); 5] = [
+    ("MODEL", "ups.model"),
+    ("SERIALNO", "ups.serial"),
+    ("FIRMWARE", "ups.firmware"),
+    ("BATTDATE", "battery.date"),
+    ("LASTXFER", "input.transfer.reason"),
+];
+/// Flags of apcupsd STATUS and their `ups.status` equivalents
+const STATUS_FLAGS: [(&str, &str); 8] = [
+    ("CAL", "CAL"),
+    ("TRIM", "TRIM"),
+    ("BOOST", "BOOST"),
+    ("ONLINE", "OL"),
+    ("ONBATT", "OB"),
+    ("OVERLOAD", "OVER"),
+    ("LOWBATT", "LB"),
+    ("REPLACEBATT", "RB"),
+];
+
+/// Fields of `KEY : value` lines
+pub fn parse_fields(status: &str) -> HashMap<String, String> {
+    status
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (String::from(key.trim()), String::from(value.trim())))
+        .collect()
+}
+
+/// `ups.status` made of NUT flags, ex. "ONBATT LOWBATT" becomes "OB LB"
+fn convert_status(status: &str) -> String {
+    let flags: Vec<&str> = status.split_whitespace().collect();
+    let mut converted: Vec<&str> = STATUS_FLAGS
+        .iter()
+        .filter(|(flag, _)| flags.contains(flag))
+        .map(|(_, nut_flag)| *nut_flag)
+        .collect();
+    if flags.contains(&"SHUTTING") {
+        converted.push("FSD");
+    }
+    converted.join(" ")
+}
+
+/// Variables named like NUT ones, so UPS shutdown, load shedding and runtime projection work the same
+pub fn to_variables(fields: &HashMap<String, String>) -> Result<HashMap<String, String>, String> {
+    let status = fields
+        .get("STATUS")
+        .ok_or_else(|| String::from("missing STATUS"))?;
+    // apcupsd keeps answering with the last known values
+    if status.contains("COMMLOST") {
+        return Err(String::from("apcupsd lost communication with the UPS"));
+    }
+    let mut variables = HashMap::new();
+    variables.insert(String::from("ups.status"), convert_status(status));
+    variables.insert(String::from("driver.name"), String::from("apcupsd"));
+    let first_word = |key: &str| {
+        fields
+            .get(key)
+            .and_then(|value| value.split_whitespace().next())
+            .filter(|value| value.parse::<f64>().is_ok())
+    };
+    for (key, variable) in NUMERIC_FIELDS {
+        if let Some(value) = first_word(key) {
+            variables.insert(String::from(variable), String::from(value));
+        }
+    }
+    for (key, variable) in MINUTES_FIELDS {
+        if let Some(minutes) = first_word(key).and_then(|value| value.parse::<f64>().ok()) {
+            let seconds = (minutes * 60.0).round();
+            variables.insert(String::from(variable), seconds.to_string());
+        }
+    }
+    for (key, variable) in TEXT_FIELDS {
+        if let Some(value) = fields.get(key).filter(|value| !value.is_empty()) {
+            variables.insert(String::from(variable), value.clone());
+        }
+    }
+    Ok(variables)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATUS: &str = "APC      : 001,036,0879
+DATE     : 2023-01-01 12:00:00 +0100
+HOSTNAME : nas
+UPSNAME  : garage
+MODEL    : Back-UPS ES 700G
+STATUS   : ONBATT LOWBATT
+LINEV    : 0.0 Volts
+LOADPCT  : 17.0 Percent
+BCHARGE  : 9.0 Percent
+TIMELEFT : 2.5 Minutes
+MBATTCHG : 10 Percent
+MINTIMEL : 3 Minutes
+LASTXFER : Low line voltage
+BATTV    : 12.1 Volts
+NOMPOWER : 405 Watts
+SERIALNO : 5B1234T56789
+END APC  : 2023-01-01 12:00:05 +0100
+";
+
+    #[test]
+    fn test_parse_fields() {
+        let fields = parse_fields(STATUS);
+        assert_eq!(fields["UPSNAME"], "garage");
+        // Only the first colon separates key and value
+        assert_eq!(fields["DATE"], "2023-01-01 12:00:00 +0100");
+        assert_eq!(fields["END APC"], "2023-01-01 12:00:05 +0100");
+    }
+
+    #[test]
+    fn test_to_variables() {
+        let variables = to_variables(&parse_fields(STATUS)).unwrap();
+        assert_eq!(variables["ups.status"], "OB LB");
+        assert_eq!(variables["battery.charge"], "9.0");
+        assert_eq!(variables["battery.charge.low"], "10");
+        assert_eq!(variables["battery.runtime"], "150");
+        assert_eq!(variables["battery.runtime.low"], "180");
+        assert_eq!(variables["ups.load"], "17.0");
+        assert_eq!(variables["input.voltage"], "0.0");
+        assert_eq!(variables["ups.realpower.nominal"], "405");
+        assert_eq!(variables["ups.model"], "Back-UPS ES 700G");
+        assert_eq!(variables["input.transfer.reason"], "Low line voltage");
+        assert!(!variables.contains_key("output.voltage"));
+    }
+
+    #[test]
+    fn test_convert_status() {
+        assert_eq!(convert_status("ONLINE"), "OL");
+        assert_eq!(convert_status("ONLINE REPLACEBATT"), "OL RB");
+        assert_eq!(convert_status("TRIM ONLINE"), "TRIM OL");
+        assert_eq!(convert_status("SHUTTING DOWN"), "FSD");
+        assert!(to_variables(&parse_fields("STATUS   : COMMLOST\n")).is_err());
+        assert!(to_variables(&parse_fields("UPSNAME  : garage\n")).is_err());
+    }
+}
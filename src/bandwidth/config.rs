@@ -0,0 +1,99 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Order in which sinks waiting for bandwidth are served
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    // Waits while any other sink is waiting
+    Bulk,
+    #[default]
+    Normal,
+    // Never waits, but still uses up bandwidth of others
+    Critical,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BandwidthConfig {
+    enabled: Option<bool>,
+    // Average outbound rate shared by all sinks
+    bytes_per_second: Option<u64>,
+    // Bytes that can be sent at once after being idle, bytes_per_second by default
+    burst: Option<u64>,
+    // Sink name -> priority, ups_shutdown is critical and others are normal by default
+    priorities: Option<HashMap<String, Priority>>,
+}
+
+impl Default for BandwidthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            // 1 Mbit/s
+            bytes_per_second: Some(125_000),
+            burst: None,
+            priorities: None,
+        }
+    }
+}
+
+impl Example for BandwidthConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(false),
+            bytes_per_second: Some(125_000),
+            burst: Some(250_000),
+            priorities: Some(HashMap::from([
+                (String::from("ups_shutdown"), Priority::Critical),
+                (String::from("graphite"), Priority::Bulk),
+            ])),
+        }
+    }
+}
+
+impl BandwidthConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_bytes_per_second(&self) -> u64 {
+        self.bytes_per_second.unwrap_or(125_000).max(1)
+    }
+
+    pub fn get_burst(&self) -> u64 {
+        self.burst
+            .unwrap_or_else(|| self.get_bytes_per_second())
+            .max(1)
+    }
+
+    pub fn get_priority(&self, sink: &str) -> Priority {
+        let configured = self
+            .priorities
+            .as_ref()
+            .and_then(|priorities| priorities.get(sink));
+        match (configured, sink) {
+            (Some(priority), _) => *priority,
+            (None, "ups_shutdown") => Priority::Critical,
+            (None, _) => Priority::Normal,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_priority() {
+        let config = BandwidthConfig::example();
+        assert_eq!(config.get_priority("ups_shutdown"), Priority::Critical);
+        assert_eq!(config.get_priority("graphite"), Priority::Bulk);
+        assert_eq!(config.get_priority("active_sender"), Priority::Normal);
+        assert_eq!(
+            BandwidthConfig::default().get_priority("ups_shutdown"),
+            Priority::Critical
+        );
+        assert_eq!(BandwidthConfig::default().get_burst(), 125_000);
+    }
+}
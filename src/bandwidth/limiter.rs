@@ -0,0 +1,262 @@
+// Licensed under the Open Software License version 3.0
+//! Token bucket shared by every sink that sends data out
+//!
+//! Sinks call `acquire` with the size of a request right before sending it. Waiting sinks are
+//! served by priority, so bulk transfers can't delay regular sends and critical ones never wait.
+use super::config::{BandwidthConfig, Priority};
+use crate::{clock::SharedClock, config::types::Config};
+use std::{
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+use tokio::sync::{broadcast, watch};
+
+// How often sinks blocked only by higher priority ones check again
+const RECHECK_INTERVAL: Duration = Duration::from_millis(10);
+const PRIORITIES: usize = 3;
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+    // Number of waiting sinks by priority
+    waiting: [usize; PRIORITIES],
+}
+
+#[derive(Debug)]
+pub struct Limiter {
+    config: BandwidthConfig,
+    clock: SharedClock,
+    bucket: Mutex<Bucket>,
+}
+
+/// Counted as waiting until dropped, so cancelled sends don't block lower priorities
+struct Waiting<'a> {
+    limiter: &'a Limiter,
+    priority: Priority,
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.limiter.with_bucket(|bucket, _| {
+            bucket.waiting[self.priority as usize] -= 1;
+        });
+    }
+}
+
+impl Limiter {
+    pub fn new(config: BandwidthConfig, clock: SharedClock) -> Self {
+        let bucket = Bucket {
+            tokens: config.get_burst() as f64,
+            refilled_at: clock.now(),
+            waiting: [0; PRIORITIES],
+        };
+        Self {
+            config,
+            clock,
+            bucket: Mutex::new(bucket),
+        }
+    }
+
+    /// Refill the bucket and run `f` on it
+    fn with_bucket<T>(&self, f: impl FnOnce(&mut Bucket, &BandwidthConfig) -> T) -> T {
+        let mut bucket = self
+            .bucket
+            .lock()
+            .unwrap_or_else(|error| error.into_inner());
+        let now = self.clock.now();
+        let elapsed = now.saturating_duration_since(bucket.refilled_at);
+        let refill = elapsed.as_secs_f64() * self.config.get_bytes_per_second() as f64;
+        bucket.tokens = (bucket.tokens + refill).min(self.config.get_burst() as f64);
+        bucket.refilled_at = now;
+        f(&mut bucket, &self.config)
+    }
+
+    /// `None` if `bytes` were taken, otherwise how long to wait before trying again
+    fn try_take(&self, priority: Priority, bytes: usize) -> Option<Duration> {
+        self.with_bucket(|bucket, config| {
+            let higher_waiting: usize = bucket.waiting[priority as usize + 1..].iter().sum();
+            // Requests larger than burst only wait for a full bucket
+            let needed = bytes.min(config.get_burst() as usize) as f64;
+            if priority == Priority::Critical || (higher_waiting == 0 && bucket.tokens >= needed) {
+                // Going below zero makes the following requests wait for the excess
+                bucket.tokens -= bytes as f64;
+                return None;
+            }
+            let deficit = (needed - bucket.tokens).max(0.0);
+            let wait = Duration::from_secs_f64(deficit / config.get_bytes_per_second() as f64);
+            Some(wait.max(RECHECK_INTERVAL))
+        })
+    }
+
+    /// Wait until `bytes` can be sent by `sink`
+    pub async fn acquire(&self, sink: &str, bytes: usize) {
+        let priority = self.config.get_priority(sink);
+        let mut waiting = None;
+        while let Some(wait) = self.try_take(priority, bytes) {
+            if waiting.is_none() {
+                self.with_bucket(|bucket, _| bucket.waiting[priority as usize] += 1);
+                waiting = Some(Waiting {
+                    limiter: self,
+                    priority,
+                });
+            }
+            self.clock.sleep(wait).await;
+        }
+    }
+}
+
+/// Limiter in use, `None` if limiting is disabled
+type Slot = RwLock<Option<Arc<Limiter>>>;
+
+// Sends already waiting finish with the limiter they started with
+static LIMITER: Slot = RwLock::new(None);
+
+fn current_in(slot: &Slot) -> Option<Arc<Limiter>> {
+    slot.read()
+        .unwrap_or_else(|error| error.into_inner())
+        .clone()
+}
+
+fn configure_in(slot: &Slot, config: &BandwidthConfig) {
+    let mut limiter = slot.write().unwrap_or_else(|error| error.into_inner());
+    if limiter.as_ref().map(|limiter| &limiter.config) == Some(config) {
+        return;
+    }
+    *limiter = config
+        .is_enabled()
+        .then(|| Arc::new(Limiter::new(config.clone(), SharedClock::default())));
+}
+
+/// Enable, replace or disable the shared limiter, an unchanged config keeps the current bucket
+pub fn configure(config: &BandwidthConfig) {
+    configure_in(&LIMITER, config);
+}
+
+/// Apply changes of `bandwidth` published by hot reload, sinks keep running
+pub async fn start_bandwidth_reload_loop(
+    mut shutdown_rx: broadcast::Receiver<()>,
+    mut config_rx: watch::Receiver<Config>,
+) {
+    loop {
+        tokio::select! {
+            _ = shutdown_rx.recv() => break,
+            changed = config_rx.changed() => {
+                // Hot reload is disabled
+                if changed.is_err() {
+                    break;
+                }
+                let config = config_rx.borrow_and_update().bandwidth.clone();
+                tracing::info!("Applying changed bandwidth limits");
+                configure(&config);
+            }
+        }
+    }
+}
+
+/// Wait until `bytes` can be sent by `sink`, returns immediately if limiting is disabled
+///
+/// `sink` is the module's name, the same as in `priorities`
+pub async fn acquire(sink: &str, bytes: usize) {
+    if let Some(limiter) = current_in(&LIMITER) {
+        limiter.acquire(sink, bytes).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use std::sync::Arc;
+
+    fn limiter(clock: &MockClock) -> Limiter {
+        let config: BandwidthConfig = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "bytes_per_second": 1000,
+            "burst": 1000,
+            "priorities": { "replay": "bulk" },
+        }))
+        .unwrap();
+        Limiter::new(config, clock.shared())
+    }
+
+    #[tokio::test]
+    async fn test_rate() {
+        let clock = MockClock::default();
+        let limiter = limiter(&clock);
+        let start = clock.shared().now();
+        // Burst is sent right away, the rest at 1000 bytes per second
+        limiter.acquire("active_sender", 1000).await;
+        limiter.acquire("active_sender", 500).await;
+        limiter.acquire("active_sender", 500).await;
+        let elapsed = clock.shared().now() - start;
+        assert!(
+            (Duration::from_millis(990)..Duration::from_millis(1100)).contains(&elapsed),
+            "{:?}",
+            elapsed
+        );
+        // Larger than burst waits for a full bucket and goes into debt
+        limiter.acquire("active_sender", 3000).await;
+        assert!(limiter.try_take(Priority::Normal, 1).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_critical_never_waits() {
+        let clock = MockClock::default();
+        let limiter = limiter(&clock);
+        limiter.acquire("active_sender", 1000).await;
+        let sleeps = clock.sleeps().len();
+        limiter.acquire("ups_shutdown", 1000).await;
+        assert_eq!(clock.sleeps().len(), sleeps);
+        // Others pay for it
+        assert!(limiter.try_take(Priority::Normal, 1000).unwrap() >= Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_bulk_waits_for_normal() {
+        let clock = MockClock::default();
+        let limiter = Arc::new(limiter(&clock));
+        limiter.acquire("replay", 1000).await;
+        let normal = {
+            let limiter = limiter.clone();
+            tokio::spawn(async move { limiter.acquire("active_sender", 1000).await })
+        };
+        // Let the normal sink register as waiting
+        while limiter.with_bucket(|bucket, _| bucket.waiting[Priority::Normal as usize]) == 0 {
+            tokio::task::yield_now().await;
+        }
+        clock.advance(Duration::from_secs(1));
+        // The bucket is full, but the normal sink goes first
+        assert!(limiter.try_take(Priority::Bulk, 1000).is_some());
+        normal.await.unwrap();
+        assert_eq!(
+            limiter.with_bucket(|bucket, _| bucket.waiting),
+            [0; PRIORITIES]
+        );
+    }
+
+    #[test]
+    fn test_configure() {
+        // Not the shared limiter, other tests send through it concurrently
+        let slot: Slot = RwLock::new(None);
+        let config: BandwidthConfig =
+            serde_json::from_value(serde_json::json!({"enabled": true})).unwrap();
+        configure_in(&slot, &config);
+        let limiter = current_in(&slot).unwrap();
+        // Unchanged config keeps the bucket
+        configure_in(&slot, &config);
+        assert!(Arc::ptr_eq(&limiter, &current_in(&slot).unwrap()));
+        let changed: BandwidthConfig = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "bytes_per_second": 1000,
+        }))
+        .unwrap();
+        configure_in(&slot, &changed);
+        assert_eq!(
+            current_in(&slot).unwrap().config.get_bytes_per_second(),
+            1000
+        );
+        configure_in(&slot, &BandwidthConfig::default());
+        assert!(current_in(&slot).is_none());
+    }
+}
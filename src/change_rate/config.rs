@@ -0,0 +1,47 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeRateConfig {
+    enabled: Option<bool>,
+    // Sliding window used to compute rate of change
+    window: Option<Duration>,
+    // Sensor ids to compute rate of change for, all sensors if empty
+    sensors: Option<Vec<String>>,
+}
+
+impl Default for ChangeRateConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            window: Some(Duration::from_secs(300)),
+            sensors: Some(Vec::new()),
+        }
+    }
+}
+
+impl Example for ChangeRateConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(true),
+            window: Some(Duration::from_secs(300)),
+            sensors: Some(Vec::new()),
+        }
+    }
+}
+
+impl ChangeRateConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_window(&self) -> Duration {
+        self.window.unwrap_or(Duration::from_secs(300))
+    }
+
+    pub fn get_sensors(&self) -> Vec<String> {
+        self.sensors.clone().unwrap_or_default()
+    }
+}
@@ -0,0 +1,180 @@
+// Licensed under the Open Software License version 3.0
+use super::config::ChangeRateConfig;
+use crate::{
+    hardware::{
+        reading::{Reading, ReadingsUpdate},
+        types::{HardwareMetadata, HardwareType, SourceType},
+    },
+    introspection,
+    one_wire::sender::MeasuredTemperature,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+use tokio::sync::broadcast;
+
+const PUBLISHER: &str = "change_rate";
+
+/// Computes rate of change per minute over a sliding window
+#[derive(Debug, Clone)]
+pub struct ChangeRateTracker {
+    window: Duration,
+    samples: HashMap<String, VecDeque<(Instant, f64)>>,
+}
+
+impl ChangeRateTracker {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            samples: HashMap::new(),
+        }
+    }
+
+    /// Add sample and return rate of change per minute, if window has at least 2 samples
+    pub fn update(&mut self, id: &str, now: Instant, value: f64) -> Option<f64> {
+        let samples = self.samples.entry(String::from(id)).or_default();
+        samples.push_back((now, value));
+        while let Some((time, _)) = samples.front() {
+            if now.duration_since(*time) <= self.window {
+                break;
+            }
+            samples.pop_front();
+        }
+        let (first_time, first_value) = samples.front()?;
+        let elapsed = now.duration_since(*first_time).as_secs_f64();
+        if elapsed <= 0.0 {
+            return None;
+        }
+        Some((value - first_value) / elapsed * 60.0)
+    }
+
+    /// Forget sensors that disappeared
+    pub fn retain(&mut self, ids: &[&str]) {
+        self.samples.retain(|id, _| ids.contains(&id.as_str()));
+    }
+}
+
+pub fn compute_change_rates(
+    tracker: &mut ChangeRateTracker,
+    sensors: &[MeasuredTemperature],
+    selected: &[String],
+    now: Instant,
+) -> Vec<Reading> {
+    let sensors: Vec<&MeasuredTemperature> = sensors
+        .iter()
+        .filter(|sensor| selected.is_empty() || selected.contains(&sensor.meta.hw.id))
+        .collect();
+    let ids: Vec<&str> = sensors
+        .iter()
+        .map(|sensor| sensor.meta.hw.id.as_str())
+        .collect();
+    tracker.retain(&ids);
+    sensors
+        .iter()
+        .filter_map(|sensor| {
+            let rate = tracker.update(&sensor.meta.hw.id, now, sensor.temperature?)?;
+            let mut meta = HardwareMetadata::new(
+                sensor.meta.hw.id.clone(),
+                HardwareType::TemperatureSensor,
+                SourceType::Derived,
+            );
+            meta.hw.name = sensor.meta.hw.name.clone();
+            Some(Reading::new(meta).with_value("celsius_per_minute", Some(rate)))
+        })
+        .collect()
+}
+
+pub async fn start_change_rate_loop(
+    mut shutdown_rx: broadcast::Receiver<()>,
+    config: ChangeRateConfig,
+    mut one_wire_rx: broadcast::Receiver<Vec<MeasuredTemperature>>,
+    tx: broadcast::Sender<ReadingsUpdate>,
+) {
+    // Check if module is enabled
+    if !config.is_enabled() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+    tracing::debug!("Starting change rate loop");
+    let selected = config.get_sensors();
+    let mut tracker = ChangeRateTracker::new(config.get_window());
+    let _task = introspection::task_started("change_rate");
+    loop {
+        tokio::select! {
+            Ok(sensors) = one_wire_rx.recv() => {
+                introspection::mark_iteration("change_rate");
+                let readings = compute_change_rates(&mut tracker, &sensors, &selected, Instant::now());
+                tracing::trace!("Sending {:?} to channel", readings);
+                if tx.receiver_count() > 0 {
+                    tx.send(ReadingsUpdate::new(PUBLISHER, readings)).unwrap();
+                    introspection::observe_channel("readings", &tx);
+                }
+            }
+            _ = shutdown_rx.recv() => {
+                tracing::trace!("Shutting down change rate loop");
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::types::Example;
+
+    #[test]
+    fn test_needs_two_samples() {
+        let mut tracker = ChangeRateTracker::new(Duration::from_secs(300));
+        assert_eq!(tracker.update("a", Instant::now(), 20.0), None);
+    }
+
+    #[test]
+    fn test_rate_over_window() {
+        let mut tracker = ChangeRateTracker::new(Duration::from_secs(120));
+        let start = Instant::now();
+        tracker.update("a", start, 20.0);
+        tracker.update("a", start + Duration::from_secs(60), 21.0);
+        assert_eq!(
+            tracker.update("a", start + Duration::from_secs(120), 22.0),
+            Some(1.0)
+        );
+        // Oldest sample falls out of the window
+        assert_eq!(
+            tracker.update("a", start + Duration::from_secs(180), 25.0),
+            Some(2.0)
+        );
+    }
+
+    #[test]
+    fn test_compute_change_rates_filters_sensors() {
+        let mut tracker = ChangeRateTracker::new(Duration::from_secs(300));
+        let start = Instant::now();
+        let sensors = vec![MeasuredTemperature::example()];
+        let selected = vec![String::from("other")];
+        compute_change_rates(&mut tracker, &sensors, &selected, start);
+        let readings = compute_change_rates(
+            &mut tracker,
+            &sensors,
+            &selected,
+            start + Duration::from_secs(60),
+        );
+        assert!(readings.is_empty());
+        let readings = compute_change_rates(
+            &mut tracker,
+            &sensors,
+            &[],
+            start + Duration::from_secs(120),
+        );
+        assert!(readings.is_empty());
+        let readings = compute_change_rates(
+            &mut tracker,
+            &sensors,
+            &[],
+            start + Duration::from_secs(180),
+        );
+        assert_eq!(readings[0].values["celsius_per_minute"], 0.0);
+        assert_eq!(readings[0].meta.source.source_type, SourceType::Derived);
+    }
+}
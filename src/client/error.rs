@@ -0,0 +1,63 @@
+// Licensed under the Open Software License version 3.0
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Connection error, timeout or a body that isn't valid JSON
+    Http(reqwest::Error),
+    /// Status other than 2xx, 404 and 503, with `error` of the response if it had one
+    Api {
+        status: u16,
+        error: Option<String>,
+    },
+    WebSocket(tokio_tungstenite::tungstenite::Error),
+    /// Live event that doesn't match `LiveEvent`
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Http(error) => write!(f, "request failed: {}", error),
+            Error::Api {
+                status,
+                error: Some(error),
+            } => write!(f, "server responded with {}: {}", status, error),
+            Error::Api {
+                status,
+                error: None,
+            } => write!(f, "server responded with {}", status),
+            Error::WebSocket(error) => write!(f, "WebSocket error: {}", error),
+            Error::Json(error) => write!(f, "invalid live event: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Http(error) => Some(error),
+            Error::Api { .. } => None,
+            Error::WebSocket(error) => Some(error),
+            Error::Json(error) => Some(error),
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(error: reqwest::Error) -> Self {
+        Error::Http(error)
+    }
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for Error {
+    fn from(error: tokio_tungstenite::tungstenite::Error) -> Self {
+        Error::WebSocket(error)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::Json(error)
+    }
+}
@@ -0,0 +1,242 @@
+// Licensed under the Open Software License version 3.0
+use super::{
+    error::Error,
+    live::LiveStream,
+    types::{ApiResponse, Changes, Reading, Temperature, Ups, VersionInfo},
+};
+use reqwest::{RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+
+/// Percent-encode a path segment, hw.ids of UPSes contain `[`, `]`, `@` and `:`
+fn encode_segment(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Client of a single passive endpoint, cheap to clone
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    // Without a trailing slash, ex. `http://localhost:63623`
+    base_url: String,
+    bearer_token: Option<String>,
+}
+
+impl Client {
+    pub fn new(base_url: &str) -> Self {
+        Self::with_http_client(reqwest::Client::new(), base_url)
+    }
+
+    /// Reuse a configured `reqwest::Client` (timeouts, proxies, root certificates)
+    pub fn with_http_client(http: reqwest::Client, base_url: &str) -> Self {
+        Self {
+            http,
+            base_url: String::from(base_url.trim_end_matches('/')),
+            bearer_token: None,
+        }
+    }
+
+    /// Sent with every request, ex. when the endpoint is behind an authenticating proxy
+    pub fn with_bearer_token(mut self, token: &str) -> Self {
+        self.bearer_token = Some(String::from(token));
+        self
+    }
+
+    fn request(&self, path: &str) -> RequestBuilder {
+        let request = self.http.get(format!("{}{}", self.base_url, path));
+        match &self.bearer_token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// `data` of the response, `None` for 404
+    ///
+    /// Data is returned with 503 too, every entry is marked with `stale` then.
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<Option<T>, Error> {
+        let response = self.request(path).send().await?;
+        let status = response.status();
+        match status {
+            StatusCode::NOT_FOUND => Ok(None),
+            StatusCode::SERVICE_UNAVAILABLE => Ok(response.json::<ApiResponse<T>>().await?.data),
+            _ if status.is_success() => Ok(response.json::<ApiResponse<T>>().await?.data),
+            _ => {
+                let error = response
+                    .json::<ApiResponse<serde_json::Value>>()
+                    .await
+                    .ok()
+                    .and_then(|response| response.error);
+                Err(Error::Api {
+                    status: status.as_u16(),
+                    error,
+                })
+            }
+        }
+    }
+
+    async fn get_list<T: DeserializeOwned>(&self, path: &str) -> Result<Vec<T>, Error> {
+        Ok(self.get(path).await?.unwrap_or_default())
+    }
+
+    pub async fn version(&self) -> Result<VersionInfo, Error> {
+        self.get("/version").await?.ok_or(Error::Api {
+            status: StatusCode::NOT_FOUND.as_u16(),
+            error: None,
+        })
+    }
+
+    pub async fn temperatures(&self) -> Result<Vec<Temperature>, Error> {
+        self.get_list("/temperature").await
+    }
+
+    pub async fn temperature(&self, id: &str) -> Result<Option<Temperature>, Error> {
+        self.get(&format!("/temperature/{}", encode_segment(id)))
+            .await
+    }
+
+    /// Sensor by its name from `names` in config of the server
+    pub async fn temperature_by_name(&self, name: &str) -> Result<Option<Temperature>, Error> {
+        self.get(&format!("/temperature/by-name/{}", encode_segment(name)))
+            .await
+    }
+
+    pub async fn upses(&self) -> Result<Vec<Ups>, Error> {
+        self.get_list("/ups").await
+    }
+
+    pub async fn ups(&self, id: &str) -> Result<Option<Ups>, Error> {
+        self.get(&format!("/ups/{}", encode_segment(id))).await
+    }
+
+    pub async fn readings(&self) -> Result<Vec<Reading>, Error> {
+        self.get_list("/readings").await
+    }
+
+    pub async fn reading(&self, id: &str) -> Result<Option<Reading>, Error> {
+        self.get(&format!("/readings/{}", encode_segment(id))).await
+    }
+
+    /// Everything updated after `since`, start with 0 and `None`, then pass the returned
+    /// `sequence` and `epoch` next time
+    pub async fn changes(&self, since: u64, epoch: Option<u64>) -> Result<Changes, Error> {
+        let path = match epoch {
+            Some(epoch) => format!("/changes?since={}&epoch={}", since, epoch),
+            None => format!("/changes?since={}", since),
+        };
+        self.get(&path).await?.ok_or(Error::Api {
+            status: StatusCode::NOT_FOUND.as_u16(),
+            error: None,
+        })
+    }
+
+    /// `true` if the server answers `/ping`
+    pub async fn ping(&self) -> Result<bool, Error> {
+        let response = self.request("/ping").send().await?;
+        Ok(response.status().is_success())
+    }
+
+    /// Connect to `/ws` and receive every temperature and UPS update
+    pub async fn subscribe(&self) -> Result<LiveStream, Error> {
+        let url = match self.base_url.strip_prefix("http") {
+            // https:// becomes wss://
+            Some(rest) => format!("ws{}/ws", rest),
+            None => format!("{}/ws", self.base_url),
+        };
+        LiveStream::connect(&url, self.bearer_token.as_deref()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Server;
+
+    fn golden_file(name: &str) -> String {
+        let path = format!("{}/tests/golden/{}.json", env!("CARGO_MANIFEST_DIR"), name);
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn test_encode_segment() {
+        assert_eq!(encode_segment("28-00000a0b0c0d"), "28-00000a0b0c0d");
+        assert_eq!(
+            encode_segment("[ups1]ups-monitor@localhost:3493"),
+            "%5Bups1%5Dups-monitor%40localhost%3A3493"
+        );
+        assert_eq!(encode_segment("Server room"), "Server%20room");
+    }
+
+    #[tokio::test]
+    async fn test_get() {
+        let mut server = Server::new_async().await;
+        let _temperature = server
+            .mock("GET", "/temperature")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(golden_file("temperature"))
+            .create_async()
+            .await;
+        let ups = server
+            .mock("GET", "/ups/%5Bups1%5Dups-monitor%40localhost%3A3493")
+            .match_header("Authorization", "Bearer token")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"success": true, "error": null, "data": {"meta": {"hw": {"id": "[ups1]ups-monitor@localhost:3493", "hardware_type": "UninterruptiblePowerSupply"}, "source": {"source_type": "NetworkUpsTools"}}, "variables": {}}}"#)
+            .create_async()
+            .await;
+        let _not_found = server
+            .mock("GET", "/readings/missing")
+            .with_status(404)
+            .with_header("content-type", "application/json")
+            .with_body(golden_file("not_found"))
+            .create_async()
+            .await;
+        let client = Client::new(&format!("{}/", server.url())).with_bearer_token("token");
+
+        let sensors = client.temperatures().await.unwrap();
+        assert_eq!(sensors[0].temperature, Some(21.5));
+        let ups = client
+            .ups("[ups1]ups-monitor@localhost:3493")
+            .await
+            .unwrap();
+        assert!(ups.is_some());
+        assert_eq!(client.reading("missing").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_stale_and_errors() {
+        let mut server = Server::new_async().await;
+        let _stale = server
+            .mock("GET", "/readings")
+            .with_status(503)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"success": false, "error": "all data is stale", "data": []}"#)
+            .create_async()
+            .await;
+        let _unauthorized = server
+            .mock("GET", "/ups")
+            .with_status(401)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"success": false, "error": "unauthorized", "data": null}"#)
+            .create_async()
+            .await;
+        let client = Client::new(&server.url());
+
+        assert_eq!(client.readings().await.unwrap(), vec![]);
+        match client.upses().await {
+            Err(Error::Api { status, error }) => {
+                assert_eq!(status, 401);
+                assert_eq!(error.as_deref(), Some("unauthorized"));
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,56 @@
+// Licensed under the Open Software License version 3.0
+use super::{error::Error, types::LiveEvent};
+use tokio::net::TcpStream;
+use tokio_stream::StreamExt;
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{
+        client::IntoClientRequest,
+        http::{header::AUTHORIZATION, HeaderValue},
+        Message,
+    },
+    MaybeTlsStream, WebSocketStream,
+};
+
+/// Updates pushed by `/ws`, created by `Client::subscribe`
+///
+/// Events missed by a slow consumer are skipped by the server, fetch `/changes` to catch up.
+pub struct LiveStream {
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl LiveStream {
+    pub(super) async fn connect(url: &str, bearer_token: Option<&str>) -> Result<Self, Error> {
+        let mut request = url.into_client_request()?;
+        if let Some(token) = bearer_token {
+            let value = HeaderValue::from_str(&format!("Bearer {}", token))
+                .map_err(tokio_tungstenite::tungstenite::Error::from)?;
+            request.headers_mut().insert(AUTHORIZATION, value);
+        }
+        let (stream, _) = connect_async(request).await?;
+        Ok(Self { stream })
+    }
+
+    /// Next event, `None` once the server closes the connection
+    pub async fn next(&mut self) -> Option<Result<LiveEvent, Error>> {
+        loop {
+            let message = match self.stream.next().await? {
+                Ok(message) => message,
+                Err(error) => return Some(Err(error.into())),
+            };
+            match message {
+                Message::Text(text) => {
+                    return Some(serde_json::from_str(&text).map_err(Error::from))
+                }
+                Message::Close(_) => return None,
+                // Pings are answered by tungstenite
+                _ => {}
+            }
+        }
+    }
+
+    pub async fn close(mut self) -> Result<(), Error> {
+        self.stream.close(None).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,22 @@
+// Licensed under the Open Software License version 3.0
+//! Typed client of the passive endpoint
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), universal_data_source::client::Error> {
+//! use universal_data_source::client::Client;
+//!
+//! let client = Client::new("http://localhost:63623");
+//! for sensor in client.temperatures().await? {
+//!     println!("{}: {:?}", sensor.meta.hw.id, sensor.temperature);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+mod error;
+mod http;
+mod live;
+pub mod types;
+
+pub use error::Error;
+pub use http::Client;
+pub use live::LiveStream;
@@ -0,0 +1,173 @@
+// Licensed under the Open Software License version 3.0
+//! Responses of the passive endpoint, pinned by the same golden files as the server
+//!
+//! Enum-like values (ex. `hardware_type`) are kept as strings, so new variants added by a newer
+//! server don't break older clients. Unknown fields are ignored for the same reason.
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Envelope of every JSON response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiResponse<T> {
+    pub success: bool,
+    pub error: Option<String>,
+    pub data: Option<T>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HardwareInfo {
+    pub id: String,
+    // ex. `TemperatureSensor` or `UninterruptiblePowerSupply`
+    pub hardware_type: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceInfo {
+    // ex. `OneWire` or `NetworkUpsTools`
+    pub source_type: String,
+}
+
+/// Link to another piece of hardware declared in config of the server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Relation {
+    pub id: String,
+    pub kind: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Meta {
+    pub hw: HardwareInfo,
+    pub source: SourceInfo,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub relations: Option<Vec<Relation>>,
+    // UTC time of the read (RFC 3339), missing for computed values
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub measured_at: Option<String>,
+    // Seconds since measured_at at the time of the request, missing in live events
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub age_secs: Option<u64>,
+    // Only set if the server has max_age for this category
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub stale: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Quality {
+    pub in_range: bool,
+    // Number of out-of-range readings since the server started
+    pub violations: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Temperature {
+    pub meta: Meta,
+    // °C, missing if the sensor couldn't be read
+    pub temperature: Option<f64>,
+    pub resolution: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub quality: Option<Quality>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ups {
+    pub meta: Meta,
+    // NUT variable names, ex. `ups.status` or `battery.charge`
+    pub variables: HashMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub clients: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reading {
+    pub meta: Meta,
+    pub values: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionInfo {
+    pub version: String,
+    pub instance_id: String,
+    // Compare with `SCHEMA_VERSION` to detect incompatible servers
+    pub schema_version: u32,
+}
+
+/// Everything updated after a given sequence number
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Changes {
+    /// Pass it as `since` on the next request
+    pub sequence: u64,
+    /// Startup time of the server (Unix ms), pass it as `epoch` on the next request
+    pub epoch: u64,
+    /// `since` is unknown to the server (ex. after a restart), replace the local copy
+    pub reset: bool,
+    pub temperature: Vec<Temperature>,
+    pub ups: Vec<Ups>,
+    pub readings: Vec<Reading>,
+    /// Removed entries as `<category>:<hw.id>`
+    pub removed: Vec<String>,
+}
+
+/// Message pushed by `/ws`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "lowercase")]
+pub enum LiveEvent {
+    Temperature(Vec<Temperature>),
+    Ups(Vec<Ups>),
+}
+
+/// Version of response shapes these types were written for
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::DeserializeOwned;
+    use serde_json::Value;
+
+    /// Types parse the golden file and serialize back to exactly the same JSON
+    fn assert_round_trips<T: Serialize + DeserializeOwned>(name: &str) {
+        let path = format!("{}/tests/golden/{}.json", env!("CARGO_MANIFEST_DIR"), name);
+        let golden: Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        let parsed: T = serde_json::from_value(golden.clone()).unwrap();
+        assert_eq!(serde_json::to_value(parsed).unwrap(), golden, "{}", name);
+    }
+
+    #[test]
+    fn test_golden_files() {
+        assert_round_trips::<ApiResponse<VersionInfo>>("version");
+        assert_round_trips::<ApiResponse<Vec<Temperature>>>("temperature");
+        assert_round_trips::<ApiResponse<Vec<Ups>>>("ups");
+        assert_round_trips::<ApiResponse<Vec<Reading>>>("readings");
+        assert_round_trips::<ApiResponse<Changes>>("changes");
+        assert_round_trips::<ApiResponse<VersionInfo>>("not_found");
+    }
+
+    #[test]
+    fn test_schema_version() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/version.json");
+        let version: ApiResponse<VersionInfo> =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(version.data.unwrap().schema_version, SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_live_event() {
+        let event: LiveEvent = serde_json::from_value(serde_json::json!({
+            "type": "ups",
+            "data": [{
+                "meta": {
+                    "hw": {"id": "ups1", "hardware_type": "UninterruptiblePowerSupply"},
+                    "source": {"source_type": "NetworkUpsTools"},
+                },
+                "variables": {"ups.status": "OB"},
+            }],
+        }))
+        .unwrap();
+        let LiveEvent::Ups(upses) = event else {
+            unreachable!();
+        };
+        assert_eq!(upses[0].variables["ups.status"], "OB");
+    }
+}
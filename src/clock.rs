@@ -0,0 +1,344 @@
+// Licensed under the Open Software License version 3.0
+//! Time source of cooldowns, backoff, staleness and history timestamps
+//!
+//! Production code uses the system clock. Tests use `MockClock`, which only moves when advanced
+//! and finishes every sleep instantly, so timing behavior can be asserted without waiting.
+//!
+//! The wall clock can jump, ex. when NTP corrects it after booting without an RTC. Cooldowns and
+//! deadlines use monotonic time, ages of measurements taken before a forward step are corrected
+//! with steps detected by `start_clock_monitor_loop`.
+use crate::introspection;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    fmt::Debug,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+use tokio::sync::broadcast;
+
+/// Smaller corrections are slewed by NTP, not stepped
+const STEP_THRESHOLD: Duration = Duration::from_secs(2);
+/// Shorter than `STEP_THRESHOLD`, so timestamps from before and after a step can't overlap
+const MONITOR_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_FORWARD_STEPS: usize = 16;
+
+pub type Sleep<'a> = Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
+
+pub trait Clock: Send + Sync {
+    /// Monotonic time for cooldowns and backoff
+    fn now(&self) -> Instant;
+    /// Wall clock time for timestamps and staleness
+    fn utc_now(&self) -> DateTime<Utc>;
+    fn sleep(&self, duration: Duration) -> Sleep<'_>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn utc_now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep<'_> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// Cheap to clone handle, the system clock by default
+#[derive(Clone)]
+pub struct SharedClock(Arc<dyn Clock>);
+
+impl SharedClock {
+    pub fn new(clock: impl Clock + 'static) -> Self {
+        Self(Arc::new(clock))
+    }
+
+    pub fn now(&self) -> Instant {
+        self.0.now()
+    }
+
+    pub fn utc_now(&self) -> DateTime<Utc> {
+        self.0.utc_now()
+    }
+
+    pub async fn sleep(&self, duration: Duration) {
+        self.0.sleep(duration).await
+    }
+}
+
+impl Default for SharedClock {
+    fn default() -> Self {
+        Self::new(SystemClock)
+    }
+}
+
+impl Debug for SharedClock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SharedClock").finish()
+    }
+}
+
+/// Wall clock jump, shown in `/status/internal`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClockStep {
+    /// Wall time right after the step
+    pub detected_at: String,
+    /// Negative if the clock went backwards
+    pub offset_ms: i64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClockStatus {
+    /// Steps are only detected while the monitor is running
+    pub monitored: bool,
+    pub steps: u64,
+    pub last_step: Option<ClockStep>,
+}
+
+/// Compares the wall clock against monotonic time between observations
+#[derive(Debug, Default)]
+pub struct StepDetector {
+    last: Option<(Instant, DateTime<Utc>)>,
+    // Wall time right before each forward step and its size, oldest first
+    forward_steps: VecDeque<(DateTime<Utc>, chrono::Duration)>,
+    status: ClockStatus,
+}
+
+impl StepDetector {
+    /// Offset of the wall clock since the previous observation, if it was stepped
+    pub fn observe(&mut self, now: Instant, utc_now: DateTime<Utc>) -> Option<chrono::Duration> {
+        let (last, last_utc) = self.last.replace((now, utc_now))?;
+        let elapsed = chrono::Duration::from_std(now.saturating_duration_since(last)).ok()?;
+        let expected = last_utc + elapsed;
+        let offset = utc_now - expected;
+        if offset.num_milliseconds().unsigned_abs() < STEP_THRESHOLD.as_millis() as u64 {
+            return None;
+        }
+        self.status.steps += 1;
+        self.status.last_step = Some(ClockStep {
+            detected_at: utc_now.to_rfc3339(),
+            offset_ms: offset.num_milliseconds(),
+        });
+        if offset > chrono::Duration::zero() {
+            self.forward_steps.push_back((expected, offset));
+            if self.forward_steps.len() > MAX_FORWARD_STEPS {
+                self.forward_steps.pop_front();
+            }
+        }
+        Some(offset)
+    }
+
+    /// Move `timestamp` taken before forward steps to the current timeline
+    ///
+    /// Backward steps are ignored, timestamps from before them look newer than they are and
+    /// ages are clamped to zero anyway. Telling them apart from newer ones isn't possible.
+    pub fn rebase(&self, timestamp: DateTime<Utc>) -> DateTime<Utc> {
+        self.forward_steps
+            .iter()
+            .fold(timestamp, |timestamp, (before, offset)| {
+                if timestamp <= *before {
+                    timestamp + *offset
+                } else {
+                    timestamp
+                }
+            })
+    }
+}
+
+fn with_steps<T>(f: impl FnOnce(&mut StepDetector) -> T) -> T {
+    static STEPS: OnceLock<Mutex<StepDetector>> = OnceLock::new();
+    let mut steps = STEPS
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(|error| error.into_inner());
+    f(&mut steps)
+}
+
+/// `timestamp` shifted by forward steps of the wall clock detected after it
+pub fn rebase_on_steps(timestamp: DateTime<Utc>) -> DateTime<Utc> {
+    with_steps(|steps| steps.rebase(timestamp))
+}
+
+pub fn status() -> ClockStatus {
+    with_steps(|steps| steps.status.clone())
+}
+
+/// Detect steps of the wall clock until shutdown
+pub async fn start_clock_monitor_loop(
+    mut shutdown_rx: broadcast::Receiver<()>,
+    clock: SharedClock,
+) {
+    tracing::debug!("Starting clock monitor loop");
+    let _task = introspection::task_started("clock");
+    with_steps(|steps| steps.status.monitored = true);
+    loop {
+        introspection::mark_iteration("clock");
+        let (now, utc_now) = (clock.now(), clock.utc_now());
+        if let Some(offset) = with_steps(|steps| steps.observe(now, utc_now)) {
+            tracing::warn!(
+                "System clock stepped by {:.3}s, now {}",
+                offset.num_milliseconds() as f64 / 1000.0,
+                utc_now.to_rfc3339()
+            );
+        }
+        tokio::select! {
+            _ = shutdown_rx.recv() => {
+                tracing::trace!("Shutting down clock monitor loop");
+                break;
+            }
+            _ = clock.sleep(MONITOR_INTERVAL) => {}
+        }
+    }
+    with_steps(|steps| steps.status.monitored = false);
+}
+
+#[cfg(test)]
+pub use mock::MockClock;
+
+#[cfg(test)]
+mod mock {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Debug)]
+    struct MockState {
+        elapsed: Duration,
+        sleeps: Vec<Duration>,
+    }
+
+    /// Clock that starts at `utc_start` and moves only when advanced or slept on
+    #[derive(Debug, Clone)]
+    pub struct MockClock {
+        start: Instant,
+        utc_start: DateTime<Utc>,
+        state: Arc<Mutex<MockState>>,
+    }
+
+    impl MockClock {
+        pub fn new(utc_start: DateTime<Utc>) -> Self {
+            Self {
+                start: Instant::now(),
+                utc_start,
+                state: Arc::new(Mutex::new(MockState {
+                    elapsed: Duration::ZERO,
+                    sleeps: Vec::new(),
+                })),
+            }
+        }
+
+        pub fn advance(&self, duration: Duration) {
+            self.state.lock().unwrap().elapsed += duration;
+        }
+
+        /// Durations of every `sleep` call so far
+        pub fn sleeps(&self) -> Vec<Duration> {
+            self.state.lock().unwrap().sleeps.clone()
+        }
+
+        pub fn shared(&self) -> SharedClock {
+            SharedClock::new(self.clone())
+        }
+    }
+
+    impl Default for MockClock {
+        fn default() -> Self {
+            Self::new(
+                DateTime::parse_from_rfc3339("2023-01-01T00:00:00Z")
+                    .unwrap()
+                    .with_timezone(&Utc),
+            )
+        }
+    }
+
+    impl Clock for MockClock {
+        fn now(&self) -> Instant {
+            self.start + self.state.lock().unwrap().elapsed
+        }
+
+        fn utc_now(&self) -> DateTime<Utc> {
+            let elapsed = self.state.lock().unwrap().elapsed;
+            self.utc_start + chrono::Duration::from_std(elapsed).unwrap()
+        }
+
+        fn sleep(&self, duration: Duration) -> Sleep<'_> {
+            {
+                let mut state = self.state.lock().unwrap();
+                state.elapsed += duration;
+                state.sleeps.push(duration);
+            }
+            // Still yield, so loops that only sleep don't starve other tasks
+            Box::pin(tokio::task::yield_now())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mock_clock() {
+        let mock = MockClock::default();
+        let clock = mock.shared();
+        let (start, utc_start) = (clock.now(), clock.utc_now());
+        clock.sleep(Duration::from_secs(5)).await;
+        mock.advance(Duration::from_secs(1));
+        assert_eq!(clock.now() - start, Duration::from_secs(6));
+        assert_eq!(clock.utc_now() - utc_start, chrono::Duration::seconds(6));
+        assert_eq!(mock.sleeps(), vec![Duration::from_secs(5)]);
+    }
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2023-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+            + chrono::Duration::seconds(seconds)
+    }
+
+    #[test]
+    fn test_forward_step() {
+        let start = Instant::now();
+        let mut steps = StepDetector::default();
+        assert_eq!(steps.observe(start, at(0)), None);
+        // Slewing isn't a step
+        let slewed = at(1) + chrono::Duration::milliseconds(500);
+        assert_eq!(steps.observe(start + Duration::from_secs(1), slewed), None);
+        // NTP correction by 2 hours
+        let stepped = at(2 + 2 * 60 * 60);
+        let offset = steps.observe(start + Duration::from_secs(2), stepped);
+        assert_eq!(
+            offset,
+            Some(chrono::Duration::seconds(2 * 60 * 60) - chrono::Duration::milliseconds(500))
+        );
+        assert_eq!(steps.status.steps, 1);
+        assert_eq!(
+            steps.status.last_step.as_ref().unwrap().offset_ms,
+            2 * 60 * 60 * 1000 - 500
+        );
+        // Measured before the step, so only seconds old
+        let rebased = steps.rebase(at(1));
+        assert_eq!(stepped - rebased, chrono::Duration::milliseconds(1500));
+        // Measured after the step
+        assert_eq!(steps.rebase(stepped), stepped);
+    }
+
+    #[test]
+    fn test_backward_step() {
+        let start = Instant::now();
+        let mut steps = StepDetector::default();
+        steps.observe(start, at(60 * 60));
+        let offset = steps.observe(start + Duration::from_secs(1), at(1));
+        assert_eq!(offset, Some(chrono::Duration::seconds(-60 * 60)));
+        assert_eq!(steps.status.last_step.unwrap().offset_ms, -60 * 60 * 1000);
+        assert_eq!(steps.rebase(at(60 * 60)), at(60 * 60));
+    }
+}
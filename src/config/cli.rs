@@ -0,0 +1,256 @@
+// Licensed under the Open Software License version 3.0
+//! `universal-data-source print-example [--module <name>]`
+//!
+//! `universal-data-source config add-nut-server --host <host> --ups <name> [--ups <name>...]
+//! [--port <port>] [--username <username>] [--password <password>] [--variables <a,b,...>]`
+//!
+//! Print example config of a single module or modify the config file without hand-merging
+use super::{
+    file::{get_config_file_path, to_pretty_json},
+    types::{Config, Example},
+};
+use crate::nut::config::{NetworkUpsToolsClientConfig, UninterruptiblePowerSupplyConfig};
+use serde_json::{Map, Value};
+use std::{error::Error, fs, io::Write, path::Path};
+
+const ADD_NUT_SERVER_USAGE: &str = "usage: universal-data-source config add-nut-server --host <host> --ups <name> [--ups <name>...] [--port <port>] [--username <username>] [--password <password>] [--variables <a,b,...>]";
+
+pub fn is_config_command(args: &[String]) -> bool {
+    matches!(
+        args.get(1).map(String::as_str),
+        Some("print-example") | Some("config")
+    )
+}
+
+/// Values of every occurrence of `flag`
+fn flag_values<'a>(args: &'a [String], flag: &str) -> Vec<&'a str> {
+    args.windows(2)
+        .filter(|pair| pair[0] == flag)
+        .map(|pair| pair[1].as_str())
+        .collect()
+}
+
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    flag_values(args, flag).last().copied()
+}
+
+/// Top-level config key of a module, accepting feature names (ex. `nut`, `one-wire`)
+fn module_key(module: &str) -> String {
+    match module.replace('-', "_").as_str() {
+        "nut" => String::from("ups_monitoring"),
+        "active_sender" => String::from("active_data_sender"),
+        "passive_endpoint" => String::from("passive_data_endpoint"),
+        key => String::from(key),
+    }
+}
+
+/// Example config reduced to a single module, ready to paste into the config file
+fn example_block(module: &str) -> Result<Value, String> {
+    let example = match serde_json::to_value(Config::example()).unwrap() {
+        Value::Object(example) => example,
+        _ => unreachable!("Config is serialized as an object"),
+    };
+    let key = module_key(module);
+    match example.get(&key) {
+        Some(block) => Ok(Value::Object(Map::from_iter([(key, block.clone())]))),
+        None => Err(format!(
+            "unknown module {}, available modules: {}",
+            module,
+            example.keys().cloned().collect::<Vec<_>>().join(", ")
+        )),
+    }
+}
+
+/// Append `server` to `ups_monitoring.servers`, keeping everything else untouched
+fn add_nut_server(config: &mut Value, server: NetworkUpsToolsClientConfig) -> Result<(), String> {
+    let ups_monitoring = config
+        .as_object_mut()
+        .ok_or("config isn't a JSON object")?
+        .entry("ups_monitoring")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or("ups_monitoring isn't a JSON object")?;
+    let servers = ups_monitoring
+        .entry("servers")
+        .or_insert_with(|| Value::Array(Vec::new()));
+    if servers.is_null() {
+        *servers = Value::Array(Vec::new());
+    }
+    let servers = servers
+        .as_array_mut()
+        .ok_or("ups_monitoring.servers isn't a JSON array")?;
+    let server_id = server.get_server_id();
+    let duplicate = servers.iter().any(|existing| {
+        serde_json::from_value::<NetworkUpsToolsClientConfig>(existing.clone())
+            .map_or(false, |existing| existing.get_server_id() == server_id)
+    });
+    if duplicate {
+        return Err(format!("{} is already configured", server_id));
+    }
+    servers.push(serde_json::to_value(server).unwrap());
+    // Never write a config that wouldn't be read back
+    serde_json::from_value::<Config>(config.clone())
+        .map_err(|error| format!("modified config is invalid: {}", error))?;
+    Ok(())
+}
+
+/// Keep a backup and replace the config file only after the new one is fully written
+/// The new file keeps permissions of the previous one, as it may contain passwords and tokens
+fn write_config(path: &Path, config: &Value) -> Result<(), Box<dyn Error>> {
+    let backup_path = format!("{}.bak", path.display());
+    let temporary_path = format!("{}.tmp", path.display());
+    fs::copy(path, &backup_path)?;
+    let mut temporary = fs::File::create(&temporary_path)?;
+    // Before writing, so secrets are never readable by others
+    temporary.set_permissions(fs::metadata(path)?.permissions())?;
+    temporary.write_all(to_pretty_json(config).as_bytes())?;
+    drop(temporary);
+    fs::rename(&temporary_path, path)?;
+    tracing::info!("Previous config saved to {}", backup_path);
+    Ok(())
+}
+
+fn run_add_nut_server_command(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let host = flag_value(args, "--host").ok_or(ADD_NUT_SERVER_USAGE)?;
+    let ups_names = flag_values(args, "--ups");
+    if ups_names.is_empty() {
+        return Err(ADD_NUT_SERVER_USAGE.into());
+    }
+    let port = flag_value(args, "--port")
+        .map(str::parse::<u16>)
+        .transpose()
+        .map_err(|error| format!("invalid port: {}", error))?;
+    // Same variables as in the example unless specified
+    let variables_to_monitor = match flag_value(args, "--variables") {
+        Some(variables) => Some(variables.split(',').map(String::from).collect()),
+        None => NetworkUpsToolsClientConfig::example()
+            .get_ups_configs()
+            .first()
+            .and_then(|ups| ups.variables_to_monitor.clone()),
+    };
+    let upses = ups_names
+        .into_iter()
+        .map(|name| UninterruptiblePowerSupplyConfig {
+            name: String::from(name),
+            variables_to_monitor: variables_to_monitor.clone(),
+            list_clients: None,
+        })
+        .collect();
+    let server = NetworkUpsToolsClientConfig::new(
+        String::from(host),
+        port,
+        flag_value(args, "--username").map(String::from),
+        flag_value(args, "--password").map(String::from),
+        upses,
+    );
+    let server_id = server.get_server_id();
+
+    let path = get_config_file_path();
+    let mut config: Value = serde_json::from_str(&fs::read_to_string(&path)?)?;
+    add_nut_server(&mut config, server)?;
+    write_config(&path, &config)?;
+    tracing::info!("Added {} to {}", server_id, path.display());
+    let config: Config = serde_json::from_value(config)?;
+    if !config.ups_monitoring.is_enabled() {
+        tracing::warn!("ups_monitoring is disabled, set enabled to true to start monitoring");
+    }
+    Ok(())
+}
+
+pub fn run_config_command(args: &[String]) -> Result<(), Box<dyn Error>> {
+    match (
+        args.get(1).map(String::as_str),
+        args.get(2).map(String::as_str),
+    ) {
+        (Some("print-example"), _) => {
+            let example = match flag_value(args, "--module") {
+                Some(module) => example_block(module)?,
+                None => serde_json::to_value(Config::example())?,
+            };
+            println!("{}", to_pretty_json(&example));
+            Ok(())
+        }
+        (Some("config"), Some("add-nut-server")) => run_add_nut_server_command(args),
+        _ => Err(ADD_NUT_SERVER_USAGE.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example_block() {
+        let block = example_block("nut").unwrap();
+        let block = block.as_object().unwrap();
+        assert_eq!(block.len(), 1);
+        assert!(block["ups_monitoring"]["servers"].is_array());
+        assert!(example_block("one-wire").unwrap().get("one_wire").is_some());
+        assert!(example_block("nonexistent").is_err());
+    }
+
+    #[test]
+    fn test_add_nut_server() {
+        let mut config = serde_json::to_value(Config::example()).unwrap();
+        // Unknown keys of a customized config are kept
+        config["comment"] = Value::from("kept");
+        let server = NetworkUpsToolsClientConfig::new(
+            String::from("nas.local"),
+            None,
+            None,
+            None,
+            vec![UninterruptiblePowerSupplyConfig {
+                name: String::from("ups2"),
+                variables_to_monitor: None,
+                list_clients: None,
+            }],
+        );
+        add_nut_server(&mut config, server.clone()).unwrap();
+        assert_eq!(config["comment"], "kept");
+        let parsed: Config = serde_json::from_value(config.clone()).unwrap();
+        let servers = parsed.ups_monitoring.get_server_configs();
+        assert_eq!(servers.len(), 2);
+        assert_eq!(servers[1], server);
+        assert!(add_nut_server(&mut config, server).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_config_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("config.json");
+        fs::write(&path, "{}").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        write_config(&path, &serde_json::json!({"read_only": true})).unwrap();
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+        let written: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written["read_only"], true);
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("config.json.bak")).unwrap(),
+            "{}"
+        );
+    }
+
+    #[test]
+    fn test_flag_values() {
+        let args: Vec<String> = [
+            "uds",
+            "config",
+            "add-nut-server",
+            "--ups",
+            "a",
+            "--ups",
+            "b",
+        ]
+        .iter()
+        .map(|arg| String::from(*arg))
+        .collect();
+        assert!(is_config_command(&args));
+        assert_eq!(flag_values(&args, "--ups"), vec!["a", "b"]);
+        assert_eq!(flag_value(&args, "--host"), None);
+    }
+}
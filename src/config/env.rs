@@ -0,0 +1,211 @@
+// Licensed under the Open Software License version 3.0
+//! Overrides of config keys from environment variables, merged over the config file
+//!
+//! `UDS_RS__ONE_WIRE__COOLDOWN=2s` sets `one_wire.cooldown`, numeric segments are array indexes
+//! (`UDS_RS__ACTIVE_DATA_SENDER__ENDPOINTS__0__URL`). Values are parsed as JSON and fall back
+//! to a plain string. Durations also accept `500ms`, `2s`, `5m` and `1h`.
+use super::types::{Config, Example};
+use serde_json::{Map, Value};
+use std::time::Duration;
+
+const PREFIX: &str = "UDS_RS__";
+const SEPARATOR: &str = "__";
+
+/// `500ms`, `2s`, `5m`, `1h` or a plain number of seconds
+fn parse_duration(raw: &str) -> Option<Duration> {
+    let raw = raw.trim();
+    let split = raw
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(raw.len());
+    let (number, unit) = raw.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let seconds = match unit.trim() {
+        "ms" => number / 1000.0,
+        "" | "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => return None,
+    };
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// Durations are serialized as `{"secs": 1, "nanos": 0}`
+fn is_duration(example: &Value) -> bool {
+    example.get("secs").is_some() && example.get("nanos").is_some()
+}
+
+/// Parse `raw` as the type of `example`, the value of the same key in the example config
+fn parse_value(raw: &str, example: Option<&Value>) -> Value {
+    match example {
+        Some(Value::String(_)) => return Value::String(String::from(raw)),
+        Some(example) if is_duration(example) => {
+            if let Some(duration) = parse_duration(raw) {
+                return serde_json::to_value(duration).unwrap();
+            }
+        }
+        _ => {}
+    }
+    serde_json::from_str(raw).unwrap_or_else(|_| Value::String(String::from(raw)))
+}
+
+/// Element of an example array that has the next key, endpoints don't all set the same keys
+fn example_item<'a>(example: Option<&'a Value>, next: Option<&String>) -> Option<&'a Value> {
+    let items = example?.as_array()?;
+    items
+        .iter()
+        .find(|item| next.map_or(true, |key| item.get(key.as_str()).is_some()))
+        .or(items.first())
+}
+
+fn set(
+    target: &mut Value,
+    example: Option<&Value>,
+    path: &[String],
+    raw: &str,
+) -> Result<(), String> {
+    let (segment, rest) = match path.split_first() {
+        Some(split) => split,
+        None => {
+            *target = parse_value(raw, example);
+            return Ok(());
+        }
+    };
+    if target.is_null() && example.map_or(false, Value::is_array) {
+        *target = Value::Array(Vec::new());
+    }
+    match target {
+        Value::Array(items) => {
+            let index: usize = segment
+                .parse()
+                .map_err(|_| format!("{} isn't an array index", segment))?;
+            if index > items.len() {
+                return Err(format!(
+                    "index {} is past the end of an array with {} items",
+                    index,
+                    items.len()
+                ));
+            }
+            if index == items.len() {
+                items.push(Value::Null);
+            }
+            let example = example_item(example, rest.first());
+            set(&mut items[index], example, rest, raw)
+        }
+        _ => {
+            if !target.is_object() {
+                *target = Value::Object(Map::new());
+            }
+            let example = example.and_then(|example| example.get(segment.as_str()));
+            let object = target.as_object_mut().unwrap();
+            let value = object.entry(segment.clone()).or_insert(Value::Null);
+            set(value, example, rest, raw)
+        }
+    }
+}
+
+/// Apply variables with the prefix in order of their keys, returns names of applied variables
+fn apply_overrides(
+    config: &mut Value,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> Vec<String> {
+    let example = serde_json::to_value(Config::example()).unwrap();
+    let mut vars: Vec<(String, String)> = vars
+        .into_iter()
+        .filter(|(name, _)| name.starts_with(PREFIX))
+        .collect();
+    // Indexes are compared as numbers, so `__10` is appended after `__9`
+    vars.sort_by_cached_key(|(name, _)| {
+        name.split(SEPARATOR)
+            .map(|segment| (segment.parse::<usize>().ok(), String::from(segment)))
+            .collect::<Vec<_>>()
+    });
+    let mut applied = Vec::new();
+    for (name, raw) in vars {
+        let path: Vec<String> = name[PREFIX.len()..]
+            .split(SEPARATOR)
+            .map(str::to_lowercase)
+            .collect();
+        if path.iter().any(String::is_empty) {
+            tracing::warn!("Ignoring {}, it has an empty key", name);
+            continue;
+        }
+        match set(config, Some(&example), &path, &raw) {
+            Ok(()) => applied.push(name),
+            Err(error) => tracing::warn!("Ignoring {}: {}", name, error),
+        }
+    }
+    applied
+}
+
+/// Config file with overrides from the environment, unchanged if it isn't a JSON object
+pub fn with_env_overrides(json: &str) -> String {
+    let mut config = match serde_json::from_str::<Value>(json) {
+        Ok(config) if config.is_object() => config,
+        _ => return String::from(json),
+    };
+    let applied = apply_overrides(&mut config, std::env::vars());
+    if applied.is_empty() {
+        return String::from(json);
+    }
+    // Only names, values may be secrets
+    tracing::info!("Config overridden by {}", applied.join(", "));
+    config.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(name, value)| (String::from(*name), String::from(*value)))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration("2s"), Some(Duration::from_secs(2)));
+        assert_eq!(parse_duration("1.5"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("5m"), Some(Duration::from_secs(300)));
+        assert_eq!(parse_duration("1h"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_duration("2 days"), None);
+        assert_eq!(parse_duration("-1s"), None);
+    }
+
+    #[test]
+    fn test_apply_overrides() {
+        let mut config = json!({
+            "one_wire": {"enabled": false},
+            "active_data_sender": {"endpoints": [{"url": "http://a"}]},
+        });
+        let applied = apply_overrides(
+            &mut config,
+            vars(&[
+                ("UDS_RS__ONE_WIRE__COOLDOWN", "2s"),
+                ("UDS_RS__ONE_WIRE__ENABLED", "true"),
+                ("UDS_RS__ACTIVE_DATA_SENDER__ENDPOINTS__0__URL", "http://b"),
+                ("UDS_RS__ACTIVE_DATA_SENDER__ENDPOINTS__1__URL", "http://c"),
+                ("UDS_RS__ACTIVE_DATA_SENDER__ENDPOINTS__5__URL", "http://d"),
+                ("UDS_RS__UPS_MONITORING__SERVERS__0__PASSWORD", "1234"),
+                ("UDS_RS_CONFIG_FILE", "config.json"),
+            ]),
+        );
+        assert_eq!(applied.len(), 5);
+        assert_eq!(
+            config["one_wire"]["cooldown"],
+            json!({"secs": 2, "nanos": 0})
+        );
+        assert_eq!(config["one_wire"]["enabled"], json!(true));
+        assert_eq!(
+            config["active_data_sender"]["endpoints"],
+            json!([{"url": "http://b"}, {"url": "http://c"}])
+        );
+        // Strings stay strings, even if they look like numbers
+        assert_eq!(
+            config["ups_monitoring"]["servers"][0]["password"],
+            json!("1234")
+        );
+    }
+}
@@ -1,5 +1,9 @@
 // Licensed under the Open Software License version 3.0
-use super::types::{Config, Example};
+use super::{
+    env::with_env_overrides,
+    types::{Config, Example},
+};
+use crate::degraded_mode::{parse::parse_partial_config, watcher::report_invalid_sections};
 use serde::Serialize;
 use serde_json::{ser::PrettyFormatter, Serializer};
 use std::{
@@ -8,16 +12,21 @@ use std::{
     process,
 };
 
-fn write_default_config_to_file(path: &PathBuf) -> bool {
-    // Create default config
-    let config = Config::example();
+/// Serialize to json with the same formatting as the default config file
+pub fn to_pretty_json<T: Serialize>(value: &T) -> String {
     // Use 4 spaces for indentation
     let formatter = PrettyFormatter::with_indent(b"    ");
-    // Serialize config to pretty json
     let mut buffer = Vec::new();
     let mut serializer = Serializer::with_formatter(&mut buffer, formatter);
-    config.serialize(&mut serializer).unwrap();
-    let json = String::from_utf8(buffer).unwrap();
+    value.serialize(&mut serializer).unwrap();
+    String::from_utf8(buffer).unwrap()
+}
+
+fn write_default_config_to_file(path: &PathBuf) -> bool {
+    // Create default config
+    let config = Config::example();
+    // Serialize config to pretty json
+    let json = to_pretty_json(&config);
     // Write config to file and return result
     fs::write(path, json).is_ok()
 }
@@ -36,21 +45,25 @@ fn create_default_config_if_not_exists(path: &PathBuf) -> bool {
 
 fn read_config(path: &PathBuf) -> Result<Config, Box<dyn std::error::Error>> {
     // Try to read config file and pass error if failed
-    let config_file = fs::read_to_string(path)?;
+    let config_file = with_env_overrides(&fs::read_to_string(path)?);
     // Try to parse config file and pass error if failed
     let config: Config = serde_json::from_str(&config_file)?;
     // Return config
     Ok(config)
 }
 
-pub fn read_config_or_create_default() -> Config {
+pub fn get_config_file_path() -> PathBuf {
     // Get path to config file from "UDS_RS_CONFIG_FILE" env var
     // If not set, use "config.json" in current directory
     tracing::trace!("Determining config file path");
-    let config_file_path = std::env::var("UDS_RS_CONFIG_FILE")
+    std::env::var("UDS_RS_CONFIG_FILE")
         .ok()
         .map(PathBuf::from)
-        .unwrap_or_else(|| PathBuf::from("config.json"));
+        .unwrap_or_else(|| PathBuf::from("config.json"))
+}
+
+pub fn read_config_or_create_default() -> Config {
+    let config_file_path = get_config_file_path();
     tracing::debug!("Reading config from: {}", config_file_path.display());
     // Read config from file
     // Exit on failure
@@ -58,6 +71,14 @@ pub fn read_config_or_create_default() -> Config {
         Ok(config) => config,
         Err(error) => {
             tracing::error!("Failed to read config: {}", error);
+            // Start modules with valid sections if degraded mode is enabled
+            let partial = fs::read_to_string(&config_file_path)
+                .ok()
+                .and_then(|json| parse_partial_config(&with_env_overrides(&json)));
+            if let Some(partial) = partial {
+                report_invalid_sections(&partial.invalid_sections);
+                return partial.config;
+            }
             // Write default config to file
             if create_default_config_if_not_exists(&config_file_path) {
                 tracing::error!(
@@ -0,0 +1,62 @@
+// Licensed under the Open Software License version 3.0
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use uuid::Uuid;
+
+const INSTANCE_ID_FILE_NAME: &str = "instance_id";
+
+/// Instance id is stored next to the config file
+fn get_instance_id_file_path(config_file_path: &Path) -> PathBuf {
+    config_file_path
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join(INSTANCE_ID_FILE_NAME)
+}
+
+/// Read instance id or generate a new one on first run
+///
+/// Falls back to a temporary id if it can't be persisted
+pub fn read_or_create_instance_id(config_file_path: &Path) -> String {
+    let path = get_instance_id_file_path(config_file_path);
+    if let Ok(contents) = fs::read_to_string(&path) {
+        if let Ok(instance_id) = Uuid::parse_str(contents.trim()) {
+            return instance_id.to_string();
+        }
+        tracing::warn!("Invalid instance id in {}, replacing it", path.display());
+    }
+    let instance_id = Uuid::new_v4().to_string();
+    match fs::write(&path, &instance_id) {
+        Ok(_) => tracing::info!("Generated instance id {}", instance_id),
+        Err(error) => tracing::error!(
+            "Failed to save instance id to {}: {}. It will change after restart",
+            path.display(),
+            error
+        ),
+    }
+    instance_id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instance_id_is_persisted() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_file_path = temp_dir.path().join("config.json");
+        let instance_id = read_or_create_instance_id(&config_file_path);
+        assert!(temp_dir.path().join(INSTANCE_ID_FILE_NAME).is_file());
+        assert_eq!(read_or_create_instance_id(&config_file_path), instance_id);
+    }
+
+    #[test]
+    fn test_invalid_instance_id_is_replaced() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_file_path = temp_dir.path().join("config.json");
+        fs::write(temp_dir.path().join(INSTANCE_ID_FILE_NAME), "not-a-uuid").unwrap();
+        let instance_id = read_or_create_instance_id(&config_file_path);
+        assert!(Uuid::parse_str(&instance_id).is_ok());
+    }
+}
@@ -1,3 +1,7 @@
 // Licensed under the Open Software License version 3.0
+pub mod cli;
+pub mod effective;
+pub mod env;
 pub mod file;
+pub mod instance;
 pub mod types;
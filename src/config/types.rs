@@ -1,8 +1,43 @@
 // Licensed under the Open Software License version 3.0
 use crate::active_sender::config::ActiveSenderConfig;
+use crate::apcupsd::config::ApcupsdConfig;
+use crate::bandwidth::config::BandwidthConfig;
+use crate::change_rate::config::ChangeRateConfig;
+use crate::cpu_freq::config::CpuFreqConfig;
+use crate::crash_report::config::CrashReportConfig;
+use crate::degraded_mode::config::DegradedModeConfig;
+use crate::dht::config::DhtConfig;
+use crate::fleet::config::FleetConfig;
+use crate::graphite::config::GraphiteConfig;
+use crate::grpc::config::GrpcConfig;
+use crate::hot_reload::config::HotReloadConfig;
+use crate::hwmon::config::HwmonConfig;
+use crate::i2c::config::I2cConfig;
+use crate::ipmi::config::IpmiConfig;
+use crate::load_shedding::config::LoadSheddingConfig;
+use crate::lorawan::config::LoRaWanConfig;
+use crate::modbus::config::ModbusConfig;
 use crate::nut::config::UpsMonitoringConfig;
 use crate::one_wire::config::OneWireConfig;
 use crate::passive_endpoint::config::PassiveEndpointConfig;
+use crate::quality::config::QualityConfig;
+use crate::recording::config::{RecordConfig, ReplayConfig};
+use crate::redis_sink::config::RedisSinkConfig;
+use crate::relations::config::RelationsConfig;
+use crate::sampling::config::SamplingConfig;
+use crate::scheduler::config::SchedulerConfig;
+use crate::self_metrics::config::SelfMetricsConfig;
+use crate::shutdown_notifier::config::SignalsConfig;
+use crate::smart::config::SmartConfig;
+use crate::snmp_ups::config::SnmpUpsConfig;
+use crate::storage::config::StorageConfig;
+use crate::thermal_zone::config::ThermalZoneConfig;
+use crate::ups_runtime::config::UpsRuntimeConfig;
+use crate::ups_shutdown::config::UpsShutdownConfig;
+use crate::usb_hid::config::UsbHidConfig;
+use crate::wake_on_lan::config::WakeOnLanConfig;
+use crate::watchdog::config::WatchdogConfig;
+use crate::zabbix::config::ZabbixConfig;
 use serde::{Deserialize, Serialize};
 
 // Values to generate example config file
@@ -10,13 +45,94 @@ pub trait Example {
     fn example() -> Self;
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 /// `Config` struct for deserializing config.json
 pub struct Config {
     pub one_wire: OneWireConfig,
     pub ups_monitoring: UpsMonitoringConfig,
     pub active_data_sender: ActiveSenderConfig,
     pub passive_data_endpoint: PassiveEndpointConfig,
+    #[serde(default)]
+    pub wake_on_lan: WakeOnLanConfig,
+    #[serde(default)]
+    pub ups_shutdown: UpsShutdownConfig,
+    #[serde(default)]
+    pub self_metrics: SelfMetricsConfig,
+    #[serde(default)]
+    pub grpc: GrpcConfig,
+    #[serde(default)]
+    pub quality: QualityConfig,
+    #[serde(default)]
+    pub ups_runtime: UpsRuntimeConfig,
+    #[serde(default)]
+    pub change_rate: ChangeRateConfig,
+    #[serde(default)]
+    pub redis: RedisSinkConfig,
+    #[serde(default)]
+    pub storage: StorageConfig,
+    #[serde(default)]
+    pub relations: RelationsConfig,
+    #[serde(default)]
+    pub lorawan: LoRaWanConfig,
+    #[serde(default)]
+    pub thermal_zone: ThermalZoneConfig,
+    #[serde(default)]
+    pub scheduler: SchedulerConfig,
+    #[serde(default)]
+    pub load_shedding: LoadSheddingConfig,
+    #[serde(default)]
+    pub degraded_mode: DegradedModeConfig,
+    #[serde(default)]
+    pub watchdog: WatchdogConfig,
+    #[serde(default)]
+    pub usb_hid: UsbHidConfig,
+    #[serde(default)]
+    pub apcupsd: ApcupsdConfig,
+    #[serde(default)]
+    pub snmp_ups: SnmpUpsConfig,
+    #[serde(default)]
+    pub hwmon: HwmonConfig,
+    #[serde(default)]
+    pub cpu_freq: CpuFreqConfig,
+    #[serde(default)]
+    pub dht: DhtConfig,
+    #[serde(default)]
+    pub i2c: I2cConfig,
+    #[serde(default)]
+    pub smart: SmartConfig,
+    #[serde(default)]
+    pub modbus: ModbusConfig,
+    #[serde(default)]
+    pub ipmi: IpmiConfig,
+    #[serde(default)]
+    pub zabbix: ZabbixConfig,
+    #[serde(default)]
+    pub graphite: GraphiteConfig,
+    #[serde(default)]
+    pub fleet: FleetConfig,
+    #[serde(default)]
+    pub bandwidth: BandwidthConfig,
+    #[serde(default)]
+    pub record: RecordConfig,
+    #[serde(default)]
+    pub replay: ReplayConfig,
+    #[serde(default)]
+    pub sampling: SamplingConfig,
+    #[serde(default)]
+    pub signals: SignalsConfig,
+    #[serde(default)]
+    pub crash_report: CrashReportConfig,
+    #[serde(default)]
+    pub hot_reload: HotReloadConfig,
+    // Never mount control routes, for passive endpoints exposed to untrusted clients
+    #[serde(default)]
+    pub read_only: Option<bool>,
+}
+
+impl Config {
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.unwrap_or_default()
+    }
 }
 
 impl Example for Config {
@@ -26,6 +142,43 @@ impl Example for Config {
             ups_monitoring: UpsMonitoringConfig::example(),
             active_data_sender: ActiveSenderConfig::example(),
             passive_data_endpoint: PassiveEndpointConfig::example(),
+            wake_on_lan: WakeOnLanConfig::example(),
+            ups_shutdown: UpsShutdownConfig::example(),
+            self_metrics: SelfMetricsConfig::example(),
+            grpc: GrpcConfig::example(),
+            quality: QualityConfig::example(),
+            ups_runtime: UpsRuntimeConfig::example(),
+            change_rate: ChangeRateConfig::example(),
+            redis: RedisSinkConfig::example(),
+            storage: StorageConfig::example(),
+            relations: RelationsConfig::example(),
+            lorawan: LoRaWanConfig::example(),
+            thermal_zone: ThermalZoneConfig::example(),
+            scheduler: SchedulerConfig::example(),
+            load_shedding: LoadSheddingConfig::example(),
+            degraded_mode: DegradedModeConfig::example(),
+            watchdog: WatchdogConfig::example(),
+            usb_hid: UsbHidConfig::example(),
+            apcupsd: ApcupsdConfig::example(),
+            snmp_ups: SnmpUpsConfig::example(),
+            hwmon: HwmonConfig::example(),
+            cpu_freq: CpuFreqConfig::example(),
+            dht: DhtConfig::example(),
+            i2c: I2cConfig::example(),
+            smart: SmartConfig::example(),
+            modbus: ModbusConfig::example(),
+            ipmi: IpmiConfig::example(),
+            zabbix: ZabbixConfig::example(),
+            graphite: GraphiteConfig::example(),
+            fleet: FleetConfig::example(),
+            bandwidth: BandwidthConfig::example(),
+            record: RecordConfig::example(),
+            replay: ReplayConfig::example(),
+            sampling: SamplingConfig::example(),
+            signals: SignalsConfig::example(),
+            crash_report: CrashReportConfig::example(),
+            hot_reload: HotReloadConfig::example(),
+            read_only: Some(false),
         }
     }
 }
@@ -0,0 +1,58 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CpuFreqConfig {
+    enabled: Option<bool>,
+    cooldown: Option<Duration>,
+    path: Option<PathBuf>,
+    // Core directory names (ex. cpu0) to read, all if not set
+    cores: Option<Vec<String>>,
+}
+
+impl Default for CpuFreqConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            cooldown: Some(Duration::from_secs(5)),
+            path: Some(PathBuf::from("/sys/devices/system/cpu")),
+            cores: None,
+        }
+    }
+}
+
+impl Example for CpuFreqConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(false),
+            cooldown: Some(Duration::from_secs(5)),
+            path: Some(PathBuf::from("/sys/devices/system/cpu")),
+            cores: None,
+        }
+    }
+}
+
+impl CpuFreqConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_cooldown(&self) -> Duration {
+        self.cooldown.unwrap_or(Duration::from_secs(5))
+    }
+
+    pub fn get_path(&self) -> PathBuf {
+        self.path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("/sys/devices/system/cpu"))
+    }
+
+    pub fn is_core_enabled(&self, directory: &str) -> bool {
+        match &self.cores {
+            Some(cores) => cores.iter().any(|core| core == directory),
+            None => true,
+        }
+    }
+}
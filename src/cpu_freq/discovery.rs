@@ -0,0 +1,152 @@
+// Licensed under the Open Software License version 3.0
+//! Frequency scaling and thermal throttle counters of cores in `/sys/devices/system/cpu/cpu*`
+use super::config::CpuFreqConfig;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Core {
+    /// Directory name, ex. `cpu0`
+    pub id: String,
+    pub path: PathBuf,
+}
+
+/// Values of a core, every one of them is optional
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CoreState {
+    // MHz
+    pub frequency: Option<f64>,
+    pub max_frequency: Option<f64>,
+    // Lowered by the governor, firmware or thermal limits
+    pub scaling_max_frequency: Option<f64>,
+    // Counters since boot, only exposed on x86
+    pub core_throttle_count: Option<f64>,
+    pub core_throttle_time_ms: Option<f64>,
+    pub package_throttle_count: Option<f64>,
+    pub package_throttle_time_ms: Option<f64>,
+}
+
+impl CoreState {
+    /// Offline cores and ones without cpufreq or throttle counters
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Number of `cpu<n>`, `None` for other entries (ex. cpufreq, cpuidle)
+fn core_number(directory: &str) -> Option<u32> {
+    directory.strip_prefix("cpu")?.parse().ok()
+}
+
+/// Enabled cores ordered by their number
+pub fn discover_cores(path: &Path, config: &CpuFreqConfig) -> Vec<Core> {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(error) => {
+            tracing::error!("Failed to read {}: {}", path.display(), error);
+            return Vec::new();
+        }
+    };
+    let mut directories: Vec<(u32, String)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let directory = entry.file_name().to_string_lossy().to_string();
+            Some((core_number(&directory)?, directory))
+        })
+        .filter(|(_, directory)| config.is_core_enabled(directory))
+        .collect();
+    directories.sort();
+    directories
+        .into_iter()
+        .map(|(_, directory)| Core {
+            path: path.join(&directory),
+            id: directory,
+        })
+        .collect()
+}
+
+fn read_number(path: &Path) -> Option<f64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Frequencies are exposed in kHz
+fn read_frequency(path: &Path) -> Option<f64> {
+    read_number(path).map(|khz| khz / 1000.0)
+}
+
+pub fn read_core(core: &Core) -> CoreState {
+    let cpufreq = core.path.join("cpufreq");
+    let throttle = core.path.join("thermal_throttle");
+    CoreState {
+        frequency: read_frequency(&cpufreq.join("scaling_cur_freq"))
+            .or_else(|| read_frequency(&cpufreq.join("cpuinfo_cur_freq"))),
+        max_frequency: read_frequency(&cpufreq.join("cpuinfo_max_freq")),
+        scaling_max_frequency: read_frequency(&cpufreq.join("scaling_max_freq")),
+        core_throttle_count: read_number(&throttle.join("core_throttle_count")),
+        core_throttle_time_ms: read_number(&throttle.join("core_throttle_total_time_ms")),
+        package_throttle_count: read_number(&throttle.join("package_throttle_count")),
+        package_throttle_time_ms: read_number(&throttle.join("package_throttle_total_time_ms")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::types::Example;
+
+    fn create_core(root: &Path, directory: &str, files: &[(&str, &str)]) {
+        for (file, value) in files {
+            let path = root.join(directory).join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, format!("{}\n", value)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_discover_and_read_cores() {
+        let root = tempfile::tempdir().unwrap();
+        create_core(
+            root.path(),
+            "cpu10",
+            &[("cpufreq/scaling_cur_freq", "800000")],
+        );
+        create_core(
+            root.path(),
+            "cpu2",
+            &[
+                ("cpufreq/cpuinfo_cur_freq", "3400000"),
+                ("cpufreq/cpuinfo_max_freq", "4200000"),
+                ("cpufreq/scaling_max_freq", "3500000"),
+                ("thermal_throttle/core_throttle_count", "7"),
+                ("thermal_throttle/core_throttle_total_time_ms", "1250"),
+                ("thermal_throttle/package_throttle_count", "3"),
+            ],
+        );
+        fs::create_dir_all(root.path().join("cpu3")).unwrap();
+        fs::create_dir_all(root.path().join("cpufreq/policy0")).unwrap();
+        fs::create_dir_all(root.path().join("cpuidle")).unwrap();
+
+        let cores = discover_cores(root.path(), &CpuFreqConfig::example());
+        let ids: Vec<&str> = cores.iter().map(|core| core.id.as_str()).collect();
+        assert_eq!(ids, vec!["cpu2", "cpu3", "cpu10"]);
+        assert_eq!(
+            read_core(&cores[0]),
+            CoreState {
+                frequency: Some(3400.0),
+                max_frequency: Some(4200.0),
+                scaling_max_frequency: Some(3500.0),
+                core_throttle_count: Some(7.0),
+                core_throttle_time_ms: Some(1250.0),
+                package_throttle_count: Some(3.0),
+                package_throttle_time_ms: None,
+            }
+        );
+        assert!(read_core(&cores[1]).is_empty());
+        assert_eq!(read_core(&cores[2]).frequency, Some(800.0));
+
+        let config: CpuFreqConfig = serde_json::from_str(r#"{"cores": ["cpu10"]}"#).unwrap();
+        assert_eq!(discover_cores(root.path(), &config).len(), 1);
+    }
+}
@@ -0,0 +1,182 @@
+// Licensed under the Open Software License version 3.0
+use super::{
+    config::CpuFreqConfig,
+    discovery::{discover_cores, read_core, CoreState},
+};
+use crate::{
+    hardware::{
+        reading::{Reading, ReadingsUpdate},
+        types::{HardwareMetadata, HardwareType, SourceType},
+    },
+    introspection, sampling,
+    scheduler::job::{PollFuture, PollJob},
+};
+use std::{cmp::max, collections::HashMap, path::PathBuf, time::Duration};
+use tokio::{sync::broadcast, time::sleep};
+
+const PUBLISHER: &str = "cpu_freq";
+
+/// Increase of a counter since the previous poll, a counter that went down was reset
+fn events(previous: Option<f64>, current: Option<f64>) -> Option<f64> {
+    let (previous, current) = (previous?, current?);
+    match current >= previous {
+        true => Some(current - previous),
+        false => Some(current),
+    }
+}
+
+fn to_reading(id: String, state: &CoreState, previous: Option<&CoreState>) -> Reading {
+    Reading::new(
+        HardwareMetadata::new(id, HardwareType::Processor, SourceType::CpuFreq).measured_now(),
+    )
+    .with_value("frequency_mhz", state.frequency)
+    .with_value("max_frequency_mhz", state.max_frequency)
+    .with_value("scaling_max_frequency_mhz", state.scaling_max_frequency)
+    .with_value("core_throttle_count", state.core_throttle_count)
+    .with_value("core_throttle_time_ms", state.core_throttle_time_ms)
+    .with_value(
+        "core_throttle_events",
+        events(
+            previous.and_then(|previous| previous.core_throttle_count),
+            state.core_throttle_count,
+        ),
+    )
+    .with_value("package_throttle_count", state.package_throttle_count)
+    .with_value("package_throttle_time_ms", state.package_throttle_time_ms)
+    .with_value(
+        "package_throttle_events",
+        events(
+            previous.and_then(|previous| previous.package_throttle_count),
+            state.package_throttle_count,
+        ),
+    )
+}
+
+pub struct CpuFreqPoller {
+    path: PathBuf,
+    cooldown: Duration,
+    config: CpuFreqConfig,
+    // Core id -> state of the previous poll, to count new throttle events
+    previous: HashMap<String, CoreState>,
+    tx: broadcast::Sender<ReadingsUpdate>,
+}
+
+impl CpuFreqPoller {
+    pub fn new(config: CpuFreqConfig, tx: broadcast::Sender<ReadingsUpdate>) -> Self {
+        Self {
+            path: config.get_path(),
+            cooldown: max(config.get_cooldown(), Duration::from_secs(1)),
+            config,
+            previous: HashMap::new(),
+            tx,
+        }
+    }
+
+    /// Readings of all enabled cores, discovered again every time as cores may go online later
+    fn read_cores(&mut self) -> Vec<Reading> {
+        let mut previous = HashMap::new();
+        let mut readings = Vec::new();
+        for core in discover_cores(&self.path, &self.config) {
+            let state = read_core(&core);
+            if state.is_empty() {
+                tracing::debug!("Skipping core without cpufreq {}", core.path.display());
+                continue;
+            }
+            readings.push(to_reading(
+                core.id.clone(),
+                &state,
+                self.previous.get(&core.id),
+            ));
+            previous.insert(core.id, state);
+        }
+        self.previous = previous;
+        readings
+    }
+
+    /// Publish frequency and throttling of all enabled cores, returns delay until the next poll
+    pub fn poll_once(&mut self) -> Duration {
+        introspection::mark_iteration("cpu_freq");
+        let readings = self.read_cores();
+        tracing::trace!("Sending {:?} to channel", readings);
+        if self.tx.receiver_count() > 0 {
+            self.tx
+                .send(ReadingsUpdate::new(PUBLISHER, readings))
+                .unwrap();
+            introspection::observe_channel("readings", &self.tx);
+        }
+        introspection::mark_idle("cpu_freq");
+        self.cooldown
+    }
+}
+
+impl PollJob for CpuFreqPoller {
+    fn name(&self) -> String {
+        String::from("cpu_freq")
+    }
+
+    fn poll(&mut self) -> PollFuture<'_> {
+        let delay = self.poll_once();
+        Box::pin(async move { delay })
+    }
+}
+
+pub async fn start_cpu_freq_loop(
+    mut shutdown_rx: broadcast::Receiver<()>,
+    config: CpuFreqConfig,
+    tx: broadcast::Sender<ReadingsUpdate>,
+) {
+    // Check if module is enabled
+    if !config.is_enabled() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+    tracing::debug!("Starting CPU frequency loop");
+    let mut poller = CpuFreqPoller::new(config, tx);
+    let _task = introspection::task_started("cpu_freq");
+    loop {
+        let delay = poller.poll_once();
+        tokio::select! {
+            _ = shutdown_rx.recv() => {
+                tracing::trace!("Shutting down CPU frequency loop");
+                break;
+            }
+            _ = sleep(sampling::tick::aligned(delay)) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_throttle_events() {
+        let root = tempfile::tempdir().unwrap();
+        let throttle = root.path().join("cpu0").join("thermal_throttle");
+        fs::create_dir_all(&throttle).unwrap();
+        fs::write(throttle.join("core_throttle_count"), "5\n").unwrap();
+        let config: CpuFreqConfig = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "path": root.path(),
+        }))
+        .unwrap();
+        let (tx, _rx) = broadcast::channel(1);
+        let mut poller = CpuFreqPoller::new(config, tx);
+
+        // Nothing to compare to on the first poll
+        let readings = poller.read_cores();
+        assert_eq!(readings[0].meta.hw.id, "cpu0");
+        assert_eq!(readings[0].values["core_throttle_count"], 5.0);
+        assert!(!readings[0].values.contains_key("core_throttle_events"));
+
+        fs::write(throttle.join("core_throttle_count"), "8\n").unwrap();
+        let readings = poller.read_cores();
+        assert_eq!(readings[0].values["core_throttle_events"], 3.0);
+
+        // Reset, ex. after the core went offline
+        fs::write(throttle.join("core_throttle_count"), "2\n").unwrap();
+        let readings = poller.read_cores();
+        assert_eq!(readings[0].values["core_throttle_events"], 2.0);
+    }
+}
@@ -0,0 +1,45 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrashReportConfig {
+    enabled: Option<bool>,
+    // Next to the config file by default
+    directory: Option<String>,
+}
+
+impl Default for CrashReportConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(true),
+            directory: None,
+        }
+    }
+}
+
+impl Example for CrashReportConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(true),
+            directory: None,
+        }
+    }
+}
+
+impl CrashReportConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+
+    pub fn get_directory(&self, config_file_path: &Path) -> PathBuf {
+        match &self.directory {
+            Some(directory) => PathBuf::from(directory),
+            None => config_file_path
+                .parent()
+                .unwrap_or_else(|| Path::new(""))
+                .join("crash_reports"),
+        }
+    }
+}
@@ -0,0 +1,231 @@
+// Licensed under the Open Software License version 3.0
+//! Panic hook that logs a crash report and writes it to a file
+//!
+//! Panics of spawned tasks only end that task, so without the hook they're easy to miss in
+//! the journal. Reports are written for every panic, the default hook runs afterwards.
+use super::config::CrashReportConfig;
+use crate::{
+    config::{file::get_config_file_path, types::Config},
+    introspection::{self, TaskStatus},
+};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use std::{
+    backtrace::Backtrace,
+    collections::BTreeMap,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+
+/// Identifies the config a crash happened with, without revealing any secrets
+pub fn config_hash(config: &Config) -> String {
+    let json = serde_json::to_vec(config).unwrap_or_default();
+    format!("sha256:{:x}", Sha256::digest(json))
+}
+
+/// Hash of the config in use, replaced on hot reload
+fn current_config_hash() -> &'static Mutex<String> {
+    static CONFIG_HASH: OnceLock<Mutex<String>> = OnceLock::new();
+    CONFIG_HASH.get_or_init(Default::default)
+}
+
+/// Report crashes with the config applied by hot reload
+pub fn set_config_hash(config_hash: String) {
+    if let Ok(mut current) = current_config_hash().lock() {
+        *current = config_hash;
+    }
+}
+
+/// `None` if the panicking thread was holding the lock
+fn get_config_hash() -> Option<String> {
+    current_config_hash()
+        .try_lock()
+        .ok()
+        .map(|hash| hash.clone())
+}
+
+/// Top-level module of a source file, ex. `nut` for `src/nut/client.rs`
+fn module_of(file: &str) -> Option<String> {
+    let path = file.replace('\\', "/");
+    let module = path.strip_prefix("src/")?.split('/').next()?;
+    Some(String::from(module.strip_suffix(".rs").unwrap_or(module)))
+}
+
+struct CrashReport {
+    timestamp: DateTime<Utc>,
+    message: String,
+    location: Option<String>,
+    module: Option<String>,
+    thread: Option<String>,
+    config_hash: String,
+    // `None` if introspection was locked by the panicking thread
+    tasks: Option<BTreeMap<String, TaskStatus>>,
+    backtrace: String,
+}
+
+impl CrashReport {
+    fn render(&self) -> String {
+        let mut report = String::new();
+        let _ = writeln!(
+            report,
+            "universal-data-source {} panicked at {}",
+            env!("CARGO_PKG_VERSION"),
+            self.timestamp.to_rfc3339()
+        );
+        let unknown = String::from("unknown");
+        let _ = writeln!(
+            report,
+            "module: {}",
+            self.module.as_ref().unwrap_or(&unknown)
+        );
+        let _ = writeln!(
+            report,
+            "location: {}",
+            self.location.as_ref().unwrap_or(&unknown)
+        );
+        let _ = writeln!(
+            report,
+            "thread: {}",
+            self.thread.as_ref().unwrap_or(&unknown)
+        );
+        let _ = writeln!(report, "message: {}", self.message);
+        let _ = writeln!(report, "config: {}", self.config_hash);
+        let _ = writeln!(report, "\nlast activity:");
+        match &self.tasks {
+            Some(tasks) if !tasks.is_empty() => {
+                for (name, task) in tasks {
+                    let _ = writeln!(
+                        report,
+                        "  {}: {} ({} iterations, {} running)",
+                        name,
+                        task.last_iteration.as_ref().unwrap_or(&unknown),
+                        task.iterations,
+                        task.running
+                    );
+                }
+            }
+            Some(_) => report.push_str("  no loops started\n"),
+            None => report.push_str("  unavailable\n"),
+        }
+        let _ = write!(report, "\nbacktrace:\n{}", self.backtrace);
+        report
+    }
+
+    fn file_name(&self) -> String {
+        format!("crash-{}.txt", self.timestamp.format("%Y%m%dT%H%M%S%.3fZ"))
+    }
+}
+
+fn write_report(directory: &Path, report: &CrashReport) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(directory)?;
+    let path = directory.join(report.file_name());
+    fs::write(&path, report.render())?;
+    Ok(path)
+}
+
+/// Log every panic with a crash report, also written to a file if enabled
+pub fn install_panic_hook(config: &CrashReportConfig, config_hash: String) {
+    set_config_hash(config_hash);
+    let directory = config
+        .is_enabled()
+        .then(|| config.get_directory(&get_config_file_path()));
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|message| String::from(*message))
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| String::from("non-string panic payload"));
+        let report = CrashReport {
+            timestamp: Utc::now(),
+            message,
+            location: info.location().map(ToString::to_string),
+            module: info
+                .location()
+                .and_then(|location| module_of(location.file())),
+            thread: std::thread::current().name().map(String::from),
+            config_hash: get_config_hash().unwrap_or_else(|| String::from("unknown")),
+            tasks: introspection::try_tasks(),
+            backtrace: Backtrace::force_capture().to_string(),
+        };
+        tracing::error!("Crash report: {}", report.render());
+        if let Some(directory) = &directory {
+            match write_report(directory, &report) {
+                Ok(path) => tracing::error!("Crash report written to {}", path.display()),
+                Err(error) => tracing::error!(
+                    "Failed to write crash report to {}: {}",
+                    directory.display(),
+                    error
+                ),
+            }
+        }
+        default_hook(info);
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::types::Example;
+
+    #[test]
+    fn test_module_of() {
+        assert_eq!(module_of("src/nut/client.rs").as_deref(), Some("nut"));
+        assert_eq!(module_of("src/main.rs").as_deref(), Some("main"));
+        assert_eq!(
+            module_of("src\\one_wire\\sender.rs").as_deref(),
+            Some("one_wire")
+        );
+        assert_eq!(
+            module_of("/home/user/.cargo/registry/src/tokio/lib.rs"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_set_config_hash() {
+        let mut config = Config::example();
+        set_config_hash(config_hash(&config));
+        config.read_only = Some(true);
+        set_config_hash(config_hash(&config));
+        assert_eq!(get_config_hash(), Some(config_hash(&config)));
+    }
+
+    #[test]
+    fn test_write_report() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut tasks = BTreeMap::new();
+        tasks.insert(
+            String::from("nut"),
+            TaskStatus {
+                running: 1,
+                iterations: 3,
+                last_iteration: Some(String::from("2024-01-01T00:00:00+00:00")),
+                last_idle: None,
+            },
+        );
+        let report = CrashReport {
+            timestamp: DateTime::parse_from_rfc3339("2024-01-01T00:00:05+00:00")
+                .unwrap()
+                .with_timezone(&Utc),
+            message: String::from("index out of bounds"),
+            location: Some(String::from("src/nut/client.rs:10:5")),
+            module: Some(String::from("nut")),
+            thread: None,
+            config_hash: config_hash(&Config::example()),
+            tasks: Some(tasks),
+            backtrace: String::from("disabled backtrace"),
+        };
+        let path = write_report(&temp_dir.path().join("crash_reports"), &report).unwrap();
+        assert_eq!(path.file_name().unwrap(), "crash-20240101T000005.000Z.txt");
+        let contents = fs::read_to_string(path).unwrap();
+        assert!(contents.contains("module: nut\n"));
+        assert!(contents.contains("thread: unknown\n"));
+        assert!(contents.contains("config: sha256:"));
+        assert!(contents.contains("  nut: 2024-01-01T00:00:00+00:00 (3 iterations, 1 running)\n"));
+        assert!(contents.ends_with("disabled backtrace"));
+    }
+}
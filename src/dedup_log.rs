@@ -0,0 +1,152 @@
+// Licensed under the Open Software License version 3.0
+//! Logging of repeated failures without flooding the journal
+//!
+//! First failure is logged as usual, repeats are counted and summarized every `SUMMARY_INTERVAL`.
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+/// How often to remind about a failure that keeps repeating
+const SUMMARY_INTERVAL: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Occurrence {
+    /// Log the message as usual
+    First,
+    /// Log a summary with number of repeats since the previous log
+    Summary(u64),
+    /// Already logged recently
+    Suppressed,
+}
+
+#[derive(Debug)]
+struct Failure {
+    last_logged: Instant,
+    // Since last_logged
+    repeats: u64,
+    total: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Deduplicator {
+    failures: HashMap<String, Failure>,
+}
+
+impl Deduplicator {
+    pub fn record(&mut self, key: &str, now: Instant, interval: Duration) -> Occurrence {
+        let failure = match self.failures.get_mut(key) {
+            Some(failure) => failure,
+            None => {
+                let failure = Failure {
+                    last_logged: now,
+                    repeats: 0,
+                    total: 1,
+                };
+                self.failures.insert(String::from(key), failure);
+                return Occurrence::First;
+            }
+        };
+        failure.repeats += 1;
+        failure.total += 1;
+        if now.duration_since(failure.last_logged) < interval {
+            return Occurrence::Suppressed;
+        }
+        let repeats = failure.repeats;
+        failure.last_logged = now;
+        failure.repeats = 0;
+        Occurrence::Summary(repeats)
+    }
+
+    /// Forget failure of `key`, returns how many times it failed in a row
+    pub fn resolve(&mut self, key: &str) -> Option<u64> {
+        self.failures.remove(key).map(|failure| failure.total)
+    }
+}
+
+fn deduplicator() -> &'static Mutex<Deduplicator> {
+    static DEDUPLICATOR: OnceLock<Mutex<Deduplicator>> = OnceLock::new();
+    DEDUPLICATOR.get_or_init(Mutex::default)
+}
+
+/// Count failure of `key`, `key` should be unique across modules
+pub fn record(key: &str) -> Occurrence {
+    let mut deduplicator = deduplicator()
+        .lock()
+        .unwrap_or_else(|error| error.into_inner());
+    deduplicator.record(key, Instant::now(), SUMMARY_INTERVAL)
+}
+
+/// Mark `key` as no longer failing
+pub fn resolve(key: &str) -> Option<u64> {
+    let mut deduplicator = deduplicator()
+        .lock()
+        .unwrap_or_else(|error| error.into_inner());
+    deduplicator.resolve(key)
+}
+
+/// `tracing::warn!` that logs repeats of the same `key` only as periodic summaries
+macro_rules! warn_deduplicated {
+    ($key:expr, $($arg:tt)+) => {
+        match $crate::dedup_log::record(&$key) {
+            $crate::dedup_log::Occurrence::First => tracing::warn!($($arg)+),
+            $crate::dedup_log::Occurrence::Summary(repeats) => {
+                tracing::warn!("Still failing (x{}): {}", repeats, format_args!($($arg)+))
+            }
+            $crate::dedup_log::Occurrence::Suppressed => {}
+        }
+    };
+}
+
+/// `tracing::info!` logged only if `key` was failing before
+macro_rules! info_resolved {
+    ($key:expr, $($arg:tt)+) => {
+        if let Some(failures) = $crate::dedup_log::resolve(&$key) {
+            tracing::info!("{} (after {} failures)", format_args!($($arg)+), failures)
+        }
+    };
+}
+
+pub(crate) use info_resolved;
+pub(crate) use warn_deduplicated;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeats_are_summarized() {
+        let mut deduplicator = Deduplicator::default();
+        let start = Instant::now();
+        let interval = Duration::from_secs(60);
+        assert_eq!(deduplicator.record("a", start, interval), Occurrence::First);
+        for second in 1..60 {
+            let now = start + Duration::from_secs(second);
+            assert_eq!(
+                deduplicator.record("a", now, interval),
+                Occurrence::Suppressed
+            );
+        }
+        let now = start + Duration::from_secs(60);
+        assert_eq!(
+            deduplicator.record("a", now, interval),
+            Occurrence::Summary(60)
+        );
+        // Other keys are independent
+        assert_eq!(deduplicator.record("b", now, interval), Occurrence::First);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut deduplicator = Deduplicator::default();
+        let now = Instant::now();
+        let interval = Duration::from_secs(60);
+        assert_eq!(deduplicator.resolve("a"), None);
+        deduplicator.record("a", now, interval);
+        deduplicator.record("a", now, interval);
+        assert_eq!(deduplicator.resolve("a"), Some(2));
+        // Failing again is logged right away
+        assert_eq!(deduplicator.record("a", now, interval), Occurrence::First);
+    }
+}
@@ -0,0 +1,39 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DegradedModeConfig {
+    enabled: Option<bool>,
+    // How often to check the config file for a corrected version
+    check_interval: Option<Duration>,
+}
+
+impl Default for DegradedModeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            check_interval: Some(Duration::from_secs(10)),
+        }
+    }
+}
+
+impl Example for DegradedModeConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(false),
+            check_interval: Some(Duration::from_secs(10)),
+        }
+    }
+}
+
+impl DegradedModeConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_check_interval(&self) -> Duration {
+        self.check_interval.unwrap_or(Duration::from_secs(10))
+    }
+}
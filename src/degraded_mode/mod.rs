@@ -0,0 +1,5 @@
+// Licensed under the Open Software License version 3.0
+//! Opt-in start with valid config sections only, restarting once the file is corrected
+pub mod config;
+pub mod parse;
+pub mod watcher;
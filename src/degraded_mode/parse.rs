@@ -0,0 +1,78 @@
+// Licensed under the Open Software License version 3.0
+use super::config::DegradedModeConfig;
+use crate::config::types::Config;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Config with invalid sections replaced by their defaults (disabled modules)
+#[derive(Debug)]
+pub struct PartialConfig {
+    pub config: Config,
+    /// Top-level key -> parse error
+    pub invalid_sections: BTreeMap<String, String>,
+}
+
+/// `None` if degraded mode isn't enabled in `json` or it isn't a JSON object
+pub fn parse_partial_config(json: &str) -> Option<PartialConfig> {
+    let file = match serde_json::from_str::<Value>(json).ok()? {
+        Value::Object(file) => file,
+        _ => return None,
+    };
+    let degraded_mode: DegradedModeConfig =
+        serde_json::from_value(file.get("degraded_mode")?.clone()).ok()?;
+    if !degraded_mode.is_enabled() {
+        return None;
+    }
+    let mut valid = match serde_json::to_value(Config::default()) {
+        Ok(Value::Object(valid)) => valid,
+        _ => unreachable!("Config is serialized as an object"),
+    };
+    let mut invalid_sections = BTreeMap::new();
+    // Fields are parsed independently, so a section that parses on its own stays valid
+    for (key, section) in file {
+        let mut candidate = valid.clone();
+        candidate.insert(key.clone(), section.clone());
+        match serde_json::from_value::<Config>(Value::Object(candidate)) {
+            Ok(_) => {
+                valid.insert(key, section);
+            }
+            Err(error) => {
+                invalid_sections.insert(key, error.to_string());
+            }
+        }
+    }
+    let config = serde_json::from_value(Value::Object(valid)).ok()?;
+    Some(PartialConfig {
+        config,
+        invalid_sections,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::types::Example;
+
+    #[test]
+    fn test_parse_partial_config() {
+        let mut json = serde_json::to_value(Config::example()).unwrap();
+        json["degraded_mode"]["enabled"] = Value::Bool(true);
+        json["ups_monitoring"]["servers"] = Value::from("typo");
+        let partial = parse_partial_config(&json.to_string()).unwrap();
+        assert_eq!(
+            partial.invalid_sections.keys().collect::<Vec<_>>(),
+            vec!["ups_monitoring"]
+        );
+        assert!(!partial.config.ups_monitoring.is_enabled());
+        assert_eq!(partial.config.one_wire, Config::example().one_wire);
+        assert!(partial.config.degraded_mode.is_enabled());
+    }
+
+    #[test]
+    fn test_parse_partial_config_requires_opt_in() {
+        let mut json = serde_json::to_value(Config::example()).unwrap();
+        json["ups_monitoring"]["servers"] = Value::from("typo");
+        assert!(parse_partial_config(&json.to_string()).is_none());
+        assert!(parse_partial_config("{").is_none());
+    }
+}
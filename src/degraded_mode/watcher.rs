@@ -0,0 +1,125 @@
+// Licensed under the Open Software License version 3.0
+use super::{config::DegradedModeConfig, parse::parse_partial_config};
+use crate::{
+    config::{env::with_env_overrides, file::get_config_file_path, types::Config},
+    introspection,
+};
+use std::{
+    collections::BTreeMap,
+    fs,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+};
+use tokio::{sync::broadcast, sync::Notify, time::sleep};
+
+static RESTART_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+fn restart_notify() -> &'static Notify {
+    static RESTART_NOTIFY: OnceLock<Notify> = OnceLock::new();
+    RESTART_NOTIFY.get_or_init(Notify::new)
+}
+
+pub fn request_restart() {
+    RESTART_REQUESTED.store(true, Ordering::SeqCst);
+    // Stores a permit, so it isn't lost if nobody is waiting yet
+    restart_notify().notify_one();
+}
+
+/// Resolves once the corrected config was found, used to shut down all tasks
+pub async fn restart_requested() {
+    restart_notify().notified().await;
+}
+
+pub fn is_restart_requested() -> bool {
+    RESTART_REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Replace current process with a new one with the same arguments
+pub fn restart_process() {
+    let args: Vec<String> = std::env::args().collect();
+    let executable = match std::env::current_exe() {
+        Ok(executable) => executable,
+        Err(error) => {
+            tracing::error!("Failed to restart, can't locate executable: {}", error);
+            std::process::exit(1);
+        }
+    };
+    tracing::info!("Restarting {}", executable.display());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        let error = std::process::Command::new(&executable)
+            .args(&args[1..])
+            .exec();
+        tracing::error!("Failed to restart: {}", error);
+        std::process::exit(1);
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = args;
+        tracing::error!("Restarting is only supported on unix, please start the program again");
+        std::process::exit(1);
+    }
+}
+
+/// Log ignored sections and expose them at `/status/internal`
+pub fn report_invalid_sections(invalid_sections: &BTreeMap<String, String>) {
+    for (section, error) in invalid_sections {
+        tracing::error!(
+            "Ignoring invalid config section {}, its module is disabled: {}",
+            section,
+            error
+        );
+    }
+    introspection::set_invalid_config(invalid_sections);
+}
+
+/// Check the config file until it's fully valid, then request a restart
+pub async fn start_config_watcher_loop(
+    mut shutdown_rx: broadcast::Receiver<()>,
+    config: DegradedModeConfig,
+) {
+    // Check if module is enabled and the config needs to be corrected
+    if !config.is_enabled() || introspection::get_invalid_config().is_empty() {
+        return;
+    }
+    let path = get_config_file_path();
+    tracing::info!("Watching {} for a corrected config", path.display());
+    let _task = introspection::task_started("degraded_mode");
+    let mut last_contents = fs::read_to_string(&path).ok();
+    loop {
+        tokio::select! {
+            _ = shutdown_rx.recv() => {
+                tracing::trace!("Shutting down config watcher loop");
+                break;
+            }
+            _ = sleep(config.get_check_interval()) => {}
+        }
+        introspection::mark_iteration("degraded_mode");
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(error) => {
+                tracing::warn!("Failed to read {}: {}", path.display(), error);
+                continue;
+            }
+        };
+        if last_contents.as_ref() == Some(&contents) {
+            continue;
+        }
+        last_contents = Some(contents.clone());
+        let contents = with_env_overrides(&contents);
+        match serde_json::from_str::<Config>(&contents) {
+            Ok(_) => {
+                tracing::info!("Config was corrected, restarting to start all modules");
+                request_restart();
+                break;
+            }
+            Err(error) => match parse_partial_config(&contents) {
+                Some(partial) => report_invalid_sections(&partial.invalid_sections),
+                None => tracing::warn!("Config is still invalid: {}", error),
+            },
+        }
+    }
+}
@@ -0,0 +1,80 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DhtSensorConfig {
+    // BCM GPIO number, not the physical pin
+    pin: u8,
+    // hw.id of the sensor, dht22-gpio<pin> if not set
+    id: Option<String>,
+    name: Option<String>,
+}
+
+impl Example for DhtSensorConfig {
+    fn example() -> Self {
+        Self {
+            pin: 4,
+            id: None,
+            name: Some(String::from("Basement")),
+        }
+    }
+}
+
+impl DhtSensorConfig {
+    pub fn get_pin(&self) -> u8 {
+        self.pin
+    }
+
+    pub fn get_id(&self) -> String {
+        self.id
+            .clone()
+            .unwrap_or_else(|| format!("dht22-gpio{}", self.pin))
+    }
+
+    pub fn get_name(&self) -> Option<String> {
+        self.name.clone()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DhtConfig {
+    enabled: Option<bool>,
+    cooldown: Option<Duration>,
+    sensors: Option<Vec<DhtSensorConfig>>,
+}
+
+impl Default for DhtConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            cooldown: Some(Duration::from_secs(10)),
+            sensors: None,
+        }
+    }
+}
+
+impl Example for DhtConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(false),
+            cooldown: Some(Duration::from_secs(10)),
+            sensors: Some(vec![DhtSensorConfig::example()]),
+        }
+    }
+}
+
+impl DhtConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_cooldown(&self) -> Duration {
+        self.cooldown.unwrap_or(Duration::from_secs(10))
+    }
+
+    pub fn get_sensors(&self) -> Vec<DhtSensorConfig> {
+        self.sensors.clone().unwrap_or_default()
+    }
+}
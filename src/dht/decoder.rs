@@ -0,0 +1,99 @@
+// Licensed under the Open Software License version 3.0
+//! DHT22 / AM2302 frames: 16 bit humidity, 16 bit temperature and a checksum byte
+use std::time::Duration;
+
+pub const FRAME_BITS: usize = 40;
+
+/// High pulses of 0 bits last 26-28µs, 1 bits last 70µs
+const ONE_THRESHOLD: Duration = Duration::from_micros(48);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DhtValues {
+    /// °C
+    pub temperature: f64,
+    /// Relative humidity in %
+    pub humidity: f64,
+}
+
+/// Frame bytes from lengths of high pulses, most significant bit first
+pub fn pulses_to_bytes(pulses: &[Duration]) -> Result<[u8; 5], String> {
+    if pulses.len() != FRAME_BITS {
+        return Err(format!("received {} of {} bits", pulses.len(), FRAME_BITS));
+    }
+    let mut bytes = [0u8; 5];
+    for (index, pulse) in pulses.iter().enumerate() {
+        if *pulse > ONE_THRESHOLD {
+            bytes[index / 8] |= 0x80 >> (index % 8);
+        }
+    }
+    Ok(bytes)
+}
+
+pub fn decode_frame(bytes: [u8; 5]) -> Result<DhtValues, String> {
+    let checksum = bytes[..4]
+        .iter()
+        .fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+    if checksum != bytes[4] {
+        return Err(format!(
+            "checksum mismatch, expected {:#04x}, received {:#04x}",
+            checksum, bytes[4]
+        ));
+    }
+    let humidity = u16::from_be_bytes([bytes[0], bytes[1]]) as f64 / 10.0;
+    // Sign and magnitude, not two's complement
+    let magnitude = u16::from_be_bytes([bytes[2] & 0x7f, bytes[3]]) as f64 / 10.0;
+    let temperature = match bytes[2] & 0x80 {
+        0 => magnitude,
+        _ => -magnitude,
+    };
+    if humidity > 100.0 {
+        return Err(format!("humidity {}% is out of range", humidity));
+    }
+    Ok(DhtValues {
+        temperature,
+        humidity,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pulses(bytes: [u8; 5]) -> Vec<Duration> {
+        (0..FRAME_BITS)
+            .map(|index| match bytes[index / 8] & (0x80 >> (index % 8)) {
+                0 => Duration::from_micros(27),
+                _ => Duration::from_micros(70),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_decode_frame() {
+        // 65.2%, 35.1°C
+        let bytes = [0x02, 0x8c, 0x01, 0x5f, 0xee];
+        assert_eq!(pulses_to_bytes(&pulses(bytes)), Ok(bytes));
+        assert_eq!(
+            decode_frame(bytes),
+            Ok(DhtValues {
+                temperature: 35.1,
+                humidity: 65.2,
+            })
+        );
+    }
+
+    #[test]
+    fn test_decode_negative_temperature() {
+        // 10.1°C below zero
+        let values = decode_frame([0x01, 0xf4, 0x80, 0x65, 0xda]).unwrap();
+        assert_eq!(values.temperature, -10.1);
+        assert_eq!(values.humidity, 50.0);
+    }
+
+    #[test]
+    fn test_decode_invalid_frame() {
+        assert!(decode_frame([0x02, 0x8c, 0x01, 0x5f, 0xef]).is_err());
+        assert!(decode_frame([0x03, 0xe9, 0x00, 0x00, 0xec]).is_err());
+        assert!(pulses_to_bytes(&[Duration::from_micros(70); 39]).is_err());
+    }
+}
@@ -0,0 +1,53 @@
+// Licensed under the Open Software License version 3.0
+//! Bit-banged DHT22 reads over rppal, blocking for a few milliseconds so run with `spawn_blocking`
+use super::decoder::{decode_frame, pulses_to_bytes, DhtValues, FRAME_BITS};
+use rppal::gpio::{Gpio, IoPin, Level, Mode, PullUpDown};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Host holds the line low for at least 1ms to request a frame
+const START_SIGNAL: Duration = Duration::from_millis(2);
+/// Longest level of the sensor is its 80µs response, anything longer means it stopped talking
+const LEVEL_TIMEOUT: Duration = Duration::from_micros(200);
+
+/// Busy wait, sleeping is far too coarse for microsecond pulses
+fn wait_for(pin: &IoPin, level: Level) -> Result<Duration, String> {
+    let start = Instant::now();
+    while pin.read() != level {
+        if start.elapsed() > LEVEL_TIMEOUT {
+            return Err(format!("timed out waiting for {} level", level));
+        }
+    }
+    Ok(start.elapsed())
+}
+
+fn read_pulses(pin: &mut IoPin) -> Result<Vec<Duration>, String> {
+    pin.set_mode(Mode::Output);
+    pin.set_low();
+    thread::sleep(START_SIGNAL);
+    pin.set_high();
+    pin.set_mode(Mode::Input);
+    pin.set_pullupdown(PullUpDown::PullUp);
+    // Response: 80µs low followed by 80µs high
+    wait_for(pin, Level::Low)?;
+    wait_for(pin, Level::High)?;
+    wait_for(pin, Level::Low)?;
+    // Every bit is 50µs low followed by a high pulse that encodes its value
+    let mut pulses = Vec::with_capacity(FRAME_BITS);
+    for _ in 0..FRAME_BITS {
+        wait_for(pin, Level::High)?;
+        pulses.push(wait_for(pin, Level::Low)?);
+    }
+    Ok(pulses)
+}
+
+pub fn read_sensor(gpio: &Gpio, pin: u8) -> Result<DhtValues, String> {
+    let mut pin = gpio
+        .get(pin)
+        .map_err(|error| error.to_string())?
+        .into_io(Mode::Output);
+    let pulses = read_pulses(&mut pin)?;
+    decode_frame(pulses_to_bytes(&pulses)?)
+}
@@ -0,0 +1,9 @@
+// Licensed under the Open Software License version 3.0
+#[cfg_attr(not(feature = "dht"), allow(dead_code))]
+pub mod config;
+// Only used by the dht feature and tests
+#[cfg_attr(not(feature = "dht"), allow(dead_code))]
+mod decoder;
+#[cfg(feature = "dht")]
+mod gpio;
+pub mod sender;
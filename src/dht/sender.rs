@@ -0,0 +1,152 @@
+// Licensed under the Open Software License version 3.0
+use super::config::DhtConfig;
+use crate::hardware::reading::ReadingsUpdate;
+use tokio::sync::broadcast;
+
+#[cfg(feature = "dht")]
+mod poller {
+    use super::super::{config::DhtSensorConfig, gpio::read_sensor};
+    use super::*;
+    use crate::{
+        dedup_log::{info_resolved, warn_deduplicated},
+        hardware::{
+            humidity::MeasuredHumidity,
+            reading::Reading,
+            types::{HardwareMetadata, HardwareType, SourceType},
+        },
+        introspection, sampling,
+        scheduler::job::{PollFuture, PollJob},
+    };
+    use rppal::gpio::Gpio;
+    use std::{cmp::max, time::Duration};
+    use tokio::time::sleep;
+
+    const PUBLISHER: &str = "dht";
+    /// Sensor returns the previous frame if read more often
+    const MIN_COOLDOWN: Duration = Duration::from_secs(2);
+
+    fn read_sensors(sensors: &[DhtSensorConfig]) -> Vec<MeasuredHumidity> {
+        let gpio = match Gpio::new() {
+            Ok(gpio) => gpio,
+            Err(error) => {
+                warn_deduplicated!("dht:gpio", "Failed to access GPIO: {}", error);
+                return Vec::new();
+            }
+        };
+        info_resolved!("dht:gpio", "Accessed GPIO");
+        let mut measured = Vec::new();
+        for sensor in sensors {
+            let id = sensor.get_id();
+            let key = format!("dht:{}", id);
+            match read_sensor(&gpio, sensor.get_pin()) {
+                Ok(values) => {
+                    info_resolved!(key, "Reading {} again", id);
+                    let mut meta =
+                        HardwareMetadata::new(id, HardwareType::HumiditySensor, SourceType::Dht)
+                            .measured_now();
+                    meta.hw.name = sensor.get_name();
+                    measured.push(MeasuredHumidity {
+                        meta,
+                        temperature: Some(values.temperature),
+                        humidity: Some(values.humidity),
+                    });
+                }
+                // Single corrupted frames are common, so they're only logged once
+                Err(error) => warn_deduplicated!(key, "Failed to read {}: {}", id, error),
+            }
+        }
+        measured
+    }
+
+    pub struct DhtPoller {
+        sensors: Vec<DhtSensorConfig>,
+        cooldown: Duration,
+        tx: broadcast::Sender<ReadingsUpdate>,
+    }
+
+    impl DhtPoller {
+        pub fn new(config: &DhtConfig, tx: broadcast::Sender<ReadingsUpdate>) -> Self {
+            Self {
+                sensors: config.get_sensors(),
+                cooldown: max(config.get_cooldown(), MIN_COOLDOWN),
+                tx,
+            }
+        }
+
+        /// Publish all readable sensors, returns delay until the next poll
+        pub async fn poll_once(&mut self) -> Duration {
+            introspection::mark_iteration("dht");
+            let sensors = self.sensors.clone();
+            let readings: Vec<Reading> =
+                tokio::task::spawn_blocking(move || read_sensors(&sensors))
+                    .await
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(Reading::from)
+                    .collect();
+            tracing::trace!("Sending {:?} to channel", readings);
+            if self.tx.receiver_count() > 0 {
+                self.tx
+                    .send(ReadingsUpdate::new(PUBLISHER, readings))
+                    .unwrap();
+                introspection::observe_channel("readings", &self.tx);
+            }
+            introspection::mark_idle("dht");
+            self.cooldown
+        }
+    }
+
+    impl PollJob for DhtPoller {
+        fn name(&self) -> String {
+            String::from("dht")
+        }
+
+        fn poll(&mut self) -> PollFuture<'_> {
+            Box::pin(self.poll_once())
+        }
+    }
+
+    pub async fn run(
+        mut shutdown_rx: broadcast::Receiver<()>,
+        config: DhtConfig,
+        tx: broadcast::Sender<ReadingsUpdate>,
+    ) {
+        tracing::debug!("Starting DHT loop");
+        let mut poller = DhtPoller::new(&config, tx);
+        let _task = introspection::task_started("dht");
+        loop {
+            let delay = poller.poll_once().await;
+            tokio::select! {
+                _ = shutdown_rx.recv() => {
+                    tracing::trace!("Shutting down DHT loop");
+                    break;
+                }
+                _ = sleep(sampling::tick::aligned(delay)) => {}
+            }
+        }
+    }
+}
+
+#[cfg(feature = "dht")]
+pub use poller::DhtPoller;
+
+pub async fn start_dht_loop(
+    shutdown_rx: broadcast::Receiver<()>,
+    config: DhtConfig,
+    tx: broadcast::Sender<ReadingsUpdate>,
+) {
+    // Check if module is enabled
+    if !config.is_enabled() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+
+    #[cfg(feature = "dht")]
+    poller::run(shutdown_rx, config, tx).await;
+
+    #[cfg(not(feature = "dht"))]
+    {
+        let _ = (shutdown_rx, tx);
+        tracing::error!("DHT is enabled in config but this binary was built without dht feature");
+    }
+}
@@ -0,0 +1,152 @@
+// Licensed under the Open Software License version 3.0
+use crate::{nut::sender::UninterruptiblePowerSupplyData, one_wire::sender::MeasuredTemperature};
+use arrow::{
+    array::{ArrayRef, Float64Array, StringArray, UInt8Array},
+    datatypes::{DataType, Field, Schema},
+    ipc::writer::FileWriter,
+    record_batch::RecordBatch,
+};
+use parquet::arrow::ArrowWriter;
+use std::{error::Error, str::FromStr, sync::Arc};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Parquet,
+    ArrowIpc,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "parquet" => Ok(Self::Parquet),
+            "arrow" => Ok(Self::ArrowIpc),
+            _ => Err(format!("unknown format {}, use parquet or arrow", format)),
+        }
+    }
+}
+
+impl ExportFormat {
+    pub fn get_media_type(&self) -> (&'static str, &'static str) {
+        match self {
+            Self::Parquet => ("application", "vnd.apache.parquet"),
+            Self::ArrowIpc => ("application", "vnd.apache.arrow.file"),
+        }
+    }
+}
+
+/// One row per sensor
+pub fn temperatures_to_batch(
+    sensors: &[MeasuredTemperature],
+) -> Result<RecordBatch, Box<dyn Error>> {
+    let schema = Schema::new(vec![
+        Field::new("id", DataType::Utf8, false),
+        Field::new("name", DataType::Utf8, true),
+        Field::new("source_type", DataType::Utf8, false),
+        Field::new("temperature", DataType::Float64, true),
+        Field::new("resolution", DataType::UInt8, true),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(
+            sensors.iter().map(|sensor| sensor.meta.hw.id.clone()),
+        )),
+        Arc::new(StringArray::from(
+            sensors
+                .iter()
+                .map(|sensor| sensor.meta.hw.name.clone())
+                .collect::<Vec<_>>(),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            sensors
+                .iter()
+                .map(|sensor| format!("{:?}", sensor.meta.source.source_type)),
+        )),
+        Arc::new(Float64Array::from(
+            sensors
+                .iter()
+                .map(|sensor| sensor.temperature)
+                .collect::<Vec<_>>(),
+        )),
+        Arc::new(UInt8Array::from(
+            sensors
+                .iter()
+                .map(|sensor| sensor.resolution)
+                .collect::<Vec<_>>(),
+        )),
+    ];
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+/// One row per UPS variable (long format), as variables differ between UPSes
+pub fn upses_to_batch(
+    upses: &[UninterruptiblePowerSupplyData],
+) -> Result<RecordBatch, Box<dyn Error>> {
+    let rows: Vec<(&String, &String, &String)> = upses
+        .iter()
+        .flat_map(|ups| {
+            let mut variables: Vec<(&String, &String)> = ups.variables.iter().collect();
+            variables.sort();
+            variables
+                .into_iter()
+                .map(move |(variable, value)| (&ups.meta.hw.id, variable, value))
+        })
+        .collect();
+    let schema = Schema::new(vec![
+        Field::new("id", DataType::Utf8, false),
+        Field::new("variable", DataType::Utf8, false),
+        Field::new("value", DataType::Utf8, false),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(rows.iter().map(|row| row.0))),
+        Arc::new(StringArray::from_iter_values(rows.iter().map(|row| row.1))),
+        Arc::new(StringArray::from_iter_values(rows.iter().map(|row| row.2))),
+    ];
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+pub fn write_batch(batch: &RecordBatch, format: ExportFormat) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut buffer = Vec::new();
+    match format {
+        ExportFormat::Parquet => {
+            let mut writer = ArrowWriter::try_new(&mut buffer, batch.schema(), None)?;
+            writer.write(batch)?;
+            writer.close()?;
+        }
+        ExportFormat::ArrowIpc => {
+            let mut writer = FileWriter::try_new(&mut buffer, &batch.schema())?;
+            writer.write(batch)?;
+            writer.finish()?;
+        }
+    }
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::types::Example;
+
+    #[test]
+    fn test_temperatures_to_batch() {
+        let batch = temperatures_to_batch(&[MeasuredTemperature::example()]).unwrap();
+        assert_eq!(batch.num_rows(), 1);
+        assert_eq!(batch.num_columns(), 5);
+    }
+
+    #[test]
+    fn test_upses_to_batch() {
+        let batch = upses_to_batch(&[UninterruptiblePowerSupplyData::example()]).unwrap();
+        // battery.charge and ups.load
+        assert_eq!(batch.num_rows(), 2);
+    }
+
+    #[test]
+    fn test_write_batch() {
+        let batch = temperatures_to_batch(&[MeasuredTemperature::example()]).unwrap();
+        let parquet = write_batch(&batch, ExportFormat::Parquet).unwrap();
+        assert_eq!(&parquet[..4], b"PAR1");
+        let arrow = write_batch(&batch, ExportFormat::ArrowIpc).unwrap();
+        assert_eq!(&arrow[..6], b"ARROW1");
+    }
+}
@@ -0,0 +1,66 @@
+// Licensed under the Open Software License version 3.0
+//! `universal-data-source export <temperature|ups> <parquet|arrow> <output> [url]`
+//!
+//! Fetches current snapshot from a running passive endpoint and writes it to a file
+use std::error::Error;
+
+pub fn is_export_command(args: &[String]) -> bool {
+    args.get(1).map(String::as_str) == Some("export")
+}
+
+#[cfg(feature = "export")]
+pub async fn run_export_command(args: &[String]) -> Result<(), Box<dyn Error>> {
+    use super::batch::{temperatures_to_batch, upses_to_batch, write_batch, ExportFormat};
+    use crate::{
+        nut::sender::UninterruptiblePowerSupplyData, one_wire::sender::MeasuredTemperature,
+        passive_endpoint::receiver::ApiResponse,
+    };
+    const DEFAULT_URL: &str = "http://localhost:63623";
+
+    let (category, format, output) = match (args.get(2), args.get(3), args.get(4)) {
+        (Some(category), Some(format), Some(output)) => (category, format, output),
+        _ => return Err(
+            "usage: universal-data-source export <temperature|ups> <parquet|arrow> <output> [url]"
+                .into(),
+        ),
+    };
+    let format: ExportFormat = format.parse()?;
+    let url = args.get(5).map(String::as_str).unwrap_or(DEFAULT_URL);
+
+    let batch = match category.as_str() {
+        "temperature" => {
+            let response: ApiResponse<Vec<MeasuredTemperature>> =
+                reqwest::get(format!("{}/temperature", url))
+                    .await?
+                    .json()
+                    .await?;
+            temperatures_to_batch(&response.data.unwrap_or_default())?
+        }
+        "ups" => {
+            let response: ApiResponse<Vec<UninterruptiblePowerSupplyData>> =
+                reqwest::get(format!("{}/ups", url)).await?.json().await?;
+            upses_to_batch(&response.data.unwrap_or_default())?
+        }
+        _ => return Err(format!("unknown category {}, use temperature or ups", category).into()),
+    };
+    tokio::fs::write(output, write_batch(&batch, format)?).await?;
+    tracing::info!("Exported {} rows to {}", batch.num_rows(), output);
+    Ok(())
+}
+
+#[cfg(not(feature = "export"))]
+pub async fn run_export_command(_: &[String]) -> Result<(), Box<dyn Error>> {
+    Err("this binary was built without export feature".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_export_command() {
+        let args: Vec<String> = vec![String::from("uds"), String::from("export")];
+        assert!(is_export_command(&args));
+        assert!(!is_export_command(&args[..1]));
+    }
+}
@@ -0,0 +1,4 @@
+// Licensed under the Open Software License version 3.0
+#[cfg(feature = "export")]
+pub mod batch;
+pub mod cli;
@@ -0,0 +1,52 @@
+// Licensed under the Open Software License version 3.0
+//! Tokens of nodes, checked the same way by both passive endpoint backends
+use std::collections::BTreeMap;
+
+/// Instance id -> token accepted from that node
+#[derive(Debug, Clone)]
+pub struct NodeTokens(BTreeMap<String, String>);
+
+impl NodeTokens {
+    pub fn new(tokens: BTreeMap<String, String>) -> Self {
+        Self(tokens)
+    }
+
+    /// Instance id the token of `Authorization: Bearer <token>` belongs to
+    pub fn node(&self, authorization: Option<&str>) -> Option<String> {
+        let token = authorization?.strip_prefix("Bearer ")?;
+        self.0
+            .iter()
+            .find(|(_, expected)| *expected == token)
+            .map(|(instance_id, _)| instance_id.clone())
+    }
+}
+
+/// A node can't register or push as another one, even with a valid token
+pub fn check_node(authorized: &str, instance_id: &str) -> Result<(), String> {
+    if authorized != instance_id {
+        return Err(format!(
+            "token of {} can't be used by {}",
+            authorized, instance_id
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node() {
+        let tokens = NodeTokens::new(BTreeMap::from([
+            (String::from("node1"), String::from("secret")),
+            (String::from("node2"), String::from("other")),
+        ]));
+        assert_eq!(tokens.node(Some("Bearer other")).as_deref(), Some("node2"));
+        assert_eq!(tokens.node(Some("Bearer wrong")), None);
+        assert_eq!(tokens.node(Some("other")), None);
+        assert_eq!(tokens.node(None), None);
+        assert!(check_node("node2", "node2").is_ok());
+        assert!(check_node("node2", "node1").is_err());
+    }
+}
@@ -0,0 +1,64 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, time::Duration};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FleetConfig {
+    enabled: Option<bool>,
+    // Instance id -> bearer token of that node, registrations and pushes are rejected if empty
+    tokens: Option<BTreeMap<String, String>>,
+    // Nodes that didn't push for this long are reported as unreachable
+    node_timeout: Option<Duration>,
+    // Nodes that didn't push or register for this long are removed with their data
+    forget_after: Option<Duration>,
+}
+
+impl Default for FleetConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            tokens: None,
+            node_timeout: Some(Duration::from_secs(60)),
+            forget_after: Some(Duration::from_secs(24 * 60 * 60)),
+        }
+    }
+}
+
+impl Example for FleetConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(false),
+            tokens: Some(BTreeMap::from([(
+                String::from("node1"),
+                String::from("change-me"),
+            )])),
+            node_timeout: Some(Duration::from_secs(60)),
+            forget_after: Some(Duration::from_secs(24 * 60 * 60)),
+        }
+    }
+}
+
+impl FleetConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_tokens(&self) -> BTreeMap<String, String> {
+        self.tokens
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(_, token)| !token.is_empty())
+            .collect()
+    }
+
+    pub fn get_node_timeout(&self) -> Duration {
+        self.node_timeout.unwrap_or(Duration::from_secs(60))
+    }
+
+    pub fn get_forget_after(&self) -> Duration {
+        self.forget_after
+            .unwrap_or(Duration::from_secs(24 * 60 * 60))
+    }
+}
@@ -0,0 +1,133 @@
+// Licensed under the Open Software License version 3.0
+//! Request bodies pushed by nodes with `compression` set on their active sender endpoint
+use std::{fmt, io::Read};
+
+/// Why a body couldn't be decoded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// `Content-Encoding` that this binary can't decode
+    Unsupported(String),
+    /// Decoded body is larger than the limit of request bodies
+    TooLarge,
+    /// Not a valid body of its encoding
+    Invalid(String),
+}
+
+impl DecodeError {
+    /// HTTP status of the response
+    pub fn status(&self) -> u16 {
+        match self {
+            DecodeError::Unsupported(_) => 415,
+            DecodeError::TooLarge => 413,
+            DecodeError::Invalid(_) => 400,
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Unsupported(encoding) => {
+                write!(f, "unsupported Content-Encoding: {}", encoding)
+            }
+            DecodeError::TooLarge => write!(f, "decoded body is too large"),
+            DecodeError::Invalid(error) => write!(f, "invalid body: {}", error),
+        }
+    }
+}
+
+/// Read at most `limit` bytes, so a small compressed body can't expand without bounds
+fn read_limited(reader: impl Read, limit: u64) -> Result<Vec<u8>, DecodeError> {
+    let mut decoded = Vec::new();
+    reader
+        .take(limit + 1)
+        .read_to_end(&mut decoded)
+        .map_err(|error| DecodeError::Invalid(error.to_string()))?;
+    if decoded.len() as u64 > limit {
+        return Err(DecodeError::TooLarge);
+    }
+    Ok(decoded)
+}
+
+#[cfg(feature = "zstd")]
+fn zstd(body: &[u8], limit: u64) -> Result<Vec<u8>, DecodeError> {
+    let decoder =
+        zstd::Decoder::new(body).map_err(|error| DecodeError::Invalid(error.to_string()))?;
+    read_limited(decoder, limit)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd(_body: &[u8], _limit: u64) -> Result<Vec<u8>, DecodeError> {
+    Err(DecodeError::Unsupported(String::from(
+        "zstd (this binary was built without zstd feature)",
+    )))
+}
+
+/// Body as sent before compression, `content_encoding` is the value of its header
+pub fn decode_body(
+    body: Vec<u8>,
+    content_encoding: Option<&str>,
+    limit: u64,
+) -> Result<Vec<u8>, DecodeError> {
+    let encoding = content_encoding.map(|encoding| encoding.trim().to_ascii_lowercase());
+    match encoding.as_deref() {
+        None | Some("") | Some("identity") => Ok(body),
+        Some("gzip") | Some("x-gzip") => {
+            read_limited(flate2::read::GzDecoder::new(body.as_slice()), limit)
+        }
+        Some("zstd") => zstd(&body, limit),
+        Some(encoding) => Err(DecodeError::Unsupported(String::from(encoding))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn gzip(body: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(body).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_decode_body() {
+        let body = br#"{"sensors": []}"#.to_vec();
+        assert_eq!(decode_body(body.clone(), None, 1024).unwrap(), body);
+        assert_eq!(
+            decode_body(body.clone(), Some("identity"), 1024).unwrap(),
+            body
+        );
+        assert_eq!(decode_body(gzip(&body), Some("gzip"), 1024).unwrap(), body);
+        assert_eq!(
+            decode_body(body.clone(), Some("br"), 1024),
+            Err(DecodeError::Unsupported(String::from("br")))
+        );
+        assert!(matches!(
+            decode_body(body, Some("gzip"), 1024),
+            Err(DecodeError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_decoded_size_limit() {
+        let body = vec![b'0'; 10_000];
+        assert_eq!(
+            decode_body(gzip(&body), Some("gzip"), 9_999),
+            Err(DecodeError::TooLarge)
+        );
+        assert_eq!(
+            decode_body(gzip(&body), Some("gzip"), 10_000).unwrap(),
+            body
+        );
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_decode_zstd() {
+        let body = b"1234".repeat(100);
+        let compressed = zstd::encode_all(body.as_slice(), 0).unwrap();
+        assert_eq!(decode_body(compressed, Some("zstd"), 1024).unwrap(), body);
+    }
+}
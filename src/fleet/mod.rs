@@ -0,0 +1,19 @@
+// Licensed under the Open Software License version 3.0
+// Only used by passive endpoint backends and tests
+#[cfg_attr(
+    not(any(feature = "passive-endpoint", feature = "axum")),
+    allow(dead_code)
+)]
+pub mod auth;
+#[cfg_attr(
+    not(any(feature = "passive-endpoint", feature = "axum")),
+    allow(dead_code)
+)]
+pub mod config;
+#[cfg(any(feature = "passive-endpoint", feature = "axum"))]
+pub mod encoding;
+#[cfg_attr(
+    not(any(feature = "passive-endpoint", feature = "axum")),
+    allow(dead_code)
+)]
+pub mod registry;
@@ -0,0 +1,392 @@
+// Licensed under the Open Software License version 3.0
+//! Nodes registered with the fleet head and their latest pushed snapshots
+use crate::{
+    hardware::reading::Reading, nut::sender::UninterruptiblePowerSupplyData,
+    one_wire::sender::MeasuredTemperature,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, time::Duration};
+use tokio::sync::{RwLock, RwLockWriteGuard};
+
+/// Sent once by a node, pushing a snapshot registers it too
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Registration {
+    pub instance_id: String,
+    pub name: Option<String>,
+    pub version: Option<String>,
+}
+
+/// Same shape as the active sender payload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct NodeSnapshot {
+    #[serde(default)]
+    pub sensors: Vec<MeasuredTemperature>,
+    #[serde(default)]
+    pub upses: Vec<UninterruptiblePowerSupplyData>,
+    #[serde(default)]
+    pub readings: Vec<Reading>,
+    pub instance_id: String,
+}
+
+impl NodeSnapshot {
+    fn extend(&mut self, part: NodeSnapshot) {
+        self.sensors.extend(part.sensors);
+        self.upses.extend(part.upses);
+        self.readings.extend(part.readings);
+    }
+}
+
+/// Entry of a combined response, tagged with the node it came from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FromNode<T> {
+    pub instance_id: String,
+    #[serde(flatten)]
+    pub data: T,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeStatus {
+    pub instance_id: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub version: Option<String>,
+    pub registered_at: String,
+    pub last_push: Option<String>,
+    // Pushed within node_timeout
+    pub reachable: bool,
+    pub sensors: usize,
+    pub upses: usize,
+    pub readings: usize,
+}
+
+/// Roll-up of every node, answers whether the whole fleet is fine in one request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FleetHealth {
+    pub nodes: usize,
+    pub reachable: usize,
+    // Instance ids of nodes that didn't push within node_timeout
+    pub unreachable: Vec<String>,
+    // Most recent push of any node
+    pub last_push: Option<String>,
+    pub sensors: usize,
+    pub upses: usize,
+    pub readings: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    registration: Registration,
+    registered_at: DateTime<Utc>,
+    last_push: Option<DateTime<Utc>>,
+    // Last registration or push
+    last_seen: DateTime<Utc>,
+    snapshot: NodeSnapshot,
+    // Number of the last received part and parts received so far
+    pending: Option<(usize, NodeSnapshot)>,
+}
+
+impl Node {
+    fn new(registration: Registration, now: DateTime<Utc>) -> Self {
+        let snapshot = NodeSnapshot {
+            instance_id: registration.instance_id.clone(),
+            ..Default::default()
+        };
+        Self {
+            registration,
+            registered_at: now,
+            last_push: None,
+            last_seen: now,
+            snapshot,
+            pending: None,
+        }
+    }
+}
+
+/// Registry shared by the routes of the fleet head
+pub type SharedRegistry = RwLock<FleetRegistry>;
+
+/// Registry without nodes that went silent, locked for writing to forget them
+pub async fn current(registry: &SharedRegistry) -> RwLockWriteGuard<'_, FleetRegistry> {
+    let mut registry = registry.write().await;
+    registry.expire(Utc::now());
+    registry
+}
+
+#[derive(Debug)]
+pub struct FleetRegistry {
+    nodes: BTreeMap<String, Node>,
+    node_timeout: Duration,
+    forget_after: Duration,
+}
+
+impl FleetRegistry {
+    pub fn new(node_timeout: Duration, forget_after: Duration) -> Self {
+        Self {
+            nodes: BTreeMap::new(),
+            node_timeout,
+            forget_after,
+        }
+    }
+
+    /// Remove nodes that didn't register or push for `forget_after`, ex. decommissioned ones
+    pub fn expire(&mut self, now: DateTime<Utc>) {
+        let forget_after = self.forget_after;
+        self.nodes.retain(|instance_id, node| {
+            let silent = now
+                .signed_duration_since(node.last_seen)
+                .to_std()
+                .map_or(false, |age| age > forget_after);
+            if silent {
+                tracing::info!("Forgetting node {} that went silent", instance_id);
+            }
+            !silent
+        });
+    }
+
+    /// Registering again updates name and version, cached data is kept
+    pub fn register(&mut self, registration: Registration, now: DateTime<Utc>) -> NodeStatus {
+        let instance_id = registration.instance_id.clone();
+        match self.nodes.get_mut(&instance_id) {
+            Some(node) => {
+                node.registration = registration;
+                node.last_seen = now;
+            }
+            None => {
+                tracing::info!("Node {} registered", instance_id);
+                self.nodes
+                    .insert(instance_id.clone(), Node::new(registration, now));
+            }
+        }
+        self.status(&instance_id, now).unwrap()
+    }
+
+    /// Replace cached data of the node, `part` is 1-based part number and total number of parts
+    ///
+    /// Parts are kept aside until the last one arrives, so readers never see a partial snapshot
+    pub fn push(
+        &mut self,
+        snapshot: NodeSnapshot,
+        part: Option<(usize, usize)>,
+        now: DateTime<Utc>,
+    ) -> Result<NodeStatus, String> {
+        let instance_id = snapshot.instance_id.clone();
+        if instance_id.is_empty() {
+            return Err(String::from("instance_id is required"));
+        }
+        if !self.nodes.contains_key(&instance_id) {
+            let registration = Registration {
+                instance_id: instance_id.clone(),
+                name: None,
+                version: None,
+            };
+            self.register(registration, now);
+        }
+        let node = self.nodes.get_mut(&instance_id).unwrap();
+        node.last_seen = now;
+        let (part, total) = part.unwrap_or((1, 1));
+        if part == 0 || part > total {
+            return Err(format!("invalid part {} of {}", part, total));
+        }
+        let received = match node.pending.take() {
+            _ if part == 1 => snapshot,
+            Some((last, mut received)) if last + 1 == part => {
+                received.extend(snapshot);
+                received
+            }
+            _ => return Err(format!("part {} of {} arrived out of order", part, total)),
+        };
+        if part == total {
+            node.snapshot = received;
+            node.last_push = Some(now);
+        } else {
+            node.pending = Some((part, received));
+        }
+        Ok(self.status(&instance_id, now).unwrap())
+    }
+
+    pub fn status(&self, instance_id: &str, now: DateTime<Utc>) -> Option<NodeStatus> {
+        let node = self.nodes.get(instance_id)?;
+        let reachable = node.last_push.map_or(false, |last_push| {
+            now.signed_duration_since(last_push)
+                .to_std()
+                .map_or(true, |age| age <= self.node_timeout)
+        });
+        Some(NodeStatus {
+            instance_id: String::from(instance_id),
+            name: node.registration.name.clone(),
+            version: node.registration.version.clone(),
+            registered_at: node.registered_at.to_rfc3339(),
+            last_push: node.last_push.map(|last_push| last_push.to_rfc3339()),
+            reachable,
+            sensors: node.snapshot.sensors.len(),
+            upses: node.snapshot.upses.len(),
+            readings: node.snapshot.readings.len(),
+        })
+    }
+
+    pub fn statuses(&self, now: DateTime<Utc>) -> Vec<NodeStatus> {
+        self.nodes
+            .keys()
+            .filter_map(|instance_id| self.status(instance_id, now))
+            .collect()
+    }
+
+    pub fn health(&self, now: DateTime<Utc>) -> FleetHealth {
+        let statuses = self.statuses(now);
+        let last_push = self.nodes.values().filter_map(|node| node.last_push).max();
+        FleetHealth {
+            nodes: statuses.len(),
+            reachable: statuses.iter().filter(|status| status.reachable).count(),
+            unreachable: statuses
+                .iter()
+                .filter(|status| !status.reachable)
+                .map(|status| status.instance_id.clone())
+                .collect(),
+            last_push: last_push.map(|last_push| last_push.to_rfc3339()),
+            sensors: statuses.iter().map(|status| status.sensors).sum(),
+            upses: statuses.iter().map(|status| status.upses).sum(),
+            readings: statuses.iter().map(|status| status.readings).sum(),
+        }
+    }
+
+    pub fn snapshot(&self, instance_id: &str) -> Option<NodeSnapshot> {
+        self.nodes
+            .get(instance_id)
+            .map(|node| node.snapshot.clone())
+    }
+
+    /// Entries of every node, selected by `category`
+    pub fn combined<T: Clone>(
+        &self,
+        category: impl Fn(&NodeSnapshot) -> &Vec<T>,
+    ) -> Vec<FromNode<T>> {
+        self.nodes
+            .iter()
+            .flat_map(|(instance_id, node)| {
+                category(&node.snapshot)
+                    .iter()
+                    .map(|data| FromNode {
+                        instance_id: instance_id.clone(),
+                        data: data.clone(),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::types::Example;
+
+    fn snapshot(instance_id: &str, sensors: usize) -> NodeSnapshot {
+        NodeSnapshot {
+            sensors: vec![MeasuredTemperature::example(); sensors],
+            instance_id: String::from(instance_id),
+            ..Default::default()
+        }
+    }
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2023-01-01T00:00:00+00:00")
+            .unwrap()
+            .with_timezone(&Utc)
+            + chrono::Duration::seconds(seconds)
+    }
+
+    #[test]
+    fn test_push_registers_node() {
+        let mut registry = FleetRegistry::new(Duration::from_secs(60), Duration::from_secs(3600));
+        let status = registry.push(snapshot("node1", 2), None, at(0)).unwrap();
+        assert!(status.reachable);
+        assert_eq!(status.sensors, 2);
+        assert!(!registry.status("node1", at(61)).unwrap().reachable);
+        let registration = Registration {
+            instance_id: String::from("node1"),
+            name: Some(String::from("garage")),
+            version: None,
+        };
+        // Registering again keeps cached data
+        let status = registry.register(registration, at(1));
+        assert_eq!(status.name.as_deref(), Some("garage"));
+        assert_eq!(status.sensors, 2);
+        assert!(registry.push(snapshot("", 1), None, at(2)).is_err());
+    }
+
+    #[test]
+    fn test_push_parts() {
+        let mut registry = FleetRegistry::new(Duration::from_secs(60), Duration::from_secs(3600));
+        registry.push(snapshot("node1", 1), None, at(0)).unwrap();
+        let status = registry
+            .push(snapshot("node1", 2), Some((1, 2)), at(1))
+            .unwrap();
+        // Previous snapshot is kept until the last part arrives
+        assert_eq!(status.sensors, 1);
+        let status = registry
+            .push(snapshot("node1", 3), Some((2, 2)), at(1))
+            .unwrap();
+        assert_eq!(status.sensors, 5);
+        assert!(registry
+            .push(snapshot("node1", 1), Some((2, 2)), at(2))
+            .is_err());
+        assert!(registry
+            .push(snapshot("node1", 1), Some((3, 2)), at(2))
+            .is_err());
+    }
+
+    #[test]
+    fn test_combined() {
+        let mut registry = FleetRegistry::new(Duration::from_secs(60), Duration::from_secs(3600));
+        registry.push(snapshot("node2", 1), None, at(0)).unwrap();
+        registry.push(snapshot("node1", 2), None, at(0)).unwrap();
+        let sensors = registry.combined(|snapshot| &snapshot.sensors);
+        let ids: Vec<&str> = sensors
+            .iter()
+            .map(|sensor| sensor.instance_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["node1", "node1", "node2"]);
+        let json = serde_json::to_value(&sensors[0]).unwrap();
+        assert_eq!(json["instance_id"], "node1");
+        assert_eq!(json["meta"]["hw"]["id"], "fake_hw_id");
+        assert_eq!(registry.snapshot("node2").unwrap().sensors.len(), 1);
+        assert!(registry.snapshot("node3").is_none());
+    }
+
+    #[test]
+    fn test_health() {
+        let mut registry = FleetRegistry::new(Duration::from_secs(60), Duration::from_secs(3600));
+        registry.push(snapshot("node1", 2), None, at(0)).unwrap();
+        registry.push(snapshot("node2", 1), None, at(30)).unwrap();
+        let registration = Registration {
+            instance_id: String::from("node3"),
+            name: None,
+            version: None,
+        };
+        registry.register(registration, at(30));
+        let health = registry.health(at(61));
+        assert_eq!(health.nodes, 3);
+        assert_eq!(health.reachable, 1);
+        assert_eq!(health.unreachable, vec!["node1", "node3"]);
+        assert_eq!(health.last_push, Some(at(30).to_rfc3339()));
+        assert_eq!(health.sensors, 3);
+        assert_eq!(health.upses, 0);
+    }
+
+    #[test]
+    fn test_expire() {
+        let mut registry = FleetRegistry::new(Duration::from_secs(60), Duration::from_secs(3600));
+        registry.push(snapshot("node1", 1), None, at(0)).unwrap();
+        registry.push(snapshot("node2", 1), None, at(0)).unwrap();
+        registry.push(snapshot("node2", 1), None, at(3000)).unwrap();
+        registry.expire(at(3600));
+        assert_eq!(registry.statuses(at(3600)).len(), 2);
+        registry.expire(at(3601));
+        let statuses = registry.statuses(at(3601));
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].instance_id, "node2");
+        assert!(registry.snapshot("node1").is_none());
+    }
+}
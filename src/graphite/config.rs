@@ -0,0 +1,57 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphiteConfig {
+    enabled: Option<bool>,
+    // Carbon plaintext receiver as host:port
+    server: Option<String>,
+    // Prepended to every metric path, ex. "uds.temperature.<id>"
+    prefix: Option<String>,
+    // Latest metrics are sent at most this often
+    interval: Option<Duration>,
+}
+
+impl Default for GraphiteConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            server: Some(String::from("127.0.0.1:2003")),
+            prefix: Some(String::from("uds")),
+            interval: Some(Duration::from_secs(60)),
+        }
+    }
+}
+
+impl Example for GraphiteConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(false),
+            server: Some(String::from("graphite.lan:2003")),
+            prefix: Some(String::from("home.uds")),
+            interval: Some(Duration::from_secs(60)),
+        }
+    }
+}
+
+impl GraphiteConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_server(&self) -> String {
+        self.server
+            .clone()
+            .unwrap_or_else(|| String::from("127.0.0.1:2003"))
+    }
+
+    pub fn get_prefix(&self) -> String {
+        self.prefix.clone().unwrap_or_else(|| String::from("uds"))
+    }
+
+    pub fn get_interval(&self) -> Duration {
+        self.interval.unwrap_or(Duration::from_secs(60))
+    }
+}
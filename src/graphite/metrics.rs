@@ -0,0 +1,196 @@
+// Licensed under the Open Software License version 3.0
+//! Graphite plaintext metrics with paths built from hw.ids
+use crate::{
+    hardware::{reading::Reading, types::HardwareMetadata},
+    nut::sender::UninterruptiblePowerSupplyData,
+    one_wire::sender::MeasuredTemperature,
+};
+use chrono::{DateTime, Utc};
+
+/// `<path> <value> <timestamp>` line of the plaintext protocol
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metric {
+    pub path: String,
+    pub value: f64,
+    pub timestamp: i64,
+}
+
+impl Metric {
+    pub fn line(&self) -> String {
+        format!("{} {} {}\n", self.path, self.value, self.timestamp)
+    }
+}
+
+/// Single path node, anything other than letters, digits, `-` and `_` becomes `_`
+///
+/// Dots would split an id into several nodes, whitespace would end the path
+pub fn safe_node(node: &str) -> String {
+    let node: String = node
+        .chars()
+        .map(|character| match character {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => character,
+            _ => '_',
+        })
+        .collect();
+    match node.is_empty() {
+        true => String::from("_"),
+        false => node,
+    }
+}
+
+/// Nodes separated by dots (ex. prefix or NUT variable name), empty ones are dropped
+pub fn safe_path(path: &str) -> String {
+    path.split('.')
+        .filter(|node| !node.is_empty())
+        .map(safe_node)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn join(nodes: &[&str]) -> String {
+    nodes
+        .iter()
+        .filter(|node| !node.is_empty())
+        .copied()
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Time of the read, or `now` for computed values
+fn timestamp(meta: &HardwareMetadata, now: DateTime<Utc>) -> i64 {
+    meta.measured_at
+        .as_deref()
+        .and_then(|measured_at| DateTime::parse_from_rfc3339(measured_at).ok())
+        .map_or(now.timestamp(), |measured_at| measured_at.timestamp())
+}
+
+/// Graphite can't store NaN or infinity
+fn metric(path: String, value: f64, timestamp: i64) -> Option<Metric> {
+    value.is_finite().then_some(Metric {
+        path,
+        value,
+        timestamp,
+    })
+}
+
+/// `<prefix>.temperature.<id>`
+pub fn temperature_metrics(
+    prefix: &str,
+    sensors: &[MeasuredTemperature],
+    now: DateTime<Utc>,
+) -> Vec<Metric> {
+    let prefix = safe_path(prefix);
+    sensors
+        .iter()
+        .filter_map(|sensor| {
+            let path = join(&[&prefix, "temperature", &safe_node(&sensor.meta.hw.id)]);
+            metric(path, sensor.temperature?, timestamp(&sensor.meta, now))
+        })
+        .collect()
+}
+
+/// `<prefix>.ups.<id>.<variable>`, only numeric variables
+pub fn ups_metrics(
+    prefix: &str,
+    upses: &[UninterruptiblePowerSupplyData],
+    now: DateTime<Utc>,
+) -> Vec<Metric> {
+    let prefix = safe_path(prefix);
+    upses
+        .iter()
+        .flat_map(|ups| {
+            let id = safe_node(&ups.meta.hw.id);
+            let timestamp = timestamp(&ups.meta, now);
+            let mut metrics: Vec<Metric> = ups
+                .variables
+                .iter()
+                .filter_map(|(variable, value)| {
+                    let path = join(&[&prefix, "ups", &id, &safe_path(variable)]);
+                    metric(path, value.trim().parse().ok()?, timestamp)
+                })
+                .collect();
+            // Stable order makes batches comparable
+            metrics.sort_by(|a, b| a.path.cmp(&b.path));
+            metrics
+        })
+        .collect()
+}
+
+/// `<prefix>.readings.<id>.<value name>`
+pub fn reading_metrics(prefix: &str, readings: &[Reading], now: DateTime<Utc>) -> Vec<Metric> {
+    let prefix = safe_path(prefix);
+    readings
+        .iter()
+        .flat_map(|reading| {
+            let id = safe_node(&reading.meta.hw.id);
+            let timestamp = timestamp(&reading.meta, now);
+            reading
+                .values
+                .iter()
+                .filter_map(|(name, value)| {
+                    let path = join(&[&prefix, "readings", &id, &safe_node(name)]);
+                    metric(path, *value, timestamp)
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::types::Example;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2023-01-01T00:00:00+00:00")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_safe_paths() {
+        assert_eq!(safe_node("28-00000a0b0c0d"), "28-00000a0b0c0d");
+        assert_eq!(
+            safe_node("[ups1]ups-monitor@localhost:3493"),
+            "_ups1_ups-monitor_localhost_3493"
+        );
+        assert_eq!(safe_node("Package id 0"), "Package_id_0");
+        assert_eq!(safe_node(""), "_");
+        assert_eq!(safe_path(".home..uds."), "home.uds");
+        assert_eq!(safe_path("battery.charge"), "battery.charge");
+    }
+
+    #[test]
+    fn test_temperature_metrics() {
+        let mut sensor = MeasuredTemperature::example();
+        sensor.meta.measured_at = Some(String::from("2023-01-01T00:00:05+00:00"));
+        sensor.temperature = Some(21.5);
+        let metrics = temperature_metrics("home.uds", &[sensor], now());
+        assert_eq!(
+            metrics[0].line(),
+            format!(
+                "home.uds.temperature.fake_hw_id 21.5 {}\n",
+                now().timestamp() + 5
+            )
+        );
+        let mut sensor = MeasuredTemperature::example();
+        sensor.temperature = Some(f64::NAN);
+        assert!(temperature_metrics("uds", &[sensor], now()).is_empty());
+    }
+
+    #[test]
+    fn test_ups_and_reading_metrics() {
+        let metrics = ups_metrics("", &[UninterruptiblePowerSupplyData::example()], now());
+        assert!(metrics
+            .iter()
+            .any(|metric| metric.path == "ups.fake_hw_id.ups.load" && metric.value == 15.0));
+        // ups.status isn't numeric
+        assert!(metrics
+            .iter()
+            .all(|metric| metric.path != "ups.fake_hw_id.ups.status"));
+
+        let metrics = reading_metrics("uds", &[Reading::example()], now());
+        assert_eq!(metrics[0].path, "uds.readings.fake_hw_id.rss_bytes");
+        assert_eq!(metrics[0].timestamp, now().timestamp());
+    }
+}
@@ -0,0 +1,53 @@
+// Licensed under the Open Software License version 3.0
+//! Carbon plaintext protocol over TCP
+use super::metrics::Metric;
+use std::time::Duration;
+use tokio::{io::AsyncWriteExt, net::TcpStream, time::timeout};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+async fn write_lines(server: &str, lines: &[u8]) -> std::io::Result<()> {
+    let mut stream = TcpStream::connect(server).await?;
+    stream.write_all(lines).await?;
+    stream.shutdown().await
+}
+
+/// Send metrics over a new connection, carbon doesn't respond
+pub async fn send_metrics(server: &str, metrics: &[Metric]) -> Result<(), String> {
+    let lines: String = metrics.iter().map(Metric::line).collect();
+    crate::bandwidth::limiter::acquire("graphite", lines.len()).await;
+    timeout(TIMEOUT, write_lines(server, lines.as_bytes()))
+        .await
+        .map_err(|_| String::from("timed out"))?
+        .map_err(|error| error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{io::AsyncReadExt, net::TcpListener};
+
+    #[tokio::test]
+    async fn test_send_metrics() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server = listener.local_addr().unwrap().to_string();
+        let carbon = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut lines = String::new();
+            stream.read_to_string(&mut lines).await.unwrap();
+            lines
+        });
+        let metric = Metric {
+            path: String::from("uds.temperature.28-00000a0b0c0d"),
+            value: 21.5,
+            timestamp: 1672531200,
+        };
+        send_metrics(&server, &[metric.clone(), metric])
+            .await
+            .unwrap();
+        assert_eq!(
+            carbon.await.unwrap(),
+            "uds.temperature.28-00000a0b0c0d 21.5 1672531200\n".repeat(2)
+        );
+    }
+}
@@ -0,0 +1,81 @@
+// Licensed under the Open Software License version 3.0
+use super::{
+    config::GraphiteConfig,
+    metrics::{reading_metrics, temperature_metrics, ups_metrics, Metric},
+    protocol::send_metrics,
+};
+use crate::{
+    dedup_log::{info_resolved, warn_deduplicated},
+    hardware::reading::{ReadingsByPublisher, ReadingsUpdate},
+    introspection,
+    nut::sender::{UpsUpdate, UpsesByPublisher},
+    one_wire::sender::MeasuredTemperature,
+    self_metrics::lag::recv_counting_lag,
+};
+use chrono::Utc;
+use std::{cmp::max, time::Duration};
+use tokio::{sync::broadcast, time::interval};
+
+/// Latest data of every category, flushed as metrics on every interval
+#[derive(Debug, Default)]
+struct Latest {
+    sensors: Vec<MeasuredTemperature>,
+    upses: UpsesByPublisher,
+    readings: ReadingsByPublisher,
+}
+
+impl Latest {
+    fn metrics(&self, prefix: &str) -> Vec<Metric> {
+        let now = Utc::now();
+        let mut metrics = temperature_metrics(prefix, &self.sensors, now);
+        metrics.extend(ups_metrics(prefix, &self.upses.all(), now));
+        metrics.extend(reading_metrics(prefix, &self.readings.all(), now));
+        metrics
+    }
+}
+
+async fn flush(server: &str, metrics: &[Metric]) {
+    if metrics.is_empty() {
+        return;
+    }
+    let key = format!("graphite:{}", server);
+    match send_metrics(server, metrics).await {
+        Ok(()) => info_resolved!(key, "Graphite server {} is reachable again", server),
+        Err(error) => warn_deduplicated!(key, "Failed to send metrics to {}: {}", server, error),
+    }
+}
+
+pub async fn start_graphite_sink_loop(
+    mut shutdown_rx: broadcast::Receiver<()>,
+    config: GraphiteConfig,
+    mut one_wire_rx: broadcast::Receiver<Vec<MeasuredTemperature>>,
+    mut ups_monitoring_rx: broadcast::Receiver<UpsUpdate>,
+    mut readings_rx: broadcast::Receiver<ReadingsUpdate>,
+) {
+    // Check if module is enabled
+    if !config.is_enabled() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+    let server = config.get_server();
+    let prefix = config.get_prefix();
+    let mut latest = Latest::default();
+    let mut flush_interval = interval(max(config.get_interval(), Duration::from_secs(1)));
+    tracing::debug!("Starting Graphite sink loop");
+    let _task = introspection::task_started("graphite");
+    loop {
+        tokio::select! {
+            Ok(sensors) = recv_counting_lag(&mut one_wire_rx) => latest.sensors = sensors,
+            Ok(update) = recv_counting_lag(&mut ups_monitoring_rx) => latest.upses.update(update),
+            Ok(update) = recv_counting_lag(&mut readings_rx) => latest.readings.update(update),
+            _ = flush_interval.tick() => {
+                introspection::mark_iteration("graphite");
+                flush(&server, &latest.metrics(&prefix)).await;
+            }
+            _ = shutdown_rx.recv() => {
+                tracing::trace!("Shutting down Graphite sink loop");
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,37 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GrpcConfig {
+    enabled: Option<bool>,
+    port: Option<u16>,
+}
+
+impl Default for GrpcConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            port: Some(63624),
+        }
+    }
+}
+
+impl Example for GrpcConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(false),
+            port: Some(63624),
+        }
+    }
+}
+
+impl GrpcConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_port(&self) -> u16 {
+        self.port.unwrap_or(63624)
+    }
+}
@@ -0,0 +1,7 @@
+// Licensed under the Open Software License version 3.0
+pub mod config;
+#[cfg(feature = "grpc")]
+mod proto {
+    tonic::include_proto!("universal_data_source");
+}
+pub mod server;
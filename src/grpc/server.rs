@@ -0,0 +1,219 @@
+// Licensed under the Open Software License version 3.0
+use super::config::GrpcConfig;
+use crate::{nut::sender::UpsUpdate, one_wire::sender::MeasuredTemperature};
+use tokio::sync::broadcast;
+
+#[cfg(feature = "grpc")]
+mod service {
+    use super::super::proto::{
+        self,
+        universal_data_source_server::{UniversalDataSource, UniversalDataSourceServer},
+        GetSnapshotRequest, Snapshot, SubscribeRequest,
+    };
+    use super::*;
+    use crate::{
+        hardware::types::HardwareMetadata,
+        nut::sender::{UninterruptiblePowerSupplyData, UpsesByPublisher},
+        self_metrics::lag::recv_counting_lag,
+    };
+    use std::{
+        net::{Ipv6Addr, SocketAddr},
+        pin::Pin,
+    };
+    use tokio::sync::watch;
+    use tokio_stream::{wrappers::WatchStream, Stream, StreamExt};
+    use tonic::{transport::Server, Request, Response, Status};
+
+    impl From<&HardwareMetadata> for proto::HardwareMetadata {
+        fn from(meta: &HardwareMetadata) -> Self {
+            Self {
+                id: meta.hw.id.clone(),
+                hardware_type: format!("{:?}", meta.hw.hardware_type),
+                source_type: format!("{:?}", meta.source.source_type),
+                name: meta.hw.name.clone(),
+                relations: meta
+                    .relations
+                    .iter()
+                    .flatten()
+                    .map(|relation| proto::HardwareRelation {
+                        id: relation.id.clone(),
+                        kind: relation.kind.clone(),
+                    })
+                    .collect(),
+                measured_at: meta.measured_at.clone(),
+            }
+        }
+    }
+
+    impl From<&MeasuredTemperature> for proto::MeasuredTemperature {
+        fn from(sensor: &MeasuredTemperature) -> Self {
+            Self {
+                meta: Some((&sensor.meta).into()),
+                temperature: sensor.temperature,
+                resolution: sensor.resolution.map(u32::from),
+            }
+        }
+    }
+
+    impl From<&UninterruptiblePowerSupplyData> for proto::UninterruptiblePowerSupplyData {
+        fn from(ups: &UninterruptiblePowerSupplyData) -> Self {
+            Self {
+                meta: Some((&ups.meta).into()),
+                variables: ups.variables.clone(),
+                clients: ups.clients.clone().unwrap_or_default(),
+            }
+        }
+    }
+
+    pub struct SnapshotService {
+        snapshot_rx: watch::Receiver<Snapshot>,
+    }
+
+    #[tonic::async_trait]
+    impl UniversalDataSource for SnapshotService {
+        type SubscribeStream = Pin<Box<dyn Stream<Item = Result<Snapshot, Status>> + Send>>;
+
+        async fn get_snapshot(
+            &self,
+            _: Request<GetSnapshotRequest>,
+        ) -> Result<Response<Snapshot>, Status> {
+            Ok(Response::new(self.snapshot_rx.borrow().clone()))
+        }
+
+        async fn subscribe(
+            &self,
+            _: Request<SubscribeRequest>,
+        ) -> Result<Response<Self::SubscribeStream>, Status> {
+            let stream = WatchStream::new(self.snapshot_rx.clone()).map(Ok);
+            Ok(Response::new(Box::pin(stream)))
+        }
+    }
+
+    pub async fn serve(
+        mut shutdown_rx: broadcast::Receiver<()>,
+        config: GrpcConfig,
+        mut one_wire_rx: broadcast::Receiver<Vec<MeasuredTemperature>>,
+        mut ups_monitoring_rx: broadcast::Receiver<UpsUpdate>,
+        instance_id: String,
+    ) {
+        let (snapshot_tx, snapshot_rx) = watch::channel(Snapshot {
+            instance_id,
+            ..Default::default()
+        });
+        let service = SnapshotService { snapshot_rx };
+        let address = SocketAddr::from((Ipv6Addr::UNSPECIFIED, config.get_port()));
+
+        let mut shutdown_rx_clone = shutdown_rx.resubscribe();
+        let server_handle = tokio::spawn(async move {
+            tracing::debug!("Starting gRPC server on {}", address);
+            let result = Server::builder()
+                .add_service(UniversalDataSourceServer::new(service))
+                .serve_with_shutdown(address, async move {
+                    let _ = shutdown_rx_clone.recv().await;
+                })
+                .await;
+            if let Err(error) = result {
+                tracing::error!("gRPC server failed: {}", error);
+            }
+        });
+
+        // Keep snapshot up to date
+        let snapshot_updater_handle = tokio::spawn(async move {
+            let mut upses = UpsesByPublisher::default();
+            loop {
+                tokio::select! {
+                    Ok(value) = recv_counting_lag(&mut one_wire_rx) => {
+                        snapshot_tx.send_modify(|snapshot| {
+                            snapshot.sensors = value.iter().map(Into::into).collect();
+                        });
+                    }
+                    Ok(update) = recv_counting_lag(&mut ups_monitoring_rx) => {
+                        upses.update(update);
+                        snapshot_tx.send_modify(|snapshot| {
+                            snapshot.upses = upses.all().iter().map(Into::into).collect();
+                        });
+                    }
+                    _ = shutdown_rx.recv() => {
+                        tracing::trace!("Shutting down gRPC snapshot updater");
+                        break;
+                    }
+                }
+            }
+        });
+
+        let _ = tokio::try_join!(server_handle, snapshot_updater_handle);
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::config::types::Example;
+
+        #[test]
+        fn test_convert_measured_temperature() {
+            let sensor = MeasuredTemperature::example();
+            let converted = proto::MeasuredTemperature::from(&sensor);
+            let meta = converted.meta.unwrap();
+            assert_eq!(meta.id, sensor.meta.hw.id);
+            assert_eq!(meta.hardware_type, "TemperatureSensor");
+            assert_eq!(meta.source_type, "OneWire");
+            assert_eq!(converted.temperature, sensor.temperature);
+            assert_eq!(converted.resolution, Some(12));
+        }
+
+        #[tokio::test]
+        async fn test_subscribe_receives_updates() {
+            let (snapshot_tx, snapshot_rx) = watch::channel(Snapshot::default());
+            let service = SnapshotService { snapshot_rx };
+            let mut stream = service
+                .subscribe(Request::new(SubscribeRequest {}))
+                .await
+                .unwrap()
+                .into_inner();
+            // Current snapshot is sent first
+            assert!(stream.next().await.unwrap().unwrap().upses.is_empty());
+
+            let ups = UninterruptiblePowerSupplyData::example();
+            snapshot_tx.send_modify(|snapshot| snapshot.upses = vec![(&ups).into()]);
+            let snapshot = stream.next().await.unwrap().unwrap();
+            assert_eq!(snapshot.upses.len(), 1);
+
+            let snapshot = service
+                .get_snapshot(Request::new(GetSnapshotRequest {}))
+                .await
+                .unwrap()
+                .into_inner();
+            assert_eq!(snapshot.upses.len(), 1);
+        }
+    }
+}
+
+pub async fn start_grpc_server_loop(
+    shutdown_rx: broadcast::Receiver<()>,
+    config: GrpcConfig,
+    one_wire_rx: broadcast::Receiver<Vec<MeasuredTemperature>>,
+    ups_monitoring_rx: broadcast::Receiver<UpsUpdate>,
+    instance_id: String,
+) {
+    // Check if module is enabled
+    if !config.is_enabled() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+
+    #[cfg(feature = "grpc")]
+    service::serve(
+        shutdown_rx,
+        config,
+        one_wire_rx,
+        ups_monitoring_rx,
+        instance_id,
+    )
+    .await;
+
+    #[cfg(not(feature = "grpc"))]
+    {
+        let _ = (shutdown_rx, one_wire_rx, ups_monitoring_rx, instance_id);
+        tracing::error!("gRPC is enabled in config but this binary was built without grpc feature");
+    }
+}
@@ -0,0 +1,66 @@
+// Licensed under the Open Software License version 3.0
+//! Duplicate hw.ids within a single snapshot, ex. the same sensor visible twice through symlinks
+use super::types::HardwareMetadata;
+use crate::{
+    dedup_log::{info_resolved, warn_deduplicated},
+    introspection,
+};
+use std::collections::HashSet;
+
+/// Keep the first entry of every hw.id, returns repeated ids in order of appearance
+///
+/// Input order has to be deterministic (ex. sorted paths) for the result to be.
+pub fn remove_duplicates<T>(
+    items: &mut Vec<T>,
+    meta: impl Fn(&T) -> &HardwareMetadata,
+) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut duplicates = Vec::new();
+    items.retain(|item| {
+        let id = &meta(item).hw.id;
+        if seen.insert(id.clone()) {
+            return true;
+        }
+        if !duplicates.contains(id) {
+            duplicates.push(id.clone());
+        }
+        false
+    });
+    duplicates
+}
+
+/// Log conflicts of `category` and list them in `/status/internal`
+pub fn report_duplicates(category: &str, duplicates: &[String]) {
+    let key = format!("duplicates:{}", category);
+    if duplicates.is_empty() {
+        info_resolved!(key, "No more duplicate {} ids", category);
+    } else {
+        warn_deduplicated!(
+            key,
+            "Duplicate {} ids in one snapshot, keeping the first of each: {}",
+            category,
+            duplicates.join(", ")
+        );
+    }
+    introspection::set_duplicates(category, duplicates);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::types::Example, hardware::reading::Reading};
+
+    #[test]
+    fn test_remove_duplicates_keeps_first() {
+        let mut first = Reading::example();
+        first.values.insert(String::from("order"), 1.0);
+        let mut second = Reading::example();
+        second.values.insert(String::from("order"), 2.0);
+        let mut other = Reading::example();
+        other.meta.hw.id = String::from("other");
+        let mut readings = vec![first.clone(), other.clone(), second.clone(), second];
+        let duplicates = remove_duplicates(&mut readings, |reading| &reading.meta);
+        assert_eq!(readings, vec![first, other]);
+        assert_eq!(duplicates, vec![String::from("fake_hw_id")]);
+    }
+}
@@ -0,0 +1,56 @@
+// Licensed under the Open Software License version 3.0
+use super::{
+    reading::Reading,
+    types::{HardwareMetadata, HardwareType, SourceType},
+};
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+
+/// Temperature (°C) and relative humidity (%) read together from a single sensor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MeasuredHumidity {
+    pub meta: HardwareMetadata,
+    pub temperature: Option<f64>,
+    pub humidity: Option<f64>,
+}
+
+impl Example for MeasuredHumidity {
+    /// Create an instance of `MeasuredHumidity` for internal testing
+    ///
+    /// Default `temperature` is 21.5
+    ///
+    /// Default `humidity` is 45
+    fn example() -> Self {
+        Self {
+            meta: HardwareMetadata::new(
+                String::from("fake_hw_id"),
+                HardwareType::HumiditySensor,
+                SourceType::Dht,
+            ),
+            temperature: Some(21.5),
+            humidity: Some(45.0),
+        }
+    }
+}
+
+/// Published as a reading, so every sink handles it like other generic sources
+impl From<MeasuredHumidity> for Reading {
+    fn from(measured: MeasuredHumidity) -> Self {
+        Reading::new(measured.meta)
+            .with_value("temperature", measured.temperature)
+            .with_value("humidity", measured.humidity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_into_reading() {
+        let reading = Reading::from(MeasuredHumidity::example());
+        assert_eq!(reading.values["temperature"], 21.5);
+        assert_eq!(reading.values["humidity"], 45.0);
+        assert_eq!(reading.meta.hw.hardware_type, HardwareType::HumiditySensor);
+    }
+}
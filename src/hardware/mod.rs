@@ -1,2 +1,7 @@
 // Licensed under the Open Software License version 3.0
+// Used by one_wire and nut
+#[cfg_attr(not(any(feature = "one-wire", feature = "nut")), allow(dead_code))]
+pub mod duplicates;
+pub mod humidity;
+pub mod reading;
 pub mod types;
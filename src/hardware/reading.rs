@@ -0,0 +1,95 @@
+// Licensed under the Open Software License version 3.0
+use super::types::{HardwareMetadata, HardwareType, SourceType};
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Generic set of named numeric values read from a single piece of hardware
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reading {
+    pub meta: HardwareMetadata,
+    pub values: BTreeMap<String, f64>,
+}
+
+impl Example for Reading {
+    /// Create an instance of `Reading` for internal testing
+    ///
+    /// Default `rss_bytes` is 1024
+    fn example() -> Self {
+        Self::new(HardwareMetadata::new(
+            String::from("fake_hw_id"),
+            HardwareType::Daemon,
+            SourceType::SelfMetrics,
+        ))
+        .with_value("rss_bytes", Some(1024.0))
+    }
+}
+
+impl Reading {
+    pub fn new(meta: HardwareMetadata) -> Self {
+        Self {
+            meta,
+            values: BTreeMap::new(),
+        }
+    }
+
+    /// Add value if it was read successfully
+    pub fn with_value(mut self, key: &str, value: Option<f64>) -> Self {
+        if let Some(value) = value {
+            self.values.insert(String::from(key), value);
+        }
+        self
+    }
+}
+
+/// All readings from a single publisher, replacing its previous update
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReadingsUpdate {
+    pub publisher: String,
+    pub readings: Vec<Reading>,
+}
+
+impl ReadingsUpdate {
+    pub fn new(publisher: &str, readings: Vec<Reading>) -> Self {
+        Self {
+            publisher: String::from(publisher),
+            readings,
+        }
+    }
+}
+
+/// Latest readings of every publisher
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ReadingsByPublisher(BTreeMap<String, Vec<Reading>>);
+
+impl ReadingsByPublisher {
+    pub fn update(&mut self, update: ReadingsUpdate) {
+        self.0.insert(update.publisher, update.readings);
+    }
+
+    pub fn all(&self) -> Vec<Reading> {
+        self.0.values().flatten().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_value_skips_missing() {
+        let reading = Reading::example().with_value("open_fds", None);
+        assert_eq!(reading.values.len(), 1);
+    }
+
+    #[test]
+    fn test_readings_by_publisher() {
+        let mut readings = ReadingsByPublisher::default();
+        readings.update(ReadingsUpdate::new("a", vec![Reading::example()]));
+        readings.update(ReadingsUpdate::new("b", vec![Reading::example()]));
+        assert_eq!(readings.all().len(), 2);
+        // Newer update replaces readings of the same publisher
+        readings.update(ReadingsUpdate::new("a", vec![]));
+        assert_eq!(readings.all().len(), 1);
+    }
+}
@@ -1,27 +1,80 @@
 // Licensed under the Open Software License version 3.0
+use crate::clock::rebase_on_steps;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SourceType {
     OneWire,
     NetworkUpsTools,
+    SelfMetrics,
+    // Uplinks from The Things Network
+    LoRaWan,
+    // Kernel thermal zones in /sys/class/thermal
+    ThermalZone,
+    // UPS read directly over USB HID, without upsd
+    UsbHid,
+    // UPS monitored by apcupsd, read over its Network Information Server
+    Apcupsd,
+    // UPS management card read over SNMP (UPS-MIB)
+    Snmp,
+    // Kernel hardware monitoring chips in /sys/class/hwmon
+    Hwmon,
+    // DHT22 / AM2302 sensors on GPIO pins
+    Dht,
+    // Environmental sensors on /dev/i2c-* buses
+    I2c,
+    // Disks read with smartctl
+    Smart,
+    // Registers of Modbus TCP or RTU devices
+    Modbus,
+    // SDR sensors of a BMC read with ipmitool or FreeIPMI
+    Ipmi,
+    // CPU frequency scaling and thermal throttle counters in /sys/devices/system/cpu
+    CpuFreq,
+    // Computed from other sources
+    Derived,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum HardwareType {
     TemperatureSensor,
     UninterruptiblePowerSupply,
+    Daemon,
+    // Battery powered node reporting over radio
+    RemoteSensor,
+    // Monitoring chip with temperature, fan and voltage inputs
+    SensorChip,
+    // Temperature and relative humidity sensor
+    HumiditySensor,
+    // Any of temperature, relative humidity and pressure
+    EnvironmentalSensor,
+    // Disk with SMART temperature and health attributes
+    StorageDevice,
+    // Energy meter, temperature controller or other device on an industrial bus
+    IndustrialDevice,
+    // Server with temperatures, fans, voltages and PSUs reported by its BMC
+    Server,
+    // CPU core with its frequency and throttle counters
+    Processor,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct HardwareInfo {
     pub id: String,
     pub hardware_type: HardwareType,
+    // Friendly name from config, omitted if not set
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
 }
 
 impl HardwareInfo {
     pub fn new(id: String, hardware_type: HardwareType) -> Self {
-        Self { id, hardware_type }
+        Self {
+            id,
+            hardware_type,
+            name: None,
+        }
     }
 }
 
@@ -36,10 +89,36 @@ impl SourceInfo {
     }
 }
 
+/// Link to another piece of hardware declared in config
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HardwareRelation {
+    pub id: String,
+    pub kind: String,
+}
+
+#[derive(Debug, Clone, Eq, Serialize, Deserialize)]
 pub struct HardwareMetadata {
     pub hw: HardwareInfo,
     pub source: SourceInfo,
+    // Related hardware from config, omitted if there is none
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub relations: Option<Vec<HardwareRelation>>,
+    // UTC time of the read (RFC 3339), omitted for computed values
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub measured_at: Option<String>,
+    // Seconds since measured_at, only set by the passive endpoint when responding
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub age_secs: Option<u64>,
+    // Older than max_age of the passive endpoint, only set when responding
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub stale: Option<bool>,
+}
+
+/// Timestamps are ignored, so reading the same value again isn't reported as a change
+impl PartialEq for HardwareMetadata {
+    fn eq(&self, other: &Self) -> bool {
+        self.hw == other.hw && self.source == other.source && self.relations == other.relations
+    }
 }
 
 impl HardwareMetadata {
@@ -47,6 +126,56 @@ impl HardwareMetadata {
         Self {
             hw: HardwareInfo::new(id, hardware_type),
             source: SourceInfo::new(source_type),
+            relations: None,
+            measured_at: None,
+            age_secs: None,
+            stale: None,
         }
     }
+
+    /// Set `measured_at` to current time, call it right after reading the hardware
+    pub fn measured_now(mut self) -> Self {
+        self.measured_at = Some(Utc::now().to_rfc3339());
+        self
+    }
+
+    /// Fill `age_secs` from `measured_at`, clock going backwards counts as fresh data
+    ///
+    /// Forward steps of the clock after the measurement don't count, see `clock`
+    pub fn set_age(&mut self, now: DateTime<Utc>) {
+        self.age_secs = self
+            .measured_at
+            .as_deref()
+            .and_then(|measured_at| DateTime::parse_from_rfc3339(measured_at).ok())
+            .map(|measured_at| {
+                let measured_at = rebase_on_steps(measured_at.with_timezone(&Utc));
+                let age = now.signed_duration_since(measured_at);
+                age.num_seconds().max(0) as u64
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_age() {
+        let mut meta = HardwareMetadata::new(
+            String::from("fake_hw_id"),
+            HardwareType::TemperatureSensor,
+            SourceType::OneWire,
+        );
+        let now = DateTime::parse_from_rfc3339("2023-01-01T00:01:30+00:00")
+            .unwrap()
+            .with_timezone(&Utc);
+        meta.set_age(now);
+        assert_eq!(meta.age_secs, None);
+        meta.measured_at = Some(String::from("2023-01-01T00:00:00+00:00"));
+        meta.set_age(now);
+        assert_eq!(meta.age_secs, Some(90));
+        // Same hardware read at another time is equal
+        let measured_again = meta.clone().measured_now();
+        assert_eq!(measured_again, meta);
+    }
 }
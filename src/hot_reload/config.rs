@@ -0,0 +1,47 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HotReloadConfig {
+    enabled: Option<bool>,
+    // Reload when the file changes, otherwise only on signals with the `reload` action
+    watch_file: Option<bool>,
+    // How often to check the config file for changes
+    check_interval: Option<Duration>,
+}
+
+impl Default for HotReloadConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            watch_file: Some(true),
+            check_interval: Some(Duration::from_secs(2)),
+        }
+    }
+}
+
+impl Example for HotReloadConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(false),
+            watch_file: Some(true),
+            check_interval: Some(Duration::from_secs(2)),
+        }
+    }
+}
+
+impl HotReloadConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn is_watch_file(&self) -> bool {
+        self.watch_file.unwrap_or(true)
+    }
+
+    pub fn get_check_interval(&self) -> Duration {
+        self.check_interval.unwrap_or(Duration::from_secs(2))
+    }
+}
@@ -0,0 +1,5 @@
+// Licensed under the Open Software License version 3.0
+//! Opt-in reload of the config file without restarting the process
+pub mod config;
+pub mod supervisor;
+pub mod watcher;
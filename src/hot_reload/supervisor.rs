@@ -0,0 +1,217 @@
+// Licensed under the Open Software License version 3.0
+use crate::{config::types::Config, startup::StartupHandle, watchdog::memory::accounted};
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex, OnceLock},
+    time::Duration,
+};
+use tokio::{
+    sync::{broadcast, watch, Notify},
+    task::JoinHandle,
+    time::timeout,
+};
+
+/// Time given to a loop to stop on its own when it's restarted on request
+const RESTART_TIMEOUT: Duration = Duration::from_secs(10);
+
+async fn stop(shutdown_tx: &broadcast::Sender<()>, task: Option<JoinHandle<()>>) {
+    let _ = shutdown_tx.send(());
+    if let Some(task) = task {
+        let _ = task.await;
+    }
+}
+
+/// Loops check for shutdown between polls, so a loop stuck in a poll is aborted
+async fn stop_or_abort(shutdown_tx: &broadcast::Sender<()>, task: Option<JoinHandle<()>>) {
+    let _ = shutdown_tx.send(());
+    if let Some(mut task) = task {
+        if timeout(RESTART_TIMEOUT, &mut task).await.is_err() {
+            task.abort();
+            let _ = task.await;
+        }
+    }
+}
+
+/// Module name -> notified to restart the module
+fn restart_requests() -> &'static Mutex<HashMap<&'static str, Arc<Notify>>> {
+    static RESTART_REQUESTS: OnceLock<Mutex<HashMap<&'static str, Arc<Notify>>>> = OnceLock::new();
+    RESTART_REQUESTS.get_or_init(Mutex::default)
+}
+
+/// Restart a supervised module with its current config, `false` if there's no such module
+pub fn request_module_restart(name: &str) -> bool {
+    let requests = restart_requests()
+        .lock()
+        .unwrap_or_else(|error| error.into_inner());
+    match requests.get(name) {
+        Some(restart) => {
+            // Stores a permit, so it isn't lost if the module is being restarted already
+            restart.notify_one();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Run a module loop with its config sections, restarting it whenever they change
+///
+/// The loop gets its own shutdown channel, so other modules keep running. A loop that
+/// returned on its own (ex. its module is disabled) is started again once its sections change.
+/// `start` is called before the module is marked ready, so it should subscribe to channels.
+/// The loop is restarted with the same sections by `request_module_restart`, ex. by the watchdog.
+pub async fn supervise<T, S, F, Fut>(
+    startup: StartupHandle,
+    mut shutdown_rx: broadcast::Receiver<()>,
+    mut config_rx: watch::Receiver<Config>,
+    sections: S,
+    start: F,
+) where
+    T: Clone + PartialEq + Send,
+    S: Fn(&Config) -> T,
+    F: Fn(broadcast::Receiver<()>, T) -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    startup.wait_for_dependencies().await;
+    let name = startup.name();
+    let restart = Arc::new(Notify::new());
+    restart_requests()
+        .lock()
+        .unwrap_or_else(|error| error.into_inner())
+        .insert(name, restart.clone());
+    let mut current = sections(&config_rx.borrow_and_update());
+    // Without hot reload the sender is dropped and the loop is started only once
+    let mut watching = true;
+    loop {
+        let (task_shutdown_tx, task_shutdown_rx) = broadcast::channel(1);
+        let mut task = Some(tokio::spawn(accounted(
+            name,
+            start(task_shutdown_rx, current.clone()),
+        )));
+        startup.ready();
+        loop {
+            tokio::select! {
+                _ = shutdown_rx.recv() => {
+                    restart_requests()
+                        .lock()
+                        .unwrap_or_else(|error| error.into_inner())
+                        .remove(name);
+                    stop(&task_shutdown_tx, task.take()).await;
+                    return;
+                }
+                _ = restart.notified() => {
+                    tracing::warn!("Restarting {} on request", name);
+                    stop_or_abort(&task_shutdown_tx, task.take()).await;
+                    break;
+                }
+                changed = config_rx.changed(), if watching => {
+                    if changed.is_err() {
+                        watching = false;
+                        continue;
+                    }
+                    let next = sections(&config_rx.borrow_and_update());
+                    if next == current {
+                        continue;
+                    }
+                    tracing::info!("Restarting {} with changed config", name);
+                    stop(&task_shutdown_tx, task.take()).await;
+                    current = next;
+                    break;
+                }
+                _ = async { task.as_mut().unwrap().await }, if task.is_some() => {
+                    tracing::trace!("{} loop returned", name);
+                    task = None;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::types::Example, startup::Startup};
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+    use tokio::time::sleep;
+
+    #[tokio::test]
+    async fn test_restart_on_changed_section() {
+        let config = Config::example();
+        let (config_tx, config_rx) = watch::channel(config.clone());
+        let (shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let started = Arc::new(AtomicUsize::new(0));
+        let stopped = Arc::new(AtomicUsize::new(0));
+        let (started_clone, stopped_clone) = (started.clone(), stopped.clone());
+        let supervisor = tokio::spawn(supervise(
+            Startup::default().register("one_wire", &[]),
+            shutdown_rx,
+            config_rx,
+            |config| config.one_wire.clone(),
+            move |mut shutdown_rx, _| {
+                let (started, stopped) = (started_clone.clone(), stopped_clone.clone());
+                async move {
+                    started.fetch_add(1, Ordering::SeqCst);
+                    let _ = shutdown_rx.recv().await;
+                    stopped.fetch_add(1, Ordering::SeqCst);
+                }
+            },
+        ));
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(started.load(Ordering::SeqCst), 1);
+
+        // Other sections don't restart the loop
+        let mut changed = config.clone();
+        changed.read_only = Some(true);
+        config_tx.send_replace(changed.clone());
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(started.load(Ordering::SeqCst), 1);
+
+        changed.one_wire = serde_json::from_value(serde_json::json!({"enabled": false})).unwrap();
+        config_tx.send_replace(changed);
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(started.load(Ordering::SeqCst), 2);
+        assert_eq!(stopped.load(Ordering::SeqCst), 1);
+
+        shutdown_tx.send(()).unwrap();
+        supervisor.await.unwrap();
+        assert_eq!(stopped.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_restart_on_request() {
+        let (_config_tx, config_rx) = watch::channel(Config::example());
+        let (shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let started = Arc::new(AtomicUsize::new(0));
+        let started_clone = started.clone();
+        assert!(!request_module_restart("restarted_module"));
+        let supervisor = tokio::spawn(supervise(
+            Startup::default().register("restarted_module", &[]),
+            shutdown_rx,
+            config_rx,
+            |config| config.one_wire.clone(),
+            move |mut shutdown_rx, _| {
+                let started = started_clone.clone();
+                async move {
+                    started.fetch_add(1, Ordering::SeqCst);
+                    let _ = shutdown_rx.recv().await;
+                }
+            },
+        ));
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(started.load(Ordering::SeqCst), 1);
+
+        assert!(request_module_restart("restarted_module"));
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(started.load(Ordering::SeqCst), 2);
+
+        shutdown_tx.send(()).unwrap();
+        supervisor.await.unwrap();
+        assert!(!request_module_restart("restarted_module"));
+    }
+}
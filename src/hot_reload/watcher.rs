@@ -0,0 +1,136 @@
+// Licensed under the Open Software License version 3.0
+use super::config::HotReloadConfig;
+use crate::{
+    config::{env::with_env_overrides, file::get_config_file_path, types::Config},
+    crash_report::hook::{config_hash, set_config_hash},
+    introspection,
+};
+use serde_json::Value;
+use std::{fs, sync::OnceLock};
+use tokio::{
+    sync::{broadcast, watch, Notify},
+    time::sleep,
+};
+
+/// Sections read only once at startup, their changes are applied on the next restart
+const RESTART_REQUIRED: &[&str] = &[
+    "passive_data_endpoint",
+    "wake_on_lan",
+    "fleet",
+    "scheduler",
+    "degraded_mode",
+    "watchdog",
+    "sampling",
+    "signals",
+    "crash_report",
+    "hot_reload",
+    "read_only",
+];
+
+fn reload_notify() -> &'static Notify {
+    static RELOAD_NOTIFY: OnceLock<Notify> = OnceLock::new();
+    RELOAD_NOTIFY.get_or_init(Notify::new)
+}
+
+/// Reload the config file now, ex. on SIGHUP
+pub fn request_reload() {
+    // Stores a permit, so it isn't lost if the file is being checked
+    reload_notify().notify_one();
+}
+
+/// Top-level keys whose values differ
+fn changed_sections(old: &Config, new: &Config) -> Vec<String> {
+    let old = serde_json::to_value(old).unwrap_or(Value::Null);
+    let new = serde_json::to_value(new).unwrap_or(Value::Null);
+    let (Some(old), Some(new)) = (old.as_object(), new.as_object()) else {
+        return Vec::new();
+    };
+    new.iter()
+        .filter(|(key, value)| old.get(key.as_str()) != Some(value))
+        .map(|(key, _)| key.clone())
+        .collect()
+}
+
+/// Re-read the config file on request or when it changes and publish it to module supervisors
+pub async fn start_hot_reload_loop(
+    mut shutdown_rx: broadcast::Receiver<()>,
+    config: HotReloadConfig,
+    config_tx: watch::Sender<Config>,
+) {
+    // Check if module is enabled
+    if !config.is_enabled() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+    let path = get_config_file_path();
+    tracing::info!("Reloading {} without restarting", path.display());
+    let _task = introspection::task_started("hot_reload");
+    let mut last_contents = fs::read_to_string(&path).ok();
+    loop {
+        let requested = tokio::select! {
+            _ = shutdown_rx.recv() => {
+                tracing::trace!("Shutting down hot reload loop");
+                break;
+            }
+            _ = reload_notify().notified() => true,
+            _ = sleep(config.get_check_interval()), if config.is_watch_file() => false,
+        };
+        introspection::mark_iteration("hot_reload");
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(error) => {
+                tracing::warn!("Failed to read {}: {}", path.display(), error);
+                continue;
+            }
+        };
+        if !requested && last_contents.as_ref() == Some(&contents) {
+            continue;
+        }
+        last_contents = Some(contents.clone());
+        let new_config = match serde_json::from_str::<Config>(&with_env_overrides(&contents)) {
+            Ok(new_config) => new_config,
+            Err(error) => {
+                tracing::error!(
+                    "Keeping current config, {} is invalid: {}",
+                    path.display(),
+                    error
+                );
+                continue;
+            }
+        };
+        let changed = changed_sections(&config_tx.borrow(), &new_config);
+        if changed.is_empty() {
+            tracing::debug!("Config is unchanged");
+            continue;
+        }
+        tracing::info!("Reloading changed config sections: {}", changed.join(", "));
+        for section in changed
+            .iter()
+            .filter(|section| RESTART_REQUIRED.contains(&section.as_str()))
+        {
+            tracing::warn!("Changes of {} are applied after a restart", section);
+        }
+        set_config_hash(config_hash(&new_config));
+        config_tx.send_replace(new_config);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::types::Example;
+
+    #[test]
+    fn test_changed_sections() {
+        let config = Config::example();
+        assert!(changed_sections(&config, &config).is_empty());
+        let mut changed = config.clone();
+        changed.read_only = Some(true);
+        changed.ups_monitoring =
+            serde_json::from_value(serde_json::json!({"enabled": false})).unwrap();
+        assert_eq!(
+            changed_sections(&config, &changed),
+            vec![String::from("ups_monitoring"), String::from("read_only")]
+        );
+    }
+}
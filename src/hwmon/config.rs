@@ -0,0 +1,96 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HwmonConfig {
+    enabled: Option<bool>,
+    cooldown: Option<Duration>,
+    path: Option<PathBuf>,
+    // Chip names (ex. coretemp) or directory names (ex. hwmon0) to read, all if not set
+    chips: Option<Vec<String>>,
+    // Input labels (ex. Package id 0) or names (ex. temp1) to read, all if not set
+    labels: Option<Vec<String>>,
+}
+
+impl Default for HwmonConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            cooldown: Some(Duration::from_secs(5)),
+            path: Some(PathBuf::from("/sys/class/hwmon")),
+            chips: None,
+            labels: None,
+        }
+    }
+}
+
+impl Example for HwmonConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(false),
+            cooldown: Some(Duration::from_secs(5)),
+            path: Some(PathBuf::from("/sys/class/hwmon")),
+            chips: Some(vec![String::from("coretemp"), String::from("nct6775")]),
+            labels: None,
+        }
+    }
+}
+
+impl HwmonConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_cooldown(&self) -> Duration {
+        self.cooldown.unwrap_or(Duration::from_secs(5))
+    }
+
+    pub fn get_path(&self) -> PathBuf {
+        self.path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("/sys/class/hwmon"))
+    }
+
+    /// Whether chip should be read, matched by its name or directory name
+    pub fn is_chip_enabled(&self, name: &str, directory: &str) -> bool {
+        match &self.chips {
+            Some(chips) => chips.iter().any(|chip| chip == name || chip == directory),
+            None => true,
+        }
+    }
+
+    /// Whether input should be read, matched by its label or name without `_input`
+    pub fn is_input_enabled(&self, label: Option<&str>, input: &str) -> bool {
+        match &self.labels {
+            Some(labels) => labels
+                .iter()
+                .any(|enabled| Some(enabled.as_str()) == label || enabled == input),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_chip_enabled() {
+        let config = HwmonConfig::example();
+        assert!(config.is_chip_enabled("coretemp", "hwmon3"));
+        assert!(!config.is_chip_enabled("nvme", "hwmon1"));
+        assert!(HwmonConfig::default().is_chip_enabled("nvme", "hwmon1"));
+    }
+
+    #[test]
+    fn test_is_input_enabled() {
+        let config: HwmonConfig =
+            serde_json::from_str(r#"{"labels": ["Core 0", "fan2"]}"#).unwrap();
+        assert!(config.is_input_enabled(Some("Core 0"), "temp2"));
+        assert!(config.is_input_enabled(None, "fan2"));
+        assert!(!config.is_input_enabled(Some("Core 1"), "temp3"));
+        assert!(HwmonConfig::default().is_input_enabled(None, "in0"));
+    }
+}
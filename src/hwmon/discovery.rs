@@ -0,0 +1,246 @@
+// Licensed under the Open Software License version 3.0
+//! Monitoring chips exposed by the kernel in `/sys/class/hwmon/hwmon*`
+use super::config::HwmonConfig;
+use crate::dedup_log::{info_resolved, warn_deduplicated};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chip {
+    /// Chip name with `-<n>` appended to repeated names, ex. `nvme`, `nvme-1`
+    pub id: String,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputKind {
+    Temperature,
+    Fan,
+    Voltage,
+}
+
+impl InputKind {
+    fn from_prefix(prefix: &str) -> Option<Self> {
+        match prefix {
+            "temp" => Some(Self::Temperature),
+            "fan" => Some(Self::Fan),
+            "in" => Some(Self::Voltage),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Temperature => "temperature",
+            Self::Fan => "fan",
+            Self::Voltage => "voltage",
+        }
+    }
+
+    /// Raw values are in millidegrees, RPM and millivolts
+    fn divisor(&self) -> f64 {
+        match self {
+            Self::Temperature | Self::Voltage => 1000.0,
+            Self::Fan => 1.0,
+        }
+    }
+}
+
+/// Number of `hwmon<n>`
+fn chip_number(directory: &str) -> Option<u32> {
+    directory.strip_prefix("hwmon")?.parse().ok()
+}
+
+/// Kind and number of `<kind><n>_input` files, ex. `temp1_input`
+fn parse_input(file_name: &str) -> Option<(InputKind, u32)> {
+    let input = file_name.strip_suffix("_input")?;
+    let digits = input.find(|c: char| c.is_ascii_digit())?;
+    let (prefix, number) = input.split_at(digits);
+    Some((InputKind::from_prefix(prefix)?, number.parse().ok()?))
+}
+
+/// Lowercase label with other characters than letters and digits replaced by `_`
+fn sanitize_label(label: &str) -> String {
+    label
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|value| value.trim().to_string())
+}
+
+/// Enabled chips ordered by their number, so ids of repeated names are stable
+pub fn discover_chips(path: &Path, config: &HwmonConfig) -> Vec<Chip> {
+    // Read on every poll, so a missing directory is logged only once
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => {
+            info_resolved!(
+                "hwmon:discovery",
+                "Reading hwmon chips from {} again",
+                path.display()
+            );
+            entries
+        }
+        Err(error) => {
+            warn_deduplicated!(
+                "hwmon:discovery",
+                "Failed to read {}: {}",
+                path.display(),
+                error
+            );
+            return Vec::new();
+        }
+    };
+    let mut directories: Vec<(u32, String)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let directory = entry.file_name().to_string_lossy().to_string();
+            Some((chip_number(&directory)?, directory))
+        })
+        .collect();
+    directories.sort();
+    let mut name_counts: HashMap<String, usize> = HashMap::new();
+    let mut chips = Vec::new();
+    for (_, directory) in directories {
+        let chip_path = path.join(&directory);
+        let name = read_trimmed(&chip_path.join("name")).unwrap_or_else(|| directory.clone());
+        // Count disabled chips too, so ids don't change with the enable list
+        let count = name_counts.entry(name.clone()).or_default();
+        let id = match *count {
+            0 => name.clone(),
+            count => format!("{}-{}", name, count),
+        };
+        *count += 1;
+        if config.is_chip_enabled(&name, &directory) {
+            chips.push(Chip {
+                id,
+                path: chip_path,
+            });
+        }
+    }
+    chips
+}
+
+/// Values of enabled inputs, named `<kind>_<label>` or `<kind>_<n>` if unlabeled
+///
+/// Temperatures are in °C, fans in RPM and voltages in V. Unreadable inputs are skipped.
+pub fn read_inputs(chip: &Chip, config: &HwmonConfig) -> BTreeMap<String, f64> {
+    let mut values = BTreeMap::new();
+    let entries = match fs::read_dir(&chip.path) {
+        Ok(entries) => entries,
+        Err(_) => return values,
+    };
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let (kind, number) = match parse_input(&file_name) {
+            Some(input) => input,
+            None => continue,
+        };
+        let input = file_name.trim_end_matches("_input");
+        let label = read_trimmed(&chip.path.join(format!("{}_label", input)));
+        if !config.is_input_enabled(label.as_deref(), input) {
+            continue;
+        }
+        let raw: f64 = match read_trimmed(&entry.path()).and_then(|raw| raw.parse().ok()) {
+            Some(raw) => raw,
+            None => continue,
+        };
+        let suffix = match &label {
+            Some(label) => sanitize_label(label),
+            None => number.to_string(),
+        };
+        values.insert(format!("{}_{}", kind.name(), suffix), raw / kind.divisor());
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::types::Example;
+
+    fn create_chip(root: &Path, directory: &str, name: &str, files: &[(&str, &str)]) {
+        let chip_path = root.join(directory);
+        fs::create_dir(&chip_path).unwrap();
+        fs::write(chip_path.join("name"), format!("{}\n", name)).unwrap();
+        for (file, content) in files {
+            fs::write(chip_path.join(file), format!("{}\n", content)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_discover_chips() {
+        let root = tempfile::tempdir().unwrap();
+        create_chip(root.path(), "hwmon10", "nvme", &[]);
+        create_chip(root.path(), "hwmon2", "nvme", &[]);
+        create_chip(root.path(), "hwmon0", "coretemp", &[]);
+
+        let chips = discover_chips(root.path(), &HwmonConfig::default());
+        let ids: Vec<&str> = chips.iter().map(|chip| chip.id.as_str()).collect();
+        assert_eq!(ids, vec!["coretemp", "nvme", "nvme-1"]);
+
+        let chips = discover_chips(root.path(), &HwmonConfig::example());
+        assert_eq!(chips.len(), 1);
+        assert_eq!(chips[0].id, "coretemp");
+    }
+
+    #[test]
+    fn test_read_inputs() {
+        let root = tempfile::tempdir().unwrap();
+        create_chip(
+            root.path(),
+            "hwmon0",
+            "nct6775",
+            &[
+                ("temp1_input", "42000"),
+                ("temp1_label", "Package id 0"),
+                ("temp2_input", "38500"),
+                ("fan1_input", "1200"),
+                ("in0_input", "1104"),
+                ("in1_input", ""),
+                ("temp1_max", "100000"),
+            ],
+        );
+        let chips = discover_chips(root.path(), &HwmonConfig::default());
+        let values = read_inputs(&chips[0], &HwmonConfig::default());
+        let expected = BTreeMap::from([
+            (String::from("fan_1"), 1200.0),
+            (String::from("temperature_2"), 38.5),
+            (String::from("temperature_package_id_0"), 42.0),
+            (String::from("voltage_0"), 1.104),
+        ]);
+        assert_eq!(values, expected);
+
+        let config: HwmonConfig = serde_json::from_str(r#"{"labels": ["Package id 0"]}"#).unwrap();
+        let values = read_inputs(&chips[0], &config);
+        assert_eq!(
+            values.keys().collect::<Vec<_>>(),
+            vec!["temperature_package_id_0"]
+        );
+    }
+
+    #[test]
+    fn test_parse_input() {
+        assert_eq!(
+            parse_input("temp12_input"),
+            Some((InputKind::Temperature, 12))
+        );
+        assert_eq!(parse_input("in0_input"), Some((InputKind::Voltage, 0)));
+        assert_eq!(parse_input("fan1_label"), None);
+        assert_eq!(parse_input("curr1_input"), None);
+    }
+}
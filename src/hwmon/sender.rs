@@ -0,0 +1,113 @@
+// Licensed under the Open Software License version 3.0
+use super::{
+    config::HwmonConfig,
+    discovery::{discover_chips, read_inputs},
+};
+use crate::{
+    hardware::{
+        reading::{Reading, ReadingsUpdate},
+        types::{HardwareMetadata, HardwareType, SourceType},
+    },
+    introspection, sampling,
+    scheduler::job::{PollFuture, PollJob},
+};
+use std::{
+    cmp::max,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::{sync::broadcast, time::sleep};
+
+const PUBLISHER: &str = "hwmon";
+
+/// Reading of every enabled chip with readable inputs, discovered again as drivers may load later
+pub fn read_hwmon_chips(path: &Path, config: &HwmonConfig) -> Vec<Reading> {
+    discover_chips(path, config)
+        .into_iter()
+        .filter_map(|chip| {
+            let values = read_inputs(&chip, config);
+            if values.is_empty() {
+                tracing::debug!(
+                    "Skipping hwmon chip without readable inputs {}",
+                    chip.path.display()
+                );
+                return None;
+            }
+            let mut reading = Reading::new(
+                HardwareMetadata::new(chip.id, HardwareType::SensorChip, SourceType::Hwmon)
+                    .measured_now(),
+            );
+            reading.values = values;
+            Some(reading)
+        })
+        .collect()
+}
+
+pub struct HwmonPoller {
+    path: PathBuf,
+    cooldown: Duration,
+    config: HwmonConfig,
+    tx: broadcast::Sender<ReadingsUpdate>,
+}
+
+impl HwmonPoller {
+    pub fn new(config: HwmonConfig, tx: broadcast::Sender<ReadingsUpdate>) -> Self {
+        Self {
+            path: config.get_path(),
+            cooldown: max(config.get_cooldown(), Duration::from_secs(1)),
+            config,
+            tx,
+        }
+    }
+
+    /// Publish inputs of all enabled chips, returns delay until the next poll
+    pub fn poll_once(&mut self) -> Duration {
+        introspection::mark_iteration("hwmon");
+        let readings = read_hwmon_chips(&self.path, &self.config);
+        tracing::trace!("Sending {:?} to channel", readings);
+        if self.tx.receiver_count() > 0 {
+            self.tx
+                .send(ReadingsUpdate::new(PUBLISHER, readings))
+                .unwrap();
+            introspection::observe_channel("readings", &self.tx);
+        }
+        introspection::mark_idle("hwmon");
+        self.cooldown
+    }
+}
+
+impl PollJob for HwmonPoller {
+    fn name(&self) -> String {
+        String::from("hwmon")
+    }
+
+    fn poll(&mut self) -> PollFuture<'_> {
+        let delay = self.poll_once();
+        Box::pin(async move { delay })
+    }
+}
+
+pub async fn start_hwmon_loop(
+    mut shutdown_rx: broadcast::Receiver<()>,
+    config: HwmonConfig,
+    tx: broadcast::Sender<ReadingsUpdate>,
+) {
+    // Check if module is enabled
+    if !config.is_enabled() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+    tracing::debug!("Starting hwmon loop");
+    let mut poller = HwmonPoller::new(config, tx);
+    let _task = introspection::task_started("hwmon");
+    loop {
+        let delay = poller.poll_once();
+        tokio::select! {
+            _ = shutdown_rx.recv() => {
+                tracing::trace!("Shutting down hwmon loop");
+                break;
+            }
+            _ = sleep(sampling::tick::aligned(delay)) => {}
+        }
+    }
+}
@@ -0,0 +1,171 @@
+// Licensed under the Open Software License version 3.0
+//! Bosch BME280, floating point compensation from the datasheet
+use super::bus::{check_chip_id, Bus, Measurement};
+use std::{thread, time::Duration};
+
+const CHIP_ID_REGISTER: u8 = 0xd0;
+const CHIP_ID: u8 = 0x60;
+/// T1..P9, a reserved byte and H1
+const CALIBRATION_REGISTER: u8 = 0x88;
+/// H2..H6
+const HUMIDITY_CALIBRATION_REGISTER: u8 = 0xe1;
+const CONTROL_HUMIDITY_REGISTER: u8 = 0xf2;
+const CONTROL_MEASUREMENT_REGISTER: u8 = 0xf4;
+const DATA_REGISTER: u8 = 0xf7;
+/// Humidity oversampling x1
+const CONTROL_HUMIDITY: u8 = 0b001;
+/// Temperature and pressure oversampling x1 in forced mode, sensor sleeps after a single measurement
+const CONTROL_MEASUREMENT: u8 = 0x25;
+/// Maximum measurement time with every oversampling x1 is 9.3ms
+const MEASUREMENT_DELAY: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calibration {
+    t: [f64; 3],
+    p: [f64; 9],
+    h: [f64; 6],
+}
+
+impl Calibration {
+    pub fn parse(bytes: &[u8; 26], humidity_bytes: &[u8; 7]) -> Self {
+        let unsigned = |index: usize| u16::from_le_bytes([bytes[index], bytes[index + 1]]) as f64;
+        let signed = |index: usize| i16::from_le_bytes([bytes[index], bytes[index + 1]]) as f64;
+        let mut p = [0.0; 9];
+        p[0] = unsigned(6);
+        for (index, value) in p.iter_mut().enumerate().skip(1) {
+            *value = signed(6 + index * 2);
+        }
+        // H4 and H5 are 12 bit signed values sharing the nibbles of 0xe5
+        let h4 = ((humidity_bytes[3] as i8 as i16) << 4) | (humidity_bytes[4] & 0x0f) as i16;
+        let h5 = ((humidity_bytes[5] as i8 as i16) << 4) | (humidity_bytes[4] >> 4) as i16;
+        Self {
+            t: [unsigned(0), signed(2), signed(4)],
+            p,
+            h: [
+                bytes[25] as f64,
+                i16::from_le_bytes([humidity_bytes[0], humidity_bytes[1]]) as f64,
+                humidity_bytes[2] as f64,
+                h4 as f64,
+                h5 as f64,
+                humidity_bytes[6] as i8 as f64,
+            ],
+        }
+    }
+
+    /// Returns fine temperature used by other values and temperature in °C
+    pub fn temperature(&self, raw: f64) -> (f64, f64) {
+        let [t1, t2, t3] = self.t;
+        let var1 = (raw / 16384.0 - t1 / 1024.0) * t2;
+        let var2 = (raw / 131072.0 - t1 / 8192.0).powi(2) * t3;
+        let fine = var1 + var2;
+        (fine, fine / 5120.0)
+    }
+
+    /// Pa, `None` if calibration would divide by zero
+    pub fn pressure(&self, fine: f64, raw: f64) -> Option<f64> {
+        let [p1, p2, p3, p4, p5, p6, p7, p8, p9] = self.p;
+        let var1 = fine / 2.0 - 64000.0;
+        let var2 = var1 * var1 * p6 / 32768.0;
+        let var2 = var2 + var1 * p5 * 2.0;
+        let var2 = var2 / 4.0 + p4 * 65536.0;
+        let var1 = (p3 * var1 * var1 / 524288.0 + p2 * var1) / 524288.0;
+        let var1 = (1.0 + var1 / 32768.0) * p1;
+        if var1 == 0.0 {
+            return None;
+        }
+        let pressure = 1048576.0 - raw;
+        let pressure = (pressure - var2 / 4096.0) * 6250.0 / var1;
+        let var1 = p9 * pressure * pressure / 2147483648.0;
+        let var2 = pressure * p8 / 32768.0;
+        Some(pressure + (var1 + var2 + p7) / 16.0)
+    }
+
+    /// Relative humidity in %
+    pub fn humidity(&self, fine: f64, raw: f64) -> f64 {
+        let [h1, h2, h3, h4, h5, h6] = self.h;
+        let var = fine - 76800.0;
+        let var = (raw - (h4 * 64.0 + h5 / 16384.0 * var))
+            * (h2 / 65536.0 * (1.0 + h6 / 67108864.0 * var * (1.0 + h3 / 67108864.0 * var)));
+        let var = var * (1.0 - h1 * var / 524288.0);
+        var.clamp(0.0, 100.0)
+    }
+}
+
+pub fn measure(bus: &mut impl Bus) -> Result<Measurement, String> {
+    check_chip_id(bus, CHIP_ID_REGISTER, CHIP_ID)?;
+    let mut calibration = [0u8; 26];
+    bus.read_registers(CALIBRATION_REGISTER, &mut calibration)?;
+    let mut humidity_calibration = [0u8; 7];
+    bus.read_registers(HUMIDITY_CALIBRATION_REGISTER, &mut humidity_calibration)?;
+    let calibration = Calibration::parse(&calibration, &humidity_calibration);
+
+    // Humidity control takes effect after writing measurement control
+    bus.write_register(CONTROL_HUMIDITY_REGISTER, CONTROL_HUMIDITY)?;
+    bus.write_register(CONTROL_MEASUREMENT_REGISTER, CONTROL_MEASUREMENT)?;
+    thread::sleep(MEASUREMENT_DELAY);
+    let mut data = [0u8; 8];
+    bus.read_registers(DATA_REGISTER, &mut data)?;
+    let raw_20_bit = |index: usize| {
+        (((data[index] as u32) << 12)
+            | ((data[index + 1] as u32) << 4)
+            | (data[index + 2] as u32 >> 4)) as f64
+    };
+    let raw_pressure = raw_20_bit(0);
+    let raw_temperature = raw_20_bit(3);
+    let raw_humidity = u16::from_be_bytes([data[6], data[7]]) as f64;
+
+    let (fine, temperature) = calibration.temperature(raw_temperature);
+    Ok(Measurement {
+        temperature: Some(temperature),
+        humidity: Some(calibration.humidity(fine, raw_humidity)),
+        pressure: calibration
+            .pressure(fine, raw_pressure)
+            .map(|pressure| pressure / 100.0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::bus::fake::FakeBus;
+    use super::*;
+
+    // Temperature and pressure example from the BMP280 datasheet, same compensation as BME280
+    const CALIBRATION: [u8; 26] = [
+        0x70, 0x6b, 0x43, 0x67, 0x18, 0xfc, 0x7d, 0x8e, 0x43, 0xd6, 0xd0, 0x0b, 0x27, 0x0b, 0x8c,
+        0x00, 0xf9, 0xff, 0x8c, 0x3c, 0xf8, 0xc6, 0x70, 0x17, 0x00, 0x4b,
+    ];
+    const HUMIDITY_CALIBRATION: [u8; 7] = [0x6a, 0x01, 0x00, 0x13, 0x2e, 0x03, 0x1e];
+
+    #[test]
+    fn test_compensate() {
+        let calibration = Calibration::parse(&CALIBRATION, &HUMIDITY_CALIBRATION);
+        let (fine, temperature) = calibration.temperature(519888.0);
+        assert!((temperature - 25.08).abs() < 0.01);
+        let pressure = calibration.pressure(fine, 415148.0).unwrap();
+        assert!((pressure - 100653.27).abs() < 0.01);
+        let humidity = calibration.humidity(fine, 0.0);
+        assert_eq!(humidity, 0.0);
+    }
+
+    #[test]
+    fn test_parse_humidity_calibration() {
+        let calibration = Calibration::parse(&CALIBRATION, &HUMIDITY_CALIBRATION);
+        assert_eq!(calibration.h, [75.0, 362.0, 0.0, 318.0, 50.0, 30.0]);
+    }
+
+    #[test]
+    fn test_measure() {
+        let mut bus = FakeBus::with_registers(CALIBRATION_REGISTER, &CALIBRATION);
+        bus.set_registers(HUMIDITY_CALIBRATION_REGISTER, &HUMIDITY_CALIBRATION);
+        bus.set_registers(CHIP_ID_REGISTER, &[CHIP_ID]);
+        bus.set_registers(
+            DATA_REGISTER,
+            &[0x65, 0x5a, 0xc0, 0x7e, 0xed, 0x00, 0x6f, 0x40],
+        );
+        let measurement = measure(&mut bus).unwrap();
+        assert!((measurement.temperature.unwrap() - 25.08).abs() < 0.01);
+        assert!((measurement.pressure.unwrap() - 1006.53).abs() < 0.01);
+        assert!((measurement.humidity.unwrap() - 44.75).abs() < 0.01);
+        assert_eq!(bus.writes[bus.writes.len() - 2], vec![0xf4, 0x25]);
+    }
+}
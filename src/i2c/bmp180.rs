@@ -0,0 +1,176 @@
+// Licensed under the Open Software License version 3.0
+//! Bosch BMP180, integer compensation from the datasheet
+use super::bus::{check_chip_id, Bus, Measurement};
+use std::{thread, time::Duration};
+
+const CHIP_ID_REGISTER: u8 = 0xd0;
+const CHIP_ID: u8 = 0x55;
+const CALIBRATION_REGISTER: u8 = 0xaa;
+const CONTROL_REGISTER: u8 = 0xf4;
+const RESULT_REGISTER: u8 = 0xf6;
+const MEASURE_TEMPERATURE: u8 = 0x2e;
+const MEASURE_PRESSURE: u8 = 0x34;
+/// Standard mode, 2 internal samples
+const OVERSAMPLING: u8 = 1;
+/// Conversion time of the temperature and standard mode pressure is at most 4.5ms and 7.5ms
+const TEMPERATURE_DELAY: Duration = Duration::from_millis(5);
+const PRESSURE_DELAY: Duration = Duration::from_millis(8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Calibration {
+    ac1: i64,
+    ac2: i64,
+    ac3: i64,
+    ac4: i64,
+    ac5: i64,
+    ac6: i64,
+    b1: i64,
+    b2: i64,
+    mc: i64,
+    md: i64,
+}
+
+impl Calibration {
+    /// 22 big endian bytes starting at 0xaa
+    pub fn parse(bytes: &[u8; 22]) -> Result<Self, String> {
+        let word = |index: usize| u16::from_be_bytes([bytes[index * 2], bytes[index * 2 + 1]]);
+        // Unprogrammed or broken EEPROM reads as all zeros or ones
+        if (0..11).any(|index| matches!(word(index), 0x0000 | 0xffff)) {
+            return Err(String::from("invalid calibration data"));
+        }
+        let signed = |index: usize| word(index) as i16 as i64;
+        let unsigned = |index: usize| word(index) as i64;
+        Ok(Self {
+            ac1: signed(0),
+            ac2: signed(1),
+            ac3: signed(2),
+            ac4: unsigned(3),
+            ac5: unsigned(4),
+            ac6: unsigned(5),
+            b1: signed(6),
+            b2: signed(7),
+            // MB at index 8 isn't used
+            mc: signed(9),
+            md: signed(10),
+        })
+    }
+
+    /// Returns temperature in 0.1°C and pressure in Pa, an error if calibration would divide by
+    /// zero
+    pub fn compensate(
+        &self,
+        raw_temperature: i64,
+        raw_pressure: i64,
+        oss: u8,
+    ) -> Result<(i64, i64), String> {
+        let x1 = ((raw_temperature - self.ac6) * self.ac5) >> 15;
+        if x1 + self.md == 0 {
+            return Err(String::from("temperature compensation divides by zero"));
+        }
+        let x2 = (self.mc << 11) / (x1 + self.md);
+        let b5 = x1 + x2;
+        let temperature = (b5 + 8) >> 4;
+
+        let b6 = b5 - 4000;
+        let x1 = (self.b2 * ((b6 * b6) >> 12)) >> 11;
+        let x2 = (self.ac2 * b6) >> 11;
+        let x3 = x1 + x2;
+        let b3 = (((self.ac1 * 4 + x3) << oss) + 2) / 4;
+        let x1 = (self.ac3 * b6) >> 13;
+        let x2 = (self.b1 * ((b6 * b6) >> 12)) >> 16;
+        let x3 = (x1 + x2 + 2) >> 2;
+        let b4 = (self.ac4 * (x3 + 32768)) >> 15;
+        if b4 == 0 {
+            return Err(String::from("pressure compensation divides by zero"));
+        }
+        let b7 = (raw_pressure - b3) * (50000 >> oss);
+        let pressure = b7 * 2 / b4;
+        let x1 = (pressure >> 8) * (pressure >> 8);
+        let x1 = (x1 * 3038) >> 16;
+        let x2 = (-7357 * pressure) >> 16;
+        Ok((temperature, pressure + ((x1 + x2 + 3791) >> 4)))
+    }
+}
+
+pub fn measure(bus: &mut impl Bus) -> Result<Measurement, String> {
+    check_chip_id(bus, CHIP_ID_REGISTER, CHIP_ID)?;
+    let mut calibration = [0u8; 22];
+    bus.read_registers(CALIBRATION_REGISTER, &mut calibration)?;
+    let calibration = Calibration::parse(&calibration)?;
+
+    bus.write_register(CONTROL_REGISTER, MEASURE_TEMPERATURE)?;
+    thread::sleep(TEMPERATURE_DELAY);
+    let mut raw = [0u8; 2];
+    bus.read_registers(RESULT_REGISTER, &mut raw)?;
+    let raw_temperature = u16::from_be_bytes(raw) as i64;
+
+    bus.write_register(CONTROL_REGISTER, MEASURE_PRESSURE + (OVERSAMPLING << 6))?;
+    thread::sleep(PRESSURE_DELAY);
+    let mut raw = [0u8; 3];
+    bus.read_registers(RESULT_REGISTER, &mut raw)?;
+    let raw_pressure =
+        (((raw[0] as i64) << 16) | ((raw[1] as i64) << 8) | raw[2] as i64) >> (8 - OVERSAMPLING);
+
+    let (temperature, pressure) =
+        calibration.compensate(raw_temperature, raw_pressure, OVERSAMPLING)?;
+    Ok(Measurement {
+        temperature: Some(temperature as f64 / 10.0),
+        humidity: None,
+        pressure: Some(pressure as f64 / 100.0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::bus::fake::FakeBus;
+    use super::*;
+
+    // Example from the datasheet
+    const CALIBRATION: [u8; 22] = [
+        0x01, 0x98, 0xff, 0xb8, 0xc7, 0xd1, 0x7f, 0xe5, 0x7f, 0xf5, 0x5a, 0x71, 0x18, 0x2e, 0x00,
+        0x04, 0x80, 0x00, 0xdd, 0xf9, 0x0b, 0x34,
+    ];
+
+    #[test]
+    fn test_compensate() {
+        let calibration = Calibration::parse(&CALIBRATION).unwrap();
+        assert_eq!(calibration.compensate(27898, 23843, 0), Ok((150, 69964)));
+    }
+
+    #[test]
+    fn test_compensate_divide_by_zero() {
+        let calibration = Calibration::parse(&CALIBRATION).unwrap();
+        // x1 is 0 at AC6, so MD alone is the divisor
+        let zero_md = Calibration {
+            md: 0,
+            ..calibration
+        };
+        assert!(zero_md.compensate(calibration.ac6, 23843, 0).is_err());
+    }
+
+    #[test]
+    fn test_invalid_calibration() {
+        let mut calibration = CALIBRATION;
+        calibration[2] = 0xff;
+        calibration[3] = 0xff;
+        assert!(Calibration::parse(&calibration).is_err());
+    }
+
+    #[test]
+    fn test_measure() {
+        let mut bus = FakeBus::with_registers(CALIBRATION_REGISTER, &CALIBRATION);
+        bus.set_registers(CHIP_ID_REGISTER, &[CHIP_ID]);
+        // Same registers are read after both conversions
+        bus.set_registers(RESULT_REGISTER, &[0x6c, 0xfa, 0x00]);
+        let measurement = measure(&mut bus).unwrap();
+        assert!(measurement.temperature.is_some());
+        assert!(measurement.humidity.is_none());
+        assert!(bus.writes.contains(&vec![CONTROL_REGISTER, 0x74]));
+    }
+
+    #[test]
+    fn test_measure_wrong_chip() {
+        let mut bus = FakeBus::with_registers(CHIP_ID_REGISTER, &[0x60]);
+        assert!(measure(&mut bus).is_err());
+    }
+}
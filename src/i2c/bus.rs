@@ -0,0 +1,111 @@
+// Licensed under the Open Software License version 3.0
+//! Minimal view of an I2C device, so drivers can be tested without hardware
+
+/// Device at a single address of a bus
+pub trait Bus {
+    fn write(&mut self, data: &[u8]) -> Result<(), String>;
+
+    fn read(&mut self, buffer: &mut [u8]) -> Result<(), String>;
+
+    /// Read consecutive registers starting at `register`
+    fn read_registers(&mut self, register: u8, buffer: &mut [u8]) -> Result<(), String> {
+        self.write(&[register])?;
+        self.read(buffer)
+    }
+
+    fn write_register(&mut self, register: u8, value: u8) -> Result<(), String> {
+        self.write(&[register, value])
+    }
+}
+
+/// Values read from a single sensor, missing if the model doesn't measure them
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Measurement {
+    /// °C
+    pub temperature: Option<f64>,
+    /// Relative humidity in %
+    pub humidity: Option<f64>,
+    /// hPa
+    pub pressure: Option<f64>,
+}
+
+/// Fails with a readable message if the chip id register doesn't match
+pub fn check_chip_id(bus: &mut impl Bus, register: u8, expected: u8) -> Result<(), String> {
+    let mut id = [0u8];
+    bus.read_registers(register, &mut id)?;
+    if id[0] != expected {
+        return Err(format!(
+            "unexpected chip id {:#04x}, expected {:#04x}",
+            id[0], expected
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod fake {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    /// Register map, reads continue from the last written register
+    #[derive(Debug, Default)]
+    pub struct FakeBus {
+        registers: BTreeMap<u8, u8>,
+        pub writes: Vec<Vec<u8>>,
+        // Returned by plain reads after a command
+        response: Vec<u8>,
+        pointer: Option<u8>,
+    }
+
+    impl FakeBus {
+        pub fn with_registers(start: u8, values: &[u8]) -> Self {
+            let mut bus = Self::default();
+            bus.set_registers(start, values);
+            bus
+        }
+
+        pub fn with_response(response: &[u8]) -> Self {
+            Self {
+                response: response.to_vec(),
+                ..Default::default()
+            }
+        }
+
+        pub fn set_registers(&mut self, start: u8, values: &[u8]) {
+            for (offset, value) in values.iter().enumerate() {
+                self.registers.insert(start + offset as u8, *value);
+            }
+        }
+    }
+
+    impl Bus for FakeBus {
+        fn write(&mut self, data: &[u8]) -> Result<(), String> {
+            self.writes.push(data.to_vec());
+            self.pointer = match data {
+                [register] => Some(*register),
+                _ => None,
+            };
+            Ok(())
+        }
+
+        fn read(&mut self, buffer: &mut [u8]) -> Result<(), String> {
+            match self.pointer {
+                Some(register) => {
+                    for (offset, value) in buffer.iter_mut().enumerate() {
+                        *value = *self
+                            .registers
+                            .get(&(register + offset as u8))
+                            .ok_or_else(|| String::from("no acknowledgement"))?;
+                    }
+                }
+                None => {
+                    if self.response.len() < buffer.len() {
+                        return Err(String::from("no acknowledgement"));
+                    }
+                    buffer.copy_from_slice(&self.response[..buffer.len()]);
+                }
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,142 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum I2cSensorModel {
+    // Temperature, humidity and pressure
+    Bme280,
+    // Temperature and humidity
+    Sht31,
+    // Temperature and pressure
+    Bmp180,
+}
+
+impl I2cSensorModel {
+    /// Address with the address pin left unconnected or pulled low
+    pub fn default_address(&self) -> u16 {
+        match self {
+            Self::Bme280 => 0x76,
+            Self::Sht31 => 0x44,
+            Self::Bmp180 => 0x77,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Bme280 => "bme280",
+            Self::Sht31 => "sht31",
+            Self::Bmp180 => "bmp180",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct I2cSensorConfig {
+    // Number of /dev/i2c-<bus>
+    bus: u8,
+    model: I2cSensorModel,
+    // Default address of the model if not set
+    address: Option<u16>,
+    // hw.id of the sensor, <model>-<bus>-<address> if not set
+    id: Option<String>,
+    name: Option<String>,
+}
+
+impl Example for I2cSensorConfig {
+    fn example() -> Self {
+        Self {
+            bus: 1,
+            model: I2cSensorModel::Bme280,
+            address: None,
+            id: None,
+            name: Some(String::from("Attic")),
+        }
+    }
+}
+
+impl I2cSensorConfig {
+    pub fn get_bus(&self) -> u8 {
+        self.bus
+    }
+
+    pub fn get_model(&self) -> I2cSensorModel {
+        self.model
+    }
+
+    pub fn get_address(&self) -> u16 {
+        self.address.unwrap_or_else(|| self.model.default_address())
+    }
+
+    pub fn get_id(&self) -> String {
+        self.id.clone().unwrap_or_else(|| {
+            format!(
+                "{}-{}-{:#04x}",
+                self.model.name(),
+                self.bus,
+                self.get_address()
+            )
+        })
+    }
+
+    pub fn get_name(&self) -> Option<String> {
+        self.name.clone()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct I2cConfig {
+    enabled: Option<bool>,
+    cooldown: Option<Duration>,
+    sensors: Option<Vec<I2cSensorConfig>>,
+}
+
+impl Default for I2cConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            cooldown: Some(Duration::from_secs(10)),
+            sensors: None,
+        }
+    }
+}
+
+impl Example for I2cConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(false),
+            cooldown: Some(Duration::from_secs(10)),
+            sensors: Some(vec![I2cSensorConfig::example()]),
+        }
+    }
+}
+
+impl I2cConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_cooldown(&self) -> Duration {
+        self.cooldown.unwrap_or(Duration::from_secs(10))
+    }
+
+    pub fn get_sensors(&self) -> Vec<I2cSensorConfig> {
+        self.sensors.clone().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sensor_config_get_id() {
+        assert_eq!(I2cSensorConfig::example().get_id(), "bme280-1-0x76");
+        let config: I2cSensorConfig =
+            serde_json::from_str(r#"{"bus": 0, "model": "sht31", "address": 69}"#).unwrap();
+        assert_eq!(config.get_address(), 0x45);
+        assert_eq!(config.get_id(), "sht31-0-0x45");
+    }
+}
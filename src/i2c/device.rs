@@ -0,0 +1,31 @@
+// Licensed under the Open Software License version 3.0
+//! Sensors on `/dev/i2c-*` character devices
+use super::{
+    bme280, bmp180,
+    bus::{Bus, Measurement},
+    config::{I2cSensorConfig, I2cSensorModel},
+    sht31,
+};
+use i2cdev::{core::I2CDevice, linux::LinuxI2CDevice};
+
+impl Bus for LinuxI2CDevice {
+    fn write(&mut self, data: &[u8]) -> Result<(), String> {
+        I2CDevice::write(self, data).map_err(|error| error.to_string())
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> Result<(), String> {
+        I2CDevice::read(self, buffer).map_err(|error| error.to_string())
+    }
+}
+
+/// Open the device again on every read, so sensors can be replugged
+pub fn read_sensor(sensor: &I2cSensorConfig) -> Result<Measurement, String> {
+    let path = format!("/dev/i2c-{}", sensor.get_bus());
+    let mut device = LinuxI2CDevice::new(&path, sensor.get_address())
+        .map_err(|error| format!("failed to open {}: {}", path, error))?;
+    match sensor.get_model() {
+        I2cSensorModel::Bme280 => bme280::measure(&mut device),
+        I2cSensorModel::Sht31 => sht31::measure(&mut device),
+        I2cSensorModel::Bmp180 => bmp180::measure(&mut device),
+    }
+}
@@ -0,0 +1,15 @@
+// Licensed under the Open Software License version 3.0
+// Drivers are only used by the i2c feature and tests
+#[cfg_attr(not(feature = "i2c"), allow(dead_code))]
+mod bme280;
+#[cfg_attr(not(feature = "i2c"), allow(dead_code))]
+mod bmp180;
+#[cfg_attr(not(feature = "i2c"), allow(dead_code))]
+mod bus;
+#[cfg_attr(not(feature = "i2c"), allow(dead_code))]
+pub mod config;
+#[cfg(feature = "i2c")]
+mod device;
+pub mod sender;
+#[cfg_attr(not(feature = "i2c"), allow(dead_code))]
+mod sht31;
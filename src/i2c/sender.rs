@@ -0,0 +1,139 @@
+// Licensed under the Open Software License version 3.0
+use super::config::I2cConfig;
+use crate::hardware::reading::ReadingsUpdate;
+use tokio::sync::broadcast;
+
+#[cfg(feature = "i2c")]
+mod poller {
+    use super::super::{config::I2cSensorConfig, device::read_sensor};
+    use super::*;
+    use crate::{
+        dedup_log::{info_resolved, warn_deduplicated},
+        hardware::{
+            reading::Reading,
+            types::{HardwareMetadata, HardwareType, SourceType},
+        },
+        introspection, sampling,
+        scheduler::job::{PollFuture, PollJob},
+    };
+    use std::{cmp::max, time::Duration};
+    use tokio::time::sleep;
+
+    const PUBLISHER: &str = "i2c";
+
+    fn read_sensors(sensors: &[I2cSensorConfig]) -> Vec<Reading> {
+        let mut readings = Vec::new();
+        for sensor in sensors {
+            let id = sensor.get_id();
+            let key = format!("i2c:{}", id);
+            match read_sensor(sensor) {
+                Ok(measurement) => {
+                    info_resolved!(key, "Reading {} again", id);
+                    let mut meta = HardwareMetadata::new(
+                        id,
+                        HardwareType::EnvironmentalSensor,
+                        SourceType::I2c,
+                    )
+                    .measured_now();
+                    meta.hw.name = sensor.get_name();
+                    readings.push(
+                        Reading::new(meta)
+                            .with_value("temperature", measurement.temperature)
+                            .with_value("humidity", measurement.humidity)
+                            .with_value("pressure", measurement.pressure),
+                    );
+                }
+                Err(error) => warn_deduplicated!(key, "Failed to read {}: {}", id, error),
+            }
+        }
+        readings
+    }
+
+    pub struct I2cPoller {
+        sensors: Vec<I2cSensorConfig>,
+        cooldown: Duration,
+        tx: broadcast::Sender<ReadingsUpdate>,
+    }
+
+    impl I2cPoller {
+        pub fn new(config: &I2cConfig, tx: broadcast::Sender<ReadingsUpdate>) -> Self {
+            Self {
+                sensors: config.get_sensors(),
+                cooldown: max(config.get_cooldown(), Duration::from_secs(1)),
+                tx,
+            }
+        }
+
+        /// Publish all readable sensors, returns delay until the next poll
+        pub async fn poll_once(&mut self) -> Duration {
+            introspection::mark_iteration("i2c");
+            let sensors = self.sensors.clone();
+            let readings = tokio::task::spawn_blocking(move || read_sensors(&sensors))
+                .await
+                .unwrap_or_default();
+            tracing::trace!("Sending {:?} to channel", readings);
+            if self.tx.receiver_count() > 0 {
+                self.tx
+                    .send(ReadingsUpdate::new(PUBLISHER, readings))
+                    .unwrap();
+                introspection::observe_channel("readings", &self.tx);
+            }
+            introspection::mark_idle("i2c");
+            self.cooldown
+        }
+    }
+
+    impl PollJob for I2cPoller {
+        fn name(&self) -> String {
+            String::from("i2c")
+        }
+
+        fn poll(&mut self) -> PollFuture<'_> {
+            Box::pin(self.poll_once())
+        }
+    }
+
+    pub async fn run(
+        mut shutdown_rx: broadcast::Receiver<()>,
+        config: I2cConfig,
+        tx: broadcast::Sender<ReadingsUpdate>,
+    ) {
+        tracing::debug!("Starting I2C loop");
+        let mut poller = I2cPoller::new(&config, tx);
+        let _task = introspection::task_started("i2c");
+        loop {
+            let delay = poller.poll_once().await;
+            tokio::select! {
+                _ = shutdown_rx.recv() => {
+                    tracing::trace!("Shutting down I2C loop");
+                    break;
+                }
+                _ = sleep(sampling::tick::aligned(delay)) => {}
+            }
+        }
+    }
+}
+
+#[cfg(feature = "i2c")]
+pub use poller::I2cPoller;
+
+pub async fn start_i2c_loop(
+    shutdown_rx: broadcast::Receiver<()>,
+    config: I2cConfig,
+    tx: broadcast::Sender<ReadingsUpdate>,
+) {
+    // Check if module is enabled
+    if !config.is_enabled() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+
+    #[cfg(feature = "i2c")]
+    poller::run(shutdown_rx, config, tx).await;
+
+    #[cfg(not(feature = "i2c"))]
+    {
+        let _ = (shutdown_rx, tx);
+        tracing::error!("I2C is enabled in config but this binary was built without i2c feature");
+    }
+}
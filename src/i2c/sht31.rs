@@ -0,0 +1,75 @@
+// Licensed under the Open Software License version 3.0
+//! Sensirion SHT31, single shot measurements without clock stretching
+use super::bus::{Bus, Measurement};
+use std::{thread, time::Duration};
+
+/// High repeatability
+const MEASURE: [u8; 2] = [0x24, 0x00];
+/// Maximum measurement time with high repeatability is 15ms
+const MEASUREMENT_DELAY: Duration = Duration::from_millis(16);
+
+/// CRC-8 with polynomial 0x31 and initial value 0xff
+fn crc(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0xff, |crc, byte| {
+        (0..8).fold(crc ^ byte, |crc, _| match crc & 0x80 {
+            0 => crc << 1,
+            _ => (crc << 1) ^ 0x31,
+        })
+    })
+}
+
+fn checked_word(bytes: &[u8]) -> Result<u16, String> {
+    let expected = crc(&bytes[..2]);
+    if expected != bytes[2] {
+        return Err(format!(
+            "checksum mismatch, expected {:#04x}, received {:#04x}",
+            expected, bytes[2]
+        ));
+    }
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+/// Temperature and humidity words, each followed by its checksum
+pub fn decode(bytes: &[u8; 6]) -> Result<Measurement, String> {
+    let temperature = checked_word(&bytes[..3])? as f64;
+    let humidity = checked_word(&bytes[3..])? as f64;
+    Ok(Measurement {
+        temperature: Some(-45.0 + 175.0 * temperature / 65535.0),
+        humidity: Some(100.0 * humidity / 65535.0),
+        pressure: None,
+    })
+}
+
+pub fn measure(bus: &mut impl Bus) -> Result<Measurement, String> {
+    bus.write(&MEASURE)?;
+    thread::sleep(MEASUREMENT_DELAY);
+    let mut bytes = [0u8; 6];
+    bus.read(&mut bytes)?;
+    decode(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::bus::fake::FakeBus;
+    use super::*;
+
+    #[test]
+    fn test_crc() {
+        // Example from the datasheet
+        assert_eq!(crc(&[0xbe, 0xef]), 0x92);
+    }
+
+    #[test]
+    fn test_measure() {
+        let mut bus = FakeBus::with_response(&[0x66, 0x66, 0x93, 0x80, 0x00, 0xa2]);
+        let measurement = measure(&mut bus).unwrap();
+        assert!((measurement.temperature.unwrap() - 25.0).abs() < 0.01);
+        assert!((measurement.humidity.unwrap() - 50.0).abs() < 0.01);
+        assert_eq!(bus.writes, vec![MEASURE.to_vec()]);
+    }
+
+    #[test]
+    fn test_decode_checksum_mismatch() {
+        assert!(decode(&[0x66, 0x66, 0x00, 0x80, 0x00, 0xa2]).is_err());
+    }
+}
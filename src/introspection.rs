@@ -0,0 +1,300 @@
+// Licensed under the Open Software License version 3.0
+//! Runtime state of module loops, served by the passive endpoint at `/status/internal`
+//!
+//! Loops register themselves with `task_started` and call `mark_iteration` once per iteration,
+//! so a wedged loop shows up as a stale `last_iteration`. Polling loops call `mark_idle` once the
+//! poll is done, so the cooldown that follows isn't mistaken for a long iteration.
+use crate::{
+    clock::{self, ClockStatus},
+    self_metrics::lag::get_lagged_messages,
+    startup::ModuleState,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    sync::{Mutex, OnceLock, TryLockError},
+};
+use tokio::sync::broadcast;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskStatus {
+    /// Number of running loops, ex. one per NUT server
+    pub running: u32,
+    pub iterations: u64,
+    pub last_iteration: Option<String>,
+    /// End of the last poll, not set by loops that wait for data instead of polling
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub last_idle: Option<String>,
+}
+
+/// Broadcast channel as seen by its producer after the last send
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelStatus {
+    pub receivers: usize,
+    /// Messages not yet received by the slowest receiver
+    pub queued: usize,
+    pub observed_at: String,
+}
+
+/// Requests sent to an active sender endpoint
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EndpointStatus {
+    pub requests: u64,
+    /// Requests without any response, ex. connection refused or timeout
+    pub failures: u64,
+    /// Responses by negotiated protocol, ex. `HTTP/1.1` or `HTTP/2.0`
+    pub versions: BTreeMap<String, u64>,
+}
+
+/// Boot sequence of a module
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StartupStatus {
+    pub state: ModuleState,
+    pub since: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InternalStatus {
+    pub startup: BTreeMap<String, StartupStatus>,
+    pub tasks: BTreeMap<String, TaskStatus>,
+    pub channels: BTreeMap<String, ChannelStatus>,
+    /// Failed attempts waiting for a retry, ex. `nut:<server>` reconnects
+    pub retries: BTreeMap<String, u32>,
+    /// Keyed by endpoint url
+    pub endpoints: BTreeMap<String, EndpointStatus>,
+    /// hw.ids repeated in the last snapshot of each category
+    pub duplicates: BTreeMap<String, Vec<String>>,
+    /// Total messages skipped by slow receivers
+    pub lagged_messages: u64,
+    /// Config sections ignored in degraded mode, keyed by top-level key
+    pub invalid_config: BTreeMap<String, String>,
+    /// Steps of the system clock, ex. NTP corrections
+    pub clock: ClockStatus,
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    status: InternalStatus,
+}
+
+impl Registry {
+    fn task_started(&mut self, name: &str) {
+        let task = self.status.tasks.entry(String::from(name)).or_default();
+        task.running += 1;
+    }
+
+    fn task_stopped(&mut self, name: &str) {
+        if let Some(task) = self.status.tasks.get_mut(name) {
+            task.running = task.running.saturating_sub(1);
+        }
+    }
+
+    fn mark_iteration(&mut self, name: &str, timestamp: String) {
+        let task = self.status.tasks.entry(String::from(name)).or_default();
+        task.iterations += 1;
+        task.last_iteration = Some(timestamp);
+    }
+
+    fn mark_idle(&mut self, name: &str, timestamp: String) {
+        let task = self.status.tasks.entry(String::from(name)).or_default();
+        task.last_idle = Some(timestamp);
+    }
+
+    fn set_startup_state(&mut self, name: &str, state: ModuleState, timestamp: String) {
+        let status = StartupStatus {
+            state,
+            since: timestamp,
+        };
+        self.status.startup.insert(String::from(name), status);
+    }
+
+    fn observe_channel(&mut self, name: &str, channel: ChannelStatus) {
+        self.status.channels.insert(String::from(name), channel);
+    }
+
+    fn set_retries(&mut self, key: &str, failed_attempts: u32) {
+        if failed_attempts == 0 {
+            self.status.retries.remove(key);
+        } else {
+            self.status
+                .retries
+                .insert(String::from(key), failed_attempts);
+        }
+    }
+
+    fn set_duplicates(&mut self, category: &str, duplicates: &[String]) {
+        if duplicates.is_empty() {
+            self.status.duplicates.remove(category);
+        } else {
+            self.status
+                .duplicates
+                .insert(String::from(category), duplicates.to_vec());
+        }
+    }
+
+    fn record_endpoint_request(&mut self, url: &str, version: Option<String>) {
+        let endpoint = self.status.endpoints.entry(String::from(url)).or_default();
+        endpoint.requests += 1;
+        match version {
+            Some(version) => *endpoint.versions.entry(version).or_default() += 1,
+            None => endpoint.failures += 1,
+        }
+    }
+}
+
+fn registry() -> &'static Mutex<Registry> {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(Mutex::default)
+}
+
+fn with_registry<T>(f: impl FnOnce(&mut Registry) -> T) -> T {
+    let mut registry = registry().lock().unwrap_or_else(|error| error.into_inner());
+    f(&mut registry)
+}
+
+fn now() -> String {
+    chrono::Utc::now().to_rfc3339()
+}
+
+/// Marks the task as stopped when dropped, so early returns are counted too
+#[must_use = "task is marked as stopped when the guard is dropped"]
+pub struct TaskGuard(&'static str);
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        with_registry(|registry| registry.task_stopped(self.0));
+    }
+}
+
+pub fn task_started(name: &'static str) -> TaskGuard {
+    with_registry(|registry| registry.task_started(name));
+    TaskGuard(name)
+}
+
+pub fn mark_iteration(name: &str) {
+    with_registry(|registry| registry.mark_iteration(name, now()));
+}
+
+pub fn mark_idle(name: &str) {
+    with_registry(|registry| registry.mark_idle(name, now()));
+}
+
+pub fn set_startup_state(name: &str, state: ModuleState) {
+    with_registry(|registry| registry.set_startup_state(name, state, now()));
+}
+
+pub fn observe_channel<T>(name: &str, tx: &broadcast::Sender<T>) {
+    let channel = ChannelStatus {
+        receivers: tx.receiver_count(),
+        queued: tx.len(),
+        observed_at: now(),
+    };
+    with_registry(|registry| registry.observe_channel(name, channel));
+}
+
+/// `0` clears the entry
+#[cfg_attr(not(any(feature = "nut", feature = "active-sender")), allow(dead_code))]
+pub fn set_retries(key: &str, failed_attempts: u32) {
+    with_registry(|registry| registry.set_retries(key, failed_attempts));
+}
+
+/// Empty `duplicates` clears the entry
+pub fn set_duplicates(category: &str, duplicates: &[String]) {
+    with_registry(|registry| registry.set_duplicates(category, duplicates));
+}
+
+pub fn set_invalid_config(invalid_sections: &BTreeMap<String, String>) {
+    with_registry(|registry| registry.status.invalid_config = invalid_sections.clone());
+}
+
+pub fn get_invalid_config() -> BTreeMap<String, String> {
+    with_registry(|registry| registry.status.invalid_config.clone())
+}
+
+/// `version` is `None` if the request failed without a response
+#[cfg_attr(not(feature = "active-sender"), allow(dead_code))]
+pub fn record_endpoint_request(url: &str, version: Option<String>) {
+    with_registry(|registry| registry.record_endpoint_request(url, version));
+}
+
+/// Loops without waiting for the registry, `None` if it's locked, ex. by a panicking thread
+pub fn try_tasks() -> Option<BTreeMap<String, TaskStatus>> {
+    match registry().try_lock() {
+        Ok(registry) => Some(registry.status.tasks.clone()),
+        Err(TryLockError::Poisoned(error)) => Some(error.into_inner().status.tasks.clone()),
+        Err(TryLockError::WouldBlock) => None,
+    }
+}
+
+/// Module loops running right now, summed over every task name
+pub fn running_tasks() -> u32 {
+    with_registry(|registry| {
+        registry
+            .status
+            .tasks
+            .values()
+            .map(|task| task.running)
+            .sum()
+    })
+}
+
+pub fn snapshot() -> InternalStatus {
+    let mut status = with_registry(|registry| registry.status.clone());
+    status.lagged_messages = get_lagged_messages();
+    status.clock = clock::status();
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_lifecycle() {
+        let mut registry = Registry::default();
+        registry.task_started("nut");
+        registry.task_started("nut");
+        registry.mark_iteration("nut", String::from("2023-01-01T00:00:00+00:00"));
+        registry.mark_idle("nut", String::from("2023-01-01T00:00:01+00:00"));
+        registry.task_stopped("nut");
+        let task = &registry.status.tasks["nut"];
+        assert_eq!(task.running, 1);
+        assert_eq!(task.iterations, 1);
+        assert_eq!(
+            task.last_iteration.as_deref(),
+            Some("2023-01-01T00:00:00+00:00")
+        );
+        assert_eq!(task.last_idle.as_deref(), Some("2023-01-01T00:00:01+00:00"));
+    }
+
+    #[test]
+    fn test_retries_are_cleared() {
+        let mut registry = Registry::default();
+        registry.set_retries("nut:localhost", 3);
+        assert_eq!(registry.status.retries["nut:localhost"], 3);
+        registry.set_retries("nut:localhost", 0);
+        assert!(registry.status.retries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_observe_channel() {
+        let (tx, _rx) = broadcast::channel::<u8>(4);
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        observe_channel("test_channel", &tx);
+        let channel = &snapshot().channels["test_channel"];
+        assert_eq!(channel.receivers, 1);
+        assert_eq!(channel.queued, 2);
+    }
+
+    #[test]
+    fn test_record_endpoint_request() {
+        let mut registry = Registry::default();
+        registry.record_endpoint_request("http://a", Some(String::from("HTTP/2.0")));
+        registry.record_endpoint_request("http://a", None);
+        let endpoint = &registry.status.endpoints["http://a"];
+        assert_eq!(endpoint.requests, 2);
+        assert_eq!(endpoint.failures, 1);
+        assert_eq!(endpoint.versions["HTTP/2.0"], 1);
+    }
+}
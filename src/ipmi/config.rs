@@ -0,0 +1,130 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum IpmiTool {
+    // `ipmitool sdr elist`
+    #[default]
+    Ipmitool,
+    // `ipmi-sensors`, interprets discrete sensors (ex. PSU status) better
+    FreeIpmi,
+}
+
+impl IpmiTool {
+    pub fn default_command(&self) -> &'static str {
+        match self {
+            IpmiTool::Ipmitool => "ipmitool",
+            IpmiTool::FreeIpmi => "ipmi-sensors",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct IpmiBmcConfig {
+    // Remote BMC over LAN (IPMI 2.0), local BMC through the kernel driver if not set
+    pub host: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    // hw.id of the server, ipmi@<host> or ipmi@localhost if not set
+    pub id: Option<String>,
+    pub name: Option<String>,
+}
+
+impl IpmiBmcConfig {
+    pub fn get_id(&self) -> String {
+        self.id
+            .clone()
+            .unwrap_or_else(|| format!("ipmi@{}", self.host.as_deref().unwrap_or("localhost")))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IpmiConfig {
+    enabled: Option<bool>,
+    cooldown: Option<Duration>,
+    tool: Option<IpmiTool>,
+    // Path of the tool's executable, looked up in PATH by default
+    command: Option<String>,
+    // Local BMC only if not set
+    bmcs: Option<Vec<IpmiBmcConfig>>,
+}
+
+impl Default for IpmiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            cooldown: Some(Duration::from_secs(60)),
+            tool: Some(IpmiTool::default()),
+            command: None,
+            bmcs: None,
+        }
+    }
+}
+
+impl Example for IpmiConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(false),
+            cooldown: Some(Duration::from_secs(60)),
+            tool: Some(IpmiTool::Ipmitool),
+            command: None,
+            bmcs: Some(vec![
+                IpmiBmcConfig::default(),
+                IpmiBmcConfig {
+                    host: Some(String::from("10.0.0.20")),
+                    username: Some(String::from("ADMIN")),
+                    password: Some(String::from("EXAMPLE_PASSWORD")),
+                    id: None,
+                    name: Some(String::from("Backup server")),
+                },
+            ]),
+        }
+    }
+}
+
+impl IpmiConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_cooldown(&self) -> Duration {
+        self.cooldown.unwrap_or(Duration::from_secs(60))
+    }
+
+    pub fn get_tool(&self) -> IpmiTool {
+        self.tool.unwrap_or_default()
+    }
+
+    pub fn get_command(&self) -> String {
+        self.command
+            .clone()
+            .unwrap_or_else(|| String::from(self.get_tool().default_command()))
+    }
+
+    pub fn get_bmcs(&self) -> Vec<IpmiBmcConfig> {
+        match &self.bmcs {
+            Some(bmcs) if !bmcs.is_empty() => bmcs.clone(),
+            _ => vec![IpmiBmcConfig::default()],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults() {
+        let config = IpmiConfig::default();
+        assert_eq!(config.get_command(), "ipmitool");
+        assert_eq!(config.get_bmcs(), vec![IpmiBmcConfig::default()]);
+        assert_eq!(config.get_bmcs()[0].get_id(), "ipmi@localhost");
+        let config: IpmiConfig =
+            serde_json::from_str(r#"{"tool": "freeipmi", "bmcs": [{"host": "bmc.lan"}]}"#).unwrap();
+        assert_eq!(config.get_command(), "ipmi-sensors");
+        assert_eq!(config.get_bmcs()[0].get_id(), "ipmi@bmc.lan");
+    }
+}
@@ -0,0 +1,4 @@
+// Licensed under the Open Software License version 3.0
+pub mod config;
+mod sdr;
+pub mod sender;
@@ -0,0 +1,164 @@
+// Licensed under the Open Software License version 3.0
+//! Sensor Data Repository listings of ipmitool and FreeIPMI
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensorState {
+    Nominal,
+    Warning,
+    Critical,
+}
+
+impl SensorState {
+    fn value(&self) -> f64 {
+        match self {
+            SensorState::Nominal => 0.0,
+            SensorState::Warning => 1.0,
+            SensorState::Critical => 2.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sensor {
+    pub name: String,
+    // Threshold sensors only, ex. temperature, fan speed or voltage
+    pub reading: Option<f64>,
+    pub state: Option<SensorState>,
+}
+
+/// `ipmitool sdr elist` lines, ex. `CPU Temp | 30h | ok | 3.1 | 45 degrees C`
+pub fn parse_ipmitool(output: &str) -> Vec<Sensor> {
+    let mut sensors = Vec::new();
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split('|').map(str::trim).collect();
+        let [name, _number, status, _entity, reading] = fields[..] else {
+            continue;
+        };
+        let state = match status {
+            "ok" => Some(SensorState::Nominal),
+            "nc" => Some(SensorState::Warning),
+            "cr" | "nr" => Some(SensorState::Critical),
+            // Not present or no reading
+            "ns" => continue,
+            _ => None,
+        };
+        // Discrete sensors have text instead (ex. "Presence detected")
+        let reading = reading
+            .split_whitespace()
+            .next()
+            .and_then(|value| value.parse().ok());
+        sensors.push(Sensor {
+            name: String::from(name),
+            reading,
+            state,
+        });
+    }
+    sensors
+}
+
+/// `ipmi-sensors --comma-separated-output --no-header-output --output-sensor-state` lines,
+/// ex. `4,CPU Temp,Temperature,Nominal,45.00,C,'OK'`
+pub fn parse_freeipmi(output: &str) -> Vec<Sensor> {
+    let mut sensors = Vec::new();
+    for line in output.lines() {
+        let fields: Vec<&str> = line.splitn(7, ',').map(str::trim).collect();
+        let [_id, name, _type, state, reading, ..] = fields[..] else {
+            continue;
+        };
+        let state = match state {
+            "Nominal" => Some(SensorState::Nominal),
+            "Warning" => Some(SensorState::Warning),
+            "Critical" => Some(SensorState::Critical),
+            _ => None,
+        };
+        let reading = reading.parse().ok();
+        if reading.is_none() && state.is_none() {
+            continue;
+        }
+        sensors.push(Sensor {
+            name: String::from(name),
+            reading,
+            state,
+        });
+    }
+    sensors
+}
+
+/// Lowercase name with runs of other characters replaced by `_`, ex. `CPU1 Temp` -> `cpu1_temp`
+fn value_key(name: &str) -> String {
+    name.to_lowercase()
+        .split(|char: char| !char.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// `<name>` with the reading and `<name>_state` (0 nominal, 1 warning, 2 critical) of every sensor
+///
+/// Repeated names get `_2`, `_3`, ... suffixes in SDR order
+pub fn to_values(sensors: &[Sensor]) -> BTreeMap<String, f64> {
+    let mut values = BTreeMap::new();
+    let mut seen: BTreeMap<String, usize> = BTreeMap::new();
+    for sensor in sensors {
+        let mut key = value_key(&sensor.name);
+        let count = seen.entry(key.clone()).or_default();
+        *count += 1;
+        if *count > 1 {
+            key = format!("{}_{}", key, count);
+        }
+        if let Some(reading) = sensor.reading {
+            values.insert(key.clone(), reading);
+        }
+        if let Some(state) = sensor.state {
+            values.insert(format!("{}_state", key), state.value());
+        }
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ipmitool() {
+        let output = "\
+CPU1 Temp        | 30h | ok  |  3.1 | 45 degrees C
+FAN1             | 41h | cr  | 29.1 | 300 RPM
+12V              | 34h | ok  |  7.17 | 12.19 Volts
+PS1 Status       | C8h | ok  | 10.1 | Presence detected
+PS2 Status       | C9h | ns  | 10.2 | No Reading
+";
+        let values = to_values(&parse_ipmitool(output));
+        assert_eq!(
+            values,
+            BTreeMap::from([
+                (String::from("12v"), 12.19),
+                (String::from("12v_state"), 0.0),
+                (String::from("cpu1_temp"), 45.0),
+                (String::from("cpu1_temp_state"), 0.0),
+                (String::from("fan1"), 300.0),
+                (String::from("fan1_state"), 2.0),
+                (String::from("ps1_status_state"), 0.0),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_freeipmi() {
+        let output = "\
+4,CPU Temp,Temperature,Nominal,45.00,C,'OK'
+5,CPU Temp,Temperature,Warning,81.00,C,'At or Above (>=) Upper Non-Critical Threshold'
+60,PS1 Status,Power Supply,Critical,N/A,N/A,'Presence detected' 'Power Supply input lost (AC/DC)'
+61,Chassis Intru,Physical Security,N/A,N/A,N/A,N/A
+";
+        let values = to_values(&parse_freeipmi(output));
+        assert_eq!(values["cpu_temp"], 45.0);
+        assert_eq!(values["cpu_temp_2"], 81.0);
+        assert_eq!(values["cpu_temp_2_state"], 1.0);
+        assert_eq!(values["ps1_status_state"], 2.0);
+        assert!(!values.contains_key("ps1_status"));
+        assert_eq!(values.len(), 5);
+    }
+}
@@ -0,0 +1,266 @@
+// Licensed under the Open Software License version 3.0
+use super::{
+    config::{IpmiBmcConfig, IpmiConfig, IpmiTool},
+    sdr::{parse_freeipmi, parse_ipmitool, to_values},
+};
+use crate::{
+    dedup_log::{info_resolved, warn_deduplicated},
+    hardware::{
+        reading::{Reading, ReadingsUpdate},
+        types::{HardwareMetadata, HardwareType, SourceType},
+    },
+    introspection, sampling,
+    scheduler::job::{PollFuture, PollJob},
+};
+use std::{
+    cmp::max,
+    process::{Output, Stdio},
+    time::Duration,
+};
+use tokio::{
+    io::AsyncWriteExt,
+    process::Command,
+    sync::broadcast,
+    time::{sleep, timeout},
+};
+
+const PUBLISHER: &str = "ipmi";
+/// Remote BMCs may take a while to list all sensors
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn build_command(tool: IpmiTool, command: &str, bmc: &IpmiBmcConfig) -> Command {
+    let mut command = Command::new(command);
+    match tool {
+        IpmiTool::Ipmitool => {
+            if let Some(host) = &bmc.host {
+                command.args(["-I", "lanplus", "-H", host]);
+                if let Some(username) = &bmc.username {
+                    command.args(["-U", username]);
+                }
+                if let Some(password) = &bmc.password {
+                    // Read from the environment, so it doesn't show up in the process list
+                    command.arg("-E").env("IPMI_PASSWORD", password);
+                }
+            }
+            command.args(["sdr", "elist"]);
+        }
+        IpmiTool::FreeIpmi => {
+            if let Some(host) = &bmc.host {
+                command.args(["-D", "LAN_2_0", "-h", host]);
+                if let Some(username) = &bmc.username {
+                    command.args(["-u", username]);
+                }
+                if bmc.password.is_some() {
+                    // Read from stdin, so it doesn't show up in the process list
+                    command.arg("--config-file=/dev/stdin");
+                }
+            }
+            command.args([
+                "--comma-separated-output",
+                "--no-header-output",
+                "--output-sensor-state",
+                "--ignore-not-available-sensors",
+            ]);
+        }
+    }
+    command.kill_on_drop(true);
+    command
+}
+
+/// FreeIPMI config file passed on stdin, only the password is set there
+fn stdin_config(tool: IpmiTool, bmc: &IpmiBmcConfig) -> Option<String> {
+    match tool {
+        IpmiTool::Ipmitool => None,
+        IpmiTool::FreeIpmi => {
+            bmc.host.as_ref()?;
+            Some(format!("password {}\n", bmc.password.as_ref()?))
+        }
+    }
+}
+
+/// Same as `Command::output`, with `input` written to stdin
+async fn output_with_input(mut command: Command, input: Option<String>) -> std::io::Result<Output> {
+    command
+        .stdin(match input {
+            Some(_) => Stdio::piped(),
+            None => Stdio::null(),
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = command.spawn()?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        // Dropped afterwards, so the tool reads until the end of the file
+        stdin.write_all(input.as_bytes()).await?;
+    }
+    child.wait_with_output().await
+}
+
+async fn read_bmc(config: &IpmiConfig, bmc: &IpmiBmcConfig) -> Result<Reading, String> {
+    let tool = config.get_tool();
+    let command = config.get_command();
+    let output = output_with_input(build_command(tool, &command, bmc), stdin_config(tool, bmc));
+    let output = timeout(COMMAND_TIMEOUT, output)
+        .await
+        .map_err(|_| format!("{} timed out", command))?
+        .map_err(|error| format!("failed to run {}: {}", command, error))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} exited with {}: {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let sensors = match tool {
+        IpmiTool::Ipmitool => parse_ipmitool(&stdout),
+        IpmiTool::FreeIpmi => parse_freeipmi(&stdout),
+    };
+    if sensors.is_empty() {
+        return Err(String::from("no sensors with a reading"));
+    }
+    let mut meta =
+        HardwareMetadata::new(bmc.get_id(), HardwareType::Server, SourceType::Ipmi).measured_now();
+    meta.hw.name = bmc.name.clone();
+    let mut reading = Reading::new(meta);
+    reading.values = to_values(&sensors);
+    Ok(reading)
+}
+
+/// Readings of all reachable BMCs
+pub async fn read_bmcs(config: &IpmiConfig) -> Vec<Reading> {
+    let mut readings = Vec::new();
+    for bmc in config.get_bmcs() {
+        let id = bmc.get_id();
+        let key = format!("ipmi:{}", id);
+        match read_bmc(config, &bmc).await {
+            Ok(reading) => {
+                info_resolved!(key, "Reading IPMI sensors of {} again", id);
+                readings.push(reading);
+            }
+            Err(error) => {
+                warn_deduplicated!(key, "Failed to read IPMI sensors of {}: {}", id, error)
+            }
+        }
+    }
+    readings
+}
+
+pub struct IpmiPoller {
+    cooldown: Duration,
+    config: IpmiConfig,
+    tx: broadcast::Sender<ReadingsUpdate>,
+}
+
+impl IpmiPoller {
+    pub fn new(config: IpmiConfig, tx: broadcast::Sender<ReadingsUpdate>) -> Self {
+        Self {
+            cooldown: max(config.get_cooldown(), Duration::from_secs(10)),
+            config,
+            tx,
+        }
+    }
+
+    /// Publish all readable BMCs, returns delay until the next poll
+    pub async fn poll_once(&mut self) -> Duration {
+        introspection::mark_iteration("ipmi");
+        let readings = read_bmcs(&self.config).await;
+        tracing::trace!("Sending {:?} to channel", readings);
+        if self.tx.receiver_count() > 0 {
+            self.tx
+                .send(ReadingsUpdate::new(PUBLISHER, readings))
+                .unwrap();
+            introspection::observe_channel("readings", &self.tx);
+        }
+        introspection::mark_idle("ipmi");
+        self.cooldown
+    }
+}
+
+impl PollJob for IpmiPoller {
+    fn name(&self) -> String {
+        String::from("ipmi")
+    }
+
+    fn poll(&mut self) -> PollFuture<'_> {
+        Box::pin(self.poll_once())
+    }
+}
+
+pub async fn start_ipmi_loop(
+    mut shutdown_rx: broadcast::Receiver<()>,
+    config: IpmiConfig,
+    tx: broadcast::Sender<ReadingsUpdate>,
+) {
+    // Check if module is enabled
+    if !config.is_enabled() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+    tracing::debug!("Starting IPMI loop");
+    let mut poller = IpmiPoller::new(config, tx);
+    let _task = introspection::task_started("ipmi");
+    loop {
+        let delay = poller.poll_once().await;
+        tokio::select! {
+            _ = shutdown_rx.recv() => {
+                tracing::trace!("Shutting down IPMI loop");
+                break;
+            }
+            _ = sleep(sampling::tick::aligned(delay)) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_remote_ipmitool_command() {
+        let bmc = IpmiBmcConfig {
+            host: Some(String::from("10.0.0.20")),
+            username: Some(String::from("ADMIN")),
+            password: Some(String::from("secret")),
+            ..Default::default()
+        };
+        let command = build_command(IpmiTool::Ipmitool, "ipmitool", &bmc);
+        let args: Vec<_> = command.as_std().get_args().collect();
+        assert_eq!(
+            args,
+            [
+                "-I",
+                "lanplus",
+                "-H",
+                "10.0.0.20",
+                "-U",
+                "ADMIN",
+                "-E",
+                "sdr",
+                "elist"
+            ]
+        );
+        assert!(!args.contains(&std::ffi::OsStr::new("secret")));
+    }
+
+    #[test]
+    fn test_build_remote_freeipmi_command() {
+        let bmc = IpmiBmcConfig {
+            host: Some(String::from("10.0.0.20")),
+            username: Some(String::from("ADMIN")),
+            password: Some(String::from("secret")),
+            ..Default::default()
+        };
+        let command = build_command(IpmiTool::FreeIpmi, "ipmi-sensors", &bmc);
+        let args: Vec<_> = command.as_std().get_args().collect();
+        assert!(args.contains(&std::ffi::OsStr::new("--config-file=/dev/stdin")));
+        assert!(!args
+            .iter()
+            .any(|arg| arg.to_string_lossy().contains("secret")));
+        assert_eq!(
+            stdin_config(IpmiTool::FreeIpmi, &bmc).as_deref(),
+            Some("password secret\n")
+        );
+        assert_eq!(stdin_config(IpmiTool::Ipmitool, &bmc), None);
+    }
+}
@@ -0,0 +1,6 @@
+// Licensed under the Open Software License version 3.0
+//! Library part of universal-data-source, the daemon itself is built from `main.rs`
+//!
+//! Build with `--features client` to use the typed client of the passive endpoint.
+#[cfg(feature = "client")]
+pub mod client;
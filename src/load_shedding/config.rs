@@ -0,0 +1,137 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, time::Duration};
+
+/// Device plugged into a UPS that can be switched off to extend its runtime
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SheddableDevice {
+    pub name: String,
+    // Devices with lower priority are shed first
+    pub priority: u32,
+    // Estimated power draw in watts
+    pub power: f64,
+    // Smart plug actuators, each command is a program followed by its arguments
+    pub off_command: Option<Vec<String>>,
+    pub off_url: Option<String>,
+    pub on_command: Option<Vec<String>>,
+    pub on_url: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoadSheddingConfig {
+    enabled: Option<bool>,
+    // hw.id of the UPS -> devices plugged into it
+    upses: Option<HashMap<String, Vec<SheddableDevice>>>,
+    // Switch devices off while on battery instead of only recommending it
+    execute: Option<bool>,
+    // Shed devices until estimated runtime reaches this
+    target_runtime: Option<Duration>,
+}
+
+impl Default for LoadSheddingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            upses: None,
+            execute: Some(false),
+            target_runtime: Some(Duration::from_secs(30 * 60)),
+        }
+    }
+}
+
+impl Example for LoadSheddingConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(false),
+            upses: Some(HashMap::from([(
+                String::from("[ups1]ups-monitor@localhost:3493"),
+                vec![
+                    SheddableDevice {
+                        name: String::from("backup-nas"),
+                        priority: 1,
+                        power: 45.0,
+                        off_command: None,
+                        off_url: Some(String::from("http://192.168.1.50/relay/0?turn=off")),
+                        on_command: None,
+                        on_url: Some(String::from("http://192.168.1.50/relay/0?turn=on")),
+                    },
+                    SheddableDevice {
+                        name: String::from("lab-switch"),
+                        priority: 2,
+                        power: 20.0,
+                        off_command: Some(vec![
+                            String::from("/usr/local/bin/plug"),
+                            String::from("lab-switch"),
+                            String::from("off"),
+                        ]),
+                        off_url: None,
+                        on_command: Some(vec![
+                            String::from("/usr/local/bin/plug"),
+                            String::from("lab-switch"),
+                            String::from("on"),
+                        ]),
+                        on_url: None,
+                    },
+                ],
+            )])),
+            execute: Some(false),
+            target_runtime: Some(Duration::from_secs(30 * 60)),
+        }
+    }
+}
+
+impl LoadSheddingConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_ups_ids(&self) -> Vec<String> {
+        self.upses
+            .as_ref()
+            .map(|upses| upses.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Devices of a UPS in shed order, `None` if the UPS isn't configured
+    pub fn get_devices(&self, ups_id: &str) -> Option<Vec<SheddableDevice>> {
+        let mut devices = self.upses.as_ref()?.get(ups_id)?.clone();
+        // Stable, so devices with equal priority keep config order
+        devices.sort_by_key(|device| device.priority);
+        Some(devices)
+    }
+
+    pub fn get_execute(&self) -> bool {
+        self.execute.unwrap_or_default()
+    }
+
+    pub fn get_target_runtime(&self) -> Duration {
+        self.target_runtime.unwrap_or(Duration::from_secs(30 * 60))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_devices_in_shed_order() {
+        let mut config = LoadSheddingConfig::example();
+        let ups_id = config.get_ups_ids().pop().unwrap();
+        config
+            .upses
+            .as_mut()
+            .unwrap()
+            .get_mut(&ups_id)
+            .unwrap()
+            .reverse();
+        let names: Vec<String> = config
+            .get_devices(&ups_id)
+            .unwrap()
+            .into_iter()
+            .map(|device| device.name)
+            .collect();
+        assert_eq!(names, vec!["backup-nas", "lab-switch"]);
+        assert!(config.get_devices("unknown").is_none());
+    }
+}
@@ -0,0 +1,227 @@
+// Licensed under the Open Software License version 3.0
+use super::{
+    config::{LoadSheddingConfig, SheddableDevice},
+    plan::{count_steps_to_target, is_on_battery, is_on_line, plan_load_shedding},
+};
+use crate::{
+    introspection,
+    nut::sender::{UninterruptiblePowerSupplyData, UpsUpdate},
+    ups_shutdown::watcher::run_command,
+};
+use std::collections::{HashMap, HashSet};
+use tokio::sync::broadcast;
+
+#[cfg(feature = "active-sender")]
+async fn call_url(url: &str) {
+    tracing::info!("Calling {}", url);
+    let result = reqwest::Client::new()
+        .get(url)
+        .timeout(std::time::Duration::from_secs(5))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    if let Err(error) = result {
+        tracing::error!("Failed to call {}: {}", url, error);
+    }
+}
+
+#[cfg(not(feature = "active-sender"))]
+async fn call_url(url: &str) {
+    tracing::error!(
+        "Can't call {}, this binary was built without active-sender feature",
+        url
+    );
+}
+
+/// Run actuators of `device`, both the command and the URL if set
+async fn switch_device(device: &SheddableDevice, on: bool) {
+    let (command, url) = match on {
+        true => (&device.on_command, &device.on_url),
+        false => (&device.off_command, &device.off_url),
+    };
+    tracing::warn!(
+        "Switching {} {}",
+        device.name,
+        if on { "on" } else { "off" }
+    );
+    if let Some(command) = command {
+        run_command(command).await;
+    }
+    if let Some(url) = url {
+        call_url(url).await;
+    }
+}
+
+/// Shed devices needed to reach target runtime while on battery, restore them on utility power
+///
+/// UPSes without a known status (ex. failed poll) are left as they are.
+async fn update_ups(
+    config: &LoadSheddingConfig,
+    ups: &UninterruptiblePowerSupplyData,
+    devices: &[SheddableDevice],
+    shed: &mut HashSet<String>,
+) {
+    if is_on_line(ups) {
+        // Restore in reverse shed order
+        for device in devices.iter().rev() {
+            if shed.remove(&device.name) {
+                switch_device(device, true).await;
+            }
+        }
+        return;
+    }
+    if !is_on_battery(ups) {
+        tracing::debug!(
+            "Unknown status of {}, keeping devices as they are",
+            ups.meta.hw.id
+        );
+        return;
+    }
+    // Current load already excludes devices that are switched off
+    let remaining: Vec<SheddableDevice> = devices
+        .iter()
+        .filter(|device| !shed.contains(&device.name))
+        .cloned()
+        .collect();
+    let plan = plan_load_shedding(ups, &remaining);
+    let count = count_steps_to_target(&plan, config.get_target_runtime().as_secs_f64());
+    for device in &remaining[..count] {
+        shed.insert(device.name.clone());
+        switch_device(device, false).await;
+    }
+}
+
+pub async fn start_load_shedding_loop(
+    mut shutdown_rx: broadcast::Receiver<()>,
+    config: LoadSheddingConfig,
+    mut ups_monitoring_rx: broadcast::Receiver<UpsUpdate>,
+) {
+    // Recommendations are served by the passive endpoint, this loop only executes them
+    if !config.is_enabled() || !config.get_execute() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+    tracing::trace!("Starting load shedding loop");
+    // hw.id of the UPS -> names of devices switched off
+    let mut shed: HashMap<String, HashSet<String>> = HashMap::new();
+    let _task = introspection::task_started("load_shedding");
+    loop {
+        tokio::select! {
+            Ok(update) = ups_monitoring_rx.recv() => {
+                introspection::mark_iteration("load_shedding");
+                for ups in &update.upses {
+                    let devices = match config.get_devices(&ups.meta.hw.id) {
+                        Some(devices) => devices,
+                        None => continue,
+                    };
+                    let shed = shed.entry(ups.meta.hw.id.clone()).or_default();
+                    update_ups(&config, ups, &devices, shed).await;
+                }
+            }
+            _ = shutdown_rx.recv() => {
+                tracing::trace!("Shutting down load shedding loop");
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::types::Example;
+
+    #[tokio::test]
+    async fn test_sheds_until_target_and_restores() {
+        let config: LoadSheddingConfig = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "execute": true,
+            "target_runtime": { "secs": 1000, "nanos": 0 },
+        }))
+        .unwrap();
+        // Actuators are left empty, so nothing is actually run
+        let devices: Vec<SheddableDevice> = ["first", "second"]
+            .iter()
+            .enumerate()
+            .map(|(priority, name)| SheddableDevice {
+                name: String::from(*name),
+                priority: priority as u32,
+                power: 45.0,
+                off_command: None,
+                off_url: None,
+                on_command: None,
+                on_url: None,
+            })
+            .collect();
+        let mut ups = UninterruptiblePowerSupplyData::example();
+        for (variable, value) in [
+            ("ups.status", "OB"),
+            ("ups.realpower", "100"),
+            ("battery.runtime", "600"),
+        ] {
+            ups.variables
+                .insert(String::from(variable), String::from(value));
+        }
+        let mut shed = HashSet::new();
+        update_ups(&config, &ups, &devices, &mut shed).await;
+        // 600 s * 100 W / 55 W is enough
+        assert_eq!(shed, HashSet::from([String::from("first")]));
+
+        // Unknown status (ex. failed poll) doesn't restore anything
+        ups.variables.remove("ups.status");
+        update_ups(&config, &ups, &devices, &mut shed).await;
+        assert_eq!(shed, HashSet::from([String::from("first")]));
+
+        ups.variables
+            .insert(String::from("ups.status"), String::from("OL CHRG"));
+        update_ups(&config, &ups, &devices, &mut shed).await;
+        assert!(shed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_consecutive_updates_on_battery() {
+        let config: LoadSheddingConfig = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "execute": true,
+            "target_runtime": { "secs": 1000, "nanos": 0 },
+        }))
+        .unwrap();
+        let devices: Vec<SheddableDevice> = ["first", "second"]
+            .iter()
+            .enumerate()
+            .map(|(priority, name)| SheddableDevice {
+                name: String::from(*name),
+                priority: priority as u32,
+                power: 45.0,
+                off_command: None,
+                off_url: None,
+                on_command: None,
+                on_url: None,
+            })
+            .collect();
+        let mut ups = UninterruptiblePowerSupplyData::example();
+        for (variable, value) in [
+            ("ups.status", "OB"),
+            ("ups.realpower", "100"),
+            ("battery.runtime", "600"),
+        ] {
+            ups.variables
+                .insert(String::from(variable), String::from(value));
+        }
+        let mut shed = HashSet::new();
+        update_ups(&config, &ups, &devices, &mut shed).await;
+        assert_eq!(shed, HashSet::from([String::from("first")]));
+
+        // UPS now reports load without the first device and battery keeps draining
+        ups.variables
+            .insert(String::from("ups.realpower"), String::from("55"));
+        ups.variables
+            .insert(String::from("battery.runtime"), String::from("800"));
+        update_ups(&config, &ups, &devices, &mut shed).await;
+        // 800 s * 55 W / 10 W, first device isn't subtracted again
+        assert_eq!(
+            shed,
+            HashSet::from([String::from("first"), String::from("second")])
+        );
+    }
+}
@@ -0,0 +1,176 @@
+// Licensed under the Open Software License version 3.0
+//! Suggested shed order and runtime gained by switching devices off
+use super::config::SheddableDevice;
+use crate::nut::sender::UninterruptiblePowerSupplyData;
+use serde::{Deserialize, Serialize};
+
+// Avoid dividing by zero when everything is shed
+const MIN_LOAD_WATTS: f64 = 1.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShedStep {
+    pub name: String,
+    pub priority: u32,
+    pub power: f64,
+    // After shedding this and all previous devices, `None` if UPS doesn't report enough data
+    pub remaining_load_watts: Option<f64>,
+    pub estimated_runtime_secs: Option<f64>,
+    pub runtime_gained_secs: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoadSheddingPlan {
+    pub ups_id: String,
+    pub on_battery: bool,
+    pub load_watts: Option<f64>,
+    pub runtime_secs: Option<f64>,
+    // Devices in shed order
+    pub steps: Vec<ShedStep>,
+}
+
+fn get_number(ups: &UninterruptiblePowerSupplyData, variable: &str) -> Option<f64> {
+    ups.variables.get(variable)?.trim().parse().ok()
+}
+
+/// Real power if reported, otherwise load percentage of nominal (real or apparent) power
+fn get_load_watts(ups: &UninterruptiblePowerSupplyData) -> Option<f64> {
+    if let Some(watts) = get_number(ups, "ups.realpower") {
+        return Some(watts);
+    }
+    let load = get_number(ups, "ups.load")?;
+    let nominal = get_number(ups, "ups.realpower.nominal")
+        .or_else(|| get_number(ups, "ups.power.nominal"))?;
+    Some(load * nominal / 100.0)
+}
+
+/// Flags of `ups.status`, `None` if it wasn't read (ex. failed or partial poll)
+fn get_status_flags(ups: &UninterruptiblePowerSupplyData) -> Option<Vec<&str>> {
+    Some(
+        ups.variables
+            .get("ups.status")?
+            .split_whitespace()
+            .collect(),
+    )
+}
+
+pub fn is_on_battery(ups: &UninterruptiblePowerSupplyData) -> bool {
+    get_status_flags(ups).map_or(false, |flags| flags.contains(&"OB"))
+}
+
+/// Explicitly on utility power, unknown status isn't
+pub fn is_on_line(ups: &UninterruptiblePowerSupplyData) -> bool {
+    get_status_flags(ups).map_or(false, |flags| {
+        flags.contains(&"OL") && !flags.contains(&"OB")
+    })
+}
+
+/// Runtime is assumed to be inversely proportional to load, same as in `ups_runtime`
+pub fn plan_load_shedding(
+    ups: &UninterruptiblePowerSupplyData,
+    devices: &[SheddableDevice],
+) -> LoadSheddingPlan {
+    let load_watts = get_load_watts(ups);
+    let runtime_secs = get_number(ups, "battery.runtime");
+    let mut shed_watts = 0.0;
+    let steps = devices
+        .iter()
+        .map(|device| {
+            shed_watts += device.power;
+            let remaining_load_watts =
+                load_watts.map(|load| (load - shed_watts).max(MIN_LOAD_WATTS));
+            let estimated_runtime_secs = match (runtime_secs, load_watts, remaining_load_watts) {
+                (Some(runtime), Some(load), Some(remaining)) => Some(runtime * load / remaining),
+                _ => None,
+            };
+            ShedStep {
+                name: device.name.clone(),
+                priority: device.priority,
+                power: device.power,
+                remaining_load_watts,
+                estimated_runtime_secs,
+                runtime_gained_secs: estimated_runtime_secs
+                    .zip(runtime_secs)
+                    .map(|(estimated, runtime)| estimated - runtime),
+            }
+        })
+        .collect();
+    LoadSheddingPlan {
+        ups_id: ups.meta.hw.id.clone(),
+        on_battery: is_on_battery(ups),
+        load_watts,
+        runtime_secs,
+        steps,
+    }
+}
+
+/// Number of first steps needed to reach `target_secs`, all of them if it can't be reached
+pub fn count_steps_to_target(plan: &LoadSheddingPlan, target_secs: f64) -> usize {
+    match plan.runtime_secs {
+        Some(runtime) if runtime >= target_secs => 0,
+        _ => plan
+            .steps
+            .iter()
+            .position(|step| {
+                step.estimated_runtime_secs
+                    .map_or(false, |runtime| runtime >= target_secs)
+            })
+            .map_or(plan.steps.len(), |index| index + 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::types::Example, load_shedding::config::LoadSheddingConfig};
+
+    fn ups_on_battery() -> UninterruptiblePowerSupplyData {
+        let mut ups = UninterruptiblePowerSupplyData::example();
+        ups.meta.hw.id = String::from("[ups1]ups-monitor@localhost:3493");
+        for (variable, value) in [
+            ("ups.status", "OB DISCHRG"),
+            ("ups.load", "20"),
+            ("ups.realpower.nominal", "500"),
+            ("battery.runtime", "600"),
+        ] {
+            ups.variables
+                .insert(String::from(variable), String::from(value));
+        }
+        ups
+    }
+
+    #[test]
+    fn test_plan_load_shedding() {
+        let ups = ups_on_battery();
+        let devices = LoadSheddingConfig::example()
+            .get_devices(&ups.meta.hw.id)
+            .unwrap();
+        let plan = plan_load_shedding(&ups, &devices);
+        assert!(plan.on_battery);
+        // 20% of 500 W
+        assert_eq!(plan.load_watts, Some(100.0));
+        assert_eq!(plan.steps[0].name, "backup-nas");
+        assert_eq!(plan.steps[0].remaining_load_watts, Some(55.0));
+        // 600 s * 100 W / 35 W
+        let runtime = plan.steps[1].estimated_runtime_secs.unwrap();
+        assert!((runtime - 600.0 * 100.0 / 35.0).abs() < 1e-9);
+        assert!((plan.steps[1].runtime_gained_secs.unwrap() - (runtime - 600.0)).abs() < 1e-9);
+
+        assert_eq!(count_steps_to_target(&plan, 300.0), 0);
+        assert_eq!(count_steps_to_target(&plan, 1000.0), 1);
+        assert_eq!(count_steps_to_target(&plan, 10_000.0), 2);
+    }
+
+    #[test]
+    fn test_plan_without_load_data() {
+        let ups = UninterruptiblePowerSupplyData::example();
+        let devices = LoadSheddingConfig::example()
+            .get_devices("[ups1]ups-monitor@localhost:3493")
+            .unwrap();
+        let plan = plan_load_shedding(&ups, &devices);
+        assert!(!plan.on_battery);
+        assert!(!is_on_line(&ups));
+        assert_eq!(plan.load_watts, None);
+        assert_eq!(plan.steps.len(), 2);
+        assert_eq!(plan.steps[0].estimated_runtime_secs, None);
+    }
+}
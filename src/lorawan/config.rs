@@ -0,0 +1,200 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ByteFieldType {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+}
+
+impl ByteFieldType {
+    pub fn size(&self) -> usize {
+        match self {
+            ByteFieldType::U8 | ByteFieldType::I8 => 1,
+            ByteFieldType::U16 | ByteFieldType::I16 => 2,
+            ByteFieldType::U32 | ByteFieldType::I32 => 4,
+        }
+    }
+}
+
+// Single value at a fixed position of the payload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ByteField {
+    pub name: String,
+    pub offset: usize,
+    #[serde(rename = "type")]
+    pub field_type: ByteFieldType,
+    // Big endian by default, same as most LoRa firmware
+    pub little_endian: Option<bool>,
+    // Raw value is multiplied by this, ex. 0.01 for centidegrees
+    pub scale: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "format", rename_all = "snake_case")]
+pub enum PayloadFormat {
+    CayenneLpp,
+    ByteMap { fields: Vec<ByteField> },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoRaWanConfig {
+    enabled: Option<bool>,
+    host: Option<String>,
+    port: Option<u16>,
+    tls: Option<bool>,
+    // TTN application id with tenant, ex. "my-app@ttn"
+    username: Option<String>,
+    // TTN API key with the "Read application traffic" right
+    password: Option<String>,
+    // Application id with tenant is used instead of `+` if username is set
+    topic: Option<String>,
+    // Used for devices without an entry in `devices`
+    default_format: Option<PayloadFormat>,
+    // Keyed by TTN device_id
+    devices: Option<HashMap<String, PayloadFormat>>,
+}
+
+impl Default for LoRaWanConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            host: Some(String::from("eu1.cloud.thethings.network")),
+            port: Some(8883),
+            tls: Some(true),
+            username: None,
+            password: None,
+            topic: None,
+            default_format: Some(PayloadFormat::CayenneLpp),
+            devices: None,
+        }
+    }
+}
+
+impl Example for LoRaWanConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(false),
+            host: Some(String::from("eu1.cloud.thethings.network")),
+            port: Some(8883),
+            tls: Some(true),
+            username: Some(String::from("my-app@ttn")),
+            password: Some(String::from("NNSXS.EXAMPLE_API_KEY")),
+            topic: None,
+            default_format: Some(PayloadFormat::CayenneLpp),
+            devices: Some(HashMap::from([(
+                String::from("garden-node"),
+                PayloadFormat::ByteMap {
+                    fields: vec![
+                        ByteField {
+                            name: String::from("temperature"),
+                            offset: 0,
+                            field_type: ByteFieldType::I16,
+                            little_endian: None,
+                            scale: Some(0.01),
+                        },
+                        ByteField {
+                            name: String::from("battery_mv"),
+                            offset: 2,
+                            field_type: ByteFieldType::U16,
+                            little_endian: None,
+                            scale: None,
+                        },
+                    ],
+                },
+            )])),
+        }
+    }
+}
+
+impl LoRaWanConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_host(&self) -> String {
+        self.host
+            .clone()
+            .unwrap_or_else(|| String::from("eu1.cloud.thethings.network"))
+    }
+
+    pub fn get_port(&self) -> u16 {
+        self.port.unwrap_or(8883)
+    }
+
+    pub fn get_tls(&self) -> bool {
+        self.tls.unwrap_or(true)
+    }
+
+    pub fn get_username(&self) -> Option<String> {
+        self.username.clone()
+    }
+
+    pub fn get_password(&self) -> Option<String> {
+        self.password.clone()
+    }
+
+    /// Uplinks of every device in the application
+    pub fn get_topic(&self) -> String {
+        if let Some(topic) = &self.topic {
+            return topic.clone();
+        }
+        match &self.username {
+            Some(application) => format!("v3/{}/devices/+/up", application),
+            None => String::from("v3/+/devices/+/up"),
+        }
+    }
+
+    pub fn get_format(&self, device_id: &str) -> PayloadFormat {
+        self.devices
+            .as_ref()
+            .and_then(|devices| devices.get(device_id))
+            .or(self.default_format.as_ref())
+            .cloned()
+            .unwrap_or(PayloadFormat::CayenneLpp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_topic() {
+        assert_eq!(
+            LoRaWanConfig::example().get_topic(),
+            "v3/my-app@ttn/devices/+/up"
+        );
+        assert_eq!(LoRaWanConfig::default().get_topic(), "v3/+/devices/+/up");
+    }
+
+    #[test]
+    fn test_get_format() {
+        let config = LoRaWanConfig::example();
+        assert!(matches!(
+            config.get_format("garden-node"),
+            PayloadFormat::ByteMap { .. }
+        ));
+        assert_eq!(config.get_format("other"), PayloadFormat::CayenneLpp);
+    }
+
+    #[test]
+    fn test_deserialize_byte_map() {
+        let format: PayloadFormat = serde_json::from_value(serde_json::json!({
+            "format": "byte_map",
+            "fields": [{ "name": "humidity", "offset": 1, "type": "u8" }]
+        }))
+        .unwrap();
+        let PayloadFormat::ByteMap { fields } = format else {
+            panic!("expected byte map");
+        };
+        assert_eq!(fields[0].field_type, ByteFieldType::U8);
+    }
+}
@@ -0,0 +1,153 @@
+// Licensed under the Open Software License version 3.0
+//! Decoding of raw uplink payloads into named values
+use super::config::{ByteField, ByteFieldType, PayloadFormat};
+use std::collections::BTreeMap;
+
+/// Read a big endian integer, sign-extended if `signed`
+fn read_be(bytes: &[u8], signed: bool) -> f64 {
+    let mut value: i64 = 0;
+    for byte in bytes {
+        value = (value << 8) | *byte as i64;
+    }
+    let bits = bytes.len() * 8;
+    if signed && bits < 64 && value & (1 << (bits - 1)) != 0 {
+        value -= 1 << bits;
+    }
+    value as f64
+}
+
+/// Channel values of a Cayenne LPP payload, named `<type>_<channel>`
+///
+/// Multi-axis types are split into `<type>_<channel>_<axis>`.
+pub fn decode_cayenne_lpp(payload: &[u8]) -> Result<BTreeMap<String, f64>, String> {
+    let mut values = BTreeMap::new();
+    let mut rest = payload;
+    while !rest.is_empty() {
+        if rest.len() < 2 {
+            return Err(String::from("truncated channel header"));
+        }
+        let (channel, data_type) = (rest[0], rest[1]);
+        // (name, size, signed, divisor, axes)
+        let (name, size, signed, divisor, axes): (&str, usize, bool, f64, &[&str]) = match data_type
+        {
+            0 => ("digital_input", 1, false, 1.0, &[]),
+            1 => ("digital_output", 1, false, 1.0, &[]),
+            2 => ("analog_input", 2, true, 100.0, &[]),
+            3 => ("analog_output", 2, true, 100.0, &[]),
+            101 => ("illuminance", 2, false, 1.0, &[]),
+            102 => ("presence", 1, false, 1.0, &[]),
+            103 => ("temperature", 2, true, 10.0, &[]),
+            104 => ("humidity", 1, false, 2.0, &[]),
+            113 => ("accelerometer", 2, true, 1000.0, &["x", "y", "z"]),
+            115 => ("barometer", 2, false, 10.0, &[]),
+            134 => ("gyrometer", 2, true, 100.0, &["x", "y", "z"]),
+            _ => return Err(format!("unsupported data type {}", data_type)),
+        };
+        let count = axes.len().max(1);
+        let data = &rest[2..];
+        if data.len() < size * count {
+            return Err(format!("truncated {} on channel {}", name, channel));
+        }
+        if axes.is_empty() {
+            let value = read_be(&data[..size], signed) / divisor;
+            values.insert(format!("{}_{}", name, channel), value);
+        }
+        for (index, axis) in axes.iter().enumerate() {
+            let value = read_be(&data[index * size..(index + 1) * size], signed) / divisor;
+            values.insert(format!("{}_{}_{}", name, channel, axis), value);
+        }
+        rest = &data[size * count..];
+    }
+    Ok(values)
+}
+
+fn decode_field(payload: &[u8], field: &ByteField) -> Result<f64, String> {
+    let size = field.field_type.size();
+    let bytes = payload
+        .get(field.offset..field.offset + size)
+        .ok_or_else(|| format!("{} is outside of the payload", field.name))?;
+    let mut bytes = bytes.to_vec();
+    if field.little_endian.unwrap_or_default() {
+        bytes.reverse();
+    }
+    let signed = matches!(
+        field.field_type,
+        ByteFieldType::I8 | ByteFieldType::I16 | ByteFieldType::I32
+    );
+    Ok(read_be(&bytes, signed) * field.scale.unwrap_or(1.0))
+}
+
+pub fn decode_byte_map(
+    payload: &[u8],
+    fields: &[ByteField],
+) -> Result<BTreeMap<String, f64>, String> {
+    fields
+        .iter()
+        .map(|field| Ok((field.name.clone(), decode_field(payload, field)?)))
+        .collect()
+}
+
+pub fn decode(payload: &[u8], format: &PayloadFormat) -> Result<BTreeMap<String, f64>, String> {
+    match format {
+        PayloadFormat::CayenneLpp => decode_cayenne_lpp(payload),
+        PayloadFormat::ByteMap { fields } => decode_byte_map(payload, fields),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_cayenne_lpp() {
+        // Temperature 27.2 on channel 3, humidity 50% on channel 5, accelerometer on channel 6
+        let payload = [
+            0x03, 0x67, 0x01, 0x10, 0x05, 0x68, 0x64, 0x06, 0x71, 0x04, 0xD2, 0xFB, 0x2E, 0x00,
+            0x00,
+        ];
+        let values = decode_cayenne_lpp(&payload).unwrap();
+        assert_eq!(values["temperature_3"], 27.2);
+        assert_eq!(values["humidity_5"], 50.0);
+        assert_eq!(values["accelerometer_6_x"], 1.234);
+        assert_eq!(values["accelerometer_6_y"], -1.234);
+        assert_eq!(values["accelerometer_6_z"], 0.0);
+    }
+
+    #[test]
+    fn test_decode_cayenne_lpp_negative_temperature() {
+        let values = decode_cayenne_lpp(&[0x01, 0x67, 0xFF, 0xD7]).unwrap();
+        assert_eq!(values["temperature_1"], -4.1);
+    }
+
+    #[test]
+    fn test_decode_cayenne_lpp_errors() {
+        assert!(decode_cayenne_lpp(&[0x01]).is_err());
+        assert!(decode_cayenne_lpp(&[0x01, 0x67, 0x00]).is_err());
+        assert!(decode_cayenne_lpp(&[0x01, 0xFF, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_decode_byte_map() {
+        let fields = vec![
+            ByteField {
+                name: String::from("temperature"),
+                offset: 0,
+                field_type: ByteFieldType::I16,
+                little_endian: None,
+                scale: Some(0.01),
+            },
+            ByteField {
+                name: String::from("battery_mv"),
+                offset: 2,
+                field_type: ByteFieldType::U16,
+                little_endian: Some(true),
+                scale: None,
+            },
+        ];
+        // -5.5 degrees, 3300 mV (little endian)
+        let values = decode_byte_map(&[0xFD, 0xDA, 0xE4, 0x0C], &fields).unwrap();
+        assert_eq!(values["temperature"], -5.5);
+        assert_eq!(values["battery_mv"], 3300.0);
+        assert!(decode_byte_map(&[0xFD], &fields).is_err());
+    }
+}
@@ -0,0 +1,9 @@
+// Licensed under the Open Software License version 3.0
+#[cfg_attr(not(feature = "lorawan"), allow(dead_code))]
+pub mod config;
+// Only used by the lorawan feature and tests
+#[cfg_attr(not(feature = "lorawan"), allow(dead_code))]
+mod decoder;
+pub mod sender;
+#[cfg(feature = "lorawan")]
+mod uplink;
@@ -0,0 +1,119 @@
+// Licensed under the Open Software License version 3.0
+use super::config::LoRaWanConfig;
+use crate::hardware::reading::ReadingsUpdate;
+use tokio::sync::broadcast;
+
+#[cfg(feature = "lorawan")]
+mod client {
+    use super::super::uplink::parse_uplink;
+    use super::*;
+    use crate::{
+        dedup_log::{info_resolved, warn_deduplicated},
+        hardware::reading::Reading,
+        introspection,
+    };
+    use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS, Transport};
+    use std::{collections::BTreeMap, time::Duration};
+    use tokio::time::sleep;
+
+    const PUBLISHER: &str = "lorawan";
+
+    pub async fn run(
+        mut shutdown_rx: broadcast::Receiver<()>,
+        config: LoRaWanConfig,
+        tx: broadcast::Sender<ReadingsUpdate>,
+    ) {
+        let client_id = format!("universal-data-source-{}", std::process::id());
+        let mut options = MqttOptions::new(client_id, config.get_host(), config.get_port());
+        options.set_keep_alive(Duration::from_secs(30));
+        if let (Some(username), Some(password)) = (config.get_username(), config.get_password()) {
+            options.set_credentials(username, password);
+        }
+        if config.get_tls() {
+            options.set_transport(Transport::tls_with_default_config());
+        }
+        let (client, mut event_loop) = AsyncClient::new(options, 10);
+        let topic = config.get_topic();
+        // Every update replaces previous readings of this publisher, so keep all devices
+        let mut devices: BTreeMap<String, Reading> = BTreeMap::new();
+        tracing::debug!("Starting LoRaWAN loop");
+        let _task = introspection::task_started("lorawan");
+        loop {
+            let event = tokio::select! {
+                event = event_loop.poll() => event,
+                _ = shutdown_rx.recv() => {
+                    tracing::trace!("Shutting down LoRaWAN loop");
+                    break;
+                }
+            };
+            match event {
+                // Subscriptions don't survive reconnects with a clean session
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    tracing::debug!(
+                        "Connected to {}, subscribing to {}",
+                        config.get_host(),
+                        topic
+                    );
+                    info_resolved!("lorawan:connection", "Reconnected to {}", config.get_host());
+                    if let Err(error) = client.subscribe(&topic, QoS::AtMostOnce).await {
+                        tracing::error!("Failed to subscribe to {}: {}", topic, error);
+                    }
+                }
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    introspection::mark_iteration("lorawan");
+                    let reading = match parse_uplink(&publish.payload, &config) {
+                        Ok(Some(reading)) => reading,
+                        Ok(None) => continue,
+                        Err(error) => {
+                            tracing::warn!("Ignoring uplink on {}: {}", publish.topic, error);
+                            continue;
+                        }
+                    };
+                    devices.insert(reading.meta.hw.id.clone(), reading);
+                    let readings = devices.values().cloned().collect();
+                    tracing::trace!("Sending {:?} to channel", readings);
+                    if tx.receiver_count() > 0 {
+                        tx.send(ReadingsUpdate::new(PUBLISHER, readings)).unwrap();
+                        introspection::observe_channel("readings", &tx);
+                    }
+                }
+                Ok(_) => {}
+                Err(error) => {
+                    warn_deduplicated!(
+                        "lorawan:connection",
+                        "LoRaWAN MQTT connection failed: {}",
+                        error
+                    );
+                    // Event loop reconnects on the next poll
+                    tokio::select! {
+                        _ = sleep(Duration::from_secs(5)) => {}
+                        _ = shutdown_rx.recv() => break,
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub async fn start_lorawan_loop(
+    shutdown_rx: broadcast::Receiver<()>,
+    config: LoRaWanConfig,
+    tx: broadcast::Sender<ReadingsUpdate>,
+) {
+    // Check if module is enabled
+    if !config.is_enabled() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+
+    #[cfg(feature = "lorawan")]
+    client::run(shutdown_rx, config, tx).await;
+
+    #[cfg(not(feature = "lorawan"))]
+    {
+        let _ = (shutdown_rx, tx);
+        tracing::error!(
+            "LoRaWAN is enabled in config but this binary was built without lorawan feature"
+        );
+    }
+}
@@ -0,0 +1,123 @@
+// Licensed under the Open Software License version 3.0
+//! The Things Stack v3 uplink messages
+use super::{config::LoRaWanConfig, decoder::decode};
+use crate::hardware::{
+    reading::Reading,
+    types::{HardwareMetadata, HardwareType, SourceType},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct EndDeviceIds {
+    device_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RxMetadata {
+    rssi: Option<f64>,
+    snr: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UplinkMessage {
+    // Missing for MAC-only uplinks
+    frm_payload: Option<String>,
+    #[serde(default)]
+    rx_metadata: Vec<RxMetadata>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Uplink {
+    end_device_ids: EndDeviceIds,
+    uplink_message: Option<UplinkMessage>,
+}
+
+/// Decode an uplink into a reading of its device, `None` if it carries no application payload
+pub fn parse_uplink(message: &[u8], config: &LoRaWanConfig) -> Result<Option<Reading>, String> {
+    let uplink: Uplink = serde_json::from_slice(message).map_err(|error| error.to_string())?;
+    let device_id = uplink.end_device_ids.device_id;
+    let (payload, rx_metadata) = match uplink.uplink_message {
+        Some(UplinkMessage {
+            frm_payload: Some(payload),
+            rx_metadata,
+        }) => (payload, rx_metadata),
+        _ => return Ok(None),
+    };
+    let payload = STANDARD
+        .decode(payload)
+        .map_err(|error| format!("invalid frm_payload from {}: {}", device_id, error))?;
+    let values = decode(&payload, &config.get_format(&device_id))
+        .map_err(|error| format!("failed to decode uplink from {}: {}", device_id, error))?;
+    // Signal of the gateway that heard the device best
+    let best_gateway = rx_metadata
+        .iter()
+        .filter(|metadata| metadata.rssi.is_some())
+        .max_by(|a, b| {
+            a.rssi
+                .partial_cmp(&b.rssi)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+    let mut reading = Reading::new(
+        HardwareMetadata::new(device_id, HardwareType::RemoteSensor, SourceType::LoRaWan)
+            .measured_now(),
+    )
+    .with_value("rssi", best_gateway.and_then(|metadata| metadata.rssi))
+    .with_value("snr", best_gateway.and_then(|metadata| metadata.snr));
+    reading.values.extend(values);
+    Ok(Some(reading))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::types::Example;
+
+    fn uplink(device_id: &str, frm_payload: &str) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({
+            "end_device_ids": { "device_id": device_id, "dev_eui": "0004A30B001C0530" },
+            "uplink_message": {
+                "f_port": 1,
+                "frm_payload": frm_payload,
+                "rx_metadata": [
+                    { "gateway_ids": { "gateway_id": "far" }, "rssi": -110, "snr": -3.5 },
+                    { "gateway_ids": { "gateway_id": "near" }, "rssi": -70, "snr": 9.25 }
+                ]
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_cayenne_uplink() {
+        // Temperature 27.2 on channel 3
+        let message = uplink("attic-node", "A2cBEA==");
+        let reading = parse_uplink(&message, &LoRaWanConfig::example())
+            .unwrap()
+            .unwrap();
+        assert_eq!(reading.meta.hw.id, "attic-node");
+        assert_eq!(reading.meta.source.source_type, SourceType::LoRaWan);
+        assert_eq!(reading.values["temperature_3"], 27.2);
+        assert_eq!(reading.values["rssi"], -70.0);
+        assert_eq!(reading.values["snr"], 9.25);
+    }
+
+    #[test]
+    fn test_parse_byte_map_uplink() {
+        // -5.5 degrees, 3300 mV
+        let message = uplink("garden-node", "/doM5A==");
+        let reading = parse_uplink(&message, &LoRaWanConfig::example())
+            .unwrap()
+            .unwrap();
+        assert_eq!(reading.values["temperature"], -5.5);
+        assert_eq!(reading.values["battery_mv"], 3300.0);
+    }
+
+    #[test]
+    fn test_parse_uplink_without_payload() {
+        let message = br#"{"end_device_ids":{"device_id":"node"},"uplink_message":{"f_port":0}}"#;
+        assert_eq!(parse_uplink(message, &LoRaWanConfig::example()), Ok(None));
+        let message = uplink("node", "not base64!");
+        assert!(parse_uplink(&message, &LoRaWanConfig::example()).is_err());
+    }
+}
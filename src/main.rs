@@ -1,19 +1,115 @@
 // Licensed under the Open Software License version 3.0
-use active_sender::receiver::start_active_sender_loop;
-use config::file::read_config_or_create_default;
-use nut::sender::{start_nut_monitoring_loop, UninterruptiblePowerSupplyData};
+use active_sender::{start_active_sender_loop, ActiveSenderState};
+use apcupsd::sender::start_apcupsd_loop;
+use change_rate::derivative::start_change_rate_loop;
+use clock::start_clock_monitor_loop;
+use config::{
+    cli::{is_config_command, run_config_command},
+    effective::{
+        is_show_effective_config_command, log_startup_banner, run_show_effective_config_command,
+    },
+    file::{get_config_file_path, read_config_or_create_default},
+    instance::read_or_create_instance_id,
+};
+use cpu_freq::sender::start_cpu_freq_loop;
+use crash_report::hook::{config_hash, install_panic_hook};
+use degraded_mode::watcher::{is_restart_requested, restart_process, start_config_watcher_loop};
+use dht::sender::start_dht_loop;
+use export::cli::{is_export_command, run_export_command};
+use graphite::writer::start_graphite_sink_loop;
+use grpc::server::start_grpc_server_loop;
+use hardware::reading::ReadingsUpdate;
+use hot_reload::{supervisor::supervise, watcher::start_hot_reload_loop};
+use hwmon::sender::start_hwmon_loop;
+use i2c::sender::start_i2c_loop;
+use ipmi::sender::start_ipmi_loop;
+use load_shedding::executor::start_load_shedding_loop;
+use lorawan::sender::start_lorawan_loop;
+use modbus::sender::start_modbus_loop;
+use nut::sender::{start_nut_monitoring_loop, UpsUpdate};
 use one_wire::sender::{start_one_wire_updater_loop, MeasuredTemperature};
 use passive_endpoint::receiver::start_passive_endpoint_loop;
-use shutdown_notifier::start_shutdown_notifier;
-use tokio::sync::broadcast;
+use recording::{
+    player::{start_replay_loop, Channels},
+    recorder::start_recorder_loop,
+};
+use redis_sink::writer::start_redis_sink_loop;
+use scheduler::{
+    jobs::{create_poll_jobs, polled_sections},
+    runner::start_scheduler_loop,
+};
+use self_metrics::sender::start_self_metrics_loop;
+use shutdown_notifier::notifier::start_shutdown_notifier;
+use signing::cli::{is_verify_command, run_verify_command};
+use smart::sender::start_smart_loop;
+use snmp_ups::sender::start_snmp_ups_loop;
+use startup::Startup;
+use storage::{
+    cli::{is_storage_command, run_storage_command},
+    writer::start_storage_loop,
+};
+use thermal_zone::sender::start_thermal_zone_loop;
+use tokio::sync::{broadcast, oneshot, watch};
 use tracing_subscriber::EnvFilter;
+use ups_runtime::projection::start_ups_runtime_loop;
+use ups_shutdown::watcher::start_ups_shutdown_loop;
+use usb_hid::sender::start_usb_hid_loop;
+use watchdog::watcher::start_watchdog_loop;
+use zabbix::writer::start_zabbix_sink_loop;
 mod active_sender;
+mod apcupsd;
+mod bandwidth;
+mod change_rate;
+mod clock;
 mod config;
+mod cpu_freq;
+mod crash_report;
+mod dedup_log;
+mod degraded_mode;
+mod dht;
+mod export;
+mod fleet;
+mod graphite;
+mod grpc;
 mod hardware;
+mod hot_reload;
+mod hwmon;
+mod i2c;
+mod introspection;
+mod ipmi;
+mod load_shedding;
+mod lorawan;
+mod modbus;
 mod nut;
 mod one_wire;
 mod passive_endpoint;
+mod quality;
+mod recording;
+mod redis_sink;
+mod relations;
+mod sampling;
+mod scheduler;
+mod schema;
+mod self_metrics;
 mod shutdown_notifier;
+mod signing;
+mod smart;
+mod snmp_ups;
+#[cfg(all(test, feature = "soak"))]
+mod soak;
+mod startup;
+mod storage;
+mod thermal_zone;
+mod ups_runtime;
+mod ups_shutdown;
+mod usb_hid;
+mod wake_on_lan;
+mod watchdog;
+mod zabbix;
+
+#[cfg(feature = "module-memory")]
+#[global_allocator]
+static ALLOCATOR: watchdog::memory::ModuleAllocator = watchdog::memory::ModuleAllocator;
 
 #[tokio::main]
 async fn main() {
@@ -26,8 +122,49 @@ async fn main() {
         )
         .init();
 
+    // One-shot commands
+    let args: Vec<String> = std::env::args().collect();
+    if is_config_command(&args) {
+        if let Err(error) = run_config_command(&args) {
+            tracing::error!("{}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if is_export_command(&args) {
+        if let Err(error) = run_export_command(&args).await {
+            tracing::error!("Export failed: {}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if is_storage_command(&args) {
+        if let Err(error) = run_storage_command(&args) {
+            tracing::error!("Storage command failed: {}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if is_show_effective_config_command(&args) {
+        run_show_effective_config_command();
+        return;
+    }
+    if is_verify_command(&args) {
+        if let Err(error) = run_verify_command(&args) {
+            tracing::error!("Verification failed: {}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     // Read config file
     let config = read_config_or_create_default();
+    let instance_id = read_or_create_instance_id(&get_config_file_path());
+    log_startup_banner(&config, &instance_id);
+    install_panic_hook(&config.crash_report, config_hash(&config));
+    bandwidth::limiter::configure(&config.bandwidth);
+    sampling::tick::configure(&config.sampling);
+    let read_only = config.is_read_only();
 
     // Prepare channels for async tasks
     let (shutdown_tx, shutdown_rx) = broadcast::channel::<()>(1);
@@ -35,63 +172,762 @@ async fn main() {
     let (one_wire_tx, one_wire_rx) =
         broadcast::channel::<Vec<MeasuredTemperature>>(BROADCAST_CAPACITY);
     let (ups_monitoring_tx, ups_monitoring_rx) =
-        broadcast::channel::<Vec<UninterruptiblePowerSupplyData>>(BROADCAST_CAPACITY);
+        broadcast::channel::<UpsUpdate>(BROADCAST_CAPACITY);
+    let (readings_tx, readings_rx) = broadcast::channel::<ReadingsUpdate>(BROADCAST_CAPACITY);
+    // Supervised modules read their sections from here, hot reload publishes new ones
+    let (config_tx, config_rx) = watch::channel(config.clone());
+
+    // Opt-in: poll sources from a single task instead of one task per source loop
+    let scheduled = config.scheduler.is_enabled();
+
+    // Sinks start first, so the first readings of sources reach all of them
+    let startup = Startup::default();
+    let config_startup = startup.register("config", &[]);
+    const SINKS: &[&str] = &[
+        "active_sender",
+        "ups_shutdown",
+        "load_shedding",
+        "ups_runtime",
+        "change_rate",
+        "redis_sink",
+        "storage",
+        "zabbix",
+        "graphite",
+        "grpc",
+        "recorder",
+        "passive_endpoint",
+    ];
+    let active_sender_startup = startup.register("active_sender", &["config"]);
+    let ups_shutdown_startup = startup.register("ups_shutdown", &["config"]);
+    let load_shedding_startup = startup.register("load_shedding", &["config"]);
+    let ups_runtime_startup = startup.register("ups_runtime", &["config"]);
+    let change_rate_startup = startup.register("change_rate", &["config"]);
+    let redis_sink_startup = startup.register("redis_sink", &["config"]);
+    let storage_startup = startup.register("storage", &["config"]);
+    let zabbix_startup = startup.register("zabbix", &["config"]);
+    let graphite_startup = startup.register("graphite", &["config"]);
+    let grpc_startup = startup.register("grpc", &["config"]);
+    let recorder_startup = startup.register("recorder", &["config"]);
+    let passive_endpoint_startup = startup.register("passive_endpoint", &["config"]);
+    let one_wire_startup = startup.register("one_wire", SINKS);
+    let lorawan_startup = startup.register("lorawan", SINKS);
+    let self_metrics_startup = startup.register("self_metrics", SINKS);
+    let thermal_zone_startup = startup.register("thermal_zone", SINKS);
+    let hwmon_startup = startup.register("hwmon", SINKS);
+    let cpu_freq_startup = startup.register("cpu_freq", SINKS);
+    let dht_startup = startup.register("dht", SINKS);
+    let i2c_startup = startup.register("i2c", SINKS);
+    let smart_startup = startup.register("smart", SINKS);
+    let ipmi_startup = startup.register("ipmi", SINKS);
+    let modbus_startup = startup.register("modbus", SINKS);
+    let usb_hid_startup = startup.register("usb_hid", SINKS);
+    let apcupsd_startup = startup.register("apcupsd", SINKS);
+    let snmp_ups_startup = startup.register("snmp_ups", SINKS);
+    let ups_monitoring_startup = startup.register("ups_monitoring", SINKS);
+    let scheduler_startup = startup.register("scheduler", SINKS);
+    let replay_startup = startup.register("replay", SINKS);
+    config_startup.ready();
 
     // Gracefully shut down tasks
     // Active sender and passive endpoint shutdown when senders are dropped
+    let signals_clone = config.signals.clone();
+    let hot_reload = config.hot_reload.is_enabled();
     let shutdown_notifier_handle = tokio::spawn(async move {
-        start_shutdown_notifier(shutdown_tx).await;
+        start_shutdown_notifier(shutdown_tx, signals_clone, hot_reload).await;
+    });
+
+    // Restart once invalid config sections are corrected
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let degraded_mode_clone = config.degraded_mode.clone();
+    let config_watcher_handle = tokio::spawn(async move {
+        start_config_watcher_loop(shutdown_rx_clone, degraded_mode_clone).await;
+    });
+
+    // Apply changes of the config file by restarting affected modules
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let hot_reload_clone = config.hot_reload.clone();
+    let hot_reload_handle = tokio::spawn(async move {
+        start_hot_reload_loop(shutdown_rx_clone, hot_reload_clone, config_tx).await;
+    });
+
+    // Swap bandwidth limits in place, so sinks don't have to be restarted
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let config_rx_clone = config_rx.clone();
+    let bandwidth_handle = tokio::spawn(async move {
+        bandwidth::limiter::start_bandwidth_reload_loop(shutdown_rx_clone, config_rx_clone).await;
+    });
+
+    // Log or restart when modules exceed their budgets
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let watchdog_clone = config.watchdog.clone();
+    let watchdog_handle = tokio::spawn(async move {
+        start_watchdog_loop(shutdown_rx_clone, watchdog_clone).await;
+    });
+
+    // Detect NTP steps of the system clock, so ages of cached data stay correct
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let clock_monitor_handle = tokio::spawn(async move {
+        start_clock_monitor_loop(shutdown_rx_clone, Default::default()).await;
     });
 
     // Channel receivers
     // Periodically send data to an HTTP endpoint
     let shutdown_rx_clone = shutdown_rx.resubscribe();
-    let one_wire_rx_clone = one_wire_rx.resubscribe();
-    let ups_monitoring_rx_clone = ups_monitoring_rx.resubscribe();
-    let active_sender_handle = tokio::spawn(async move {
-        start_active_sender_loop(
-            shutdown_rx_clone,
-            config.active_data_sender,
-            one_wire_rx_clone,
-            ups_monitoring_rx_clone,
-        )
-        .await;
-    });
+    let one_wire_tx_clone = one_wire_tx.clone();
+    let ups_monitoring_tx_clone = ups_monitoring_tx.clone();
+    let readings_tx_clone = readings_tx.clone();
+    let instance_id_clone = instance_id.clone();
+    // Merged data, retry queues and cooldowns are handed over to restarted loops
+    let active_sender_state = ActiveSenderState::default();
+    let active_sender_handle = tokio::spawn(supervise(
+        active_sender_startup,
+        shutdown_rx_clone,
+        config_rx.clone(),
+        |config| config.active_data_sender.clone(),
+        move |shutdown_rx, active_data_sender| {
+            start_active_sender_loop(
+                shutdown_rx,
+                active_data_sender,
+                one_wire_tx_clone.subscribe(),
+                ups_monitoring_tx_clone.subscribe(),
+                readings_tx_clone.subscribe(),
+                instance_id_clone.clone(),
+                active_sender_state.clone(),
+            )
+        },
+    ));
+
+    // Run actions when the designated UPS is on battery with low charge
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let ups_monitoring_tx_clone = ups_monitoring_tx.clone();
+    let instance_id_clone = instance_id.clone();
+    let ups_shutdown_handle = tokio::spawn(supervise(
+        ups_shutdown_startup,
+        shutdown_rx_clone,
+        config_rx.clone(),
+        |config| config.ups_shutdown.clone(),
+        move |shutdown_rx, ups_shutdown| {
+            start_ups_shutdown_loop(
+                shutdown_rx,
+                ups_shutdown,
+                ups_monitoring_tx_clone.subscribe(),
+                instance_id_clone.clone(),
+            )
+        },
+    ));
+
+    // Switch off low priority devices while on battery
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let ups_monitoring_tx_clone = ups_monitoring_tx.clone();
+    let load_shedding_handle = tokio::spawn(supervise(
+        load_shedding_startup,
+        shutdown_rx_clone,
+        config_rx.clone(),
+        |config| config.load_shedding.clone(),
+        move |shutdown_rx, load_shedding| {
+            start_load_shedding_loop(
+                shutdown_rx,
+                load_shedding,
+                ups_monitoring_tx_clone.subscribe(),
+            )
+        },
+    ));
+
+    // Smoothed UPS runtime projection published as readings
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let ups_monitoring_tx_clone = ups_monitoring_tx.clone();
+    let readings_tx_clone = readings_tx.clone();
+    let ups_runtime_handle = tokio::spawn(supervise(
+        ups_runtime_startup,
+        shutdown_rx_clone,
+        config_rx.clone(),
+        |config| config.ups_runtime.clone(),
+        move |shutdown_rx, ups_runtime| {
+            start_ups_runtime_loop(
+                shutdown_rx,
+                ups_runtime,
+                ups_monitoring_tx_clone.subscribe(),
+                readings_tx_clone.clone(),
+            )
+        },
+    ));
+
+    // Temperature rate of change published as readings
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let one_wire_tx_clone = one_wire_tx.clone();
+    let readings_tx_clone = readings_tx.clone();
+    let change_rate_handle = tokio::spawn(supervise(
+        change_rate_startup,
+        shutdown_rx_clone,
+        config_rx.clone(),
+        |config| config.change_rate.clone(),
+        move |shutdown_rx, change_rate| {
+            start_change_rate_loop(
+                shutdown_rx,
+                change_rate,
+                one_wire_tx_clone.subscribe(),
+                readings_tx_clone.clone(),
+            )
+        },
+    ));
+
+    // Latest readings written to Redis
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let one_wire_tx_clone = one_wire_tx.clone();
+    let ups_monitoring_tx_clone = ups_monitoring_tx.clone();
+    let readings_tx_clone = readings_tx.clone();
+    let redis_sink_handle = tokio::spawn(supervise(
+        redis_sink_startup,
+        shutdown_rx_clone,
+        config_rx.clone(),
+        |config| config.redis.clone(),
+        move |shutdown_rx, redis| {
+            start_redis_sink_loop(
+                shutdown_rx,
+                redis,
+                one_wire_tx_clone.subscribe(),
+                ups_monitoring_tx_clone.subscribe(),
+                readings_tx_clone.subscribe(),
+            )
+        },
+    ));
+
+    // Every measurement appended to a local SQLite database
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let one_wire_tx_clone = one_wire_tx.clone();
+    let ups_monitoring_tx_clone = ups_monitoring_tx.clone();
+    let readings_tx_clone = readings_tx.clone();
+    let storage_handle = tokio::spawn(supervise(
+        storage_startup,
+        shutdown_rx_clone,
+        config_rx.clone(),
+        |config| config.storage.clone(),
+        move |shutdown_rx, storage| {
+            start_storage_loop(
+                shutdown_rx,
+                storage,
+                one_wire_tx_clone.subscribe(),
+                ups_monitoring_tx_clone.subscribe(),
+                readings_tx_clone.subscribe(),
+            )
+        },
+    ));
+
+    // Flat items sent to Zabbix trapper
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let one_wire_tx_clone = one_wire_tx.clone();
+    let ups_monitoring_tx_clone = ups_monitoring_tx.clone();
+    let readings_tx_clone = readings_tx.clone();
+    let zabbix_handle = tokio::spawn(supervise(
+        zabbix_startup,
+        shutdown_rx_clone,
+        config_rx.clone(),
+        |config| config.zabbix.clone(),
+        move |shutdown_rx, zabbix| {
+            start_zabbix_sink_loop(
+                shutdown_rx,
+                zabbix,
+                one_wire_tx_clone.subscribe(),
+                ups_monitoring_tx_clone.subscribe(),
+                readings_tx_clone.subscribe(),
+            )
+        },
+    ));
+
+    // Numeric values sent to Graphite carbon
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let one_wire_tx_clone = one_wire_tx.clone();
+    let ups_monitoring_tx_clone = ups_monitoring_tx.clone();
+    let readings_tx_clone = readings_tx.clone();
+    let graphite_handle = tokio::spawn(supervise(
+        graphite_startup,
+        shutdown_rx_clone,
+        config_rx.clone(),
+        |config| config.graphite.clone(),
+        move |shutdown_rx, graphite| {
+            start_graphite_sink_loop(
+                shutdown_rx,
+                graphite,
+                one_wire_tx_clone.subscribe(),
+                ups_monitoring_tx_clone.subscribe(),
+                readings_tx_clone.subscribe(),
+            )
+        },
+    ));
+
+    // Typed gRPC API
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let one_wire_tx_clone = one_wire_tx.clone();
+    let ups_monitoring_tx_clone = ups_monitoring_tx.clone();
+    let instance_id_clone = instance_id.clone();
+    let grpc_handle = tokio::spawn(supervise(
+        grpc_startup,
+        shutdown_rx_clone,
+        config_rx.clone(),
+        |config| config.grpc.clone(),
+        move |shutdown_rx, grpc| {
+            start_grpc_server_loop(
+                shutdown_rx,
+                grpc,
+                one_wire_tx_clone.subscribe(),
+                ups_monitoring_tx_clone.subscribe(),
+                instance_id_clone.clone(),
+            )
+        },
+    ));
+
+    // Every broadcast written to a file for replay
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let one_wire_tx_clone = one_wire_tx.clone();
+    let ups_monitoring_tx_clone = ups_monitoring_tx.clone();
+    let readings_tx_clone = readings_tx.clone();
+    let recorder_handle = tokio::spawn(supervise(
+        recorder_startup,
+        shutdown_rx_clone,
+        config_rx.clone(),
+        |config| (config.record.clone(), config.replay.clone()),
+        move |shutdown_rx, (record, replay)| {
+            start_recorder_loop(
+                shutdown_rx,
+                record,
+                replay,
+                one_wire_tx_clone.subscribe(),
+                ups_monitoring_tx_clone.subscribe(),
+                readings_tx_clone.subscribe(),
+            )
+        },
+    ));
 
     // Passive endpoint that returns cached data on request
+    // Not supervised, so its cache and connections survive hot reloads
     // Don't clone receivers as this is the last receiving module
     let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let instance_id_clone = instance_id.clone();
     let passive_endpoint_handle = tokio::spawn(async move {
-        start_passive_endpoint_loop(
+        passive_endpoint_startup.wait_for_dependencies().await;
+        // Dependents start once the port is bound, not when binding is attempted
+        let (ready_tx, ready_rx) = oneshot::channel();
+        let endpoint = start_passive_endpoint_loop(
             shutdown_rx_clone,
             config.passive_data_endpoint,
             one_wire_rx,
             ups_monitoring_rx,
-        )
-        .await;
+            readings_rx,
+            config.wake_on_lan,
+            config.load_shedding,
+            config.fleet,
+            config.storage,
+            instance_id_clone,
+            read_only,
+            ready_tx,
+        );
+        let ready = async {
+            if ready_rx.await.is_ok() {
+                passive_endpoint_startup.ready();
+            }
+        };
+        tokio::join!(endpoint, ready);
     });
 
     // Channel senders
+    // Recorded broadcasts sent again, usually with other sources disabled
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let channels = Channels {
+        one_wire_tx: one_wire_tx.clone(),
+        ups_monitoring_tx: ups_monitoring_tx.clone(),
+        readings_tx: readings_tx.clone(),
+    };
+    let replay_handle = tokio::spawn(supervise(
+        replay_startup,
+        shutdown_rx_clone,
+        config_rx.clone(),
+        |config| config.replay.clone(),
+        move |shutdown_rx, replay| start_replay_loop(shutdown_rx, replay, channels.clone()),
+    ));
+
+    // Sources below are polled by the scheduler instead of their own loops when it's enabled
     // 1-Wire
     let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let config_rx_clone = config_rx.clone();
+    let one_wire_tx_clone = one_wire_tx.clone();
     let one_wire_handle = tokio::spawn(async move {
-        start_one_wire_updater_loop(shutdown_rx_clone, config.one_wire, one_wire_tx).await
+        if !scheduled {
+            supervise(
+                one_wire_startup,
+                shutdown_rx_clone,
+                config_rx_clone,
+                |config| {
+                    (
+                        config.one_wire.clone(),
+                        config.quality.clone(),
+                        config.relations.clone(),
+                    )
+                },
+                move |shutdown_rx, (one_wire, quality, relations)| {
+                    start_one_wire_updater_loop(
+                        shutdown_rx,
+                        one_wire,
+                        quality,
+                        relations,
+                        one_wire_tx_clone.clone(),
+                    )
+                },
+            )
+            .await
+        }
+    });
+
+    // LoRaWAN uplinks from The Things Network
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let readings_tx_clone = readings_tx.clone();
+    let lorawan_handle = tokio::spawn(supervise(
+        lorawan_startup,
+        shutdown_rx_clone,
+        config_rx.clone(),
+        |config| config.lorawan.clone(),
+        move |shutdown_rx, lorawan| {
+            start_lorawan_loop(shutdown_rx, lorawan, readings_tx_clone.clone())
+        },
+    ));
+
+    // Kernel thermal zones
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let config_rx_clone = config_rx.clone();
+    let readings_tx_clone = readings_tx.clone();
+    let thermal_zone_handle = tokio::spawn(async move {
+        if !scheduled {
+            supervise(
+                thermal_zone_startup,
+                shutdown_rx_clone,
+                config_rx_clone,
+                |config| config.thermal_zone.clone(),
+                move |shutdown_rx, thermal_zone| {
+                    start_thermal_zone_loop(shutdown_rx, thermal_zone, readings_tx_clone.clone())
+                },
+            )
+            .await
+        }
+    });
+
+    // Kernel hardware monitoring chips
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let config_rx_clone = config_rx.clone();
+    let readings_tx_clone = readings_tx.clone();
+    let hwmon_handle = tokio::spawn(async move {
+        if !scheduled {
+            supervise(
+                hwmon_startup,
+                shutdown_rx_clone,
+                config_rx_clone,
+                |config| config.hwmon.clone(),
+                move |shutdown_rx, hwmon| {
+                    start_hwmon_loop(shutdown_rx, hwmon, readings_tx_clone.clone())
+                },
+            )
+            .await
+        }
+    });
+
+    // CPU frequency and throttle counters
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let config_rx_clone = config_rx.clone();
+    let readings_tx_clone = readings_tx.clone();
+    let cpu_freq_handle = tokio::spawn(async move {
+        if !scheduled {
+            supervise(
+                cpu_freq_startup,
+                shutdown_rx_clone,
+                config_rx_clone,
+                |config| config.cpu_freq.clone(),
+                move |shutdown_rx, cpu_freq| {
+                    start_cpu_freq_loop(shutdown_rx, cpu_freq, readings_tx_clone.clone())
+                },
+            )
+            .await
+        }
+    });
+
+    // DHT22 sensors on GPIO pins
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let config_rx_clone = config_rx.clone();
+    let readings_tx_clone = readings_tx.clone();
+    let dht_handle = tokio::spawn(async move {
+        if !scheduled {
+            supervise(
+                dht_startup,
+                shutdown_rx_clone,
+                config_rx_clone,
+                |config| config.dht.clone(),
+                move |shutdown_rx, dht| start_dht_loop(shutdown_rx, dht, readings_tx_clone.clone()),
+            )
+            .await
+        }
+    });
+
+    // BME280, SHT31 and BMP180 sensors on I2C buses
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let config_rx_clone = config_rx.clone();
+    let readings_tx_clone = readings_tx.clone();
+    let i2c_handle = tokio::spawn(async move {
+        if !scheduled {
+            supervise(
+                i2c_startup,
+                shutdown_rx_clone,
+                config_rx_clone,
+                |config| config.i2c.clone(),
+                move |shutdown_rx, i2c| start_i2c_loop(shutdown_rx, i2c, readings_tx_clone.clone()),
+            )
+            .await
+        }
+    });
+
+    // Disk temperatures and health attributes from smartctl
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let config_rx_clone = config_rx.clone();
+    let readings_tx_clone = readings_tx.clone();
+    let smart_handle = tokio::spawn(async move {
+        if !scheduled {
+            supervise(
+                smart_startup,
+                shutdown_rx_clone,
+                config_rx_clone,
+                |config| config.smart.clone(),
+                move |shutdown_rx, smart| {
+                    start_smart_loop(shutdown_rx, smart, readings_tx_clone.clone())
+                },
+            )
+            .await
+        }
+    });
+
+    // Server sensors reported by local or remote BMCs
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let config_rx_clone = config_rx.clone();
+    let readings_tx_clone = readings_tx.clone();
+    let ipmi_handle = tokio::spawn(async move {
+        if !scheduled {
+            supervise(
+                ipmi_startup,
+                shutdown_rx_clone,
+                config_rx_clone,
+                |config| config.ipmi.clone(),
+                move |shutdown_rx, ipmi| {
+                    start_ipmi_loop(shutdown_rx, ipmi, readings_tx_clone.clone())
+                },
+            )
+            .await
+        }
+    });
+
+    // Energy meters and controllers over Modbus TCP or RTU
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let config_rx_clone = config_rx.clone();
+    let readings_tx_clone = readings_tx.clone();
+    let modbus_handle = tokio::spawn(async move {
+        if !scheduled {
+            supervise(
+                modbus_startup,
+                shutdown_rx_clone,
+                config_rx_clone,
+                |config| config.modbus.clone(),
+                move |shutdown_rx, modbus| {
+                    start_modbus_loop(shutdown_rx, modbus, readings_tx_clone.clone())
+                },
+            )
+            .await
+        }
+    });
+
+    // Daemon's own resource usage
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let config_rx_clone = config_rx.clone();
+    let readings_tx_clone = readings_tx.clone();
+    let instance_id_clone = instance_id.clone();
+    let self_metrics_handle = tokio::spawn(async move {
+        if !scheduled {
+            supervise(
+                self_metrics_startup,
+                shutdown_rx_clone,
+                config_rx_clone,
+                |config| config.self_metrics.clone(),
+                move |shutdown_rx, self_metrics| {
+                    start_self_metrics_loop(
+                        shutdown_rx,
+                        self_metrics,
+                        readings_tx_clone.clone(),
+                        instance_id_clone.clone(),
+                    )
+                },
+            )
+            .await
+        }
+    });
+
+    // Single task polling all sources above when scheduler is enabled
+    // Restarted when any of the polled sections change
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let config_rx_clone = config_rx.clone();
+    let one_wire_tx_clone = one_wire_tx.clone();
+    let ups_monitoring_tx_clone = ups_monitoring_tx.clone();
+    let readings_tx_clone = readings_tx.clone();
+    let scheduler_handle = tokio::spawn(async move {
+        if scheduled {
+            supervise(
+                scheduler_startup,
+                shutdown_rx_clone,
+                config_rx_clone,
+                polled_sections,
+                move |shutdown_rx, config| {
+                    let poll_jobs = create_poll_jobs(
+                        &config,
+                        &one_wire_tx_clone,
+                        &ups_monitoring_tx_clone,
+                        &readings_tx_clone,
+                        &instance_id,
+                    );
+                    start_scheduler_loop(shutdown_rx, poll_jobs)
+                },
+            )
+            .await
+        }
+    });
+
+    // UPSes connected over USB, without upsd
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let config_rx_clone = config_rx.clone();
+    let ups_monitoring_tx_clone = ups_monitoring_tx.clone();
+    let usb_hid_handle = tokio::spawn(async move {
+        if !scheduled {
+            supervise(
+                usb_hid_startup,
+                shutdown_rx_clone,
+                config_rx_clone,
+                |config| (config.usb_hid.clone(), config.relations.clone()),
+                move |shutdown_rx, (usb_hid, relations)| {
+                    start_usb_hid_loop(
+                        shutdown_rx,
+                        usb_hid,
+                        relations,
+                        ups_monitoring_tx_clone.clone(),
+                    )
+                },
+            )
+            .await
+        }
+    });
+
+    // UPSes monitored by apcupsd
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let config_rx_clone = config_rx.clone();
+    let ups_monitoring_tx_clone = ups_monitoring_tx.clone();
+    let apcupsd_handle = tokio::spawn(async move {
+        if !scheduled {
+            supervise(
+                apcupsd_startup,
+                shutdown_rx_clone,
+                config_rx_clone,
+                |config| (config.apcupsd.clone(), config.relations.clone()),
+                move |shutdown_rx, (apcupsd, relations)| {
+                    start_apcupsd_loop(
+                        shutdown_rx,
+                        apcupsd,
+                        relations,
+                        ups_monitoring_tx_clone.clone(),
+                    )
+                },
+            )
+            .await
+        }
+    });
+
+    // UPS management cards read over SNMP
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
+    let config_rx_clone = config_rx.clone();
+    let ups_monitoring_tx_clone = ups_monitoring_tx.clone();
+    let snmp_ups_handle = tokio::spawn(async move {
+        if !scheduled {
+            supervise(
+                snmp_ups_startup,
+                shutdown_rx_clone,
+                config_rx_clone,
+                |config| (config.snmp_ups.clone(), config.relations.clone()),
+                move |shutdown_rx, (snmp_ups, relations)| {
+                    start_snmp_ups_loop(
+                        shutdown_rx,
+                        snmp_ups,
+                        relations,
+                        ups_monitoring_tx_clone.clone(),
+                    )
+                },
+            )
+            .await
+        }
     });
 
     // Network UPS tools
     // Don't clone shutdown_rx as this is the last module
     let ups_monitoring_handle = tokio::spawn(async move {
-        start_nut_monitoring_loop(shutdown_rx, config.ups_monitoring, ups_monitoring_tx).await
+        if !scheduled {
+            supervise(
+                ups_monitoring_startup,
+                shutdown_rx,
+                config_rx,
+                |config| (config.ups_monitoring.clone(), config.relations.clone()),
+                move |shutdown_rx, (ups_monitoring, relations)| {
+                    start_nut_monitoring_loop(
+                        shutdown_rx,
+                        ups_monitoring,
+                        relations,
+                        ups_monitoring_tx.clone(),
+                    )
+                },
+            )
+            .await
+        }
     });
 
     // Join handles
     let _ = tokio::try_join!(
         shutdown_notifier_handle,
+        config_watcher_handle,
+        hot_reload_handle,
+        bandwidth_handle,
+        watchdog_handle,
+        clock_monitor_handle,
         active_sender_handle,
+        ups_shutdown_handle,
+        load_shedding_handle,
+        ups_runtime_handle,
+        change_rate_handle,
+        redis_sink_handle,
+        storage_handle,
+        zabbix_handle,
+        graphite_handle,
+        grpc_handle,
+        recorder_handle,
         passive_endpoint_handle,
+        replay_handle,
         one_wire_handle,
+        lorawan_handle,
+        thermal_zone_handle,
+        hwmon_handle,
+        cpu_freq_handle,
+        dht_handle,
+        i2c_handle,
+        smart_handle,
+        ipmi_handle,
+        modbus_handle,
+        self_metrics_handle,
+        scheduler_handle,
+        usb_hid_handle,
+        apcupsd_handle,
+        snmp_ups_handle,
         ups_monitoring_handle
     );
 
+    drop(config_startup);
     tracing::debug!("Successfully shut down");
+    if is_restart_requested() {
+        restart_process();
+    }
 }
@@ -0,0 +1,168 @@
+// Licensed under the Open Software License version 3.0
+//! Register reads over TCP or a serial line
+use super::{
+    config::{ModbusTransport, RegisterKind},
+    frame::{
+        decode_mbap_header, decode_read_response, encode_read_request, encode_tcp, function_code,
+        MBAP_HEADER_LENGTH,
+    },
+};
+use std::time::Duration;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    time::timeout,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+fn to_string(error: std::io::Error) -> String {
+    error.to_string()
+}
+
+/// Connection to a TCP device or a serial bus, opened for a single poll
+pub enum Connection {
+    Tcp {
+        stream: TcpStream,
+        transaction_id: u16,
+    },
+    #[cfg(feature = "modbus-rtu")]
+    Rtu(tokio_serial::SerialStream),
+}
+
+impl Connection {
+    pub async fn open(transport: &ModbusTransport) -> Result<Self, String> {
+        match transport {
+            ModbusTransport::Tcp { address } => {
+                let stream = timeout(TIMEOUT, TcpStream::connect(address))
+                    .await
+                    .map_err(|_| String::from("connection timed out"))?
+                    .map_err(to_string)?;
+                Ok(Self::Tcp {
+                    stream,
+                    transaction_id: 0,
+                })
+            }
+            #[cfg(feature = "modbus-rtu")]
+            ModbusTransport::Rtu {
+                serial_port,
+                baud_rate,
+                parity,
+            } => {
+                use super::config::Parity;
+                use tokio_serial::SerialPortBuilderExt;
+
+                let parity = match parity {
+                    Parity::None => tokio_serial::Parity::None,
+                    Parity::Even => tokio_serial::Parity::Even,
+                    Parity::Odd => tokio_serial::Parity::Odd,
+                };
+                tokio_serial::new(serial_port, *baud_rate)
+                    .parity(parity)
+                    .timeout(TIMEOUT)
+                    .open_native_async()
+                    .map(Self::Rtu)
+                    .map_err(|error| error.to_string())
+            }
+            #[cfg(not(feature = "modbus-rtu"))]
+            ModbusTransport::Rtu { .. } => Err(String::from(
+                "Modbus RTU requires a binary built with modbus-rtu feature",
+            )),
+        }
+    }
+
+    /// Values of `quantity` registers starting at `address`
+    pub async fn read_registers(
+        &mut self,
+        unit_id: u8,
+        kind: RegisterKind,
+        address: u16,
+        quantity: u16,
+    ) -> Result<Vec<u16>, String> {
+        let function = function_code(kind);
+        let request = encode_read_request(function, address, quantity);
+        let response = timeout(TIMEOUT, self.exchange(unit_id, &request))
+            .await
+            .map_err(|_| String::from("no response"))??;
+        decode_read_response(function, quantity, &response)
+    }
+
+    /// Response PDU of request `pdu`
+    async fn exchange(&mut self, unit_id: u8, pdu: &[u8]) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Tcp {
+                stream,
+                transaction_id,
+            } => {
+                *transaction_id = transaction_id.wrapping_add(1);
+                stream
+                    .write_all(&encode_tcp(*transaction_id, unit_id, pdu))
+                    .await
+                    .map_err(to_string)?;
+                let mut header = [0u8; MBAP_HEADER_LENGTH];
+                stream.read_exact(&mut header).await.map_err(to_string)?;
+                let (response_id, length) = decode_mbap_header(&header)?;
+                let mut response = vec![0u8; length];
+                stream.read_exact(&mut response).await.map_err(to_string)?;
+                if response_id != *transaction_id {
+                    return Err(String::from("response to a different transaction"));
+                }
+                Ok(response)
+            }
+            #[cfg(feature = "modbus-rtu")]
+            Self::Rtu(port) => {
+                use super::frame::{
+                    decode_rtu, encode_rtu, rtu_remaining_length, RTU_HEADER_LENGTH,
+                };
+
+                port.write_all(&encode_rtu(unit_id, pdu))
+                    .await
+                    .map_err(to_string)?;
+                let mut header = [0u8; RTU_HEADER_LENGTH];
+                port.read_exact(&mut header).await.map_err(to_string)?;
+                let mut frame = header.to_vec();
+                frame.resize(RTU_HEADER_LENGTH + rtu_remaining_length(&header), 0);
+                port.read_exact(&mut frame[RTU_HEADER_LENGTH..])
+                    .await
+                    .map_err(to_string)?;
+                decode_rtu(unit_id, &frame).map(<[u8]>::to_vec)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_read_registers_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 12];
+            socket.read_exact(&mut request).await.unwrap();
+            // Read 2 input registers from address 12 of unit 5
+            assert_eq!(
+                request[2..],
+                [0x00, 0x00, 0x00, 0x06, 0x05, 0x04, 0x00, 0x0c, 0x00, 0x02]
+            );
+            let response = [
+                request[0], request[1], 0x00, 0x00, 0x00, 0x07, 0x05, 0x04, 0x04, 0x43, 0x66, 0x33,
+                0x33,
+            ];
+            socket.write_all(&response).await.unwrap();
+        });
+        let mut connection = Connection::open(&ModbusTransport::Tcp { address })
+            .await
+            .unwrap();
+        assert_eq!(
+            connection
+                .read_registers(5, RegisterKind::Input, 12, 2)
+                .await,
+            Ok(vec![0x4366, 0x3333])
+        );
+    }
+}
@@ -0,0 +1,281 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Default port of Modbus TCP
+const DEFAULT_PORT: u16 = 502;
+const DEFAULT_BAUD_RATE: u32 = 9600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RegisterKind {
+    // Function 0x03, read-write registers
+    #[default]
+    Holding,
+    // Function 0x04, read-only registers
+    Input,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RegisterType {
+    #[default]
+    U16,
+    I16,
+    U32,
+    I32,
+    // IEEE 754, ex. most energy meters
+    F32,
+}
+
+impl RegisterType {
+    /// Number of 16-bit registers
+    pub fn size(&self) -> u16 {
+        match self {
+            RegisterType::U16 | RegisterType::I16 => 1,
+            RegisterType::U32 | RegisterType::I32 | RegisterType::F32 => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Parity {
+    #[default]
+    None,
+    Even,
+    Odd,
+}
+
+// Single value stored in one or two consecutive registers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModbusRegisterConfig {
+    pub name: String,
+    // 0-based, ex. 0 for holding register 40001
+    pub address: u16,
+    pub kind: Option<RegisterKind>,
+    #[serde(rename = "type")]
+    pub register_type: Option<RegisterType>,
+    // Most significant register first by default
+    pub word_swap: Option<bool>,
+    // Raw value is multiplied by this, ex. 0.1 for tenths of a degree
+    pub scale: Option<f64>,
+}
+
+impl ModbusRegisterConfig {
+    pub fn get_kind(&self) -> RegisterKind {
+        self.kind.unwrap_or_default()
+    }
+
+    pub fn get_register_type(&self) -> RegisterType {
+        self.register_type.unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(not(feature = "modbus-rtu"), allow(dead_code))]
+pub enum ModbusTransport {
+    Tcp {
+        address: String,
+    },
+    Rtu {
+        serial_port: String,
+        baud_rate: u32,
+        parity: Parity,
+    },
+}
+
+// Either host (TCP) or serial_port (RTU) has to be set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModbusDeviceConfig {
+    host: Option<String>,
+    port: Option<u16>,
+    // ex. /dev/ttyUSB0 of an RS-485 adapter
+    serial_port: Option<String>,
+    baud_rate: Option<u32>,
+    parity: Option<Parity>,
+    // Slave address, also required by most TCP gateways
+    unit_id: Option<u8>,
+    // hw.id of the device, modbus@<host>:<port>/<unit_id> or modbus@<serial_port>/<unit_id> if not set
+    id: Option<String>,
+    name: Option<String>,
+    registers: Vec<ModbusRegisterConfig>,
+}
+
+impl Example for ModbusDeviceConfig {
+    fn example() -> Self {
+        Self {
+            host: None,
+            port: None,
+            serial_port: Some(String::from("/dev/ttyUSB0")),
+            baud_rate: Some(DEFAULT_BAUD_RATE),
+            parity: Some(Parity::None),
+            unit_id: Some(1),
+            id: None,
+            name: Some(String::from("Main energy meter")),
+            registers: vec![
+                ModbusRegisterConfig {
+                    name: String::from("voltage"),
+                    address: 0,
+                    kind: Some(RegisterKind::Input),
+                    register_type: Some(RegisterType::F32),
+                    word_swap: None,
+                    scale: None,
+                },
+                ModbusRegisterConfig {
+                    name: String::from("active_power"),
+                    address: 12,
+                    kind: Some(RegisterKind::Input),
+                    register_type: Some(RegisterType::F32),
+                    word_swap: None,
+                    scale: None,
+                },
+            ],
+        }
+    }
+}
+
+impl ModbusDeviceConfig {
+    pub fn get_transport(&self) -> Result<ModbusTransport, String> {
+        match (&self.host, &self.serial_port) {
+            (Some(host), None) => {
+                let port = self.port.unwrap_or(DEFAULT_PORT);
+                let address = if host.contains(':') {
+                    format!("[{}]:{}", host, port)
+                } else {
+                    format!("{}:{}", host, port)
+                };
+                Ok(ModbusTransport::Tcp { address })
+            }
+            (None, Some(serial_port)) => Ok(ModbusTransport::Rtu {
+                serial_port: serial_port.clone(),
+                baud_rate: self.baud_rate.unwrap_or(DEFAULT_BAUD_RATE),
+                parity: self.parity.unwrap_or_default(),
+            }),
+            _ => Err(String::from("set either host or serial_port")),
+        }
+    }
+
+    pub fn get_unit_id(&self) -> u8 {
+        self.unit_id.unwrap_or(1)
+    }
+
+    pub fn get_id(&self) -> String {
+        if let Some(id) = &self.id {
+            return id.clone();
+        }
+        let location = match self.get_transport() {
+            Ok(ModbusTransport::Tcp { address }) => address,
+            Ok(ModbusTransport::Rtu { serial_port, .. }) => serial_port,
+            Err(_) => String::from("unknown"),
+        };
+        format!("modbus@{}/{}", location, self.get_unit_id())
+    }
+
+    pub fn get_name(&self) -> Option<String> {
+        self.name.clone()
+    }
+
+    pub fn get_registers(&self) -> &[ModbusRegisterConfig] {
+        &self.registers
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModbusConfig {
+    enabled: Option<bool>,
+    cooldown: Option<Duration>,
+    devices: Option<Vec<ModbusDeviceConfig>>,
+}
+
+impl Default for ModbusConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            cooldown: Some(Duration::from_secs(10)),
+            devices: None,
+        }
+    }
+}
+
+impl Example for ModbusConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(false),
+            cooldown: Some(Duration::from_secs(10)),
+            devices: Some(vec![
+                ModbusDeviceConfig::example(),
+                ModbusDeviceConfig {
+                    host: Some(String::from("192.168.1.50")),
+                    port: Some(DEFAULT_PORT),
+                    serial_port: None,
+                    baud_rate: None,
+                    parity: None,
+                    unit_id: Some(1),
+                    id: Some(String::from("kiln-controller")),
+                    name: None,
+                    registers: vec![ModbusRegisterConfig {
+                        name: String::from("temperature"),
+                        address: 1,
+                        kind: None,
+                        register_type: Some(RegisterType::I16),
+                        word_swap: None,
+                        scale: Some(0.1),
+                    }],
+                },
+            ]),
+        }
+    }
+}
+
+impl ModbusConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_cooldown(&self) -> Duration {
+        self.cooldown.unwrap_or(Duration::from_secs(10))
+    }
+
+    pub fn get_devices(&self) -> Vec<ModbusDeviceConfig> {
+        self.devices.clone().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_config_get_id() {
+        assert_eq!(
+            ModbusDeviceConfig::example().get_id(),
+            "modbus@/dev/ttyUSB0/1"
+        );
+        let config: ModbusDeviceConfig = serde_json::from_str(
+            r#"{"host": "fd00::50", "unit_id": 3, "registers": [{"name": "power", "address": 4, "type": "u32"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(config.get_id(), "modbus@[fd00::50]:502/3");
+        assert_eq!(
+            config.get_registers()[0].get_register_type(),
+            RegisterType::U32
+        );
+        assert_eq!(config.get_registers()[0].get_kind(), RegisterKind::Holding);
+    }
+
+    #[test]
+    fn test_get_transport_requires_one_of_host_and_serial_port() {
+        let mut config = ModbusDeviceConfig::example();
+        config.host = Some(String::from("meter.lan"));
+        assert!(config.get_transport().is_err());
+        config.serial_port = None;
+        assert_eq!(
+            config.get_transport(),
+            Ok(ModbusTransport::Tcp {
+                address: String::from("meter.lan:502")
+            })
+        );
+    }
+}
@@ -0,0 +1,64 @@
+// Licensed under the Open Software License version 3.0
+//! Values of registers mapped with type, word order and scale
+use super::config::{ModbusRegisterConfig, RegisterType};
+
+pub fn decode_value(register: &ModbusRegisterConfig, words: &[u16]) -> f64 {
+    let (high, low) = match words {
+        [high, low] if register.word_swap.unwrap_or_default() => (*low, *high),
+        [high, low] => (*high, *low),
+        [single, ..] => (0, *single),
+        [] => (0, 0),
+    };
+    let raw = (high as u32) << 16 | low as u32;
+    let value = match register.get_register_type() {
+        RegisterType::U16 => low as f64,
+        RegisterType::I16 => low as i16 as f64,
+        RegisterType::U32 => raw as f64,
+        RegisterType::I32 => raw as i32 as f64,
+        RegisterType::F32 => f32::from_bits(raw) as f64,
+    };
+    value * register.scale.unwrap_or(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn register(
+        register_type: RegisterType,
+        word_swap: bool,
+        scale: Option<f64>,
+    ) -> ModbusRegisterConfig {
+        ModbusRegisterConfig {
+            name: String::from("value"),
+            address: 0,
+            kind: None,
+            register_type: Some(register_type),
+            word_swap: Some(word_swap),
+            scale,
+        }
+    }
+
+    #[test]
+    fn test_decode_value() {
+        assert_eq!(
+            decode_value(&register(RegisterType::I16, false, Some(0.1)), &[0xff38]),
+            -20.0
+        );
+        assert_eq!(
+            decode_value(&register(RegisterType::U16, false, None), &[0xff38]),
+            65336.0
+        );
+        assert_eq!(
+            decode_value(&register(RegisterType::U32, false, None), &[0x0001, 0x0002]),
+            65538.0
+        );
+        assert_eq!(
+            decode_value(&register(RegisterType::I32, true, None), &[0xfffe, 0xffff]),
+            -2.0
+        );
+        // 230.2 V as sent by Eastron SDM meters
+        let voltage = decode_value(&register(RegisterType::F32, false, None), &[0x4366, 0x3333]);
+        assert!((voltage - 230.2).abs() < 0.001);
+    }
+}
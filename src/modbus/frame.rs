@@ -0,0 +1,186 @@
+// Licensed under the Open Software License version 3.0
+//! Read requests with TCP (MBAP header) and RTU (CRC) framing
+use super::config::RegisterKind;
+
+const READ_HOLDING_REGISTERS: u8 = 0x03;
+const READ_INPUT_REGISTERS: u8 = 0x04;
+const EXCEPTION: u8 = 0x80;
+/// Transaction id, protocol id, length and unit id
+pub const MBAP_HEADER_LENGTH: usize = 7;
+/// Unit id, function and byte count (or exception code) of an RTU response
+pub const RTU_HEADER_LENGTH: usize = 3;
+
+pub fn function_code(kind: RegisterKind) -> u8 {
+    match kind {
+        RegisterKind::Holding => READ_HOLDING_REGISTERS,
+        RegisterKind::Input => READ_INPUT_REGISTERS,
+    }
+}
+
+pub fn encode_read_request(function: u8, address: u16, quantity: u16) -> Vec<u8> {
+    let mut pdu = vec![function];
+    pdu.extend_from_slice(&address.to_be_bytes());
+    pdu.extend_from_slice(&quantity.to_be_bytes());
+    pdu
+}
+
+fn exception_name(code: u8) -> &'static str {
+    match code {
+        1 => "illegal function",
+        2 => "illegal data address",
+        3 => "illegal data value",
+        4 => "server device failure",
+        6 => "server device busy",
+        10 => "gateway path unavailable",
+        11 => "gateway target device failed to respond",
+        _ => "unknown exception",
+    }
+}
+
+/// Register values of a read response PDU
+pub fn decode_read_response(function: u8, quantity: u16, pdu: &[u8]) -> Result<Vec<u16>, String> {
+    match pdu {
+        [code, exception] if *code == function | EXCEPTION => Err(format!(
+            "device responded with exception {} ({})",
+            exception,
+            exception_name(*exception)
+        )),
+        [code, count, data @ ..] if *code == function => {
+            if *count as usize != data.len() || data.len() != quantity as usize * 2 {
+                return Err(String::from("unexpected number of registers"));
+            }
+            Ok(data
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect())
+        }
+        _ => Err(String::from("unexpected response")),
+    }
+}
+
+pub fn encode_tcp(transaction_id: u16, unit_id: u8, pdu: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(MBAP_HEADER_LENGTH + pdu.len());
+    frame.extend_from_slice(&transaction_id.to_be_bytes());
+    // Protocol id is always 0
+    frame.extend_from_slice(&[0, 0]);
+    frame.extend_from_slice(&(pdu.len() as u16 + 1).to_be_bytes());
+    frame.push(unit_id);
+    frame.extend_from_slice(pdu);
+    frame
+}
+
+/// Transaction id and length of the PDU following the header
+pub fn decode_mbap_header(header: &[u8; MBAP_HEADER_LENGTH]) -> Result<(u16, usize), String> {
+    let transaction_id = u16::from_be_bytes([header[0], header[1]]);
+    if header[2..4] != [0, 0] {
+        return Err(String::from("not a Modbus response"));
+    }
+    let length = u16::from_be_bytes([header[4], header[5]]) as usize;
+    // Unit id is already part of the header, a PDU has at least function and one byte
+    if !(3..=254).contains(&length) {
+        return Err(String::from("invalid length"));
+    }
+    Ok((transaction_id, length - 1))
+}
+
+/// CRC-16/MODBUS
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xffffu16;
+    for byte in data {
+        crc ^= *byte as u16;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xa001
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+pub fn encode_rtu(unit_id: u8, pdu: &[u8]) -> Vec<u8> {
+    let mut frame = vec![unit_id];
+    frame.extend_from_slice(pdu);
+    // Low byte first, unlike everything else
+    frame.extend_from_slice(&crc16(&frame).to_le_bytes());
+    frame
+}
+
+/// Number of bytes following the header of an RTU response, including CRC
+pub fn rtu_remaining_length(header: &[u8; RTU_HEADER_LENGTH]) -> usize {
+    if header[1] & EXCEPTION != 0 {
+        2
+    } else {
+        header[2] as usize + 2
+    }
+}
+
+/// PDU of a complete RTU response
+pub fn decode_rtu(unit_id: u8, frame: &[u8]) -> Result<&[u8], String> {
+    if frame.len() < 4 {
+        return Err(String::from("truncated frame"));
+    }
+    let (content, crc) = frame.split_at(frame.len() - 2);
+    if crc16(content).to_le_bytes() != crc {
+        return Err(String::from("CRC mismatch"));
+    }
+    if content[0] != unit_id {
+        return Err(format!("response from unit {}", content[0]));
+    }
+    Ok(&content[1..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_rtu() {
+        // Read 10 holding registers from address 0 of unit 1
+        let request = encode_rtu(1, &encode_read_request(READ_HOLDING_REGISTERS, 0, 10));
+        assert_eq!(request, [0x01, 0x03, 0x00, 0x00, 0x00, 0x0a, 0xc5, 0xcd]);
+    }
+
+    #[test]
+    fn test_decode_rtu_response() {
+        let response = encode_rtu(1, &[0x04, 0x04, 0x43, 0x66, 0x33, 0x33]);
+        let header: [u8; RTU_HEADER_LENGTH] = response[..3].try_into().unwrap();
+        assert_eq!(rtu_remaining_length(&header), 6);
+        let pdu = decode_rtu(1, &response).unwrap();
+        assert_eq!(
+            decode_read_response(READ_INPUT_REGISTERS, 2, pdu),
+            Ok(vec![0x4366, 0x3333])
+        );
+        assert!(decode_rtu(2, &response).is_err());
+        let mut corrupted = response.clone();
+        corrupted[3] ^= 0xff;
+        assert_eq!(decode_rtu(1, &corrupted), Err(String::from("CRC mismatch")));
+    }
+
+    #[test]
+    fn test_decode_exception() {
+        let response = encode_rtu(1, &[0x83, 0x02]);
+        let header: [u8; RTU_HEADER_LENGTH] = response[..3].try_into().unwrap();
+        assert_eq!(rtu_remaining_length(&header), 2);
+        let error =
+            decode_read_response(READ_HOLDING_REGISTERS, 1, decode_rtu(1, &response).unwrap())
+                .unwrap_err();
+        assert_eq!(
+            error,
+            "device responded with exception 2 (illegal data address)"
+        );
+    }
+
+    #[test]
+    fn test_tcp_frame() {
+        let request = encode_tcp(7, 1, &encode_read_request(READ_INPUT_REGISTERS, 12, 2));
+        assert_eq!(
+            request,
+            [0x00, 0x07, 0x00, 0x00, 0x00, 0x06, 0x01, 0x04, 0x00, 0x0c, 0x00, 0x02]
+        );
+        let header: [u8; MBAP_HEADER_LENGTH] = [0x00, 0x07, 0x00, 0x00, 0x00, 0x07, 0x01];
+        assert_eq!(decode_mbap_header(&header), Ok((7, 6)));
+        assert!(decode_mbap_header(&[0x00, 0x07, 0x00, 0x01, 0x00, 0x07, 0x01]).is_err());
+    }
+}
@@ -0,0 +1,8 @@
+// Licensed under the Open Software License version 3.0
+mod client;
+pub mod config;
+mod decoder;
+// RTU framing is used only with modbus-rtu feature
+#[cfg_attr(not(feature = "modbus-rtu"), allow(dead_code))]
+mod frame;
+pub mod sender;
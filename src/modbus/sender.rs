@@ -0,0 +1,128 @@
+// Licensed under the Open Software License version 3.0
+use super::{
+    client::Connection,
+    config::{ModbusConfig, ModbusDeviceConfig},
+    decoder::decode_value,
+};
+use crate::{
+    dedup_log::{info_resolved, warn_deduplicated},
+    hardware::{
+        reading::{Reading, ReadingsUpdate},
+        types::{HardwareMetadata, HardwareType, SourceType},
+    },
+    introspection, sampling,
+    scheduler::job::{PollFuture, PollJob},
+};
+use std::{cmp::max, time::Duration};
+use tokio::{sync::broadcast, time::sleep};
+
+const PUBLISHER: &str = "modbus";
+
+/// All registers of `device`, a single failed read fails the whole device
+async fn read_device(device: &ModbusDeviceConfig) -> Result<Reading, String> {
+    let mut connection = Connection::open(&device.get_transport()?).await?;
+    let mut meta = HardwareMetadata::new(
+        device.get_id(),
+        HardwareType::IndustrialDevice,
+        SourceType::Modbus,
+    )
+    .measured_now();
+    meta.hw.name = device.get_name();
+    let mut reading = Reading::new(meta);
+    for register in device.get_registers() {
+        let words = connection
+            .read_registers(
+                device.get_unit_id(),
+                register.get_kind(),
+                register.address,
+                register.get_register_type().size(),
+            )
+            .await
+            .map_err(|error| format!("{}: {}", register.name, error))?;
+        reading = reading.with_value(&register.name, Some(decode_value(register, &words)));
+    }
+    Ok(reading)
+}
+
+/// Devices are read one after another, so devices on the same serial bus don't collide
+async fn read_devices(devices: &[ModbusDeviceConfig]) -> Vec<Reading> {
+    let mut readings = Vec::new();
+    for device in devices {
+        let id = device.get_id();
+        let key = format!("modbus:{}", id);
+        match read_device(device).await {
+            Ok(reading) => {
+                info_resolved!(key, "Reading {} again", id);
+                readings.push(reading);
+            }
+            Err(error) => warn_deduplicated!(key, "Failed to read {}: {}", id, error),
+        }
+    }
+    readings
+}
+
+pub struct ModbusPoller {
+    devices: Vec<ModbusDeviceConfig>,
+    cooldown: Duration,
+    tx: broadcast::Sender<ReadingsUpdate>,
+}
+
+impl ModbusPoller {
+    pub fn new(config: &ModbusConfig, tx: broadcast::Sender<ReadingsUpdate>) -> Self {
+        Self {
+            devices: config.get_devices(),
+            cooldown: max(config.get_cooldown(), Duration::from_secs(1)),
+            tx,
+        }
+    }
+
+    /// Publish all readable devices, returns delay until the next poll
+    pub async fn poll_once(&mut self) -> Duration {
+        introspection::mark_iteration("modbus");
+        let readings = read_devices(&self.devices).await;
+        tracing::trace!("Sending {:?} to channel", readings);
+        if self.tx.receiver_count() > 0 {
+            self.tx
+                .send(ReadingsUpdate::new(PUBLISHER, readings))
+                .unwrap();
+            introspection::observe_channel("readings", &self.tx);
+        }
+        introspection::mark_idle("modbus");
+        self.cooldown
+    }
+}
+
+impl PollJob for ModbusPoller {
+    fn name(&self) -> String {
+        String::from("modbus")
+    }
+
+    fn poll(&mut self) -> PollFuture<'_> {
+        Box::pin(self.poll_once())
+    }
+}
+
+pub async fn start_modbus_loop(
+    mut shutdown_rx: broadcast::Receiver<()>,
+    config: ModbusConfig,
+    tx: broadcast::Sender<ReadingsUpdate>,
+) {
+    // Check if module is enabled
+    if !config.is_enabled() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+    tracing::debug!("Starting Modbus loop");
+    let mut poller = ModbusPoller::new(&config, tx);
+    let _task = introspection::task_started("modbus");
+    loop {
+        let delay = poller.poll_once().await;
+        tokio::select! {
+            _ = shutdown_rx.recv() => {
+                tracing::trace!("Shutting down Modbus loop");
+                break;
+            }
+            _ = sleep(sampling::tick::aligned(delay)) => {}
+        }
+    }
+}
@@ -1,27 +1,45 @@
 // Licensed under the Open Software License version 3.0
-#[mockall_double::double]
 use super::connection::Connection;
-use super::{config::NetworkUpsToolsClientConfig, sender::UninterruptiblePowerSupplyData};
-use crate::hardware::types::{HardwareMetadata, HardwareType, SourceType};
+use super::{
+    config::NetworkUpsToolsClientConfig,
+    pattern::{expand_variables, is_pattern, literal_variables},
+    sender::UninterruptiblePowerSupplyData,
+};
+use crate::{
+    clock::SharedClock,
+    dedup_log::{info_resolved, warn_deduplicated},
+    hardware::types::{HardwareMetadata, HardwareType, SourceType},
+    introspection,
+};
 use rups::Config;
 use serde::{Deserialize, Serialize};
-use std::{cmp::min, collections::HashMap, sync::Arc, time::Duration};
-use tokio::{
-    sync::{Mutex, RwLock},
-    time::sleep,
+use std::{
+    cmp::min,
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
 };
+use tokio::sync::{Mutex, RwLock};
+
+/// An unreachable primary server can't delay polls of the failover server for long
+const FAIL_BACK_TIMEOUT: Duration = Duration::from_secs(3);
+/// Delay after the first failed fail-back attempt, doubled after every next one
+const FAIL_BACK_BACKOFF: Duration = Duration::from_secs(10);
+const MAX_FAIL_BACK_BACKOFF: Duration = Duration::from_secs(600);
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct UninterruptiblePowerSupply {
     pub meta: HardwareMetadata,
     ups_name: String,
     variables_to_monitor: Vec<String>,
+    list_clients: bool,
 }
 impl UninterruptiblePowerSupply {
     pub fn new(
         ups_name: String,
         server_id: String,
         variables_to_monitor: Option<Vec<String>>,
+        list_clients: bool,
     ) -> Self {
         // Create id by prepending "[ups_name]" to "server_id"
         let id = format!("[{}]{}", ups_name, server_id);
@@ -55,6 +73,7 @@ impl UninterruptiblePowerSupply {
             ),
             ups_name,
             variables_to_monitor,
+            list_clients,
         }
     }
 
@@ -62,9 +81,54 @@ impl UninterruptiblePowerSupply {
         self.variables_to_monitor.clone()
     }
 
+    fn has_patterns(&self) -> bool {
+        self.variables_to_monitor
+            .iter()
+            .any(|variable| is_pattern(variable))
+    }
+
+    /// Expand patterns in `variables_to_monitor` using `LIST VAR`
+    ///
+    /// Returns `None` if there are no patterns or listing failed
+    async fn expand_variables(&self, connection: &mut Connection) -> Option<Vec<String>> {
+        if !self.has_patterns() {
+            return None;
+        }
+        match connection.list_vars(&self.ups_name).await {
+            Ok(variables) => {
+                info_resolved!(
+                    format!("nut:list_vars:{}", self.meta.hw.id),
+                    "Listed variables of UPS {}",
+                    self.meta.hw.id
+                );
+                let available: Vec<String> = variables
+                    .iter()
+                    .map(|variable| String::from(variable.name()))
+                    .collect();
+                let expanded = expand_variables(&self.variables_to_monitor, &available);
+                tracing::debug!(
+                    "Expanded variables of UPS {}: {:?}",
+                    self.meta.hw.id,
+                    expanded
+                );
+                Some(expanded)
+            }
+            Err(error) => {
+                warn_deduplicated!(
+                    format!("nut:list_vars:{}", self.meta.hw.id),
+                    "Failed to list variables of UPS {}: {:?}",
+                    self.meta.hw.id,
+                    error
+                );
+                None
+            }
+        }
+    }
+
     pub async fn query_variables(
         &self,
         guarded_connection: Arc<Mutex<Option<Connection>>>,
+        variables_to_monitor: &[String],
     ) -> HashMap<String, String> {
         let mut variables_with_values: HashMap<String, String> = HashMap::new();
         // Acquire lock on connection
@@ -77,15 +141,24 @@ impl UninterruptiblePowerSupply {
         }
         // Unwrap connection and query server for variables
         let mut connection = connection.unwrap();
-        for variable_to_get in self.get_variables_to_monitor() {
+        for variable_to_get in variables_to_monitor.iter().cloned() {
             let returned_variable = connection
                 .get_var(&self.ups_name, &variable_to_get)
                 .await
                 .ok();
+            // Unsupported variables fail on every query
+            let key = format!("nut:get_var:{}:{}", self.meta.hw.id, variable_to_get);
             if returned_variable.is_some() {
+                info_resolved!(
+                    key,
+                    "Got variable {} from UPS {}",
+                    variable_to_get,
+                    self.meta.hw.id
+                );
                 variables_with_values.insert(variable_to_get, returned_variable.unwrap().value());
             } else {
-                tracing::warn!(
+                warn_deduplicated!(
+                    key,
                     "Failed to get variable {} from UPS {}",
                     variable_to_get,
                     self.meta.hw.id
@@ -97,16 +170,62 @@ impl UninterruptiblePowerSupply {
         // Return variables as key-value hashmap
         variables_with_values
     }
+
+    /// Query `LIST CLIENT` for this UPS
+    ///
+    /// Returns `None` if listing clients is disabled or the query failed
+    pub async fn query_clients(
+        &self,
+        guarded_connection: Arc<Mutex<Option<Connection>>>,
+    ) -> Option<Vec<String>> {
+        if !self.list_clients {
+            return None;
+        }
+        // Acquire lock on connection
+        let mut locked_connection = guarded_connection.lock().await;
+        // Borrow connection
+        let mut connection = locked_connection.take()?;
+        let clients = connection.list_clients(&self.ups_name).await;
+        // Release connection
+        locked_connection.replace(connection);
+        match clients {
+            Ok(clients) => {
+                info_resolved!(
+                    format!("nut:list_clients:{}", self.meta.hw.id),
+                    "Listed clients of UPS {}",
+                    self.meta.hw.id
+                );
+                Some(clients)
+            }
+            Err(error) => {
+                warn_deduplicated!(
+                    format!("nut:list_clients:{}", self.meta.hw.id),
+                    "Failed to list clients of UPS {}: {:?}",
+                    self.meta.hw.id,
+                    error
+                );
+                None
+            }
+        }
+    }
 }
 
 pub struct NetworkUpsToolsClient {
     // Required to do basic tasks
     connection: Arc<Mutex<Option<Connection>>>,
     upses: Vec<UninterruptiblePowerSupply>,
-    // Required to (re)connect
-    rups_config: Config,
+    // Required to (re)connect, server id and config of the primary server first
+    servers: Vec<(String, Config)>,
+    // Index of the connected server in `servers`
+    active_server: Arc<RwLock<usize>>,
     failed_attempts: Arc<RwLock<u32>>,
+    // Failed attempts to go back to the primary server and when to try again
+    fail_back_attempts: Arc<RwLock<(u32, Option<Instant>)>>,
     cooldown: Duration,
+    // Sleeps between reconnection attempts
+    clock: SharedClock,
+    // hw.id -> variables_to_monitor with patterns expanded on connect
+    expanded_variables: Arc<RwLock<HashMap<String, Vec<String>>>>,
     // Required for tracing
     server_id: String,
 }
@@ -115,19 +234,34 @@ impl NetworkUpsToolsClient {
     // Easy to construct from deserialized config
     pub fn new(client_config: &NetworkUpsToolsClientConfig, cooldown: Duration) -> Self {
         let server_id = client_config.get_server_id();
-        let rups_config = client_config.build_rups_config();
+        // hw.ids are based on the primary server, so they don't change after failing over
         let upses = client_config.get_upses(server_id.clone());
+        let servers = client_config
+            .get_failover_group()
+            .iter()
+            .map(|config| (config.get_server_id(), config.build_rups_config()))
+            .collect();
 
         Self {
             connection: Arc::new(Mutex::new(None)),
             upses,
-            rups_config,
+            servers,
+            active_server: Arc::default(),
             failed_attempts: Arc::new(RwLock::new(0)),
+            fail_back_attempts: Arc::default(),
             cooldown,
+            clock: SharedClock::default(),
+            expanded_variables: Arc::default(),
             server_id,
         }
     }
 
+    #[cfg(test)]
+    fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     async fn is_connected(&self) -> bool {
         let mut locked_connection = self.connection.lock().await;
         let connection = locked_connection.take();
@@ -141,28 +275,105 @@ impl NetworkUpsToolsClient {
         false
     }
 
+    /// Try every server of the group in order, the primary one first
     async fn connect(&self) {
         // Acquire locks
         let mut locked_connection = self.connection.lock().await;
         let mut locked_failed_attempts = self.failed_attempts.write().await;
         *locked_failed_attempts = locked_failed_attempts.saturating_add(1);
         // Try to connect
-        let connection = Connection::new(&self.rups_config).await;
+        let mut connected = None;
+        for (index, (server_id, rups_config)) in self.servers.iter().enumerate() {
+            match Connection::new(rups_config).await {
+                Ok(connection) => {
+                    connected = Some((index, connection));
+                    break;
+                }
+                Err(error_message) => warn_deduplicated!(
+                    format!("nut:connect:{}", server_id),
+                    "Failed to connect to UPS {}: {:?}",
+                    server_id,
+                    error_message
+                ),
+            }
+        }
         // Handle failure
-        if connection.is_err() {
-            let error_message = connection.err().unwrap();
+        let (index, connection) = match connected {
+            Some(connected) => connected,
+            None => {
+                introspection::set_retries(
+                    &format!("nut:{}", self.server_id),
+                    *locked_failed_attempts,
+                );
+                return;
+            }
+        };
+        // On success: reset failed attempts and save connection
+        *locked_failed_attempts = 0;
+        introspection::set_retries(&format!("nut:{}", self.server_id), 0);
+        let connection = self.activate(index, connection).await;
+        locked_connection.replace(connection);
+    }
+
+    /// Prepare a new connection to `servers[index]` for queries
+    async fn activate(&self, index: usize, mut connection: Connection) -> Connection {
+        let server_id = &self.servers[index].0;
+        tracing::debug!("Connected to UPS {:?}", server_id);
+        info_resolved!(
+            format!("nut:connect:{}", server_id),
+            "Reconnected to UPS {}",
+            server_id
+        );
+        let previous = std::mem::replace(&mut *self.active_server.write().await, index);
+        if previous != index && index == 0 {
+            tracing::info!("Failed back to primary UPS server {}", server_id);
+        } else if previous != index {
             tracing::warn!(
-                "Failed to connect to UPS {}: {:?}",
+                "UPS server {} is unreachable, failed over to {}",
                 self.server_id,
-                error_message
+                server_id
             );
+        }
+        // Variables may differ after reconnecting, ex. PDU firmware update
+        let mut expanded_variables = self.expanded_variables.write().await;
+        for ups in &self.upses {
+            if let Some(expanded) = ups.expand_variables(&mut connection).await {
+                expanded_variables.insert(ups.meta.hw.id.clone(), expanded);
+            }
+        }
+        connection
+    }
+
+    /// Go back to the primary server once it's reachable again, with backoff between attempts
+    async fn fail_back(&self) {
+        if *self.active_server.read().await == 0 {
             return;
         }
-        // On success: reset failed attempts and save connection
-        let connection = connection.unwrap();
-        tracing::debug!("Connected to UPS {:?}", self.server_id);
-        *locked_failed_attempts = 0;
-        locked_connection.replace(connection);
+        let mut fail_back_attempts = self.fail_back_attempts.write().await;
+        let (failed, next_attempt) = &mut *fail_back_attempts;
+        let now = self.clock.now();
+        if next_attempt.is_some_and(|next_attempt| now < next_attempt) {
+            return;
+        }
+        let (_, rups_config) = &self.servers[0];
+        match tokio::time::timeout(FAIL_BACK_TIMEOUT, Connection::new(rups_config)).await {
+            Ok(Ok(connection)) => {
+                *fail_back_attempts = (0, None);
+                let connection = self.activate(0, connection).await;
+                self.connection.lock().await.replace(connection);
+            }
+            _ => {
+                *failed = failed.saturating_add(1);
+                let backoff = FAIL_BACK_BACKOFF.saturating_mul(2u32.saturating_pow(*failed - 1));
+                let backoff = min(backoff, MAX_FAIL_BACK_BACKOFF);
+                tracing::debug!(
+                    "Primary UPS server {} is still unreachable, next attempt in {:?}",
+                    self.server_id,
+                    backoff
+                );
+                *next_attempt = Some(now + backoff);
+            }
+        }
     }
 
     async fn connect_if_not_connected(&self) {
@@ -175,32 +386,75 @@ impl NetworkUpsToolsClient {
             }
             let should_sleep_for = self.cooldown.saturating_mul(failed_attempts);
             let sleep_for = min(should_sleep_for, Duration::from_secs(3600)); // Limit to 1 hour
-            sleep(sleep_for).await;
+            self.clock.sleep(sleep_for).await;
             self.connect().await;
         }
     }
 
     pub async fn query_all_upses(&self) -> Vec<UninterruptiblePowerSupplyData> {
         // Check connection
+        self.fail_back().await;
         self.connect_if_not_connected().await;
         // Query all UPSes
         let mut data_from_upses: Vec<UninterruptiblePowerSupplyData> = Vec::new();
         for ups in &self.upses {
-            let variables = ups.query_variables(self.connection.clone()).await;
-            data_from_upses.push(UninterruptiblePowerSupplyData::new(ups, variables));
+            let variables_to_monitor =
+                match self.expanded_variables.read().await.get(&ups.meta.hw.id) {
+                    Some(expanded) => expanded.clone(),
+                    None => literal_variables(&ups.get_variables_to_monitor()),
+                };
+            let variables = ups
+                .query_variables(self.connection.clone(), &variables_to_monitor)
+                .await;
+            let clients = ups.query_clients(self.connection.clone()).await;
+            data_from_upses.push(UninterruptiblePowerSupplyData::new(ups, variables, clients));
         }
         data_from_upses
     }
+
+    /// Query only `ups.status` of every UPS, without reconnecting
+    ///
+    /// Cheap enough to run every second between full polls. Returns hw.id -> status.
+    pub async fn query_statuses(&self) -> HashMap<String, String> {
+        let status_variable = [String::from("ups.status")];
+        let mut statuses = HashMap::new();
+        for ups in &self.upses {
+            let variables = ups
+                .query_variables(self.connection.clone(), &status_variable)
+                .await;
+            if let Some(status) = variables.get("ups.status") {
+                statuses.insert(ups.meta.hw.id.clone(), status.clone());
+            }
+        }
+        statuses
+    }
 }
 
+#[cfg(all(test, feature = "nut-integration"))]
+mod integration_tests;
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::types::Example;
+    use crate::{clock::MockClock, nut::fake_server::FakeNutServer};
+    use tokio::time::sleep;
+
+    async fn start_fake_server() -> FakeNutServer {
+        let server = FakeNutServer::start().await;
+        server.set_var("ups1", "battery.charge", "100").await;
+        server.set_var("ups1", "battery.charge.low", "30").await;
+        server.set_var("ups1", "battery.runtime", "15").await;
+        server.set_var("ups1", "battery.runtime.low", "5").await;
+        server
+            .set_clients("ups1", vec![String::from("192.168.1.10")])
+            .await;
+        server
+    }
 
     #[tokio::test]
     async fn test_connect() {
-        let config = NetworkUpsToolsClientConfig::example();
+        let server = start_fake_server().await;
+        let config = server.client_config(&["ups1"]);
         let cooldown = Duration::default();
         let client = NetworkUpsToolsClient::new(&config, cooldown);
 
@@ -211,14 +465,18 @@ mod tests {
 
     #[tokio::test]
     async fn test_query_all_upses() {
-        let config = NetworkUpsToolsClientConfig::example();
+        let server = start_fake_server().await;
+        let config = server.client_config(&["ups1"]);
         let cooldown = Duration::default();
         let client = NetworkUpsToolsClient::new(&config, cooldown);
         let upses = client.query_all_upses().await;
         assert_eq!(upses.len(), 1);
 
         let ups = &upses[0];
-        assert_eq!(ups.meta.hw.id, "[ups1]ups-monitor@localhost:3493");
+        assert_eq!(
+            ups.meta.hw.id,
+            format!("[ups1]ups-monitor@{}", server.address())
+        );
         assert_eq!(
             ups.meta.hw.hardware_type,
             HardwareType::UninterruptiblePowerSupply
@@ -231,5 +489,186 @@ mod tests {
         assert_eq!(variables.get("battery.charge.low").unwrap(), "30");
         assert_eq!(variables.get("battery.runtime").unwrap(), "15");
         assert_eq!(variables.get("battery.runtime.low").unwrap(), "5");
+        assert!(ups.clients.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_query_removed_variable() {
+        let server = start_fake_server().await;
+        let config = server.client_config(&["ups1"]);
+        let client = NetworkUpsToolsClient::new(&config, Duration::default());
+        assert_eq!(client.query_all_upses().await[0].variables.len(), 4);
+
+        server.remove_var("ups1", "battery.charge").await;
+        let upses = client.query_all_upses().await;
+        assert_eq!(upses[0].variables.len(), 3);
+        assert!(upses[0].variables.get("battery.charge").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_after_server_restart() {
+        let server = start_fake_server().await;
+        let address = server.address();
+        let config = server.client_config(&["ups1"]);
+        let client = NetworkUpsToolsClient::new(&config, Duration::from_millis(10));
+        assert_eq!(client.query_all_upses().await[0].variables.len(), 4);
+
+        // Simulate upsd restart
+        drop(server);
+        let restarted_server = tokio::spawn(async move {
+            sleep(Duration::from_millis(50)).await;
+            let server = FakeNutServer::start_on(address).await;
+            server.set_var("ups1", "battery.charge", "99").await;
+            server
+        });
+        let upses = client.query_all_upses().await;
+        let _server = restarted_server.await.unwrap();
+        assert_eq!(upses[0].variables.get("battery.charge").unwrap(), "99");
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_backoff() {
+        let server = start_fake_server().await;
+        let address = server.address();
+        let config = server.client_config(&["ups1"]);
+        let cooldown = Duration::from_secs(20 * 60);
+        let mock = MockClock::default();
+        let client = NetworkUpsToolsClient::new(&config, cooldown).with_clock(mock.shared());
+        client.connect().await;
+
+        // Sleeps of the mock clock end instantly, so attempts only wait for refused connections
+        drop(server);
+        let restarted_server = tokio::spawn(async move {
+            sleep(Duration::from_millis(50)).await;
+            let server = FakeNutServer::start_on(address).await;
+            server.set_var("ups1", "battery.charge", "99").await;
+            server
+        });
+        let upses = client.query_all_upses().await;
+        let _server = restarted_server.await.unwrap();
+        assert_eq!(upses[0].variables.get("battery.charge").unwrap(), "99");
+
+        let sleeps = mock.sleeps();
+        assert!(sleeps.len() >= 2);
+        for (failed_attempts, slept) in sleeps.into_iter().enumerate() {
+            let expected = cooldown.saturating_mul(failed_attempts as u32);
+            assert_eq!(slept, min(expected, Duration::from_secs(3600)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failover() {
+        let primary = start_fake_server().await;
+        let primary_address = primary.address();
+        let secondary = FakeNutServer::start().await;
+        secondary.set_var("ups1", "battery.charge", "98").await;
+        let mut config = serde_json::to_value(primary.client_config(&["ups1"])).unwrap();
+        config["failover"] = serde_json::json!([{
+            "host": secondary.address().ip().to_string(),
+            "port": secondary.address().port(),
+        }]);
+        let config: NetworkUpsToolsClientConfig = serde_json::from_value(config).unwrap();
+        let client = NetworkUpsToolsClient::new(&config, Duration::default());
+        let upses = client.query_all_upses().await;
+        assert_eq!(upses[0].variables.get("battery.charge").unwrap(), "100");
+        let hw_id = upses[0].meta.hw.id.clone();
+
+        // Same UPS with the same hw.id, read from the secondary server
+        drop(primary);
+        let upses = client.query_all_upses().await;
+        assert_eq!(upses.len(), 1);
+        assert_eq!(upses[0].meta.hw.id, hw_id);
+        assert_eq!(upses[0].variables.get("battery.charge").unwrap(), "98");
+
+        // Primary server is preferred once it's back
+        let primary = FakeNutServer::start_on(primary_address).await;
+        primary.set_var("ups1", "battery.charge", "99").await;
+        let upses = client.query_all_upses().await;
+        assert_eq!(upses[0].variables.get("battery.charge").unwrap(), "99");
+        assert_eq!(*client.active_server.read().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_fail_back_backoff() {
+        let primary = start_fake_server().await;
+        let primary_address = primary.address();
+        let secondary = FakeNutServer::start().await;
+        secondary.set_var("ups1", "battery.charge", "98").await;
+        let mut config = serde_json::to_value(primary.client_config(&["ups1"])).unwrap();
+        config["failover"] = serde_json::json!([{
+            "host": secondary.address().ip().to_string(),
+            "port": secondary.address().port(),
+        }]);
+        let config: NetworkUpsToolsClientConfig = serde_json::from_value(config).unwrap();
+        let mock = MockClock::default();
+        let client =
+            NetworkUpsToolsClient::new(&config, Duration::default()).with_clock(mock.shared());
+        client.query_all_upses().await;
+        drop(primary);
+        client.query_all_upses().await;
+        assert_eq!(*client.active_server.read().await, 1);
+
+        // Failed attempt to fail back
+        client.query_all_upses().await;
+        assert_eq!(client.fail_back_attempts.read().await.0, 1);
+
+        // Primary server isn't tried again before the backoff elapses
+        let primary = FakeNutServer::start_on(primary_address).await;
+        primary.set_var("ups1", "battery.charge", "99").await;
+        let upses = client.query_all_upses().await;
+        assert_eq!(upses[0].variables.get("battery.charge").unwrap(), "98");
+
+        mock.advance(FAIL_BACK_BACKOFF);
+        let upses = client.query_all_upses().await;
+        assert_eq!(upses[0].variables.get("battery.charge").unwrap(), "99");
+        assert_eq!(*client.fail_back_attempts.read().await, (0, None));
+    }
+
+    #[tokio::test]
+    async fn test_query_clients() {
+        let server = start_fake_server().await;
+        let config = server.client_config(&["ups1"]);
+        let ups = UninterruptiblePowerSupply::new(
+            String::from("ups1"),
+            config.get_server_id(),
+            None,
+            true,
+        );
+        let connection = Connection::new(&config.build_rups_config()).await.unwrap();
+        let connection = Arc::new(Mutex::new(Some(connection)));
+
+        let clients = ups.query_clients(connection).await.unwrap();
+        assert_eq!(clients, vec![String::from("192.168.1.10")]);
+    }
+
+    #[tokio::test]
+    async fn test_query_variable_patterns() {
+        let server = start_fake_server().await;
+        server.set_var("ups1", "ups.load", "15").await;
+        let mut config = serde_json::to_value(server.client_config(&["ups1"])).unwrap();
+        config["upses"][0]["variables_to_monitor"] =
+            serde_json::json!(["ups.load", "battery.charge*"]);
+        let config: NetworkUpsToolsClientConfig = serde_json::from_value(config).unwrap();
+        let client = NetworkUpsToolsClient::new(&config, Duration::default());
+        let variables = &client.query_all_upses().await[0].variables;
+        assert_eq!(variables.len(), 3);
+        assert_eq!(variables.get("ups.load").unwrap(), "15");
+        assert_eq!(variables.get("battery.charge.low").unwrap(), "30");
+        assert!(variables.get("battery.runtime").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_query_statuses() {
+        let server = start_fake_server().await;
+        server.set_var("ups1", "ups.status", "OL").await;
+        let config = server.client_config(&["ups1"]);
+        let client = NetworkUpsToolsClient::new(&config, Duration::default());
+        // Not connected yet
+        assert!(client.query_statuses().await.is_empty());
+
+        client.query_all_upses().await;
+        server.set_var("ups1", "ups.status", "OB DISCHRG").await;
+        let statuses = client.query_statuses().await;
+        assert_eq!(statuses.values().next().unwrap(), "OB DISCHRG");
     }
 }
@@ -0,0 +1,77 @@
+// Licensed under the Open Software License version 3.0
+//! Tests against a real upsd with a dummy driver, see tests/nut/docker-compose.yml
+use super::*;
+use std::{path::PathBuf, process::Command};
+use tokio::time::sleep;
+
+fn compose(args: &[&str]) {
+    let compose_file =
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/nut/docker-compose.yml");
+    let status = Command::new("docker")
+        .arg("compose")
+        .arg("-f")
+        .arg(compose_file)
+        .args(args)
+        .status()
+        .expect("docker compose is required for integration tests");
+    assert!(status.success(), "docker compose {:?} failed", args);
+}
+
+fn client_config(enable_tls: bool) -> NetworkUpsToolsClientConfig {
+    serde_json::from_value(serde_json::json!({
+        "host": std::env::var("NUT_HOST").unwrap_or_else(|_| String::from("127.0.0.1")),
+        "port": 3493,
+        "enable_tls": enable_tls,
+        "username": "ups-monitor",
+        "password": "EXAMPLE_PASSWORD",
+        "upses": [{ "name": "dummy", "variables_to_monitor": null, "list_clients": true }],
+    }))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_connect_and_query() {
+    compose(&["up", "-d", "--build", "--wait"]);
+    let client = NetworkUpsToolsClient::new(&client_config(false), Duration::from_millis(100));
+    let upses = client.query_all_upses().await;
+    let variables = &upses[0].variables;
+    assert_eq!(variables.get("battery.charge").unwrap(), "100");
+    assert_eq!(variables.get("battery.runtime").unwrap(), "1800");
+    assert!(upses[0].clients.is_some());
+}
+
+#[tokio::test]
+async fn test_tls_rejects_untrusted_certificate() {
+    compose(&["up", "-d", "--build", "--wait"]);
+    let client = NetworkUpsToolsClient::new(&client_config(true), Duration::from_millis(100));
+    // upsd uses a self-signed certificate
+    client.connect().await;
+    assert!(!client.is_connected().await);
+}
+
+#[tokio::test]
+async fn test_stale_data() {
+    compose(&["up", "-d", "--build", "--wait"]);
+    let client = NetworkUpsToolsClient::new(&client_config(false), Duration::from_millis(100));
+    assert!(!client.query_all_upses().await[0].variables.is_empty());
+
+    // upsd reports DATA-STALE after MAXAGE without driver updates
+    compose(&["exec", "-T", "upsd", "upsdrvctl", "-u", "root", "stop"]);
+    sleep(Duration::from_secs(8)).await;
+    assert!(client.query_all_upses().await[0].variables.is_empty());
+
+    compose(&["exec", "-T", "upsd", "upsdrvctl", "-u", "root", "start"]);
+    sleep(Duration::from_secs(3)).await;
+    assert!(!client.query_all_upses().await[0].variables.is_empty());
+}
+
+#[tokio::test]
+async fn test_reconnect_after_upsd_restart() {
+    compose(&["up", "-d", "--build", "--wait"]);
+    let client = NetworkUpsToolsClient::new(&client_config(false), Duration::from_millis(100));
+    assert!(!client.query_all_upses().await[0].variables.is_empty());
+
+    compose(&["restart", "upsd"]);
+    let upses = client.query_all_upses().await;
+    assert_eq!(upses[0].variables.get("battery.charge").unwrap(), "100");
+}
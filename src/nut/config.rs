@@ -1,14 +1,31 @@
 // Licensed under the Open Software License version 3.0
+#[cfg(feature = "nut")]
 use super::client::UninterruptiblePowerSupply;
 use crate::config::types::Example;
+#[cfg(feature = "nut")]
 use rups::{Auth, Config, ConfigBuilder};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
+/// Same as `DEFAULT_PORT`, kept here so config works without the nut feature
+const DEFAULT_PORT: u16 = 3493;
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct UninterruptiblePowerSupplyConfig {
     pub name: String,
     pub variables_to_monitor: Option<Vec<String>>,
+    pub list_clients: Option<bool>,
+}
+
+/// Another upsd serving the same UPSes, ex. secondary of a primary/secondary pair
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailoverServerConfig {
+    host: String,
+    port: Option<u16>,
+    enable_tls: Option<bool>,
+    // Credentials of the primary server are used if not set
+    username: Option<String>,
+    password: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -19,13 +36,15 @@ pub struct NetworkUpsToolsClientConfig {
     username: Option<String>,
     password: Option<String>,
     upses: Vec<UninterruptiblePowerSupplyConfig>,
+    // Tried in order while this server is unreachable
+    failover: Option<Vec<FailoverServerConfig>>,
 }
 
 impl Example for NetworkUpsToolsClientConfig {
     fn example() -> Self {
         Self {
             host: String::from("localhost"),
-            port: Some(rups::DEFAULT_PORT),
+            port: Some(DEFAULT_PORT),
             enable_tls: Some(false),
             username: Some(String::from("ups-monitor")),
             password: Some(String::from("EXAMPLE_PASSWORD")),
@@ -37,12 +56,38 @@ impl Example for NetworkUpsToolsClientConfig {
                     String::from("battery.runtime"),
                     String::from("battery.runtime.low"),
                 ]),
+                list_clients: Some(false),
             }],
+            failover: Some(vec![FailoverServerConfig {
+                host: String::from("ups-secondary.lan"),
+                port: Some(DEFAULT_PORT),
+                enable_tls: Some(false),
+                username: None,
+                password: None,
+            }]),
         }
     }
 }
 
 impl NetworkUpsToolsClientConfig {
+    pub fn new(
+        host: String,
+        port: Option<u16>,
+        username: Option<String>,
+        password: Option<String>,
+        upses: Vec<UninterruptiblePowerSupplyConfig>,
+    ) -> Self {
+        Self {
+            host,
+            port,
+            enable_tls: None,
+            username,
+            password,
+            upses,
+            failover: None,
+        }
+    }
+
     pub fn get_server_id(&self) -> String {
         // Format server id as username@host:port
         // It should be done here because it's used in multiple places
@@ -50,10 +95,11 @@ impl NetworkUpsToolsClientConfig {
             "{}@{}:{}",
             self.username.clone().unwrap_or_default(),
             self.host,
-            self.port.unwrap_or(rups::DEFAULT_PORT),
+            self.port.unwrap_or(DEFAULT_PORT),
         )
     }
 
+    #[cfg(feature = "nut")]
     pub fn build_rups_config(&self) -> Config {
         // Read-only commands don't need auth
         let auth: Option<Auth> = match (self.username.clone(), self.password.clone()) {
@@ -64,7 +110,7 @@ impl NetworkUpsToolsClientConfig {
         ConfigBuilder::new()
             .with_timeout(Duration::from_secs(1))
             .with_host(
-                (self.host.clone(), self.port.unwrap_or(rups::DEFAULT_PORT))
+                (self.host.clone(), self.port.unwrap_or(DEFAULT_PORT))
                     .try_into()
                     .unwrap_or_default(),
             )
@@ -73,6 +119,30 @@ impl NetworkUpsToolsClientConfig {
             .build()
     }
 
+    /// Primary server followed by failover servers, each with the UPSes of the primary one
+    pub fn get_failover_group(&self) -> Vec<NetworkUpsToolsClientConfig> {
+        let failover = self.failover.clone().unwrap_or_default();
+        let secondaries = failover.into_iter().map(|server| Self {
+            host: server.host,
+            port: server.port,
+            enable_tls: server.enable_tls,
+            username: server.username.or_else(|| self.username.clone()),
+            password: server.password.or_else(|| self.password.clone()),
+            upses: self.upses.clone(),
+            failover: None,
+        });
+        let primary = Self {
+            failover: None,
+            ..self.clone()
+        };
+        std::iter::once(primary).chain(secondaries).collect()
+    }
+
+    pub fn get_ups_configs(&self) -> Vec<UninterruptiblePowerSupplyConfig> {
+        self.upses.clone()
+    }
+
+    #[cfg(feature = "nut")]
     pub fn get_upses(&self, server_id: String) -> Vec<UninterruptiblePowerSupply> {
         self.upses
             .iter()
@@ -81,17 +151,31 @@ impl NetworkUpsToolsClientConfig {
                     config.name.clone(),
                     server_id.clone(),
                     config.variables_to_monitor.clone(),
+                    config.list_clients.unwrap_or_default(),
                 )
             })
             .collect()
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct UpsMonitoringConfig {
     enabled: Option<bool>,
     servers: Option<Vec<NetworkUpsToolsClientConfig>>,
     cooldown: Option<Duration>,
+    // Poll only ups.status this often between full polls
+    status_interval: Option<Duration>,
+}
+
+impl Default for UpsMonitoringConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            servers: None,
+            cooldown: Some(Duration::from_secs(5)),
+            status_interval: None,
+        }
+    }
 }
 
 impl Example for UpsMonitoringConfig {
@@ -100,6 +184,7 @@ impl Example for UpsMonitoringConfig {
             enabled: Some(true),
             cooldown: Some(Duration::from_secs(5)),
             servers: Some(vec![NetworkUpsToolsClientConfig::example()]),
+            status_interval: Some(Duration::from_secs(1)),
         }
     }
 }
@@ -116,6 +201,12 @@ impl UpsMonitoringConfig {
     pub fn get_cooldown(&self) -> Duration {
         self.cooldown.unwrap_or(Duration::from_secs(5))
     }
+
+    /// `None` if disabled or not shorter than `cooldown`
+    pub fn get_status_interval(&self) -> Option<Duration> {
+        self.status_interval
+            .filter(|interval| !interval.is_zero() && *interval < self.get_cooldown())
+    }
 }
 
 #[cfg(test)]
@@ -127,4 +218,19 @@ mod tests {
         let config = NetworkUpsToolsClientConfig::example();
         assert_eq!(config.get_server_id(), "ups-monitor@localhost:3493");
     }
+
+    #[test]
+    fn test_get_failover_group() {
+        let config = NetworkUpsToolsClientConfig::example();
+        let group = config.get_failover_group();
+        assert_eq!(group.len(), 2);
+        assert_eq!(group[0].get_server_id(), config.get_server_id());
+        // Credentials and UPSes are inherited from the primary server
+        assert_eq!(
+            group[1].get_server_id(),
+            "ups-monitor@ups-secondary.lan:3493"
+        );
+        assert_eq!(group[1].password, config.password);
+        assert_eq!(group[1].get_ups_configs(), config.get_ups_configs());
+    }
 }
@@ -0,0 +1,229 @@
+// Licensed under the Open Software License version 3.0
+//! Minimal in-process NUT protocol server for tests
+//!
+//! Supports `USERNAME`, `PASSWORD`, `VER`, `NETVER`, `GET VAR`, `LIST VAR` and `LIST CLIENT`
+use super::config::NetworkUpsToolsClientConfig;
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::{broadcast, RwLock},
+};
+
+#[derive(Debug, Clone, Default)]
+struct FakeUps {
+    variables: HashMap<String, String>,
+    clients: Vec<String>,
+}
+
+type FakeUpses = Arc<RwLock<HashMap<String, FakeUps>>>;
+
+pub struct FakeNutServer {
+    address: SocketAddr,
+    upses: FakeUpses,
+    shutdown_tx: broadcast::Sender<()>,
+}
+
+impl FakeNutServer {
+    /// Start server on a random port
+    pub async fn start() -> Self {
+        Self::start_on("127.0.0.1:0".parse().unwrap()).await
+    }
+
+    /// Start server on a specific address, ex. to simulate restarted upsd
+    pub async fn start_on(address: SocketAddr) -> Self {
+        let listener = TcpListener::bind(address).await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let upses: FakeUpses = Arc::default();
+        let (shutdown_tx, _) = broadcast::channel::<()>(1);
+
+        let upses_clone = upses.clone();
+        let mut shutdown_rx = shutdown_tx.subscribe();
+        let shutdown_tx_clone = shutdown_tx.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    Ok((stream, _)) = listener.accept() => {
+                        let upses = upses_clone.clone();
+                        let shutdown_rx = shutdown_tx_clone.subscribe();
+                        tokio::spawn(handle_connection(stream, upses, shutdown_rx));
+                    }
+                    _ = shutdown_rx.recv() => break,
+                }
+            }
+        });
+
+        Self {
+            address,
+            upses,
+            shutdown_tx,
+        }
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    pub async fn set_var(&self, ups_name: &str, variable: &str, value: &str) {
+        self.upses
+            .write()
+            .await
+            .entry(String::from(ups_name))
+            .or_default()
+            .variables
+            .insert(String::from(variable), String::from(value));
+    }
+
+    pub async fn remove_var(&self, ups_name: &str, variable: &str) {
+        if let Some(ups) = self.upses.write().await.get_mut(ups_name) {
+            ups.variables.remove(variable);
+        }
+    }
+
+    pub async fn set_clients(&self, ups_name: &str, clients: Vec<String>) {
+        self.upses
+            .write()
+            .await
+            .entry(String::from(ups_name))
+            .or_default()
+            .clients = clients;
+    }
+
+    /// Client config pointing to this server
+    pub fn client_config(&self, ups_names: &[&str]) -> NetworkUpsToolsClientConfig {
+        let upses: Vec<serde_json::Value> = ups_names
+            .iter()
+            .map(|name| serde_json::json!({ "name": name, "variables_to_monitor": null }))
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "host": self.address.ip().to_string(),
+            "port": self.address.port(),
+            "enable_tls": false,
+            "username": "ups-monitor",
+            "password": "EXAMPLE_PASSWORD",
+            "upses": upses,
+        }))
+        .unwrap()
+    }
+
+    /// Stop accepting and drop all open connections
+    pub fn stop(&self) {
+        let _ = self.shutdown_tx.send(());
+    }
+}
+
+impl Drop for FakeNutServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+async fn respond(line: &str, upses: &FakeUpses) -> String {
+    let args: Vec<&str> = line.split_whitespace().collect();
+    let upses = upses.read().await;
+    match args.as_slice() {
+        ["USERNAME", _] | ["PASSWORD", _] | ["LOGIN", _] => String::from("OK\n"),
+        ["VER"] => String::from("Fake NUT server 1.0\n"),
+        ["NETVER"] => String::from("1.2\n"),
+        ["GET", "VAR", ups_name, variable] => match upses.get(*ups_name) {
+            Some(ups) => match ups.variables.get(*variable) {
+                Some(value) => format!("VAR {} {} \"{}\"\n", ups_name, variable, value),
+                None => String::from("ERR VAR-NOT-SUPPORTED\n"),
+            },
+            None => String::from("ERR UNKNOWN-UPS\n"),
+        },
+        ["LIST", "VAR", ups_name] => match upses.get(*ups_name) {
+            Some(ups) => {
+                let mut variables: Vec<(&String, &String)> = ups.variables.iter().collect();
+                variables.sort();
+                let mut response = format!("BEGIN LIST VAR {}\n", ups_name);
+                for (variable, value) in variables {
+                    response.push_str(&format!("VAR {} {} \"{}\"\n", ups_name, variable, value));
+                }
+                response.push_str(&format!("END LIST VAR {}\n", ups_name));
+                response
+            }
+            None => String::from("ERR UNKNOWN-UPS\n"),
+        },
+        ["LIST", "CLIENT", ups_name] => match upses.get(*ups_name) {
+            Some(ups) => {
+                let mut response = format!("BEGIN LIST CLIENT {}\n", ups_name);
+                for client in &ups.clients {
+                    response.push_str(&format!("CLIENT {} {}\n", ups_name, client));
+                }
+                response.push_str(&format!("END LIST CLIENT {}\n", ups_name));
+                response
+            }
+            None => String::from("ERR UNKNOWN-UPS\n"),
+        },
+        _ => String::from("ERR UNKNOWN-COMMAND\n"),
+    }
+}
+
+async fn handle_connection(
+    stream: TcpStream,
+    upses: FakeUpses,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                let line = match line {
+                    Ok(Some(line)) => line,
+                    _ => break,
+                };
+                let response = respond(&line, &upses).await;
+                if writer.write_all(response.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+            _ = shutdown_rx.recv() => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_respond() {
+        let server = FakeNutServer::start().await;
+        server.set_var("ups1", "battery.charge", "100").await;
+        server
+            .set_clients("ups1", vec![String::from("192.168.1.10")])
+            .await;
+
+        let upses = server.upses.clone();
+        assert_eq!(respond("USERNAME user", &upses).await, "OK\n");
+        assert_eq!(
+            respond("GET VAR ups1 battery.charge", &upses).await,
+            "VAR ups1 battery.charge \"100\"\n"
+        );
+        assert_eq!(
+            respond("GET VAR ups1 ups.load", &upses).await,
+            "ERR VAR-NOT-SUPPORTED\n"
+        );
+        assert_eq!(
+            respond("LIST CLIENT ups1", &upses).await,
+            "BEGIN LIST CLIENT ups1\nCLIENT ups1 192.168.1.10\nEND LIST CLIENT ups1\n"
+        );
+        assert_eq!(
+            respond("GET VAR ups2 battery.charge", &upses).await,
+            "ERR UNKNOWN-UPS\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_respond_list_var() {
+        let server = FakeNutServer::start().await;
+        server.set_var("ups1", "ups.load", "15").await;
+        server.set_var("ups1", "battery.charge", "100").await;
+        assert_eq!(
+            respond("LIST VAR ups1", &server.upses.clone()).await,
+            "BEGIN LIST VAR ups1\nVAR ups1 battery.charge \"100\"\nVAR ups1 ups.load \"15\"\nEND LIST VAR ups1\n"
+        );
+    }
+}
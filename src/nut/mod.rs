@@ -1,5 +1,13 @@
 // Licensed under the Open Software License version 3.0
+#[cfg(feature = "nut")]
 mod client;
+#[cfg_attr(not(feature = "nut"), allow(dead_code))]
 pub mod config;
+#[cfg(feature = "nut")]
 mod connection;
+#[cfg(any(test, feature = "fake-nut-server"))]
+#[cfg_attr(not(test), allow(dead_code))]
+pub mod fake_server;
+#[cfg(feature = "nut")]
+mod pattern;
 pub mod sender;
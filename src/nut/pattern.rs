@@ -0,0 +1,121 @@
+// Licensed under the Open Software License version 3.0
+use regex::Regex;
+
+/// Check if `variable` is a glob (`battery.*`) or regex (`/^outlet\.\d+\.status$/`) pattern
+pub fn is_pattern(variable: &str) -> bool {
+    variable.contains('*') || variable.contains('?') || is_regex(variable)
+}
+
+fn is_regex(variable: &str) -> bool {
+    variable.len() > 2 && variable.starts_with('/') && variable.ends_with('/')
+}
+
+fn to_regex(pattern: &str) -> Option<Regex> {
+    let regex = if is_regex(pattern) {
+        String::from(&pattern[1..pattern.len() - 1])
+    } else {
+        // Glob: `*` matches any characters, `?` matches a single character
+        let escaped = regex::escape(pattern)
+            .replace(r"\*", ".*")
+            .replace(r"\?", ".");
+        format!("^{}$", escaped)
+    };
+    match Regex::new(&regex) {
+        Ok(regex) => Some(regex),
+        Err(error) => {
+            tracing::error!("Invalid variable pattern {}: {}", pattern, error);
+            None
+        }
+    }
+}
+
+/// Expand patterns in `configured` against `available` variables
+///
+/// Keeps order of `configured`, plain variable names are kept even if not available
+pub fn expand_variables(configured: &[String], available: &[String]) -> Vec<String> {
+    let mut expanded: Vec<String> = Vec::new();
+    for variable in configured {
+        let matching: Vec<String> = if is_pattern(variable) {
+            match to_regex(variable) {
+                Some(regex) => available
+                    .iter()
+                    .filter(|name| regex.is_match(name))
+                    .cloned()
+                    .collect(),
+                None => Vec::new(),
+            }
+        } else {
+            vec![variable.clone()]
+        };
+        for name in matching {
+            if !expanded.contains(&name) {
+                expanded.push(name);
+            }
+        }
+    }
+    expanded
+}
+
+/// Configured variables without patterns, used until patterns can be expanded
+pub fn literal_variables(configured: &[String]) -> Vec<String> {
+    configured
+        .iter()
+        .filter(|variable| !is_pattern(variable))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| String::from(*value)).collect()
+    }
+
+    #[test]
+    fn test_is_pattern() {
+        assert!(is_pattern("battery.*"));
+        assert!(is_pattern("outlet.?.status"));
+        assert!(is_pattern(r"/^outlet\.\d+\.status$/"));
+        assert!(!is_pattern("battery.charge"));
+        assert!(!is_pattern("/"));
+    }
+
+    #[test]
+    fn test_expand_variables() {
+        let available = strings(&[
+            "battery.charge",
+            "battery.runtime",
+            "outlet.1.status",
+            "outlet.1.desc",
+            "outlet.12.status",
+            "ups.load",
+        ]);
+        assert_eq!(
+            expand_variables(&strings(&["ups.load", "battery.*"]), &available),
+            strings(&["ups.load", "battery.charge", "battery.runtime"])
+        );
+        assert_eq!(
+            expand_variables(&strings(&["outlet.*.status"]), &available),
+            strings(&["outlet.1.status", "outlet.12.status"])
+        );
+        assert_eq!(
+            expand_variables(&strings(&[r"/^outlet\.\d\.status$/"]), &available),
+            strings(&["outlet.1.status"])
+        );
+        // Duplicates are skipped
+        assert_eq!(
+            expand_variables(&strings(&["battery.charge", "battery.c*"]), &available),
+            strings(&["battery.charge"])
+        );
+    }
+
+    #[test]
+    fn test_literal_variables() {
+        assert_eq!(
+            literal_variables(&strings(&["ups.load", "battery.*"])),
+            strings(&["ups.load"])
+        );
+    }
+}
@@ -1,21 +1,37 @@
 // Licensed under the Open Software License version 3.0
+use super::config::UpsMonitoringConfig;
+#[cfg(feature = "nut")]
 use super::{
     client::{NetworkUpsToolsClient, UninterruptiblePowerSupply},
-    config::{NetworkUpsToolsClientConfig, UpsMonitoringConfig},
+    config::NetworkUpsToolsClientConfig,
 };
 use crate::{
     config::types::Example,
     hardware::types::{HardwareMetadata, HardwareType, SourceType},
+    relations::config::RelationsConfig,
+};
+#[cfg(feature = "nut")]
+use crate::{
+    hardware::duplicates::{remove_duplicates, report_duplicates},
+    introspection, sampling,
+    scheduler::job::{PollFuture, PollJob},
 };
 use serde::{Deserialize, Serialize};
-use std::{cmp::max, collections::HashMap, time::Duration};
-use tokio::{sync::broadcast, time::sleep};
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "nut")]
+use std::{cmp::max, time::Duration};
+use tokio::sync::broadcast;
+#[cfg(feature = "nut")]
+use tokio::time::{sleep, Instant};
+#[cfg(feature = "nut")]
 use tokio_stream::StreamExt;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct UninterruptiblePowerSupplyData {
     pub meta: HardwareMetadata,
     pub variables: HashMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub clients: Option<Vec<String>>,
 }
 
 impl Example for UninterruptiblePowerSupplyData {
@@ -36,73 +52,406 @@ impl Example for UninterruptiblePowerSupplyData {
                 SourceType::NetworkUpsTools,
             ),
             variables,
+            clients: None,
         }
     }
 }
 
 impl UninterruptiblePowerSupplyData {
-    pub fn new(ups: &UninterruptiblePowerSupply, variables: HashMap<String, String>) -> Self {
+    #[cfg(feature = "nut")]
+    pub fn new(
+        ups: &UninterruptiblePowerSupply,
+        mut variables: HashMap<String, String>,
+        clients: Option<Vec<String>>,
+    ) -> Self {
+        // Without any variable nothing was measured (ex. upsd is unreachable)
+        let meta = match variables.is_empty() {
+            true => ups.meta.clone(),
+            false => ups.meta.clone().measured_now(),
+        };
+        // Expose number of attached clients as a regular variable
+        if let Some(clients) = &clients {
+            variables.insert(String::from("ups.clients"), clients.len().to_string());
+        }
         Self {
-            meta: ups.meta.clone(),
+            meta,
             variables,
+            clients,
+        }
+    }
+
+    /// Remove variables that shouldn't leave the LAN
+    pub fn redacted(&self, redacted_variables: &[String]) -> Self {
+        let mut ups = self.clone();
+        for variable in redacted_variables {
+            ups.variables.remove(variable);
+        }
+        ups
+    }
+}
+
+/// Remove `redacted_variables` from every UPS
+pub fn redact_upses(
+    upses: &[UninterruptiblePowerSupplyData],
+    redacted_variables: &[String],
+) -> Vec<UninterruptiblePowerSupplyData> {
+    upses
+        .iter()
+        .map(|ups| ups.redacted(redacted_variables))
+        .collect()
+}
+
+/// All UPSes from a single publisher (ex. a NUT server), replacing its previous update
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpsUpdate {
+    pub publisher: String,
+    pub upses: Vec<UninterruptiblePowerSupplyData>,
+}
+
+impl UpsUpdate {
+    pub fn new(publisher: &str, upses: Vec<UninterruptiblePowerSupplyData>) -> Self {
+        Self {
+            publisher: String::from(publisher),
+            upses,
+        }
+    }
+}
+
+/// Latest UPSes of every publisher
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct UpsesByPublisher(BTreeMap<String, Vec<UninterruptiblePowerSupplyData>>);
+
+impl UpsesByPublisher {
+    pub fn update(&mut self, update: UpsUpdate) {
+        self.0.insert(update.publisher, update.upses);
+    }
+
+    pub fn all(&self) -> Vec<UninterruptiblePowerSupplyData> {
+        self.0.values().flatten().cloned().collect()
+    }
+}
+
+/// Apply fresh `ups.status` values to the last full snapshot, returns true if any changed
+#[cfg_attr(not(feature = "nut"), allow(dead_code))]
+fn merge_statuses(
+    upses: &mut [UninterruptiblePowerSupplyData],
+    statuses: &HashMap<String, String>,
+) -> bool {
+    let mut changed = false;
+    for ups in upses {
+        let status = match statuses.get(&ups.meta.hw.id) {
+            Some(status) => status,
+            None => continue,
+        };
+        if ups.variables.get("ups.status") != Some(status) {
+            ups.variables
+                .insert(String::from("ups.status"), status.clone());
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Keep `measured_at` of the previous snapshot for UPSes that weren't read this time
+///
+/// Data of an unreachable server ages, so it becomes stale after `max_age`.
+#[cfg_attr(not(feature = "nut"), allow(dead_code))]
+fn keep_measured_at(
+    upses: &mut [UninterruptiblePowerSupplyData],
+    previous: &[UninterruptiblePowerSupplyData],
+) {
+    for ups in upses
+        .iter_mut()
+        .filter(|ups| ups.meta.measured_at.is_none())
+    {
+        ups.meta.measured_at = previous
+            .iter()
+            .find(|previous| previous.meta.hw.id == ups.meta.hw.id)
+            .and_then(|previous| previous.meta.measured_at.clone());
+    }
+}
+
+/// State kept between polls of a single NUT server
+#[cfg(feature = "nut")]
+pub struct NutServerPoller {
+    server_config: NetworkUpsToolsClientConfig,
+    relations_config: RelationsConfig,
+    client: NetworkUpsToolsClient,
+    tx: broadcast::Sender<UpsUpdate>,
+    cooldown: Duration,
+    status_interval: Option<Duration>,
+    next_full_poll: Instant,
+    upses_with_variables: Vec<UninterruptiblePowerSupplyData>,
+}
+
+#[cfg(feature = "nut")]
+impl NutServerPoller {
+    pub fn new(
+        server_config: NetworkUpsToolsClientConfig,
+        relations_config: RelationsConfig,
+        tx: broadcast::Sender<UpsUpdate>,
+        cooldown: Duration,
+        status_interval: Option<Duration>,
+    ) -> Self {
+        let client = NetworkUpsToolsClient::new(&server_config, cooldown);
+        Self {
+            server_config,
+            relations_config,
+            client,
+            tx,
+            cooldown,
+            status_interval,
+            next_full_poll: Instant::now(),
+            upses_with_variables: Vec::new(),
+        }
+    }
+
+    pub fn get_server_id(&self) -> String {
+        self.server_config.get_server_id()
+    }
+
+    async fn poll_all_variables(&mut self) {
+        introspection::mark_iteration("nut");
+        self.next_full_poll = Instant::now() + self.cooldown;
+        let mut upses_with_variables = self.client.query_all_upses().await;
+        let duplicates = remove_duplicates(&mut upses_with_variables, |ups| &ups.meta);
+        report_duplicates(&format!("ups:{}", self.get_server_id()), &duplicates);
+        for ups in &mut upses_with_variables {
+            self.relations_config.annotate(&mut ups.meta);
+        }
+        keep_measured_at(&mut upses_with_variables, &self.upses_with_variables);
+        self.upses_with_variables = upses_with_variables;
+        self.publish();
+        introspection::mark_idle("nut");
+    }
+
+    /// Replace UPSes of this server only, other servers and sources publish on their own
+    fn publish(&self) {
+        if self.tx.receiver_count() > 0 {
+            let publisher = format!("nut:{}", self.get_server_id());
+            self.tx
+                .send(UpsUpdate::new(
+                    &publisher,
+                    self.upses_with_variables.clone(),
+                ))
+                .unwrap();
+            introspection::observe_channel("ups_monitoring", &self.tx);
         }
     }
+
+    async fn poll_statuses(&mut self) {
+        let statuses = self.client.query_statuses().await;
+        if merge_statuses(&mut self.upses_with_variables, &statuses) {
+            tracing::debug!("UPS status changed on {}", self.get_server_id());
+            self.publish();
+        }
+    }
+
+    /// Query all variables when due, otherwise only `ups.status`
+    ///
+    /// Returns delay until the next poll
+    pub async fn poll_once(&mut self) -> Duration {
+        if Instant::now() >= self.next_full_poll {
+            self.poll_all_variables().await;
+        } else {
+            self.poll_statuses().await;
+        }
+        // upsd doesn't push events, so status transitions are caught by polling only ups.status
+        let now = Instant::now();
+        let wake_up = match self.status_interval {
+            Some(interval) => self.next_full_poll.min(now + interval),
+            None => self.next_full_poll,
+        };
+        wake_up.saturating_duration_since(now)
+    }
+}
+
+#[cfg(feature = "nut")]
+impl PollJob for NutServerPoller {
+    fn name(&self) -> String {
+        format!("nut:{}", self.get_server_id())
+    }
+
+    fn poll(&mut self) -> PollFuture<'_> {
+        Box::pin(self.poll_once())
+    }
 }
 
+#[cfg(feature = "nut")]
 async fn start_nut_client_loop(
     mut shutdown_rx: broadcast::Receiver<()>,
     server_config: NetworkUpsToolsClientConfig,
-    tx: broadcast::Sender<Vec<UninterruptiblePowerSupplyData>>,
+    relations_config: RelationsConfig,
+    tx: broadcast::Sender<UpsUpdate>,
     cooldown: Duration,
+    status_interval: Option<Duration>,
 ) {
-    tracing::trace!(
-        "Starting nut client loop for {}",
-        server_config.get_server_id()
+    let server_id = server_config.get_server_id();
+    tracing::trace!("Starting nut client loop for {}", server_id);
+    let mut poller = NutServerPoller::new(
+        server_config,
+        relations_config,
+        tx,
+        cooldown,
+        status_interval,
     );
-    let client = NetworkUpsToolsClient::new(&server_config, cooldown);
+    let _task = introspection::task_started("nut");
     loop {
-        let upses_with_variables = client.query_all_upses().await;
-        if tx.receiver_count() > 0 {
-            tx.send(upses_with_variables).unwrap();
-        }
+        let delay = poller.poll_once().await;
         tokio::select! {
             _ = shutdown_rx.recv() => {
-                tracing::trace!("Shutting down nut client loop for {}", server_config.get_server_id());
+                tracing::trace!("Shutting down nut client loop for {}", server_id);
                 break;
             }
-            _ = sleep(cooldown) => {}
+            _ = sleep(sampling::tick::aligned(delay)) => {}
         }
     }
-    tracing::trace!(
-        "Stopped nut client loop for {}",
-        server_config.get_server_id()
-    );
+    tracing::trace!("Stopped nut client loop for {}", server_id);
 }
 
 pub async fn start_nut_monitoring_loop(
     shutdown_rx: broadcast::Receiver<()>,
     config: UpsMonitoringConfig,
-    tx: broadcast::Sender<Vec<UninterruptiblePowerSupplyData>>,
+    relations_config: RelationsConfig,
+    tx: broadcast::Sender<UpsUpdate>,
 ) {
     // Check if module is enabled
     if !config.is_enabled() {
         tracing::trace!("Module is disabled");
         return;
     }
+    #[cfg(feature = "nut")]
+    run(shutdown_rx, config, relations_config, tx).await;
+    #[cfg(not(feature = "nut"))]
+    {
+        let _ = (shutdown_rx, relations_config, tx);
+        tracing::error!(
+            "UPS monitoring is enabled in config but this binary was built without nut feature"
+        );
+    }
+}
 
+/// Cooldown and status interval clamped to at least 200ms
+#[cfg(feature = "nut")]
+fn get_poll_intervals(config: &UpsMonitoringConfig) -> (Duration, Option<Duration>) {
+    let cooldown = max(config.get_cooldown(), Duration::from_millis(200));
+    let status_interval = config
+        .get_status_interval()
+        .map(|interval| max(interval, Duration::from_millis(200)));
+    (cooldown, status_interval)
+}
+
+/// One poller per server, for the scheduler
+#[cfg(feature = "nut")]
+pub fn create_nut_server_pollers(
+    config: &UpsMonitoringConfig,
+    relations_config: &RelationsConfig,
+    tx: &broadcast::Sender<UpsUpdate>,
+) -> Vec<NutServerPoller> {
+    let (cooldown, status_interval) = get_poll_intervals(config);
+    config
+        .get_server_configs()
+        .into_iter()
+        .map(|server_config| {
+            NutServerPoller::new(
+                server_config,
+                relations_config.clone(),
+                tx.clone(),
+                cooldown,
+                status_interval,
+            )
+        })
+        .collect()
+}
+
+#[cfg(feature = "nut")]
+async fn run(
+    shutdown_rx: broadcast::Receiver<()>,
+    config: UpsMonitoringConfig,
+    relations_config: RelationsConfig,
+    tx: broadcast::Sender<UpsUpdate>,
+) {
     // Spawn task for each server
     tracing::trace!("Starting nut monitoring loop");
-    let cooldown = max(config.get_cooldown(), Duration::from_millis(200));
+    let (cooldown, status_interval) = get_poll_intervals(&config);
     let server_configs = config.get_server_configs();
     let mut server_configs = tokio_stream::iter(server_configs);
 
     while let Some(server_config) = server_configs.next().await {
         let shutdown_rx_clone = shutdown_rx.resubscribe();
+        let relations_config = relations_config.clone();
         let tx = tx.clone();
         tokio::spawn(async move {
-            start_nut_client_loop(shutdown_rx_clone, server_config, tx, cooldown).await;
+            start_nut_client_loop(
+                shutdown_rx_clone,
+                server_config,
+                relations_config,
+                tx,
+                cooldown,
+                status_interval,
+            )
+            .await;
         })
         .await
         .unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_upses() {
+        let upses = vec![UninterruptiblePowerSupplyData::example()];
+        let redacted = redact_upses(&upses, &[String::from("ups.load")]);
+        assert!(redacted[0].variables.get("ups.load").is_none());
+        assert_eq!(redacted[0].variables.get("battery.charge").unwrap(), "100");
+        // Original data is left untouched
+        assert!(upses[0].variables.get("ups.load").is_some());
+    }
+
+    #[test]
+    fn test_upses_by_publisher() {
+        let mut upses = UpsesByPublisher::default();
+        let ups = UninterruptiblePowerSupplyData::example();
+        upses.update(UpsUpdate::new("nut:first", vec![ups.clone()]));
+        upses.update(UpsUpdate::new("apcupsd", vec![ups.clone()]));
+        assert_eq!(upses.all().len(), 2);
+        // Newer update replaces UPSes of the same publisher
+        upses.update(UpsUpdate::new("nut:first", vec![]));
+        assert_eq!(upses.all().len(), 1);
+    }
+
+    #[test]
+    fn test_merge_statuses() {
+        let mut upses = vec![UninterruptiblePowerSupplyData::example()];
+        let mut statuses = HashMap::new();
+        assert!(!merge_statuses(&mut upses, &statuses));
+        statuses.insert(String::from("fake_hw_id"), String::from("OB DISCHRG"));
+        assert!(merge_statuses(&mut upses, &statuses));
+        assert_eq!(upses[0].variables.get("ups.status").unwrap(), "OB DISCHRG");
+        // Same status again isn't a change
+        assert!(!merge_statuses(&mut upses, &statuses));
+    }
+
+    #[test]
+    fn test_keep_measured_at() {
+        let mut previous = UninterruptiblePowerSupplyData::example();
+        previous.meta.measured_at = Some(String::from("2023-01-01T00:00:00+00:00"));
+        let mut unreachable = UninterruptiblePowerSupplyData::example();
+        unreachable.variables.clear();
+        let mut upses = vec![unreachable];
+        keep_measured_at(&mut upses, &[previous.clone()]);
+        assert_eq!(upses[0].meta.measured_at, previous.meta.measured_at);
+
+        // Fresh data keeps its own timestamp
+        let mut upses = vec![UninterruptiblePowerSupplyData::example()];
+        upses[0].meta.measured_at = Some(String::from("2023-01-01T00:01:00+00:00"));
+        keep_measured_at(&mut upses, &[previous]);
+        assert_eq!(
+            upses[0].meta.measured_at.as_deref(),
+            Some("2023-01-01T00:01:00+00:00")
+        );
+    }
+}
@@ -0,0 +1,142 @@
+// Licensed under the Open Software License version 3.0
+//! Sensor aliases kept in a separate file, reloaded when it changes
+//!
+//! Every line is `<id> = <alias>`, ex. `28-00000a0b0c0d = living-room`. OWFS style ids
+//! (`28.00000A0B0C0D`) are accepted too. Empty lines and lines starting with `#` are ignored.
+use crate::dedup_log::{info_resolved, warn_deduplicated};
+use std::{collections::HashMap, path::PathBuf, time::SystemTime};
+
+/// Kernel style id, ex. `28.00000A0B0C0D` -> `28-00000a0b0c0d`
+fn normalize_id(id: &str) -> String {
+    id.replace('.', "-").to_lowercase()
+}
+
+/// hw.id -> alias, invalid lines are returned as errors with their line numbers
+pub fn parse_aliases(contents: &str) -> (HashMap<String, String>, Vec<String>) {
+    let mut aliases = HashMap::new();
+    let mut errors = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_once('=') {
+            Some((id, alias)) if !id.trim().is_empty() && !alias.trim().is_empty() => {
+                aliases.insert(normalize_id(id.trim()), String::from(alias.trim()));
+            }
+            _ => errors.push(format!("line {}: expected `<id> = <alias>`", index + 1)),
+        }
+    }
+    (aliases, errors)
+}
+
+#[derive(Debug)]
+pub struct AliasFile {
+    path: PathBuf,
+    // Modification time and size of the loaded version, `None` before the first load
+    version: Option<(SystemTime, u64)>,
+    aliases: HashMap<String, String>,
+}
+
+impl AliasFile {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            version: None,
+            aliases: HashMap::new(),
+        }
+    }
+
+    /// Reload the file if it was modified since the last call
+    ///
+    /// Aliases are kept if the file disappears or can't be read, so a failed edit doesn't
+    /// rename sensors back to their ids
+    pub async fn refresh(&mut self) {
+        let version = match tokio::fs::metadata(&self.path).await {
+            Ok(metadata) => metadata
+                .modified()
+                .ok()
+                .map(|modified| (modified, metadata.len())),
+            Err(error) => {
+                warn_deduplicated!(
+                    "one_wire:aliases",
+                    "Failed to read 1-Wire aliases from {}: {}",
+                    self.path.display(),
+                    error
+                );
+                return;
+            }
+        };
+        if version.is_some() && version == self.version {
+            return;
+        }
+        let contents = match tokio::fs::read_to_string(&self.path).await {
+            Ok(contents) => contents,
+            Err(error) => {
+                warn_deduplicated!(
+                    "one_wire:aliases",
+                    "Failed to read 1-Wire aliases from {}: {}",
+                    self.path.display(),
+                    error
+                );
+                return;
+            }
+        };
+        info_resolved!("one_wire:aliases", "Reading 1-Wire aliases again");
+        let (aliases, errors) = parse_aliases(&contents);
+        for error in errors {
+            tracing::warn!("Invalid 1-Wire alias in {}, {}", self.path.display(), error);
+        }
+        tracing::info!(
+            "Loaded {} 1-Wire aliases from {}",
+            aliases.len(),
+            self.path.display()
+        );
+        self.version = version;
+        self.aliases = aliases;
+    }
+
+    pub fn get(&self, id: &str) -> Option<String> {
+        self.aliases.get(id).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_aliases() {
+        let (aliases, errors) = parse_aliases(
+            "# Ground floor\n\n28-00000a0b0c0d = living-room\n28.00000A0B0C0E=kitchen\nbroken\n",
+        );
+        assert_eq!(aliases["28-00000a0b0c0d"], "living-room");
+        assert_eq!(aliases["28-00000a0b0c0e"], "kitchen");
+        assert_eq!(aliases.len(), 2);
+        assert_eq!(errors, vec!["line 5: expected `<id> = <alias>`"]);
+    }
+
+    #[tokio::test]
+    async fn test_refresh() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("aliases");
+        let mut file = AliasFile::new(path.clone());
+        file.refresh().await;
+        assert_eq!(file.get("28-00000a0b0c0d"), None);
+
+        std::fs::write(&path, "28-00000a0b0c0d = living-room\n").unwrap();
+        file.refresh().await;
+        assert_eq!(file.get("28-00000a0b0c0d").unwrap(), "living-room");
+
+        // Different size, so the change is noticed even with coarse modification times
+        std::fs::write(&path, "28-00000a0b0c0d = bedroom\n").unwrap();
+        file.refresh().await;
+        assert_eq!(file.get("28-00000a0b0c0d").unwrap(), "bedroom");
+
+        // Removing the file keeps the last aliases
+        std::fs::remove_file(&path).unwrap();
+        file.refresh().await;
+        assert_eq!(file.get("28-00000a0b0c0d").unwrap(), "bedroom");
+    }
+}
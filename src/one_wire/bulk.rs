@@ -0,0 +1,100 @@
+// Licensed under the Open Software License version 3.0
+use crate::dedup_log::{info_resolved, warn_deduplicated};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::{
+    fs::{read_dir, read_to_string, write},
+    time::{sleep, Instant},
+};
+
+// Longest DS18B20 conversion (12-bit) is 750 ms, leave some headroom
+const CONVERSION_TIMEOUT: Duration = Duration::from_millis(1000);
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Find `therm_bulk_read` files of all bus masters in `base_path`
+pub async fn find_bulk_read_paths(base_path: &Path) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    let mut entries = match read_dir(base_path).await {
+        Ok(entries) => entries,
+        Err(_) => return paths,
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let is_bus_master = entry
+            .file_name()
+            .to_str()
+            .map_or(false, |name| name.starts_with("w1_bus_master"));
+        let path = entry.path().join("therm_bulk_read");
+        if is_bus_master && path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+/// Start simultaneous conversion on every bus and wait until it's done
+///
+/// Sensors return converted values on the next read without starting their own conversion.
+/// Returns `false` if any bus failed to trigger or didn't finish in time.
+pub async fn trigger_bulk_conversion(paths: &[PathBuf]) -> bool {
+    let mut success = true;
+    for path in paths {
+        let key = format!("one_wire:trigger:{}", path.display());
+        match write(path, "trigger").await {
+            Ok(_) => info_resolved!(key, "Triggered {}", path.display()),
+            Err(error) => {
+                warn_deduplicated!(key, "Failed to trigger {}: {}", path.display(), error);
+                success = false;
+            }
+        }
+    }
+    let deadline = Instant::now() + CONVERSION_TIMEOUT;
+    for path in paths {
+        // -1 means conversion is still in progress
+        while let Ok(status) = read_to_string(path).await {
+            if status.trim() != "-1" {
+                break;
+            }
+            if Instant::now() >= deadline {
+                warn_deduplicated!(
+                    format!("one_wire:timeout:{}", path.display()),
+                    "Bulk conversion of {} timed out",
+                    path.display()
+                );
+                success = false;
+                break;
+            }
+            sleep(POLL_INTERVAL).await;
+        }
+    }
+    success
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_find_bulk_read_paths() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let master = temp_dir.path().join("w1_bus_master1");
+        std::fs::create_dir(&master).unwrap();
+        std::fs::write(master.join("therm_bulk_read"), "0").unwrap();
+        // Old kernels don't have therm_bulk_read
+        std::fs::create_dir(temp_dir.path().join("w1_bus_master2")).unwrap();
+        std::fs::create_dir(temp_dir.path().join("28-00000a0b0c0d")).unwrap();
+        let paths = find_bulk_read_paths(temp_dir.path()).await;
+        assert_eq!(paths, vec![master.join("therm_bulk_read")]);
+    }
+
+    #[tokio::test]
+    async fn test_trigger_bulk_conversion() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("therm_bulk_read");
+        std::fs::write(&path, "0").unwrap();
+        assert!(trigger_bulk_conversion(&[path.clone()]).await);
+        // Regular file keeps whatever was written
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "trigger");
+    }
+}
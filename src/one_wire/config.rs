@@ -1,13 +1,36 @@
 // Licensed under the Open Software License version 3.0
 use crate::config::types::Example;
 use serde::{Deserialize, Serialize};
-use std::{path::PathBuf, time::Duration};
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+/// Quantization step, compared bit by bit so the config stays `Eq`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Step(pub f64);
+
+impl PartialEq for Step {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for Step {}
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct OneWireConfig {
     enabled: Option<bool>,
     base_path: Option<String>,
     cooldown: Option<Duration>,
+    // hw.id -> friendly name
+    names: Option<HashMap<String, String>>,
+    // File with `<id> = <alias>` lines, reloaded when modified, takes precedence over names
+    aliases_file: Option<String>,
+    // Trigger simultaneous conversion on every bus before reading sensors
+    bulk_read: Option<bool>,
+    // hw.id -> step to round temperature to, ex. 0.5
+    quantization: Option<HashMap<String, Step>>,
+    // Publish every this many sensors during the first sweep, for buses with 100+ sensors
+    startup_batch_size: Option<usize>,
 }
 
 impl Default for OneWireConfig {
@@ -17,6 +40,11 @@ impl Default for OneWireConfig {
             enabled: Some(false),
             base_path: Some(String::from("/sys/bus/w1/devices")),
             cooldown: Some(Duration::from_secs(1)),
+            names: None,
+            aliases_file: None,
+            bulk_read: Some(false),
+            quantization: None,
+            startup_batch_size: None,
         }
     }
 }
@@ -27,6 +55,17 @@ impl Example for OneWireConfig {
             enabled: Some(true),
             base_path: Some(String::from("/sys/bus/w1/devices")),
             cooldown: Some(Duration::from_secs(1)),
+            names: Some(HashMap::from([(
+                String::from("28-00000a0b0c0d"),
+                String::from("living-room"),
+            )])),
+            aliases_file: None,
+            bulk_read: Some(true),
+            quantization: Some(HashMap::from([(
+                String::from("28-00000a0b0c0d"),
+                Step(0.5),
+            )])),
+            startup_batch_size: Some(20),
         }
     }
 }
@@ -43,4 +82,27 @@ impl OneWireConfig {
     pub fn get_cooldown(&self) -> Duration {
         self.cooldown.unwrap_or_default()
     }
+
+    pub fn get_name(&self, id: &str) -> Option<String> {
+        self.names.as_ref()?.get(id).cloned()
+    }
+
+    pub fn get_aliases_file(&self) -> Option<PathBuf> {
+        self.aliases_file.as_ref().map(PathBuf::from)
+    }
+
+    pub fn get_bulk_read(&self) -> bool {
+        self.bulk_read.unwrap_or_default()
+    }
+
+    /// `None` if the first sweep is published at once
+    pub fn get_startup_batch_size(&self) -> Option<usize> {
+        self.startup_batch_size.filter(|size| *size > 0)
+    }
+
+    /// Positive step for `id`, invalid steps are ignored
+    pub fn get_quantization(&self, id: &str) -> Option<f64> {
+        let Step(step) = *self.quantization.as_ref()?.get(id)?;
+        (step.is_finite() && step > 0.0).then_some(step)
+    }
 }
@@ -77,6 +77,12 @@ impl Ds18b20TemperatureSensor {
         // Return temperature
         Some(temperature)
     }
+    /// Contents of the kernel's `name` attribute if it differs from the id
+    pub fn get_kernel_name(&self) -> Option<String> {
+        let name = read_to_string(self.path.join("name")).ok()?;
+        let name = name.trim();
+        (!name.is_empty() && name != self.meta.hw.id).then(|| String::from(name))
+    }
     pub fn get_resolution(&self) -> Option<u8> {
         // Check if "resolution" file inside path exists
         // Return an error if it doesn't but don't panic
@@ -226,6 +232,21 @@ mod tests {
         assert!(resolution.is_none());
     }
 
+    #[test]
+    fn get_kernel_name() {
+        // Create a valid device dir
+        let temp_dir = create_valid_device_dir();
+        let device_dir = temp_dir.path().join(VALID_DEVICE_ID);
+        let sensor = Ds18b20TemperatureSensor::new(device_dir.clone());
+        // Missing attribute
+        assert_eq!(sensor.get_kernel_name(), None);
+        // Kernel's default name is the id itself
+        std::fs::write(device_dir.join("name"), format!("{}\n", VALID_DEVICE_ID)).unwrap();
+        assert_eq!(sensor.get_kernel_name(), None);
+        std::fs::write(device_dir.join("name"), "boiler\n").unwrap();
+        assert_eq!(sensor.get_kernel_name().unwrap(), "boiler");
+    }
+
     #[test]
     fn serialize_as_measured_temperature() {
         // Create a valid device dir
@@ -238,6 +259,7 @@ mod tests {
             meta: sensor.meta.clone(),
             temperature: sensor.get_temperature(),
             resolution: sensor.get_resolution(),
+            quality: None,
         };
         // Serialize sensor as measured temperature
         let serialized = serde_json::to_string(&measured);
@@ -1,5 +1,14 @@
 // Licensed under the Open Software License version 3.0
+#[cfg(feature = "one-wire")]
+mod aliases;
+#[cfg(feature = "one-wire")]
+mod bulk;
+#[cfg_attr(not(feature = "one-wire"), allow(dead_code))]
 pub mod config;
+#[cfg(feature = "one-wire")]
 mod ds18b20;
+#[cfg(feature = "one-wire")]
+mod quantize;
+#[cfg(feature = "one-wire")]
 mod scanner;
 pub mod sender;
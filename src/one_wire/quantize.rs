@@ -0,0 +1,28 @@
+// Licensed under the Open Software License version 3.0
+
+/// Round `value` to the nearest multiple of `step`
+///
+/// Result is rounded to 6 decimal places, so steps like 0.1 don't produce 21.900000000000002
+pub fn quantize(value: f64, step: f64) -> f64 {
+    let quantized = (value / step).round() * step;
+    (quantized * 1_000_000.0).round() / 1_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quantize() {
+        assert_eq!(quantize(21.437, 0.5), 21.5);
+        assert_eq!(quantize(21.187, 0.5), 21.0);
+        assert_eq!(quantize(21.94, 0.1), 21.9);
+        assert_eq!(quantize(-0.2, 0.5), -0.0);
+    }
+
+    #[test]
+    fn test_noise_is_absorbed() {
+        // Typical DS18B20 jitter of one 12-bit step
+        assert_eq!(quantize(22.0625, 0.5), quantize(21.9375, 0.5));
+    }
+}
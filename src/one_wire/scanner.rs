@@ -1,13 +1,84 @@
 // Licensed under the Open Software License version 3.0
 use super::ds18b20::Ds18b20TemperatureSensor;
-use std::path::PathBuf;
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
 use tokio::fs::read_dir;
 
+// Present if w1_therm is loaded as a module or built into the kernel
+const W1_THERM_MODULE_PATH: &str = "/sys/module/w1_therm";
+
+/// Problems with `base_path` that would make every scan return zero sensors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BasePathProblem {
+    Missing(PathBuf),
+    NotADirectory(PathBuf),
+    NotReadable(PathBuf, String),
+    DriverNotLoaded,
+}
+
+impl fmt::Display for BasePathProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing(path) => write!(
+                f,
+                "{} does not exist, check base_path or enable 1-Wire (ex. dtoverlay=w1-gpio)",
+                path.display()
+            ),
+            Self::NotADirectory(path) => {
+                write!(f, "{} is not a directory, check base_path", path.display())
+            }
+            Self::NotReadable(path, error) => write!(
+                f,
+                "{} is not readable ({}), check permissions of the service user",
+                path.display(),
+                error
+            ),
+            Self::DriverNotLoaded => write!(
+                f,
+                "w1_therm does not appear to be loaded, try running `modprobe w1_therm`"
+            ),
+        }
+    }
+}
+
+/// Check if `base_path` can be scanned and w1_therm is available
+pub fn check_base_path(base_path: &Path, w1_therm_module_path: &Path) -> Vec<BasePathProblem> {
+    let mut problems = Vec::new();
+    if !base_path.exists() {
+        problems.push(BasePathProblem::Missing(base_path.to_path_buf()));
+    } else if !base_path.is_dir() {
+        problems.push(BasePathProblem::NotADirectory(base_path.to_path_buf()));
+    } else if let Err(error) = std::fs::read_dir(base_path) {
+        problems.push(BasePathProblem::NotReadable(
+            base_path.to_path_buf(),
+            error.to_string(),
+        ));
+    }
+    if !w1_therm_module_path.exists() {
+        problems.push(BasePathProblem::DriverNotLoaded);
+    }
+    problems
+}
+
+/// Log actionable diagnostics for `base_path`
+///
+/// Returns `true` if no problems were found
+pub fn validate_base_path(base_path: &Path) -> bool {
+    let problems = check_base_path(base_path, Path::new(W1_THERM_MODULE_PATH));
+    for problem in &problems {
+        tracing::error!("1-Wire: {}", problem);
+    }
+    problems.is_empty()
+}
+
 pub async fn get_all_ds18b20_sensors(base_path: &PathBuf) -> Vec<Ds18b20TemperatureSensor> {
     let mut list: Vec<Ds18b20TemperatureSensor> = Vec::new();
     // Return empty list if base_path is not a directory
+    // Problems are reported once by validate_base_path
     if !base_path.is_dir() {
-        tracing::error!("base_path is not a directory");
+        tracing::trace!("base_path is not a directory");
         return list;
     }
     // Read base_path directory
@@ -19,17 +90,22 @@ pub async fn get_all_ds18b20_sensors(base_path: &PathBuf) -> Vec<Ds18b20Temperat
     // Leave only directories from entries
     // Push instances of valid Ds18b20TemperatureSensors to list
     tracing::trace!("Pushing Ds18b20TemperatureSensors");
+    let mut paths = Vec::new();
     while let Some(entry) = entries.next_entry().await.unwrap() {
         let path = entry.path();
         if path.is_dir() {
-            let sensor = Ds18b20TemperatureSensor::new(path.clone());
-            // Push if sensor is valid
-            if sensor.is_valid() {
-                list.push(sensor);
-            }
+            paths.push(path);
+        }
+    }
+    // Directory order isn't stable, duplicate ids are resolved by the first path
+    paths.sort();
+    for path in paths {
+        let sensor = Ds18b20TemperatureSensor::new(path);
+        // Push if sensor is valid
+        if sensor.is_valid() {
+            list.push(sensor);
         }
     }
-    // Return list
     list
 }
 
@@ -67,4 +143,35 @@ mod tests {
         let list = get_all_ds18b20_sensors(&temp_path).await;
         assert_eq!(list.len(), 0);
     }
+
+    #[test]
+    fn test_check_base_path_valid() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let problems = check_base_path(temp_dir.path(), temp_dir.path());
+        assert!(problems.is_empty());
+    }
+
+    #[test]
+    fn test_check_base_path_missing() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let base_path = temp_dir.path().join("missing");
+        let module_path = temp_dir.path().join("w1_therm");
+        let problems = check_base_path(&base_path, &module_path);
+        assert_eq!(
+            problems,
+            vec![
+                BasePathProblem::Missing(base_path),
+                BasePathProblem::DriverNotLoaded
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_base_path_not_a_directory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let base_path = temp_dir.path().join("file");
+        std::fs::write(&base_path, "").unwrap();
+        let problems = check_base_path(&base_path, temp_dir.path());
+        assert_eq!(problems, vec![BasePathProblem::NotADirectory(base_path)]);
+    }
 }
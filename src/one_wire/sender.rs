@@ -1,18 +1,41 @@
 // Licensed under the Open Software License version 3.0
-use super::{config::OneWireConfig, scanner::get_all_ds18b20_sensors};
+use super::config::OneWireConfig;
+#[cfg(feature = "one-wire")]
+use super::{
+    aliases::AliasFile,
+    bulk::{find_bulk_read_paths, trigger_bulk_conversion},
+    ds18b20::Ds18b20TemperatureSensor,
+    quantize::quantize,
+    scanner::{get_all_ds18b20_sensors, validate_base_path},
+};
 use crate::{
     config::types::Example,
     hardware::types::{HardwareMetadata, HardwareType, SourceType},
+    quality::{config::QualityConfig, range::ReadingQuality},
+    relations::config::RelationsConfig,
+};
+#[cfg(feature = "one-wire")]
+use crate::{
+    hardware::duplicates::{remove_duplicates, report_duplicates},
+    introspection,
+    quality::range::RangeChecker,
+    sampling,
+    scheduler::job::{PollFuture, PollJob},
 };
 use serde::{Deserialize, Serialize};
-use std::{cmp::max, time::Duration};
-use tokio::{sync::broadcast, time::sleep};
+#[cfg(feature = "one-wire")]
+use std::{cmp::max, path::PathBuf, time::Duration};
+use tokio::sync::broadcast;
+#[cfg(feature = "one-wire")]
+use tokio::time::sleep;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MeasuredTemperature {
     pub meta: HardwareMetadata,
     pub temperature: Option<f64>,
     pub resolution: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub quality: Option<ReadingQuality>,
 }
 
 impl Example for MeasuredTemperature {
@@ -30,13 +53,16 @@ impl Example for MeasuredTemperature {
             ),
             temperature: Some(0.0),
             resolution: Some(12),
+            quality: None,
         }
     }
 }
 
 pub async fn start_one_wire_updater_loop(
-    mut shutdown_rx: broadcast::Receiver<()>,
+    shutdown_rx: broadcast::Receiver<()>,
     config: OneWireConfig,
+    quality_config: QualityConfig,
+    relations_config: RelationsConfig,
     tx: broadcast::Sender<Vec<MeasuredTemperature>>,
 ) {
     // Check if module is enabled
@@ -44,45 +70,224 @@ pub async fn start_one_wire_updater_loop(
         tracing::trace!("Module is disabled");
         return;
     }
-    tracing::debug!("Starting one wire updater loop");
-    // Extract config fields
-    let base_path = config.get_base_path();
-    let cooldown = max(config.get_cooldown(), Duration::from_millis(200));
-    // Start measuring temperature
-    loop {
+    #[cfg(feature = "one-wire")]
+    run(shutdown_rx, config, quality_config, relations_config, tx).await;
+    #[cfg(not(feature = "one-wire"))]
+    {
+        let _ = (shutdown_rx, quality_config, relations_config, tx);
+        tracing::error!(
+            "1-Wire is enabled in config but this binary was built without one-wire feature"
+        );
+    }
+}
+
+/// State kept between 1-Wire polls
+#[cfg(feature = "one-wire")]
+pub struct OneWirePoller {
+    config: OneWireConfig,
+    relations_config: RelationsConfig,
+    aliases: Option<AliasFile>,
+    range_checker: RangeChecker,
+    base_path: PathBuf,
+    base_path_existed: bool,
+    cooldown: Duration,
+    // Partial snapshots are published only until the first sweep is done
+    first_sweep_done: bool,
+    tx: broadcast::Sender<Vec<MeasuredTemperature>>,
+}
+
+#[cfg(feature = "one-wire")]
+impl OneWirePoller {
+    pub fn new(
+        config: OneWireConfig,
+        quality_config: &QualityConfig,
+        relations_config: RelationsConfig,
+        tx: broadcast::Sender<Vec<MeasuredTemperature>>,
+    ) -> Self {
+        // Extract config fields
+        let base_path = config.get_base_path();
+        let cooldown = max(config.get_cooldown(), Duration::from_millis(200));
+        // Report misconfiguration once instead of silently returning zero sensors
+        validate_base_path(&base_path);
+        let base_path_existed = base_path.is_dir();
+        let aliases = config.get_aliases_file().map(AliasFile::new);
+        Self {
+            config,
+            relations_config,
+            aliases,
+            range_checker: RangeChecker::new(quality_config),
+            base_path,
+            base_path_existed,
+            cooldown,
+            first_sweep_done: false,
+            tx,
+        }
+    }
+
+    /// Read temperature and resolution of a single sensor
+    fn measure(&mut self, sensor: &Ds18b20TemperatureSensor) -> MeasuredTemperature {
+        let mut meta = sensor.meta.clone().measured_now();
+        meta.hw.name = self
+            .aliases
+            .as_ref()
+            .and_then(|aliases| aliases.get(&meta.hw.id))
+            .or_else(|| self.config.get_name(&meta.hw.id))
+            .or_else(|| sensor.get_kernel_name());
+        self.relations_config.annotate(&mut meta);
+        let mut temperature = sensor.get_temperature();
+        // Before any comparison, so noise doesn't look like a change
+        if let Some(step) = self.config.get_quantization(&meta.hw.id) {
+            temperature = temperature.map(|temperature| quantize(temperature, step));
+        }
+        let resolution = sensor.get_resolution();
+        let quality = self.range_checker.check(&meta.hw.id, temperature);
+        MeasuredTemperature {
+            meta,
+            temperature,
+            resolution,
+            quality,
+        }
+    }
+
+    fn publish(&self, sensors: Vec<MeasuredTemperature>) {
+        tracing::trace!("Sending {:?} to channel", sensors);
+        if self.tx.receiver_count() > 0 {
+            self.tx.send(sensors).unwrap();
+            introspection::observe_channel("one_wire", &self.tx);
+        }
+    }
+
+    /// Read all sensors once and publish them, returns delay until the next poll
+    pub async fn poll_once(&mut self) -> Duration {
+        introspection::mark_iteration("one_wire");
+        if self.config.get_bulk_read() {
+            // Bus masters can appear later, same as sensors
+            let bulk_read_paths = find_bulk_read_paths(&self.base_path).await;
+            if bulk_read_paths.is_empty() {
+                tracing::debug!("No bus master supports therm_bulk_read");
+            } else {
+                trigger_bulk_conversion(&bulk_read_paths).await;
+            }
+        }
+        // Checking every poll makes renaming sensors take effect without a restart
+        if let Some(aliases) = &mut self.aliases {
+            aliases.refresh().await;
+        }
         // Find all sensors - calling inside loop makes sensors hot-swappable
-        let sensors = get_all_ds18b20_sensors(&base_path).await;
-        // Map additional fields: temperature and resolution
+        let mut sensors = get_all_ds18b20_sensors(&self.base_path).await;
+        let duplicates = remove_duplicates(&mut sensors, |sensor| &sensor.meta);
+        report_duplicates("temperature", &duplicates);
+        if !self.base_path_existed && self.base_path.is_dir() {
+            // Re-check after base_path appears (ex. w1-gpio loaded late)
+            self.base_path_existed = true;
+            if validate_base_path(&self.base_path) {
+                tracing::info!("1-Wire base_path is now available");
+            }
+        }
+        // Sensors without any temperature reading are skipped
         tracing::trace!("Mapping temperature and resolution");
-        let sensors: Vec<MeasuredTemperature> = sensors
-            .iter()
-            .map(|sensor| {
-                let meta = sensor.meta.clone();
-                let temperature = sensor.get_temperature();
-                let resolution = sensor.get_resolution();
-                MeasuredTemperature {
-                    meta,
-                    temperature,
-                    resolution,
-                }
-            })
-            .collect();
-        // Filter sensors that have any temperature reading
-        tracing::trace!("Filtering empty readings");
-        let sensors: Vec<MeasuredTemperature> = sensors
-            .into_iter()
-            .filter(|sensor| sensor.temperature.is_some())
-            .collect();
-        tracing::trace!("Sending {:?} to channel", sensors);
-        if tx.receiver_count() > 0 {
-            tx.send(sensors).unwrap();
+        let batch_size = match self.first_sweep_done {
+            true => None,
+            false => self.config.get_startup_batch_size(),
+        };
+        let total = sensors.len();
+        let mut measured = Vec::with_capacity(total);
+        for (index, sensor) in sensors.iter().enumerate() {
+            let sensor = self.measure(sensor);
+            if sensor.temperature.is_some() {
+                measured.push(sensor);
+            }
+            let read = index + 1;
+            // Every partial snapshot has all sensors of the previous one, so sinks only gain data
+            if batch_size.map_or(false, |size| read % size == 0 && read < total) {
+                tracing::debug!("Read {} of {} 1-Wire sensors, publishing", read, total);
+                self.publish(measured.clone());
+                tokio::task::yield_now().await;
+            }
         }
+        self.first_sweep_done = true;
+        self.publish(measured);
+        introspection::mark_idle("one_wire");
+        self.cooldown
+    }
+}
+
+#[cfg(feature = "one-wire")]
+impl PollJob for OneWirePoller {
+    fn name(&self) -> String {
+        String::from("one_wire")
+    }
+
+    fn poll(&mut self) -> PollFuture<'_> {
+        Box::pin(self.poll_once())
+    }
+}
+
+#[cfg(feature = "one-wire")]
+async fn run(
+    mut shutdown_rx: broadcast::Receiver<()>,
+    config: OneWireConfig,
+    quality_config: QualityConfig,
+    relations_config: RelationsConfig,
+    tx: broadcast::Sender<Vec<MeasuredTemperature>>,
+) {
+    tracing::debug!("Starting one wire updater loop");
+    let mut poller = OneWirePoller::new(config, &quality_config, relations_config, tx);
+    let _task = introspection::task_started("one_wire");
+    // Start measuring temperature
+    loop {
+        let delay = poller.poll_once().await;
         tokio::select! {
             _ = shutdown_rx.recv() => {
                 tracing::trace!("Shutting down one wire updater loop");
                 break;
             }
-            _ = sleep(cooldown) => {}
+            _ = sleep(sampling::tick::aligned(delay)) => {}
         }
     }
 }
+
+#[cfg(all(test, feature = "one-wire"))]
+mod tests {
+    use super::*;
+
+    fn create_sensors(base_path: &std::path::Path, count: usize) {
+        for index in 0..count {
+            let device_dir = base_path.join(format!("28-{:012x}", index));
+            std::fs::create_dir(&device_dir).unwrap();
+            std::fs::write(device_dir.join("temperature"), "21500").unwrap();
+            std::fs::write(device_dir.join("resolution"), "12").unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_startup_batches() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        create_sensors(temp_dir.path(), 5);
+        let config: OneWireConfig = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "base_path": temp_dir.path(),
+            "startup_batch_size": 2,
+        }))
+        .unwrap();
+        let (tx, mut rx) = broadcast::channel(16);
+        let mut poller = OneWirePoller::new(
+            config,
+            &QualityConfig::default(),
+            RelationsConfig::default(),
+            tx,
+        );
+
+        poller.poll_once().await;
+        let sizes: Vec<usize> = std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|sensors| sensors.len())
+            .collect();
+        assert_eq!(sizes, vec![2, 4, 5]);
+        // Later sweeps are published at once
+        poller.poll_once().await;
+        let sizes: Vec<usize> = std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|sensors| sensors.len())
+            .collect();
+        assert_eq!(sizes, vec![5]);
+    }
+}
@@ -0,0 +1,101 @@
+// Licensed under the Open Software License version 3.0
+use super::client_ip::{ClientIp, TrustedProxies, FORWARDED_FOR_HEADER};
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    request::{FromRequest, Outcome},
+    Build, Request, Response, Rocket,
+};
+
+/// Resolve client address once per request
+fn client_ip<'r>(request: &'r Request<'_>) -> &'r ClientIp {
+    request.local_cache(|| {
+        let peer = request.remote().map(|remote| remote.ip());
+        let ip = match (request.rocket().state::<TrustedProxies>(), peer) {
+            (Some(proxies), Some(peer)) => {
+                Some(proxies.resolve(peer, request.headers().get_one(FORWARDED_FOR_HEADER)))
+            }
+            (_, peer) => peer,
+        };
+        ClientIp(ip)
+    })
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientIp {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(*client_ip(request))
+    }
+}
+
+/// Log every response with the resolved client address
+struct AccessLog;
+
+#[rocket::async_trait]
+impl Fairing for AccessLog {
+    fn info(&self) -> Info {
+        Info {
+            name: "Access log",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        tracing::debug!(
+            "{} \"{} {}\" {}",
+            client_ip(request),
+            request.method(),
+            request.uri(),
+            response.status().code
+        );
+    }
+}
+
+pub fn mount_access_log(rocket: Rocket<Build>, trusted_proxies: &[String]) -> Rocket<Build> {
+    rocket
+        .manage(TrustedProxies::new(trusted_proxies))
+        .attach(AccessLog)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::{get, http::Header, local::asynchronous::Client, routes};
+    use std::net::SocketAddr;
+
+    #[get("/ip")]
+    fn ip_route(client_ip: ClientIp) -> String {
+        client_ip.to_string()
+    }
+
+    async fn client() -> Client {
+        let rocket = rocket::build().mount("/", routes![ip_route]);
+        let rocket = mount_access_log(rocket, &[String::from("10.0.0.0/8")]);
+        Client::tracked(rocket).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_client_ip_behind_trusted_proxy() {
+        let client = client().await;
+        let response = client
+            .get("/ip")
+            .remote("10.0.0.2:50000".parse::<SocketAddr>().unwrap())
+            .header(Header::new(FORWARDED_FOR_HEADER, "198.51.100.1"))
+            .dispatch()
+            .await;
+        assert_eq!(response.into_string().await.unwrap(), "198.51.100.1");
+    }
+
+    #[tokio::test]
+    async fn test_client_ip_ignores_untrusted_header() {
+        let client = client().await;
+        let response = client
+            .get("/ip")
+            .remote("203.0.113.7:50000".parse::<SocketAddr>().unwrap())
+            .header(Header::new(FORWARDED_FOR_HEADER, "198.51.100.1"))
+            .dispatch()
+            .await;
+        assert_eq!(response.into_string().await.unwrap(), "203.0.113.7");
+    }
+}
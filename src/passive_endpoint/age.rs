@@ -0,0 +1,91 @@
+// Licensed under the Open Software License version 3.0
+//! `age_secs` of cached data, filled in right before it's returned
+use super::changes::Changes;
+use crate::{
+    fleet::registry::FromNode,
+    hardware::{reading::Reading, types::HardwareMetadata},
+    nut::sender::UninterruptiblePowerSupplyData,
+    one_wire::sender::MeasuredTemperature,
+};
+use chrono::{DateTime, Utc};
+
+/// Entry of any category in the cache
+pub(super) trait Cached {
+    fn meta(&self) -> &HardwareMetadata;
+    fn meta_mut(&mut self) -> &mut HardwareMetadata;
+}
+
+impl Cached for MeasuredTemperature {
+    fn meta(&self) -> &HardwareMetadata {
+        &self.meta
+    }
+
+    fn meta_mut(&mut self) -> &mut HardwareMetadata {
+        &mut self.meta
+    }
+}
+
+impl Cached for UninterruptiblePowerSupplyData {
+    fn meta(&self) -> &HardwareMetadata {
+        &self.meta
+    }
+
+    fn meta_mut(&mut self) -> &mut HardwareMetadata {
+        &mut self.meta
+    }
+}
+
+impl Cached for Reading {
+    fn meta(&self) -> &HardwareMetadata {
+        &self.meta
+    }
+
+    fn meta_mut(&mut self) -> &mut HardwareMetadata {
+        &mut self.meta
+    }
+}
+
+impl<T: Cached> Cached for FromNode<T> {
+    fn meta(&self) -> &HardwareMetadata {
+        self.data.meta()
+    }
+
+    fn meta_mut(&mut self) -> &mut HardwareMetadata {
+        self.data.meta_mut()
+    }
+}
+
+pub(super) trait WithAge {
+    fn with_age(self, now: DateTime<Utc>) -> Self;
+}
+
+impl<T: Cached> WithAge for Vec<T> {
+    fn with_age(self, now: DateTime<Utc>) -> Self {
+        self.into_iter()
+            .map(|mut item| {
+                item.meta_mut().set_age(now);
+                item
+            })
+            .collect()
+    }
+}
+
+impl<T: Cached> WithAge for Option<T> {
+    fn with_age(self, now: DateTime<Utc>) -> Self {
+        self.map(|mut item| {
+            item.meta_mut().set_age(now);
+            item
+        })
+    }
+}
+
+impl WithAge for Changes {
+    fn with_age(self, now: DateTime<Utc>) -> Self {
+        Self {
+            temperature: self.temperature.with_age(now),
+            ups: self.ups.with_age(now),
+            readings: self.readings.with_age(now),
+            ..self
+        }
+    }
+}
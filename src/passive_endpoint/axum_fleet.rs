@@ -0,0 +1,406 @@
+// Licensed under the Open Software License version 3.0
+//! `/fleet` routes of axum backend, same as the Rocket ones
+use super::{
+    age::WithAge,
+    axum_server::{json_response, ResponseFormat},
+    receiver::ApiResponse,
+};
+use crate::{
+    active_sender::headers::{parse_part, PART_HEADER, TOTAL_PARTS_HEADER},
+    fleet::{
+        auth::{check_node, NodeTokens},
+        config::FleetConfig,
+        encoding::decode_body,
+        registry::{current, FleetRegistry, NodeSnapshot, Registration, SharedRegistry},
+    },
+};
+use axum::{
+    async_trait,
+    body::Bytes,
+    extract::{DefaultBodyLimit, FromRequestParts, Path, State},
+    http::{header, request::Parts, HeaderMap, StatusCode},
+    response::Response,
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::Utc;
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Same as the default JSON limit of Rocket, applies to decoded bodies too
+const BODY_LIMIT: usize = 1024 * 1024;
+
+#[derive(Clone)]
+struct FleetState {
+    tokens: Arc<NodeTokens>,
+    registry: Arc<SharedRegistry>,
+    pretty_json: bool,
+}
+
+/// Passes only with `Authorization: Bearer` header of any fleet token,
+/// holds instance id the token belongs to
+struct NodeAuthorized(String);
+
+#[async_trait]
+impl FromRequestParts<FleetState> for NodeAuthorized {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &FleetState,
+    ) -> Result<Self, Self::Rejection> {
+        let authorization = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok());
+        state
+            .tokens
+            .node(authorization)
+            .map(NodeAuthorized)
+            .ok_or(StatusCode::UNAUTHORIZED)
+    }
+}
+
+fn header_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn json<T: Serialize>(
+    state: &FleetState,
+    format: &ResponseFormat,
+    status: StatusCode,
+    data: &T,
+) -> Response {
+    json_response(state.pretty_json, format, status, data)
+}
+
+fn error(state: &FleetState, format: &ResponseFormat, status: StatusCode, error: &str) -> Response {
+    json(state, format, status, &ApiResponse::<()>::error(error))
+}
+
+fn found<T: Serialize>(state: &FleetState, format: &ResponseFormat, data: Option<T>) -> Response {
+    let data = ApiResponse::new(data);
+    let status = match data.success {
+        true => StatusCode::OK,
+        false => StatusCode::NOT_FOUND,
+    };
+    json(state, format, status, &data)
+}
+
+/// Snapshot of a possibly compressed body
+fn read_snapshot(headers: &HeaderMap, body: Bytes) -> Result<NodeSnapshot, (StatusCode, String)> {
+    let encoding = header_value(headers, header::CONTENT_ENCODING.as_str());
+    let body = decode_body(body.to_vec(), encoding, BODY_LIMIT as u64).map_err(|error| {
+        let status = StatusCode::from_u16(error.status()).unwrap_or(StatusCode::BAD_REQUEST);
+        (status, error.to_string())
+    })?;
+    serde_json::from_slice(&body).map_err(|error| (StatusCode::BAD_REQUEST, error.to_string()))
+}
+
+async fn register_route(
+    NodeAuthorized(authorized): NodeAuthorized,
+    State(state): State<FleetState>,
+    format: ResponseFormat,
+    Json(registration): Json<Registration>,
+) -> Response {
+    if let Err(message) = check_node(&authorized, &registration.instance_id) {
+        return error(&state, &format, StatusCode::FORBIDDEN, &message);
+    }
+    let status = current(&state.registry)
+        .await
+        .register(registration, Utc::now());
+    json(
+        &state,
+        &format,
+        StatusCode::OK,
+        &ApiResponse::new(Some(status)),
+    )
+}
+
+async fn push_route(
+    NodeAuthorized(authorized): NodeAuthorized,
+    State(state): State<FleetState>,
+    format: ResponseFormat,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let snapshot = match read_snapshot(&headers, body) {
+        Ok(snapshot) => snapshot,
+        Err((status, message)) => return error(&state, &format, status, &message),
+    };
+    if let Err(message) = check_node(&authorized, &snapshot.instance_id) {
+        return error(&state, &format, StatusCode::FORBIDDEN, &message);
+    }
+    let part = parse_part(
+        header_value(&headers, PART_HEADER),
+        header_value(&headers, TOTAL_PARTS_HEADER),
+    );
+    let result = current(&state.registry)
+        .await
+        .push(snapshot, part, Utc::now());
+    match result {
+        Ok(status) => json(
+            &state,
+            &format,
+            StatusCode::OK,
+            &ApiResponse::new(Some(status)),
+        ),
+        Err(message) => error(&state, &format, StatusCode::BAD_REQUEST, &message),
+    }
+}
+
+async fn get_nodes_route(State(state): State<FleetState>, format: ResponseFormat) -> Response {
+    let statuses = current(&state.registry).await.statuses(Utc::now());
+    json(
+        &state,
+        &format,
+        StatusCode::OK,
+        &ApiResponse::new(Some(statuses)),
+    )
+}
+
+async fn get_health_route(State(state): State<FleetState>, format: ResponseFormat) -> Response {
+    let health = current(&state.registry).await.health(Utc::now());
+    json(
+        &state,
+        &format,
+        StatusCode::OK,
+        &ApiResponse::new(Some(health)),
+    )
+}
+
+async fn get_node_route(
+    State(state): State<FleetState>,
+    format: ResponseFormat,
+    Path(id): Path<String>,
+) -> Response {
+    let status = current(&state.registry).await.status(&id, Utc::now());
+    found(&state, &format, status)
+}
+
+async fn get_node_temperature_route(
+    State(state): State<FleetState>,
+    format: ResponseFormat,
+    Path(id): Path<String>,
+) -> Response {
+    let snapshot = current(&state.registry).await.snapshot(&id);
+    let sensors = snapshot.map(|snapshot| snapshot.sensors.with_age(Utc::now()));
+    found(&state, &format, sensors)
+}
+
+async fn get_node_ups_route(
+    State(state): State<FleetState>,
+    format: ResponseFormat,
+    Path(id): Path<String>,
+) -> Response {
+    let snapshot = current(&state.registry).await.snapshot(&id);
+    let upses = snapshot.map(|snapshot| snapshot.upses.with_age(Utc::now()));
+    found(&state, &format, upses)
+}
+
+async fn get_node_readings_route(
+    State(state): State<FleetState>,
+    format: ResponseFormat,
+    Path(id): Path<String>,
+) -> Response {
+    let snapshot = current(&state.registry).await.snapshot(&id);
+    let readings = snapshot.map(|snapshot| snapshot.readings.with_age(Utc::now()));
+    found(&state, &format, readings)
+}
+
+async fn get_temperature_route(
+    State(state): State<FleetState>,
+    format: ResponseFormat,
+) -> Response {
+    let sensors = current(&state.registry)
+        .await
+        .combined(|snapshot| &snapshot.sensors);
+    let sensors = sensors.with_age(Utc::now());
+    json(
+        &state,
+        &format,
+        StatusCode::OK,
+        &ApiResponse::new(Some(sensors)),
+    )
+}
+
+async fn get_ups_route(State(state): State<FleetState>, format: ResponseFormat) -> Response {
+    let upses = current(&state.registry)
+        .await
+        .combined(|snapshot| &snapshot.upses);
+    let upses = upses.with_age(Utc::now());
+    json(
+        &state,
+        &format,
+        StatusCode::OK,
+        &ApiResponse::new(Some(upses)),
+    )
+}
+
+async fn get_readings_route(State(state): State<FleetState>, format: ResponseFormat) -> Response {
+    let readings = current(&state.registry)
+        .await
+        .combined(|snapshot| &snapshot.readings);
+    let readings = readings.with_age(Utc::now());
+    json(
+        &state,
+        &format,
+        StatusCode::OK,
+        &ApiResponse::new(Some(readings)),
+    )
+}
+
+/// Nest `/fleet` routes if this instance is a fleet head
+pub(super) fn nest_fleet<S>(router: Router<S>, config: FleetConfig, pretty_json: bool) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    if !config.is_enabled() {
+        return router;
+    }
+    let tokens = config.get_tokens();
+    if tokens.is_empty() {
+        tracing::warn!("Fleet nodes can't push data because no tokens are configured");
+    }
+    let state = FleetState {
+        tokens: Arc::new(NodeTokens::new(tokens)),
+        registry: Arc::new(RwLock::new(FleetRegistry::new(
+            config.get_node_timeout(),
+            config.get_forget_after(),
+        ))),
+        pretty_json,
+    };
+    let fleet = Router::new()
+        .route("/register", post(register_route))
+        .route("/push", post(push_route))
+        .route("/nodes", get(get_nodes_route))
+        .route("/health", get(get_health_route))
+        .route("/nodes/:id", get(get_node_route))
+        .route("/nodes/:id/temperature", get(get_node_temperature_route))
+        .route("/nodes/:id/ups", get(get_node_ups_route))
+        .route("/nodes/:id/readings", get(get_node_readings_route))
+        .route("/temperature", get(get_temperature_route))
+        .route("/ups", get(get_ups_route))
+        .route("/readings", get(get_readings_route))
+        .layer(DefaultBodyLimit::max(BODY_LIMIT))
+        .with_state(state);
+    router.nest_service("/fleet", fleet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::types::Example,
+        fleet::registry::{FleetHealth, FromNode, NodeStatus},
+        one_wire::sender::MeasuredTemperature,
+    };
+    use axum::{body::Body, http::Request};
+    use std::io::Write;
+    use tower::ServiceExt;
+
+    fn test_router() -> Router {
+        let config: FleetConfig = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "tokens": {"node1": "secret", "node2": "other"},
+        }))
+        .unwrap();
+        nest_fleet(Router::new(), config, false)
+    }
+
+    fn snapshot(instance_id: &str) -> Vec<u8> {
+        serde_json::json!({
+            "sensors": [MeasuredTemperature::example()],
+            "upses": [],
+            "readings": [],
+            "instance_id": instance_id,
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    async fn push(
+        router: &Router,
+        token: &str,
+        encoding: Option<&str>,
+        body: Vec<u8>,
+    ) -> StatusCode {
+        let request = Request::post("/fleet/push")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, format!("Bearer {}", token));
+        let request = match encoding {
+            Some(encoding) => request.header(header::CONTENT_ENCODING, encoding),
+            None => request,
+        };
+        let request = request.body(Body::from(body)).unwrap();
+        router.clone().oneshot(request).await.unwrap().status()
+    }
+
+    async fn get(router: &Router, uri: &str) -> (StatusCode, String) {
+        let request = Request::get(uri).body(Body::empty()).unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_push_requires_token() {
+        let router = test_router();
+        let status = push(&router, "wrong", None, snapshot("node1")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_push_as_other_node() {
+        let router = test_router();
+        let status = push(&router, "other", None, snapshot("node1")).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = get(&router, "/fleet/nodes/node1").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_push_compressed() {
+        let router = test_router();
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&snapshot("node1")).unwrap();
+        let status = push(&router, "secret", Some("gzip"), encoder.finish().unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = get(&router, "/fleet/nodes/node1").await;
+        assert_eq!(status, StatusCode::OK);
+
+        let status = push(&router, "secret", Some("br"), snapshot("node1")).await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[tokio::test]
+    async fn test_push_and_query() {
+        let router = test_router();
+        let status = push(&router, "secret", None, snapshot("node1")).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (_, body) = get(&router, "/fleet/nodes").await;
+        let response: ApiResponse<Vec<NodeStatus>> = serde_json::from_str(&body).unwrap();
+        let nodes = response.data.unwrap();
+        assert_eq!(nodes[0].instance_id, "node1");
+        assert!(nodes[0].reachable);
+        assert_eq!(nodes[0].sensors, 1);
+
+        let (_, body) = get(&router, "/fleet/health").await;
+        let response: ApiResponse<FleetHealth> = serde_json::from_str(&body).unwrap();
+        let health = response.data.unwrap();
+        assert_eq!((health.nodes, health.reachable, health.sensors), (1, 1, 1));
+
+        let (_, body) = get(&router, "/fleet/temperature").await;
+        let response: ApiResponse<Vec<FromNode<MeasuredTemperature>>> =
+            serde_json::from_str(&body).unwrap();
+        assert_eq!(response.data.unwrap()[0].instance_id, "node1");
+
+        let (status, _) = get(&router, "/fleet/nodes/node1/ups").await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = get(&router, "/fleet/nodes/node2/ups").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
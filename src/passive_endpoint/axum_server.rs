@@ -0,0 +1,1077 @@
+// Licensed under the Open Software License version 3.0
+//! Lighter alternative to Rocket with the same routes and response shape
+use super::{
+    axum_fleet::nest_fleet,
+    changes::Changes,
+    client_ip::{ClientIp, TrustedProxies, FORWARDED_FOR_HEADER},
+    config::PassiveEndpointConfig,
+    expiry::Category,
+    live::{next_message, LiveEvent},
+    prometheus::{self, render_metrics},
+    receiver::{ApiResponse, CachedData, VersionInfo},
+    summary::{summarize_temperatures, summarize_upses},
+};
+use crate::{
+    fleet::config::FleetConfig,
+    introspection,
+    load_shedding::config::LoadSheddingConfig,
+    storage::config::StorageConfig,
+    wake_on_lan::{
+        config::{WakeOnLanConfig, WakeOnLanTarget},
+        packet::{wake_targets, WakeOutcome},
+    },
+};
+use axum::{
+    async_trait,
+    extract::{
+        rejection::QueryRejection,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        ConnectInfo, FromRequestParts, Path, Query, State,
+    },
+    http::{header, request::Parts, HeaderMap, Method, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    sync::Arc,
+};
+use tokio::sync::{broadcast, oneshot};
+
+#[derive(Clone)]
+struct AppState {
+    cache: Arc<CachedData>,
+    version: VersionInfo,
+    // Default JSON formatting, overridden by `?pretty=<bool>`
+    pretty_json: bool,
+    control_token: Option<String>,
+    wake_on_lan: WakeOnLanConfig,
+    load_shedding: LoadSheddingConfig,
+    trusted_proxies: TrustedProxies,
+}
+
+#[derive(Debug, Deserialize)]
+struct PrettyQuery {
+    pretty: Option<bool>,
+}
+
+/// `?pretty=<bool>` and whether the body is sent at all
+#[derive(Debug)]
+pub(super) struct ResponseFormat {
+    pretty: Option<bool>,
+    // Axum answers HEAD with the GET route, only headers are needed
+    head: bool,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ResponseFormat {
+    type Rejection = QueryRejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(query) = Query::<PrettyQuery>::from_request_parts(parts, state).await?;
+        Ok(Self {
+            pretty: query.pretty,
+            head: parts.method == Method::HEAD,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ChangesQuery {
+    since: Option<u64>,
+    epoch: Option<u64>,
+}
+
+/// JSON response that supports pretty and compact output
+fn json<T: Serialize>(
+    state: &AppState,
+    format: &ResponseFormat,
+    status: StatusCode,
+    data: &T,
+) -> Response {
+    json_response(state.pretty_json, format, status, data)
+}
+
+/// Same as `json`, for routes with a state of their own
+pub(super) fn json_response<T: Serialize>(
+    pretty_json: bool,
+    format: &ResponseFormat,
+    status: StatusCode,
+    data: &T,
+) -> Response {
+    if format.head {
+        return (status, [(header::CONTENT_TYPE, "application/json")]).into_response();
+    }
+    let body = match format.pretty.unwrap_or(pretty_json) {
+        true => serde_json::to_string_pretty(data),
+        false => serde_json::to_string(data),
+    };
+    match body {
+        Ok(body) => (status, [(header::CONTENT_TYPE, "application/json")], body).into_response(),
+        Err(error) => {
+            tracing::error!("Failed to serialize response: {}", error);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// 404 if `data` is `None`, same as Rocket routes
+fn json_or_not_found<T: Serialize>(
+    state: &AppState,
+    format: &ResponseFormat,
+    data: Option<T>,
+) -> Response {
+    let data = ApiResponse::new(data);
+    let status = match data.success {
+        true => StatusCode::OK,
+        false => StatusCode::NOT_FOUND,
+    };
+    json(state, format, status, &data)
+}
+
+/// 503 if every cached entry of `category` is stale
+async fn json_or_expired<T: Serialize>(
+    state: &AppState,
+    format: &ResponseFormat,
+    category: Category,
+    data: T,
+) -> Response {
+    if state.cache.is_expired(category).await {
+        let data = ApiResponse::expired(data);
+        return json(state, format, StatusCode::SERVICE_UNAVAILABLE, &data);
+    }
+    json_or_not_found(state, format, Some(data))
+}
+
+async fn get_version_route(State(state): State<AppState>, format: ResponseFormat) -> Response {
+    json_or_not_found(&state, &format, Some(state.version.clone()))
+}
+
+async fn get_temperature_sensors_route(
+    State(state): State<AppState>,
+    format: ResponseFormat,
+) -> Response {
+    let data = state.cache.get_temperature_sensors().await;
+    json_or_expired(&state, &format, Category::Temperature, data).await
+}
+
+async fn get_temperature_summary_route(
+    State(state): State<AppState>,
+    format: ResponseFormat,
+) -> Response {
+    let data = summarize_temperatures(&state.cache.get_temperature_sensors().await);
+    json_or_expired(&state, &format, Category::Temperature, data).await
+}
+
+async fn get_temperature_sensor_by_hw_id_route(
+    State(state): State<AppState>,
+    format: ResponseFormat,
+    Path(id): Path<String>,
+) -> Response {
+    let data = state.cache.get_temperature_sensor_by_hw_id(id).await;
+    json_or_not_found(&state, &format, data)
+}
+
+async fn get_temperature_sensor_by_name_route(
+    State(state): State<AppState>,
+    format: ResponseFormat,
+    Path(name): Path<String>,
+) -> Response {
+    let data = state.cache.get_temperature_sensor_by_name(name).await;
+    json_or_not_found(&state, &format, data)
+}
+
+async fn get_temperature_history_route(
+    State(state): State<AppState>,
+    format: ResponseFormat,
+    Path(id): Path<String>,
+) -> Response {
+    let data = state.cache.get_temperature_history(id).await;
+    json_or_not_found(&state, &format, data)
+}
+
+async fn get_upses_route(State(state): State<AppState>, format: ResponseFormat) -> Response {
+    let data = state.cache.get_upses().await;
+    json_or_expired(&state, &format, Category::Ups, data).await
+}
+
+async fn get_ups_summary_route(State(state): State<AppState>, format: ResponseFormat) -> Response {
+    let data = summarize_upses(&state.cache.get_upses().await);
+    json_or_expired(&state, &format, Category::Ups, data).await
+}
+
+async fn get_ups_by_hw_id_route(
+    State(state): State<AppState>,
+    format: ResponseFormat,
+    Path(id): Path<String>,
+) -> Response {
+    let data = state.cache.get_ups_by_hw_id(id).await;
+    json_or_not_found(&state, &format, data)
+}
+
+async fn get_ups_history_route(
+    State(state): State<AppState>,
+    format: ResponseFormat,
+    Path(id): Path<String>,
+) -> Response {
+    let data = state.cache.get_ups_history(id).await;
+    json_or_not_found(&state, &format, data)
+}
+
+async fn get_ups_clients_by_hw_id_route(
+    State(state): State<AppState>,
+    format: ResponseFormat,
+    Path(id): Path<String>,
+) -> Response {
+    let data = state
+        .cache
+        .get_ups_by_hw_id(id)
+        .await
+        .and_then(|ups| ups.clients);
+    json_or_not_found(&state, &format, data)
+}
+
+async fn get_load_shedding_route(
+    State(state): State<AppState>,
+    format: ResponseFormat,
+    Path(id): Path<String>,
+) -> Response {
+    let data = state
+        .cache
+        .get_load_shedding_plan(id, &state.load_shedding)
+        .await;
+    json_or_not_found(&state, &format, data)
+}
+
+async fn get_readings_route(State(state): State<AppState>, format: ResponseFormat) -> Response {
+    let data = state.cache.get_readings().await;
+    json_or_expired(&state, &format, Category::Readings, data).await
+}
+
+async fn get_metrics_route(State(state): State<AppState>, method: Method) -> Response {
+    if method == Method::HEAD {
+        return [(header::CONTENT_TYPE, prometheus::CONTENT_TYPE)].into_response();
+    }
+    let metrics = render_metrics(
+        &state.cache.get_temperature_sensors().await,
+        &state.cache.get_upses().await,
+        &state.cache.get_readings().await,
+    );
+    ([(header::CONTENT_TYPE, prometheus::CONTENT_TYPE)], metrics).into_response()
+}
+
+/// Liveness probe for load balancers, doesn't touch the cache
+async fn ping_route() -> &'static str {
+    "pong"
+}
+
+async fn get_reading_by_hw_id_route(
+    State(state): State<AppState>,
+    format: ResponseFormat,
+    Path(id): Path<String>,
+) -> Response {
+    let data = state.cache.get_reading_by_hw_id(id).await;
+    json_or_not_found(&state, &format, data)
+}
+
+async fn get_changes_route(
+    State(state): State<AppState>,
+    format: ResponseFormat,
+    Query(changes): Query<ChangesQuery>,
+) -> Response {
+    let data: Changes = state
+        .cache
+        .get_changes(changes.since.unwrap_or_default(), changes.epoch)
+        .await;
+    json_or_not_found(&state, &format, Some(data))
+}
+
+async fn get_internal_status_route(
+    State(state): State<AppState>,
+    format: ResponseFormat,
+) -> Response {
+    json_or_not_found(&state, &format, Some(introspection::snapshot()))
+}
+
+async fn live_stream_route(State(state): State<AppState>, ws: WebSocketUpgrade) -> Response {
+    let events = state.cache.subscribe_live();
+    ws.on_upgrade(move |socket| stream_live_events(socket, events))
+}
+
+/// Push every sensor and UPS update until the client disconnects
+async fn stream_live_events(mut socket: WebSocket, mut events: broadcast::Receiver<LiveEvent>) {
+    loop {
+        tokio::select! {
+            message = next_message(&mut events) => match message {
+                Some(message) => {
+                    if socket.send(Message::Text(message)).await.is_err() {
+                        break;
+                    }
+                }
+                None => break,
+            },
+            // Pings are answered while reading, anything else from the client is ignored
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+/// Resolve client address for handlers and log every response
+async fn access_log<B>(
+    State(state): State<AppState>,
+    mut request: axum::http::Request<B>,
+    next: Next<B>,
+) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(address)| address.ip());
+    let forwarded_for = request
+        .headers()
+        .get(FORWARDED_FOR_HEADER)
+        .and_then(|header| header.to_str().ok());
+    let client_ip = ClientIp(peer.map(|peer| state.trusted_proxies.resolve(peer, forwarded_for)));
+    request.extensions_mut().insert(client_ip);
+    let method = request.method().clone();
+    let uri = request.uri().clone();
+    let response = next.run(request).await;
+    tracing::debug!(
+        "{} \"{} {}\" {}",
+        client_ip,
+        method,
+        uri,
+        response.status().as_u16()
+    );
+    response
+}
+
+/// Pass only with a valid `Authorization: Bearer` header
+async fn authorize<B>(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    request: axum::http::Request<B>,
+    next: Next<B>,
+) -> Response {
+    let expected = match &state.control_token {
+        Some(token) => format!("Bearer {}", token),
+        None => return StatusCode::UNAUTHORIZED.into_response(),
+    };
+    match headers.get(header::AUTHORIZATION) {
+        Some(header) if header.as_bytes() == expected.as_bytes() => next.run(request).await,
+        _ => StatusCode::UNAUTHORIZED.into_response(),
+    }
+}
+
+async fn wake(
+    state: &AppState,
+    format: &ResponseFormat,
+    targets: Vec<WakeOnLanTarget>,
+) -> Response {
+    match wake_targets(&state.wake_on_lan, targets).await {
+        WakeOutcome::NoTargets => json_or_not_found::<Vec<String>>(state, format, None),
+        WakeOutcome::Failed => json(
+            state,
+            format,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &ApiResponse::<Vec<String>>::error("failed to send magic packet"),
+        ),
+        WakeOutcome::Woken(woken) => json_or_not_found(state, format, Some(woken)),
+    }
+}
+
+async fn wake_all_route(State(state): State<AppState>, format: ResponseFormat) -> Response {
+    let targets = state.wake_on_lan.get_targets();
+    wake(&state, &format, targets).await
+}
+
+async fn wake_by_name_route(
+    State(state): State<AppState>,
+    format: ResponseFormat,
+    Path(name): Path<String>,
+) -> Response {
+    let targets = state
+        .wake_on_lan
+        .get_targets()
+        .into_iter()
+        .filter(|target| target.name == name)
+        .collect();
+    wake(&state, &format, targets).await
+}
+
+#[cfg(feature = "export")]
+async fn export_route(
+    State(state): State<AppState>,
+    Path((category, format)): Path<(String, String)>,
+) -> Response {
+    use crate::export::batch::{temperatures_to_batch, upses_to_batch, write_batch, ExportFormat};
+
+    let format: ExportFormat = match format.parse() {
+        Ok(format) => format,
+        Err(_) => return StatusCode::NOT_FOUND.into_response(),
+    };
+    let batch = match category.as_str() {
+        "temperature" => temperatures_to_batch(&state.cache.get_temperature_sensors().await),
+        "ups" => upses_to_batch(&state.cache.get_upses().await),
+        _ => return StatusCode::NOT_FOUND.into_response(),
+    };
+    match batch.and_then(|batch| write_batch(&batch, format)) {
+        Ok(body) => {
+            let (top, sub) = format.get_media_type();
+            let content_type = format!("{}/{}", top, sub);
+            ([(header::CONTENT_TYPE, content_type)], body).into_response()
+        }
+        Err(error) => {
+            tracing::error!("Failed to export {}: {}", category, error);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[cfg(feature = "storage")]
+#[derive(Clone)]
+struct StorageState {
+    config: StorageConfig,
+    pretty_json: bool,
+}
+
+#[cfg(feature = "storage")]
+#[derive(Debug, Deserialize)]
+struct StorageQuery {
+    since: Option<String>,
+    until: Option<String>,
+    limit: Option<usize>,
+}
+
+#[cfg(feature = "storage")]
+#[derive(Debug, Deserialize)]
+struct DeleteQuery {
+    before: Option<String>,
+}
+
+/// Same response as Rocket for the result of a query or delete
+#[cfg(feature = "storage")]
+fn storage_response<T: Serialize>(
+    state: &StorageState,
+    format: &ResponseFormat,
+    result: Result<T, crate::storage::query::QueryError>,
+) -> Response {
+    let (status, data) = match result {
+        Ok(data) => (StatusCode::OK, ApiResponse::new(Some(data))),
+        Err(error) => {
+            let data = match error.message() {
+                Some(message) => ApiResponse::error(message),
+                None => ApiResponse::new(None),
+            };
+            let status =
+                StatusCode::from_u16(error.status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+            (status, data)
+        }
+    };
+    json_response(state.pretty_json, format, status, &data)
+}
+
+#[cfg(feature = "storage")]
+async fn get_stored_measurements_route(
+    State(state): State<StorageState>,
+    format: ResponseFormat,
+    Path((category, id)): Path<(String, String)>,
+    Query(query): Query<StorageQuery>,
+) -> Response {
+    use crate::storage::query::query_stored;
+
+    let result = query_stored(
+        &state.config,
+        &category,
+        id,
+        query.since.as_deref(),
+        query.until.as_deref(),
+        query.limit,
+    )
+    .await;
+    storage_response(&state, &format, result)
+}
+
+/// Number of deleted rows
+#[cfg(feature = "storage")]
+async fn delete_stored_measurements_route(
+    State(state): State<StorageState>,
+    format: ResponseFormat,
+    Path((category, id)): Path<(String, String)>,
+    Query(query): Query<DeleteQuery>,
+) -> Response {
+    use crate::storage::query::delete_stored;
+
+    let result = delete_stored(&state.config, &category, id, query.before.as_deref()).await;
+    storage_response(&state, &format, result)
+}
+
+/// Nest `/storage/<temperature|ups|readings>/<id>` if the module is enabled,
+/// deleting requires the control token and isn't routed in read-only mode
+#[cfg(feature = "storage")]
+fn nest_storage<S>(
+    router: Router<S>,
+    config: StorageConfig,
+    state: &AppState,
+    read_only: bool,
+) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    if !config.is_enabled() {
+        return router;
+    }
+    let storage = Router::new().route("/:category/:id", get(get_stored_measurements_route));
+    let storage = match read_only {
+        true => storage,
+        false => storage.route(
+            "/:category/:id",
+            axum::routing::delete(delete_stored_measurements_route)
+                .route_layer(middleware::from_fn_with_state(state.clone(), authorize)),
+        ),
+    };
+    let storage = storage.with_state(StorageState {
+        config,
+        pretty_json: state.pretty_json,
+    });
+    router.nest_service("/storage", storage)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn router(
+    cache: Arc<CachedData>,
+    config: &PassiveEndpointConfig,
+    wake_on_lan: WakeOnLanConfig,
+    load_shedding: LoadSheddingConfig,
+    fleet: FleetConfig,
+    storage: StorageConfig,
+    instance_id: String,
+    read_only: bool,
+) -> Router {
+    let control_token = config
+        .get_control_token()
+        .filter(|control_token| !control_token.is_empty());
+    let state = AppState {
+        cache,
+        version: VersionInfo::new(instance_id),
+        pretty_json: config.get_pretty_json(),
+        control_token,
+        wake_on_lan,
+        load_shedding,
+        trusted_proxies: TrustedProxies::new(&config.get_trusted_proxies()),
+    };
+    let router = Router::new()
+        .route("/version", get(get_version_route))
+        .route("/temperature", get(get_temperature_sensors_route))
+        .route("/temperature/summary", get(get_temperature_summary_route))
+        .route(
+            "/temperature/:id",
+            get(get_temperature_sensor_by_hw_id_route),
+        )
+        .route(
+            "/temperature/by-name/:name",
+            get(get_temperature_sensor_by_name_route),
+        )
+        .route(
+            "/temperature/:id/history",
+            get(get_temperature_history_route),
+        )
+        .route("/ups", get(get_upses_route))
+        .route("/ups/summary", get(get_ups_summary_route))
+        .route("/ups/:id", get(get_ups_by_hw_id_route))
+        .route("/ups/:id/clients", get(get_ups_clients_by_hw_id_route))
+        .route("/ups/:id/history", get(get_ups_history_route))
+        .route("/readings", get(get_readings_route))
+        .route("/readings/:id", get(get_reading_by_hw_id_route))
+        .route("/changes", get(get_changes_route))
+        .route("/metrics", get(get_metrics_route))
+        .route("/ping", get(ping_route))
+        .route("/ws", get(live_stream_route));
+    let router = match state.load_shedding.is_enabled() {
+        true => router.route("/ups/:id/load-shedding", get(get_load_shedding_route)),
+        false => router,
+    };
+    #[cfg(feature = "export")]
+    let router = router.route("/export/:category/:format", get(export_route));
+    let router = nest_fleet(router, fleet, state.pretty_json);
+    #[cfg(feature = "storage")]
+    let router = nest_storage(router, storage, &state, read_only);
+    #[cfg(not(feature = "storage"))]
+    let _ = storage;
+    // Internal state isn't public, it always requires the control token
+    let internal_status = Router::new()
+        .route("/status/internal", get(get_internal_status_route))
+        .route_layer(middleware::from_fn_with_state(state.clone(), authorize));
+    let router = router.merge(internal_status);
+    // Mount `/control` routes if a token is configured and not in read-only mode
+    let router = match (&state.control_token, state.wake_on_lan.is_enabled()) {
+        (Some(_), _) if read_only => {
+            tracing::info!("Control routes are disabled in read-only mode");
+            router
+        }
+        (Some(_), true) => {
+            let control = Router::new()
+                .route("/wol", post(wake_all_route))
+                .route("/wol/:name", post(wake_by_name_route))
+                .route_layer(middleware::from_fn_with_state(state.clone(), authorize));
+            router.nest("/control", control)
+        }
+        (None, _) => {
+            tracing::trace!("Control routes are disabled because control_token is not set");
+            router
+        }
+        _ => router,
+    };
+    router
+        .layer(middleware::from_fn_with_state(state.clone(), access_log))
+        .with_state(state)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn serve(
+    mut shutdown_rx: broadcast::Receiver<()>,
+    cache: Arc<CachedData>,
+    config: PassiveEndpointConfig,
+    wake_on_lan: WakeOnLanConfig,
+    load_shedding: LoadSheddingConfig,
+    fleet: FleetConfig,
+    storage: StorageConfig,
+    instance_id: String,
+    read_only: bool,
+    ready_tx: oneshot::Sender<()>,
+) {
+    // Never fall back to plain HTTP if HTTPS was requested
+    if config.get_tls_cert_path().is_some() || config.get_tls_key_path().is_some() {
+        tracing::error!("Passive endpoint not started: TLS is only supported by the Rocket backend, build without axum feature");
+        return;
+    }
+    // Same as Rocket's default address
+    let address = SocketAddr::from((Ipv4Addr::LOCALHOST, config.get_port()));
+    let router = router(
+        cache,
+        &config,
+        wake_on_lan,
+        load_shedding,
+        fleet,
+        storage,
+        instance_id,
+        read_only,
+    );
+    let server = match axum::Server::try_bind(&address) {
+        Ok(server) => {
+            let _ = ready_tx.send(());
+            server
+        }
+        Err(error) => {
+            tracing::error!("Failed to bind passive endpoint to {}: {}", address, error);
+            return;
+        }
+    };
+    let result = server
+        .serve(router.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(async move {
+            let _ = shutdown_rx.recv().await;
+            tracing::trace!("Shutting down axum");
+        })
+        .await;
+    if let Err(error) = result {
+        tracing::error!("Passive endpoint failed: {}", error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::types::Example, load_shedding::plan::LoadSheddingPlan,
+        nut::sender::UninterruptiblePowerSupplyData, one_wire::sender::MeasuredTemperature,
+        passive_endpoint::summary::UpsSummary,
+    };
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    fn test_router(cache: Arc<CachedData>) -> Router {
+        test_router_with(cache, false)
+    }
+
+    fn test_router_with(cache: Arc<CachedData>, read_only: bool) -> Router {
+        let config: PassiveEndpointConfig = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "control_token": "secret",
+        }))
+        .unwrap();
+        // Send magic packets to localhost instead of broadcasting them
+        let wake_on_lan: WakeOnLanConfig = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "broadcast_address": "127.0.0.1:9",
+            "targets": WakeOnLanConfig::example().get_targets()
+        }))
+        .unwrap();
+        let mut load_shedding = serde_json::to_value(LoadSheddingConfig::example()).unwrap();
+        load_shedding["enabled"] = serde_json::Value::Bool(true);
+        let load_shedding = serde_json::from_value(load_shedding).unwrap();
+        router(
+            cache,
+            &config,
+            wake_on_lan,
+            load_shedding,
+            FleetConfig::default(),
+            StorageConfig::default(),
+            String::from("00000000-0000-0000-0000-000000000000"),
+            read_only,
+        )
+    }
+
+    async fn get(router: Router, uri: &str) -> (StatusCode, String) {
+        let request = Request::get(uri).body(Body::empty()).unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_get_sensors() {
+        let cache = Arc::new(CachedData::default());
+        let sensors = vec![MeasuredTemperature::example()];
+        cache.set_sensors(sensors.clone()).await;
+
+        let (status, body) = get(test_router(cache.clone()), "/temperature").await;
+        assert_eq!(status, StatusCode::OK);
+        let response: ApiResponse<Vec<MeasuredTemperature>> = serde_json::from_str(&body).unwrap();
+        assert!(response.success);
+        assert_eq!(response.data.unwrap(), sensors);
+
+        let (status, body) = get(test_router(cache), "/temperature/fake_hw_id?pretty=true").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("\n  \"success\": true"));
+    }
+
+    #[tokio::test]
+    async fn test_get_ups_summary() {
+        let cache = Arc::new(CachedData::default());
+        cache
+            .set_upses(vec![UninterruptiblePowerSupplyData::example()])
+            .await;
+        let (status, body) = get(test_router(cache), "/ups/summary").await;
+        assert_eq!(status, StatusCode::OK);
+        let response: ApiResponse<UpsSummary> = serde_json::from_str(&body).unwrap();
+        let summary = response.data.unwrap();
+        assert_eq!(summary.count, 1);
+        assert_eq!(summary.max_load, Some(15.0));
+        assert_eq!(summary.min_battery_charge, Some(100.0));
+    }
+
+    #[tokio::test]
+    async fn test_get_ups_404() {
+        let cache = Arc::new(CachedData::default());
+        cache
+            .set_upses(vec![UninterruptiblePowerSupplyData::example()])
+            .await;
+        let (status, body) = get(test_router(cache.clone()), "/ups/non-existent-id").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let response: ApiResponse<UninterruptiblePowerSupplyData> =
+            serde_json::from_str(&body).unwrap();
+        assert!(!response.success);
+        assert_eq!(response.error.unwrap(), "not found");
+
+        // UPS without listed clients
+        let (status, _) = get(test_router(cache), "/ups/fake_hw_id/clients").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_wol_requires_token() {
+        let cache = Arc::new(CachedData::default());
+        let request = Request::post("/control/wol").body(Body::empty()).unwrap();
+        let response = test_router(cache.clone()).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let request = Request::post("/control/wol/server1")
+            .header(header::AUTHORIZATION, "Bearer secret")
+            .body(Body::empty())
+            .unwrap();
+        let response = test_router(cache).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_control_disabled_in_read_only_mode() {
+        let cache = Arc::new(CachedData::default());
+        let request = Request::post("/control/wol/server1")
+            .header(header::AUTHORIZATION, "Bearer secret")
+            .body(Body::empty())
+            .unwrap();
+        let response = test_router_with(cache.clone(), true)
+            .oneshot(request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        // Internal status isn't a control route
+        let request = Request::get("/status/internal")
+            .header(header::AUTHORIZATION, "Bearer secret")
+            .body(Body::empty())
+            .unwrap();
+        let response = test_router_with(cache, true)
+            .oneshot(request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_get_changes() {
+        let cache = Arc::new(CachedData::default());
+        cache
+            .set_sensors(vec![MeasuredTemperature::example()])
+            .await;
+
+        let (status, body) = get(test_router(cache.clone()), "/changes").await;
+        assert_eq!(status, StatusCode::OK);
+        let response: ApiResponse<Changes> = serde_json::from_str(&body).unwrap();
+        let changes = response.data.unwrap();
+        assert_eq!(changes.temperature.len(), 1);
+
+        let uri = format!(
+            "/changes?since={}&epoch={}",
+            changes.sequence, changes.epoch
+        );
+        let (status, body) = get(test_router(cache.clone()), &uri).await;
+        assert_eq!(status, StatusCode::OK);
+        let response: ApiResponse<Changes> = serde_json::from_str(&body).unwrap();
+        assert!(response.data.unwrap().temperature.is_empty());
+
+        // Synced with a previous run of the server
+        let uri = format!(
+            "/changes?since={}&epoch={}",
+            changes.sequence,
+            changes.epoch - 1
+        );
+        let (_, body) = get(test_router(cache), &uri).await;
+        let response: ApiResponse<Changes> = serde_json::from_str(&body).unwrap();
+        let changes = response.data.unwrap();
+        assert!(changes.reset);
+        assert_eq!(changes.temperature.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_access_log_resolves_client_ip() {
+        let cache = Arc::new(CachedData::default());
+        let config: PassiveEndpointConfig = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "trusted_proxies": ["10.0.0.0/8"],
+        }))
+        .unwrap();
+        let state = AppState {
+            cache,
+            version: VersionInfo::new(String::new()),
+            pretty_json: false,
+            control_token: None,
+            wake_on_lan: WakeOnLanConfig::default(),
+            load_shedding: LoadSheddingConfig::default(),
+            trusted_proxies: TrustedProxies::new(&config.get_trusted_proxies()),
+        };
+        async fn ip_route(axum::Extension(client_ip): axum::Extension<ClientIp>) -> String {
+            client_ip.to_string()
+        }
+        let router = Router::new()
+            .route("/ip", get(ip_route))
+            .layer(middleware::from_fn_with_state(state.clone(), access_log))
+            .with_state(state);
+
+        let peer: SocketAddr = "10.0.0.2:50000".parse().unwrap();
+        let mut request = Request::get("/ip")
+            .header(FORWARDED_FOR_HEADER, "198.51.100.1")
+            .body(Body::empty())
+            .unwrap();
+        request.extensions_mut().insert(ConnectInfo(peer));
+        let response = router.oneshot(request).await.unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"198.51.100.1");
+    }
+
+    #[tokio::test]
+    async fn test_internal_status_requires_token() {
+        let cache = Arc::new(CachedData::default());
+        let (status, _) = get(test_router(cache.clone()), "/status/internal").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let request = Request::get("/status/internal")
+            .header(header::AUTHORIZATION, "Bearer secret")
+            .body(Body::empty())
+            .unwrap();
+        let response = test_router(cache).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_get_metrics() {
+        let cache = Arc::new(CachedData::default());
+        cache
+            .set_upses(vec![UninterruptiblePowerSupplyData::example()])
+            .await;
+        let (status, body) = get(test_router(cache), "/metrics").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("# TYPE uds_ups_battery_charge gauge"));
+    }
+
+    #[tokio::test]
+    async fn test_head_and_ping() {
+        let cache = Arc::new(CachedData::default());
+        cache
+            .set_sensors(vec![MeasuredTemperature::example()])
+            .await;
+        let head = |uri: &str| Request::head(uri).body(Body::empty()).unwrap();
+
+        let response = test_router(cache.clone())
+            .oneshot(head("/temperature"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert!(body.is_empty());
+
+        let response = test_router(cache.clone())
+            .oneshot(head("/temperature/non-existent-id"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let (status, body) = get(test_router(cache), "/ping").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "pong");
+    }
+
+    #[tokio::test]
+    async fn test_get_load_shedding_plan() {
+        let cache = Arc::new(CachedData::default());
+        cache.set_upses(vec![crate::schema::fixtures::ups()]).await;
+
+        let (status, body) = get(
+            test_router(cache.clone()),
+            "/ups/%5Bups1%5Dups-monitor@localhost:3493/load-shedding",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let response: ApiResponse<LoadSheddingPlan> = serde_json::from_str(&body).unwrap();
+        assert_eq!(response.data.unwrap().steps.len(), 2);
+
+        let (status, _) = get(test_router(cache), "/ups/fake_hw_id/load-shedding").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[cfg(feature = "storage")]
+    fn test_storage_router(read_only: bool) -> (std::path::PathBuf, Router) {
+        use crate::storage::{
+            database::Database,
+            rows::{Category, Row},
+        };
+
+        let path =
+            std::env::temp_dir().join(format!("uds-storage-{}.sqlite", uuid::Uuid::new_v4()));
+        let rows: Vec<Row> = (0..3)
+            .map(|second| Row {
+                measured_at: 1672531200000 + second * 1000,
+                category: Category::Temperature,
+                hw_id: String::from("fake_hw_id"),
+                data: format!("{{\"temperature\":{}}}", second),
+            })
+            .collect();
+        Database::open(&path).unwrap().insert(&rows).unwrap();
+        let config: StorageConfig = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "path": path,
+        }))
+        .unwrap();
+        let endpoint: PassiveEndpointConfig = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "control_token": "secret",
+        }))
+        .unwrap();
+        let router = router(
+            Arc::new(CachedData::default()),
+            &endpoint,
+            WakeOnLanConfig::default(),
+            LoadSheddingConfig::default(),
+            FleetConfig::default(),
+            config,
+            String::from("00000000-0000-0000-0000-000000000000"),
+            read_only,
+        );
+        (path, router)
+    }
+
+    #[cfg(feature = "storage")]
+    fn remove(path: &std::path::Path) {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+
+    #[cfg(feature = "storage")]
+    #[tokio::test]
+    async fn test_get_stored_measurements() {
+        use crate::storage::database::StoredMeasurement;
+
+        let (path, router) = test_storage_router(false);
+        let (status, body) = get(
+            router.clone(),
+            "/storage/temperature/fake_hw_id?since=2023-01-01T00:00:01Z&limit=5",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let response: ApiResponse<Vec<StoredMeasurement>> = serde_json::from_str(&body).unwrap();
+        let data = response.data.unwrap();
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[0].data["temperature"], 1);
+
+        let (status, _) = get(
+            router.clone(),
+            "/storage/temperature/fake_hw_id?until=yesterday",
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = get(router, "/storage/changes/fake_hw_id").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        remove(&path);
+    }
+
+    #[cfg(feature = "storage")]
+    #[tokio::test]
+    async fn test_delete_stored_measurements() {
+        let uri = "/storage/temperature/fake_hw_id?before=2023-01-01T00:00:01Z";
+        let delete = |token: Option<&str>| {
+            let request = Request::delete(uri);
+            let request = match token {
+                Some(token) => request.header(header::AUTHORIZATION, format!("Bearer {}", token)),
+                None => request,
+            };
+            request.body(Body::empty()).unwrap()
+        };
+
+        let (path, router) = test_storage_router(false);
+        let response = router.clone().oneshot(delete(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = router
+            .clone()
+            .oneshot(delete(Some("secret")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let response: ApiResponse<usize> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response.data, Some(1));
+        remove(&path);
+
+        let (path, router) = test_storage_router(true);
+        let response = router.oneshot(delete(Some("secret"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        remove(&path);
+    }
+}
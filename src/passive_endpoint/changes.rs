@@ -0,0 +1,296 @@
+// Licensed under the Open Software License version 3.0
+use crate::{
+    hardware::reading::Reading, nut::sender::UninterruptiblePowerSupplyData,
+    one_wire::sender::MeasuredTemperature,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Ids of removed entries kept for clients that sync rarely, older ones are dropped
+const MAX_REMOVED: usize = 1024;
+
+/// Everything updated after a given sequence number
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct Changes {
+    /// Latest sequence number, pass it as `since` on the next request
+    pub sequence: u64,
+    /// Startup time of the server (Unix ms), pass it as `epoch` on the next request
+    pub epoch: u64,
+    /// `since` is from another epoch, newer than `sequence` or older than kept removed ids,
+    /// replace the local copy
+    pub reset: bool,
+    pub temperature: Vec<MeasuredTemperature>,
+    pub ups: Vec<UninterruptiblePowerSupplyData>,
+    pub readings: Vec<Reading>,
+    /// Removed entries as `<category>:<hw.id>`
+    pub removed: Vec<String>,
+}
+
+/// Latest value of every entry with the sequence number of its last change
+#[derive(Debug)]
+struct Tracked<T> {
+    entries: HashMap<String, (u64, T)>,
+}
+
+impl<T> Default for Tracked<T> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl<T: PartialEq + Clone> Tracked<T> {
+    /// Replace all entries, returning ids that disappeared
+    fn replace(&mut self, items: Vec<(String, T)>, sequence: u64) -> Vec<String> {
+        let mut previous = std::mem::take(&mut self.entries);
+        for (id, item) in items {
+            let changed_at = match previous.remove(&id) {
+                Some((changed_at, old)) if old == item => changed_at,
+                _ => sequence,
+            };
+            self.entries.insert(id, (changed_at, item));
+        }
+        previous.into_keys().collect()
+    }
+
+    fn changed_since(&self, since: u64) -> Vec<T> {
+        let mut changed: Vec<&(u64, T)> = self
+            .entries
+            .values()
+            .filter(|(changed_at, _)| *changed_at > since)
+            .collect();
+        changed.sort_by_key(|(changed_at, _)| *changed_at);
+        changed.into_iter().map(|(_, item)| item.clone()).collect()
+    }
+}
+
+/// Assigns increasing sequence numbers to cache updates
+#[derive(Debug)]
+pub(super) struct ChangeTracker {
+    // Sequence numbers start over after a restart, so they're only comparable within an epoch
+    epoch: u64,
+    sequence: u64,
+    temperature: Tracked<MeasuredTemperature>,
+    ups: Tracked<UninterruptiblePowerSupplyData>,
+    readings: Tracked<Reading>,
+    // Key is `<category>:<hw.id>`
+    removed: HashMap<String, u64>,
+    // Clients that synced before this sequence may have missed dropped removals
+    horizon: u64,
+}
+
+impl Default for ChangeTracker {
+    fn default() -> Self {
+        Self {
+            epoch: Utc::now().timestamp_millis() as u64,
+            sequence: 0,
+            temperature: Tracked::default(),
+            ups: Tracked::default(),
+            readings: Tracked::default(),
+            removed: HashMap::new(),
+            horizon: 0,
+        }
+    }
+}
+
+impl ChangeTracker {
+    fn next_sequence(&mut self) -> u64 {
+        self.sequence += 1;
+        self.sequence
+    }
+
+    fn mark_removed(&mut self, category: &str, ids: Vec<String>, sequence: u64) {
+        for id in ids {
+            self.removed
+                .insert(format!("{}:{}", category, id), sequence);
+        }
+        self.drop_oldest_removed();
+    }
+
+    /// Keep at most `MAX_REMOVED` removed ids, entries removed together are dropped together
+    fn drop_oldest_removed(&mut self) {
+        if self.removed.len() <= MAX_REMOVED {
+            return;
+        }
+        let mut removed_at: Vec<u64> = self.removed.values().copied().collect();
+        removed_at.sort_unstable();
+        let dropped_at = removed_at[removed_at.len() - MAX_REMOVED - 1];
+        self.removed
+            .retain(|_, removed_at| *removed_at > dropped_at);
+        self.horizon = self.horizon.max(dropped_at);
+    }
+
+    fn mark_present(&mut self, category: &str, ids: Vec<String>) {
+        for id in ids {
+            self.removed.remove(&format!("{}:{}", category, id));
+        }
+    }
+
+    pub fn set_sensors(&mut self, sensors: &[MeasuredTemperature]) {
+        let sequence = self.next_sequence();
+        let items = sensors
+            .iter()
+            .map(|sensor| (sensor.meta.hw.id.clone(), sensor.clone()))
+            .collect();
+        let removed = self.temperature.replace(items, sequence);
+        self.mark_removed("temperature", removed, sequence);
+        let present = self.temperature.entries.keys().cloned().collect();
+        self.mark_present("temperature", present);
+    }
+
+    pub fn set_upses(&mut self, upses: &[UninterruptiblePowerSupplyData]) {
+        let sequence = self.next_sequence();
+        let items = upses
+            .iter()
+            .map(|ups| (ups.meta.hw.id.clone(), ups.clone()))
+            .collect();
+        let removed = self.ups.replace(items, sequence);
+        self.mark_removed("ups", removed, sequence);
+        let present = self.ups.entries.keys().cloned().collect();
+        self.mark_present("ups", present);
+    }
+
+    /// Takes all readings after merging the update, so other publishers are compared too
+    pub fn set_readings(&mut self, readings: &[Reading]) {
+        let sequence = self.next_sequence();
+        let items = readings
+            .iter()
+            .map(|reading| (reading.meta.hw.id.clone(), reading.clone()))
+            .collect();
+        let removed = self.readings.replace(items, sequence);
+        self.mark_removed("readings", removed, sequence);
+        let present = self.readings.entries.keys().cloned().collect();
+        self.mark_present("readings", present);
+    }
+
+    /// Without `epoch` (ex. older clients), only `since` ahead of `sequence` is detected
+    pub fn changes_since(&self, since: u64, epoch: Option<u64>) -> Changes {
+        // Client synced with a previous run, is ahead of this one or missed dropped removals,
+        // it has to start over
+        let reset = since > self.sequence
+            || since < self.horizon
+            || epoch.is_some_and(|epoch| epoch != self.epoch);
+        let since = if reset { 0 } else { since };
+        let mut removed: Vec<(&String, &u64)> = self
+            .removed
+            .iter()
+            .filter(|(_, removed_at)| **removed_at > since)
+            .collect();
+        removed.sort_by_key(|(_, removed_at)| **removed_at);
+        Changes {
+            sequence: self.sequence,
+            epoch: self.epoch,
+            reset,
+            temperature: self.temperature.changed_since(since),
+            ups: self.ups.changed_since(since),
+            readings: self.readings.changed_since(since),
+            removed: removed.into_iter().map(|(key, _)| key.clone()).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::types::Example;
+
+    #[test]
+    fn test_unchanged_entries_keep_sequence() {
+        let mut tracker = ChangeTracker::default();
+        tracker.set_sensors(&[MeasuredTemperature::example()]);
+        let first = tracker.changes_since(0, None);
+        assert_eq!(first.temperature.len(), 1);
+        // Same value again doesn't show up as a change
+        tracker.set_sensors(&[MeasuredTemperature::example()]);
+        let second = tracker.changes_since(first.sequence, Some(first.epoch));
+        assert!(second.temperature.is_empty());
+        assert!(second.sequence > first.sequence);
+        // Different value does
+        let mut sensor = MeasuredTemperature::example();
+        sensor.temperature = Some(21.5);
+        tracker.set_sensors(&[sensor.clone()]);
+        let third = tracker.changes_since(second.sequence, Some(second.epoch));
+        assert_eq!(third.temperature, vec![sensor]);
+    }
+
+    #[test]
+    fn test_removed_entries() {
+        let mut tracker = ChangeTracker::default();
+        tracker.set_upses(&[UninterruptiblePowerSupplyData::example()]);
+        let sequence = tracker.changes_since(0, None).sequence;
+        tracker.set_upses(&[]);
+        let changes = tracker.changes_since(sequence, None);
+        assert!(changes.ups.is_empty());
+        assert_eq!(changes.removed, vec![String::from("ups:fake_hw_id")]);
+        // Reappearing entry is no longer reported as removed
+        tracker.set_upses(&[UninterruptiblePowerSupplyData::example()]);
+        let changes = tracker.changes_since(sequence, None);
+        assert_eq!(changes.ups.len(), 1);
+        assert!(changes.removed.is_empty());
+    }
+
+    #[test]
+    fn test_removed_entries_are_capped() {
+        let sensor = |id: usize| {
+            let mut sensor = MeasuredTemperature::example();
+            sensor.meta.hw.id = id.to_string();
+            sensor
+        };
+        let mut tracker = ChangeTracker::default();
+        tracker.set_sensors(&[sensor(0)]);
+        let early = tracker.changes_since(0, None);
+        // Every update removes the previous sensor
+        for id in 1..=MAX_REMOVED + 1 {
+            tracker.set_sensors(&[sensor(id)]);
+        }
+        assert_eq!(tracker.removed.len(), MAX_REMOVED);
+        assert!(!tracker.removed.contains_key("temperature:0"));
+        // Client that could have missed removal of sensor 0 starts over
+        let changes = tracker.changes_since(early.sequence, Some(early.epoch));
+        assert!(changes.reset);
+        assert_eq!(changes.temperature, vec![sensor(MAX_REMOVED + 1)]);
+
+        let changes = tracker.changes_since(changes.sequence, Some(changes.epoch));
+        tracker.set_sensors(&[]);
+        let changes = tracker.changes_since(changes.sequence, Some(changes.epoch));
+        assert!(!changes.reset);
+        assert_eq!(
+            changes.removed,
+            vec![format!("temperature:{}", MAX_REMOVED + 1)]
+        );
+    }
+
+    #[test]
+    fn test_since_ahead_resets() {
+        let mut tracker = ChangeTracker::default();
+        tracker.set_readings(&[Reading::example()]);
+        let changes = tracker.changes_since(100, None);
+        assert!(changes.reset);
+        assert_eq!(changes.readings.len(), 1);
+    }
+
+    #[test]
+    fn test_other_epoch_resets() {
+        let mut tracker = ChangeTracker::default();
+        tracker.set_readings(&[Reading::example()]);
+        tracker.set_readings(&[Reading::example()]);
+        let changes = tracker.changes_since(0, None);
+
+        // Same sequence, but from before a restart
+        let mut restarted = ChangeTracker {
+            epoch: changes.epoch + 1,
+            ..ChangeTracker::default()
+        };
+        restarted.set_readings(&[Reading::example()]);
+        restarted.set_readings(&[Reading::example()]);
+        let changes = restarted.changes_since(changes.sequence, Some(changes.epoch));
+        assert!(changes.reset);
+        assert_eq!(changes.readings.len(), 1);
+
+        let changes = restarted.changes_since(changes.sequence, Some(changes.epoch));
+        assert!(!changes.reset);
+        assert!(changes.readings.is_empty());
+    }
+}
@@ -0,0 +1,169 @@
+// Licensed under the Open Software License version 3.0
+use std::{fmt, net::IpAddr};
+
+pub const FORWARDED_FOR_HEADER: &str = "X-Forwarded-For";
+
+/// Address of the client that sent a request, `None` if the peer is unknown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+impl fmt::Display for ClientIp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(ip) => write!(f, "{}", ip),
+            None => write!(f, "-"),
+        }
+    }
+}
+
+/// Network in CIDR notation, single address without prefix length is also accepted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cidr {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    fn parse(value: &str) -> Option<Self> {
+        let (address, prefix_len) = match value.split_once('/') {
+            Some((address, prefix_len)) => (address, Some(prefix_len)),
+            None => (value, None),
+        };
+        let network: IpAddr = address.trim().parse().ok()?;
+        let max_prefix_len = match network {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len.trim().parse().ok()?,
+            None => max_prefix_len,
+        };
+        if prefix_len > max_prefix_len {
+            return None;
+        }
+        Some(Self {
+            network,
+            prefix_len,
+        })
+    }
+
+    fn contains(&self, ip: &IpAddr) -> bool {
+        // Dual-stack sockets report IPv4 peers as IPv4-mapped IPv6 addresses
+        let ip = match ip {
+            IpAddr::V6(ip) => ip
+                .to_ipv4_mapped()
+                .map(IpAddr::V4)
+                .unwrap_or(IpAddr::V6(*ip)),
+            IpAddr::V4(_) => *ip,
+        };
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix_len as u32);
+                let mask = mask.unwrap_or_default();
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix_len as u32);
+                let mask = mask.unwrap_or_default();
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Proxies allowed to set `X-Forwarded-For`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrustedProxies(Vec<Cidr>);
+
+impl TrustedProxies {
+    pub fn new(networks: &[String]) -> Self {
+        let networks = networks
+            .iter()
+            .filter_map(|network| {
+                let cidr = Cidr::parse(network);
+                if cidr.is_none() {
+                    tracing::warn!("Ignoring invalid trusted proxy {:?}", network);
+                }
+                cidr
+            })
+            .collect();
+        Self(networks)
+    }
+
+    fn is_trusted(&self, ip: &IpAddr) -> bool {
+        self.0.iter().any(|network| network.contains(ip))
+    }
+
+    /// Client address as seen by the first untrusted hop
+    ///
+    /// `X-Forwarded-For` is read right to left, so only entries appended by trusted proxies are used
+    pub fn resolve(&self, peer: IpAddr, forwarded_for: Option<&str>) -> IpAddr {
+        if !self.is_trusted(&peer) {
+            return peer;
+        }
+        let forwarded_for = match forwarded_for {
+            Some(forwarded_for) => forwarded_for,
+            None => return peer,
+        };
+        let mut client = peer;
+        for hop in forwarded_for.rsplit(',') {
+            let hop: IpAddr = match hop.trim().parse() {
+                Ok(hop) => hop,
+                // Garbage can't be trusted, stop at the last valid hop
+                Err(_) => break,
+            };
+            client = hop;
+            if !self.is_trusted(&hop) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxies() -> TrustedProxies {
+        TrustedProxies::new(&[
+            String::from("10.0.0.0/8"),
+            String::from("::1"),
+            String::from("invalid"),
+        ])
+    }
+
+    #[test]
+    fn test_cidr_parse() {
+        assert!(Cidr::parse("192.168.1.0/24").is_some());
+        assert!(Cidr::parse("fd00::/8").is_some());
+        assert!(Cidr::parse("192.168.1.0/33").is_none());
+        assert!(Cidr::parse("localhost").is_none());
+        assert_eq!(proxies().0.len(), 2);
+    }
+
+    #[test]
+    fn test_untrusted_peer_is_client() {
+        let peer: IpAddr = "203.0.113.7".parse().unwrap();
+        let client = proxies().resolve(peer, Some("198.51.100.1"));
+        assert_eq!(client, peer);
+    }
+
+    #[test]
+    fn test_trusted_peer_uses_forwarded_for() {
+        let peer: IpAddr = "10.0.0.2".parse().unwrap();
+        // Spoofed leftmost entry is ignored
+        let client = proxies().resolve(peer, Some("1.2.3.4, 198.51.100.1, 10.0.0.3"));
+        assert_eq!(client, "198.51.100.1".parse::<IpAddr>().unwrap());
+        // Without the header proxy itself is the client
+        assert_eq!(proxies().resolve(peer, None), peer);
+    }
+
+    #[test]
+    fn test_ipv4_mapped_peer() {
+        let peer: IpAddr = "::ffff:10.0.0.2".parse().unwrap();
+        let client = proxies().resolve(peer, Some("198.51.100.1"));
+        assert_eq!(client, "198.51.100.1".parse::<IpAddr>().unwrap());
+    }
+}
@@ -1,11 +1,46 @@
 // Licensed under the Open Software License version 3.0
 use crate::config::types::Example;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Entries measured longer ago than this are stale, by category
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct MaxAge {
+    pub temperature: Option<Duration>,
+    pub ups: Option<Duration>,
+    pub readings: Option<Duration>,
+}
+
+/// Samples kept per temperature sensor and UPS for `/history` routes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct HistoryConfig {
+    pub max_samples: Option<usize>,
+    // Older samples are dropped even if there are fewer than max_samples
+    pub max_age: Option<Duration>,
+}
+
+impl HistoryConfig {
+    pub fn get_max_samples(&self) -> usize {
+        self.max_samples.unwrap_or(360)
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PassiveEndpointConfig {
     enabled: Option<bool>,
     port: Option<u16>,
+    control_token: Option<String>,
+    pretty_json: Option<bool>,
+    redacted_variables: Option<Vec<String>>,
+    trusted_proxies: Option<Vec<String>>,
+    // PEM encoded certificate chain and private key, both are required to serve HTTPS
+    tls_cert_path: Option<String>,
+    tls_key_path: Option<String>,
+    max_age: Option<MaxAge>,
+    // Remove stale entries from responses instead of flagging them
+    drop_stale: Option<bool>,
+    // History is kept only if set
+    history: Option<HistoryConfig>,
 }
 
 impl Default for PassiveEndpointConfig {
@@ -13,6 +48,15 @@ impl Default for PassiveEndpointConfig {
         Self {
             enabled: Some(false),
             port: Some(63623),
+            control_token: None,
+            pretty_json: Some(false),
+            redacted_variables: None,
+            trusted_proxies: None,
+            tls_cert_path: None,
+            tls_key_path: None,
+            max_age: None,
+            drop_stale: None,
+            history: None,
         }
     }
 }
@@ -22,6 +66,18 @@ impl Example for PassiveEndpointConfig {
         Self {
             enabled: Some(true),
             port: Some(63623),
+            control_token: None,
+            pretty_json: Some(false),
+            redacted_variables: None,
+            trusted_proxies: None,
+            tls_cert_path: None,
+            tls_key_path: None,
+            max_age: None,
+            drop_stale: None,
+            history: Some(HistoryConfig {
+                max_samples: Some(360),
+                max_age: Some(Duration::from_secs(60 * 60)),
+            }),
         }
     }
 }
@@ -34,4 +90,40 @@ impl PassiveEndpointConfig {
     pub fn get_port(&self) -> u16 {
         self.port.unwrap_or_default()
     }
+
+    pub fn get_control_token(&self) -> Option<String> {
+        self.control_token.clone()
+    }
+
+    pub fn get_pretty_json(&self) -> bool {
+        self.pretty_json.unwrap_or_default()
+    }
+
+    pub fn get_redacted_variables(&self) -> Vec<String> {
+        self.redacted_variables.clone().unwrap_or_default()
+    }
+
+    pub fn get_trusted_proxies(&self) -> Vec<String> {
+        self.trusted_proxies.clone().unwrap_or_default()
+    }
+
+    pub fn get_tls_cert_path(&self) -> Option<String> {
+        self.tls_cert_path.clone()
+    }
+
+    pub fn get_tls_key_path(&self) -> Option<String> {
+        self.tls_key_path.clone()
+    }
+
+    pub fn get_max_age(&self) -> MaxAge {
+        self.max_age.clone().unwrap_or_default()
+    }
+
+    pub fn get_drop_stale(&self) -> bool {
+        self.drop_stale.unwrap_or_default()
+    }
+
+    pub fn get_history(&self) -> Option<HistoryConfig> {
+        self.history.clone()
+    }
 }
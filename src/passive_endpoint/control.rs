@@ -0,0 +1,194 @@
+// Licensed under the Open Software License version 3.0
+use super::{receiver::ApiResponse, response::ApiJson};
+use crate::wake_on_lan::{
+    config::{WakeOnLanConfig, WakeOnLanTarget},
+    packet::WakeOutcome,
+};
+use rocket::{
+    http::Status,
+    post,
+    request::{FromRequest, Outcome},
+    routes, Build, Request, Rocket, State,
+};
+
+/// Token required to use `/control` routes
+pub struct ControlToken(pub String);
+
+/// Request guard that passes only with a valid `Authorization: Bearer` header
+pub struct Authorized;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Authorized {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let expected = match request.rocket().state::<ControlToken>() {
+            Some(token) => format!("Bearer {}", token.0),
+            None => return Outcome::Failure((Status::Unauthorized, ())),
+        };
+        match request.headers().get_one("Authorization") {
+            Some(header) if header == expected => Outcome::Success(Authorized),
+            _ => Outcome::Failure((Status::Unauthorized, ())),
+        }
+    }
+}
+
+async fn wake_targets(
+    config: &WakeOnLanConfig,
+    targets: Vec<WakeOnLanTarget>,
+) -> (Status, ApiJson<ApiResponse<Vec<String>>>) {
+    match crate::wake_on_lan::packet::wake_targets(config, targets).await {
+        WakeOutcome::NoTargets => (Status::NotFound, ApiJson(ApiResponse::new(None))),
+        WakeOutcome::Failed => (
+            Status::InternalServerError,
+            ApiJson(ApiResponse::error("failed to send magic packet")),
+        ),
+        WakeOutcome::Woken(woken) => (Status::Ok, ApiJson(ApiResponse::new(Some(woken)))),
+    }
+}
+
+#[post("/wol")]
+async fn wake_all_route(
+    _authorized: Authorized,
+    config: &State<WakeOnLanConfig>,
+) -> (Status, ApiJson<ApiResponse<Vec<String>>>) {
+    wake_targets(config, config.get_targets()).await
+}
+
+#[post("/wol/<name>")]
+async fn wake_by_name_route(
+    _authorized: Authorized,
+    config: &State<WakeOnLanConfig>,
+    name: String,
+) -> (Status, ApiJson<ApiResponse<Vec<String>>>) {
+    let targets = config
+        .get_targets()
+        .into_iter()
+        .filter(|target| target.name == name)
+        .collect();
+    wake_targets(config, targets).await
+}
+
+/// Mount `/control` routes if a token is configured and not in read-only mode
+pub fn mount_control(
+    rocket: Rocket<Build>,
+    control_token: Option<String>,
+    wake_on_lan: WakeOnLanConfig,
+    read_only: bool,
+) -> Rocket<Build> {
+    let control_token = match control_token {
+        Some(control_token) if !control_token.is_empty() => control_token,
+        _ => {
+            tracing::trace!("Control routes are disabled because control_token is not set");
+            return rocket;
+        }
+    };
+    // Token is still required by `/status/internal`
+    let rocket = rocket.manage(ControlToken(control_token));
+    if read_only {
+        tracing::info!("Control routes are disabled in read-only mode");
+        return rocket;
+    }
+    if !wake_on_lan.is_enabled() {
+        return rocket;
+    }
+    rocket
+        .manage(wake_on_lan)
+        .mount("/control", routes![wake_all_route, wake_by_name_route])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::types::Example;
+    use rocket::{http::Header, local::asynchronous::Client};
+
+    async fn client_with_control() -> Client {
+        // Send magic packets to localhost instead of broadcasting them
+        let wake_on_lan: WakeOnLanConfig = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "broadcast_address": "127.0.0.1:9",
+            "targets": WakeOnLanConfig::example().get_targets()
+        }))
+        .unwrap();
+        let rocket = mount_control(
+            rocket::build(),
+            Some(String::from("secret")),
+            wake_on_lan,
+            false,
+        );
+        Client::tracked(rocket).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_wol_without_token() {
+        let client = client_with_control().await;
+        let response = client.post("/control/wol").dispatch().await;
+        assert_eq!(response.status(), Status::Unauthorized);
+    }
+
+    #[tokio::test]
+    async fn test_wol_with_invalid_token() {
+        let client = client_with_control().await;
+        let response = client
+            .post("/control/wol")
+            .header(Header::new("Authorization", "Bearer wrong"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Unauthorized);
+    }
+
+    #[tokio::test]
+    async fn test_wol_by_name() {
+        let client = client_with_control().await;
+        let response = client
+            .post("/control/wol/server1")
+            .header(Header::new("Authorization", "Bearer secret"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = response.into_string().await.unwrap();
+        let response: ApiResponse<Vec<String>> = serde_json::from_str(&response).unwrap();
+        assert_eq!(response.data.unwrap(), vec![String::from("server1")]);
+    }
+
+    #[tokio::test]
+    async fn test_wol_unknown_name() {
+        let client = client_with_control().await;
+        let response = client
+            .post("/control/wol/unknown")
+            .header(Header::new("Authorization", "Bearer secret"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_control_disabled_without_token() {
+        let rocket = mount_control(rocket::build(), None, WakeOnLanConfig::example(), false);
+        let client = Client::tracked(rocket).await.unwrap();
+        let response = client
+            .post("/control/wol")
+            .header(Header::new("Authorization", "Bearer "))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_control_disabled_in_read_only_mode() {
+        let rocket = mount_control(
+            rocket::build(),
+            Some(String::from("secret")),
+            WakeOnLanConfig::example(),
+            true,
+        );
+        let client = Client::tracked(rocket).await.unwrap();
+        let response = client
+            .post("/control/wol")
+            .header(Header::new("Authorization", "Bearer secret"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+    }
+}
@@ -0,0 +1,131 @@
+// Licensed under the Open Software License version 3.0
+//! Entries that weren't measured within `max_age` of their category, judged by `age_secs`
+use super::{
+    age::Cached,
+    changes::Changes,
+    config::{MaxAge, PassiveEndpointConfig},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Category {
+    Temperature,
+    Ups,
+    Readings,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(super) struct Expiry {
+    max_age: MaxAge,
+    drop_stale: bool,
+}
+
+impl Expiry {
+    pub fn new(config: &PassiveEndpointConfig) -> Self {
+        Self {
+            max_age: config.get_max_age(),
+            drop_stale: config.get_drop_stale(),
+        }
+    }
+
+    /// `None` if `max_age` isn't set for `category`, entries without `age_secs` are never stale
+    fn is_stale<T: Cached>(&self, category: Category, item: &T) -> Option<bool> {
+        let max_age = match category {
+            Category::Temperature => self.max_age.temperature,
+            Category::Ups => self.max_age.ups,
+            Category::Readings => self.max_age.readings,
+        }?;
+        Some(
+            item.meta()
+                .age_secs
+                .map_or(false, |age_secs| age_secs > max_age.as_secs()),
+        )
+    }
+
+    /// Flag or drop stale entries, `age_secs` has to be set before
+    pub fn apply<T: Cached>(&self, category: Category, items: Vec<T>) -> Vec<T> {
+        items
+            .into_iter()
+            .filter_map(|mut item| {
+                let stale = self.is_stale(category, &item);
+                if self.drop_stale && stale == Some(true) {
+                    return None;
+                }
+                item.meta_mut().stale = stale;
+                Some(item)
+            })
+            .collect()
+    }
+
+    pub fn apply_one<T: Cached>(&self, category: Category, item: Option<T>) -> Option<T> {
+        self.apply(category, item.into_iter().collect()).pop()
+    }
+
+    /// Only flag entries, so incremental sync never skips an update
+    pub fn flag_changes(&self, mut changes: Changes) -> Changes {
+        let expiry = Self {
+            drop_stale: false,
+            ..self.clone()
+        };
+        changes.temperature = expiry.apply(Category::Temperature, changes.temperature);
+        changes.ups = expiry.apply(Category::Ups, changes.ups);
+        changes.readings = expiry.apply(Category::Readings, changes.readings);
+        changes
+    }
+
+    /// Cache has data of `category`, but all of it is stale
+    pub fn is_expired<T: Cached>(&self, category: Category, items: &[T]) -> bool {
+        !items.is_empty()
+            && items
+                .iter()
+                .all(|item| self.is_stale(category, item) == Some(true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::types::Example, one_wire::sender::MeasuredTemperature};
+    use std::time::Duration;
+
+    fn sensor(age_secs: Option<u64>) -> MeasuredTemperature {
+        let mut sensor = MeasuredTemperature::example();
+        sensor.meta.age_secs = age_secs;
+        sensor
+    }
+
+    fn expiry(drop_stale: bool) -> Expiry {
+        Expiry {
+            max_age: MaxAge {
+                temperature: Some(Duration::from_secs(60)),
+                ups: None,
+                readings: None,
+            },
+            drop_stale,
+        }
+    }
+
+    #[test]
+    fn test_flag_stale_entries() {
+        let sensors = vec![sensor(Some(10)), sensor(Some(61)), sensor(None)];
+        let sensors = expiry(false).apply(Category::Temperature, sensors);
+        let stale: Vec<_> = sensors.iter().map(|sensor| sensor.meta.stale).collect();
+        assert_eq!(stale, vec![Some(false), Some(true), Some(false)]);
+        // Not flagged without max_age
+        let readings = expiry(false).apply(Category::Ups, vec![sensor(Some(61))]);
+        assert_eq!(readings[0].meta.stale, None);
+    }
+
+    #[test]
+    fn test_drop_stale_entries() {
+        let expiry = expiry(true);
+        let sensors = vec![sensor(Some(10)), sensor(Some(61))];
+        assert!(!expiry.is_expired(Category::Temperature, &sensors));
+        assert_eq!(expiry.apply(Category::Temperature, sensors).len(), 1);
+        let sensors = vec![sensor(Some(61))];
+        assert!(expiry.is_expired(Category::Temperature, &sensors));
+        assert!(expiry
+            .apply_one(Category::Temperature, sensors.into_iter().next())
+            .is_none());
+        assert!(!expiry.is_expired::<MeasuredTemperature>(Category::Temperature, &[]));
+    }
+}
@@ -0,0 +1,54 @@
+// Licensed under the Open Software License version 3.0
+use super::receiver::CachedData;
+use crate::export::batch::{temperatures_to_batch, upses_to_batch, write_batch, ExportFormat};
+use rocket::{get, http::ContentType, http::Status, routes, Build, Rocket, State};
+use std::sync::Arc;
+
+#[get("/<category>/<format>")]
+async fn export_route(
+    cache: &State<Arc<CachedData>>,
+    category: &str,
+    format: &str,
+) -> Result<(ContentType, Vec<u8>), Status> {
+    let format: ExportFormat = format.parse().map_err(|_| Status::NotFound)?;
+    let batch = match category {
+        "temperature" => temperatures_to_batch(&cache.get_temperature_sensors().await),
+        "ups" => upses_to_batch(&cache.get_upses().await),
+        _ => return Err(Status::NotFound),
+    };
+    let body = batch
+        .and_then(|batch| write_batch(&batch, format))
+        .map_err(|error| {
+            tracing::error!("Failed to export {}: {}", category, error);
+            Status::InternalServerError
+        })?;
+    let (top, sub) = format.get_media_type();
+    Ok((ContentType::new(top, sub), body))
+}
+
+/// Mount `/export/<temperature|ups>/<parquet|arrow>`
+pub fn mount_export(rocket: Rocket<Build>) -> Rocket<Build> {
+    rocket.mount("/export", routes![export_route])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::local::asynchronous::Client;
+
+    #[tokio::test]
+    async fn test_export_parquet() {
+        let rocket = rocket::build()
+            .manage(Arc::new(CachedData::default()))
+            .mount("/export", routes![export_route]);
+        let client = Client::tracked(rocket).await.unwrap();
+
+        let response = client.get("/export/temperature/parquet").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let body = response.into_bytes().await.unwrap();
+        assert_eq!(&body[..4], b"PAR1");
+
+        let response = client.get("/export/ups/csv").dispatch().await;
+        assert_eq!(response.status(), Status::NotFound);
+    }
+}
@@ -0,0 +1,392 @@
+// Licensed under the Open Software License version 3.0
+use super::{age::WithAge, receiver::ApiResponse, response::ApiJson};
+use crate::{
+    active_sender::headers::{parse_part, PART_HEADER, TOTAL_PARTS_HEADER},
+    fleet::{
+        auth::{check_node, NodeTokens},
+        config::FleetConfig,
+        encoding::decode_body,
+        registry::{
+            current, FleetHealth, FleetRegistry, FromNode, NodeSnapshot, NodeStatus, Registration,
+            SharedRegistry,
+        },
+    },
+    hardware::reading::Reading,
+    nut::sender::UninterruptiblePowerSupplyData,
+    one_wire::sender::MeasuredTemperature,
+};
+use chrono::Utc;
+use rocket::{
+    data::{Data, Limits},
+    get,
+    http::Status,
+    post,
+    request::{FromRequest, Outcome},
+    routes,
+    serde::json::Json,
+    Build, Request, Rocket, State,
+};
+use tokio::sync::RwLock;
+
+/// Request guard that passes only with `Authorization: Bearer` header of any fleet token,
+/// holds instance id the token belongs to
+struct NodeAuthorized(String);
+
+impl NodeAuthorized {
+    fn check(&self, instance_id: &str) -> Result<(), String> {
+        check_node(&self.0, instance_id)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for NodeAuthorized {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let tokens = match request.rocket().state::<NodeTokens>() {
+            Some(tokens) => tokens,
+            None => return Outcome::Failure((Status::Unauthorized, ())),
+        };
+        match tokens.node(request.headers().get_one("Authorization")) {
+            Some(instance_id) => Outcome::Success(NodeAuthorized(instance_id)),
+            None => Outcome::Failure((Status::Unauthorized, ())),
+        }
+    }
+}
+
+/// Part number and total number of parts, `None` if the snapshot wasn't split
+struct Part(Option<(usize, usize)>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Part {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let headers = request.headers();
+        Outcome::Success(Part(parse_part(
+            headers.get_one(PART_HEADER),
+            headers.get_one(TOTAL_PARTS_HEADER),
+        )))
+    }
+}
+
+/// `Content-Encoding` of the body, set by nodes with `compression` on their endpoint
+struct ContentEncoding(Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ContentEncoding {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let encoding = request
+            .headers()
+            .get_one("Content-Encoding")
+            .map(String::from);
+        Outcome::Success(ContentEncoding(encoding))
+    }
+}
+
+/// Snapshot of a possibly compressed body, decoded within the JSON limit of Rocket
+async fn read_snapshot(
+    body: Data<'_>,
+    encoding: ContentEncoding,
+    limits: &Limits,
+) -> Result<NodeSnapshot, (Status, String)> {
+    let limit = limits.get("json").unwrap_or(Limits::JSON);
+    let body = body
+        .open(limit)
+        .into_bytes()
+        .await
+        .map_err(|error| (Status::BadRequest, error.to_string()))?;
+    if !body.is_complete() {
+        return Err((Status::PayloadTooLarge, String::from("body is too large")));
+    }
+    let body = decode_body(body.into_inner(), encoding.0.as_deref(), limit.as_u64())
+        .map_err(|error| (Status::new(error.status()), error.to_string()))?;
+    serde_json::from_slice(&body).map_err(|error| (Status::BadRequest, error.to_string()))
+}
+
+fn found<T>(data: Option<T>) -> (Status, ApiJson<ApiResponse<T>>) {
+    let data = ApiResponse::new(data);
+    if !data.success {
+        return (Status::NotFound, ApiJson(data));
+    }
+    (Status::Ok, ApiJson(data))
+}
+
+#[post("/register", data = "<registration>")]
+async fn register_route(
+    authorized: NodeAuthorized,
+    registry: &State<SharedRegistry>,
+    registration: Json<Registration>,
+) -> (Status, ApiJson<ApiResponse<NodeStatus>>) {
+    if let Err(error) = authorized.check(&registration.instance_id) {
+        return (Status::Forbidden, ApiJson(ApiResponse::error(&error)));
+    }
+    let status = current(registry)
+        .await
+        .register(registration.into_inner(), Utc::now());
+    (Status::Ok, ApiJson(ApiResponse::new(Some(status))))
+}
+
+#[post("/push", data = "<body>")]
+async fn push_route(
+    authorized: NodeAuthorized,
+    registry: &State<SharedRegistry>,
+    part: Part,
+    encoding: ContentEncoding,
+    limits: &Limits,
+    body: Data<'_>,
+) -> (Status, ApiJson<ApiResponse<NodeStatus>>) {
+    let snapshot = match read_snapshot(body, encoding, limits).await {
+        Ok(snapshot) => snapshot,
+        Err((status, error)) => return (status, ApiJson(ApiResponse::error(&error))),
+    };
+    if let Err(error) = authorized.check(&snapshot.instance_id) {
+        return (Status::Forbidden, ApiJson(ApiResponse::error(&error)));
+    }
+    let result = current(registry).await.push(snapshot, part.0, Utc::now());
+    match result {
+        Ok(status) => (Status::Ok, ApiJson(ApiResponse::new(Some(status)))),
+        Err(error) => (Status::BadRequest, ApiJson(ApiResponse::error(&error))),
+    }
+}
+
+#[get("/nodes")]
+async fn get_nodes_route(
+    registry: &State<SharedRegistry>,
+) -> ApiJson<ApiResponse<Vec<NodeStatus>>> {
+    let statuses = current(registry).await.statuses(Utc::now());
+    ApiJson(ApiResponse::new(Some(statuses)))
+}
+
+#[get("/health")]
+async fn get_health_route(registry: &State<SharedRegistry>) -> ApiJson<ApiResponse<FleetHealth>> {
+    let health = current(registry).await.health(Utc::now());
+    ApiJson(ApiResponse::new(Some(health)))
+}
+
+#[get("/nodes/<id>")]
+async fn get_node_route(
+    registry: &State<SharedRegistry>,
+    id: String,
+) -> (Status, ApiJson<ApiResponse<NodeStatus>>) {
+    found(current(registry).await.status(&id, Utc::now()))
+}
+
+#[get("/nodes/<id>/temperature")]
+async fn get_node_temperature_route(
+    registry: &State<SharedRegistry>,
+    id: String,
+) -> (Status, ApiJson<ApiResponse<Vec<MeasuredTemperature>>>) {
+    let snapshot = current(registry).await.snapshot(&id);
+    found(snapshot.map(|snapshot| snapshot.sensors.with_age(Utc::now())))
+}
+
+#[get("/nodes/<id>/ups")]
+async fn get_node_ups_route(
+    registry: &State<SharedRegistry>,
+    id: String,
+) -> (
+    Status,
+    ApiJson<ApiResponse<Vec<UninterruptiblePowerSupplyData>>>,
+) {
+    let snapshot = current(registry).await.snapshot(&id);
+    found(snapshot.map(|snapshot| snapshot.upses.with_age(Utc::now())))
+}
+
+#[get("/nodes/<id>/readings")]
+async fn get_node_readings_route(
+    registry: &State<SharedRegistry>,
+    id: String,
+) -> (Status, ApiJson<ApiResponse<Vec<Reading>>>) {
+    let snapshot = current(registry).await.snapshot(&id);
+    found(snapshot.map(|snapshot| snapshot.readings.with_age(Utc::now())))
+}
+
+#[get("/temperature")]
+async fn get_temperature_route(
+    registry: &State<SharedRegistry>,
+) -> ApiJson<ApiResponse<Vec<FromNode<MeasuredTemperature>>>> {
+    let sensors = current(registry)
+        .await
+        .combined(|snapshot| &snapshot.sensors);
+    ApiJson(ApiResponse::new(Some(sensors.with_age(Utc::now()))))
+}
+
+#[get("/ups")]
+async fn get_ups_route(
+    registry: &State<SharedRegistry>,
+) -> ApiJson<ApiResponse<Vec<FromNode<UninterruptiblePowerSupplyData>>>> {
+    let upses = current(registry).await.combined(|snapshot| &snapshot.upses);
+    ApiJson(ApiResponse::new(Some(upses.with_age(Utc::now()))))
+}
+
+#[get("/readings")]
+async fn get_readings_route(
+    registry: &State<SharedRegistry>,
+) -> ApiJson<ApiResponse<Vec<FromNode<Reading>>>> {
+    let readings = current(registry)
+        .await
+        .combined(|snapshot| &snapshot.readings);
+    ApiJson(ApiResponse::new(Some(readings.with_age(Utc::now()))))
+}
+
+/// Mount `/fleet` routes if this instance is a fleet head
+pub fn mount_fleet(rocket: Rocket<Build>, config: FleetConfig) -> Rocket<Build> {
+    if !config.is_enabled() {
+        return rocket;
+    }
+    let tokens = config.get_tokens();
+    if tokens.is_empty() {
+        tracing::warn!("Fleet nodes can't push data because no tokens are configured");
+    }
+    rocket
+        .manage(NodeTokens::new(tokens))
+        .manage(RwLock::new(FleetRegistry::new(
+            config.get_node_timeout(),
+            config.get_forget_after(),
+        )))
+        .mount(
+            "/fleet",
+            routes![
+                register_route,
+                push_route,
+                get_nodes_route,
+                get_health_route,
+                get_node_route,
+                get_node_temperature_route,
+                get_node_ups_route,
+                get_node_readings_route,
+                get_temperature_route,
+                get_ups_route,
+                get_readings_route
+            ],
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::types::Example;
+    use rocket::{
+        http::{ContentType, Header},
+        local::asynchronous::Client,
+    };
+
+    async fn client() -> Client {
+        let config: FleetConfig = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "tokens": {"node1": "secret", "node2": "other"},
+        }))
+        .unwrap();
+        Client::tracked(mount_fleet(rocket::build(), config))
+            .await
+            .unwrap()
+    }
+
+    fn snapshot(instance_id: &str) -> String {
+        serde_json::json!({
+            "sensors": [MeasuredTemperature::example()],
+            "upses": [],
+            "readings": [],
+            "instance_id": instance_id,
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_push_requires_token() {
+        let client = client().await;
+        let response = client
+            .post("/fleet/push")
+            .header(ContentType::JSON)
+            .header(Header::new("Authorization", "Bearer wrong"))
+            .body(snapshot("node1"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Unauthorized);
+    }
+
+    #[tokio::test]
+    async fn test_push_compressed() {
+        use std::io::Write;
+        let client = client().await;
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(snapshot("node1").as_bytes()).unwrap();
+        let response = client
+            .post("/fleet/push")
+            .header(ContentType::JSON)
+            .header(Header::new("Content-Encoding", "gzip"))
+            .header(Header::new("Authorization", "Bearer secret"))
+            .body(encoder.finish().unwrap())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = client.get("/fleet/nodes/node1").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client
+            .post("/fleet/push")
+            .header(ContentType::JSON)
+            .header(Header::new("Content-Encoding", "br"))
+            .header(Header::new("Authorization", "Bearer secret"))
+            .body(snapshot("node1"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::UnsupportedMediaType);
+    }
+
+    #[tokio::test]
+    async fn test_push_as_other_node() {
+        let client = client().await;
+        let response = client
+            .post("/fleet/push")
+            .header(ContentType::JSON)
+            .header(Header::new("Authorization", "Bearer other"))
+            .body(snapshot("node1"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Forbidden);
+        let response = client.get("/fleet/nodes/node1").dispatch().await;
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_push_and_query() {
+        let client = client().await;
+        let response = client
+            .post("/fleet/push")
+            .header(ContentType::JSON)
+            .header(Header::new("Authorization", "Bearer secret"))
+            .body(snapshot("node1"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client.get("/fleet/nodes").dispatch().await;
+        let response = response.into_string().await.unwrap();
+        let response: ApiResponse<Vec<NodeStatus>> = serde_json::from_str(&response).unwrap();
+        let nodes = response.data.unwrap();
+        assert_eq!(nodes[0].instance_id, "node1");
+        assert!(nodes[0].reachable);
+        assert_eq!(nodes[0].sensors, 1);
+
+        let response = client.get("/fleet/health").dispatch().await;
+        let response = response.into_string().await.unwrap();
+        let response: ApiResponse<FleetHealth> = serde_json::from_str(&response).unwrap();
+        let health = response.data.unwrap();
+        assert_eq!((health.nodes, health.reachable, health.sensors), (1, 1, 1));
+
+        let response = client.get("/fleet/temperature").dispatch().await;
+        let response = response.into_string().await.unwrap();
+        let response: ApiResponse<Vec<FromNode<MeasuredTemperature>>> =
+            serde_json::from_str(&response).unwrap();
+        assert_eq!(response.data.unwrap()[0].instance_id, "node1");
+
+        let response = client.get("/fleet/nodes/node1/ups").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = client.get("/fleet/nodes/node2/ups").dispatch().await;
+        assert_eq!(response.status(), Status::NotFound);
+    }
+}
@@ -0,0 +1,185 @@
+// Licensed under the Open Software License version 3.0
+//! Recent samples of every temperature sensor and UPS, bounded by count and age
+use super::config::HistoryConfig;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemperaturePoint {
+    pub temperature: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpsPoint {
+    pub variables: HashMap<String, String>,
+}
+
+/// Item of `/history` responses, oldest first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistorySample<T> {
+    // RFC 3339, measured_at of the sample or time it was cached
+    pub timestamp: String,
+    #[serde(flatten)]
+    pub point: T,
+}
+
+/// Ring buffer per hw.id, disabled (always empty) by default
+#[derive(Debug, Clone)]
+pub(super) struct History<T> {
+    config: Option<HistoryConfig>,
+    series: HashMap<String, VecDeque<(DateTime<Utc>, T)>>,
+}
+
+impl<T> Default for History<T> {
+    fn default() -> Self {
+        Self {
+            config: None,
+            series: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Clone> History<T> {
+    pub fn new(config: Option<HistoryConfig>) -> Self {
+        Self {
+            config,
+            series: HashMap::new(),
+        }
+    }
+
+    /// Append the latest update, series of ids missing from it are forgotten
+    pub fn record(&mut self, points: Vec<(String, DateTime<Utc>, T)>, now: DateTime<Utc>) {
+        let config = match &self.config {
+            Some(config) => config,
+            None => return,
+        };
+        let max_samples = config.get_max_samples();
+        let oldest = config
+            .max_age
+            .and_then(|max_age| chrono::Duration::from_std(max_age).ok())
+            .map(|max_age| now - max_age);
+        let is_too_old =
+            |timestamp: &DateTime<Utc>| oldest.map_or(false, |oldest| *timestamp < oldest);
+        let mut series = std::mem::take(&mut self.series);
+        for (id, timestamp, point) in points {
+            let mut samples = series.remove(&id).unwrap_or_default();
+            // Sources republish unchanged measurements, ex. between 1-Wire conversions
+            if samples.back().map(|(last, _)| *last) != Some(timestamp) {
+                samples.push_back((timestamp, point));
+            }
+            while samples.len() > max_samples
+                || samples
+                    .front()
+                    .map_or(false, |(first, _)| is_too_old(first))
+            {
+                samples.pop_front();
+            }
+            if !samples.is_empty() {
+                self.series.insert(id, samples);
+            }
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<Vec<HistorySample<T>>> {
+        let samples = self.series.get(id)?;
+        Some(
+            samples
+                .iter()
+                .map(|(timestamp, point)| HistorySample {
+                    timestamp: timestamp.to_rfc3339_opts(SecondsFormat::Millis, true),
+                    point: point.clone(),
+                })
+                .collect(),
+        )
+    }
+}
+
+/// `measured_at` of a sample, `now` if it's missing or invalid
+pub(super) fn sample_time(measured_at: &Option<String>, now: DateTime<Utc>) -> DateTime<Utc> {
+    measured_at
+        .as_deref()
+        .and_then(|measured_at| DateTime::parse_from_rfc3339(measured_at).ok())
+        .map_or(now, |measured_at| measured_at.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2023-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+            + chrono::Duration::seconds(seconds)
+    }
+
+    fn point(temperature: f64) -> TemperaturePoint {
+        TemperaturePoint {
+            temperature: Some(temperature),
+        }
+    }
+
+    fn temperatures(history: &History<TemperaturePoint>, id: &str) -> Vec<f64> {
+        history
+            .get(id)
+            .unwrap()
+            .into_iter()
+            .filter_map(|sample| sample.point.temperature)
+            .collect()
+    }
+
+    #[test]
+    fn test_max_samples() {
+        let mut history = History::new(Some(HistoryConfig {
+            max_samples: Some(3),
+            max_age: None,
+        }));
+        for second in 0..5 {
+            let points = vec![(String::from("a"), at(second), point(second as f64))];
+            history.record(points, at(second));
+        }
+        assert_eq!(temperatures(&history, "a"), vec![2.0, 3.0, 4.0]);
+        assert_eq!(
+            history.get("a").unwrap()[0].timestamp,
+            "2023-01-01T00:00:02.000Z"
+        );
+        // Unchanged measurement isn't a new sample
+        history.record(vec![(String::from("a"), at(4), point(4.0))], at(5));
+        assert_eq!(temperatures(&history, "a"), vec![2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn test_max_age_and_removed_ids() {
+        let mut history = History::new(Some(HistoryConfig {
+            max_samples: None,
+            max_age: Some(Duration::from_secs(60)),
+        }));
+        history.record(vec![(String::from("a"), at(0), point(1.0))], at(0));
+        history.record(
+            vec![
+                (String::from("a"), at(30), point(2.0)),
+                (String::from("b"), at(30), point(3.0)),
+            ],
+            at(30),
+        );
+        history.record(vec![(String::from("a"), at(90), point(4.0))], at(90));
+        assert_eq!(temperatures(&history, "a"), vec![2.0, 4.0]);
+        assert!(history.get("b").is_none());
+    }
+
+    #[test]
+    fn test_disabled() {
+        let mut history = History::default();
+        history.record(vec![(String::from("a"), at(0), point(1.0))], at(0));
+        assert!(history.get("a").is_none());
+    }
+
+    #[test]
+    fn test_sample_time() {
+        let measured_at = Some(String::from("2023-01-01T00:00:10+00:00"));
+        assert_eq!(sample_time(&measured_at, at(99)), at(10));
+        assert_eq!(sample_time(&None, at(99)), at(99));
+    }
+}
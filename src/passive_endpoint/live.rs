@@ -0,0 +1,81 @@
+// Licensed under the Open Software License version 3.0
+//! Updates pushed to `/ws` clients as soon as they reach the cache
+use crate::{nut::sender::UninterruptiblePowerSupplyData, one_wire::sender::MeasuredTemperature};
+use serde::Serialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+// Slow clients skip events instead of holding them in memory
+const CAPACITY: usize = 16;
+
+/// `{"type": "temperature", "data": [...]}`, data is the same as in `/temperature` and `/ups`
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "lowercase")]
+pub(super) enum LiveEvent {
+    Temperature(Vec<MeasuredTemperature>),
+    Ups(Vec<UninterruptiblePowerSupplyData>),
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct LiveEvents(broadcast::Sender<LiveEvent>);
+
+impl Default for LiveEvents {
+    fn default() -> Self {
+        Self(broadcast::channel(CAPACITY).0)
+    }
+}
+
+impl LiveEvents {
+    pub fn publish(&self, event: LiveEvent) {
+        // No connected clients isn't an error
+        let _ = self.0.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<LiveEvent> {
+        self.0.subscribe()
+    }
+}
+
+/// Next event as JSON text message, `None` once the cache is gone
+pub(super) async fn next_message(events: &mut broadcast::Receiver<LiveEvent>) -> Option<String> {
+    loop {
+        match events.recv().await {
+            Ok(event) => match serde_json::to_string(&event) {
+                Ok(message) => return Some(message),
+                Err(error) => tracing::error!("Failed to serialize live event: {}", error),
+            },
+            Err(RecvError::Lagged(skipped)) => {
+                tracing::debug!("WebSocket client skipped {} events", skipped);
+            }
+            Err(RecvError::Closed) => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::fixtures;
+
+    #[tokio::test]
+    async fn test_next_message() {
+        let events = LiveEvents::default();
+        let mut rx = events.subscribe();
+        events.publish(LiveEvent::Temperature(vec![fixtures::sensor()]));
+        let message: serde_json::Value =
+            serde_json::from_str(&next_message(&mut rx).await.unwrap()).unwrap();
+        assert_eq!(message["type"], "temperature");
+        assert_eq!(message["data"][0]["temperature"], 21.5);
+        // Lagging clients get the oldest event that's still buffered
+        for _ in 0..CAPACITY + 1 {
+            events.publish(LiveEvent::Ups(vec![fixtures::ups()]));
+        }
+        let message: serde_json::Value =
+            serde_json::from_str(&next_message(&mut rx).await.unwrap()).unwrap();
+        assert_eq!(message["type"], "ups");
+        drop(events);
+        for _ in 0..CAPACITY - 1 {
+            assert!(next_message(&mut rx).await.is_some());
+        }
+        assert_eq!(next_message(&mut rx).await, None);
+    }
+}
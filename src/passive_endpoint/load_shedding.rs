@@ -0,0 +1,62 @@
+// Licensed under the Open Software License version 3.0
+use super::{
+    receiver::{ApiResponse, CachedData},
+    response::ApiJson,
+};
+use crate::load_shedding::{config::LoadSheddingConfig, plan::LoadSheddingPlan};
+use rocket::{get, http::Status, routes, Build, Rocket, State};
+use std::sync::Arc;
+
+#[get("/ups/<id>/load-shedding")]
+async fn get_load_shedding_route(
+    cache: &State<Arc<CachedData>>,
+    config: &State<LoadSheddingConfig>,
+    id: String,
+) -> (Status, ApiJson<ApiResponse<LoadSheddingPlan>>) {
+    let data = cache.get_load_shedding_plan(id, config).await;
+    let data = ApiResponse::new(data);
+    if !data.success {
+        return (Status::NotFound, ApiJson(data));
+    }
+    (Status::Ok, ApiJson(data))
+}
+
+/// Mount load shedding recommendations if the module is enabled
+pub fn mount_load_shedding(rocket: Rocket<Build>, config: LoadSheddingConfig) -> Rocket<Build> {
+    if !config.is_enabled() {
+        return rocket;
+    }
+    rocket
+        .manage(config)
+        .mount("/", routes![get_load_shedding_route])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::types::Example, schema::fixtures};
+    use rocket::local::asynchronous::Client;
+
+    #[tokio::test]
+    async fn test_get_load_shedding_plan() {
+        let cache = Arc::new(CachedData::default());
+        cache.set_upses(vec![fixtures::ups()]).await;
+        let mut config = serde_json::to_value(LoadSheddingConfig::example()).unwrap();
+        config["enabled"] = serde_json::Value::Bool(true);
+        let config: LoadSheddingConfig = serde_json::from_value(config).unwrap();
+        let rocket = mount_load_shedding(rocket::build().manage(cache), config);
+        let client = Client::tracked(rocket).await.unwrap();
+
+        let response = client
+            .get("/ups/%5Bups1%5Dups-monitor@localhost:3493/load-shedding")
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = response.into_string().await.unwrap();
+        let response: ApiResponse<LoadSheddingPlan> = serde_json::from_str(&response).unwrap();
+        assert_eq!(response.data.unwrap().steps.len(), 2);
+
+        let response = client.get("/ups/fake_hw_id/load-shedding").dispatch().await;
+        assert_eq!(response.status(), Status::NotFound);
+    }
+}
@@ -1,3 +1,58 @@
 // Licensed under the Open Software License version 3.0
+#[cfg(all(feature = "passive-endpoint", not(feature = "axum")))]
+mod access_log;
+mod age;
+#[cfg(feature = "axum")]
+mod axum_fleet;
+#[cfg(feature = "axum")]
+mod axum_server;
+mod changes;
+#[cfg(any(feature = "passive-endpoint", feature = "axum"))]
+mod client_ip;
 pub mod config;
+#[cfg(all(feature = "passive-endpoint", not(feature = "axum")))]
+mod control;
+mod expiry;
+#[cfg(all(
+    feature = "export",
+    feature = "passive-endpoint",
+    not(feature = "axum")
+))]
+mod export;
+#[cfg(all(feature = "passive-endpoint", not(feature = "axum")))]
+mod fleet;
+#[cfg_attr(
+    not(any(feature = "passive-endpoint", feature = "axum")),
+    allow(dead_code)
+)]
+mod history;
+#[cfg_attr(
+    not(any(feature = "passive-endpoint", feature = "axum")),
+    allow(dead_code)
+)]
+mod live;
+#[cfg(all(feature = "passive-endpoint", not(feature = "axum")))]
+mod load_shedding;
+#[cfg(any(feature = "passive-endpoint", feature = "axum"))]
+mod prometheus;
+// Cache and API types stay available for the export CLI without any server backend
+#[cfg_attr(
+    not(any(feature = "passive-endpoint", feature = "axum")),
+    allow(dead_code)
+)]
 pub mod receiver;
+#[cfg(all(feature = "passive-endpoint", not(feature = "axum")))]
+mod response;
+#[cfg(all(feature = "passive-endpoint", not(feature = "axum")))]
+mod rocket_server;
+#[cfg(all(
+    feature = "storage",
+    feature = "passive-endpoint",
+    not(feature = "axum")
+))]
+mod storage;
+#[cfg_attr(
+    not(any(feature = "passive-endpoint", feature = "axum")),
+    allow(dead_code)
+)]
+mod summary;
@@ -0,0 +1,174 @@
+// Licensed under the Open Software License version 3.0
+//! Cached data in Prometheus text exposition format
+use crate::{
+    hardware::{reading::Reading, types::HardwareMetadata},
+    nut::sender::UninterruptiblePowerSupplyData,
+    one_wire::sender::MeasuredTemperature,
+};
+use std::collections::BTreeMap;
+
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Metric name with every character outside of `[a-zA-Z0-9_]` replaced by `_`
+fn sanitize_name(name: &str) -> String {
+    name.chars()
+        .map(|character| match character {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => character,
+            _ => '_',
+        })
+        .collect()
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn labels(meta: &HardwareMetadata, extra: &[(&str, &str)]) -> String {
+    let hardware_type = format!("{:?}", meta.hw.hardware_type);
+    let source_type = format!("{:?}", meta.source.source_type);
+    let mut labels = vec![
+        ("id", meta.hw.id.as_str()),
+        ("hardware_type", hardware_type.as_str()),
+        ("source_type", source_type.as_str()),
+    ];
+    if let Some(name) = &meta.hw.name {
+        labels.push(("name", name.as_str()));
+    }
+    labels.extend_from_slice(extra);
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(key, value)| format!("{}=\"{}\"", key, escape_label_value(value)))
+        .collect();
+    labels.join(",")
+}
+
+/// Samples grouped by metric name, so every metric has a single `# TYPE` line
+#[derive(Default)]
+struct Metrics(BTreeMap<String, Vec<(String, f64)>>);
+
+impl Metrics {
+    fn add(&mut self, name: &str, labels: String, value: f64) {
+        let name = format!("uds_{}", sanitize_name(name));
+        self.0.entry(name).or_default().push((labels, value));
+    }
+
+    fn render(&self) -> String {
+        let mut output = String::new();
+        for (name, samples) in &self.0 {
+            output.push_str(&format!("# TYPE {} gauge\n", name));
+            for (labels, value) in samples {
+                output.push_str(&format!("{}{{{}}} {}\n", name, labels, value));
+            }
+        }
+        output
+    }
+}
+
+/// Numeric UPS variables become `uds_ups_<variable>`, `ups.status` flags become `uds_ups_status`
+pub fn render_metrics(
+    sensors: &[MeasuredTemperature],
+    upses: &[UninterruptiblePowerSupplyData],
+    readings: &[Reading],
+) -> String {
+    let mut metrics = Metrics::default();
+    for sensor in sensors {
+        if let Some(temperature) = sensor.temperature {
+            metrics.add(
+                "temperature_celsius",
+                labels(&sensor.meta, &[]),
+                temperature,
+            );
+        }
+        if let Some(resolution) = sensor.resolution {
+            metrics.add(
+                "temperature_resolution_bits",
+                labels(&sensor.meta, &[]),
+                resolution as f64,
+            );
+        }
+        // Alerting rules can fire on these instead of repeating thresholds
+        if let Some(quality) = &sensor.quality {
+            metrics.add(
+                "temperature_in_range",
+                labels(&sensor.meta, &[]),
+                quality.in_range as u8 as f64,
+            );
+            metrics.add(
+                "temperature_range_violations",
+                labels(&sensor.meta, &[]),
+                quality.violations as f64,
+            );
+        }
+    }
+    for ups in upses {
+        let variables: BTreeMap<_, _> = ups.variables.iter().collect();
+        for (variable, value) in variables {
+            if variable == "ups.status" {
+                for flag in value.split_whitespace() {
+                    metrics.add("ups_status", labels(&ups.meta, &[("flag", flag)]), 1.0);
+                }
+            } else if let Ok(value) = value.trim().parse::<f64>() {
+                metrics.add(&format!("ups_{}", variable), labels(&ups.meta, &[]), value);
+            }
+        }
+    }
+    for reading in readings {
+        for (key, value) in &reading.values {
+            metrics.add(
+                &format!("reading_{}", key),
+                labels(&reading.meta, &[]),
+                *value,
+            );
+        }
+    }
+    metrics.render()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::types::Example, quality::range::ReadingQuality};
+
+    #[test]
+    fn test_render_metrics() {
+        let mut sensor = MeasuredTemperature::example();
+        sensor.temperature = Some(21.5);
+        sensor.meta.hw.name = Some(String::from("Rack \"A\""));
+        let mut ups = UninterruptiblePowerSupplyData::example();
+        ups.variables
+            .insert(String::from("ups.status"), String::from("OL CHRG"));
+        ups.variables
+            .insert(String::from("ups.serial"), String::from("ABC123"));
+        sensor.quality = Some(ReadingQuality {
+            in_range: false,
+            violations: 3,
+        });
+        let output = render_metrics(&[sensor], &[ups], &[Reading::example()]);
+        assert!(output.contains("# TYPE uds_temperature_celsius gauge\n"));
+        assert!(output.contains(
+            r#"uds_temperature_celsius{id="fake_hw_id",hardware_type="TemperatureSensor",source_type="OneWire",name="Rack \"A\""} 21.5"#
+        ));
+        assert!(output.contains(
+            r#"uds_temperature_in_range{id="fake_hw_id",hardware_type="TemperatureSensor",source_type="OneWire",name="Rack \"A\""} 0"#
+        ));
+        assert!(output.contains(
+            r#"uds_temperature_range_violations{id="fake_hw_id",hardware_type="TemperatureSensor",source_type="OneWire",name="Rack \"A\""} 3"#
+        ));
+        assert!(output.contains(r#"uds_ups_battery_charge{id="fake_hw_id",hardware_type="UninterruptiblePowerSupply",source_type="NetworkUpsTools"} 100"#));
+        assert!(output.contains(r#",flag="CHRG"} 1"#));
+        assert!(output.contains("uds_reading_rss_bytes{"));
+        // Text variables other than ups.status are skipped
+        assert!(!output.contains("ABC123"));
+    }
+
+    #[test]
+    fn test_sanitize_name() {
+        assert_eq!(
+            sanitize_name("ups.realpower-nominal"),
+            "ups_realpower_nominal"
+        );
+    }
+}
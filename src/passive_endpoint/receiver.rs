@@ -1,23 +1,44 @@
 // Licensed under the Open Software License version 3.0
-use super::config::PassiveEndpointConfig;
-use crate::{nut::sender::UninterruptiblePowerSupplyData, one_wire::sender::MeasuredTemperature};
-use rocket::{get, http::Status, routes, serde::json::Json, Build, Rocket, State};
+use super::{
+    age::WithAge,
+    changes::{ChangeTracker, Changes},
+    config::{HistoryConfig, PassiveEndpointConfig},
+    expiry::{Category, Expiry},
+    history::{sample_time, History, HistorySample, TemperaturePoint, UpsPoint},
+    live::{LiveEvent, LiveEvents},
+};
+use crate::{
+    clock::SharedClock,
+    fleet::config::FleetConfig,
+    hardware::reading::{Reading, ReadingsByPublisher, ReadingsUpdate},
+    introspection,
+    load_shedding::{
+        config::LoadSheddingConfig,
+        plan::{plan_load_shedding, LoadSheddingPlan},
+    },
+    nut::sender::{redact_upses, UninterruptiblePowerSupplyData, UpsUpdate, UpsesByPublisher},
+    one_wire::sender::MeasuredTemperature,
+    schema::SCHEMA_VERSION,
+    self_metrics::lag::recv_counting_lag,
+    storage::config::StorageConfig,
+    wake_on_lan::config::WakeOnLanConfig,
+};
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, sync::Arc};
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::{broadcast, oneshot, RwLock};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
 struct ApiToken<'a>(&'a str);
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
-struct ApiResponse<T> {
-    success: bool,
-    error: Option<String>,
-    data: Option<T>,
+pub struct ApiResponse<T> {
+    pub success: bool,
+    pub error: Option<String>,
+    pub data: Option<T>,
 }
 
 impl<T> ApiResponse<T> {
-    fn new(data: Option<T>) -> Self {
+    pub fn new(data: Option<T>) -> Self {
         // If data is None, error is "not found"
         let error = match data.is_none() {
             true => Some(String::from("not found")),
@@ -29,55 +50,257 @@ impl<T> ApiResponse<T> {
             data,
         }
     }
+
+    /// Every entry is stale, `data` is returned anyway (empty if stale entries are dropped)
+    pub fn expired(data: T) -> Self {
+        Self {
+            success: false,
+            error: Some(String::from("all data is stale")),
+            data: Some(data),
+        }
+    }
+
+    pub fn error(error: &str) -> Self {
+        Self {
+            success: false,
+            error: Some(String::from(error)),
+            data: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct VersionInfo {
+    pub(super) version: String,
+    pub(super) instance_id: String,
+    // Bumped on incompatible changes of payload and response shapes
+    pub(super) schema_version: u32,
+}
+
+impl VersionInfo {
+    pub(super) fn new(instance_id: String) -> Self {
+        Self {
+            version: String::from(env!("CARGO_PKG_VERSION")),
+            instance_id,
+            schema_version: SCHEMA_VERSION,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default)]
-struct CachedData {
+pub(super) struct CachedData {
     // By category
     temperature_sensors: Arc<RwLock<Vec<MeasuredTemperature>>>,
     upses: Arc<RwLock<Vec<UninterruptiblePowerSupplyData>>>,
     // By category + hw.id
     temperature_sensors_by_hw_id: Arc<RwLock<HashMap<String, MeasuredTemperature>>>,
     upses_by_hw_id: Arc<RwLock<HashMap<String, UninterruptiblePowerSupplyData>>>,
+    // By category + hw.name
+    temperature_sensors_by_name: Arc<RwLock<HashMap<String, MeasuredTemperature>>>,
+    // Generic sources, merged from all publishers
+    readings: Arc<RwLock<ReadingsByPublisher>>,
+    // Sequence numbers for incremental sync
+    changes: Arc<RwLock<ChangeTracker>>,
+    // Stale entries are flagged or dropped when returned
+    expiry: Expiry,
+    // Recent samples by category + hw.id
+    temperature_history: Arc<RwLock<History<TemperaturePoint>>>,
+    ups_history: Arc<RwLock<History<UpsPoint>>>,
+    // Every update, pushed to `/ws` clients
+    live: LiveEvents,
+    // Ages and staleness are relative to its time
+    clock: SharedClock,
 }
 
 impl CachedData {
+    pub fn new(expiry: Expiry) -> Self {
+        Self {
+            expiry,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_history(mut self, config: Option<HistoryConfig>) -> Self {
+        self.temperature_history = Arc::new(RwLock::new(History::new(config.clone())));
+        self.ups_history = Arc::new(RwLock::new(History::new(config)));
+        self
+    }
+
+    #[cfg(test)]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Cache has data of `category`, but all of it is stale
+    pub async fn is_expired(&self, category: Category) -> bool {
+        let now = self.clock.utc_now();
+        match category {
+            Category::Temperature => {
+                let sensors = self.temperature_sensors.read().await.clone();
+                self.expiry.is_expired(category, &sensors.with_age(now))
+            }
+            Category::Ups => {
+                let upses = self.upses.read().await.clone();
+                self.expiry.is_expired(category, &upses.with_age(now))
+            }
+            Category::Readings => {
+                let readings = self.readings.read().await.all();
+                self.expiry.is_expired(category, &readings.with_age(now))
+            }
+        }
+    }
+
     pub async fn get_temperature_sensors(&self) -> Vec<MeasuredTemperature> {
-        self.temperature_sensors.read().await.clone()
+        let sensors = self.temperature_sensors.read().await.clone();
+        self.expiry.apply(
+            Category::Temperature,
+            sensors.with_age(self.clock.utc_now()),
+        )
     }
 
     pub async fn get_temperature_sensor_by_hw_id(&self, id: String) -> Option<MeasuredTemperature> {
-        self.temperature_sensors_by_hw_id
+        let sensor = self
+            .temperature_sensors_by_hw_id
             .read()
             .await
             .get(&id)
-            .cloned()
+            .cloned();
+        self.expiry
+            .apply_one(Category::Temperature, sensor.with_age(self.clock.utc_now()))
+    }
+
+    pub async fn get_temperature_sensor_by_name(
+        &self,
+        name: String,
+    ) -> Option<MeasuredTemperature> {
+        let sensor = self
+            .temperature_sensors_by_name
+            .read()
+            .await
+            .get(&name)
+            .cloned();
+        self.expiry
+            .apply_one(Category::Temperature, sensor.with_age(self.clock.utc_now()))
     }
 
     pub async fn set_sensors(&self, sensors: Vec<MeasuredTemperature>) {
-        *self.temperature_sensors.write().await = sensors.clone();
-        let hash_map = &mut self.temperature_sensors_by_hw_id.write().await;
-        hash_map.clear();
-        for sensor in sensors {
-            hash_map.insert(sensor.meta.hw.id.clone(), sensor);
+        // Hold all write locks at once so readers never see a partial update
+        let mut list = self.temperature_sensors.write().await;
+        let mut by_hw_id = self.temperature_sensors_by_hw_id.write().await;
+        let mut by_name = self.temperature_sensors_by_name.write().await;
+        self.changes.write().await.set_sensors(&sensors);
+        by_hw_id.clear();
+        by_name.clear();
+        for sensor in &sensors {
+            by_hw_id.insert(sensor.meta.hw.id.clone(), sensor.clone());
+            if let Some(name) = &sensor.meta.hw.name {
+                by_name.insert(name.clone(), sensor.clone());
+            }
         }
+        let now = self.clock.utc_now();
+        let points = sensors
+            .iter()
+            .map(|sensor| {
+                let point = TemperaturePoint {
+                    temperature: sensor.temperature,
+                };
+                let timestamp = sample_time(&sensor.meta.measured_at, now);
+                (sensor.meta.hw.id.clone(), timestamp, point)
+            })
+            .collect();
+        self.temperature_history.write().await.record(points, now);
+        *list = sensors.clone();
+        self.live.publish(LiveEvent::Temperature(sensors));
     }
 
     pub async fn get_upses(&self) -> Vec<UninterruptiblePowerSupplyData> {
-        self.upses.read().await.clone()
+        let upses = self.upses.read().await.clone();
+        self.expiry
+            .apply(Category::Ups, upses.with_age(self.clock.utc_now()))
     }
 
     pub async fn get_ups_by_hw_id(&self, id: String) -> Option<UninterruptiblePowerSupplyData> {
-        self.upses_by_hw_id.read().await.get(&id).cloned()
+        let ups = self.upses_by_hw_id.read().await.get(&id).cloned();
+        self.expiry
+            .apply_one(Category::Ups, ups.with_age(self.clock.utc_now()))
+    }
+
+    /// `None` if history is disabled or the sensor isn't cached
+    pub async fn get_temperature_history(
+        &self,
+        id: String,
+    ) -> Option<Vec<HistorySample<TemperaturePoint>>> {
+        self.temperature_history.read().await.get(&id)
+    }
+
+    pub async fn get_ups_history(&self, id: String) -> Option<Vec<HistorySample<UpsPoint>>> {
+        self.ups_history.read().await.get(&id)
+    }
+
+    /// `None` if the UPS isn't cached or has no sheddable devices
+    pub async fn get_load_shedding_plan(
+        &self,
+        id: String,
+        config: &LoadSheddingConfig,
+    ) -> Option<LoadSheddingPlan> {
+        let devices = config.get_devices(&id)?;
+        let ups = self.get_ups_by_hw_id(id).await?;
+        Some(plan_load_shedding(&ups, &devices))
     }
 
     pub async fn set_upses(&self, upses: Vec<UninterruptiblePowerSupplyData>) {
-        *self.upses.write().await = upses.clone();
-        let mut hash_map = self.upses_by_hw_id.write().await;
-        hash_map.clear();
-        for ups in upses {
-            hash_map.insert(ups.meta.hw.id.clone(), ups);
+        // Hold all write locks at once so readers never see a partial update
+        let mut list = self.upses.write().await;
+        let mut by_hw_id = self.upses_by_hw_id.write().await;
+        self.changes.write().await.set_upses(&upses);
+        by_hw_id.clear();
+        for ups in &upses {
+            by_hw_id.insert(ups.meta.hw.id.clone(), ups.clone());
         }
+        let now = self.clock.utc_now();
+        let points = upses
+            .iter()
+            .map(|ups| {
+                let point = UpsPoint {
+                    variables: ups.variables.clone(),
+                };
+                let timestamp = sample_time(&ups.meta.measured_at, now);
+                (ups.meta.hw.id.clone(), timestamp, point)
+            })
+            .collect();
+        self.ups_history.write().await.record(points, now);
+        *list = upses.clone();
+        self.live.publish(LiveEvent::Ups(upses));
+    }
+
+    pub async fn get_readings(&self) -> Vec<Reading> {
+        let readings = self.readings.read().await.all();
+        self.expiry
+            .apply(Category::Readings, readings.with_age(self.clock.utc_now()))
+    }
+
+    pub async fn get_reading_by_hw_id(&self, id: String) -> Option<Reading> {
+        self.get_readings()
+            .await
+            .into_iter()
+            .find(|reading| reading.meta.hw.id == id)
+    }
+
+    pub async fn update_readings(&self, update: ReadingsUpdate) {
+        let mut readings = self.readings.write().await;
+        readings.update(update);
+        self.changes.write().await.set_readings(&readings.all());
+    }
+
+    pub fn subscribe_live(&self) -> broadcast::Receiver<LiveEvent> {
+        self.live.subscribe()
+    }
+
+    pub async fn get_changes(&self, since: u64, epoch: Option<u64>) -> Changes {
+        let changes = self.changes.read().await.changes_since(since, epoch);
+        self.expiry
+            .flag_changes(changes.with_age(self.clock.utc_now()))
     }
 }
 
@@ -85,17 +308,28 @@ async fn start_cache_updater_loop(
     mut shutdown_rx: broadcast::Receiver<()>,
     cache: Arc<CachedData>,
     mut one_wire_rx: broadcast::Receiver<Vec<MeasuredTemperature>>,
-    mut ups_monitoring_rx: broadcast::Receiver<Vec<UninterruptiblePowerSupplyData>>,
+    mut ups_monitoring_rx: broadcast::Receiver<UpsUpdate>,
+    mut readings_rx: broadcast::Receiver<ReadingsUpdate>,
+    redacted_variables: Vec<String>,
 ) {
+    // Cached UPSes are replaced as a whole, so updates of every publisher are kept here
+    let mut upses = UpsesByPublisher::default();
+    let _task = introspection::task_started("passive_endpoint");
     loop {
+        introspection::mark_iteration("passive_endpoint");
         tokio::select! {
-            Ok(value) = one_wire_rx.recv() => {
+            Ok(value) = recv_counting_lag(&mut one_wire_rx) => {
                 tracing::trace!("{:?}", value);
                 cache.set_sensors(value).await;
             }
-            Ok(value) = ups_monitoring_rx.recv() => {
+            Ok(value) = recv_counting_lag(&mut ups_monitoring_rx) => {
                 tracing::trace!("{:?}", value);
-                cache.set_upses(value).await;
+                upses.update(value);
+                cache.set_upses(redact_upses(&upses.all(), &redacted_variables)).await;
+            }
+            Ok(value) = recv_counting_lag(&mut readings_rx) => {
+                tracing::trace!("{:?}", value);
+                cache.update_readings(value).await;
             }
             _ = shutdown_rx.recv() => {
                 tracing::trace!("Shutting down cache updater loop");
@@ -105,299 +339,224 @@ async fn start_cache_updater_loop(
     }
 }
 
-#[get("/temperature")]
-async fn get_temperature_sensors_route(
-    cache: &State<Arc<CachedData>>,
-) -> Json<ApiResponse<Vec<MeasuredTemperature>>> {
-    Json(ApiResponse::new(Some(
-        cache.get_temperature_sensors().await,
-    )))
-}
-
-#[get("/temperature/<id>")]
-async fn get_temperature_sensor_by_hw_id_route(
-    cache: &State<Arc<CachedData>>,
-    id: String,
-) -> (Status, Json<ApiResponse<MeasuredTemperature>>) {
-    let data = cache.get_temperature_sensor_by_hw_id(id).await;
-    let data = ApiResponse::new(data);
-    if !data.success {
-        return (Status::NotFound, Json(data));
-    }
-    (Status::Ok, Json(data))
-}
-
-#[get("/ups")]
-async fn get_upses_route(
-    cache: &State<Arc<CachedData>>,
-) -> Json<ApiResponse<Vec<UninterruptiblePowerSupplyData>>> {
-    Json(ApiResponse::new(Some(cache.get_upses().await)))
-}
-
-#[get("/ups/<id>")]
-async fn get_ups_by_hw_id_route(
-    cache: &State<Arc<CachedData>>,
-    id: String,
-) -> (Status, Json<ApiResponse<UninterruptiblePowerSupplyData>>) {
-    let data = cache.get_ups_by_hw_id(id).await;
-    let data = ApiResponse::new(data);
-    if !data.success {
-        return (Status::NotFound, Json(data));
-    }
-    (Status::Ok, Json(data))
-}
-
-fn rocket(cache: Arc<CachedData>) -> Rocket<Build> {
-    rocket::build().manage(cache).mount(
-        "/",
-        routes![
-            get_temperature_sensors_route,
-            get_temperature_sensor_by_hw_id_route,
-            get_upses_route,
-            get_ups_by_hw_id_route
-        ],
-    )
-}
-
+#[allow(clippy::too_many_arguments)]
 pub async fn start_passive_endpoint_loop(
     shutdown_rx: broadcast::Receiver<()>,
     config: PassiveEndpointConfig,
     one_wire_rx: broadcast::Receiver<Vec<MeasuredTemperature>>,
-    ups_monitoring_rx: broadcast::Receiver<Vec<UninterruptiblePowerSupplyData>>,
+    ups_monitoring_rx: broadcast::Receiver<UpsUpdate>,
+    readings_rx: broadcast::Receiver<ReadingsUpdate>,
+    wake_on_lan: WakeOnLanConfig,
+    load_shedding: LoadSheddingConfig,
+    fleet: FleetConfig,
+    storage: StorageConfig,
+    instance_id: String,
+    read_only: bool,
+    // Sent once the server is listening, dropped if it fails to start
+    ready_tx: oneshot::Sender<()>,
 ) {
     // Check if module is enabled
     if !config.is_enabled() {
         tracing::trace!("Module is disabled");
         return;
     }
+    #[cfg(any(feature = "passive-endpoint", feature = "axum"))]
+    run(
+        shutdown_rx,
+        config,
+        one_wire_rx,
+        ups_monitoring_rx,
+        readings_rx,
+        wake_on_lan,
+        load_shedding,
+        fleet,
+        storage,
+        instance_id,
+        read_only,
+        ready_tx,
+    )
+    .await;
+    #[cfg(not(any(feature = "passive-endpoint", feature = "axum")))]
+    {
+        let _ = (
+            shutdown_rx,
+            one_wire_rx,
+            ups_monitoring_rx,
+            readings_rx,
+            wake_on_lan,
+            load_shedding,
+            fleet,
+            storage,
+            instance_id,
+            read_only,
+            ready_tx,
+        );
+        tracing::error!(
+            "Passive endpoint is enabled in config but this binary was built without passive-endpoint or axum feature"
+        );
+    }
+}
 
-    let cache = Arc::new(CachedData::default());
+#[cfg(any(feature = "passive-endpoint", feature = "axum"))]
+#[allow(clippy::too_many_arguments)]
+async fn run(
+    shutdown_rx: broadcast::Receiver<()>,
+    config: PassiveEndpointConfig,
+    one_wire_rx: broadcast::Receiver<Vec<MeasuredTemperature>>,
+    ups_monitoring_rx: broadcast::Receiver<UpsUpdate>,
+    readings_rx: broadcast::Receiver<ReadingsUpdate>,
+    wake_on_lan: WakeOnLanConfig,
+    load_shedding: LoadSheddingConfig,
+    fleet: FleetConfig,
+    storage: StorageConfig,
+    instance_id: String,
+    read_only: bool,
+    ready_tx: oneshot::Sender<()>,
+) {
+    let cache = Arc::new(CachedData::new(Expiry::new(&config)).with_history(config.get_history()));
 
     // Simple API that returns cached data as JSON
     tracing::trace!("Starting passive endpoint loop");
-    let mut shutdown_rx_clone = shutdown_rx.resubscribe();
+    let shutdown_rx_clone = shutdown_rx.resubscribe();
     let cache_arc_clone: Arc<CachedData> = cache.clone();
-    let rocket_handle = tokio::spawn(async move {
-        let prepared_rocket = rocket(cache_arc_clone)
-            .configure(rocket::Config {
-                port: config.get_port(),
-                shutdown: rocket::config::Shutdown {
-                    ctrlc: false,
-                    ..Default::default()
-                },
-                ..Default::default()
-            })
-            .launch();
-
-        tokio::select! {
-            _ = prepared_rocket => {},
-            _ = shutdown_rx_clone.recv() => {
-                tracing::trace!("Aborting rocket");
-            }
-        }
+    let config_clone = config.clone();
+    let server_handle = tokio::spawn(async move {
+        // axum is lighter, pick it if both backends are compiled in
+        #[cfg(feature = "axum")]
+        super::axum_server::serve(
+            shutdown_rx_clone,
+            cache_arc_clone,
+            config_clone,
+            wake_on_lan,
+            load_shedding,
+            fleet,
+            storage,
+            instance_id,
+            read_only,
+            ready_tx,
+        )
+        .await;
+
+        #[cfg(not(feature = "axum"))]
+        super::rocket_server::serve(
+            shutdown_rx_clone,
+            cache_arc_clone,
+            config_clone,
+            wake_on_lan,
+            load_shedding,
+            fleet,
+            storage,
+            instance_id,
+            read_only,
+            ready_tx,
+        )
+        .await;
     });
 
     // Cache updater
+    let redacted_variables = config.get_redacted_variables();
     let cache_updater_handle = tokio::spawn(async move {
-        start_cache_updater_loop(shutdown_rx, cache, one_wire_rx, ups_monitoring_rx).await;
+        start_cache_updater_loop(
+            shutdown_rx,
+            cache,
+            one_wire_rx,
+            ups_monitoring_rx,
+            readings_rx,
+            redacted_variables,
+        )
+        .await;
     });
 
-    let _ = tokio::try_join!(rocket_handle, cache_updater_handle);
+    let _ = tokio::try_join!(server_handle, cache_updater_handle);
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::types::Example;
-    use rocket::{
-        http::{ContentType, Status},
-        local::asynchronous::Client,
-        uri,
+    use crate::{
+        clock::{Clock, MockClock},
+        schema::{assert_matches_golden_file, fixtures},
     };
+    use chrono::Utc;
 
-    #[tokio::test]
-    async fn test_get_sensors_empty_cache() {
-        let cache = Arc::new(CachedData::default());
-        let client = Client::tracked(rocket(cache.clone())).await.unwrap();
-
-        let response = client
-            .get(uri!(super::get_temperature_sensors_route))
-            .dispatch()
-            .await;
-        // Basic checks
-        assert_eq!(response.status(), Status::Ok);
-        assert_eq!(response.content_type(), Some(ContentType::JSON));
-        // Inspect JSON response
-        let response = response.into_string().await.unwrap();
-        let response: ApiResponse<Vec<MeasuredTemperature>> =
-            serde_json::from_str(&response).unwrap();
-        assert!(response.success);
-        assert!(response.error.is_none());
-        assert_eq!(response.data.unwrap(), vec![]);
-    }
-
-    #[tokio::test]
-    async fn test_get_sensors_with_updated_data() {
-        let cache = Arc::new(CachedData::default());
-        let client = Client::tracked(rocket(cache.clone())).await.unwrap();
-
-        let response = client
-            .get(uri!(super::get_temperature_sensors_route))
-            .dispatch()
-            .await;
-        assert_eq!(response.status(), Status::Ok);
-        assert_eq!(response.content_type(), Some(ContentType::JSON));
-
-        let sensors = vec![MeasuredTemperature::example()];
-        cache.set_sensors(sensors.clone()).await;
-
-        let response = client
-            .get(uri!(super::get_temperature_sensors_route))
-            .dispatch()
-            .await;
-        assert_eq!(response.status(), Status::Ok);
-        assert_eq!(response.content_type(), Some(ContentType::JSON));
-
-        let response = response.into_string().await.unwrap();
-        let response: ApiResponse<Vec<MeasuredTemperature>> =
-            serde_json::from_str(&response).unwrap();
-        assert!(response.success);
-        assert!(response.error.is_none());
-        assert_eq!(response.data.unwrap(), sensors);
-    }
-
-    #[tokio::test]
-    async fn test_get_sensor_by_hw_id() {
-        let cache = Arc::new(CachedData::default());
-        let client = Client::tracked(rocket(cache.clone())).await.unwrap();
-
-        let sensors = vec![MeasuredTemperature::example()];
-        cache.set_sensors(sensors.clone()).await;
-
-        let response = client
-            .get(uri!(super::get_temperature_sensor_by_hw_id_route(
-                sensors[0].meta.hw.id.clone()
-            )))
-            .dispatch()
-            .await;
-        assert_eq!(response.status(), Status::Ok);
-        assert_eq!(response.content_type(), Some(ContentType::JSON));
-
-        let response = response.into_string().await.unwrap();
-        let response: ApiResponse<MeasuredTemperature> = serde_json::from_str(&response).unwrap();
-        assert!(response.success);
-        assert!(response.error.is_none());
-        assert!(response.data.is_some());
-        assert_eq!(response.data.unwrap(), sensors[0]);
-    }
-
-    #[tokio::test]
-    async fn test_get_sensor_by_hw_id_404() {
-        let cache = Arc::new(CachedData::default());
-        let client = Client::tracked(rocket(cache)).await.unwrap();
-
-        let response = client
-            .get(uri!(super::get_temperature_sensor_by_hw_id_route(
-                String::from("non-existent-id")
-            )))
-            .dispatch()
-            .await;
-        assert_eq!(response.status(), Status::NotFound);
-        assert_eq!(response.content_type(), Some(ContentType::JSON));
-
-        let response = response.into_string().await.unwrap();
-        let response: ApiResponse<MeasuredTemperature> = serde_json::from_str(&response).unwrap();
-        assert!(!response.success);
-        assert!(response.error.is_some());
-        assert!(response.data.is_none());
-    }
-
-    #[tokio::test]
-    async fn test_get_upses_empty_cache() {
-        let cache = Arc::new(CachedData::default());
-        let client = Client::tracked(rocket(cache.clone())).await.unwrap();
-
-        let response = client.get(uri!(super::get_upses_route)).dispatch().await;
-        assert_eq!(response.status(), Status::Ok);
-        assert_eq!(response.content_type(), Some(ContentType::JSON));
-
-        let response = response.into_string().await.unwrap();
-        let response: ApiResponse<Vec<UninterruptiblePowerSupplyData>> =
-            serde_json::from_str(&response).unwrap();
-        assert!(response.success);
-        assert!(response.error.is_none());
-        assert_eq!(response.data.unwrap(), vec![]);
+    /// Five seconds after `fixtures::MEASURED_AT`
+    fn now() -> chrono::DateTime<Utc> {
+        chrono::DateTime::parse_from_rfc3339("2023-01-01T00:00:05+00:00")
+            .unwrap()
+            .with_timezone(&Utc)
     }
 
-    #[tokio::test]
-    async fn test_get_upses_with_updated_data() {
-        let cache = Arc::new(CachedData::default());
-        let client = Client::tracked(rocket(cache.clone())).await.unwrap();
-
-        let response = client.get(uri!(super::get_upses_route)).dispatch().await;
-        assert_eq!(response.status(), Status::Ok);
-        assert_eq!(response.content_type(), Some(ContentType::JSON));
-
-        let upses = vec![UninterruptiblePowerSupplyData::example()];
-        cache.set_upses(upses.clone()).await;
-
-        let response = client.get(uri!(super::get_upses_route)).dispatch().await;
-        assert_eq!(response.status(), Status::Ok);
-        assert_eq!(response.content_type(), Some(ContentType::JSON));
-
-        let response = response.into_string().await.unwrap();
-        let response: ApiResponse<Vec<UninterruptiblePowerSupplyData>> =
-            serde_json::from_str(&response).unwrap();
-        assert!(response.success);
-        assert!(response.error.is_none());
-        assert_eq!(response.data.unwrap(), upses);
+    #[test]
+    fn test_responses_match_golden_files() {
+        let version = VersionInfo {
+            version: String::from("0.0.0"),
+            instance_id: String::from(fixtures::INSTANCE_ID),
+            schema_version: SCHEMA_VERSION,
+        };
+        assert_matches_golden_file("version", &ApiResponse::new(Some(version)));
+        assert_matches_golden_file(
+            "temperature",
+            &ApiResponse::new(Some(vec![fixtures::sensor()].with_age(now()))),
+        );
+        assert_matches_golden_file(
+            "ups",
+            &ApiResponse::new(Some(vec![fixtures::ups()].with_age(now()))),
+        );
+        assert_matches_golden_file(
+            "readings",
+            &ApiResponse::new(Some(vec![fixtures::reading()].with_age(now()))),
+        );
+        assert_matches_golden_file("not_found", &ApiResponse::<()>::new(None));
+        let changes = Changes {
+            sequence: 3,
+            epoch: 1688212800000,
+            reset: false,
+            temperature: vec![fixtures::sensor()],
+            ups: vec![fixtures::ups()],
+            readings: vec![fixtures::reading()],
+            removed: vec![String::from("temperature:28-000000000001")],
+        }
+        .with_age(now());
+        assert_matches_golden_file("changes", &ApiResponse::new(Some(changes)));
     }
 
     #[tokio::test]
-    async fn test_get_ups_by_hw_id() {
-        let cache = Arc::new(CachedData::default());
-        let client = Client::tracked(rocket(cache.clone())).await.unwrap();
-
-        let upses = vec![UninterruptiblePowerSupplyData::example()];
-        cache.set_upses(upses.clone()).await;
-
-        let response = client
-            .get(uri!(super::get_ups_by_hw_id_route(
-                upses[0].meta.hw.id.clone(),
-            )))
-            .dispatch()
-            .await;
-        assert_eq!(response.status(), Status::Ok);
-        assert_eq!(response.content_type(), Some(ContentType::JSON));
-
-        let response = response.into_string().await.unwrap();
-        let response: ApiResponse<UninterruptiblePowerSupplyData> =
-            serde_json::from_str(&response).unwrap();
-        assert!(response.success);
-        assert!(response.error.is_none());
-        assert!(response.data.is_some());
-        assert_eq!(response.data.unwrap(), upses[0]);
+    async fn test_staleness_follows_clock() {
+        let config: PassiveEndpointConfig = serde_json::from_value(serde_json::json!({
+            "max_age": { "temperature": { "secs": 10, "nanos": 0 } }
+        }))
+        .unwrap();
+        let mock = MockClock::new(now());
+        let cache = CachedData::new(Expiry::new(&config)).with_clock(mock.shared());
+        cache.set_sensors(vec![fixtures::sensor()]).await;
+        assert!(!cache.is_expired(Category::Temperature).await);
+        let sensors = cache.get_temperature_sensors().await;
+        assert_eq!(sensors[0].meta.age_secs, Some(5));
+
+        mock.advance(std::time::Duration::from_secs(6));
+        assert!(cache.is_expired(Category::Temperature).await);
     }
 
     #[tokio::test]
-    async fn test_get_ups_by_hw_id_404() {
-        let cache = Arc::new(CachedData::default());
-        let client = Client::tracked(rocket(cache)).await.unwrap();
-
-        let response = client
-            .get(uri!(super::get_ups_by_hw_id_route(String::from(
-                "non-existent-id"
-            ))))
-            .dispatch()
-            .await;
-        assert_eq!(response.status(), Status::NotFound);
-        assert_eq!(response.content_type(), Some(ContentType::JSON));
-
-        let response = response.into_string().await.unwrap();
-        let response: ApiResponse<UninterruptiblePowerSupplyData> =
-            serde_json::from_str(&response).unwrap();
-        assert!(!response.success);
-        assert!(response.error.is_some());
-        assert!(response.data.is_none());
+    async fn test_history() {
+        let mock = MockClock::new(now());
+        let cache = CachedData::default()
+            .with_history(Some(Default::default()))
+            .with_clock(mock.shared());
+        let mut sensor = fixtures::sensor();
+        for temperature in [21.0, 22.0] {
+            sensor.meta.measured_at = Some(mock.utc_now().to_rfc3339());
+            sensor.temperature = Some(temperature);
+            cache.set_sensors(vec![sensor.clone()]).await;
+            mock.advance(std::time::Duration::from_secs(1));
+        }
+        let history = cache
+            .get_temperature_history(sensor.meta.hw.id.clone())
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].timestamp, "2023-01-01T00:00:05.000Z");
+        assert_eq!(history[1].point.temperature, Some(22.0));
+        assert!(cache.get_ups_history(sensor.meta.hw.id).await.is_none());
     }
 }
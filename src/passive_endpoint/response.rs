@@ -0,0 +1,134 @@
+// Licensed under the Open Software License version 3.0
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::{ContentType, Method, Status},
+    request::{FromRequest, Outcome},
+    response::{self, Responder},
+    Data, Request, Response,
+};
+use serde::Serialize;
+
+/// Default JSON formatting, overridden by `?pretty=<bool>`
+pub struct PrettyJson(pub bool);
+
+/// Whether the client sent `HEAD`, Rocket answers it with the `GET` route and drops the body
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IsHead(pub bool);
+
+fn is_head(request: &Request<'_>) -> bool {
+    request.local_cache(|| IsHead(false)).0
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IsHead {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(IsHead(is_head(request)))
+    }
+}
+
+/// Remember the method before routing, `GET` routes see it changed from `HEAD`
+pub struct HeadRequests;
+
+#[rocket::async_trait]
+impl Fairing for HeadRequests {
+    fn info(&self) -> Info {
+        Info {
+            name: "HEAD requests",
+            kind: Kind::Request,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        let head = request.method() == Method::Head;
+        request.local_cache(|| IsHead(head));
+    }
+}
+
+/// Headers without a body, its length isn't known without serializing it
+pub fn headers_only(content_type: ContentType) -> response::Result<'static> {
+    Response::build()
+        .header(content_type)
+        .streamed_body(tokio::io::empty())
+        .ok()
+}
+
+/// JSON responder that supports pretty and compact output
+pub struct ApiJson<T>(pub T);
+
+impl<'r, T: Serialize> Responder<'r, 'static> for ApiJson<T> {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        if is_head(request) {
+            return headers_only(ContentType::JSON);
+        }
+        let default = request
+            .rocket()
+            .state::<PrettyJson>()
+            .map(|pretty| pretty.0)
+            .unwrap_or_default();
+        let pretty = request
+            .query_value::<bool>("pretty")
+            .and_then(|value| value.ok())
+            .unwrap_or(default);
+        let body = match pretty {
+            true => serde_json::to_string_pretty(&self.0),
+            false => serde_json::to_string(&self.0),
+        }
+        .map_err(|error| {
+            tracing::error!("Failed to serialize response: {}", error);
+            Status::InternalServerError
+        })?;
+        (ContentType::JSON, body).respond_to(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::{get, local::asynchronous::Client, routes};
+
+    #[get("/")]
+    fn index() -> ApiJson<Vec<u8>> {
+        ApiJson(vec![1, 2])
+    }
+
+    async fn client(pretty: bool) -> Client {
+        let rocket = rocket::build()
+            .manage(PrettyJson(pretty))
+            .attach(HeadRequests)
+            .mount("/", routes![index]);
+        Client::tracked(rocket).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_compact_by_default() {
+        let client = client(false).await;
+        let response = client.get("/").dispatch().await;
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+        assert_eq!(response.into_string().await.unwrap(), "[1,2]");
+    }
+
+    #[tokio::test]
+    async fn test_pretty_query() {
+        let client = client(false).await;
+        let response = client.get("/?pretty=true").dispatch().await;
+        assert_eq!(response.into_string().await.unwrap(), "[\n  1,\n  2\n]");
+    }
+
+    #[tokio::test]
+    async fn test_compact_query_overrides_config() {
+        let client = client(true).await;
+        let response = client.get("/?pretty=false").dispatch().await;
+        assert_eq!(response.into_string().await.unwrap(), "[1,2]");
+    }
+
+    #[tokio::test]
+    async fn test_head_skips_body() {
+        let client = client(false).await;
+        let response = client.head("/").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+        assert_eq!(response.into_string().await.unwrap_or_default(), "");
+    }
+}
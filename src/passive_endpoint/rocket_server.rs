@@ -0,0 +1,938 @@
+// Licensed under the Open Software License version 3.0
+use super::{
+    access_log::mount_access_log,
+    changes::Changes,
+    config::PassiveEndpointConfig,
+    control::{mount_control, Authorized},
+    expiry::Category,
+    fleet::mount_fleet,
+    history::{HistorySample, TemperaturePoint, UpsPoint},
+    live::next_message,
+    load_shedding::mount_load_shedding,
+    prometheus::{self, render_metrics},
+    receiver::{ApiResponse, CachedData, VersionInfo},
+    response::{headers_only, ApiJson, HeadRequests, IsHead, PrettyJson},
+    summary::{summarize_temperatures, summarize_upses, TemperatureSummary, UpsSummary},
+};
+use crate::{
+    fleet::config::FleetConfig,
+    hardware::reading::Reading,
+    introspection::{self, InternalStatus},
+    load_shedding::config::LoadSheddingConfig,
+    nut::sender::UninterruptiblePowerSupplyData,
+    one_wire::sender::MeasuredTemperature,
+    storage::config::StorageConfig,
+    wake_on_lan::config::WakeOnLanConfig,
+};
+use rocket::{
+    config::TlsConfig,
+    fairing::AdHoc,
+    futures::{SinkExt, StreamExt},
+    get,
+    http::{ContentType, Status},
+    response::{self, Responder},
+    routes, Build, Request, Rocket, State,
+};
+use rocket_ws::{Channel, Message, WebSocket};
+use std::sync::Arc;
+use tokio::sync::{broadcast, oneshot};
+
+/// 503 if every cached entry of `category` is stale
+async fn list_response<T>(
+    cache: &CachedData,
+    category: Category,
+    data: T,
+) -> (Status, ApiJson<ApiResponse<T>>) {
+    if cache.is_expired(category).await {
+        return (
+            Status::ServiceUnavailable,
+            ApiJson(ApiResponse::expired(data)),
+        );
+    }
+    (Status::Ok, ApiJson(ApiResponse::new(Some(data))))
+}
+
+#[get("/temperature")]
+async fn get_temperature_sensors_route(
+    cache: &State<Arc<CachedData>>,
+) -> (Status, ApiJson<ApiResponse<Vec<MeasuredTemperature>>>) {
+    let data = cache.get_temperature_sensors().await;
+    list_response(cache, Category::Temperature, data).await
+}
+
+#[get("/temperature/summary")]
+async fn get_temperature_summary_route(
+    cache: &State<Arc<CachedData>>,
+) -> (Status, ApiJson<ApiResponse<TemperatureSummary>>) {
+    let data = summarize_temperatures(&cache.get_temperature_sensors().await);
+    list_response(cache, Category::Temperature, data).await
+}
+
+#[get("/temperature/<id>")]
+async fn get_temperature_sensor_by_hw_id_route(
+    cache: &State<Arc<CachedData>>,
+    id: String,
+) -> (Status, ApiJson<ApiResponse<MeasuredTemperature>>) {
+    let data = cache.get_temperature_sensor_by_hw_id(id).await;
+    let data = ApiResponse::new(data);
+    if !data.success {
+        return (Status::NotFound, ApiJson(data));
+    }
+    (Status::Ok, ApiJson(data))
+}
+
+#[get("/temperature/by-name/<name>")]
+async fn get_temperature_sensor_by_name_route(
+    cache: &State<Arc<CachedData>>,
+    name: String,
+) -> (Status, ApiJson<ApiResponse<MeasuredTemperature>>) {
+    let data = cache.get_temperature_sensor_by_name(name).await;
+    let data = ApiResponse::new(data);
+    if !data.success {
+        return (Status::NotFound, ApiJson(data));
+    }
+    (Status::Ok, ApiJson(data))
+}
+
+// Ranked after `/temperature/by-name/<name>`, so a sensor named "history" can still be found
+#[get("/temperature/<id>/history", rank = 2)]
+async fn get_temperature_history_route(
+    cache: &State<Arc<CachedData>>,
+    id: String,
+) -> (
+    Status,
+    ApiJson<ApiResponse<Vec<HistorySample<TemperaturePoint>>>>,
+) {
+    let data = cache.get_temperature_history(id).await;
+    let data = ApiResponse::new(data);
+    if !data.success {
+        return (Status::NotFound, ApiJson(data));
+    }
+    (Status::Ok, ApiJson(data))
+}
+
+#[get("/ups")]
+async fn get_upses_route(
+    cache: &State<Arc<CachedData>>,
+) -> (
+    Status,
+    ApiJson<ApiResponse<Vec<UninterruptiblePowerSupplyData>>>,
+) {
+    let data = cache.get_upses().await;
+    list_response(cache, Category::Ups, data).await
+}
+
+#[get("/ups/summary")]
+async fn get_ups_summary_route(
+    cache: &State<Arc<CachedData>>,
+) -> (Status, ApiJson<ApiResponse<UpsSummary>>) {
+    let data = summarize_upses(&cache.get_upses().await);
+    list_response(cache, Category::Ups, data).await
+}
+
+#[get("/ups/<id>")]
+async fn get_ups_by_hw_id_route(
+    cache: &State<Arc<CachedData>>,
+    id: String,
+) -> (Status, ApiJson<ApiResponse<UninterruptiblePowerSupplyData>>) {
+    let data = cache.get_ups_by_hw_id(id).await;
+    let data = ApiResponse::new(data);
+    if !data.success {
+        return (Status::NotFound, ApiJson(data));
+    }
+    (Status::Ok, ApiJson(data))
+}
+
+#[get("/ups/<id>/clients")]
+async fn get_ups_clients_by_hw_id_route(
+    cache: &State<Arc<CachedData>>,
+    id: String,
+) -> (Status, ApiJson<ApiResponse<Vec<String>>>) {
+    let data = cache.get_ups_by_hw_id(id).await.and_then(|ups| ups.clients);
+    let data = ApiResponse::new(data);
+    if !data.success {
+        return (Status::NotFound, ApiJson(data));
+    }
+    (Status::Ok, ApiJson(data))
+}
+
+#[get("/ups/<id>/history")]
+async fn get_ups_history_route(
+    cache: &State<Arc<CachedData>>,
+    id: String,
+) -> (Status, ApiJson<ApiResponse<Vec<HistorySample<UpsPoint>>>>) {
+    let data = cache.get_ups_history(id).await;
+    let data = ApiResponse::new(data);
+    if !data.success {
+        return (Status::NotFound, ApiJson(data));
+    }
+    (Status::Ok, ApiJson(data))
+}
+
+#[get("/readings")]
+async fn get_readings_route(
+    cache: &State<Arc<CachedData>>,
+) -> (Status, ApiJson<ApiResponse<Vec<Reading>>>) {
+    let data = cache.get_readings().await;
+    list_response(cache, Category::Readings, data).await
+}
+
+#[get("/readings/<id>")]
+async fn get_reading_by_hw_id_route(
+    cache: &State<Arc<CachedData>>,
+    id: String,
+) -> (Status, ApiJson<ApiResponse<Reading>>) {
+    let data = cache.get_reading_by_hw_id(id).await;
+    let data = ApiResponse::new(data);
+    if !data.success {
+        return (Status::NotFound, ApiJson(data));
+    }
+    (Status::Ok, ApiJson(data))
+}
+
+#[get("/changes?<since>&<epoch>")]
+async fn get_changes_route(
+    cache: &State<Arc<CachedData>>,
+    since: Option<u64>,
+    epoch: Option<u64>,
+) -> ApiJson<ApiResponse<Changes>> {
+    ApiJson(ApiResponse::new(Some(
+        cache.get_changes(since.unwrap_or_default(), epoch).await,
+    )))
+}
+
+/// Prometheus text, `None` for `HEAD` requests
+struct Metrics(Option<String>);
+
+impl<'r> Responder<'r, 'static> for Metrics {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let content_type = ContentType::parse_flexible(prometheus::CONTENT_TYPE).unwrap();
+        match self.0 {
+            Some(metrics) => (content_type, metrics).respond_to(request),
+            None => headers_only(content_type),
+        }
+    }
+}
+
+#[get("/metrics")]
+async fn get_metrics_route(cache: &State<Arc<CachedData>>, head: IsHead) -> Metrics {
+    if head.0 {
+        return Metrics(None);
+    }
+    Metrics(Some(render_metrics(
+        &cache.get_temperature_sensors().await,
+        &cache.get_upses().await,
+        &cache.get_readings().await,
+    )))
+}
+
+/// Liveness probe for load balancers, doesn't touch the cache
+#[get("/ping")]
+fn ping_route() -> &'static str {
+    "pong"
+}
+
+#[get("/status/internal")]
+async fn get_internal_status_route(
+    _authorized: Authorized,
+) -> ApiJson<ApiResponse<InternalStatus>> {
+    ApiJson(ApiResponse::new(Some(introspection::snapshot())))
+}
+
+/// Push every sensor and UPS update until the client disconnects
+#[get("/ws")]
+fn live_stream_route(ws: WebSocket, cache: &State<Arc<CachedData>>) -> Channel<'static> {
+    let mut events = cache.subscribe_live();
+    ws.channel(move |mut stream| {
+        Box::pin(async move {
+            loop {
+                tokio::select! {
+                    message = next_message(&mut events) => match message {
+                        Some(message) => stream.send(Message::Text(message)).await?,
+                        None => break,
+                    },
+                    // Pings are answered while reading, anything else from the client is ignored
+                    message = stream.next() => match message {
+                        Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                        Some(Ok(_)) => {}
+                    },
+                }
+            }
+            Ok(())
+        })
+    })
+}
+
+#[get("/version")]
+async fn get_version_route(version: &State<VersionInfo>) -> ApiJson<ApiResponse<VersionInfo>> {
+    ApiJson(ApiResponse::new(Some(version.inner().clone())))
+}
+
+fn rocket(cache: Arc<CachedData>, instance_id: String) -> Rocket<Build> {
+    let rocket = rocket::build()
+        .manage(cache)
+        .manage(VersionInfo::new(instance_id))
+        .attach(HeadRequests)
+        .mount(
+            "/",
+            routes![
+                get_version_route,
+                get_temperature_sensors_route,
+                get_temperature_summary_route,
+                get_temperature_sensor_by_hw_id_route,
+                get_temperature_sensor_by_name_route,
+                get_temperature_history_route,
+                get_upses_route,
+                get_ups_summary_route,
+                get_ups_by_hw_id_route,
+                get_ups_clients_by_hw_id_route,
+                get_ups_history_route,
+                get_readings_route,
+                get_reading_by_hw_id_route,
+                get_changes_route,
+                get_metrics_route,
+                ping_route,
+                get_internal_status_route,
+                live_stream_route
+            ],
+        );
+    #[cfg(feature = "export")]
+    let rocket = super::export::mount_export(rocket);
+    rocket
+}
+
+/// HTTPS is used only if both paths are set, setting just one of them is an error
+fn tls_config(config: &PassiveEndpointConfig) -> Result<Option<TlsConfig>, String> {
+    match (config.get_tls_cert_path(), config.get_tls_key_path()) {
+        (Some(cert_path), Some(key_path)) => Ok(Some(TlsConfig::from_paths(cert_path, key_path))),
+        (None, None) => Ok(None),
+        _ => Err(String::from(
+            "both tls_cert_path and tls_key_path have to be set to enable TLS",
+        )),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn serve(
+    mut shutdown_rx: broadcast::Receiver<()>,
+    cache: Arc<CachedData>,
+    config: PassiveEndpointConfig,
+    wake_on_lan: WakeOnLanConfig,
+    load_shedding: LoadSheddingConfig,
+    fleet: FleetConfig,
+    storage: StorageConfig,
+    instance_id: String,
+    read_only: bool,
+    ready_tx: oneshot::Sender<()>,
+) {
+    let tls = match tls_config(&config) {
+        Ok(tls) => tls,
+        Err(error) => {
+            tracing::error!("Passive endpoint not started: {}", error);
+            return;
+        }
+    };
+    let prepared_rocket = mount_control(
+        rocket(cache, instance_id),
+        config.get_control_token(),
+        wake_on_lan,
+        read_only,
+    );
+    let prepared_rocket = mount_load_shedding(prepared_rocket, load_shedding);
+    let prepared_rocket = mount_fleet(prepared_rocket, fleet);
+    #[cfg(feature = "storage")]
+    let prepared_rocket = super::storage::mount_storage(prepared_rocket, storage, read_only);
+    #[cfg(not(feature = "storage"))]
+    let _ = storage;
+    let prepared_rocket = mount_access_log(prepared_rocket, &config.get_trusted_proxies())
+        .manage(PrettyJson(config.get_pretty_json()))
+        .configure(rocket::Config {
+            port: config.get_port(),
+            // Client address is resolved using trusted_proxies instead of X-Real-IP
+            ip_header: None,
+            tls,
+            shutdown: rocket::config::Shutdown {
+                ctrlc: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        // Liftoff happens once the port is bound
+        .attach(AdHoc::on_liftoff("Ready", |_| {
+            Box::pin(async move {
+                let _ = ready_tx.send(());
+            })
+        }))
+        .launch();
+
+    tokio::select! {
+        _ = prepared_rocket => {},
+        _ = shutdown_rx.recv() => {
+            tracing::trace!("Aborting rocket");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::types::Example, hardware::reading::ReadingsUpdate, passive_endpoint::expiry::Expiry,
+    };
+    use rocket::{
+        http::{ContentType, Status},
+        local::asynchronous::Client,
+        uri,
+    };
+
+    fn test_instance_id() -> String {
+        String::from("00000000-0000-0000-0000-000000000000")
+    }
+
+    #[tokio::test]
+    async fn test_get_version() {
+        let cache = Arc::new(CachedData::default());
+        let client = Client::tracked(rocket(cache, test_instance_id()))
+            .await
+            .unwrap();
+
+        let response = client.get(uri!(super::get_version_route)).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+        let response = response.into_string().await.unwrap();
+        let response: ApiResponse<VersionInfo> = serde_json::from_str(&response).unwrap();
+        let version = response.data.unwrap();
+        assert_eq!(version.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(version.instance_id, test_instance_id());
+    }
+
+    #[tokio::test]
+    async fn test_get_sensors_empty_cache() {
+        let cache = Arc::new(CachedData::default());
+        let client = Client::tracked(rocket(cache.clone(), test_instance_id()))
+            .await
+            .unwrap();
+
+        let response = client
+            .get(uri!(super::get_temperature_sensors_route))
+            .dispatch()
+            .await;
+        // Basic checks
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+        // Inspect JSON response
+        let response = response.into_string().await.unwrap();
+        let response: ApiResponse<Vec<MeasuredTemperature>> =
+            serde_json::from_str(&response).unwrap();
+        assert!(response.success);
+        assert!(response.error.is_none());
+        assert_eq!(response.data.unwrap(), vec![]);
+    }
+
+    #[tokio::test]
+    async fn test_get_sensors_expired() {
+        let config: PassiveEndpointConfig = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "max_age": { "temperature": { "secs": 60, "nanos": 0 } },
+        }))
+        .unwrap();
+        let cache = Arc::new(CachedData::new(Expiry::new(&config)));
+        let mut sensor = MeasuredTemperature::example();
+        let measured_at = chrono::Utc::now() - chrono::Duration::seconds(120);
+        sensor.meta.measured_at = Some(measured_at.to_rfc3339());
+        cache.set_sensors(vec![sensor]).await;
+        let client = Client::tracked(rocket(cache, test_instance_id()))
+            .await
+            .unwrap();
+
+        let response = client
+            .get(uri!(super::get_temperature_sensors_route))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::ServiceUnavailable);
+        let response = response.into_string().await.unwrap();
+        let response: ApiResponse<Vec<MeasuredTemperature>> =
+            serde_json::from_str(&response).unwrap();
+        assert!(!response.success);
+        let sensors = response.data.unwrap();
+        assert_eq!(sensors[0].meta.stale, Some(true));
+        assert!(sensors[0].meta.age_secs.unwrap() >= 120);
+    }
+
+    #[tokio::test]
+    async fn test_get_sensors_with_updated_data() {
+        let cache = Arc::new(CachedData::default());
+        let client = Client::tracked(rocket(cache.clone(), test_instance_id()))
+            .await
+            .unwrap();
+
+        let response = client
+            .get(uri!(super::get_temperature_sensors_route))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+        let sensors = vec![MeasuredTemperature::example()];
+        cache.set_sensors(sensors.clone()).await;
+
+        let response = client
+            .get(uri!(super::get_temperature_sensors_route))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+        let response = response.into_string().await.unwrap();
+        let response: ApiResponse<Vec<MeasuredTemperature>> =
+            serde_json::from_str(&response).unwrap();
+        assert!(response.success);
+        assert!(response.error.is_none());
+        assert_eq!(response.data.unwrap(), sensors);
+    }
+
+    #[tokio::test]
+    async fn test_get_sensor_by_hw_id() {
+        let cache = Arc::new(CachedData::default());
+        let client = Client::tracked(rocket(cache.clone(), test_instance_id()))
+            .await
+            .unwrap();
+
+        let sensors = vec![MeasuredTemperature::example()];
+        cache.set_sensors(sensors.clone()).await;
+
+        let response = client
+            .get(uri!(super::get_temperature_sensor_by_hw_id_route(
+                sensors[0].meta.hw.id.clone()
+            )))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+        let response = response.into_string().await.unwrap();
+        let response: ApiResponse<MeasuredTemperature> = serde_json::from_str(&response).unwrap();
+        assert!(response.success);
+        assert!(response.error.is_none());
+        assert!(response.data.is_some());
+        assert_eq!(response.data.unwrap(), sensors[0]);
+    }
+
+    #[tokio::test]
+    async fn test_get_sensor_by_hw_id_404() {
+        let cache = Arc::new(CachedData::default());
+        let client = Client::tracked(rocket(cache, test_instance_id()))
+            .await
+            .unwrap();
+
+        let response = client
+            .get(uri!(super::get_temperature_sensor_by_hw_id_route(
+                String::from("non-existent-id")
+            )))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+        let response = response.into_string().await.unwrap();
+        let response: ApiResponse<MeasuredTemperature> = serde_json::from_str(&response).unwrap();
+        assert!(!response.success);
+        assert!(response.error.is_some());
+        assert!(response.data.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_sensor_by_name() {
+        let cache = Arc::new(CachedData::default());
+        let client = Client::tracked(rocket(cache.clone(), test_instance_id()))
+            .await
+            .unwrap();
+
+        let mut sensor = MeasuredTemperature::example();
+        sensor.meta.hw.name = Some(String::from("living-room"));
+        cache.set_sensors(vec![sensor.clone()]).await;
+
+        let response = client
+            .get(uri!(super::get_temperature_sensor_by_name_route(
+                String::from("living-room")
+            )))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+        let response = response.into_string().await.unwrap();
+        let response: ApiResponse<MeasuredTemperature> = serde_json::from_str(&response).unwrap();
+        assert!(response.success);
+        assert_eq!(response.data.unwrap(), sensor);
+    }
+
+    #[tokio::test]
+    async fn test_get_sensor_by_name_404() {
+        let cache = Arc::new(CachedData::default());
+        let client = Client::tracked(rocket(cache.clone(), test_instance_id()))
+            .await
+            .unwrap();
+
+        // Sensor without a name can't be found by its id
+        let sensors = vec![MeasuredTemperature::example()];
+        cache.set_sensors(sensors.clone()).await;
+
+        let response = client
+            .get(uri!(super::get_temperature_sensor_by_name_route(
+                sensors[0].meta.hw.id.clone()
+            )))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_get_temperature_summary() {
+        let cache = Arc::new(CachedData::default());
+        let mut sensors = vec![
+            MeasuredTemperature::example(),
+            MeasuredTemperature::example(),
+        ];
+        sensors[0].temperature = Some(21.5);
+        sensors[1].meta.hw.id = String::from("summary");
+        sensors[1].temperature = Some(30.0);
+        cache.set_sensors(sensors).await;
+        let client = Client::tracked(rocket(cache, test_instance_id()))
+            .await
+            .unwrap();
+
+        // Not mistaken for a sensor with "summary" id
+        let response = client
+            .get(uri!(super::get_temperature_summary_route))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = response.into_string().await.unwrap();
+        let response: ApiResponse<TemperatureSummary> = serde_json::from_str(&response).unwrap();
+        let summary = response.data.unwrap();
+        assert_eq!(summary.count, 2);
+        assert_eq!(summary.max, Some(30.0));
+        assert_eq!(summary.min_ids, vec!["fake_hw_id"]);
+    }
+
+    #[tokio::test]
+    async fn test_get_upses_empty_cache() {
+        let cache = Arc::new(CachedData::default());
+        let client = Client::tracked(rocket(cache.clone(), test_instance_id()))
+            .await
+            .unwrap();
+
+        let response = client.get(uri!(super::get_upses_route)).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+        let response = response.into_string().await.unwrap();
+        let response: ApiResponse<Vec<UninterruptiblePowerSupplyData>> =
+            serde_json::from_str(&response).unwrap();
+        assert!(response.success);
+        assert!(response.error.is_none());
+        assert_eq!(response.data.unwrap(), vec![]);
+    }
+
+    #[tokio::test]
+    async fn test_get_upses_with_updated_data() {
+        let cache = Arc::new(CachedData::default());
+        let client = Client::tracked(rocket(cache.clone(), test_instance_id()))
+            .await
+            .unwrap();
+
+        let response = client.get(uri!(super::get_upses_route)).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+        let upses = vec![UninterruptiblePowerSupplyData::example()];
+        cache.set_upses(upses.clone()).await;
+
+        let response = client.get(uri!(super::get_upses_route)).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+        let response = response.into_string().await.unwrap();
+        let response: ApiResponse<Vec<UninterruptiblePowerSupplyData>> =
+            serde_json::from_str(&response).unwrap();
+        assert!(response.success);
+        assert!(response.error.is_none());
+        assert_eq!(response.data.unwrap(), upses);
+    }
+
+    #[tokio::test]
+    async fn test_get_ups_by_hw_id() {
+        let cache = Arc::new(CachedData::default());
+        let client = Client::tracked(rocket(cache.clone(), test_instance_id()))
+            .await
+            .unwrap();
+
+        let upses = vec![UninterruptiblePowerSupplyData::example()];
+        cache.set_upses(upses.clone()).await;
+
+        let response = client
+            .get(uri!(super::get_ups_by_hw_id_route(
+                upses[0].meta.hw.id.clone(),
+            )))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+        let response = response.into_string().await.unwrap();
+        let response: ApiResponse<UninterruptiblePowerSupplyData> =
+            serde_json::from_str(&response).unwrap();
+        assert!(response.success);
+        assert!(response.error.is_none());
+        assert!(response.data.is_some());
+        assert_eq!(response.data.unwrap(), upses[0]);
+    }
+
+    #[tokio::test]
+    async fn test_get_ups_by_hw_id_404() {
+        let cache = Arc::new(CachedData::default());
+        let client = Client::tracked(rocket(cache, test_instance_id()))
+            .await
+            .unwrap();
+
+        let response = client
+            .get(uri!(super::get_ups_by_hw_id_route(String::from(
+                "non-existent-id"
+            ))))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+        let response = response.into_string().await.unwrap();
+        let response: ApiResponse<UninterruptiblePowerSupplyData> =
+            serde_json::from_str(&response).unwrap();
+        assert!(!response.success);
+        assert!(response.error.is_some());
+        assert!(response.data.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_ups_clients_by_hw_id() {
+        let cache = Arc::new(CachedData::default());
+        let client = Client::tracked(rocket(cache.clone(), test_instance_id()))
+            .await
+            .unwrap();
+
+        let mut ups = UninterruptiblePowerSupplyData::example();
+        ups.clients = Some(vec![String::from("192.168.1.10")]);
+        cache.set_upses(vec![ups.clone()]).await;
+
+        let response = client
+            .get(uri!(super::get_ups_clients_by_hw_id_route(
+                ups.meta.hw.id.clone(),
+            )))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+        let response = response.into_string().await.unwrap();
+        let response: ApiResponse<Vec<String>> = serde_json::from_str(&response).unwrap();
+        assert!(response.success);
+        assert_eq!(response.data, ups.clients);
+    }
+
+    #[tokio::test]
+    async fn test_get_ups_clients_by_hw_id_404_when_not_listed() {
+        let cache = Arc::new(CachedData::default());
+        let client = Client::tracked(rocket(cache.clone(), test_instance_id()))
+            .await
+            .unwrap();
+
+        let upses = vec![UninterruptiblePowerSupplyData::example()];
+        cache.set_upses(upses.clone()).await;
+
+        let response = client
+            .get(uri!(super::get_ups_clients_by_hw_id_route(
+                upses[0].meta.hw.id.clone(),
+            )))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_get_readings() {
+        let cache = Arc::new(CachedData::default());
+        let client = Client::tracked(rocket(cache.clone(), test_instance_id()))
+            .await
+            .unwrap();
+
+        let readings = vec![Reading::example()];
+        cache
+            .update_readings(ReadingsUpdate::new("test", readings.clone()))
+            .await;
+
+        let response = client.get(uri!(super::get_readings_route)).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = response.into_string().await.unwrap();
+        let response: ApiResponse<Vec<Reading>> = serde_json::from_str(&response).unwrap();
+        assert_eq!(response.data.unwrap(), readings);
+
+        let response = client
+            .get(uri!(super::get_reading_by_hw_id_route(
+                readings[0].meta.hw.id.clone()
+            )))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client
+            .get(uri!(super::get_reading_by_hw_id_route(String::from(
+                "non-existent-id"
+            ))))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_get_changes_since() {
+        let cache = Arc::new(CachedData::default());
+        let client = Client::tracked(rocket(cache.clone(), test_instance_id()))
+            .await
+            .unwrap();
+
+        cache
+            .set_sensors(vec![MeasuredTemperature::example()])
+            .await;
+        let response = client
+            .get(uri!(super::get_changes_route(Some(0u64), None::<u64>)))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = response.into_string().await.unwrap();
+        let response: ApiResponse<Changes> = serde_json::from_str(&response).unwrap();
+        let first = response.data.unwrap();
+        assert_eq!(first.temperature.len(), 1);
+
+        // Only the UPS is new after the first sync
+        cache
+            .set_upses(vec![UninterruptiblePowerSupplyData::example()])
+            .await;
+        let response = client
+            .get(uri!(super::get_changes_route(
+                Some(first.sequence),
+                Some(first.epoch)
+            )))
+            .dispatch()
+            .await;
+        let response = response.into_string().await.unwrap();
+        let response: ApiResponse<Changes> = serde_json::from_str(&response).unwrap();
+        let second = response.data.unwrap();
+        assert!(second.temperature.is_empty());
+        assert_eq!(second.ups.len(), 1);
+        assert!(second.sequence > first.sequence);
+        assert!(!second.reset);
+    }
+
+    #[tokio::test]
+    async fn test_internal_status_requires_token() {
+        let cache = Arc::new(CachedData::default());
+        let rocket = mount_control(
+            rocket(cache, test_instance_id()),
+            Some(String::from("secret")),
+            WakeOnLanConfig::default(),
+            false,
+        );
+        let client = Client::tracked(rocket).await.unwrap();
+
+        let response = client
+            .get(uri!(super::get_internal_status_route))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Unauthorized);
+
+        let response = client
+            .get(uri!(super::get_internal_status_route))
+            .header(rocket::http::Header::new("Authorization", "Bearer secret"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = response.into_string().await.unwrap();
+        let response: ApiResponse<InternalStatus> = serde_json::from_str(&response).unwrap();
+        assert!(response.success);
+    }
+
+    #[tokio::test]
+    async fn test_get_metrics() {
+        let cache = Arc::new(CachedData::default());
+        let client = Client::tracked(rocket(cache.clone(), test_instance_id()))
+            .await
+            .unwrap();
+        cache
+            .set_sensors(vec![MeasuredTemperature::example()])
+            .await;
+
+        let response = client.get(uri!(super::get_metrics_route)).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let content_type = response.content_type().unwrap();
+        assert_eq!(content_type.sub(), "plain");
+        assert_eq!(content_type.param("version"), Some("0.0.4"));
+        let response = response.into_string().await.unwrap();
+        assert!(response.contains("uds_temperature_celsius{id=\"fake_hw_id\""));
+
+        let response = client.head(uri!(super::get_metrics_route)).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type().unwrap().sub(), "plain");
+    }
+
+    #[tokio::test]
+    async fn test_head_keeps_status() {
+        let cache = Arc::new(CachedData::default());
+        let client = Client::tracked(rocket(cache.clone(), test_instance_id()))
+            .await
+            .unwrap();
+        cache
+            .set_upses(vec![UninterruptiblePowerSupplyData::example()])
+            .await;
+
+        let response = client.head(uri!(super::get_upses_route)).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+        let response = client
+            .head(uri!(super::get_ups_by_hw_id_route(String::from(
+                "non-existent-id"
+            ))))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+
+        let response = client.get(uri!(super::ping_route)).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.into_string().await.unwrap(), "pong");
+    }
+
+    #[test]
+    fn test_tls_config() {
+        let config = |tls: serde_json::Value| -> PassiveEndpointConfig {
+            let mut config = serde_json::to_value(PassiveEndpointConfig::example()).unwrap();
+            config
+                .as_object_mut()
+                .unwrap()
+                .extend(tls.as_object().unwrap().clone());
+            serde_json::from_value(config).unwrap()
+        };
+        assert!(tls_config(&PassiveEndpointConfig::example())
+            .unwrap()
+            .is_none());
+        assert!(tls_config(&config(serde_json::json!({
+            "tls_cert_path": "/etc/uds/cert.pem",
+            "tls_key_path": "/etc/uds/key.pem",
+        })))
+        .unwrap()
+        .is_some());
+        assert!(tls_config(&config(serde_json::json!({
+            "tls_cert_path": "/etc/uds/cert.pem",
+        })))
+        .is_err());
+    }
+}
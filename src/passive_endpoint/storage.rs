@@ -0,0 +1,194 @@
+// Licensed under the Open Software License version 3.0
+use super::{control::Authorized, receiver::ApiResponse, response::ApiJson};
+use crate::storage::{
+    config::StorageConfig,
+    database::StoredMeasurement,
+    query::{delete_stored, query_stored, QueryError},
+};
+use rocket::{delete, get, http::Status, routes, Build, Rocket, State};
+
+type StorageResponse<T> = (Status, ApiJson<ApiResponse<T>>);
+
+fn error_response<T>(error: QueryError) -> StorageResponse<T> {
+    let data = match error.message() {
+        Some(message) => ApiResponse::error(message),
+        None => ApiResponse::new(None),
+    };
+    (Status::new(error.status()), ApiJson(data))
+}
+
+#[get("/storage/<category>/<id>?<since>&<until>&<limit>")]
+async fn get_stored_measurements_route(
+    config: &State<StorageConfig>,
+    category: &str,
+    id: String,
+    since: Option<&str>,
+    until: Option<&str>,
+    limit: Option<usize>,
+) -> StorageResponse<Vec<StoredMeasurement>> {
+    match query_stored(config, category, id, since, until, limit).await {
+        Ok(measurements) => (Status::Ok, ApiJson(ApiResponse::new(Some(measurements)))),
+        Err(error) => error_response(error),
+    }
+}
+
+/// Number of deleted rows
+#[delete("/storage/<category>/<id>?<before>")]
+async fn delete_stored_measurements_route(
+    _authorized: Authorized,
+    config: &State<StorageConfig>,
+    category: &str,
+    id: String,
+    before: Option<&str>,
+) -> StorageResponse<usize> {
+    match delete_stored(config, category, id, before).await {
+        Ok(deleted) => (Status::Ok, ApiJson(ApiResponse::new(Some(deleted)))),
+        Err(error) => error_response(error),
+    }
+}
+
+/// Mount `/storage/<temperature|ups|readings>/<id>` if the module is enabled,
+/// deleting requires the control token and isn't mounted in read-only mode
+pub fn mount_storage(
+    rocket: Rocket<Build>,
+    config: StorageConfig,
+    read_only: bool,
+) -> Rocket<Build> {
+    if !config.is_enabled() {
+        return rocket;
+    }
+    let rocket = rocket
+        .manage(config)
+        .mount("/", routes![get_stored_measurements_route]);
+    match read_only {
+        true => rocket,
+        false => rocket.mount("/", routes![delete_stored_measurements_route]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        passive_endpoint::control::mount_control,
+        storage::{
+            database::Database,
+            rows::{Category, Row},
+        },
+        wake_on_lan::config::WakeOnLanConfig,
+    };
+    use rocket::{http::Header, local::asynchronous::Client};
+
+    fn database() -> (std::path::PathBuf, StorageConfig) {
+        let path =
+            std::env::temp_dir().join(format!("uds-storage-{}.sqlite", uuid::Uuid::new_v4()));
+        let rows: Vec<Row> = (0..3)
+            .map(|second| Row {
+                measured_at: 1672531200000 + second * 1000,
+                category: Category::Temperature,
+                hw_id: String::from("fake_hw_id"),
+                data: format!("{{\"temperature\":{}}}", second),
+            })
+            .collect();
+        Database::open(&path).unwrap().insert(&rows).unwrap();
+        let config: StorageConfig = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "path": path,
+        }))
+        .unwrap();
+        (path, config)
+    }
+
+    fn remove(path: &std::path::Path) {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_stored_measurements() {
+        let (path, config) = database();
+        let client = Client::tracked(mount_storage(rocket::build(), config, false))
+            .await
+            .unwrap();
+
+        let response = client
+            .get("/storage/temperature/fake_hw_id?since=2023-01-01T00:00:01Z&limit=5")
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = response.into_string().await.unwrap();
+        let response: ApiResponse<Vec<StoredMeasurement>> =
+            serde_json::from_str(&response).unwrap();
+        let data = response.data.unwrap();
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[0].data["temperature"], 1);
+
+        let response = client
+            .get("/storage/temperature/fake_hw_id?until=yesterday")
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::BadRequest);
+        let response = client.get("/storage/changes/fake_hw_id").dispatch().await;
+        assert_eq!(response.status(), Status::NotFound);
+        remove(&path);
+    }
+
+    #[tokio::test]
+    async fn test_delete_stored_measurements() {
+        let (path, config) = database();
+        let rocket = mount_control(
+            rocket::build(),
+            Some(String::from("secret")),
+            WakeOnLanConfig::default(),
+            false,
+        );
+        let client = Client::tracked(mount_storage(rocket, config, false))
+            .await
+            .unwrap();
+
+        let uri = "/storage/temperature/fake_hw_id?before=2023-01-01T00:00:01Z";
+        let response = client.delete(uri).dispatch().await;
+        assert_eq!(response.status(), Status::Unauthorized);
+        let response = client
+            .delete(uri)
+            .header(Header::new("Authorization", "Bearer secret"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = response.into_string().await.unwrap();
+        let response: ApiResponse<usize> = serde_json::from_str(&response).unwrap();
+        assert_eq!(response.data, Some(1));
+
+        let response = client
+            .get("/storage/temperature/fake_hw_id")
+            .dispatch()
+            .await;
+        let response = response.into_string().await.unwrap();
+        let response: ApiResponse<Vec<StoredMeasurement>> =
+            serde_json::from_str(&response).unwrap();
+        assert_eq!(response.data.unwrap().len(), 2);
+        remove(&path);
+    }
+
+    #[tokio::test]
+    async fn test_delete_disabled_in_read_only_mode() {
+        let (path, config) = database();
+        let rocket = mount_control(
+            rocket::build(),
+            Some(String::from("secret")),
+            WakeOnLanConfig::default(),
+            true,
+        );
+        let client = Client::tracked(mount_storage(rocket, config, true))
+            .await
+            .unwrap();
+        let response = client
+            .delete("/storage/temperature/fake_hw_id")
+            .header(Header::new("Authorization", "Bearer secret"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+        remove(&path);
+    }
+}
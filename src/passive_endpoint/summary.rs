@@ -0,0 +1,192 @@
+// Licensed under the Open Software License version 3.0
+//! Aggregates of every cached sensor or UPS, so status screens need a single request
+//!
+//! Stale entries (see `max_age`) and ones without a value are left out.
+use crate::{nut::sender::UninterruptiblePowerSupplyData, one_wire::sender::MeasuredTemperature};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemperatureSummary {
+    // Sensors included in the summary
+    pub count: usize,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub mean: Option<f64>,
+    // hw.id of every sensor at min / max
+    pub min_ids: Vec<String>,
+    pub max_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UpsSummary {
+    // UPSes with `ups.load` or `battery.charge`, the mean covers only ones with a load
+    pub count: usize,
+    // Mean and highest `ups.load` (%)
+    pub mean_load: Option<f64>,
+    pub max_load: Option<f64>,
+    pub max_load_ids: Vec<String>,
+    // Lowest `battery.charge` (%)
+    pub min_battery_charge: Option<f64>,
+    pub min_battery_charge_ids: Vec<String>,
+}
+
+/// Values with ids of their entries, non-finite values are skipped
+struct Values(Vec<(String, f64)>);
+
+impl Values {
+    fn new<'a>(values: impl Iterator<Item = (&'a String, Option<f64>)>) -> Self {
+        Self(
+            values
+                .filter_map(|(id, value)| Some((id.clone(), value.filter(|v| v.is_finite())?)))
+                .collect(),
+        )
+    }
+
+    fn min(&self) -> Option<f64> {
+        self.0.iter().map(|(_, value)| *value).reduce(f64::min)
+    }
+
+    fn max(&self) -> Option<f64> {
+        self.0.iter().map(|(_, value)| *value).reduce(f64::max)
+    }
+
+    fn mean(&self) -> Option<f64> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let sum: f64 = self.0.iter().map(|(_, value)| value).sum();
+        Some(sum / self.0.len() as f64)
+    }
+
+    fn has(&self, id: &str) -> bool {
+        self.0.iter().any(|(entry_id, _)| entry_id == id)
+    }
+
+    /// Every id with `value`, there can be ties
+    fn ids_of(&self, value: Option<f64>) -> Vec<String> {
+        self.0
+            .iter()
+            .filter(|(_, v)| Some(*v) == value)
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+}
+
+fn get_number(ups: &UninterruptiblePowerSupplyData, variable: &str) -> Option<f64> {
+    ups.variables.get(variable)?.trim().parse().ok()
+}
+
+pub fn summarize_temperatures(sensors: &[MeasuredTemperature]) -> TemperatureSummary {
+    let temperatures = Values::new(
+        sensors
+            .iter()
+            .filter(|sensor| sensor.meta.stale != Some(true))
+            .map(|sensor| (&sensor.meta.hw.id, sensor.temperature)),
+    );
+    let (min, max) = (temperatures.min(), temperatures.max());
+    TemperatureSummary {
+        count: temperatures.0.len(),
+        min,
+        max,
+        mean: temperatures.mean(),
+        min_ids: temperatures.ids_of(min),
+        max_ids: temperatures.ids_of(max),
+    }
+}
+
+pub fn summarize_upses(upses: &[UninterruptiblePowerSupplyData]) -> UpsSummary {
+    let upses: Vec<&UninterruptiblePowerSupplyData> = upses
+        .iter()
+        .filter(|ups| ups.meta.stale != Some(true))
+        .collect();
+    let loads = Values::new(
+        upses
+            .iter()
+            .map(|ups| (&ups.meta.hw.id, get_number(ups, "ups.load"))),
+    );
+    let charges = Values::new(
+        upses
+            .iter()
+            .map(|ups| (&ups.meta.hw.id, get_number(ups, "battery.charge"))),
+    );
+    let (max_load, min_battery_charge) = (loads.max(), charges.min());
+    let count = upses
+        .iter()
+        .filter(|ups| loads.has(&ups.meta.hw.id) || charges.has(&ups.meta.hw.id))
+        .count();
+    UpsSummary {
+        count,
+        mean_load: loads.mean(),
+        max_load,
+        max_load_ids: loads.ids_of(max_load),
+        min_battery_charge,
+        min_battery_charge_ids: charges.ids_of(min_battery_charge),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::types::Example;
+
+    fn sensor(id: &str, temperature: Option<f64>) -> MeasuredTemperature {
+        let mut sensor = MeasuredTemperature::example();
+        sensor.meta.hw.id = String::from(id);
+        sensor.temperature = temperature;
+        sensor
+    }
+
+    fn ups(id: &str, load: &str, charge: &str) -> UninterruptiblePowerSupplyData {
+        let mut ups = UninterruptiblePowerSupplyData::example();
+        ups.meta.hw.id = String::from(id);
+        ups.variables
+            .insert(String::from("ups.load"), String::from(load));
+        ups.variables
+            .insert(String::from("battery.charge"), String::from(charge));
+        ups
+    }
+
+    #[test]
+    fn test_summarize_temperatures() {
+        let mut stale = sensor("stale", Some(-40.0));
+        stale.meta.stale = Some(true);
+        let summary = summarize_temperatures(&[
+            sensor("a", Some(20.0)),
+            sensor("b", Some(26.0)),
+            sensor("c", Some(20.0)),
+            sensor("missing", None),
+            stale,
+        ]);
+        assert_eq!(summary.count, 3);
+        assert_eq!(summary.min, Some(20.0));
+        assert_eq!(summary.max, Some(26.0));
+        assert_eq!(summary.mean, Some(22.0));
+        assert_eq!(summary.min_ids, vec!["a", "c"]);
+        assert_eq!(summary.max_ids, vec!["b"]);
+    }
+
+    #[test]
+    fn test_summarize_empty() {
+        let summary = summarize_temperatures(&[]);
+        assert_eq!(summary.count, 0);
+        assert_eq!(summary.mean, None);
+        assert!(summary.min_ids.is_empty());
+    }
+
+    #[test]
+    fn test_summarize_upses() {
+        let summary = summarize_upses(&[
+            ups("rack", "40", "100"),
+            ups("desk", "10", "35.5"),
+            ups("broken", "n/a", "80"),
+            ups("offline", "", ""),
+        ]);
+        // Without any value the UPS isn't counted
+        assert_eq!(summary.count, 3);
+        assert_eq!(summary.mean_load, Some(25.0));
+        assert_eq!(summary.max_load, Some(40.0));
+        assert_eq!(summary.max_load_ids, vec!["rack"]);
+        assert_eq!(summary.min_battery_charge, Some(35.5));
+        assert_eq!(summary.min_battery_charge_ids, vec!["desk"]);
+    }
+}
@@ -0,0 +1,42 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExpectedRange {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl ExpectedRange {
+    pub fn contains(&self, value: f64) -> bool {
+        self.min.map_or(true, |min| value >= min) && self.max.map_or(true, |max| value <= max)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct QualityConfig {
+    // hw.id -> expected range of values
+    expected_ranges: Option<HashMap<String, ExpectedRange>>,
+}
+
+impl Example for QualityConfig {
+    fn example() -> Self {
+        Self {
+            expected_ranges: Some(HashMap::from([(
+                String::from("28-00000a0b0c0d"),
+                ExpectedRange {
+                    min: Some(-25.0),
+                    max: Some(-15.0),
+                },
+            )])),
+        }
+    }
+}
+
+impl QualityConfig {
+    pub fn get_expected_ranges(&self) -> HashMap<String, ExpectedRange> {
+        self.expected_ranges.clone().unwrap_or_default()
+    }
+}
@@ -0,0 +1,102 @@
+// Licensed under the Open Software License version 3.0
+use super::config::{ExpectedRange, QualityConfig};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Quality flags attached to readings that have an expected range
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReadingQuality {
+    pub in_range: bool,
+    // Number of out-of-range readings since startup
+    pub violations: u64,
+}
+
+/// Tracks out-of-range readings per hw.id
+#[derive(Debug, Clone, Default)]
+pub struct RangeChecker {
+    expected_ranges: HashMap<String, ExpectedRange>,
+    violations: HashMap<String, u64>,
+    out_of_range: HashSet<String>,
+}
+
+impl RangeChecker {
+    pub fn new(config: &QualityConfig) -> Self {
+        Self {
+            expected_ranges: config.get_expected_ranges(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns `None` if there is no expected range for `id` or no value
+    pub fn check(&mut self, id: &str, value: Option<f64>) -> Option<ReadingQuality> {
+        let range = self.expected_ranges.get(id)?;
+        let value = value?;
+        let in_range = range.contains(value);
+        let violations = self.violations.entry(String::from(id)).or_default();
+        if in_range {
+            if self.out_of_range.remove(id) {
+                tracing::info!("{} is back in expected range: {}", id, value);
+            }
+        } else {
+            *violations += 1;
+            // Alert only when entering out-of-range state
+            if self.out_of_range.insert(String::from(id)) {
+                tracing::warn!(
+                    "{} is out of expected range {:?}..{:?}: {}",
+                    id,
+                    range.min,
+                    range.max,
+                    value
+                );
+            }
+        }
+        Some(ReadingQuality {
+            in_range,
+            violations: *violations,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn freezer_checker() -> RangeChecker {
+        let config: QualityConfig =
+            serde_json::from_str(r#"{"expected_ranges":{"freezer":{"min":-25.0,"max":-15.0}}}"#)
+                .unwrap();
+        RangeChecker::new(&config)
+    }
+
+    #[test]
+    fn test_ignores_unknown_ids_and_missing_values() {
+        let mut checker = freezer_checker();
+        assert_eq!(checker.check("fridge", Some(100.0)), None);
+        assert_eq!(checker.check("freezer", None), None);
+    }
+
+    #[test]
+    fn test_counts_violations() {
+        let mut checker = freezer_checker();
+        let quality = checker.check("freezer", Some(-20.0)).unwrap();
+        assert!(quality.in_range);
+        assert_eq!(quality.violations, 0);
+        let quality = checker.check("freezer", Some(-10.0)).unwrap();
+        assert!(!quality.in_range);
+        assert_eq!(quality.violations, 1);
+        checker.check("freezer", Some(-30.0));
+        let quality = checker.check("freezer", Some(-15.0)).unwrap();
+        assert!(quality.in_range);
+        assert_eq!(quality.violations, 2);
+    }
+
+    #[test]
+    fn test_open_ended_range() {
+        let range = ExpectedRange {
+            min: None,
+            max: Some(40.0),
+        };
+        assert!(range.contains(-100.0));
+        assert!(!range.contains(40.1));
+    }
+}
@@ -0,0 +1,108 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_FILE: &str = "/var/lib/universal-data-source/recording.jsonl";
+// Not the recording, so enabling both doesn't replay a file that is being replaced
+const DEFAULT_REPLAY_FILE: &str = "/var/lib/universal-data-source/replay.jsonl";
+
+// Every broadcast of sources appended to a file, replaced on every start
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordConfig {
+    enabled: Option<bool>,
+    file: Option<String>,
+    // Bytes, recording stops once the file would grow larger
+    max_size: Option<u64>,
+}
+
+impl Default for RecordConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            file: Some(String::from(DEFAULT_FILE)),
+            max_size: Some(100 * 1024 * 1024),
+        }
+    }
+}
+
+impl Example for RecordConfig {
+    fn example() -> Self {
+        Self::default()
+    }
+}
+
+impl RecordConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_file(&self) -> String {
+        self.file
+            .clone()
+            .unwrap_or_else(|| String::from(DEFAULT_FILE))
+    }
+
+    pub fn get_max_size(&self) -> u64 {
+        self.max_size.unwrap_or(100 * 1024 * 1024)
+    }
+}
+
+// Recorded broadcasts sent again as if they came from sources
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayConfig {
+    enabled: Option<bool>,
+    file: Option<String>,
+    // 2.0 replays twice as fast as recorded
+    speed: Option<f64>,
+    // Start over at the end of the file
+    repeat: Option<bool>,
+    // Keep recorded measured_at instead of replacing it with the time of replay
+    keep_timestamps: Option<bool>,
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            file: Some(String::from(DEFAULT_REPLAY_FILE)),
+            speed: Some(1.0),
+            repeat: Some(false),
+            keep_timestamps: Some(false),
+        }
+    }
+}
+
+impl Example for ReplayConfig {
+    fn example() -> Self {
+        Self {
+            speed: Some(10.0),
+            ..Self::default()
+        }
+    }
+}
+
+impl ReplayConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_file(&self) -> String {
+        self.file
+            .clone()
+            .unwrap_or_else(|| String::from(DEFAULT_REPLAY_FILE))
+    }
+
+    pub fn get_speed(&self) -> f64 {
+        self.speed
+            .filter(|speed| speed.is_finite() && *speed > 0.0)
+            .unwrap_or(1.0)
+    }
+
+    pub fn get_repeat(&self) -> bool {
+        self.repeat.unwrap_or_default()
+    }
+
+    pub fn get_keep_timestamps(&self) -> bool {
+        self.keep_timestamps.unwrap_or_default()
+    }
+}
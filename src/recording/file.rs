@@ -0,0 +1,126 @@
+// Licensed under the Open Software License version 3.0
+//! Recording format, one JSON object per line with a single broadcast
+//!
+//! `{"offset_ms": 1500, "one_wire": [...]}` was broadcast 1.5 s after the recording started.
+//! Lines are self-contained, so a recording cut short by a crash or `max_size` is still valid.
+use crate::{
+    hardware::{reading::ReadingsUpdate, types::HardwareMetadata},
+    nut::sender::UpsUpdate,
+    one_wire::sender::MeasuredTemperature,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Message of one of the source channels
+#[derive(Debug, Clone, PartialEq)]
+pub enum Broadcast {
+    OneWire(Vec<MeasuredTemperature>),
+    UpsMonitoring(UpsUpdate),
+    Readings(ReadingsUpdate),
+}
+
+impl Broadcast {
+    /// Replace every `measured_at` with `timestamp`, so replayed data isn't stale
+    pub fn restamp(&mut self, timestamp: &str) {
+        let metas: Vec<&mut HardwareMetadata> = match self {
+            Broadcast::OneWire(sensors) => {
+                sensors.iter_mut().map(|sensor| &mut sensor.meta).collect()
+            }
+            Broadcast::UpsMonitoring(update) => {
+                update.upses.iter_mut().map(|ups| &mut ups.meta).collect()
+            }
+            Broadcast::Readings(update) => update
+                .readings
+                .iter_mut()
+                .map(|reading| &mut reading.meta)
+                .collect(),
+        };
+        for meta in metas {
+            if meta.measured_at.is_some() {
+                meta.measured_at = Some(String::from(timestamp));
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    // Milliseconds since the recording started
+    pub offset_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    one_wire: Option<Vec<MeasuredTemperature>>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    ups_monitoring: Option<UpsUpdate>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    readings: Option<ReadingsUpdate>,
+}
+
+impl Entry {
+    pub fn new(offset: Duration, broadcast: Broadcast) -> Self {
+        let mut entry = Self {
+            offset_ms: offset.as_millis() as u64,
+            one_wire: None,
+            ups_monitoring: None,
+            readings: None,
+        };
+        match broadcast {
+            Broadcast::OneWire(sensors) => entry.one_wire = Some(sensors),
+            Broadcast::UpsMonitoring(update) => entry.ups_monitoring = Some(update),
+            Broadcast::Readings(update) => entry.readings = Some(update),
+        }
+        entry
+    }
+
+    pub fn get_offset(&self) -> Duration {
+        Duration::from_millis(self.offset_ms)
+    }
+
+    /// Usually exactly one, entries written by hand may have more or none
+    pub fn into_broadcasts(self) -> Vec<Broadcast> {
+        let one_wire = self.one_wire.map(Broadcast::OneWire);
+        let ups_monitoring = self.ups_monitoring.map(Broadcast::UpsMonitoring);
+        let readings = self.readings.map(Broadcast::Readings);
+        [one_wire, ups_monitoring, readings]
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::types::Example, hardware::reading::Reading};
+
+    #[test]
+    fn test_entry_format() {
+        let entry = Entry::new(
+            Duration::from_millis(1500),
+            Broadcast::Readings(ReadingsUpdate::new("test", vec![Reading::example()])),
+        );
+        let json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(json["offset_ms"], 1500);
+        assert_eq!(json["readings"]["publisher"], "test");
+        assert!(json.get("one_wire").is_none());
+        let entry: Entry = serde_json::from_value(json).unwrap();
+        assert_eq!(entry.get_offset(), Duration::from_millis(1500));
+        assert_eq!(entry.into_broadcasts().len(), 1);
+    }
+
+    #[test]
+    fn test_restamp() {
+        let mut sensor = MeasuredTemperature::example();
+        sensor.meta.measured_at = Some(String::from("2023-01-01T00:00:00+00:00"));
+        let mut broadcast = Broadcast::OneWire(vec![sensor, MeasuredTemperature::example()]);
+        broadcast.restamp("2024-01-01T00:00:00+00:00");
+        let Broadcast::OneWire(sensors) = broadcast else {
+            unreachable!();
+        };
+        assert_eq!(
+            sensors[0].meta.measured_at.as_deref(),
+            Some("2024-01-01T00:00:00+00:00")
+        );
+        // Missing timestamps stay missing
+        assert_eq!(sensors[1].meta.measured_at, None);
+    }
+}
@@ -0,0 +1,5 @@
+// Licensed under the Open Software License version 3.0
+pub mod config;
+mod file;
+pub mod player;
+pub mod recorder;
@@ -0,0 +1,182 @@
+// Licensed under the Open Software License version 3.0
+use super::{
+    config::ReplayConfig,
+    file::{Broadcast, Entry},
+};
+use crate::{
+    bandwidth::limiter, hardware::reading::ReadingsUpdate, introspection, nut::sender::UpsUpdate,
+    one_wire::sender::MeasuredTemperature,
+};
+use chrono::Utc;
+use tokio::{
+    fs::File,
+    io::{AsyncBufReadExt, BufReader},
+    sync::broadcast,
+    time::{sleep_until, Instant},
+};
+
+/// Channels of sources, replayed broadcasts look the same to sinks
+#[derive(Debug, Clone)]
+pub struct Channels {
+    pub one_wire_tx: broadcast::Sender<Vec<MeasuredTemperature>>,
+    pub ups_monitoring_tx: broadcast::Sender<UpsUpdate>,
+    pub readings_tx: broadcast::Sender<ReadingsUpdate>,
+}
+
+impl Channels {
+    fn send(&self, broadcast: Broadcast) {
+        match broadcast {
+            Broadcast::OneWire(sensors) if self.one_wire_tx.receiver_count() > 0 => {
+                self.one_wire_tx.send(sensors).unwrap();
+                introspection::observe_channel("one_wire", &self.one_wire_tx);
+            }
+            Broadcast::UpsMonitoring(update) if self.ups_monitoring_tx.receiver_count() > 0 => {
+                self.ups_monitoring_tx.send(update).unwrap();
+                introspection::observe_channel("ups_monitoring", &self.ups_monitoring_tx);
+            }
+            Broadcast::Readings(update) if self.readings_tx.receiver_count() > 0 => {
+                self.readings_tx.send(update).unwrap();
+                introspection::observe_channel("readings", &self.readings_tx);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Send every entry of the file at its offset, `false` if interrupted by shutdown
+async fn replay_file(
+    shutdown_rx: &mut broadcast::Receiver<()>,
+    config: &ReplayConfig,
+    channels: &Channels,
+) -> std::io::Result<bool> {
+    let path = config.get_file();
+    let mut lines = BufReader::new(File::open(&path).await?).lines();
+    let started_at = Instant::now();
+    let mut line_number = 0;
+    while let Some(line) = lines.next_line().await? {
+        line_number += 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: Entry = match serde_json::from_str(&line) {
+            Ok(entry) => entry,
+            Err(error) => {
+                tracing::warn!("Skipping line {} of {}: {}", line_number, path, error);
+                continue;
+            }
+        };
+        let due = started_at + entry.get_offset().div_f64(config.get_speed());
+        tokio::select! {
+            _ = sleep_until(due) => {}
+            _ = shutdown_rx.recv() => return Ok(false),
+        }
+        // Counted by the size of the recorded line, replays are usually bulk traffic
+        limiter::acquire("replay", line.len()).await;
+        introspection::mark_iteration("replay");
+        let timestamp = Utc::now().to_rfc3339();
+        for mut broadcast in entry.into_broadcasts() {
+            if !config.get_keep_timestamps() {
+                broadcast.restamp(&timestamp);
+            }
+            channels.send(broadcast);
+        }
+    }
+    Ok(true)
+}
+
+pub async fn start_replay_loop(
+    mut shutdown_rx: broadcast::Receiver<()>,
+    config: ReplayConfig,
+    channels: Channels,
+) {
+    // Check if module is enabled
+    if !config.is_enabled() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+    tracing::info!(
+        "Replaying {} at {}x speed",
+        config.get_file(),
+        config.get_speed()
+    );
+    let _task = introspection::task_started("replay");
+    loop {
+        match replay_file(&mut shutdown_rx, &config, &channels).await {
+            Ok(true) if config.get_repeat() => {
+                tracing::debug!("Replaying {} again", config.get_file());
+            }
+            Ok(true) => {
+                tracing::info!("Finished replaying {}", config.get_file());
+                break;
+            }
+            Ok(false) => {
+                tracing::trace!("Shutting down replay loop");
+                break;
+            }
+            Err(error) => {
+                tracing::error!("Failed to replay {}: {}", config.get_file(), error);
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::types::Example, nut::sender::UninterruptiblePowerSupplyData};
+    use std::time::Duration;
+
+    fn channels() -> Channels {
+        Channels {
+            one_wire_tx: broadcast::channel(16).0,
+            ups_monitoring_tx: broadcast::channel(16).0,
+            readings_tx: broadcast::channel(16).0,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replay_at_speed() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("recording.jsonl");
+        let mut sensor = MeasuredTemperature::example();
+        sensor.meta.measured_at = Some(String::from("2023-01-01T00:00:00+00:00"));
+        let entries = [
+            Entry::new(Duration::ZERO, Broadcast::OneWire(vec![sensor.clone()])),
+            Entry::new(
+                Duration::from_secs(10),
+                Broadcast::UpsMonitoring(UpsUpdate::new(
+                    "test",
+                    vec![UninterruptiblePowerSupplyData::example()],
+                )),
+            ),
+        ];
+        let mut recording: Vec<String> = entries
+            .iter()
+            .map(|entry| serde_json::to_string(entry).unwrap())
+            .collect();
+        recording.insert(1, String::from("not json"));
+        std::fs::write(&path, recording.join("\n")).unwrap();
+
+        let config: ReplayConfig = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "file": path,
+            "speed": 2.0,
+        }))
+        .unwrap();
+        let channels = channels();
+        let mut one_wire_rx = channels.one_wire_tx.subscribe();
+        let mut ups_monitoring_rx = channels.ups_monitoring_tx.subscribe();
+        let (_shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let started_at = Instant::now();
+        let replay = tokio::spawn(start_replay_loop(shutdown_rx, config, channels));
+
+        let sensors = one_wire_rx.recv().await.unwrap();
+        assert_ne!(sensors[0].meta.measured_at, sensor.meta.measured_at);
+        ups_monitoring_rx.recv().await.unwrap();
+        // 10 s into the recording at twice the speed
+        let elapsed = started_at.elapsed();
+        assert!(elapsed >= Duration::from_secs(5) && elapsed < Duration::from_secs(10));
+        replay.await.unwrap();
+    }
+}
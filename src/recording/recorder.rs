@@ -0,0 +1,154 @@
+// Licensed under the Open Software License version 3.0
+use super::{
+    config::{RecordConfig, ReplayConfig},
+    file::{Broadcast, Entry},
+};
+use crate::{
+    hardware::reading::ReadingsUpdate, introspection, nut::sender::UpsUpdate,
+    one_wire::sender::MeasuredTemperature, self_metrics::lag::recv_counting_lag,
+};
+use std::{io, path::Path, time::Instant};
+use tokio::{fs::File, io::AsyncWriteExt, sync::broadcast};
+
+/// Appends entries to a new file, every line is written at once
+struct Recorder {
+    file: File,
+    started_at: Instant,
+    size: u64,
+    max_size: u64,
+}
+
+impl Recorder {
+    /// Replaces a previous recording
+    async fn create(path: &Path, max_size: u64) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        Ok(Self {
+            file: File::create(path).await?,
+            started_at: Instant::now(),
+            size: 0,
+            max_size,
+        })
+    }
+
+    /// `false` if the entry doesn't fit within `max_size`
+    async fn write(&mut self, broadcast: Broadcast) -> io::Result<bool> {
+        let entry = Entry::new(self.started_at.elapsed(), broadcast);
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        if self.size + line.len() as u64 > self.max_size {
+            return Ok(false);
+        }
+        self.file.write_all(&line).await?;
+        self.size += line.len() as u64;
+        Ok(true)
+    }
+}
+
+pub async fn start_recorder_loop(
+    mut shutdown_rx: broadcast::Receiver<()>,
+    config: RecordConfig,
+    replay: ReplayConfig,
+    mut one_wire_rx: broadcast::Receiver<Vec<MeasuredTemperature>>,
+    mut ups_monitoring_rx: broadcast::Receiver<UpsUpdate>,
+    mut readings_rx: broadcast::Receiver<ReadingsUpdate>,
+) {
+    // Check if module is enabled
+    if !config.is_enabled() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+    let path = config.get_file();
+    // Creating the recording would truncate the file being replayed
+    if replay.is_enabled() && replay.get_file() == path {
+        tracing::error!("Refusing to record to {}, it's being replayed", path);
+        return;
+    }
+    let mut recorder = match Recorder::create(Path::new(&path), config.get_max_size()).await {
+        Ok(recorder) => recorder,
+        Err(error) => {
+            tracing::error!("Failed to create recording {}: {}", path, error);
+            return;
+        }
+    };
+    tracing::info!("Recording every broadcast to {}", path);
+    let _task = introspection::task_started("recorder");
+    loop {
+        let broadcast = tokio::select! {
+            Ok(sensors) = recv_counting_lag(&mut one_wire_rx) => Broadcast::OneWire(sensors),
+            Ok(update) = recv_counting_lag(&mut ups_monitoring_rx) => Broadcast::UpsMonitoring(update),
+            Ok(update) = recv_counting_lag(&mut readings_rx) => Broadcast::Readings(update),
+            _ = shutdown_rx.recv() => {
+                tracing::trace!("Shutting down recorder loop");
+                break;
+            }
+        };
+        introspection::mark_iteration("recorder");
+        match recorder.write(broadcast).await {
+            Ok(true) => {}
+            Ok(false) => {
+                tracing::warn!("Recording {} reached max_size, stopped recording", path);
+                break;
+            }
+            Err(error) => {
+                tracing::error!("Failed to write recording {}: {}", path, error);
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::types::Example;
+
+    #[tokio::test]
+    async fn test_max_size() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("nested").join("recording.jsonl");
+        let mut recorder = Recorder::create(&path, 1024).await.unwrap();
+        let broadcast = Broadcast::OneWire(vec![MeasuredTemperature::example()]);
+        let mut written = 0;
+        while recorder.write(broadcast.clone()).await.unwrap() {
+            written += 1;
+        }
+        assert!(written > 0);
+        let recording = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(recording.lines().count(), written);
+        assert!(recording.len() <= 1024);
+    }
+
+    #[tokio::test]
+    async fn test_refuse_replayed_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("recording.jsonl");
+        std::fs::write(&path, "{\"offset_ms\": 0}\n").unwrap();
+        let record: RecordConfig = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "file": path,
+        }))
+        .unwrap();
+        let replay: ReplayConfig = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "file": path,
+        }))
+        .unwrap();
+        let (_shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        // Returns right away instead of recording until shutdown
+        start_recorder_loop(
+            shutdown_rx,
+            record,
+            replay,
+            broadcast::channel(1).1,
+            broadcast::channel(1).1,
+            broadcast::channel(1).1,
+        )
+        .await;
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "{\"offset_ms\": 0}\n"
+        );
+    }
+}
@@ -0,0 +1,66 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedisSinkConfig {
+    enabled: Option<bool>,
+    url: Option<String>,
+    // Prepended to every key and channel, ex. "uds:temperature:<id>"
+    key_prefix: Option<String>,
+    // Keys expire if not refreshed, so consumers don't read stale data
+    ttl: Option<Duration>,
+    // Also publish every update on Pub/Sub channels
+    publish: Option<bool>,
+}
+
+impl Default for RedisSinkConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            url: Some(String::from("redis://127.0.0.1/")),
+            key_prefix: Some(String::from("uds")),
+            ttl: Some(Duration::from_secs(60)),
+            publish: Some(false),
+        }
+    }
+}
+
+impl Example for RedisSinkConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(false),
+            url: Some(String::from("redis://127.0.0.1/")),
+            key_prefix: Some(String::from("uds")),
+            ttl: Some(Duration::from_secs(60)),
+            publish: Some(true),
+        }
+    }
+}
+
+impl RedisSinkConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_url(&self) -> String {
+        self.url
+            .clone()
+            .unwrap_or_else(|| String::from("redis://127.0.0.1/"))
+    }
+
+    pub fn get_key_prefix(&self) -> String {
+        self.key_prefix
+            .clone()
+            .unwrap_or_else(|| String::from("uds"))
+    }
+
+    pub fn get_ttl(&self) -> Duration {
+        self.ttl.unwrap_or(Duration::from_secs(60))
+    }
+
+    pub fn get_publish(&self) -> bool {
+        self.publish.unwrap_or_default()
+    }
+}
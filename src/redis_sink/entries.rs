@@ -0,0 +1,108 @@
+// Licensed under the Open Software License version 3.0
+use crate::{
+    hardware::reading::Reading, nut::sender::UninterruptiblePowerSupplyData,
+    one_wire::sender::MeasuredTemperature,
+};
+
+/// Redis hash with its key and fields
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashEntry {
+    pub key: String,
+    pub fields: Vec<(String, String)>,
+}
+
+pub fn temperature_entries(prefix: &str, sensors: &[MeasuredTemperature]) -> Vec<HashEntry> {
+    sensors
+        .iter()
+        .map(|sensor| {
+            let mut fields = Vec::new();
+            if let Some(temperature) = sensor.temperature {
+                fields.push((String::from("temperature"), temperature.to_string()));
+            }
+            if let Some(resolution) = sensor.resolution {
+                fields.push((String::from("resolution"), resolution.to_string()));
+            }
+            if let Some(name) = &sensor.meta.hw.name {
+                fields.push((String::from("name"), name.clone()));
+            }
+            HashEntry {
+                key: format!("{}:temperature:{}", prefix, sensor.meta.hw.id),
+                fields,
+            }
+        })
+        .collect()
+}
+
+pub fn ups_entries(prefix: &str, upses: &[UninterruptiblePowerSupplyData]) -> Vec<HashEntry> {
+    upses
+        .iter()
+        .map(|ups| {
+            let mut fields: Vec<(String, String)> = ups
+                .variables
+                .iter()
+                .map(|(variable, value)| (variable.clone(), value.clone()))
+                .collect();
+            // Stable field order makes HSET deterministic
+            fields.sort();
+            HashEntry {
+                key: format!("{}:ups:{}", prefix, ups.meta.hw.id),
+                fields,
+            }
+        })
+        .collect()
+}
+
+pub fn reading_entries(prefix: &str, publisher: &str, readings: &[Reading]) -> Vec<HashEntry> {
+    readings
+        .iter()
+        .map(|reading| HashEntry {
+            key: format!("{}:readings:{}:{}", prefix, publisher, reading.meta.hw.id),
+            fields: reading
+                .values
+                .iter()
+                .map(|(key, value)| (key.clone(), value.to_string()))
+                .collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::types::Example;
+
+    #[test]
+    fn test_temperature_entries() {
+        let entries = temperature_entries("uds", &[MeasuredTemperature::example()]);
+        assert_eq!(
+            entries,
+            vec![HashEntry {
+                key: String::from("uds:temperature:fake_hw_id"),
+                fields: vec![
+                    (String::from("temperature"), String::from("0")),
+                    (String::from("resolution"), String::from("12")),
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_ups_entries() {
+        let entries = ups_entries("uds", &[UninterruptiblePowerSupplyData::example()]);
+        assert_eq!(entries[0].key, "uds:ups:fake_hw_id");
+        assert_eq!(
+            entries[0].fields[0],
+            (String::from("battery.charge"), String::from("100"))
+        );
+    }
+
+    #[test]
+    fn test_reading_entries() {
+        let entries = reading_entries("uds", "self_metrics", &[Reading::example()]);
+        assert_eq!(entries[0].key, "uds:readings:self_metrics:fake_hw_id");
+        assert_eq!(
+            entries[0].fields,
+            vec![(String::from("rss_bytes"), String::from("1024"))]
+        );
+    }
+}
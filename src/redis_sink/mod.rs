@@ -0,0 +1,6 @@
+// Licensed under the Open Software License version 3.0
+pub mod config;
+// Only used by the redis feature and tests
+#[cfg_attr(not(feature = "redis"), allow(dead_code))]
+mod entries;
+pub mod writer;
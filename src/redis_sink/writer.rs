@@ -0,0 +1,197 @@
+// Licensed under the Open Software License version 3.0
+use super::config::RedisSinkConfig;
+use crate::{
+    hardware::reading::ReadingsUpdate, nut::sender::UpsUpdate,
+    one_wire::sender::MeasuredTemperature,
+};
+use tokio::sync::broadcast;
+
+#[cfg(feature = "redis")]
+mod client {
+    use super::super::entries::{reading_entries, temperature_entries, ups_entries, HashEntry};
+    use super::*;
+    use crate::{
+        bandwidth,
+        dedup_log::{info_resolved, warn_deduplicated},
+        introspection,
+        nut::sender::UpsesByPublisher,
+        self_metrics::lag::recv_counting_lag,
+    };
+    use redis::{aio::ConnectionManager, AsyncCommands, RedisResult};
+    use serde::Serialize;
+    use std::{cmp::min, time::Duration};
+
+    // Backoff of the first connection, ConnectionManager handles later reconnects itself
+    const INITIAL_CONNECT_DELAY: Duration = Duration::from_secs(1);
+    const MAX_CONNECT_DELAY: Duration = Duration::from_secs(60);
+
+    /// Keep trying until connected, `None` on shutdown
+    async fn connect(
+        shutdown_rx: &mut broadcast::Receiver<()>,
+        client: redis::Client,
+    ) -> Option<ConnectionManager> {
+        let mut delay = INITIAL_CONNECT_DELAY;
+        loop {
+            match ConnectionManager::new(client.clone()).await {
+                Ok(connection) => {
+                    info_resolved!("redis_sink:connect", "Connected to Redis");
+                    return Some(connection);
+                }
+                Err(error) => {
+                    warn_deduplicated!(
+                        "redis_sink:connect",
+                        "Failed to connect to Redis, retrying in {:?}: {}",
+                        delay,
+                        error
+                    );
+                }
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = shutdown_rx.recv() => return None,
+            }
+            delay = min(delay.saturating_mul(2), MAX_CONNECT_DELAY);
+        }
+    }
+
+    /// Replace hashes atomically and refresh their TTL
+    async fn write_entries(
+        connection: &mut ConnectionManager,
+        entries: &[HashEntry],
+        ttl: Duration,
+    ) -> RedisResult<()> {
+        // Close enough to the size of the commands on the wire
+        let bytes = entries
+            .iter()
+            .flat_map(|entry| &entry.fields)
+            .map(|(field, value)| field.len() + value.len())
+            .sum::<usize>()
+            + entries.iter().map(|entry| entry.key.len()).sum::<usize>();
+        bandwidth::limiter::acquire("redis_sink", bytes).await;
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for entry in entries {
+            // Remove fields that are no longer reported
+            pipe.del(&entry.key).ignore();
+            if !entry.fields.is_empty() {
+                pipe.hset_multiple(&entry.key, &entry.fields).ignore();
+                pipe.expire(&entry.key, ttl.as_secs().max(1) as usize)
+                    .ignore();
+            }
+        }
+        pipe.query_async(connection).await
+    }
+
+    async fn publish<T>(
+        connection: &mut ConnectionManager,
+        channel: &str,
+        value: &T,
+    ) -> RedisResult<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let json = serde_json::to_string(value).unwrap_or_default();
+        bandwidth::limiter::acquire("redis_sink", json.len()).await;
+        connection.publish(channel, json).await
+    }
+
+    fn report(result: RedisResult<()>) {
+        if let Err(error) = result {
+            tracing::warn!("Redis write failed: {}", error);
+        }
+    }
+
+    pub async fn run(
+        mut shutdown_rx: broadcast::Receiver<()>,
+        config: RedisSinkConfig,
+        mut one_wire_rx: broadcast::Receiver<Vec<MeasuredTemperature>>,
+        mut ups_monitoring_rx: broadcast::Receiver<UpsUpdate>,
+        mut readings_rx: broadcast::Receiver<ReadingsUpdate>,
+    ) {
+        let client = match redis::Client::open(config.get_url()) {
+            Ok(client) => client,
+            Err(error) => {
+                tracing::error!("Invalid Redis url: {}", error);
+                return;
+            }
+        };
+        // Reconnects automatically after the first successful connection
+        let Some(mut connection) = connect(&mut shutdown_rx, client).await else {
+            tracing::trace!("Shutting down Redis sink loop");
+            return;
+        };
+        let prefix = config.get_key_prefix();
+        let ttl = config.get_ttl();
+        let publish_updates = config.get_publish();
+        // Every UPS publisher, so updates on the channel list all of them
+        let mut upses = UpsesByPublisher::default();
+        tracing::debug!("Starting Redis sink loop");
+        let _task = introspection::task_started("redis_sink");
+        loop {
+            introspection::mark_iteration("redis_sink");
+            tokio::select! {
+                Ok(sensors) = recv_counting_lag(&mut one_wire_rx) => {
+                    let entries = temperature_entries(&prefix, &sensors);
+                    report(write_entries(&mut connection, &entries, ttl).await);
+                    if publish_updates {
+                        let channel = format!("{}:temperature", prefix);
+                        report(publish(&mut connection, &channel, &sensors).await);
+                    }
+                }
+                Ok(update) = recv_counting_lag(&mut ups_monitoring_rx) => {
+                    let entries = ups_entries(&prefix, &update.upses);
+                    report(write_entries(&mut connection, &entries, ttl).await);
+                    upses.update(update);
+                    if publish_updates {
+                        let channel = format!("{}:ups", prefix);
+                        report(publish(&mut connection, &channel, &upses.all()).await);
+                    }
+                }
+                Ok(update) = recv_counting_lag(&mut readings_rx) => {
+                    let entries = reading_entries(&prefix, &update.publisher, &update.readings);
+                    report(write_entries(&mut connection, &entries, ttl).await);
+                    if publish_updates {
+                        let channel = format!("{}:readings", prefix);
+                        report(publish(&mut connection, &channel, &update).await);
+                    }
+                }
+                _ = shutdown_rx.recv() => {
+                    tracing::trace!("Shutting down Redis sink loop");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+pub async fn start_redis_sink_loop(
+    shutdown_rx: broadcast::Receiver<()>,
+    config: RedisSinkConfig,
+    one_wire_rx: broadcast::Receiver<Vec<MeasuredTemperature>>,
+    ups_monitoring_rx: broadcast::Receiver<UpsUpdate>,
+    readings_rx: broadcast::Receiver<ReadingsUpdate>,
+) {
+    // Check if module is enabled
+    if !config.is_enabled() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+
+    #[cfg(feature = "redis")]
+    client::run(
+        shutdown_rx,
+        config,
+        one_wire_rx,
+        ups_monitoring_rx,
+        readings_rx,
+    )
+    .await;
+
+    #[cfg(not(feature = "redis"))]
+    {
+        let _ = (shutdown_rx, one_wire_rx, ups_monitoring_rx, readings_rx);
+        tracing::error!(
+            "Redis sink is enabled in config but this binary was built without redis feature"
+        );
+    }
+}
@@ -0,0 +1,104 @@
+// Licensed under the Open Software License version 3.0
+use crate::{
+    config::types::Example,
+    hardware::types::{HardwareMetadata, HardwareRelation},
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelationConfig {
+    // hw.id of both related devices
+    pub from: String,
+    pub to: String,
+    // Free-form, ex. "battery_room"
+    pub kind: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct RelationsConfig {
+    links: Option<Vec<RelationConfig>>,
+}
+
+impl Example for RelationsConfig {
+    fn example() -> Self {
+        Self {
+            links: Some(vec![RelationConfig {
+                from: String::from("28-00000a0b0c0d"),
+                to: String::from("[ups1]ups-monitor@localhost:3493"),
+                kind: String::from("battery_room"),
+            }]),
+        }
+    }
+}
+
+impl RelationsConfig {
+    /// Relations of `id` in both directions, pointing at the other device
+    pub fn get_relations(&self, id: &str) -> Vec<HardwareRelation> {
+        self.links
+            .iter()
+            .flatten()
+            .filter_map(|link| {
+                let other = match (link.from == id, link.to == id) {
+                    (true, _) => &link.to,
+                    (_, true) => &link.from,
+                    _ => return None,
+                };
+                Some(HardwareRelation {
+                    id: other.clone(),
+                    kind: link.kind.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Set `relations` of `meta`, omitted if there are none
+    pub fn annotate(&self, meta: &mut HardwareMetadata) {
+        let relations = self.get_relations(&meta.hw.id);
+        meta.relations = match relations.is_empty() {
+            true => None,
+            false => Some(relations),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardware::types::{HardwareType, SourceType};
+
+    #[test]
+    fn test_annotate_both_directions() {
+        let config = RelationsConfig::example();
+        let mut sensor = HardwareMetadata::new(
+            String::from("28-00000a0b0c0d"),
+            HardwareType::TemperatureSensor,
+            SourceType::OneWire,
+        );
+        config.annotate(&mut sensor);
+        let relations = sensor.relations.unwrap();
+        assert_eq!(relations[0].id, "[ups1]ups-monitor@localhost:3493");
+        assert_eq!(relations[0].kind, "battery_room");
+
+        let mut ups = HardwareMetadata::new(
+            String::from("[ups1]ups-monitor@localhost:3493"),
+            HardwareType::UninterruptiblePowerSupply,
+            SourceType::NetworkUpsTools,
+        );
+        config.annotate(&mut ups);
+        assert_eq!(ups.relations.unwrap()[0].id, "28-00000a0b0c0d");
+    }
+
+    #[test]
+    fn test_unrelated_hardware() {
+        let mut meta = HardwareMetadata::new(
+            String::from("28-unrelated"),
+            HardwareType::TemperatureSensor,
+            SourceType::OneWire,
+        );
+        RelationsConfig::example().annotate(&mut meta);
+        assert!(meta.relations.is_none());
+        // Relations are omitted from JSON
+        let json = serde_json::to_value(&meta).unwrap();
+        assert!(json.get("relations").is_none());
+    }
+}
@@ -0,0 +1,2 @@
+// Licensed under the Open Software License version 3.0
+pub mod config;
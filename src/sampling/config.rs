@@ -0,0 +1,42 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SamplingConfig {
+    enabled: Option<bool>,
+    // Sources poll at multiples of this since the Unix epoch, ex. every 10 s on the wall clock
+    tick: Option<Duration>,
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            tick: Some(Duration::from_secs(10)),
+        }
+    }
+}
+
+impl Example for SamplingConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(false),
+            tick: Some(Duration::from_secs(10)),
+        }
+    }
+}
+
+impl SamplingConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    /// At least 100ms
+    pub fn get_tick(&self) -> Duration {
+        self.tick
+            .unwrap_or(Duration::from_secs(10))
+            .max(Duration::from_millis(100))
+    }
+}
@@ -0,0 +1,98 @@
+// Licensed under the Open Software License version 3.0
+//! Wall clock tick shared by every source, so their polls happen at the same moments
+//!
+//! Delays returned by pollers are extended to the next tick. Cooldowns shorter than the tick
+//! poll once per tick, longer ones poll at the first tick after the cooldown. Delays are measured
+//! after the poll, so ones ending up to a tenth of the tick (at most 1s) past a tick snap back to it.
+use super::config::SamplingConfig;
+use chrono::{DateTime, Utc};
+use std::{sync::OnceLock, time::Duration};
+
+static TICK: OnceLock<Duration> = OnceLock::new();
+// Upper bound of the time taken by a poll, in ms
+const MAX_TOLERANCE: i64 = 1000;
+
+pub fn configure(config: &SamplingConfig) {
+    if !config.is_enabled() {
+        return;
+    }
+    let tick = config.get_tick();
+    if TICK.set(tick).is_err() {
+        tracing::warn!("Sampling tick is already configured");
+        return;
+    }
+    tracing::debug!("Aligning polls of sources to {}ms ticks", tick.as_millis());
+}
+
+/// `delay` extended to the next multiple of `tick` since the Unix epoch
+///
+/// A few ms spent polling would otherwise push a cooldown equal to the tick past the next tick.
+fn align(delay: Duration, tick: Duration, now: DateTime<Utc>) -> Duration {
+    let tick = tick.as_millis().max(1) as i64;
+    let tolerance = (tick / 10).min(MAX_TOLERANCE);
+    let now = now.timestamp_millis();
+    let due = now + delay.as_millis() as i64 - tolerance;
+    let mut aligned = (due + tick - 1).div_euclid(tick) * tick;
+    if aligned < now {
+        aligned += tick;
+    }
+    Duration::from_millis((aligned - now) as u64)
+}
+
+/// Delay until the next poll of a source, unchanged if sampling isn't enabled
+pub fn aligned(delay: Duration) -> Duration {
+    match TICK.get() {
+        Some(tick) => align(delay, *tick, Utc::now()),
+        None => delay,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_align() {
+        let tick = Duration::from_secs(10);
+        let now = DateTime::parse_from_rfc3339("2024-01-01T00:00:03.250+00:00")
+            .unwrap()
+            .with_timezone(&Utc);
+        // Shorter than the tick, polled at the next one
+        assert_eq!(
+            align(Duration::from_secs(5), tick, now),
+            Duration::from_millis(6750)
+        );
+        // Longer than the tick, polled at the first one after the delay
+        assert_eq!(
+            align(Duration::from_secs(60), tick, now),
+            Duration::from_millis(66750)
+        );
+        // Already on a tick
+        let now = DateTime::parse_from_rfc3339("2024-01-01T00:00:00+00:00")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(align(Duration::from_secs(10), tick, now), tick);
+    }
+
+    #[test]
+    fn test_align_after_poll() {
+        let tick = Duration::from_secs(10);
+        // Poll started on a tick and took 5ms
+        let now = DateTime::parse_from_rfc3339("2024-01-01T00:00:10.005+00:00")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            align(Duration::from_secs(10), tick, now),
+            Duration::from_millis(9995)
+        );
+        assert_eq!(
+            align(Duration::from_secs(5), tick, now),
+            Duration::from_millis(9995)
+        );
+        // Never in the past
+        assert_eq!(
+            align(Duration::ZERO, tick, now),
+            Duration::from_millis(9995)
+        );
+    }
+}
@@ -0,0 +1,13 @@
+// Licensed under the Open Software License version 3.0
+use std::{future::Future, pin::Pin, time::Duration};
+
+pub type PollFuture<'a> = Pin<Box<dyn Future<Output = Duration> + Send + 'a>>;
+
+/// Single iteration of a source loop, scheduled by its own returned delay
+pub trait PollJob: Send {
+    /// Used in logs, ex. `one_wire` or `nut:<server id>`
+    fn name(&self) -> String;
+
+    /// Poll and publish once, returns delay until the next poll
+    fn poll(&mut self) -> PollFuture<'_>;
+}
@@ -0,0 +1,196 @@
+// Licensed under the Open Software License version 3.0
+use super::job::PollJob;
+use crate::{
+    apcupsd::sender::ApcupsdPoller, config::types::Config, cpu_freq::sender::CpuFreqPoller,
+    hardware::reading::ReadingsUpdate, hwmon::sender::HwmonPoller, ipmi::sender::IpmiPoller,
+    modbus::sender::ModbusPoller, nut::sender::UpsUpdate, one_wire::sender::MeasuredTemperature,
+    self_metrics::sender::SelfMetricsPoller, smart::sender::SmartPoller,
+    snmp_ups::sender::SnmpUpsPoller, thermal_zone::sender::ThermalZonePoller,
+};
+use tokio::sync::broadcast;
+
+/// Sections read by `create_poll_jobs`, others are left default so their changes don't restart it
+pub fn polled_sections(config: &Config) -> Config {
+    Config {
+        one_wire: config.one_wire.clone(),
+        ups_monitoring: config.ups_monitoring.clone(),
+        usb_hid: config.usb_hid.clone(),
+        apcupsd: config.apcupsd.clone(),
+        snmp_ups: config.snmp_ups.clone(),
+        thermal_zone: config.thermal_zone.clone(),
+        hwmon: config.hwmon.clone(),
+        cpu_freq: config.cpu_freq.clone(),
+        dht: config.dht.clone(),
+        i2c: config.i2c.clone(),
+        smart: config.smart.clone(),
+        ipmi: config.ipmi.clone(),
+        modbus: config.modbus.clone(),
+        self_metrics: config.self_metrics.clone(),
+        quality: config.quality.clone(),
+        relations: config.relations.clone(),
+        ..Default::default()
+    }
+}
+
+/// Jobs of all enabled polling sources, push-based sources (ex. LoRaWAN) keep their own tasks
+pub fn create_poll_jobs(
+    config: &Config,
+    one_wire_tx: &broadcast::Sender<Vec<MeasuredTemperature>>,
+    ups_monitoring_tx: &broadcast::Sender<UpsUpdate>,
+    readings_tx: &broadcast::Sender<ReadingsUpdate>,
+    instance_id: &str,
+) -> Vec<Box<dyn PollJob>> {
+    let mut jobs: Vec<Box<dyn PollJob>> = Vec::new();
+    if config.one_wire.is_enabled() {
+        #[cfg(feature = "one-wire")]
+        jobs.push(Box::new(crate::one_wire::sender::OneWirePoller::new(
+            config.one_wire.clone(),
+            &config.quality,
+            config.relations.clone(),
+            one_wire_tx.clone(),
+        )));
+        #[cfg(not(feature = "one-wire"))]
+        {
+            let _ = one_wire_tx;
+            tracing::error!(
+                "1-Wire is enabled in config but this binary was built without one-wire feature"
+            );
+        }
+    }
+    if config.ups_monitoring.is_enabled() {
+        #[cfg(feature = "nut")]
+        for poller in crate::nut::sender::create_nut_server_pollers(
+            &config.ups_monitoring,
+            &config.relations,
+            ups_monitoring_tx,
+        ) {
+            jobs.push(Box::new(poller));
+        }
+        #[cfg(not(feature = "nut"))]
+        {
+            let _ = ups_monitoring_tx;
+            tracing::error!(
+                "UPS monitoring is enabled in config but this binary was built without nut feature"
+            );
+        }
+    }
+    if config.usb_hid.is_enabled() {
+        #[cfg(feature = "usb-hid")]
+        jobs.push(Box::new(crate::usb_hid::sender::UsbHidPoller::new(
+            &config.usb_hid,
+            config.relations.clone(),
+            ups_monitoring_tx.clone(),
+        )));
+        #[cfg(not(feature = "usb-hid"))]
+        tracing::error!(
+            "USB HID is enabled in config but this binary was built without usb-hid feature"
+        );
+    }
+    if config.apcupsd.is_enabled() {
+        jobs.push(Box::new(ApcupsdPoller::new(
+            &config.apcupsd,
+            config.relations.clone(),
+            ups_monitoring_tx.clone(),
+        )));
+    }
+    if config.snmp_ups.is_enabled() {
+        jobs.push(Box::new(SnmpUpsPoller::new(
+            &config.snmp_ups,
+            config.relations.clone(),
+            ups_monitoring_tx.clone(),
+        )));
+    }
+    if config.thermal_zone.is_enabled() {
+        jobs.push(Box::new(ThermalZonePoller::new(
+            config.thermal_zone.clone(),
+            readings_tx.clone(),
+        )));
+    }
+    if config.cpu_freq.is_enabled() {
+        jobs.push(Box::new(CpuFreqPoller::new(
+            config.cpu_freq.clone(),
+            readings_tx.clone(),
+        )));
+    }
+    if config.hwmon.is_enabled() {
+        jobs.push(Box::new(HwmonPoller::new(
+            config.hwmon.clone(),
+            readings_tx.clone(),
+        )));
+    }
+    if config.dht.is_enabled() {
+        #[cfg(feature = "dht")]
+        jobs.push(Box::new(crate::dht::sender::DhtPoller::new(
+            &config.dht,
+            readings_tx.clone(),
+        )));
+        #[cfg(not(feature = "dht"))]
+        tracing::error!("DHT is enabled in config but this binary was built without dht feature");
+    }
+    if config.i2c.is_enabled() {
+        #[cfg(feature = "i2c")]
+        jobs.push(Box::new(crate::i2c::sender::I2cPoller::new(
+            &config.i2c,
+            readings_tx.clone(),
+        )));
+        #[cfg(not(feature = "i2c"))]
+        tracing::error!("I2C is enabled in config but this binary was built without i2c feature");
+    }
+    if config.smart.is_enabled() {
+        jobs.push(Box::new(SmartPoller::new(
+            config.smart.clone(),
+            readings_tx.clone(),
+        )));
+    }
+    if config.ipmi.is_enabled() {
+        jobs.push(Box::new(IpmiPoller::new(
+            config.ipmi.clone(),
+            readings_tx.clone(),
+        )));
+    }
+    if config.modbus.is_enabled() {
+        jobs.push(Box::new(ModbusPoller::new(
+            &config.modbus,
+            readings_tx.clone(),
+        )));
+    }
+    if config.self_metrics.is_enabled() {
+        jobs.push(Box::new(SelfMetricsPoller::new(
+            &config.self_metrics,
+            readings_tx.clone(),
+            String::from(instance_id),
+        )));
+    }
+    jobs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_poll_jobs_of_enabled_sources() {
+        let mut config = Config::default();
+        config.self_metrics = serde_json::from_str(r#"{"enabled": true}"#).unwrap();
+        let jobs = create_poll_jobs(
+            &config,
+            &broadcast::channel(1).0,
+            &broadcast::channel(1).0,
+            &broadcast::channel(1).0,
+            "instance",
+        );
+        let names: Vec<String> = jobs.iter().map(|job| job.name()).collect();
+        assert_eq!(names, vec!["self_metrics"]);
+    }
+
+    #[test]
+    fn test_polled_sections() {
+        let config = Config::example();
+        let mut changed = config.clone();
+        changed.read_only = Some(true);
+        changed.active_data_sender = Default::default();
+        assert_eq!(polled_sections(&config), polled_sections(&changed));
+        changed.hwmon = serde_json::from_str(r#"{"enabled": true}"#).unwrap();
+        assert_ne!(polled_sections(&config), polled_sections(&changed));
+    }
+}
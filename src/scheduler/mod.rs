@@ -0,0 +1,7 @@
+// Licensed under the Open Software License version 3.0
+//! Opt-in single task polling all sources, instead of one task per source loop
+pub mod config;
+pub mod job;
+pub mod jobs;
+mod queue;
+pub mod runner;
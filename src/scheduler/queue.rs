@@ -0,0 +1,40 @@
+// Licensed under the Open Software License version 3.0
+use std::{cmp::Reverse, collections::BinaryHeap};
+use tokio::time::Instant;
+
+/// Min-heap of job indexes by due time, jobs due at the same time keep insertion order
+#[derive(Default)]
+pub struct DueQueue {
+    heap: BinaryHeap<Reverse<(Instant, u64, usize)>>,
+    sequence: u64,
+}
+
+impl DueQueue {
+    pub fn push(&mut self, due: Instant, job: usize) {
+        self.heap.push(Reverse((due, self.sequence, job)));
+        self.sequence += 1;
+    }
+
+    /// Earliest due time and its job
+    pub fn pop(&mut self) -> Option<(Instant, usize)> {
+        self.heap.pop().map(|Reverse((due, _, job))| (due, job))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_pop_in_due_order() {
+        let now = Instant::now();
+        let mut queue = DueQueue::default();
+        queue.push(now + Duration::from_secs(5), 0);
+        queue.push(now + Duration::from_secs(1), 1);
+        queue.push(now + Duration::from_secs(1), 2);
+        queue.push(now, 3);
+        let order: Vec<usize> = std::iter::from_fn(|| queue.pop().map(|(_, job)| job)).collect();
+        assert_eq!(order, vec![3, 1, 2, 0]);
+    }
+}
@@ -0,0 +1,100 @@
+// Licensed under the Open Software License version 3.0
+use super::{job::PollJob, queue::DueQueue};
+use crate::{introspection, sampling};
+use std::time::Duration;
+use tokio::{
+    sync::broadcast,
+    time::{sleep_until, Instant},
+};
+
+/// Jobs taking longer than this delay every other job, so they are reported
+const SLOW_POLL: Duration = Duration::from_secs(1);
+
+pub async fn start_scheduler_loop(
+    mut shutdown_rx: broadcast::Receiver<()>,
+    mut jobs: Vec<Box<dyn PollJob>>,
+) {
+    if jobs.is_empty() {
+        tracing::trace!("No jobs to schedule");
+        return;
+    }
+    tracing::debug!("Starting scheduler with {} jobs", jobs.len());
+    let _task = introspection::task_started("scheduler");
+    let mut queue = DueQueue::default();
+    let now = Instant::now();
+    for job in 0..jobs.len() {
+        queue.push(now, job);
+    }
+    while let Some((due, job)) = queue.pop() {
+        tokio::select! {
+            _ = shutdown_rx.recv() => {
+                tracing::trace!("Shutting down scheduler");
+                break;
+            }
+            _ = sleep_until(due) => {}
+        }
+        introspection::mark_iteration("scheduler");
+        let started_at = Instant::now();
+        let delay = jobs[job].poll().await;
+        let elapsed = started_at.elapsed();
+        introspection::mark_idle("scheduler");
+        if elapsed > SLOW_POLL {
+            tracing::warn!(
+                "Polling {} took {}ms, other jobs were delayed",
+                jobs[job].name(),
+                elapsed.as_millis()
+            );
+        }
+        queue.push(Instant::now() + sampling::tick::aligned(delay), job);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::job::PollFuture;
+    use std::sync::{Arc, Mutex};
+
+    struct RecordingJob {
+        name: &'static str,
+        interval: Duration,
+        polls: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    impl PollJob for RecordingJob {
+        fn name(&self) -> String {
+            String::from(self.name)
+        }
+
+        fn poll(&mut self) -> PollFuture<'_> {
+            self.polls.lock().unwrap().push(self.name);
+            let interval = self.interval;
+            Box::pin(async move { interval })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_jobs_are_polled_at_their_intervals() {
+        let polls = Arc::new(Mutex::new(Vec::new()));
+        let jobs: Vec<Box<dyn PollJob>> = vec![
+            Box::new(RecordingJob {
+                name: "fast",
+                interval: Duration::from_millis(50),
+                polls: polls.clone(),
+            }),
+            Box::new(RecordingJob {
+                name: "slow",
+                interval: Duration::from_secs(10),
+                polls: polls.clone(),
+            }),
+        ];
+        let (shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let scheduler = tokio::spawn(start_scheduler_loop(shutdown_rx, jobs));
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        shutdown_tx.send(()).unwrap();
+        scheduler.await.unwrap();
+        let polls = polls.lock().unwrap();
+        assert!(polls.iter().filter(|name| **name == "fast").count() >= 3);
+        assert_eq!(polls.iter().filter(|name| **name == "slow").count(), 1);
+    }
+}
@@ -0,0 +1,79 @@
+// Licensed under the Open Software License version 3.0
+//! Compatibility guarantees of outbound payloads and API responses
+//!
+//! Every shape is pinned by a golden file in `tests/golden`. Adding a field is compatible,
+//! renaming or removing one (or changing its type) isn't and requires bumping `SCHEMA_VERSION`.
+
+/// Version of payload and response shapes, returned by `/version`
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Deterministic values with every optional field set, so none of them is skipped in golden files
+#[cfg(test)]
+pub mod fixtures {
+    use crate::{
+        config::types::Example,
+        hardware::{reading::Reading, types::HardwareRelation},
+        nut::sender::UninterruptiblePowerSupplyData,
+        one_wire::sender::MeasuredTemperature,
+        quality::range::ReadingQuality,
+    };
+
+    pub const INSTANCE_ID: &str = "00000000-0000-0000-0000-000000000000";
+    pub const MEASURED_AT: &str = "2023-01-01T00:00:00+00:00";
+
+    pub fn sensor() -> MeasuredTemperature {
+        let mut sensor = MeasuredTemperature::example();
+        sensor.meta.hw.id = String::from("28-00000a0b0c0d");
+        sensor.meta.hw.name = Some(String::from("Server room"));
+        sensor.meta.relations = Some(vec![HardwareRelation {
+            id: String::from("ups1"),
+            kind: String::from("powered_by"),
+        }]);
+        sensor.meta.measured_at = Some(String::from(MEASURED_AT));
+        sensor.temperature = Some(21.5);
+        sensor.quality = Some(ReadingQuality {
+            in_range: true,
+            violations: 0,
+        });
+        sensor
+    }
+
+    pub fn ups() -> UninterruptiblePowerSupplyData {
+        let mut ups = UninterruptiblePowerSupplyData::example();
+        ups.meta.hw.id = String::from("[ups1]ups-monitor@localhost:3493");
+        ups.variables
+            .insert(String::from("ups.status"), String::from("OL"));
+        ups.meta.measured_at = Some(String::from(MEASURED_AT));
+        ups.clients = Some(vec![String::from("192.168.1.10")]);
+        ups
+    }
+
+    pub fn reading() -> Reading {
+        let mut reading = Reading::example();
+        reading.meta.measured_at = Some(String::from(MEASURED_AT));
+        reading
+    }
+}
+
+/// Compare `value` with `tests/golden/<name>.json`, or overwrite it if `UPDATE_GOLDEN_FILES` is set
+#[cfg(test)]
+pub fn assert_matches_golden_file<T: serde::Serialize>(name: &str, value: &T) {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.json", name));
+    let actual = serde_json::to_value(value).unwrap();
+    if std::env::var_os("UPDATE_GOLDEN_FILES").is_some() {
+        let json = crate::config::file::to_pretty_json(&actual);
+        std::fs::write(&path, json + "\n").unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|error| panic!("Failed to read {}: {}", path.display(), error));
+    let expected: serde_json::Value = serde_json::from_str(&expected).unwrap();
+    assert_eq!(
+        actual,
+        expected,
+        "{} changed shape. If this is intended, run tests with UPDATE_GOLDEN_FILES=1 and bump SCHEMA_VERSION unless fields were only added",
+        name
+    );
+}
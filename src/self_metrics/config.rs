@@ -0,0 +1,38 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelfMetricsConfig {
+    enabled: Option<bool>,
+    cooldown: Option<Duration>,
+}
+
+impl Default for SelfMetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            cooldown: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+impl Example for SelfMetricsConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(true),
+            cooldown: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+impl SelfMetricsConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_cooldown(&self) -> Duration {
+        self.cooldown.unwrap_or(Duration::from_secs(30))
+    }
+}
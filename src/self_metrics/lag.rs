@@ -0,0 +1,39 @@
+// Licensed under the Open Software License version 3.0
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+// Total number of messages skipped by slow receivers
+static LAGGED_MESSAGES: AtomicU64 = AtomicU64::new(0);
+
+pub fn get_lagged_messages() -> u64 {
+    LAGGED_MESSAGES.load(Ordering::Relaxed)
+}
+
+/// Receive next message and count skipped ones instead of returning `Lagged`
+pub async fn recv_counting_lag<T: Clone>(rx: &mut Receiver<T>) -> Result<T, RecvError> {
+    loop {
+        match rx.recv().await {
+            Err(RecvError::Lagged(skipped)) => {
+                tracing::warn!("Receiver lagged behind by {} messages", skipped);
+                LAGGED_MESSAGES.fetch_add(skipped, Ordering::Relaxed);
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::broadcast;
+
+    #[tokio::test]
+    async fn test_recv_counting_lag() {
+        let (tx, mut rx) = broadcast::channel::<u8>(1);
+        let lagged_before = get_lagged_messages();
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        assert_eq!(recv_counting_lag(&mut rx).await, Ok(2));
+        assert!(get_lagged_messages() > lagged_before);
+    }
+}
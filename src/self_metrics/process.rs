@@ -0,0 +1,102 @@
+// Licensed under the Open Software License version 3.0
+use std::{
+    ffi::{c_int, c_long},
+    fs,
+    path::Path,
+};
+
+// USER_HZ is 100 on virtually every Linux system
+const CLOCK_TICKS_PER_SECOND: f64 = 100.0;
+// Used if sysconf fails
+const DEFAULT_PAGE_SIZE: f64 = 4096.0;
+// _SC_PAGESIZE of glibc and musl
+const SC_PAGESIZE: c_int = 30;
+
+extern "C" {
+    fn sysconf(name: c_int) -> c_long;
+}
+
+/// Pages are 16 KiB or 64 KiB on some arm64 and ppc64 kernels
+fn page_size() -> f64 {
+    // SAFETY: sysconf only reads a system constant
+    match unsafe { sysconf(SC_PAGESIZE) } {
+        size if size > 0 => size as f64,
+        _ => DEFAULT_PAGE_SIZE,
+    }
+}
+
+/// Read resident set size from `/proc/<pid>/statm`
+pub fn read_rss_bytes(proc_path: &Path) -> Option<f64> {
+    let statm = fs::read_to_string(proc_path.join("statm")).ok()?;
+    let resident_pages: f64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    Some(resident_pages * page_size())
+}
+
+/// Read user + system CPU time from `/proc/<pid>/stat`
+pub fn read_cpu_time_secs(proc_path: &Path) -> Option<f64> {
+    let stat = fs::read_to_string(proc_path.join("stat")).ok()?;
+    // Skip "pid (comm)" as comm may contain spaces
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    // utime and stime are 14th and 15th fields, state is the 3rd one
+    let utime: f64 = fields.get(11)?.parse().ok()?;
+    let stime: f64 = fields.get(12)?.parse().ok()?;
+    Some((utime + stime) / CLOCK_TICKS_PER_SECOND)
+}
+
+/// Count entries of `/proc/<pid>/fd`
+pub fn read_open_fds(proc_path: &Path) -> Option<f64> {
+    let entries = fs::read_dir(proc_path.join("fd")).ok()?;
+    Some(entries.count() as f64)
+}
+
+/// Read number of threads from `/proc/<pid>/status`
+pub fn read_threads(proc_path: &Path) -> Option<f64> {
+    let status = fs::read_to_string(proc_path.join("status")).ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("Threads:"))?
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_fake_proc() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let proc_path = temp_dir.path();
+        fs::write(proc_path.join("statm"), "1000 250 100 1 0 300 0").unwrap();
+        fs::write(
+            proc_path.join("stat"),
+            "42 (universal data) S 1 42 42 0 -1 4194560 100 0 0 0 150 50 0 0 20 0 8 0",
+        )
+        .unwrap();
+        fs::write(proc_path.join("status"), "Name:\tuds\nThreads:\t8\n").unwrap();
+        fs::create_dir(proc_path.join("fd")).unwrap();
+        fs::write(proc_path.join("fd").join("0"), "").unwrap();
+
+        assert_eq!(read_rss_bytes(proc_path), Some(250.0 * page_size()));
+        assert_eq!(read_cpu_time_secs(proc_path), Some(2.0));
+        assert_eq!(read_threads(proc_path), Some(8.0));
+        assert_eq!(read_open_fds(proc_path), Some(1.0));
+    }
+
+    #[test]
+    fn test_page_size() {
+        let page_size = page_size();
+        assert!(page_size >= 4096.0);
+        assert_eq!(page_size as u64 % 4096, 0);
+    }
+
+    #[test]
+    fn test_read_missing_proc() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        assert_eq!(read_rss_bytes(temp_dir.path()), None);
+        assert_eq!(read_cpu_time_secs(temp_dir.path()), None);
+        assert_eq!(read_open_fds(temp_dir.path()), None);
+        assert_eq!(read_threads(temp_dir.path()), None);
+    }
+}
@@ -0,0 +1,127 @@
+// Licensed under the Open Software License version 3.0
+use super::{
+    config::SelfMetricsConfig,
+    lag::get_lagged_messages,
+    process::{read_cpu_time_secs, read_open_fds, read_rss_bytes, read_threads},
+};
+use crate::{
+    hardware::{
+        reading::{Reading, ReadingsUpdate},
+        types::{HardwareMetadata, HardwareType, SourceType},
+    },
+    introspection, sampling,
+    scheduler::job::{PollFuture, PollJob},
+};
+use std::{
+    cmp::max,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::{sync::broadcast, time::sleep};
+
+const PUBLISHER: &str = "self_metrics";
+
+/// Read resource usage of the process described by `proc_path`
+pub fn read_self_metrics(proc_path: &Path, instance_id: &str) -> Reading {
+    Reading::new(
+        HardwareMetadata::new(
+            String::from(instance_id),
+            HardwareType::Daemon,
+            SourceType::SelfMetrics,
+        )
+        .measured_now(),
+    )
+    .with_value("rss_bytes", read_rss_bytes(proc_path))
+    .with_value("cpu_time_secs", read_cpu_time_secs(proc_path))
+    .with_value("open_fds", read_open_fds(proc_path))
+    .with_value("threads", read_threads(proc_path))
+    .with_value("tasks", Some(introspection::running_tasks() as f64))
+    .with_value("lagged_messages", Some(get_lagged_messages() as f64))
+}
+
+pub struct SelfMetricsPoller {
+    proc_path: PathBuf,
+    instance_id: String,
+    cooldown: Duration,
+    tx: broadcast::Sender<ReadingsUpdate>,
+}
+
+impl SelfMetricsPoller {
+    pub fn new(
+        config: &SelfMetricsConfig,
+        tx: broadcast::Sender<ReadingsUpdate>,
+        instance_id: String,
+    ) -> Self {
+        Self {
+            proc_path: PathBuf::from("/proc/self"),
+            instance_id,
+            cooldown: max(config.get_cooldown(), Duration::from_secs(1)),
+            tx,
+        }
+    }
+
+    /// Publish current resource usage, returns delay until the next poll
+    pub fn poll_once(&mut self) -> Duration {
+        introspection::mark_iteration("self_metrics");
+        let reading = read_self_metrics(&self.proc_path, &self.instance_id);
+        tracing::trace!("Sending {:?} to channel", reading);
+        if self.tx.receiver_count() > 0 {
+            self.tx
+                .send(ReadingsUpdate::new(PUBLISHER, vec![reading]))
+                .unwrap();
+            introspection::observe_channel("readings", &self.tx);
+        }
+        introspection::mark_idle("self_metrics");
+        self.cooldown
+    }
+}
+
+impl PollJob for SelfMetricsPoller {
+    fn name(&self) -> String {
+        String::from("self_metrics")
+    }
+
+    fn poll(&mut self) -> PollFuture<'_> {
+        let delay = self.poll_once();
+        Box::pin(async move { delay })
+    }
+}
+
+pub async fn start_self_metrics_loop(
+    mut shutdown_rx: broadcast::Receiver<()>,
+    config: SelfMetricsConfig,
+    tx: broadcast::Sender<ReadingsUpdate>,
+    instance_id: String,
+) {
+    // Check if module is enabled
+    if !config.is_enabled() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+    tracing::debug!("Starting self metrics loop");
+    let mut poller = SelfMetricsPoller::new(&config, tx, instance_id);
+    let _task = introspection::task_started("self_metrics");
+    loop {
+        let delay = poller.poll_once();
+        tokio::select! {
+            _ = shutdown_rx.recv() => {
+                tracing::trace!("Shutting down self metrics loop");
+                break;
+            }
+            _ = sleep(sampling::tick::aligned(delay)) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_self_metrics() {
+        let reading = read_self_metrics(Path::new("/proc/self"), "instance");
+        assert_eq!(reading.meta.hw.id, "instance");
+        assert_eq!(reading.meta.hw.hardware_type, HardwareType::Daemon);
+        assert!(reading.values.contains_key("lagged_messages"));
+    }
+}
@@ -0,0 +1,113 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt};
+
+/// Unix signals the daemon listens for, `sigint` is also Ctrl+C on other platforms
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Signal {
+    Sigint,
+    Sigterm,
+    Sighup,
+    Sigusr1,
+    Sigusr2,
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format!("{:?}", self).to_uppercase())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignalAction {
+    // Stop all modules and exit
+    Shutdown,
+    // Stop all modules and start again with the current config file
+    Reload,
+    // Keep running, the signal is only logged
+    Ignore,
+}
+
+impl Signal {
+    /// Action of signals that aren't configured, `None` keeps the default disposition
+    fn default_action(self) -> Option<SignalAction> {
+        match self {
+            Signal::Sigint | Signal::Sigterm => Some(SignalAction::Shutdown),
+            Signal::Sighup => Some(SignalAction::Reload),
+            Signal::Sigusr1 | Signal::Sigusr2 => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct SignalsConfig {
+    // Signal -> action, replaces the default action of that signal
+    actions: Option<HashMap<Signal, SignalAction>>,
+}
+
+impl Example for SignalsConfig {
+    fn example() -> Self {
+        Self {
+            actions: Some(HashMap::from([
+                (Signal::Sigterm, SignalAction::Shutdown),
+                (Signal::Sighup, SignalAction::Reload),
+            ])),
+        }
+    }
+}
+
+impl SignalsConfig {
+    /// Every signal to listen for with its action
+    pub fn get_actions(&self) -> Vec<(Signal, SignalAction)> {
+        let actions = self.actions.clone().unwrap_or_default();
+        [
+            Signal::Sigint,
+            Signal::Sigterm,
+            Signal::Sighup,
+            Signal::Sigusr1,
+            Signal::Sigusr2,
+        ]
+        .into_iter()
+        .filter_map(|signal| {
+            let action = actions
+                .get(&signal)
+                .copied()
+                .or_else(|| signal.default_action())?;
+            Some((signal, action))
+        })
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_actions() {
+        assert_eq!(
+            SignalsConfig::default().get_actions(),
+            vec![
+                (Signal::Sigint, SignalAction::Shutdown),
+                (Signal::Sigterm, SignalAction::Shutdown),
+                (Signal::Sighup, SignalAction::Reload),
+            ]
+        );
+        let config: SignalsConfig = serde_json::from_value(serde_json::json!({
+            "actions": {"sighup": "ignore", "sigusr1": "reload"}
+        }))
+        .unwrap();
+        assert_eq!(
+            config.get_actions(),
+            vec![
+                (Signal::Sigint, SignalAction::Shutdown),
+                (Signal::Sigterm, SignalAction::Shutdown),
+                (Signal::Sighup, SignalAction::Ignore),
+                (Signal::Sigusr1, SignalAction::Reload),
+            ]
+        );
+    }
+}
@@ -0,0 +1,131 @@
+// Licensed under the Open Software License version 3.0
+use super::config::{Signal, SignalAction, SignalsConfig};
+use crate::{
+    degraded_mode::watcher::{request_restart, restart_requested},
+    hot_reload::watcher::request_reload,
+};
+#[cfg(unix)]
+use std::collections::HashMap;
+use tokio::sync::broadcast::Sender;
+#[cfg(unix)]
+use tokio_stream::{Stream, StreamExt};
+
+/// First received signal whose action isn't `Ignore`, `None` once `signals` end
+#[cfg(unix)]
+async fn next_action<S>(
+    signals: &mut S,
+    actions: &HashMap<Signal, SignalAction>,
+) -> Option<(Signal, SignalAction)>
+where
+    S: Stream<Item = Signal> + Unpin,
+{
+    while let Some(received) = signals.next().await {
+        match actions.get(&received) {
+            Some(SignalAction::Ignore) | None => tracing::info!("Ignoring {}", received),
+            Some(action) => return Some((received, *action)),
+        }
+    }
+    None
+}
+
+/// Wait for a signal whose action isn't `Ignore`
+#[cfg(unix)]
+async fn wait_for_signal(config: &SignalsConfig) -> (Signal, SignalAction) {
+    use tokio::signal::unix::{signal, SignalKind};
+    use tokio_stream::{wrappers::SignalStream, StreamMap};
+
+    let mut streams = StreamMap::new();
+    let mut actions = HashMap::new();
+    for (unix_signal, action) in config.get_actions() {
+        let kind = match unix_signal {
+            Signal::Sigint => SignalKind::interrupt(),
+            Signal::Sigterm => SignalKind::terminate(),
+            Signal::Sighup => SignalKind::hangup(),
+            Signal::Sigusr1 => SignalKind::user_defined1(),
+            Signal::Sigusr2 => SignalKind::user_defined2(),
+        };
+        match signal(kind) {
+            Ok(stream) => {
+                streams.insert(unix_signal, SignalStream::new(stream));
+                actions.insert(unix_signal, action);
+            }
+            Err(error) => tracing::warn!("Failed to listen for {}: {}", unix_signal, error),
+        }
+    }
+    let mut signals = streams.map(|(received, ())| received);
+    match next_action(&mut signals, &actions).await {
+        Some(received) => received,
+        // Nothing to listen for
+        None => std::future::pending().await,
+    }
+}
+
+/// Only Ctrl+C is available
+#[cfg(not(unix))]
+async fn wait_for_signal(config: &SignalsConfig) -> (Signal, SignalAction) {
+    let action = config
+        .get_actions()
+        .into_iter()
+        .find_map(|(signal, action)| (signal == Signal::Sigint).then_some(action))
+        .unwrap_or(SignalAction::Shutdown);
+    loop {
+        if let Err(error) = tokio::signal::ctrl_c().await {
+            tracing::warn!("Failed to listen for {}: {}", Signal::Sigint, error);
+            std::future::pending::<()>().await;
+        }
+        if action != SignalAction::Ignore {
+            return (Signal::Sigint, action);
+        }
+        tracing::info!("Ignoring {}", Signal::Sigint);
+    }
+}
+
+/// `hot_reload` makes `reload` apply the config in place instead of restarting
+pub async fn start_shutdown_notifier(tx: Sender<()>, config: SignalsConfig, hot_reload: bool) {
+    tracing::trace!("Starting shutdown notifier");
+    loop {
+        tokio::select! {
+            (signal, action) = wait_for_signal(&config) => match action {
+                SignalAction::Reload if hot_reload => {
+                    tracing::info!("Received {}, reloading config", signal);
+                    request_reload();
+                    continue;
+                }
+                SignalAction::Reload => {
+                    tracing::info!("Received {}, restarting to reload config", signal);
+                    request_restart();
+                }
+                _ => tracing::debug!("Received {}, shutting down", signal),
+            },
+            _ = restart_requested() => tracing::debug!("Shutting down to restart"),
+        }
+        break;
+    }
+    tracing::trace!("Sending message to {} receivers", tx.receiver_count());
+    let _ = tx.send(());
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_ignored_signal() {
+        let config: SignalsConfig = serde_json::from_value(serde_json::json!({
+            "actions": {"sigusr2": "ignore", "sigusr1": "shutdown"}
+        }))
+        .unwrap();
+        let actions: HashMap<Signal, SignalAction> = config.get_actions().into_iter().collect();
+        // Signals are injected, sending real ones could kill the test binary
+        let mut signals = tokio_stream::iter([Signal::Sigusr2, Signal::Sigusr1, Signal::Sighup]);
+        assert_eq!(
+            next_action(&mut signals, &actions).await,
+            Some((Signal::Sigusr1, SignalAction::Shutdown))
+        );
+        assert_eq!(
+            next_action(&mut signals, &actions).await,
+            Some((Signal::Sighup, SignalAction::Reload))
+        );
+        assert_eq!(next_action(&mut signals, &actions).await, None);
+    }
+}
@@ -0,0 +1,65 @@
+// Licensed under the Open Software License version 3.0
+//! `universal-data-source verify <payload> <signature> [public key file]`
+//!
+//! Checks `X-Signature` of an archived request body, public key defaults to `signing_key.pub` next to the config file
+use super::key::{get_public_key_file_path, verify};
+use crate::config::file::get_config_file_path;
+use std::{error::Error, fs, path::PathBuf};
+
+pub fn is_verify_command(args: &[String]) -> bool {
+    args.get(1).map(String::as_str) == Some("verify")
+}
+
+pub fn run_verify_command(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let (payload, signature) = match (args.get(2), args.get(3)) {
+        (Some(payload), Some(signature)) => (payload, signature),
+        _ => {
+            return Err(
+                "usage: universal-data-source verify <payload> <signature> [public key file]"
+                    .into(),
+            )
+        }
+    };
+    let public_key_path = args
+        .get(4)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| get_public_key_file_path(&get_config_file_path()));
+    let public_key = fs::read_to_string(&public_key_path)
+        .map_err(|error| format!("failed to read {}: {}", public_key_path.display(), error))?;
+    let body =
+        fs::read(payload).map_err(|error| format!("failed to read {}: {}", payload, error))?;
+    verify(&public_key, &body, signature)?;
+    tracing::info!("Signature of {} is valid", payload);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::key::read_or_create_signer;
+
+    #[test]
+    fn test_run_verify_command() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let signer = read_or_create_signer(&temp_dir.path().join("config.json")).unwrap();
+        let payload = temp_dir.path().join("snapshot.json");
+        fs::write(&payload, b"[1,2,3]").unwrap();
+        let args = |signature: String| -> Vec<String> {
+            vec![
+                String::from("uds"),
+                String::from("verify"),
+                payload.display().to_string(),
+                signature,
+                temp_dir
+                    .path()
+                    .join("signing_key.pub")
+                    .display()
+                    .to_string(),
+            ]
+        };
+        assert!(is_verify_command(&args(String::new())));
+        assert!(run_verify_command(&args(signer.sign(b"[1,2,3]"))).is_ok());
+        assert!(run_verify_command(&args(signer.sign(b"[1,2,4]"))).is_err());
+        assert!(run_verify_command(&args(String::new())[..3]).is_err());
+    }
+}
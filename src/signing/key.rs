@@ -0,0 +1,150 @@
+// Licensed under the Open Software License version 3.0
+use ed25519_dalek::{Signature, Signer as _, SigningKey, VerifyingKey};
+use std::{
+    fs,
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+const SIGNING_KEY_FILE_NAME: &str = "signing_key";
+const PUBLIC_KEY_FILE_NAME: &str = "signing_key.pub";
+/// Hex-encoded Ed25519 signature of the exact request body
+pub const SIGNATURE_HEADER: &str = "X-Signature";
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode_hex<const N: usize>(text: &str) -> Result<[u8; N], String> {
+    let text = text.trim();
+    if text.len() != N * 2 || !text.chars().all(|char| char.is_ascii_hexdigit()) {
+        return Err(format!("expected {} hex characters", N * 2));
+    }
+    let mut bytes = [0u8; N];
+    for (index, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&text[index * 2..index * 2 + 2], 16).unwrap();
+    }
+    Ok(bytes)
+}
+
+/// Keys are stored next to the config file, like instance id
+fn get_key_file_path(config_file_path: &Path, file_name: &str) -> PathBuf {
+    config_file_path
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join(file_name)
+}
+
+pub fn get_public_key_file_path(config_file_path: &Path) -> PathBuf {
+    get_key_file_path(config_file_path, PUBLIC_KEY_FILE_NAME)
+}
+
+/// Local Ed25519 key signing request bodies
+#[derive(Clone)]
+pub struct Signer {
+    key: SigningKey,
+}
+
+impl Signer {
+    /// Hex-encoded signature, value of `SIGNATURE_HEADER`
+    pub fn sign(&self, body: &[u8]) -> String {
+        encode_hex(&self.key.sign(body).to_bytes())
+    }
+
+    pub fn public_key(&self) -> String {
+        encode_hex(self.key.verifying_key().as_bytes())
+    }
+}
+
+/// Private key is readable only by its owner
+fn write_private_file(path: &Path, contents: &str) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(contents.as_bytes())
+}
+
+/// Read signing key or generate a new one on first run
+///
+/// Unlike instance id, a key that's invalid or can't be persisted is an error,
+/// replacing it would make earlier signatures unverifiable with the published public key
+pub fn read_or_create_signer(config_file_path: &Path) -> Result<Signer, String> {
+    let path = get_key_file_path(config_file_path, SIGNING_KEY_FILE_NAME);
+    let key = match fs::read_to_string(&path) {
+        Ok(contents) => SigningKey::from_bytes(
+            &decode_hex(&contents)
+                .map_err(|error| format!("invalid key in {}: {}", path.display(), error))?,
+        ),
+        Err(error) if error.kind() == ErrorKind::NotFound => {
+            let mut seed = [0u8; 32];
+            getrandom::getrandom(&mut seed).map_err(|error| error.to_string())?;
+            write_private_file(&path, &encode_hex(&seed))
+                .map_err(|error| format!("failed to save {}: {}", path.display(), error))?;
+            tracing::info!("Generated signing key {}", path.display());
+            SigningKey::from_bytes(&seed)
+        }
+        Err(error) => return Err(format!("failed to read {}: {}", path.display(), error)),
+    };
+    let signer = Signer { key };
+    // Rewritten on every start, so it can't drift from the private key
+    let public_key_path = get_public_key_file_path(config_file_path);
+    if let Err(error) = fs::write(&public_key_path, format!("{}\n", signer.public_key())) {
+        tracing::warn!(
+            "Failed to save public key to {}: {}",
+            public_key_path.display(),
+            error
+        );
+    }
+    Ok(signer)
+}
+
+/// Check hex-encoded `signature` of `body` against hex-encoded `public_key`
+pub fn verify(public_key: &str, body: &[u8], signature: &str) -> Result<(), String> {
+    let public_key = VerifyingKey::from_bytes(
+        &decode_hex(public_key).map_err(|error| format!("invalid public key: {}", error))?,
+    )
+    .map_err(|_| String::from("invalid public key"))?;
+    let signature = Signature::from_bytes(
+        &decode_hex(signature).map_err(|error| format!("invalid signature: {}", error))?,
+    );
+    public_key
+        .verify_strict(body, &signature)
+        .map_err(|_| String::from("signature doesn't match"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_file_path = temp_dir.path().join("config.json");
+        let signer = read_or_create_signer(&config_file_path).unwrap();
+        let public_key = fs::read_to_string(temp_dir.path().join(PUBLIC_KEY_FILE_NAME)).unwrap();
+        assert_eq!(public_key.trim(), signer.public_key());
+        let body = br#"{"sensors":[],"upses":[]}"#;
+        let signature = signer.sign(body);
+        assert_eq!(signature.len(), 128);
+        assert_eq!(verify(&public_key, body, &signature), Ok(()));
+        assert!(verify(&public_key, br#"{"sensors":[],"upses":[{}]}"#, &signature).is_err());
+        assert!(verify(&public_key, body, "not-a-signature").is_err());
+        // Same key after restart
+        let signer = read_or_create_signer(&config_file_path).unwrap();
+        assert_eq!(signer.public_key(), public_key.trim());
+    }
+
+    #[test]
+    fn test_invalid_key_is_not_replaced() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_file_path = temp_dir.path().join("config.json");
+        let key_path = temp_dir.path().join(SIGNING_KEY_FILE_NAME);
+        fs::write(&key_path, "not-a-key").unwrap();
+        assert!(read_or_create_signer(&config_file_path).is_err());
+        assert_eq!(fs::read_to_string(key_path).unwrap(), "not-a-key");
+    }
+}
@@ -0,0 +1,5 @@
+// Licensed under the Open Software License version 3.0
+//! Detached Ed25519 signatures of outbound snapshots
+pub mod cli;
+#[cfg_attr(not(feature = "active-sender"), allow(dead_code))]
+pub mod key;
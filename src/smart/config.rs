@@ -0,0 +1,64 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SmartConfig {
+    enabled: Option<bool>,
+    cooldown: Option<Duration>,
+    // Path of smartctl executable, looked up in PATH by default
+    smartctl: Option<String>,
+    // Device paths (ex. /dev/sda), found with smartctl --scan if not set
+    devices: Option<Vec<String>>,
+    // Read disks in standby instead of skipping them, spins them up
+    wake_up: Option<bool>,
+}
+
+impl Default for SmartConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            cooldown: Some(Duration::from_secs(300)),
+            smartctl: Some(String::from("smartctl")),
+            devices: None,
+            wake_up: Some(false),
+        }
+    }
+}
+
+impl Example for SmartConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(false),
+            cooldown: Some(Duration::from_secs(300)),
+            smartctl: Some(String::from("smartctl")),
+            devices: None,
+            wake_up: Some(false),
+        }
+    }
+}
+
+impl SmartConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_cooldown(&self) -> Duration {
+        self.cooldown.unwrap_or(Duration::from_secs(300))
+    }
+
+    pub fn get_smartctl(&self) -> String {
+        self.smartctl
+            .clone()
+            .unwrap_or_else(|| String::from("smartctl"))
+    }
+
+    pub fn get_devices(&self) -> Option<Vec<String>> {
+        self.devices.clone()
+    }
+
+    pub fn get_wake_up(&self) -> bool {
+        self.wake_up.unwrap_or_default()
+    }
+}
@@ -0,0 +1,269 @@
+// Licensed under the Open Software License version 3.0
+//! Parsing of `smartctl --json` output
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// Bits of `exit_status` meaning that the device wasn't read at all
+const FATAL_EXIT_STATUS: i64 = 0b11;
+/// ATA attribute ids published with their raw values
+const ATA_ATTRIBUTES: [(u8, &str); 3] = [
+    (5, "reallocated_sectors"),
+    (197, "pending_sectors"),
+    (198, "offline_uncorrectable"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ScannedDevice {
+    pub name: String,
+    // Passed to smartctl -d, ex. sat or nvme
+    #[serde(rename = "type")]
+    pub device_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScanOutput {
+    #[serde(default)]
+    devices: Vec<ScannedDevice>,
+}
+
+/// Devices listed by `smartctl --scan --json`
+pub fn parse_scan(json: &str) -> Result<Vec<ScannedDevice>, String> {
+    serde_json::from_str::<ScanOutput>(json)
+        .map(|output| output.devices)
+        .map_err(|error| error.to_string())
+}
+
+#[derive(Debug, Deserialize)]
+struct Message {
+    string: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SmartctlInfo {
+    exit_status: i64,
+    #[serde(default)]
+    messages: Vec<Message>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SmartStatus {
+    passed: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct Temperature {
+    current: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PowerOnTime {
+    hours: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawValue {
+    value: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct AtaAttribute {
+    id: u8,
+    raw: RawValue,
+}
+
+#[derive(Debug, Deserialize)]
+struct AtaAttributes {
+    table: Vec<AtaAttribute>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NvmeHealth {
+    percentage_used: Option<f64>,
+    available_spare: Option<f64>,
+    media_errors: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SmartctlOutput {
+    smartctl: SmartctlInfo,
+    serial_number: Option<String>,
+    smart_status: Option<SmartStatus>,
+    temperature: Option<Temperature>,
+    power_on_time: Option<PowerOnTime>,
+    power_cycle_count: Option<f64>,
+    ata_smart_attributes: Option<AtaAttributes>,
+    nvme_smart_health_information_log: Option<NvmeHealth>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceReport {
+    Read {
+        // Serial number, survives reordering of /dev/sd*
+        id: String,
+        values: BTreeMap<String, f64>,
+    },
+    // Skipped so it isn't spun up
+    Standby,
+}
+
+/// Values of `smartctl --json --all` output, `device` is used as id if serial number is missing
+pub fn parse_report(device: &str, json: &str) -> Result<DeviceReport, String> {
+    let output: SmartctlOutput = serde_json::from_str(json).map_err(|error| error.to_string())?;
+    if output.smartctl.exit_status & FATAL_EXIT_STATUS != 0 {
+        let messages: Vec<&str> = output
+            .smartctl
+            .messages
+            .iter()
+            .map(|message| message.string.as_str())
+            .collect();
+        if messages.iter().any(|message| message.contains("STANDBY")) {
+            return Ok(DeviceReport::Standby);
+        }
+        if messages.is_empty() {
+            return Err(format!(
+                "smartctl exited with {}",
+                output.smartctl.exit_status
+            ));
+        }
+        return Err(messages.join(", "));
+    }
+    let mut values = BTreeMap::new();
+    let mut insert = |key: &str, value: Option<f64>| {
+        if let Some(value) = value {
+            values.insert(String::from(key), value);
+        }
+    };
+    insert(
+        "temperature",
+        output
+            .temperature
+            .and_then(|temperature| temperature.current),
+    );
+    insert(
+        "smart_passed",
+        output
+            .smart_status
+            .map(|status| if status.passed { 1.0 } else { 0.0 }),
+    );
+    insert(
+        "power_on_hours",
+        output.power_on_time.and_then(|time| time.hours),
+    );
+    insert("power_cycles", output.power_cycle_count);
+    if let Some(attributes) = output.ata_smart_attributes {
+        for (id, key) in ATA_ATTRIBUTES {
+            let value = attributes
+                .table
+                .iter()
+                .find(|attribute| attribute.id == id)
+                .map(|attribute| attribute.raw.value);
+            insert(key, value);
+        }
+    }
+    if let Some(health) = output.nvme_smart_health_information_log {
+        insert("percentage_used", health.percentage_used);
+        insert("available_spare", health.available_spare);
+        insert("media_errors", health.media_errors);
+    }
+    let id = output
+        .serial_number
+        .filter(|serial| !serial.is_empty())
+        .unwrap_or_else(|| String::from(device));
+    Ok(DeviceReport::Read { id, values })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scan() {
+        let devices = parse_scan(
+            r#"{"devices": [
+                {"name": "/dev/sda", "info_name": "/dev/sda [SAT]", "type": "sat", "protocol": "ATA"},
+                {"name": "/dev/nvme0", "info_name": "/dev/nvme0", "type": "nvme", "protocol": "NVMe"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[1].name, "/dev/nvme0");
+        assert_eq!(devices[1].device_type.as_deref(), Some("nvme"));
+        assert_eq!(parse_scan(r#"{"smartctl": {}}"#).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_parse_ata_report() {
+        let report = parse_report(
+            "/dev/sda",
+            r#"{
+                "smartctl": {"exit_status": 0},
+                "model_name": "WDC WD40EFRX-68N32N0",
+                "serial_number": "WD-WCC7K0000000",
+                "smart_status": {"passed": true},
+                "temperature": {"current": 34},
+                "power_on_time": {"hours": 23456},
+                "power_cycle_count": 120,
+                "ata_smart_attributes": {"table": [
+                    {"id": 5, "name": "Reallocated_Sector_Ct", "raw": {"value": 0, "string": "0"}},
+                    {"id": 194, "name": "Temperature_Celsius", "raw": {"value": 34, "string": "34"}},
+                    {"id": 197, "name": "Current_Pending_Sector", "raw": {"value": 2, "string": "2"}}
+                ]}
+            }"#,
+        )
+        .unwrap();
+        let (id, values) = match report {
+            DeviceReport::Read { id, values } => (id, values),
+            DeviceReport::Standby => panic!("expected values"),
+        };
+        assert_eq!(id, "WD-WCC7K0000000");
+        assert_eq!(values["temperature"], 34.0);
+        assert_eq!(values["smart_passed"], 1.0);
+        assert_eq!(values["power_on_hours"], 23456.0);
+        assert_eq!(values["reallocated_sectors"], 0.0);
+        assert_eq!(values["pending_sectors"], 2.0);
+        assert!(!values.contains_key("offline_uncorrectable"));
+    }
+
+    #[test]
+    fn test_parse_nvme_report() {
+        // Exit status 8 means failing SMART status, values are still valid
+        let report = parse_report(
+            "/dev/nvme0",
+            r#"{
+                "smartctl": {"exit_status": 8},
+                "smart_status": {"passed": false},
+                "temperature": {"current": 41},
+                "nvme_smart_health_information_log": {
+                    "percentage_used": 3, "available_spare": 100, "media_errors": 0
+                }
+            }"#,
+        )
+        .unwrap();
+        let (id, values) = match report {
+            DeviceReport::Read { id, values } => (id, values),
+            DeviceReport::Standby => panic!("expected values"),
+        };
+        assert_eq!(id, "/dev/nvme0");
+        assert_eq!(values["smart_passed"], 0.0);
+        assert_eq!(values["percentage_used"], 3.0);
+        assert_eq!(values["available_spare"], 100.0);
+    }
+
+    #[test]
+    fn test_parse_failed_report() {
+        let standby = r#"{"smartctl": {"exit_status": 2, "messages": [
+            {"string": "Device is in STANDBY mode, exit(2)", "severity": "information"}
+        ]}}"#;
+        assert_eq!(parse_report("/dev/sdb", standby), Ok(DeviceReport::Standby));
+        let missing = r#"{"smartctl": {"exit_status": 2, "messages": [
+            {"string": "Smartctl open device: /dev/sdz failed: No such device", "severity": "error"}
+        ]}}"#;
+        assert_eq!(
+            parse_report("/dev/sdz", missing),
+            Err(String::from(
+                "Smartctl open device: /dev/sdz failed: No such device"
+            ))
+        );
+        assert!(parse_report("/dev/sda", "not json").is_err());
+    }
+}
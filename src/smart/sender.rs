@@ -0,0 +1,171 @@
+// Licensed under the Open Software License version 3.0
+use super::{
+    config::SmartConfig,
+    report::{parse_report, parse_scan, DeviceReport, ScannedDevice},
+};
+use crate::{
+    dedup_log::{info_resolved, warn_deduplicated},
+    hardware::{
+        reading::{Reading, ReadingsUpdate},
+        types::{HardwareMetadata, HardwareType, SourceType},
+    },
+    introspection, sampling,
+    scheduler::job::{PollFuture, PollJob},
+};
+use std::{cmp::max, time::Duration};
+use tokio::{
+    process::Command,
+    sync::broadcast,
+    time::{sleep, timeout},
+};
+
+const PUBLISHER: &str = "smart";
+/// smartctl may hang on unresponsive disks or USB bridges
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Stdout of smartctl, which exits with non-zero status bits also for readable but failing disks
+async fn run_smartctl(smartctl: &str, args: &[&str]) -> Result<String, String> {
+    let output = timeout(
+        COMMAND_TIMEOUT,
+        Command::new(smartctl)
+            .args(args)
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .map_err(|_| format!("{} timed out", smartctl))?
+    .map_err(|error| format!("failed to run {}: {}", smartctl, error))?;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+async fn find_devices(config: &SmartConfig) -> Result<Vec<ScannedDevice>, String> {
+    if let Some(devices) = config.get_devices() {
+        return Ok(devices
+            .into_iter()
+            .map(|name| ScannedDevice {
+                name,
+                device_type: None,
+            })
+            .collect());
+    }
+    let output = run_smartctl(&config.get_smartctl(), &["--scan", "--json"]).await?;
+    parse_scan(&output)
+}
+
+async fn read_device(config: &SmartConfig, device: &ScannedDevice) -> Result<DeviceReport, String> {
+    let mut args = vec!["--json", "--all"];
+    if !config.get_wake_up() {
+        args.extend(["--nocheck", "standby"]);
+    }
+    if let Some(device_type) = &device.device_type {
+        args.extend(["--device", device_type.as_str()]);
+    }
+    args.push(&device.name);
+    let output = run_smartctl(&config.get_smartctl(), &args).await?;
+    parse_report(&device.name, &output)
+}
+
+/// Reading of every disk that isn't sleeping, scanned again as disks may be hot-plugged
+pub async fn read_smart_devices(config: &SmartConfig) -> Vec<Reading> {
+    let devices = match find_devices(config).await {
+        Ok(devices) => devices,
+        Err(error) => {
+            warn_deduplicated!("smart:scan", "Failed to scan for SMART devices: {}", error);
+            return Vec::new();
+        }
+    };
+    info_resolved!("smart:scan", "Scanning for SMART devices again");
+    let mut readings = Vec::new();
+    for device in devices {
+        let key = format!("smart:{}", device.name);
+        match read_device(config, &device).await {
+            Ok(DeviceReport::Read { id, values }) => {
+                info_resolved!(key, "Reading SMART data of {} again", device.name);
+                let mut reading = Reading::new(
+                    HardwareMetadata::new(id, HardwareType::StorageDevice, SourceType::Smart)
+                        .measured_now(),
+                );
+                reading.values = values;
+                readings.push(reading);
+            }
+            Ok(DeviceReport::Standby) => {
+                tracing::trace!("Skipping {} in standby", device.name);
+            }
+            Err(error) => {
+                warn_deduplicated!(
+                    key,
+                    "Failed to read SMART data of {}: {}",
+                    device.name,
+                    error
+                );
+            }
+        }
+    }
+    readings
+}
+
+pub struct SmartPoller {
+    cooldown: Duration,
+    config: SmartConfig,
+    tx: broadcast::Sender<ReadingsUpdate>,
+}
+
+impl SmartPoller {
+    pub fn new(config: SmartConfig, tx: broadcast::Sender<ReadingsUpdate>) -> Self {
+        Self {
+            cooldown: max(config.get_cooldown(), Duration::from_secs(10)),
+            config,
+            tx,
+        }
+    }
+
+    /// Publish all readable disks, returns delay until the next poll
+    pub async fn poll_once(&mut self) -> Duration {
+        introspection::mark_iteration("smart");
+        let readings = read_smart_devices(&self.config).await;
+        tracing::trace!("Sending {:?} to channel", readings);
+        if self.tx.receiver_count() > 0 {
+            self.tx
+                .send(ReadingsUpdate::new(PUBLISHER, readings))
+                .unwrap();
+            introspection::observe_channel("readings", &self.tx);
+        }
+        introspection::mark_idle("smart");
+        self.cooldown
+    }
+}
+
+impl PollJob for SmartPoller {
+    fn name(&self) -> String {
+        String::from("smart")
+    }
+
+    fn poll(&mut self) -> PollFuture<'_> {
+        Box::pin(self.poll_once())
+    }
+}
+
+pub async fn start_smart_loop(
+    mut shutdown_rx: broadcast::Receiver<()>,
+    config: SmartConfig,
+    tx: broadcast::Sender<ReadingsUpdate>,
+) {
+    // Check if module is enabled
+    if !config.is_enabled() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+    tracing::debug!("Starting SMART loop");
+    let mut poller = SmartPoller::new(config, tx);
+    let _task = introspection::task_started("smart");
+    loop {
+        let delay = poller.poll_once().await;
+        tokio::select! {
+            _ = shutdown_rx.recv() => {
+                tracing::trace!("Shutting down SMART loop");
+                break;
+            }
+            _ = sleep(sampling::tick::aligned(delay)) => {}
+        }
+    }
+}
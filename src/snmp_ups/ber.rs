@@ -0,0 +1,313 @@
+// Licensed under the Open Software License version 3.0
+//! Subset of BER used by SNMP messages
+pub const INTEGER: u8 = 0x02;
+pub const OCTET_STRING: u8 = 0x04;
+pub const NULL: u8 = 0x05;
+pub const OBJECT_IDENTIFIER: u8 = 0x06;
+pub const SEQUENCE: u8 = 0x30;
+const COUNTER32: u8 = 0x41;
+const GAUGE32: u8 = 0x42;
+const TIME_TICKS: u8 = 0x43;
+const COUNTER64: u8 = 0x46;
+const NO_SUCH_OBJECT: u8 = 0x80;
+const NO_SUCH_INSTANCE: u8 = 0x81;
+const END_OF_MIB_VIEW: u8 = 0x82;
+
+/// Value of a variable binding
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Integer(i64),
+    // Counter32, Gauge32, TimeTicks and Counter64
+    Unsigned(u64),
+    OctetString(Vec<u8>),
+    ObjectIdentifier(Vec<u32>),
+    Null,
+    // noSuchObject, noSuchInstance or endOfMibView
+    Missing,
+}
+
+impl Value {
+    /// Number or text as NUT-like variable value
+    pub fn to_variable(&self) -> Option<String> {
+        match self {
+            Value::Integer(value) => Some(value.to_string()),
+            Value::Unsigned(value) => Some(value.to_string()),
+            Value::OctetString(value) => Some(String::from_utf8_lossy(value).trim().to_string()),
+            _ => None,
+        }
+    }
+}
+
+fn encode_length(length: usize, output: &mut Vec<u8>) {
+    if length < 0x80 {
+        output.push(length as u8);
+        return;
+    }
+    let bytes = length.to_be_bytes();
+    let skip = bytes.iter().take_while(|byte| **byte == 0).count();
+    output.push(0x80 | (bytes.len() - skip) as u8);
+    output.extend_from_slice(&bytes[skip..]);
+}
+
+/// Tag, length and contents
+pub fn tlv(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let mut output = vec![tag];
+    encode_length(contents.len(), &mut output);
+    output.extend_from_slice(contents);
+    output
+}
+
+/// Length of tag and length bytes in front of `length` bytes of contents
+#[cfg_attr(not(feature = "snmp-v3"), allow(dead_code))]
+pub fn header_length(length: usize) -> usize {
+    let mut output = Vec::new();
+    encode_length(length, &mut output);
+    1 + output.len()
+}
+
+pub fn integer(value: i64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    // Shortest two's complement form
+    let mut start = 0;
+    while start < bytes.len() - 1 {
+        let redundant = (bytes[start] == 0x00 && bytes[start + 1] & 0x80 == 0)
+            || (bytes[start] == 0xff && bytes[start + 1] & 0x80 != 0);
+        if !redundant {
+            break;
+        }
+        start += 1;
+    }
+    tlv(INTEGER, &bytes[start..])
+}
+
+pub fn octet_string(value: &[u8]) -> Vec<u8> {
+    tlv(OCTET_STRING, value)
+}
+
+pub fn null() -> Vec<u8> {
+    tlv(NULL, &[])
+}
+
+pub fn sequence(items: &[Vec<u8>]) -> Vec<u8> {
+    tlv(SEQUENCE, &items.concat())
+}
+
+pub fn object_identifier(oid: &[u32]) -> Vec<u8> {
+    let mut contents = Vec::new();
+    if oid.len() >= 2 {
+        encode_subidentifier(oid[0] * 40 + oid[1], &mut contents);
+    }
+    for arc in oid.iter().skip(2) {
+        encode_subidentifier(*arc, &mut contents);
+    }
+    tlv(OBJECT_IDENTIFIER, &contents)
+}
+
+fn encode_subidentifier(value: u32, output: &mut Vec<u8>) {
+    let mut groups = vec![(value & 0x7f) as u8];
+    let mut rest = value >> 7;
+    while rest > 0 {
+        groups.push(0x80 | (rest & 0x7f) as u8);
+        rest >>= 7;
+    }
+    output.extend(groups.iter().rev());
+}
+
+/// Parse dotted OID, ex. "1.3.6.1.2.1.33.1.2.4.0"
+pub fn parse_oid(oid: &str) -> Option<Vec<u32>> {
+    oid.trim_start_matches('.')
+        .split('.')
+        .map(|arc| arc.parse().ok())
+        .collect()
+}
+
+/// Reads TLVs one after another
+pub struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Tag and contents of the next TLV
+    pub fn read(&mut self) -> Result<(u8, &'a [u8]), String> {
+        let truncated = || String::from("truncated message");
+        let (&tag, rest) = self.data.split_first().ok_or_else(truncated)?;
+        let (&first, mut rest) = rest.split_first().ok_or_else(truncated)?;
+        let length = if first & 0x80 == 0 {
+            first as usize
+        } else {
+            let count = (first & 0x7f) as usize;
+            if count == 0 || count > 4 || rest.len() < count {
+                return Err(String::from("invalid length"));
+            }
+            let length = rest[..count]
+                .iter()
+                .fold(0usize, |length, byte| length << 8 | *byte as usize);
+            rest = &rest[count..];
+            length
+        };
+        if rest.len() < length {
+            return Err(truncated());
+        }
+        let (contents, rest) = rest.split_at(length);
+        self.data = rest;
+        Ok((tag, contents))
+    }
+
+    /// Contents of the next TLV, which has to have `expected` tag
+    pub fn expect(&mut self, expected: u8) -> Result<&'a [u8], String> {
+        let (tag, contents) = self.read()?;
+        if tag != expected {
+            return Err(format!("expected tag {:#04x}, got {:#04x}", expected, tag));
+        }
+        Ok(contents)
+    }
+
+    pub fn integer(&mut self) -> Result<i64, String> {
+        decode_integer(self.expect(INTEGER)?)
+    }
+
+    pub fn octet_string(&mut self) -> Result<&'a [u8], String> {
+        self.expect(OCTET_STRING)
+    }
+
+    pub fn sequence(&mut self) -> Result<Reader<'a>, String> {
+        self.expect(SEQUENCE).map(Reader::new)
+    }
+
+    pub fn object_identifier(&mut self) -> Result<Vec<u32>, String> {
+        decode_object_identifier(self.expect(OBJECT_IDENTIFIER)?)
+    }
+
+    pub fn value(&mut self) -> Result<Value, String> {
+        let (tag, contents) = self.read()?;
+        Ok(match tag {
+            INTEGER => Value::Integer(decode_integer(contents)?),
+            COUNTER32 | GAUGE32 | TIME_TICKS | COUNTER64 => {
+                Value::Unsigned(decode_unsigned(contents)?)
+            }
+            OCTET_STRING => Value::OctetString(contents.to_vec()),
+            OBJECT_IDENTIFIER => Value::ObjectIdentifier(decode_object_identifier(contents)?),
+            NULL => Value::Null,
+            NO_SUCH_OBJECT | NO_SUCH_INSTANCE | END_OF_MIB_VIEW => Value::Missing,
+            _ => return Err(format!("unsupported value tag {:#04x}", tag)),
+        })
+    }
+}
+
+fn decode_integer(contents: &[u8]) -> Result<i64, String> {
+    if contents.is_empty() || contents.len() > 8 {
+        return Err(String::from("invalid integer"));
+    }
+    let sign = if contents[0] & 0x80 != 0 { -1i64 } else { 0 };
+    Ok(contents
+        .iter()
+        .fold(sign, |value, byte| value << 8 | *byte as i64))
+}
+
+fn decode_unsigned(contents: &[u8]) -> Result<u64, String> {
+    // Leading zero byte keeps large values positive
+    if contents.is_empty() || contents.len() > 9 {
+        return Err(String::from("invalid unsigned integer"));
+    }
+    Ok(contents
+        .iter()
+        .fold(0u64, |value, byte| value << 8 | *byte as u64))
+}
+
+fn decode_object_identifier(contents: &[u8]) -> Result<Vec<u32>, String> {
+    let mut subidentifiers = Vec::new();
+    let mut value = 0u32;
+    for byte in contents {
+        value = value
+            .checked_mul(128)
+            .ok_or_else(|| String::from("invalid object identifier"))?
+            | (byte & 0x7f) as u32;
+        if byte & 0x80 == 0 {
+            subidentifiers.push(value);
+            value = 0;
+        }
+    }
+    let (&first, rest) = subidentifiers
+        .split_first()
+        .ok_or_else(|| String::from("empty object identifier"))?;
+    let mut oid = match first {
+        0..=39 => vec![0, first],
+        40..=79 => vec![1, first - 40],
+        _ => vec![2, first - 80],
+    };
+    oid.extend_from_slice(rest);
+    Ok(oid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integer() {
+        assert_eq!(integer(0), [0x02, 0x01, 0x00]);
+        assert_eq!(integer(127), [0x02, 0x01, 0x7f]);
+        assert_eq!(integer(128), [0x02, 0x02, 0x00, 0x80]);
+        assert_eq!(integer(-1), [0x02, 0x01, 0xff]);
+        assert_eq!(integer(-129), [0x02, 0x02, 0xff, 0x7f]);
+        for value in [0, 1, -1, 255, 65507, -129, i64::from(i32::MAX)] {
+            assert_eq!(Reader::new(&integer(value)).integer(), Ok(value));
+        }
+    }
+
+    #[test]
+    fn test_object_identifier() {
+        let oid = parse_oid("1.3.6.1.2.1.33.1.2.4.0").unwrap();
+        let encoded = object_identifier(&oid);
+        assert_eq!(
+            encoded,
+            [0x06, 0x0a, 0x2b, 0x06, 0x01, 0x02, 0x01, 0x21, 0x01, 0x02, 0x04, 0x00]
+        );
+        assert_eq!(Reader::new(&encoded).object_identifier(), Ok(oid));
+        // Arcs above 127 take multiple bytes
+        let oid = parse_oid(".1.3.6.1.4.1.318.1.1.1.2.2.1.0").unwrap();
+        assert_eq!(
+            Reader::new(&object_identifier(&oid)).object_identifier(),
+            Ok(oid)
+        );
+        assert_eq!(parse_oid("1.3.x"), None);
+    }
+
+    #[test]
+    fn test_long_length() {
+        let contents = vec![b'a'; 300];
+        let encoded = octet_string(&contents);
+        assert_eq!(&encoded[..4], [0x04, 0x82, 0x01, 0x2c]);
+        assert_eq!(header_length(300), 4);
+        assert_eq!(header_length(12), 2);
+        assert_eq!(
+            Reader::new(&encoded).octet_string(),
+            Ok(contents.as_slice())
+        );
+        assert!(Reader::new(&encoded[..100]).octet_string().is_err());
+    }
+
+    #[test]
+    fn test_values() {
+        let mut reader = Reader::new(&[
+            0x42, 0x05, 0x00, 0xff, 0xff, 0xff, 0xff, // Gauge32 4294967295
+            0x04, 0x03, b'A', b'P', b'C', // OCTET STRING
+            0x81, 0x00, // noSuchInstance
+        ]);
+        assert_eq!(reader.value(), Ok(Value::Unsigned(u32::MAX as u64)));
+        assert_eq!(
+            reader.value().unwrap().to_variable(),
+            Some(String::from("APC"))
+        );
+        assert_eq!(reader.value(), Ok(Value::Missing));
+        assert!(reader.is_empty());
+    }
+}
@@ -0,0 +1,173 @@
+// Licensed under the Open Software License version 3.0
+//! GetRequests over UDP with v2c communities or v3 users
+use super::{
+    config::SnmpUpsTargetConfig,
+    pdu::{decode_v2c, encode_get_request, encode_v2c, VarBind},
+};
+use std::time::Duration;
+use tokio::{net::UdpSocket, time::timeout};
+
+const TIMEOUT: Duration = Duration::from_secs(3);
+const ATTEMPTS: usize = 3;
+/// Largest UDP payload
+const MAX_DATAGRAM_SIZE: usize = 65507;
+
+/// Send `request` until a datagram comes back, a new socket per request drops late responses
+async fn exchange(address: &str, request: &[u8]) -> Result<Vec<u8>, String> {
+    let bind_address = if address.starts_with('[') {
+        "[::]:0"
+    } else {
+        "0.0.0.0:0"
+    };
+    let socket = UdpSocket::bind(bind_address)
+        .await
+        .map_err(|error| error.to_string())?;
+    socket
+        .connect(address)
+        .await
+        .map_err(|error| error.to_string())?;
+    let mut buffer = vec![0u8; MAX_DATAGRAM_SIZE];
+    for _ in 0..ATTEMPTS {
+        socket
+            .send(request)
+            .await
+            .map_err(|error| error.to_string())?;
+        if let Ok(received) = timeout(TIMEOUT, socket.recv(&mut buffer)).await {
+            let length = received.map_err(|error| error.to_string())?;
+            buffer.truncate(length);
+            return Ok(buffer);
+        }
+    }
+    Err(String::from("no response"))
+}
+
+enum Security {
+    Community(String),
+    #[cfg(feature = "snmp-v3")]
+    Usm(super::usm::UsmSession),
+}
+
+/// Connection settings of a single agent, kept between polls
+pub struct Session {
+    address: String,
+    security: Security,
+    request_id: i64,
+}
+
+impl Session {
+    pub fn new(target: &SnmpUpsTargetConfig) -> Result<Self, String> {
+        let security = match target.get_v3() {
+            None => Security::Community(target.get_community()),
+            #[cfg(feature = "snmp-v3")]
+            Some(v3) => Security::Usm(super::usm::UsmSession::new(
+                v3.username,
+                v3.auth_password,
+                v3.privacy_password,
+            )?),
+            #[cfg(not(feature = "snmp-v3"))]
+            Some(_) => {
+                return Err(String::from(
+                    "SNMPv3 requires a binary built with snmp-v3 feature",
+                ))
+            }
+        };
+        Ok(Self {
+            address: target.get_address(),
+            security,
+            request_id: 0,
+        })
+    }
+
+    fn next_request_id(&mut self) -> i64 {
+        // Request ids are Integer32
+        self.request_id = self.request_id % i64::from(i32::MAX) + 1;
+        self.request_id
+    }
+
+    pub async fn get(&mut self, oids: &[Vec<u32>]) -> Result<Vec<VarBind>, String> {
+        let request_id = self.next_request_id();
+        let pdu = encode_get_request(request_id, oids);
+        match &self.security {
+            Security::Community(community) => {
+                let response = exchange(&self.address, &encode_v2c(community, pdu)).await?;
+                decode_v2c(&response)?.into_response(request_id)
+            }
+            #[cfg(feature = "snmp-v3")]
+            Security::Usm(_) => self.get_v3(request_id, pdu).await,
+        }
+    }
+
+    #[cfg(feature = "snmp-v3")]
+    async fn get_v3(&mut self, request_id: i64, pdu: Vec<u8>) -> Result<Vec<VarBind>, String> {
+        use super::usm::{is_not_in_time_window, report_reason};
+
+        let discovery_id = self.next_request_id();
+        let message_id = self.next_request_id();
+        let session = match &mut self.security {
+            Security::Usm(session) => session,
+            Security::Community(_) => unreachable!(),
+        };
+        // Engine time is discovered on every poll, so agent reboots don't need any handling
+        let response = exchange(&self.address, &session.discovery_request(discovery_id)).await?;
+        let mut engine = session.decode_discovery(&response)?;
+        // Agents may reveal engine time only in an authenticated report
+        for attempt in 0..2 {
+            let request = session.request(&engine, message_id, pdu.clone());
+            let response = exchange(&self.address, &request).await?;
+            let response = session.decode_response(&engine, message_id, &response)?;
+            if response.pdu.tag == super::pdu::REPORT {
+                if attempt == 0 && is_not_in_time_window(&response.pdu) {
+                    engine.boots = response.boots;
+                    engine.time = response.time;
+                    continue;
+                }
+                return Err(report_reason(&response.pdu));
+            }
+            return response.pdu.into_response(request_id);
+        }
+        Err(String::from("agent keeps reporting time window errors"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snmp_ups::ber;
+
+    #[tokio::test]
+    async fn test_get_v2c() {
+        let agent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = agent.local_addr().unwrap().to_string();
+        let target: SnmpUpsTargetConfig = serde_json::from_str(&format!(
+            r#"{{"host": "127.0.0.1", "port": {}, "community": "private"}}"#,
+            address.rsplit(':').next().unwrap()
+        ))
+        .unwrap();
+        let oid = ber::parse_oid("1.3.6.1.2.1.33.1.4.1.0").unwrap();
+        let oid_clone = oid.clone();
+        tokio::spawn(async move {
+            let mut buffer = [0u8; 1500];
+            let (length, peer) = agent.recv_from(&mut buffer).await.unwrap();
+            let mut message = ber::Reader::new(&buffer[..length]).sequence().unwrap();
+            assert_eq!(message.integer(), Ok(1));
+            assert_eq!(message.octet_string(), Ok(&b"private"[..]));
+            let (_, pdu) = message.read().unwrap();
+            let request_id = ber::Reader::new(pdu).integer().unwrap();
+            let varbind = ber::sequence(&[ber::object_identifier(&oid_clone), ber::integer(3)]);
+            let contents = [
+                ber::integer(request_id),
+                ber::integer(0),
+                ber::integer(0),
+                ber::sequence(&[varbind]),
+            ]
+            .concat();
+            let response = encode_v2c("private", ber::tlv(0xa2, &contents));
+            agent.send_to(&response, peer).await.unwrap();
+        });
+        let mut session = Session::new(&target).unwrap();
+        assert_eq!(
+            session.get(&[oid.clone()]).await,
+            Ok(vec![(oid, ber::Value::Integer(3))])
+        );
+    }
+}
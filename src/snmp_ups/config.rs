@@ -0,0 +1,127 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const DEFAULT_PORT: u16 = 161;
+
+/// User of SNMPv3, security level depends on which passwords are set
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnmpV3Config {
+    pub username: String,
+    // HMAC-SHA-96, authNoPriv if privacy_password isn't set
+    pub auth_password: Option<String>,
+    // AES-128, requires auth_password
+    pub privacy_password: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnmpUpsTargetConfig {
+    host: String,
+    port: Option<u16>,
+    // hw.id of the UPS, snmp@<host>:<port> if not set
+    id: Option<String>,
+    // v2c community, ignored if v3 is set
+    community: Option<String>,
+    v3: Option<SnmpV3Config>,
+}
+
+impl Example for SnmpUpsTargetConfig {
+    fn example() -> Self {
+        Self {
+            host: String::from("ups-rack.lan"),
+            port: Some(DEFAULT_PORT),
+            id: Some(String::from("ups-rack")),
+            community: None,
+            v3: Some(SnmpV3Config {
+                username: String::from("monitor"),
+                auth_password: Some(String::from("EXAMPLE_AUTH_PASSWORD")),
+                privacy_password: Some(String::from("EXAMPLE_PRIVACY_PASSWORD")),
+            }),
+        }
+    }
+}
+
+impl SnmpUpsTargetConfig {
+    pub fn get_address(&self) -> String {
+        let port = self.port.unwrap_or(DEFAULT_PORT);
+        // IPv6 addresses have to be bracketed
+        match self.host.contains(':') {
+            true => format!("[{}]:{}", self.host, port),
+            false => format!("{}:{}", self.host, port),
+        }
+    }
+
+    pub fn get_id(&self) -> String {
+        self.id
+            .clone()
+            .unwrap_or_else(|| format!("snmp@{}", self.get_address()))
+    }
+
+    pub fn get_community(&self) -> String {
+        self.community
+            .clone()
+            .unwrap_or_else(|| String::from("public"))
+    }
+
+    pub fn get_v3(&self) -> Option<SnmpV3Config> {
+        self.v3.clone()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnmpUpsConfig {
+    enabled: Option<bool>,
+    cooldown: Option<Duration>,
+    targets: Option<Vec<SnmpUpsTargetConfig>>,
+}
+
+impl Default for SnmpUpsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            cooldown: Some(Duration::from_secs(10)),
+            targets: None,
+        }
+    }
+}
+
+impl Example for SnmpUpsConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(false),
+            cooldown: Some(Duration::from_secs(10)),
+            targets: Some(vec![SnmpUpsTargetConfig::example()]),
+        }
+    }
+}
+
+impl SnmpUpsConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_cooldown(&self) -> Duration {
+        self.cooldown.unwrap_or(Duration::from_secs(10))
+    }
+
+    pub fn get_targets(&self) -> Vec<SnmpUpsTargetConfig> {
+        self.targets.clone().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_config_defaults() {
+        let config: SnmpUpsTargetConfig = serde_json::from_str(r#"{"host": "10.0.0.5"}"#).unwrap();
+        assert_eq!(config.get_address(), "10.0.0.5:161");
+        assert_eq!(config.get_id(), "snmp@10.0.0.5:161");
+        assert_eq!(config.get_community(), "public");
+        assert_eq!(config.get_v3(), None);
+        let config: SnmpUpsTargetConfig = serde_json::from_str(r#"{"host": "fd00::5"}"#).unwrap();
+        assert_eq!(config.get_address(), "[fd00::5]:161");
+    }
+}
@@ -0,0 +1,168 @@
+// Licensed under the Open Software License version 3.0
+//! RFC 1628 UPS-MIB objects mapped to NUT variables
+use super::{
+    ber::{parse_oid, Value},
+    pdu::VarBind,
+};
+use std::collections::HashMap;
+
+/// upsMIB.upsObjects
+const UPS_OBJECTS: &str = "1.3.6.1.2.1.33.1";
+const BATTERY_STATUS: &str = "2.1.0";
+const OUTPUT_SOURCE: &str = "4.1.0";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Unit {
+    AsIs,
+    // 0.1 V, 0.1 Hz or 0.1 A
+    Tenths,
+    // NUT uses seconds for runtime
+    Minutes,
+}
+
+/// Objects relative to upsObjects, tables are read for the first line (single-phase UPSes)
+const OBJECTS: [(&str, &str, Unit); 19] = [
+    ("1.1.0", "ups.mfr", Unit::AsIs),
+    ("1.2.0", "ups.model", Unit::AsIs),
+    ("1.3.0", "ups.firmware", Unit::AsIs),
+    ("2.3.0", "battery.runtime", Unit::Minutes),
+    ("2.4.0", "battery.charge", Unit::AsIs),
+    ("2.5.0", "battery.voltage", Unit::Tenths),
+    ("2.7.0", "battery.temperature", Unit::AsIs),
+    ("3.3.1.2.1", "input.frequency", Unit::Tenths),
+    ("3.3.1.3.1", "input.voltage", Unit::AsIs),
+    ("4.2.0", "output.frequency", Unit::Tenths),
+    ("4.4.1.2.1", "output.voltage", Unit::AsIs),
+    ("4.4.1.3.1", "output.current", Unit::Tenths),
+    ("4.4.1.4.1", "ups.realpower", Unit::AsIs),
+    ("4.4.1.5.1", "ups.load", Unit::AsIs),
+    ("9.1.0", "input.voltage.nominal", Unit::AsIs),
+    ("9.3.0", "output.voltage.nominal", Unit::AsIs),
+    ("9.5.0", "ups.power.nominal", Unit::AsIs),
+    ("9.6.0", "ups.realpower.nominal", Unit::AsIs),
+    ("9.7.0", "battery.runtime.low", Unit::Minutes),
+];
+
+fn object_oid(object: &str) -> Vec<u32> {
+    parse_oid(&format!("{}.{}", UPS_OBJECTS, object)).unwrap()
+}
+
+/// OIDs requested on every poll
+pub fn requested_oids() -> Vec<Vec<u32>> {
+    OBJECTS
+        .iter()
+        .map(|(object, _, _)| *object)
+        .chain([BATTERY_STATUS, OUTPUT_SOURCE])
+        .map(object_oid)
+        .collect()
+}
+
+fn convert(value: &Value, unit: Unit) -> Option<String> {
+    let raw = value.to_variable()?;
+    if unit == Unit::AsIs {
+        return Some(raw);
+    }
+    let number: f64 = raw.parse().ok()?;
+    let converted = match unit {
+        Unit::Tenths => number / 10.0,
+        _ => number * 60.0,
+    };
+    Some(converted.to_string())
+}
+
+/// `ups.status` flags of upsOutputSource and upsBatteryStatus
+fn status(output_source: Option<i64>, battery_status: Option<i64>) -> Option<String> {
+    let mut flags: Vec<&str> = match output_source? {
+        // none
+        2 => vec!["OFF"],
+        // normal
+        3 => vec!["OL"],
+        // bypass
+        4 => vec!["OL", "BYPASS"],
+        // battery
+        5 => vec!["OB"],
+        // booster
+        6 => vec!["OL", "BOOST"],
+        // reducer
+        7 => vec!["OL", "TRIM"],
+        _ => vec![],
+    };
+    // batteryLow or batteryDepleted
+    if matches!(battery_status, Some(3) | Some(4)) {
+        flags.push("LB");
+    }
+    Some(flags.join(" "))
+}
+
+/// Variables named like NUT ones, so UPS shutdown, load shedding and runtime projection work the same
+pub fn to_variables(varbinds: &[VarBind]) -> Result<HashMap<String, String>, String> {
+    let values: HashMap<&[u32], &Value> = varbinds
+        .iter()
+        .map(|(oid, value)| (oid.as_slice(), value))
+        .collect();
+    let integer = |object: &str| match values.get(object_oid(object).as_slice()) {
+        Some(Value::Integer(value)) => Some(*value),
+        _ => None,
+    };
+    let mut variables = HashMap::new();
+    for (object, variable, unit) in OBJECTS {
+        if let Some(value) = values
+            .get(object_oid(object).as_slice())
+            .and_then(|value| convert(value, unit))
+        {
+            variables.insert(String::from(variable), value);
+        }
+    }
+    if let Some(status) = status(integer(OUTPUT_SOURCE), integer(BATTERY_STATUS)) {
+        variables.insert(String::from("ups.status"), status);
+    }
+    if variables.is_empty() {
+        return Err(String::from("agent doesn't implement UPS-MIB"));
+    }
+    Ok(variables)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn varbind(object: &str, value: Value) -> VarBind {
+        (object_oid(object), value)
+    }
+
+    #[test]
+    fn test_to_variables() {
+        let varbinds = vec![
+            varbind("1.2.0", Value::OctetString(b"Smart-UPS 1500 ".to_vec())),
+            varbind("2.1.0", Value::Integer(3)),
+            varbind("2.3.0", Value::Integer(4)),
+            varbind("2.4.0", Value::Integer(12)),
+            varbind("2.5.0", Value::Integer(245)),
+            varbind("3.3.1.2.1", Value::Integer(499)),
+            varbind("4.1.0", Value::Integer(5)),
+            varbind("4.4.1.5.1", Value::Integer(38)),
+            varbind("2.7.0", Value::Missing),
+        ];
+        let variables = to_variables(&varbinds).unwrap();
+        assert_eq!(variables["ups.model"], "Smart-UPS 1500");
+        assert_eq!(variables["ups.status"], "OB LB");
+        assert_eq!(variables["battery.runtime"], "240");
+        assert_eq!(variables["battery.charge"], "12");
+        assert_eq!(variables["battery.voltage"], "24.5");
+        assert_eq!(variables["input.frequency"], "49.9");
+        assert_eq!(variables["ups.load"], "38");
+        assert!(!variables.contains_key("battery.temperature"));
+    }
+
+    #[test]
+    fn test_status() {
+        assert_eq!(status(Some(3), Some(2)), Some(String::from("OL")));
+        assert_eq!(status(Some(7), None), Some(String::from("OL TRIM")));
+        assert_eq!(status(None, Some(3)), None);
+        let missing: Vec<VarBind> = requested_oids()
+            .into_iter()
+            .map(|oid| (oid, Value::Missing))
+            .collect();
+        assert!(to_variables(&missing).is_err());
+    }
+}
@@ -0,0 +1,9 @@
+// Licensed under the Open Software License version 3.0
+mod ber;
+mod client;
+pub mod config;
+mod mib;
+mod pdu;
+pub mod sender;
+#[cfg(feature = "snmp-v3")]
+mod usm;
@@ -0,0 +1,127 @@
+// Licensed under the Open Software License version 3.0
+//! GetRequest PDUs and community-based (v2c) messages
+use super::ber::{self, Reader, Value};
+
+const GET_REQUEST: u8 = 0xa0;
+const RESPONSE: u8 = 0xa2;
+#[cfg_attr(not(feature = "snmp-v3"), allow(dead_code))]
+pub const REPORT: u8 = 0xa8;
+const VERSION_2C: i64 = 1;
+
+pub type VarBind = (Vec<u32>, Value);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pdu {
+    pub tag: u8,
+    pub request_id: i64,
+    pub error_status: i64,
+    pub varbinds: Vec<VarBind>,
+}
+
+impl Pdu {
+    /// Variable bindings of a successful response to `request_id`
+    pub fn into_response(self, request_id: i64) -> Result<Vec<VarBind>, String> {
+        if self.tag != RESPONSE {
+            return Err(format!("unexpected PDU type {:#04x}", self.tag));
+        }
+        if self.request_id != request_id {
+            return Err(String::from("response to a different request"));
+        }
+        if self.error_status != 0 {
+            return Err(format!("agent responded with error {}", self.error_status));
+        }
+        Ok(self.varbinds)
+    }
+}
+
+pub fn encode_get_request(request_id: i64, oids: &[Vec<u32>]) -> Vec<u8> {
+    let varbinds: Vec<Vec<u8>> = oids
+        .iter()
+        .map(|oid| ber::sequence(&[ber::object_identifier(oid), ber::null()]))
+        .collect();
+    let contents = [
+        ber::integer(request_id),
+        ber::integer(0),
+        ber::integer(0),
+        ber::sequence(&varbinds),
+    ]
+    .concat();
+    ber::tlv(GET_REQUEST, &contents)
+}
+
+pub fn decode_pdu(reader: &mut Reader) -> Result<Pdu, String> {
+    let (tag, contents) = reader.read()?;
+    let mut pdu = Reader::new(contents);
+    let request_id = pdu.integer()?;
+    let error_status = pdu.integer()?;
+    let _error_index = pdu.integer()?;
+    let mut list = pdu.sequence()?;
+    let mut varbinds = Vec::new();
+    while !list.is_empty() {
+        let mut varbind = list.sequence()?;
+        varbinds.push((varbind.object_identifier()?, varbind.value()?));
+    }
+    Ok(Pdu {
+        tag,
+        request_id,
+        error_status,
+        varbinds,
+    })
+}
+
+pub fn encode_v2c(community: &str, pdu: Vec<u8>) -> Vec<u8> {
+    ber::sequence(&[
+        ber::integer(VERSION_2C),
+        ber::octet_string(community.as_bytes()),
+        pdu,
+    ])
+}
+
+pub fn decode_v2c(data: &[u8]) -> Result<Pdu, String> {
+    let mut message = Reader::new(data).sequence()?;
+    if message.integer()? != VERSION_2C {
+        return Err(String::from("unexpected SNMP version"));
+    }
+    let _community = message.octet_string()?;
+    decode_pdu(&mut message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_get_request() {
+        let oid = ber::parse_oid("1.3.6.1.2.1.33.1.2.4.0").unwrap();
+        let message = encode_v2c("public", encode_get_request(1, &[oid]));
+        // Same bytes as `snmpget -v2c -c public <host> 1.3.6.1.2.1.33.1.2.4.0` with request id 1
+        assert_eq!(
+            message,
+            [
+                0x30, 0x28, 0x02, 0x01, 0x01, 0x04, 0x06, b'p', b'u', b'b', b'l', b'i', b'c', 0xa0,
+                0x1b, 0x02, 0x01, 0x01, 0x02, 0x01, 0x00, 0x02, 0x01, 0x00, 0x30, 0x10, 0x30, 0x0e,
+                0x06, 0x0a, 0x2b, 0x06, 0x01, 0x02, 0x01, 0x21, 0x01, 0x02, 0x04, 0x00, 0x05, 0x00
+            ][..]
+        );
+    }
+
+    #[test]
+    fn test_decode_response() {
+        let oid = ber::parse_oid("1.3.6.1.2.1.33.1.2.4.0").unwrap();
+        let varbind = ber::sequence(&[ber::object_identifier(&oid), ber::integer(87)]);
+        let contents = [
+            ber::integer(7),
+            ber::integer(0),
+            ber::integer(0),
+            ber::sequence(&[varbind]),
+        ]
+        .concat();
+        let message = encode_v2c("public", ber::tlv(RESPONSE, &contents));
+        let pdu = decode_v2c(&message).unwrap();
+        assert_eq!(
+            pdu.clone().into_response(7),
+            Ok(vec![(oid, Value::Integer(87))])
+        );
+        assert!(pdu.into_response(8).is_err());
+    }
+}
@@ -0,0 +1,139 @@
+// Licensed under the Open Software License version 3.0
+use super::{
+    client::Session,
+    config::{SnmpUpsConfig, SnmpUpsTargetConfig},
+    mib::{requested_oids, to_variables},
+};
+use crate::{
+    dedup_log::{info_resolved, warn_deduplicated},
+    hardware::types::{HardwareMetadata, HardwareType, SourceType},
+    introspection,
+    nut::sender::{UninterruptiblePowerSupplyData, UpsUpdate},
+    relations::config::RelationsConfig,
+    sampling,
+    scheduler::job::{PollFuture, PollJob},
+};
+use std::{cmp::max, collections::HashMap, time::Duration};
+use tokio::{sync::broadcast, time::sleep};
+
+const PUBLISHER: &str = "snmp_ups";
+
+/// Agent of a single UPS, an invalid config is logged on every poll
+struct Target {
+    config: SnmpUpsTargetConfig,
+    session: Result<Session, String>,
+}
+
+impl Target {
+    fn new(config: SnmpUpsTargetConfig) -> Self {
+        let session = Session::new(&config);
+        Self { config, session }
+    }
+
+    async fn read(&mut self, oids: &[Vec<u32>]) -> Result<HashMap<String, String>, String> {
+        let session = self.session.as_mut().map_err(|error| error.clone())?;
+        to_variables(&session.get(oids).await?)
+    }
+}
+
+pub struct SnmpUpsPoller {
+    targets: Vec<Target>,
+    oids: Vec<Vec<u32>>,
+    relations_config: RelationsConfig,
+    cooldown: Duration,
+    tx: broadcast::Sender<UpsUpdate>,
+}
+
+impl SnmpUpsPoller {
+    pub fn new(
+        config: &SnmpUpsConfig,
+        relations_config: RelationsConfig,
+        tx: broadcast::Sender<UpsUpdate>,
+    ) -> Self {
+        Self {
+            targets: config.get_targets().into_iter().map(Target::new).collect(),
+            oids: requested_oids(),
+            relations_config,
+            cooldown: max(config.get_cooldown(), Duration::from_secs(1)),
+            tx,
+        }
+    }
+
+    async fn read_targets(&mut self) -> Vec<UninterruptiblePowerSupplyData> {
+        let mut upses = Vec::new();
+        for target in &mut self.targets {
+            let id = target.config.get_id();
+            let key = format!("snmp_ups:{}", id);
+            match target.read(&self.oids).await {
+                Ok(variables) => {
+                    info_resolved!(key, "Reading {} over SNMP again", id);
+                    let mut meta = HardwareMetadata::new(
+                        id,
+                        HardwareType::UninterruptiblePowerSupply,
+                        SourceType::Snmp,
+                    )
+                    .measured_now();
+                    self.relations_config.annotate(&mut meta);
+                    upses.push(UninterruptiblePowerSupplyData {
+                        meta,
+                        variables,
+                        clients: None,
+                    });
+                }
+                Err(error) => {
+                    warn_deduplicated!(key, "Failed to read {} over SNMP: {}", id, error);
+                }
+            }
+        }
+        upses
+    }
+
+    /// Publish all readable UPSes, returns delay until the next poll
+    pub async fn poll_once(&mut self) -> Duration {
+        introspection::mark_iteration("snmp_ups");
+        let upses = self.read_targets().await;
+        tracing::trace!("Sending {:?} to channel", upses);
+        if self.tx.receiver_count() > 0 {
+            self.tx.send(UpsUpdate::new(PUBLISHER, upses)).unwrap();
+            introspection::observe_channel("ups_monitoring", &self.tx);
+        }
+        introspection::mark_idle("snmp_ups");
+        self.cooldown
+    }
+}
+
+impl PollJob for SnmpUpsPoller {
+    fn name(&self) -> String {
+        String::from("snmp_ups")
+    }
+
+    fn poll(&mut self) -> PollFuture<'_> {
+        Box::pin(self.poll_once())
+    }
+}
+
+pub async fn start_snmp_ups_loop(
+    mut shutdown_rx: broadcast::Receiver<()>,
+    config: SnmpUpsConfig,
+    relations_config: RelationsConfig,
+    tx: broadcast::Sender<UpsUpdate>,
+) {
+    // Check if module is enabled
+    if !config.is_enabled() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+    tracing::debug!("Starting SNMP UPS loop");
+    let mut poller = SnmpUpsPoller::new(&config, relations_config, tx);
+    let _task = introspection::task_started("snmp_ups");
+    loop {
+        let delay = poller.poll_once().await;
+        tokio::select! {
+            _ = shutdown_rx.recv() => {
+                tracing::trace!("Shutting down SNMP UPS loop");
+                break;
+            }
+            _ = sleep(sampling::tick::aligned(delay)) => {}
+        }
+    }
+}
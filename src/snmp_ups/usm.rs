@@ -0,0 +1,541 @@
+// Licensed under the Open Software License version 3.0
+//! SNMPv3 User-based Security Model (RFC 3414) with HMAC-SHA-96 and AES-128 (RFC 3826)
+use super::{
+    ber::{self, Reader},
+    pdu::{decode_pdu, Pdu},
+};
+use aes::Aes128;
+use cfb_mode::{
+    cipher::{AsyncStreamCipher, KeyIvInit},
+    Decryptor, Encryptor,
+};
+use hmac::{Hmac, Mac};
+use sha1::{Digest, Sha1};
+
+const VERSION_3: i64 = 3;
+const MAX_MESSAGE_SIZE: i64 = 65507;
+const USM_SECURITY_MODEL: i64 = 3;
+const FLAG_AUTH: u8 = 0x01;
+const FLAG_PRIV: u8 = 0x02;
+const FLAG_REPORTABLE: u8 = 0x04;
+/// HMAC-SHA-96 is truncated to 12 bytes
+const AUTH_LENGTH: usize = 12;
+/// usmStats counters sent in reports, RFC 3414 section 5
+const USM_STATS: [u32; 9] = [1, 3, 6, 1, 6, 3, 15, 1, 1];
+const NOT_IN_TIME_WINDOWS: u32 = 2;
+const REPORT_REASONS: [&str; 6] = [
+    "unsupported security level",
+    "not in time window",
+    "unknown user name",
+    "unknown engine id",
+    "wrong digest",
+    "decryption error",
+];
+/// Shortest password allowed by RFC 3414
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+type HmacSha1 = Hmac<Sha1>;
+
+/// Password to key algorithm of RFC 3414 A.2.2, password repeated to 1 MiB
+fn password_to_key(password: &str) -> [u8; 20] {
+    let password = password.as_bytes();
+    let mut hasher = Sha1::new();
+    let mut chunk = [0u8; 64];
+    let mut index = 0;
+    for _ in 0..(1024 * 1024 / chunk.len()) {
+        for byte in chunk.iter_mut() {
+            *byte = password[index % password.len()];
+            index += 1;
+        }
+        hasher.update(chunk);
+    }
+    hasher.finalize().into()
+}
+
+/// Key of `password` bound to a single engine
+fn localized_key(password: &str, engine_id: &[u8]) -> [u8; 20] {
+    let key = password_to_key(password);
+    Sha1::new()
+        .chain_update(key)
+        .chain_update(engine_id)
+        .chain_update(key)
+        .finalize()
+        .into()
+}
+
+fn authenticate(key: &[u8], message: &[u8]) -> [u8; AUTH_LENGTH] {
+    let mut mac = HmacSha1::new_from_slice(key).unwrap();
+    mac.update(message);
+    let digest = mac.finalize().into_bytes();
+    let mut truncated = [0u8; AUTH_LENGTH];
+    truncated.copy_from_slice(&digest[..AUTH_LENGTH]);
+    truncated
+}
+
+fn initialization_vector(boots: i64, time: i64, salt: &[u8]) -> Vec<u8> {
+    let mut iv = Vec::with_capacity(16);
+    iv.extend_from_slice(&(boots as u32).to_be_bytes());
+    iv.extend_from_slice(&(time as u32).to_be_bytes());
+    iv.extend_from_slice(salt);
+    iv
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SecurityParameters<'a> {
+    engine_id: &'a [u8],
+    boots: i64,
+    time: i64,
+    username: &'a [u8],
+    auth: &'a [u8],
+    privacy: &'a [u8],
+}
+
+/// Message and offset of authentication parameters, which are filled in after encoding
+fn encode_message(
+    message_id: i64,
+    flags: u8,
+    parameters: &SecurityParameters,
+    data: Vec<u8>,
+) -> (Vec<u8>, usize) {
+    let prefix = [
+        ber::octet_string(parameters.engine_id),
+        ber::integer(parameters.boots),
+        ber::integer(parameters.time),
+        ber::octet_string(parameters.username),
+    ]
+    .concat();
+    let usm_contents = [
+        prefix.clone(),
+        ber::octet_string(parameters.auth),
+        ber::octet_string(parameters.privacy),
+    ]
+    .concat();
+    let usm = ber::tlv(ber::SEQUENCE, &usm_contents);
+    let version = ber::integer(VERSION_3);
+    let header = ber::sequence(&[
+        ber::integer(message_id),
+        ber::integer(MAX_MESSAGE_SIZE),
+        ber::octet_string(&[flags]),
+        ber::integer(USM_SECURITY_MODEL),
+    ]);
+    let security = ber::octet_string(&usm);
+    let auth_offset = version.len()
+        + header.len()
+        + ber::header_length(usm.len())
+        + ber::header_length(usm_contents.len())
+        + prefix.len()
+        + ber::header_length(parameters.auth.len());
+    let contents = [version, header, security, data].concat();
+    let auth_offset = ber::header_length(contents.len()) + auth_offset;
+    (ber::tlv(ber::SEQUENCE, &contents), auth_offset)
+}
+
+struct Message<'a> {
+    message_id: i64,
+    flags: u8,
+    parameters: SecurityParameters<'a>,
+    // Tag and contents of plain scoped PDU or encrypted one
+    data: (u8, &'a [u8]),
+}
+
+fn decode_message(data: &[u8]) -> Result<Message, String> {
+    let mut message = Reader::new(data).sequence()?;
+    if message.integer()? != VERSION_3 {
+        return Err(String::from("unexpected SNMP version"));
+    }
+    let mut header = message.sequence()?;
+    let message_id = header.integer()?;
+    let _max_size = header.integer()?;
+    let flags = header.octet_string()?.first().copied().unwrap_or_default();
+    if header.integer()? != USM_SECURITY_MODEL {
+        return Err(String::from("unexpected security model"));
+    }
+    let mut usm = Reader::new(message.octet_string()?).sequence()?;
+    let parameters = SecurityParameters {
+        engine_id: usm.octet_string()?,
+        boots: usm.integer()?,
+        time: usm.integer()?,
+        username: usm.octet_string()?,
+        auth: usm.octet_string()?,
+        privacy: usm.octet_string()?,
+    };
+    Ok(Message {
+        message_id,
+        flags,
+        parameters,
+        data: message.read()?,
+    })
+}
+
+fn encode_scoped_pdu(engine_id: &[u8], pdu: Vec<u8>) -> Vec<u8> {
+    ber::sequence(&[ber::octet_string(engine_id), ber::octet_string(&[]), pdu])
+}
+
+fn decode_scoped_pdu(contents: &[u8]) -> Result<Pdu, String> {
+    let mut scoped = Reader::new(contents);
+    let _context_engine_id = scoped.octet_string()?;
+    let _context_name = scoped.octet_string()?;
+    decode_pdu(&mut scoped)
+}
+
+/// Authoritative engine of the agent, learned from reports
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Engine {
+    pub id: Vec<u8>,
+    pub boots: i64,
+    pub time: i64,
+}
+
+/// Decoded response with engine time, which is authoritative if it was authenticated
+#[derive(Debug)]
+pub struct Response {
+    pub pdu: Pdu,
+    pub boots: i64,
+    pub time: i64,
+}
+
+/// Reason of a report sent instead of a response, ex. "wrong digest"
+pub fn report_reason(pdu: &Pdu) -> String {
+    pdu.varbinds
+        .iter()
+        .find_map(|(oid, _)| {
+            let counter = oid.strip_prefix(USM_STATS.as_slice())?.first()?;
+            REPORT_REASONS.get((*counter as usize).checked_sub(1)?)
+        })
+        .map(|reason| String::from(*reason))
+        .unwrap_or_else(|| String::from("agent sent a report"))
+}
+
+pub fn is_not_in_time_window(pdu: &Pdu) -> bool {
+    pdu.varbinds.iter().any(|(oid, _)| {
+        oid.strip_prefix(USM_STATS.as_slice())
+            .and_then(|rest| rest.first())
+            == Some(&NOT_IN_TIME_WINDOWS)
+    })
+}
+
+struct LocalizedKeys {
+    engine_id: Vec<u8>,
+    auth: Option<[u8; 20]>,
+    privacy: Option<[u8; 20]>,
+}
+
+/// Credentials of a single user, with keys localized to the last seen engine
+pub struct UsmSession {
+    username: String,
+    auth_password: Option<String>,
+    privacy_password: Option<String>,
+    keys: Option<LocalizedKeys>,
+    salt: u64,
+}
+
+impl UsmSession {
+    pub fn new(
+        username: String,
+        auth_password: Option<String>,
+        privacy_password: Option<String>,
+    ) -> Result<Self, String> {
+        if privacy_password.is_some() && auth_password.is_none() {
+            return Err(String::from("privacy_password requires auth_password"));
+        }
+        let too_short = [&auth_password, &privacy_password].iter().any(
+            |password| matches!(password, Some(password) if password.len() < MIN_PASSWORD_LENGTH),
+        );
+        if too_short {
+            return Err(format!(
+                "passwords have to be at least {} characters long",
+                MIN_PASSWORD_LENGTH
+            ));
+        }
+        Ok(Self {
+            username,
+            auth_password,
+            privacy_password,
+            keys: None,
+            salt: uuid::Uuid::new_v4().as_u64_pair().0,
+        })
+    }
+
+    fn flags(&self) -> u8 {
+        let mut flags = FLAG_REPORTABLE;
+        if self.auth_password.is_some() {
+            flags |= FLAG_AUTH;
+        }
+        if self.privacy_password.is_some() {
+            flags |= FLAG_PRIV;
+        }
+        flags
+    }
+
+    /// Keys are localized once per engine, as hashing 1 MiB on every poll is wasteful
+    fn keys(&mut self, engine_id: &[u8]) -> &LocalizedKeys {
+        let outdated = !matches!(&self.keys, Some(keys) if keys.engine_id == engine_id);
+        if outdated {
+            self.keys = Some(LocalizedKeys {
+                engine_id: engine_id.to_vec(),
+                auth: self
+                    .auth_password
+                    .as_deref()
+                    .map(|password| localized_key(password, engine_id)),
+                privacy: self
+                    .privacy_password
+                    .as_deref()
+                    .map(|password| localized_key(password, engine_id)),
+            });
+        }
+        self.keys.as_ref().unwrap()
+    }
+
+    /// Empty unauthenticated request, answered with a report revealing the engine
+    pub fn discovery_request(&self, message_id: i64) -> Vec<u8> {
+        let parameters = SecurityParameters {
+            engine_id: &[],
+            boots: 0,
+            time: 0,
+            username: &[],
+            auth: &[],
+            privacy: &[],
+        };
+        let pdu = super::pdu::encode_get_request(message_id, &[]);
+        encode_message(
+            message_id,
+            FLAG_REPORTABLE,
+            &parameters,
+            encode_scoped_pdu(&[], pdu),
+        )
+        .0
+    }
+
+    pub fn decode_discovery(&self, data: &[u8]) -> Result<Engine, String> {
+        let message = decode_message(data)?;
+        if message.parameters.engine_id.is_empty() {
+            return Err(String::from("agent didn't reveal its engine id"));
+        }
+        Ok(Engine {
+            id: message.parameters.engine_id.to_vec(),
+            boots: message.parameters.boots,
+            time: message.parameters.time,
+        })
+    }
+
+    pub fn request(&mut self, engine: &Engine, message_id: i64, pdu: Vec<u8>) -> Vec<u8> {
+        let flags = self.flags();
+        self.salt = self.salt.wrapping_add(1);
+        let salt = self.salt.to_be_bytes();
+        let username = self.username.clone();
+        let keys = self.keys(&engine.id);
+        let mut data = encode_scoped_pdu(&engine.id, pdu);
+        let mut privacy: &[u8] = &[];
+        if let Some(key) = keys.privacy {
+            let iv = initialization_vector(engine.boots, engine.time, &salt);
+            Encryptor::<Aes128>::new_from_slices(&key[..16], &iv)
+                .unwrap()
+                .encrypt(&mut data);
+            data = ber::octet_string(&data);
+            privacy = &salt;
+        }
+        let auth = [0u8; AUTH_LENGTH];
+        let parameters = SecurityParameters {
+            engine_id: &engine.id,
+            boots: engine.boots,
+            time: engine.time,
+            username: username.as_bytes(),
+            auth: if keys.auth.is_some() { &auth[..] } else { &[] },
+            privacy,
+        };
+        let (mut message, offset) = encode_message(message_id, flags, &parameters, data);
+        if let Some(key) = keys.auth {
+            let digest = authenticate(&key, &message);
+            message[offset..offset + AUTH_LENGTH].copy_from_slice(&digest);
+        }
+        message
+    }
+
+    /// Verify and decrypt response to `message_id`, reports of failures may be unauthenticated
+    pub fn decode_response(
+        &mut self,
+        engine: &Engine,
+        message_id: i64,
+        data: &[u8],
+    ) -> Result<Response, String> {
+        let message = decode_message(data)?;
+        if message.message_id != message_id {
+            return Err(String::from("response to a different message"));
+        }
+        let keys = self.keys(&engine.id);
+        let authenticated = message.flags & FLAG_AUTH != 0;
+        if authenticated {
+            let key = keys
+                .auth
+                .ok_or_else(|| String::from("unexpected authenticated response"))?;
+            let received = message.parameters.auth;
+            if received.len() != AUTH_LENGTH {
+                return Err(String::from("invalid authentication parameters"));
+            }
+            // Digest is computed with zeroed authentication parameters
+            let offset = received.as_ptr() as usize - data.as_ptr() as usize;
+            let mut zeroed = data.to_vec();
+            zeroed[offset..offset + AUTH_LENGTH].fill(0);
+            if authenticate(&key, &zeroed) != received {
+                return Err(String::from("response has a wrong digest"));
+            }
+        }
+        let (tag, contents) = message.data;
+        let pdu = if message.flags & FLAG_PRIV != 0 {
+            let key = keys
+                .privacy
+                .ok_or_else(|| String::from("unexpected encrypted response"))?;
+            if tag != ber::OCTET_STRING || !authenticated {
+                return Err(String::from("invalid encrypted response"));
+            }
+            let iv = initialization_vector(
+                message.parameters.boots,
+                message.parameters.time,
+                message.parameters.privacy,
+            );
+            let mut decrypted = contents.to_vec();
+            Decryptor::<Aes128>::new_from_slices(&key[..16], &iv)
+                .map_err(|_| String::from("invalid privacy parameters"))?
+                .decrypt(&mut decrypted);
+            decode_scoped_pdu(&decrypted)?
+        } else {
+            if tag != ber::SEQUENCE {
+                return Err(String::from("invalid scoped PDU"));
+            }
+            decode_scoped_pdu(contents)?
+        };
+        if !authenticated && keys.auth.is_some() && pdu.tag != super::pdu::REPORT {
+            return Err(String::from("unauthenticated response"));
+        }
+        Ok(Response {
+            pdu,
+            boots: message.parameters.boots,
+            time: message.parameters.time,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snmp_ups::{
+        ber::{parse_oid, Value},
+        pdu::{encode_get_request, REPORT},
+    };
+
+    fn engine() -> Engine {
+        Engine {
+            id: vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2],
+            boots: 3,
+            time: 1200,
+        }
+    }
+
+    /// Response of an agent sharing keys with `session`, same as `request` but from the other side
+    fn respond(request: &[u8], auth: [u8; 20], privacy: [u8; 20], varbinds: Vec<u8>) -> Vec<u8> {
+        let request = decode_message(request).unwrap();
+        let engine = engine();
+        let contents = [
+            ber::integer(request.message_id),
+            ber::integer(0),
+            ber::integer(0),
+            ber::sequence(&[varbinds]),
+        ]
+        .concat();
+        let mut data = encode_scoped_pdu(&engine.id, ber::tlv(0xa2, &contents));
+        let salt = [9u8; 8];
+        let iv = initialization_vector(engine.boots, engine.time, &salt);
+        Encryptor::<Aes128>::new_from_slices(&privacy[..16], &iv)
+            .unwrap()
+            .encrypt(&mut data);
+        let parameters = SecurityParameters {
+            engine_id: &engine.id,
+            boots: engine.boots,
+            time: engine.time,
+            username: request.parameters.username,
+            auth: &[0u8; AUTH_LENGTH],
+            privacy: &salt,
+        };
+        let (mut message, offset) = encode_message(
+            request.message_id,
+            FLAG_AUTH | FLAG_PRIV,
+            &parameters,
+            ber::octet_string(&data),
+        );
+        let digest = authenticate(&auth, &message);
+        message[offset..offset + AUTH_LENGTH].copy_from_slice(&digest);
+        message
+    }
+
+    #[test]
+    fn test_localized_key() {
+        // RFC 3414 A.3.2
+        let key = localized_key("maplesyrup", &engine().id);
+        assert_eq!(
+            key,
+            [
+                0x66, 0x95, 0xfe, 0xbc, 0x92, 0x88, 0xe3, 0x62, 0x82, 0x23, 0x5f, 0xc7, 0x15, 0x1f,
+                0x12, 0x84, 0x97, 0xb3, 0x8f, 0x3f
+            ]
+        );
+    }
+
+    #[test]
+    fn test_authenticated_encrypted_exchange() {
+        let mut session = UsmSession::new(
+            String::from("monitor"),
+            Some(String::from("maplesyrup")),
+            Some(String::from("sugarmaple")),
+        )
+        .unwrap();
+        let oid = parse_oid("1.3.6.1.2.1.33.1.2.4.0").unwrap();
+        let request = session.request(&engine(), 42, encode_get_request(42, &[oid.clone()]));
+
+        // Agent side checks the digest and decrypts the scoped PDU
+        let auth = localized_key("maplesyrup", &engine().id);
+        let privacy = localized_key("sugarmaple", &engine().id);
+        let decoded = decode_message(&request).unwrap();
+        assert_eq!(decoded.flags, FLAG_AUTH | FLAG_PRIV | FLAG_REPORTABLE);
+        assert_eq!(decoded.parameters.username, b"monitor");
+        let offset = decoded.parameters.auth.as_ptr() as usize - request.as_ptr() as usize;
+        let mut zeroed = request.clone();
+        zeroed[offset..offset + AUTH_LENGTH].fill(0);
+        assert_eq!(authenticate(&auth, &zeroed), decoded.parameters.auth);
+        let mut scoped = decoded.data.1.to_vec();
+        let iv = initialization_vector(3, 1200, decoded.parameters.privacy);
+        Decryptor::<Aes128>::new_from_slices(&privacy[..16], &iv)
+            .unwrap()
+            .decrypt(&mut scoped);
+        assert_eq!(decode_scoped_pdu(&scoped).unwrap().request_id, 42);
+
+        let varbind = ber::sequence(&[ber::object_identifier(&oid), ber::integer(97)]);
+        let mut response = respond(&request, auth, privacy, varbind);
+        let decoded = session.decode_response(&engine(), 42, &response).unwrap();
+        assert_eq!(
+            decoded.pdu.into_response(42),
+            Ok(vec![(oid, Value::Integer(97))])
+        );
+        let last = response.len() - 1;
+        response[last] ^= 1;
+        assert!(session.decode_response(&engine(), 42, &response).is_err());
+    }
+
+    #[test]
+    fn test_report_reason() {
+        let report = Pdu {
+            tag: REPORT,
+            request_id: 1,
+            error_status: 0,
+            varbinds: vec![(
+                parse_oid("1.3.6.1.6.3.15.1.1.2.0").unwrap(),
+                Value::Unsigned(1),
+            )],
+        };
+        assert_eq!(report_reason(&report), "not in time window");
+        assert!(is_not_in_time_window(&report));
+        assert!(UsmSession::new(String::from("monitor"), None, Some(String::from("x"))).is_err());
+        assert!(
+            UsmSession::new(String::from("monitor"), Some(String::from("short")), None).is_err()
+        );
+    }
+}
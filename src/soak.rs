@@ -0,0 +1,259 @@
+// Licensed under the Open Software License version 3.0
+//! Long-running test of the whole pipeline with simulated sources
+//!
+//! Run with `cargo test --release --features soak soak -- --nocapture`. Memory and task counts
+//! are sampled after a warm-up and compared against budgets, so slow accumulation in caches,
+//! watch channels or per-id state fails the test instead of showing up after weeks on a Pi Zero.
+use crate::{
+    active_sender::{config::ActiveSenderConfig, start_active_sender_loop},
+    change_rate::{config::ChangeRateConfig, derivative::start_change_rate_loop},
+    config::types::Example,
+    hardware::{
+        reading::{Reading, ReadingsUpdate},
+        types::{HardwareMetadata, HardwareType, SourceType},
+    },
+    introspection,
+    nut::{
+        config::UpsMonitoringConfig,
+        fake_server::FakeNutServer,
+        sender::{start_nut_monitoring_loop, UpsUpdate},
+    },
+    one_wire::sender::MeasuredTemperature,
+    passive_endpoint::{config::PassiveEndpointConfig, receiver::start_passive_endpoint_loop},
+    relations::config::RelationsConfig,
+    self_metrics::process::read_rss_bytes,
+    ups_runtime::{config::UpsRuntimeConfig, projection::start_ups_runtime_loop},
+};
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+use tokio::sync::{broadcast, oneshot};
+
+const MIB: f64 = 1024.0 * 1024.0;
+const SENSORS: usize = 20;
+// Sensors come and go, so removed ids have to be forgotten
+const SENSOR_IDS: usize = 40;
+const READINGS: usize = 10;
+
+/// Settings read from `SOAK_*` environment variables
+struct SoakSettings {
+    duration: Duration,
+    // Time between simulated source updates
+    interval: Duration,
+    warm_up: Duration,
+    max_rss_growth: f64,
+}
+
+impl SoakSettings {
+    fn from_env() -> Self {
+        let var = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default)
+        };
+        let duration = Duration::from_secs(var("SOAK_DURATION_SECS", 4 * 60 * 60));
+        Self {
+            duration,
+            interval: Duration::from_millis(var("SOAK_INTERVAL_MS", 100)),
+            warm_up: Duration::from_secs(var("SOAK_WARM_UP_SECS", 60)).min(duration / 4),
+            max_rss_growth: var("SOAK_MAX_RSS_GROWTH_MIB", 16) as f64 * MIB,
+        }
+    }
+}
+
+fn simulated_sensors(tick: usize) -> Vec<MeasuredTemperature> {
+    (0..SENSORS)
+        .map(|index| {
+            let id = (tick / 100 + index) % SENSOR_IDS;
+            let mut sensor = MeasuredTemperature::example();
+            sensor.meta = HardwareMetadata::new(
+                format!("28-soak{:08}", id),
+                HardwareType::TemperatureSensor,
+                SourceType::OneWire,
+            )
+            .measured_now();
+            sensor.temperature = Some(20.0 + (tick % 50) as f64 / 10.0);
+            sensor
+        })
+        .collect()
+}
+
+fn simulated_readings(tick: usize) -> ReadingsUpdate {
+    let readings = (0..READINGS)
+        .map(|index| {
+            let meta = HardwareMetadata::new(
+                format!("soak-{}", index),
+                HardwareType::SensorChip,
+                SourceType::Hwmon,
+            )
+            .measured_now();
+            Reading::new(meta).with_value("temperature", Some((tick % 30) as f64))
+        })
+        .collect();
+    ReadingsUpdate::new("soak", readings)
+}
+
+fn running_tasks() -> u32 {
+    introspection::snapshot()
+        .tasks
+        .values()
+        .map(|task| task.running)
+        .sum()
+}
+
+fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn soak() {
+    // Progress is logged, shown with --nocapture
+    let _ = tracing_subscriber::fmt()
+        .with_test_writer()
+        .with_env_filter("universal_data_source=info")
+        .try_init();
+    let settings = SoakSettings::from_env();
+    let (shutdown_tx, shutdown_rx) = broadcast::channel::<()>(1);
+    let (one_wire_tx, one_wire_rx) = broadcast::channel::<Vec<MeasuredTemperature>>(16);
+    let (ups_monitoring_tx, ups_monitoring_rx) = broadcast::channel::<UpsUpdate>(16);
+    let (readings_tx, readings_rx) = broadcast::channel::<ReadingsUpdate>(16);
+
+    // Sinks
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/soak")
+        .with_status(200)
+        .expect_at_least(1)
+        .create_async()
+        .await;
+    let active_sender: ActiveSenderConfig = serde_json::from_value(serde_json::json!({
+        "enabled": true,
+        "cooldown": Duration::from_secs(1),
+        "ignore_connection_errors": false,
+        "endpoints": [{ "url": format!("{}/soak", server.url()) }],
+    }))
+    .unwrap();
+    let port = free_port();
+    let passive_endpoint: PassiveEndpointConfig =
+        serde_json::from_value(serde_json::json!({ "enabled": true, "port": port })).unwrap();
+    let mut handles = vec![
+        tokio::spawn(start_active_sender_loop(
+            shutdown_rx.resubscribe(),
+            active_sender,
+            one_wire_rx.resubscribe(),
+            ups_monitoring_rx.resubscribe(),
+            readings_rx.resubscribe(),
+            String::from("soak"),
+        )),
+        tokio::spawn(start_change_rate_loop(
+            shutdown_rx.resubscribe(),
+            ChangeRateConfig::example(),
+            one_wire_rx.resubscribe(),
+            readings_tx.clone(),
+        )),
+        tokio::spawn(start_ups_runtime_loop(
+            shutdown_rx.resubscribe(),
+            UpsRuntimeConfig::example(),
+            ups_monitoring_rx.resubscribe(),
+            readings_tx.clone(),
+        )),
+        tokio::spawn(start_passive_endpoint_loop(
+            shutdown_rx.resubscribe(),
+            passive_endpoint,
+            one_wire_rx,
+            ups_monitoring_rx,
+            readings_rx,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            String::from("soak"),
+            false,
+            oneshot::channel().0,
+        )),
+    ];
+
+    // Sources
+    let nut_server = FakeNutServer::start().await;
+    nut_server.set_var("soak", "ups.status", "OL").await;
+    nut_server.set_var("soak", "battery.runtime", "1800").await;
+    let ups_monitoring: UpsMonitoringConfig = serde_json::from_value(serde_json::json!({
+        "enabled": true,
+        "servers": [nut_server.client_config(&["soak"])],
+        "cooldown": settings.interval,
+    }))
+    .unwrap();
+    handles.push(tokio::spawn(start_nut_monitoring_loop(
+        shutdown_rx.resubscribe(),
+        ups_monitoring,
+        RelationsConfig::default(),
+        ups_monitoring_tx,
+    )));
+
+    let client = reqwest::Client::new();
+    let started_at = Instant::now();
+    let mut baseline: Option<(f64, u32)> = None;
+    let mut next_sample = started_at + settings.warm_up;
+    let mut tick = 0;
+    while started_at.elapsed() < settings.duration {
+        one_wire_tx.send(simulated_sensors(tick)).unwrap();
+        readings_tx.send(simulated_readings(tick)).unwrap();
+        nut_server
+            .set_var("soak", "battery.charge", &(50 + tick % 50).to_string())
+            .await;
+        tick += 1;
+        tokio::time::sleep(settings.interval).await;
+        if Instant::now() < next_sample {
+            continue;
+        }
+        next_sample += settings.warm_up;
+
+        // Clients of the passive endpoint are part of the pipeline too
+        for path in ["temperature", "ups", "readings", "changes?since=0"] {
+            let response = client
+                .get(format!("http://127.0.0.1:{}/{}", port, path))
+                .send()
+                .await
+                .unwrap();
+            assert!(response.status().is_success(), "GET /{} failed", path);
+        }
+
+        let status = introspection::snapshot();
+        assert_eq!(status.lagged_messages, 0, "a receiver fell behind");
+        let rss = read_rss_bytes(Path::new("/proc/self")).unwrap();
+        let tasks = running_tasks();
+        let (baseline_rss, baseline_tasks) = *baseline.get_or_insert((rss, tasks));
+        tracing::info!(
+            "soak: {:?} elapsed, {} ticks, {:.1} MiB RSS, {} tasks",
+            started_at.elapsed(),
+            tick,
+            rss / MIB,
+            tasks
+        );
+        assert!(
+            rss - baseline_rss <= settings.max_rss_growth,
+            "RSS grew from {:.1} MiB to {:.1} MiB",
+            baseline_rss / MIB,
+            rss / MIB
+        );
+        assert!(
+            tasks <= baseline_tasks,
+            "running tasks grew from {} to {}",
+            baseline_tasks,
+            tasks
+        );
+    }
+    assert!(baseline.is_some(), "SOAK_DURATION_SECS is too short");
+
+    shutdown_tx.send(()).unwrap();
+    for handle in handles {
+        handle.await.unwrap();
+    }
+    mock.assert_async().await;
+}
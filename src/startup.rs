@@ -0,0 +1,154 @@
+// Licensed under the Open Software License version 3.0
+//! Ordered startup of modules
+//!
+//! Every module is registered with its dependencies before anything is spawned, then waits until
+//! all of them are ready (or stopped, ex. disabled in config) before starting itself.
+use crate::introspection;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+use tokio::sync::watch;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModuleState {
+    Registered,
+    Waiting,
+    Ready,
+    Stopped,
+}
+
+impl ModuleState {
+    /// Dependents may start
+    fn is_settled(&self) -> bool {
+        matches!(self, ModuleState::Ready | ModuleState::Stopped)
+    }
+}
+
+struct Inner {
+    states: watch::Sender<BTreeMap<String, ModuleState>>,
+    started_at: Instant,
+    finished: AtomicBool,
+}
+
+#[derive(Clone)]
+pub struct Startup(Arc<Inner>);
+
+impl Default for Startup {
+    fn default() -> Self {
+        Self(Arc::new(Inner {
+            states: watch::channel(BTreeMap::new()).0,
+            started_at: Instant::now(),
+            finished: AtomicBool::new(false),
+        }))
+    }
+}
+
+impl Startup {
+    fn set_state(&self, name: &str, state: ModuleState) {
+        tracing::debug!("Startup: {} is {:?}", name, state);
+        introspection::set_startup_state(name, state);
+        self.0.states.send_modify(|states| {
+            states.insert(String::from(name), state);
+        });
+        let all_settled = self.0.states.borrow().values().all(ModuleState::is_settled);
+        if all_settled && !self.0.finished.swap(true, Ordering::Relaxed) {
+            tracing::info!(
+                "Startup finished in {}ms",
+                self.0.started_at.elapsed().as_millis()
+            );
+        }
+    }
+
+    /// Register all modules before spawning any of them, so dependencies can be checked
+    pub fn register(&self, name: &'static str, depends_on: &[&'static str]) -> StartupHandle {
+        self.set_state(name, ModuleState::Registered);
+        StartupHandle {
+            startup: self.clone(),
+            name,
+            depends_on: depends_on.to_vec(),
+        }
+    }
+}
+
+/// Marks the module as stopped when dropped, so dependents of a failed module aren't stuck
+pub struct StartupHandle {
+    startup: Startup,
+    name: &'static str,
+    depends_on: Vec<&'static str>,
+}
+
+impl StartupHandle {
+    pub async fn wait_for_dependencies(&self) {
+        let mut states = self.startup.0.states.subscribe();
+        for dependency in &self.depends_on {
+            if !states.borrow().contains_key(*dependency) {
+                tracing::warn!(
+                    "{} depends on {}, which isn't registered",
+                    self.name,
+                    dependency
+                );
+            }
+        }
+        self.startup.set_state(self.name, ModuleState::Waiting);
+        let depends_on = &self.depends_on;
+        let _ = states
+            .wait_for(|states| {
+                depends_on.iter().all(|dependency| {
+                    states
+                        .get(*dependency)
+                        .map_or(true, ModuleState::is_settled)
+                })
+            })
+            .await;
+    }
+
+    pub fn ready(&self) {
+        self.startup.set_state(self.name, ModuleState::Ready);
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl Drop for StartupHandle {
+    fn drop(&mut self) {
+        self.startup.set_state(self.name, ModuleState::Stopped);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::time::timeout;
+
+    #[tokio::test]
+    async fn test_waits_for_dependencies() {
+        let startup = Startup::default();
+        let sink = startup.register("test_sink", &[]);
+        let source = startup.register("test_source", &["test_sink"]);
+        let waiting = timeout(Duration::from_millis(50), source.wait_for_dependencies()).await;
+        assert!(waiting.is_err());
+        sink.ready();
+        let waiting = timeout(Duration::from_millis(50), source.wait_for_dependencies()).await;
+        assert!(waiting.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_stopped_dependency_releases_dependents() {
+        let startup = Startup::default();
+        let sink = startup.register("test_disabled_sink", &[]);
+        let source = startup.register("test_source", &["test_disabled_sink", "test_unknown"]);
+        drop(sink);
+        let waiting = timeout(Duration::from_millis(50), source.wait_for_dependencies()).await;
+        assert!(waiting.is_ok());
+    }
+}
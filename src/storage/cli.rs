@@ -0,0 +1,76 @@
+// Licensed under the Open Software License version 3.0
+//! `universal-data-source storage prune`
+//! `universal-data-source storage purge <temperature|ups|readings> <id> [before]`
+//!
+//! Deletes stored measurements older than `retention` or of a decommissioned sensor, UPS or reading
+//! (only older than `before` if set), then vacuums the database to shrink the file
+use std::error::Error;
+
+pub fn is_storage_command(args: &[String]) -> bool {
+    args.get(1).map(String::as_str) == Some("storage")
+}
+
+#[cfg(feature = "storage")]
+pub fn run_storage_command(args: &[String]) -> Result<(), Box<dyn Error>> {
+    use super::{database::Database, rows::Category};
+    use crate::config::file::read_config_or_create_default;
+    use chrono::{DateTime, Utc};
+
+    const USAGE: &str = "usage: universal-data-source storage prune | storage purge <temperature|ups|readings> <id> [before]";
+
+    // `None` prunes by retention, otherwise rows of a single sensor, UPS or reading are deleted
+    let purge = match (args.get(2).map(String::as_str), args.get(3), args.get(4)) {
+        (Some("prune"), _, _) => None,
+        (Some("purge"), Some(category), Some(id)) => {
+            let category: Category = category.parse().map_err(|_| {
+                format!(
+                    "unknown category {}, use temperature, ups or readings",
+                    category
+                )
+            })?;
+            let before = match args.get(5) {
+                Some(before) => Some(DateTime::parse_from_rfc3339(before)?.timestamp_millis()),
+                None => None,
+            };
+            Some((category, id, before))
+        }
+        _ => return Err(USAGE.into()),
+    };
+    let config = read_config_or_create_default().storage;
+    let path = config.get_path();
+    let database = Database::open(&path)?;
+    let deleted = match purge {
+        Some((category, id, before)) => database.delete(category, id, before)?,
+        None => {
+            let retention = chrono::Duration::from_std(config.get_retention())?;
+            let oldest = Utc::now()
+                .checked_sub_signed(retention)
+                .map_or(i64::MIN, |oldest| oldest.timestamp_millis());
+            database.prune(oldest)?
+        }
+    };
+    database.vacuum()?;
+    tracing::info!(
+        "Deleted {} stored measurements from {}",
+        deleted,
+        path.display()
+    );
+    Ok(())
+}
+
+#[cfg(not(feature = "storage"))]
+pub fn run_storage_command(_: &[String]) -> Result<(), Box<dyn Error>> {
+    Err("this binary was built without storage feature".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_storage_command() {
+        let args: Vec<String> = vec![String::from("uds"), String::from("storage")];
+        assert!(is_storage_command(&args));
+        assert!(!is_storage_command(&args[..1]));
+    }
+}
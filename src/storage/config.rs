@@ -0,0 +1,64 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageConfig {
+    enabled: Option<bool>,
+    // SQLite database file, created if it doesn't exist
+    path: Option<PathBuf>,
+    // Measurements older than that are deleted
+    retention: Option<Duration>,
+    // How often to delete old measurements
+    prune_interval: Option<Duration>,
+    // Maximum number of rows returned by `/storage` routes
+    max_query_rows: Option<usize>,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            path: Some(PathBuf::from("measurements.sqlite")),
+            retention: Some(Duration::from_secs(7 * 24 * 60 * 60)),
+            prune_interval: Some(Duration::from_secs(60 * 60)),
+            max_query_rows: Some(1000),
+        }
+    }
+}
+
+impl Example for StorageConfig {
+    fn example() -> Self {
+        Self::default()
+    }
+}
+
+impl StorageConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    #[cfg_attr(not(feature = "storage"), allow(dead_code))]
+    pub fn get_path(&self) -> PathBuf {
+        self.path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("measurements.sqlite"))
+    }
+
+    #[cfg_attr(not(feature = "storage"), allow(dead_code))]
+    pub fn get_retention(&self) -> Duration {
+        self.retention
+            .unwrap_or(Duration::from_secs(7 * 24 * 60 * 60))
+    }
+
+    #[cfg_attr(not(feature = "storage"), allow(dead_code))]
+    pub fn get_prune_interval(&self) -> Duration {
+        self.prune_interval.unwrap_or(Duration::from_secs(60 * 60))
+    }
+
+    #[cfg_attr(not(feature = "storage"), allow(dead_code))]
+    pub fn get_max_query_rows(&self) -> usize {
+        self.max_query_rows.unwrap_or(1000)
+    }
+}
@@ -0,0 +1,266 @@
+// Licensed under the Open Software License version 3.0
+//! SQLite database with one row per measurement
+use super::rows::{Category, Row};
+use rusqlite::{params, Connection, OpenFlags, Result};
+use serde::{Deserialize, Serialize};
+use std::{path::Path, time::Duration};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS measurements (
+    measured_at INTEGER NOT NULL,
+    category TEXT NOT NULL,
+    hw_id TEXT NOT NULL,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS measurements_by_hw_id
+    ON measurements (category, hw_id, measured_at);
+CREATE INDEX IF NOT EXISTS measurements_by_time ON measurements (measured_at);
+";
+
+/// Item of `/storage` responses, oldest first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredMeasurement {
+    // Unix timestamp in milliseconds
+    pub measured_at: i64,
+    pub data: serde_json::Value,
+}
+
+/// Time range and row limit of a query, bounds are inclusive unix timestamps in milliseconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Query {
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub limit: usize,
+}
+
+pub struct Database {
+    connection: Connection,
+}
+
+impl Database {
+    /// Open or create the database and its table
+    pub fn open(path: &Path) -> Result<Self> {
+        let connection = Connection::open(path)?;
+        // Readers of the passive endpoint don't block writes
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        // The writer and deletes of the passive endpoint or CLI wait for each other
+        connection.busy_timeout(Duration::from_secs(5))?;
+        Self::init(connection)
+    }
+
+    /// Used by the passive endpoint, fails if the writer didn't create the database yet
+    #[cfg_attr(
+        not(any(feature = "passive-endpoint", feature = "axum")),
+        allow(dead_code)
+    )]
+    pub fn open_read_only(path: &Path) -> Result<Self> {
+        let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+        Ok(Self {
+            connection: Connection::open_with_flags(path, flags)?,
+        })
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(connection: Connection) -> Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection })
+    }
+
+    /// Insert a whole batch in one transaction
+    pub fn insert(&mut self, rows: &[Row]) -> Result<()> {
+        let transaction = self.connection.transaction()?;
+        {
+            let mut statement = transaction.prepare_cached(
+                "INSERT INTO measurements (measured_at, category, hw_id, data) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for row in rows {
+                statement.execute(params![
+                    row.measured_at,
+                    row.category.as_str(),
+                    row.hw_id,
+                    row.data
+                ])?;
+            }
+        }
+        transaction.commit()
+    }
+
+    /// Delete measurements older than `oldest`, returns number of deleted rows
+    pub fn prune(&self, oldest: i64) -> Result<usize> {
+        self.connection.execute(
+            "DELETE FROM measurements WHERE measured_at < ?1",
+            params![oldest],
+        )
+    }
+
+    /// Delete measurements of a sensor, UPS or reading, only older than `before` if set
+    pub fn delete(&self, category: Category, hw_id: &str, before: Option<i64>) -> Result<usize> {
+        self.connection.execute(
+            "DELETE FROM measurements WHERE category = ?1 AND hw_id = ?2 AND measured_at < ?3",
+            params![category.as_str(), hw_id, before.unwrap_or(i64::MAX)],
+        )
+    }
+
+    /// Give space of deleted rows back to the file system
+    pub fn vacuum(&self) -> Result<()> {
+        self.connection.execute_batch("VACUUM")
+    }
+
+    #[cfg_attr(
+        not(any(feature = "passive-endpoint", feature = "axum")),
+        allow(dead_code)
+    )]
+    pub fn query(
+        &self,
+        category: Category,
+        hw_id: &str,
+        query: Query,
+    ) -> Result<Vec<StoredMeasurement>> {
+        let mut statement = self.connection.prepare_cached(
+            "SELECT measured_at, data FROM measurements
+            WHERE category = ?1 AND hw_id = ?2 AND measured_at >= ?3 AND measured_at <= ?4
+            ORDER BY measured_at LIMIT ?5",
+        )?;
+        let rows = statement.query_map(
+            params![
+                category.as_str(),
+                hw_id,
+                query.since.unwrap_or(i64::MIN),
+                query.until.unwrap_or(i64::MAX),
+                query.limit as i64
+            ],
+            |row| {
+                let data: String = row.get(1)?;
+                Ok(StoredMeasurement {
+                    measured_at: row.get(0)?,
+                    // Written by `insert`, so it's always valid JSON
+                    data: serde_json::from_str(&data).unwrap_or_default(),
+                })
+            },
+        )?;
+        rows.collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(measured_at: i64, category: Category, hw_id: &str) -> Row {
+        Row {
+            measured_at,
+            category,
+            hw_id: String::from(hw_id),
+            data: format!("{{\"at\":{}}}", measured_at),
+        }
+    }
+
+    fn query(since: Option<i64>, until: Option<i64>, limit: usize) -> Query {
+        Query {
+            since,
+            until,
+            limit,
+        }
+    }
+
+    #[test]
+    fn test_insert_and_query() {
+        let mut database = Database::open_in_memory().unwrap();
+        database
+            .insert(&[
+                row(1, Category::Temperature, "a"),
+                row(2, Category::Temperature, "a"),
+                row(2, Category::Temperature, "b"),
+                row(2, Category::Ups, "a"),
+                row(3, Category::Temperature, "a"),
+            ])
+            .unwrap();
+        let stored = database
+            .query(Category::Temperature, "a", query(None, None, 10))
+            .unwrap();
+        let times: Vec<i64> = stored.iter().map(|row| row.measured_at).collect();
+        assert_eq!(times, vec![1, 2, 3]);
+        assert_eq!(stored[0].data["at"], 1);
+        let stored = database
+            .query(Category::Temperature, "a", query(Some(2), Some(3), 1))
+            .unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].measured_at, 2);
+        let stored = database
+            .query(Category::Readings, "a", query(None, None, 10))
+            .unwrap();
+        assert!(stored.is_empty());
+    }
+
+    #[test]
+    fn test_prune() {
+        let mut database = Database::open_in_memory().unwrap();
+        database
+            .insert(&[
+                row(1, Category::Temperature, "a"),
+                row(2, Category::Ups, "a"),
+                row(3, Category::Temperature, "a"),
+            ])
+            .unwrap();
+        assert_eq!(database.prune(3).unwrap(), 2);
+        let stored = database
+            .query(Category::Temperature, "a", query(None, None, 10))
+            .unwrap();
+        assert_eq!(stored.len(), 1);
+    }
+
+    #[test]
+    fn test_delete() {
+        let mut database = Database::open_in_memory().unwrap();
+        database
+            .insert(&[
+                row(1, Category::Temperature, "a"),
+                row(2, Category::Temperature, "a"),
+                row(2, Category::Temperature, "b"),
+                row(2, Category::Ups, "a"),
+            ])
+            .unwrap();
+        assert_eq!(
+            database
+                .delete(Category::Temperature, "a", Some(2))
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            database.delete(Category::Temperature, "a", None).unwrap(),
+            1
+        );
+        let stored = database
+            .query(Category::Temperature, "a", query(None, None, 10))
+            .unwrap();
+        assert!(stored.is_empty());
+        let stored = database
+            .query(Category::Ups, "a", query(None, None, 10))
+            .unwrap();
+        assert_eq!(stored.len(), 1);
+        database.vacuum().unwrap();
+    }
+
+    #[test]
+    fn test_survives_reopening() {
+        let path =
+            std::env::temp_dir().join(format!("uds-storage-{}.sqlite", uuid::Uuid::new_v4()));
+        Database::open(&path)
+            .unwrap()
+            .insert(&[row(1, Category::Temperature, "a")])
+            .unwrap();
+        let database = Database::open_read_only(&path).unwrap();
+        let stored = database
+            .query(Category::Temperature, "a", query(None, None, 10))
+            .unwrap();
+        assert_eq!(stored.len(), 1);
+        drop(database);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+}
@@ -0,0 +1,14 @@
+// Licensed under the Open Software License version 3.0
+pub mod cli;
+pub mod config;
+#[cfg(feature = "storage")]
+pub mod database;
+#[cfg(all(
+    feature = "storage",
+    any(feature = "passive-endpoint", feature = "axum")
+))]
+pub mod query;
+// Only used by the storage feature and tests
+#[cfg_attr(not(feature = "storage"), allow(dead_code))]
+pub mod rows;
+pub mod writer;
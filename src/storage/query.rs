@@ -0,0 +1,104 @@
+// Licensed under the Open Software License version 3.0
+//! `/storage` queries and deletes, answered the same way by both passive endpoint backends
+use super::{
+    config::StorageConfig,
+    database::{Database, Query, StoredMeasurement},
+    rows::Category,
+};
+use chrono::DateTime;
+
+/// Why a query wasn't answered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    /// Not `temperature`, `ups` or `readings`, answered like a missing route
+    UnknownCategory,
+    InvalidBound,
+    Failed(String),
+}
+
+impl QueryError {
+    /// HTTP status of the response
+    pub fn status(&self) -> u16 {
+        match self {
+            QueryError::UnknownCategory => 404,
+            QueryError::InvalidBound => 400,
+            QueryError::Failed(_) => 500,
+        }
+    }
+
+    /// Error message of the response, `None` for a plain "not found"
+    pub fn message(&self) -> Option<&'static str> {
+        match self {
+            QueryError::UnknownCategory => None,
+            QueryError::InvalidBound => {
+                Some("since, until and before have to be RFC 3339 timestamps")
+            }
+            QueryError::Failed(_) => Some("failed to access stored measurements"),
+        }
+    }
+}
+
+/// RFC 3339 bound of a query as unix timestamp in milliseconds
+fn parse_bound(bound: Option<&str>) -> Result<Option<i64>, QueryError> {
+    match bound {
+        Some(bound) => DateTime::parse_from_rfc3339(bound)
+            .map(|bound| Some(bound.timestamp_millis()))
+            .map_err(|_| QueryError::InvalidBound),
+        None => Ok(None),
+    }
+}
+
+/// Run `task` outside of the async runtime, SQLite calls block
+async fn blocking<T: Send + 'static>(
+    task: impl FnOnce() -> rusqlite::Result<T> + Send + 'static,
+) -> Result<T, QueryError> {
+    let result = tokio::task::spawn_blocking(task)
+        .await
+        .map_err(|error| error.to_string())
+        .and_then(|result| result.map_err(|error| error.to_string()));
+    result.map_err(|error| {
+        tracing::error!("Failed to access stored measurements: {}", error);
+        QueryError::Failed(error)
+    })
+}
+
+/// Stored measurements of `id`, at most `max_query_rows` of them
+pub async fn query_stored(
+    config: &StorageConfig,
+    category: &str,
+    id: String,
+    since: Option<&str>,
+    until: Option<&str>,
+    limit: Option<usize>,
+) -> Result<Vec<StoredMeasurement>, QueryError> {
+    let category: Category = category.parse().map_err(|_| QueryError::UnknownCategory)?;
+    let max_rows = config.get_max_query_rows();
+    let query = Query {
+        since: parse_bound(since)?,
+        until: parse_bound(until)?,
+        limit: limit.unwrap_or(max_rows).min(max_rows),
+    };
+    let path = config.get_path();
+    blocking(move || Database::open_read_only(&path)?.query(category, &id, query)).await
+}
+
+/// Delete stored measurements of `id`, only older than `before` if set, returns number of deleted rows
+pub async fn delete_stored(
+    config: &StorageConfig,
+    category: &str,
+    id: String,
+    before: Option<&str>,
+) -> Result<usize, QueryError> {
+    let category: Category = category.parse().map_err(|_| QueryError::UnknownCategory)?;
+    let before = parse_bound(before)?;
+    let path = config.get_path();
+    let hw_id = id.clone();
+    let deleted = blocking(move || Database::open(&path)?.delete(category, &hw_id, before)).await?;
+    tracing::info!(
+        "Deleted {} stored measurements of {} {}",
+        deleted,
+        category.as_str(),
+        id
+    );
+    Ok(deleted)
+}
@@ -0,0 +1,185 @@
+// Licensed under the Open Software License version 3.0
+//! Measurements flattened to rows of the `measurements` table
+use crate::{
+    hardware::{reading::Reading, types::HardwareMetadata},
+    nut::sender::UninterruptiblePowerSupplyData,
+    one_wire::sender::MeasuredTemperature,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::{collections::HashMap, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    Temperature,
+    Ups,
+    Readings,
+}
+
+impl Category {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Temperature => "temperature",
+            Self::Ups => "ups",
+            Self::Readings => "readings",
+        }
+    }
+}
+
+impl FromStr for Category {
+    type Err = ();
+
+    fn from_str(category: &str) -> Result<Self, Self::Err> {
+        match category {
+            "temperature" => Ok(Self::Temperature),
+            "ups" => Ok(Self::Ups),
+            "readings" => Ok(Self::Readings),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    // Unix timestamp in milliseconds
+    pub measured_at: i64,
+    pub category: Category,
+    pub hw_id: String,
+    // Whole measurement as JSON, the same as in passive endpoint responses
+    pub data: String,
+}
+
+/// `measured_at` of a measurement, `now` if it's missing or invalid
+fn timestamp(meta: &HardwareMetadata, now: DateTime<Utc>) -> i64 {
+    meta.measured_at
+        .as_deref()
+        .and_then(|measured_at| DateTime::parse_from_rfc3339(measured_at).ok())
+        .map_or(now, |measured_at| measured_at.with_timezone(&Utc))
+        .timestamp_millis()
+}
+
+fn to_rows<'a, T: Serialize + 'a>(
+    category: Category,
+    items: impl IntoIterator<Item = (&'a HardwareMetadata, &'a T)>,
+    now: DateTime<Utc>,
+) -> Vec<Row> {
+    items
+        .into_iter()
+        .filter_map(|(meta, item)| match serde_json::to_string(item) {
+            Ok(data) => Some(Row {
+                measured_at: timestamp(meta, now),
+                category,
+                hw_id: meta.hw.id.clone(),
+                data,
+            }),
+            Err(error) => {
+                tracing::warn!("Failed to serialize {}: {}", meta.hw.id, error);
+                None
+            }
+        })
+        .collect()
+}
+
+pub fn temperature_rows(sensors: &[MeasuredTemperature], now: DateTime<Utc>) -> Vec<Row> {
+    let items = sensors.iter().map(|sensor| (&sensor.meta, sensor));
+    to_rows(Category::Temperature, items, now)
+}
+
+pub fn ups_rows(upses: &[UninterruptiblePowerSupplyData], now: DateTime<Utc>) -> Vec<Row> {
+    let items = upses.iter().map(|ups| (&ups.meta, ups));
+    to_rows(Category::Ups, items, now)
+}
+
+pub fn reading_rows(readings: &[Reading], now: DateTime<Utc>) -> Vec<Row> {
+    let items = readings.iter().map(|reading| (&reading.meta, reading));
+    to_rows(Category::Readings, items, now)
+}
+
+/// Data of the last stored row of every measurement
+///
+/// Sources republish their whole snapshot (ex. on a status change of another UPS), so a
+/// measurement that wasn't read again would be stored once per republish.
+#[derive(Debug, Default)]
+pub struct LastStored(HashMap<(Category, String), String>);
+
+impl LastStored {
+    /// Rows that differ from the last stored ones, remembered as stored
+    pub fn changed(&mut self, rows: Vec<Row>) -> Vec<Row> {
+        rows.into_iter()
+            .filter(|row| {
+                let key = (row.category, row.hw_id.clone());
+                match self.0.get(&key) {
+                    Some(data) if *data == row.data => false,
+                    _ => {
+                        self.0.insert(key, row.data.clone());
+                        true
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::types::Example;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2023-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_temperature_rows() {
+        let mut sensor = MeasuredTemperature::example();
+        sensor.meta.measured_at = Some(String::from("2023-01-01T00:00:10+00:00"));
+        let rows = temperature_rows(&[sensor, MeasuredTemperature::example()], now());
+        assert_eq!(rows[0].measured_at, now().timestamp_millis() + 10_000);
+        assert_eq!(rows[0].category, Category::Temperature);
+        assert_eq!(rows[0].hw_id, "fake_hw_id");
+        let data: serde_json::Value = serde_json::from_str(&rows[0].data).unwrap();
+        assert_eq!(data["resolution"], 12);
+        // Missing measured_at falls back to now
+        assert_eq!(rows[1].measured_at, now().timestamp_millis());
+    }
+
+    #[test]
+    fn test_ups_and_reading_rows() {
+        let rows = ups_rows(&[UninterruptiblePowerSupplyData::example()], now());
+        assert_eq!(rows[0].category, Category::Ups);
+        let rows = reading_rows(&[Reading::example()], now());
+        assert_eq!(rows[0].category, Category::Readings);
+        let data: serde_json::Value = serde_json::from_str(&rows[0].data).unwrap();
+        assert_eq!(data["values"]["rss_bytes"], 1024.0);
+    }
+
+    #[test]
+    fn test_last_stored_skips_unchanged() {
+        let mut last_stored = LastStored::default();
+        let ups = UninterruptiblePowerSupplyData::example();
+        assert_eq!(
+            last_stored.changed(ups_rows(&[ups.clone()], now())).len(),
+            1
+        );
+        // Republished without a new read
+        assert!(last_stored
+            .changed(ups_rows(&[ups.clone()], now()))
+            .is_empty());
+        let mut read_again = ups;
+        read_again.meta.measured_at = Some(String::from("2023-01-01T00:00:10+00:00"));
+        assert_eq!(last_stored.changed(ups_rows(&[read_again], now())).len(), 1);
+        // Same data in another category is a different measurement
+        let rows = reading_rows(&[Reading::example()], now());
+        assert_eq!(last_stored.changed(rows).len(), 1);
+    }
+
+    #[test]
+    fn test_category() {
+        for category in [Category::Temperature, Category::Ups, Category::Readings] {
+            assert_eq!(category.as_str().parse(), Ok(category));
+        }
+        assert_eq!("changes".parse::<Category>(), Err(()));
+    }
+}
@@ -0,0 +1,134 @@
+// Licensed under the Open Software License version 3.0
+use super::config::StorageConfig;
+use crate::{
+    hardware::reading::ReadingsUpdate, nut::sender::UpsUpdate,
+    one_wire::sender::MeasuredTemperature,
+};
+use tokio::sync::broadcast;
+
+#[cfg(feature = "storage")]
+mod client {
+    use super::super::{
+        database::Database,
+        rows::{reading_rows, temperature_rows, ups_rows, LastStored, Row},
+    };
+    use super::*;
+    use crate::{clock::SharedClock, introspection, self_metrics::lag::recv_counting_lag};
+    use std::{
+        cmp::max,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    /// SQLite calls block, so they run outside of the async runtime
+    async fn write_rows(database: &Arc<Mutex<Database>>, rows: Vec<Row>) {
+        if rows.is_empty() {
+            return;
+        }
+        let database = database.clone();
+        let result =
+            tokio::task::spawn_blocking(move || database.lock().unwrap().insert(&rows)).await;
+        match result {
+            Ok(Ok(())) => (),
+            Ok(Err(error)) => tracing::warn!("Failed to store measurements: {}", error),
+            Err(error) => tracing::error!("Storage writer panicked: {}", error),
+        }
+    }
+
+    async fn prune(database: &Arc<Mutex<Database>>, oldest: i64) {
+        let database = database.clone();
+        let result =
+            tokio::task::spawn_blocking(move || database.lock().unwrap().prune(oldest)).await;
+        match result {
+            Ok(Ok(deleted)) => tracing::debug!("Pruned {} stored measurements", deleted),
+            Ok(Err(error)) => tracing::warn!("Failed to prune stored measurements: {}", error),
+            Err(error) => tracing::error!("Storage pruning panicked: {}", error),
+        }
+    }
+
+    pub async fn run(
+        mut shutdown_rx: broadcast::Receiver<()>,
+        config: StorageConfig,
+        mut one_wire_rx: broadcast::Receiver<Vec<MeasuredTemperature>>,
+        mut ups_monitoring_rx: broadcast::Receiver<UpsUpdate>,
+        mut readings_rx: broadcast::Receiver<ReadingsUpdate>,
+    ) {
+        let path = config.get_path();
+        let database = match Database::open(&path) {
+            Ok(database) => Arc::new(Mutex::new(database)),
+            Err(error) => {
+                tracing::error!("Failed to open {}: {}", path.display(), error);
+                return;
+            }
+        };
+        let clock = SharedClock::default();
+        let retention = chrono::Duration::from_std(config.get_retention())
+            .unwrap_or_else(|_| chrono::Duration::max_value());
+        // First tick is immediate, so leftovers from before a restart are pruned on startup
+        let mut prune_interval =
+            tokio::time::interval(max(config.get_prune_interval(), Duration::from_secs(1)));
+        let mut last_stored = LastStored::default();
+        tracing::debug!("Starting storage loop");
+        let _task = introspection::task_started("storage");
+        loop {
+            introspection::mark_iteration("storage");
+            tokio::select! {
+                Ok(sensors) = recv_counting_lag(&mut one_wire_rx) => {
+                    let rows = temperature_rows(&sensors, clock.utc_now());
+                    write_rows(&database, last_stored.changed(rows)).await;
+                }
+                Ok(update) = recv_counting_lag(&mut ups_monitoring_rx) => {
+                    let rows = ups_rows(&update.upses, clock.utc_now());
+                    write_rows(&database, last_stored.changed(rows)).await;
+                }
+                Ok(update) = recv_counting_lag(&mut readings_rx) => {
+                    let rows = reading_rows(&update.readings, clock.utc_now());
+                    write_rows(&database, last_stored.changed(rows)).await;
+                }
+                _ = prune_interval.tick() => {
+                    let oldest = clock
+                        .utc_now()
+                        .checked_sub_signed(retention)
+                        .map_or(i64::MIN, |oldest| oldest.timestamp_millis());
+                    prune(&database, oldest).await;
+                }
+                _ = shutdown_rx.recv() => {
+                    tracing::trace!("Shutting down storage loop");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+pub async fn start_storage_loop(
+    shutdown_rx: broadcast::Receiver<()>,
+    config: StorageConfig,
+    one_wire_rx: broadcast::Receiver<Vec<MeasuredTemperature>>,
+    ups_monitoring_rx: broadcast::Receiver<UpsUpdate>,
+    readings_rx: broadcast::Receiver<ReadingsUpdate>,
+) {
+    // Check if module is enabled
+    if !config.is_enabled() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+
+    #[cfg(feature = "storage")]
+    client::run(
+        shutdown_rx,
+        config,
+        one_wire_rx,
+        ups_monitoring_rx,
+        readings_rx,
+    )
+    .await;
+
+    #[cfg(not(feature = "storage"))]
+    {
+        let _ = (shutdown_rx, one_wire_rx, ups_monitoring_rx, readings_rx);
+        tracing::error!(
+            "Storage is enabled in config but this binary was built without storage feature"
+        );
+    }
+}
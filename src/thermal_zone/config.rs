@@ -0,0 +1,74 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThermalZoneConfig {
+    enabled: Option<bool>,
+    cooldown: Option<Duration>,
+    path: Option<PathBuf>,
+    // Zone types (ex. cpu-thermal) or directory names (ex. thermal_zone0) to read, all if not set
+    zones: Option<Vec<String>>,
+}
+
+impl Default for ThermalZoneConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            cooldown: Some(Duration::from_secs(5)),
+            path: Some(PathBuf::from("/sys/class/thermal")),
+            zones: None,
+        }
+    }
+}
+
+impl Example for ThermalZoneConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(false),
+            cooldown: Some(Duration::from_secs(5)),
+            path: Some(PathBuf::from("/sys/class/thermal")),
+            zones: Some(vec![String::from("cpu-thermal")]),
+        }
+    }
+}
+
+impl ThermalZoneConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_cooldown(&self) -> Duration {
+        self.cooldown.unwrap_or(Duration::from_secs(5))
+    }
+
+    pub fn get_path(&self) -> PathBuf {
+        self.path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("/sys/class/thermal"))
+    }
+
+    /// Whether zone should be read, matched by its type or directory name
+    pub fn is_zone_enabled(&self, zone_type: &str, directory: &str) -> bool {
+        match &self.zones {
+            Some(zones) => zones
+                .iter()
+                .any(|zone| zone == zone_type || zone == directory),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_zone_enabled() {
+        let config = ThermalZoneConfig::example();
+        assert!(config.is_zone_enabled("cpu-thermal", "thermal_zone0"));
+        assert!(!config.is_zone_enabled("gpu-thermal", "thermal_zone1"));
+        assert!(ThermalZoneConfig::default().is_zone_enabled("gpu-thermal", "thermal_zone1"));
+    }
+}
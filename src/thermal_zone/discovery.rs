@@ -0,0 +1,123 @@
+// Licensed under the Open Software License version 3.0
+//! Thermal zones exposed by the kernel in `/sys/class/thermal/thermal_zone*`
+use super::config::ThermalZoneConfig;
+use crate::dedup_log::{info_resolved, warn_deduplicated};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThermalZone {
+    /// Zone type with `-<n>` appended to repeated types, ex. `acpitz`, `acpitz-1`
+    pub id: String,
+    pub zone_type: String,
+    pub path: PathBuf,
+}
+
+/// Number of `thermal_zone<n>`, `None` for other entries (ex. cooling devices)
+fn zone_number(directory: &str) -> Option<u32> {
+    directory.strip_prefix("thermal_zone")?.parse().ok()
+}
+
+/// Enabled zones ordered by their number, so ids of repeated types are stable
+pub fn discover_zones(path: &Path, config: &ThermalZoneConfig) -> Vec<ThermalZone> {
+    // Read on every poll, so a missing directory is logged only once
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => {
+            info_resolved!(
+                "thermal_zone:discovery",
+                "Reading thermal zones from {} again",
+                path.display()
+            );
+            entries
+        }
+        Err(error) => {
+            warn_deduplicated!(
+                "thermal_zone:discovery",
+                "Failed to read {}: {}",
+                path.display(),
+                error
+            );
+            return Vec::new();
+        }
+    };
+    let mut directories: Vec<(u32, String)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let directory = entry.file_name().to_string_lossy().to_string();
+            Some((zone_number(&directory)?, directory))
+        })
+        .collect();
+    directories.sort();
+    let mut type_counts: HashMap<String, usize> = HashMap::new();
+    let mut zones = Vec::new();
+    for (_, directory) in directories {
+        let zone_path = path.join(&directory);
+        let zone_type = fs::read_to_string(zone_path.join("type"))
+            .map(|zone_type| zone_type.trim().to_string())
+            .unwrap_or_else(|_| directory.clone());
+        // Count disabled zones too, so ids don't change with the enable list
+        let count = type_counts.entry(zone_type.clone()).or_default();
+        let id = match *count {
+            0 => zone_type.clone(),
+            count => format!("{}-{}", zone_type, count),
+        };
+        *count += 1;
+        if config.is_zone_enabled(&zone_type, &directory) {
+            zones.push(ThermalZone {
+                id,
+                zone_type,
+                path: zone_path,
+            });
+        }
+    }
+    zones
+}
+
+/// Temperature in °C, `None` if zone is disabled or unreadable
+pub fn read_temperature(zone: &ThermalZone) -> Option<f64> {
+    let millidegrees = fs::read_to_string(zone.path.join("temp")).ok()?;
+    let millidegrees: i64 = millidegrees.trim().parse().ok()?;
+    Some(millidegrees as f64 / 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::types::Example;
+
+    fn create_zone(root: &Path, directory: &str, zone_type: &str, temp: &str) {
+        let zone_path = root.join(directory);
+        fs::create_dir(&zone_path).unwrap();
+        fs::write(zone_path.join("type"), format!("{}\n", zone_type)).unwrap();
+        fs::write(zone_path.join("temp"), format!("{}\n", temp)).unwrap();
+    }
+
+    #[test]
+    fn test_discover_zones() {
+        let root = tempfile::tempdir().unwrap();
+        create_zone(root.path(), "thermal_zone10", "acpitz", "30000");
+        create_zone(root.path(), "thermal_zone2", "acpitz", "25000");
+        create_zone(root.path(), "thermal_zone0", "cpu-thermal", "48312");
+        fs::create_dir(root.path().join("cooling_device0")).unwrap();
+
+        let zones = discover_zones(root.path(), &serde_json::from_str("{}").unwrap());
+        let ids: Vec<&str> = zones.iter().map(|zone| zone.id.as_str()).collect();
+        assert_eq!(ids, vec!["cpu-thermal", "acpitz", "acpitz-1"]);
+        assert_eq!(read_temperature(&zones[0]), Some(48.312));
+
+        let zones = discover_zones(root.path(), &ThermalZoneConfig::example());
+        assert_eq!(zones.len(), 1);
+        assert_eq!(zones[0].zone_type, "cpu-thermal");
+    }
+
+    #[test]
+    fn test_read_temperature_of_disabled_zone() {
+        let root = tempfile::tempdir().unwrap();
+        create_zone(root.path(), "thermal_zone0", "battery", "");
+        let zones = discover_zones(root.path(), &ThermalZoneConfig::default());
+        assert_eq!(read_temperature(&zones[0]), None);
+    }
+}
@@ -0,0 +1,115 @@
+// Licensed under the Open Software License version 3.0
+use super::{
+    config::ThermalZoneConfig,
+    discovery::{discover_zones, read_temperature},
+};
+use crate::{
+    hardware::{
+        reading::{Reading, ReadingsUpdate},
+        types::{HardwareMetadata, HardwareType, SourceType},
+    },
+    introspection, sampling,
+    scheduler::job::{PollFuture, PollJob},
+};
+use std::{
+    cmp::max,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::{sync::broadcast, time::sleep};
+
+const PUBLISHER: &str = "thermal_zone";
+
+/// Readings of all enabled zones, discovered again every time as zones may appear later
+pub fn read_thermal_zones(path: &Path, config: &ThermalZoneConfig) -> Vec<Reading> {
+    discover_zones(path, config)
+        .into_iter()
+        .filter_map(|zone| {
+            let temperature = read_temperature(&zone);
+            if temperature.is_none() {
+                tracing::debug!("Skipping unreadable thermal zone {}", zone.path.display());
+                return None;
+            }
+            Some(
+                Reading::new(
+                    HardwareMetadata::new(
+                        zone.id,
+                        HardwareType::TemperatureSensor,
+                        SourceType::ThermalZone,
+                    )
+                    .measured_now(),
+                )
+                .with_value("temperature", temperature),
+            )
+        })
+        .collect()
+}
+
+pub struct ThermalZonePoller {
+    path: PathBuf,
+    cooldown: Duration,
+    config: ThermalZoneConfig,
+    tx: broadcast::Sender<ReadingsUpdate>,
+}
+
+impl ThermalZonePoller {
+    pub fn new(config: ThermalZoneConfig, tx: broadcast::Sender<ReadingsUpdate>) -> Self {
+        Self {
+            path: config.get_path(),
+            cooldown: max(config.get_cooldown(), Duration::from_secs(1)),
+            config,
+            tx,
+        }
+    }
+
+    /// Publish temperature of all enabled zones, returns delay until the next poll
+    pub fn poll_once(&mut self) -> Duration {
+        introspection::mark_iteration("thermal_zone");
+        let readings = read_thermal_zones(&self.path, &self.config);
+        tracing::trace!("Sending {:?} to channel", readings);
+        if self.tx.receiver_count() > 0 {
+            self.tx
+                .send(ReadingsUpdate::new(PUBLISHER, readings))
+                .unwrap();
+            introspection::observe_channel("readings", &self.tx);
+        }
+        introspection::mark_idle("thermal_zone");
+        self.cooldown
+    }
+}
+
+impl PollJob for ThermalZonePoller {
+    fn name(&self) -> String {
+        String::from("thermal_zone")
+    }
+
+    fn poll(&mut self) -> PollFuture<'_> {
+        let delay = self.poll_once();
+        Box::pin(async move { delay })
+    }
+}
+
+pub async fn start_thermal_zone_loop(
+    mut shutdown_rx: broadcast::Receiver<()>,
+    config: ThermalZoneConfig,
+    tx: broadcast::Sender<ReadingsUpdate>,
+) {
+    // Check if module is enabled
+    if !config.is_enabled() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+    tracing::debug!("Starting thermal zone loop");
+    let mut poller = ThermalZonePoller::new(config, tx);
+    let _task = introspection::task_started("thermal_zone");
+    loop {
+        let delay = poller.poll_once();
+        tokio::select! {
+            _ = shutdown_rx.recv() => {
+                tracing::trace!("Shutting down thermal zone loop");
+                break;
+            }
+            _ = sleep(sampling::tick::aligned(delay)) => {}
+        }
+    }
+}
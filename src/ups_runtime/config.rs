@@ -0,0 +1,41 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UpsRuntimeConfig {
+    enabled: Option<bool>,
+    // Weight of the newest sample in exponential moving average (0, 1]
+    smoothing: Option<f64>,
+}
+
+impl Default for UpsRuntimeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            smoothing: Some(0.2),
+        }
+    }
+}
+
+impl Example for UpsRuntimeConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(true),
+            smoothing: Some(0.2),
+        }
+    }
+}
+
+impl UpsRuntimeConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_smoothing(&self) -> f64 {
+        match self.smoothing {
+            Some(smoothing) if smoothing > 0.0 && smoothing <= 1.0 => smoothing,
+            _ => 0.2,
+        }
+    }
+}
@@ -0,0 +1,170 @@
+// Licensed under the Open Software License version 3.0
+use super::config::UpsRuntimeConfig;
+use crate::{
+    hardware::{
+        reading::{Reading, ReadingsUpdate},
+        types::{HardwareMetadata, HardwareType, SourceType},
+    },
+    introspection,
+    nut::sender::{UninterruptiblePowerSupplyData, UpsUpdate},
+};
+use std::collections::HashMap;
+use tokio::sync::broadcast;
+
+const PUBLISHER: &str = "ups_runtime";
+// Avoid dividing by zero when UPS reports no load
+const MIN_LOAD: f64 = 1.0;
+
+fn get_number(ups: &UninterruptiblePowerSupplyData, variable: &str) -> Option<f64> {
+    ups.variables.get(variable)?.trim().parse().ok()
+}
+
+/// Projects runtime remaining at current load from UPS history
+///
+/// Runtime is assumed to be inversely proportional to load and proportional to charge,
+/// so `battery.runtime * ups.load / battery.charge` is roughly constant for a battery.
+/// Smoothing that constant removes jumps caused by momentary load spikes.
+#[derive(Debug, Clone, Default)]
+pub struct RuntimeProjector {
+    smoothing: f64,
+    // Smoothed runtime (s) * load (%) per charge (%) for each UPS
+    capacity: HashMap<String, f64>,
+}
+
+impl RuntimeProjector {
+    pub fn new(smoothing: f64) -> Self {
+        Self {
+            smoothing,
+            capacity: HashMap::new(),
+        }
+    }
+
+    /// Update history with `ups` and return projected minutes remaining
+    pub fn update(&mut self, ups: &UninterruptiblePowerSupplyData) -> Option<f64> {
+        let charge = get_number(ups, "battery.charge")?;
+        let load = get_number(ups, "ups.load")?.max(MIN_LOAD);
+        let id = &ups.meta.hw.id;
+        // Learn only from samples that report runtime
+        if let Some(runtime) = get_number(ups, "battery.runtime") {
+            if charge > 0.0 {
+                let sample = runtime * load / charge;
+                let smoothed = match self.capacity.get(id) {
+                    Some(previous) => previous + self.smoothing * (sample - previous),
+                    None => sample,
+                };
+                self.capacity.insert(id.clone(), smoothed);
+            }
+        }
+        let capacity = self.capacity.get(id)?;
+        Some(capacity * charge / load / 60.0)
+    }
+
+    pub fn project(&mut self, upses: &[UninterruptiblePowerSupplyData]) -> Vec<Reading> {
+        upses
+            .iter()
+            .filter_map(|ups| {
+                let minutes = self.update(ups)?;
+                let mut meta = HardwareMetadata::new(
+                    ups.meta.hw.id.clone(),
+                    HardwareType::UninterruptiblePowerSupply,
+                    SourceType::Derived,
+                );
+                meta.hw.name = ups.meta.hw.name.clone();
+                Some(Reading::new(meta).with_value("projected_minutes_remaining", Some(minutes)))
+            })
+            .collect()
+    }
+}
+
+pub async fn start_ups_runtime_loop(
+    mut shutdown_rx: broadcast::Receiver<()>,
+    config: UpsRuntimeConfig,
+    mut ups_monitoring_rx: broadcast::Receiver<UpsUpdate>,
+    tx: broadcast::Sender<ReadingsUpdate>,
+) {
+    // Check if module is enabled
+    if !config.is_enabled() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+    tracing::debug!("Starting UPS runtime projection loop");
+    let mut projector = RuntimeProjector::new(config.get_smoothing());
+    let _task = introspection::task_started("ups_runtime");
+    loop {
+        tokio::select! {
+            Ok(update) = ups_monitoring_rx.recv() => {
+                introspection::mark_iteration("ups_runtime");
+                let readings = projector.project(&update.upses);
+                tracing::trace!("Sending {:?} to channel", readings);
+                if tx.receiver_count() > 0 {
+                    // Projections of other UPS publishers are kept
+                    let publisher = format!("{}:{}", PUBLISHER, update.publisher);
+                    tx.send(ReadingsUpdate::new(&publisher, readings)).unwrap();
+                    introspection::observe_channel("readings", &tx);
+                }
+            }
+            _ = shutdown_rx.recv() => {
+                tracing::trace!("Shutting down UPS runtime projection loop");
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::types::Example;
+
+    fn ups(charge: &str, load: &str, runtime: Option<&str>) -> UninterruptiblePowerSupplyData {
+        let mut ups = UninterruptiblePowerSupplyData::example();
+        ups.variables
+            .insert(String::from("battery.charge"), String::from(charge));
+        ups.variables
+            .insert(String::from("ups.load"), String::from(load));
+        if let Some(runtime) = runtime {
+            ups.variables
+                .insert(String::from("battery.runtime"), String::from(runtime));
+        }
+        ups
+    }
+
+    #[test]
+    fn test_requires_runtime_history() {
+        let mut projector = RuntimeProjector::new(0.5);
+        assert_eq!(projector.update(&ups("100", "20", None)), None);
+        assert_eq!(
+            projector.update(&ups("100", "20", Some("1200"))),
+            Some(20.0)
+        );
+        // Later samples without runtime reuse history
+        assert_eq!(projector.update(&ups("50", "20", None)), Some(10.0));
+    }
+
+    #[test]
+    fn test_scales_with_current_load() {
+        let mut projector = RuntimeProjector::new(0.5);
+        projector.update(&ups("100", "20", Some("1200")));
+        assert_eq!(projector.update(&ups("100", "40", None)), Some(10.0));
+    }
+
+    #[test]
+    fn test_smooths_jumps() {
+        let mut projector = RuntimeProjector::new(0.5);
+        projector.update(&ups("100", "20", Some("1200")));
+        // Reported runtime jumps to 30 minutes, projection moves halfway
+        assert_eq!(
+            projector.update(&ups("100", "20", Some("1800"))),
+            Some(25.0)
+        );
+    }
+
+    #[test]
+    fn test_project_publishes_derived_reading() {
+        let mut projector = RuntimeProjector::new(0.2);
+        let readings = projector.project(&[ups("100", "15", Some("600"))]);
+        assert_eq!(readings.len(), 1);
+        assert_eq!(readings[0].meta.source.source_type, SourceType::Derived);
+        assert_eq!(readings[0].values["projected_minutes_remaining"], 10.0);
+    }
+}
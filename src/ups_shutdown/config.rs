@@ -0,0 +1,71 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpsShutdownConfig {
+    enabled: Option<bool>,
+    // hw.id of the UPS to watch, ex. "[ups1]ups-monitor@localhost:3493"
+    ups_id: Option<String>,
+    // Each command is a program followed by its arguments
+    commands: Option<Vec<Vec<String>>>,
+    webhooks: Option<Vec<String>>,
+    shutdown_host: Option<bool>,
+    shutdown_delay: Option<Duration>,
+}
+
+impl Default for UpsShutdownConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            ups_id: None,
+            commands: None,
+            webhooks: None,
+            shutdown_host: Some(false),
+            shutdown_delay: Some(Duration::from_secs(60)),
+        }
+    }
+}
+
+impl Example for UpsShutdownConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(false),
+            ups_id: Some(String::from("[ups1]ups-monitor@localhost:3493")),
+            commands: Some(vec![vec![
+                String::from("/usr/local/bin/notify"),
+                String::from("UPS battery is low"),
+            ]]),
+            webhooks: Some(vec![String::from("http://localhost:3001/anything/ups-low")]),
+            shutdown_host: Some(false),
+            shutdown_delay: Some(Duration::from_secs(60)),
+        }
+    }
+}
+
+impl UpsShutdownConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_ups_id(&self) -> Option<String> {
+        self.ups_id.clone()
+    }
+
+    pub fn get_commands(&self) -> Vec<Vec<String>> {
+        self.commands.clone().unwrap_or_default()
+    }
+
+    pub fn get_webhooks(&self) -> Vec<String> {
+        self.webhooks.clone().unwrap_or_default()
+    }
+
+    pub fn get_shutdown_host(&self) -> bool {
+        self.shutdown_host.unwrap_or_default()
+    }
+
+    pub fn get_shutdown_delay(&self) -> Duration {
+        self.shutdown_delay.unwrap_or(Duration::from_secs(60))
+    }
+}
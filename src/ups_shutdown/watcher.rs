@@ -0,0 +1,214 @@
+// Licensed under the Open Software License version 3.0
+use super::config::UpsShutdownConfig;
+#[cfg(feature = "active-sender")]
+use crate::active_sender::{config::Endpoint, receiver::send_data};
+use crate::{
+    introspection,
+    nut::sender::{UninterruptiblePowerSupplyData, UpsUpdate},
+};
+#[cfg(feature = "active-sender")]
+use serde::Serialize;
+#[cfg(feature = "active-sender")]
+use std::time::Duration;
+use tokio::{
+    process::Command,
+    sync::broadcast,
+    time::{sleep_until, Instant},
+};
+
+/// Check if `ups.status` reports running on battery with low battery (or forced shutdown)
+pub fn is_critical(ups: &UninterruptiblePowerSupplyData) -> bool {
+    let status = match ups.variables.get("ups.status") {
+        Some(status) => status,
+        None => return false,
+    };
+    let flags: Vec<&str> = status.split_whitespace().collect();
+    flags.contains(&"FSD") || (flags.contains(&"OB") && flags.contains(&"LB"))
+}
+
+pub async fn run_command(command: &[String]) {
+    let (program, args) = match command.split_first() {
+        Some(parts) => parts,
+        None => return,
+    };
+    tracing::info!("Running {:?}", command);
+    match Command::new(program).args(args).status().await {
+        Ok(status) if status.success() => {}
+        Ok(status) => tracing::warn!("{:?} exited with {}", command, status),
+        Err(error) => tracing::error!("Failed to run {:?}: {}", command, error),
+    }
+}
+
+async fn run_actions(
+    config: &UpsShutdownConfig,
+    ups: &UninterruptiblePowerSupplyData,
+    instance_id: &str,
+) {
+    for command in config.get_commands() {
+        run_command(&command).await;
+    }
+    send_webhooks(config, ups, instance_id).await;
+}
+
+/// UPS data with the instance that saw it, so receivers of many instances can tell them apart
+#[cfg(feature = "active-sender")]
+#[derive(Debug, Serialize)]
+struct WebhookPayload<'a> {
+    #[serde(flatten)]
+    ups: &'a UninterruptiblePowerSupplyData,
+    instance_id: &'a str,
+}
+
+#[cfg(feature = "active-sender")]
+async fn send_webhooks(
+    config: &UpsShutdownConfig,
+    ups: &UninterruptiblePowerSupplyData,
+    instance_id: &str,
+) {
+    let payload = WebhookPayload { ups, instance_id };
+    let client = reqwest::Client::new();
+    for url in config.get_webhooks() {
+        let endpoint = Endpoint {
+            url,
+            bearer_token: None,
+            ..Default::default()
+        };
+        send_data(
+            &client,
+            &payload,
+            &endpoint,
+            "ups_shutdown",
+            &Duration::from_secs(5),
+            &false,
+            &1024,
+        )
+        .await;
+    }
+}
+
+#[cfg(not(feature = "active-sender"))]
+async fn send_webhooks(
+    config: &UpsShutdownConfig,
+    _ups: &UninterruptiblePowerSupplyData,
+    _instance_id: &str,
+) {
+    if !config.get_webhooks().is_empty() {
+        tracing::error!("Webhooks are configured but the active-sender feature is disabled");
+    }
+}
+
+async fn shutdown_host() {
+    tracing::warn!("Shutting down host");
+    let command = [
+        String::from("shutdown"),
+        String::from("-h"),
+        String::from("now"),
+    ];
+    run_command(&command).await;
+}
+
+pub async fn start_ups_shutdown_loop(
+    mut shutdown_rx: broadcast::Receiver<()>,
+    config: UpsShutdownConfig,
+    mut ups_monitoring_rx: broadcast::Receiver<UpsUpdate>,
+    instance_id: String,
+) {
+    // Check if module is enabled
+    if !config.is_enabled() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+    let ups_id = match config.get_ups_id() {
+        Some(ups_id) => ups_id,
+        None => {
+            tracing::error!("UPS shutdown is enabled but ups_id is not set");
+            return;
+        }
+    };
+
+    tracing::trace!("Starting UPS shutdown loop");
+    // Run actions once per power event
+    let mut triggered = false;
+    let mut shutdown_deadline: Option<Instant> = None;
+    let _task = introspection::task_started("ups_shutdown");
+    loop {
+        tokio::select! {
+            Ok(update) = ups_monitoring_rx.recv() => {
+                introspection::mark_iteration("ups_shutdown");
+                let ups = match update.upses.iter().find(|ups| ups.meta.hw.id == ups_id) {
+                    Some(ups) => ups,
+                    None => continue,
+                };
+                let critical = is_critical(ups);
+                if critical && !triggered {
+                    tracing::warn!("UPS {} is on battery with low charge", ups_id);
+                    triggered = true;
+                    run_actions(&config, ups, &instance_id).await;
+                    if config.get_shutdown_host() {
+                        let delay = config.get_shutdown_delay();
+                        tracing::warn!("Host will be shut down in {:?}", delay);
+                        shutdown_deadline = Some(Instant::now() + delay);
+                    }
+                } else if !critical && triggered {
+                    tracing::warn!("UPS {} recovered", ups_id);
+                    triggered = false;
+                    if shutdown_deadline.take().is_some() {
+                        tracing::warn!("Cancelled host shutdown");
+                    }
+                }
+            }
+            _ = sleep_until(shutdown_deadline.unwrap_or_else(Instant::now)), if shutdown_deadline.is_some() => {
+                shutdown_deadline = None;
+                shutdown_host().await;
+            }
+            _ = shutdown_rx.recv() => {
+                tracing::trace!("Shutting down UPS shutdown loop");
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::types::Example;
+
+    fn ups_with_status(status: &str) -> UninterruptiblePowerSupplyData {
+        let mut ups = UninterruptiblePowerSupplyData::example();
+        ups.variables
+            .insert(String::from("ups.status"), String::from(status));
+        ups
+    }
+
+    #[test]
+    fn test_is_critical() {
+        assert!(is_critical(&ups_with_status("OB LB")));
+        assert!(is_critical(&ups_with_status("OB DISCHRG LB")));
+        assert!(is_critical(&ups_with_status("FSD OB LB")));
+        assert!(is_critical(&ups_with_status("OL FSD")));
+    }
+
+    #[test]
+    fn test_is_not_critical() {
+        assert!(!is_critical(&ups_with_status("OL")));
+        assert!(!is_critical(&ups_with_status("OB")));
+        assert!(!is_critical(&ups_with_status("OL LB")));
+        assert!(!is_critical(&UninterruptiblePowerSupplyData::example()));
+    }
+
+    #[cfg(feature = "active-sender")]
+    #[test]
+    fn test_webhook_payload_has_instance_id() {
+        let ups = UninterruptiblePowerSupplyData::example();
+        let payload = WebhookPayload {
+            ups: &ups,
+            instance_id: "instance",
+        };
+        let json = serde_json::to_value(&payload).unwrap();
+        assert_eq!(json["instance_id"], "instance");
+        // Same fields as before for existing receivers
+        assert_eq!(json["meta"]["hw"]["id"], "fake_hw_id");
+        assert_eq!(json["variables"]["battery.charge"], "100");
+    }
+}
@@ -0,0 +1,116 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsbHidProtocol {
+    // Q1 command over HID reports, used by many cheap UPSes (ex. with Cypress chips)
+    Megatec,
+    // HID Power Device Class feature reports of CyberPower units
+    CyberPower,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsbHidDeviceConfig {
+    vendor_id: u16,
+    product_id: u16,
+    protocol: UsbHidProtocol,
+    // Pick one of multiple identical units
+    serial: Option<String>,
+    // hw.id of the UPS, usb-<vendor_id>:<product_id> if not set
+    id: Option<String>,
+}
+
+impl Example for UsbHidDeviceConfig {
+    fn example() -> Self {
+        Self {
+            vendor_id: 0x0665,
+            product_id: 0x5161,
+            protocol: UsbHidProtocol::Megatec,
+            serial: None,
+            id: Some(String::from("ups-office")),
+        }
+    }
+}
+
+impl UsbHidDeviceConfig {
+    pub fn get_vendor_id(&self) -> u16 {
+        self.vendor_id
+    }
+
+    pub fn get_product_id(&self) -> u16 {
+        self.product_id
+    }
+
+    pub fn get_protocol(&self) -> UsbHidProtocol {
+        self.protocol
+    }
+
+    pub fn get_serial(&self) -> Option<String> {
+        self.serial.clone()
+    }
+
+    pub fn get_id(&self) -> String {
+        self.id
+            .clone()
+            .unwrap_or_else(|| format!("usb-{:04x}:{:04x}", self.vendor_id, self.product_id))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsbHidConfig {
+    enabled: Option<bool>,
+    cooldown: Option<Duration>,
+    devices: Option<Vec<UsbHidDeviceConfig>>,
+}
+
+impl Default for UsbHidConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            cooldown: Some(Duration::from_secs(5)),
+            devices: None,
+        }
+    }
+}
+
+impl Example for UsbHidConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(false),
+            cooldown: Some(Duration::from_secs(5)),
+            devices: Some(vec![UsbHidDeviceConfig::example()]),
+        }
+    }
+}
+
+impl UsbHidConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_cooldown(&self) -> Duration {
+        self.cooldown.unwrap_or(Duration::from_secs(5))
+    }
+
+    pub fn get_devices(&self) -> Vec<UsbHidDeviceConfig> {
+        self.devices.clone().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_config_get_id() {
+        assert_eq!(UsbHidDeviceConfig::example().get_id(), "ups-office");
+        let config: UsbHidDeviceConfig = serde_json::from_str(
+            r#"{"vendor_id": 1892, "product_id": 1281, "protocol": "cyber_power"}"#,
+        )
+        .unwrap();
+        assert_eq!(config.get_id(), "usb-0764:0501");
+    }
+}
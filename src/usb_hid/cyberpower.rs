@@ -0,0 +1,118 @@
+// Licensed under the Open Software License version 3.0
+//! Feature reports of CyberPower HID Power Device Class units (CP, OR and VP series)
+use std::collections::HashMap;
+
+/// `battery.charge` (%) and `battery.runtime` (s, little endian u16)
+pub const BATTERY_REPORT: u8 = 0x08;
+/// `PresentStatus` bits
+pub const STATUS_REPORT: u8 = 0x0b;
+/// `input.voltage` (V, little endian u16)
+pub const INPUT_VOLTAGE_REPORT: u8 = 0x0f;
+/// `output.voltage` (V, little endian u16)
+pub const OUTPUT_VOLTAGE_REPORT: u8 = 0x12;
+/// `ups.load` (%)
+pub const LOAD_REPORT: u8 = 0x13;
+
+pub const REPORTS: [u8; 5] = [
+    BATTERY_REPORT,
+    STATUS_REPORT,
+    INPUT_VOLTAGE_REPORT,
+    OUTPUT_VOLTAGE_REPORT,
+    LOAD_REPORT,
+];
+
+const AC_PRESENT: u8 = 1 << 0;
+const CHARGING: u8 = 1 << 1;
+const DISCHARGING: u8 = 1 << 2;
+const BELOW_REMAINING_CAPACITY_LIMIT: u8 = 1 << 3;
+
+fn u16_at(report: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes([
+        *report.get(offset)?,
+        *report.get(offset + 1)?,
+    ]))
+}
+
+fn status_flags(bits: u8) -> Vec<&'static str> {
+    let mut flags = Vec::new();
+    flags.push(if bits & AC_PRESENT != 0 { "OL" } else { "OB" });
+    if bits & CHARGING != 0 {
+        flags.push("CHRG");
+    }
+    if bits & DISCHARGING != 0 {
+        flags.push("DISCHRG");
+    }
+    if bits & BELOW_REMAINING_CAPACITY_LIMIT != 0 {
+        flags.push("LB");
+    }
+    flags
+}
+
+/// Variables named after their NUT counterparts
+///
+/// Every report starts with its id, unknown and truncated reports are skipped
+pub fn parse_reports(reports: &[Vec<u8>]) -> HashMap<String, String> {
+    let mut variables = HashMap::new();
+    let mut insert = |name: &str, value: Option<String>| {
+        if let Some(value) = value {
+            variables.insert(String::from(name), value);
+        }
+    };
+    for report in reports {
+        match report.first() {
+            Some(&BATTERY_REPORT) => {
+                insert("battery.charge", report.get(1).map(u8::to_string));
+                insert("battery.runtime", u16_at(report, 2).map(|v| v.to_string()));
+            }
+            Some(&STATUS_REPORT) => {
+                let status = report.get(1).map(|bits| status_flags(*bits).join(" "));
+                insert("ups.status", status);
+            }
+            Some(&INPUT_VOLTAGE_REPORT) => {
+                insert("input.voltage", u16_at(report, 1).map(|v| v.to_string()));
+            }
+            Some(&OUTPUT_VOLTAGE_REPORT) => {
+                insert("output.voltage", u16_at(report, 1).map(|v| v.to_string()));
+            }
+            Some(&LOAD_REPORT) => {
+                insert("ups.load", report.get(1).map(u8::to_string));
+            }
+            _ => {}
+        }
+    }
+    variables
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reports() {
+        let variables = parse_reports(&[
+            vec![BATTERY_REPORT, 97, 0x10, 0x0e],
+            vec![STATUS_REPORT, AC_PRESENT | CHARGING],
+            vec![INPUT_VOLTAGE_REPORT, 0xe6, 0x00],
+            vec![LOAD_REPORT, 21],
+        ]);
+        assert_eq!(variables["battery.charge"], "97");
+        assert_eq!(variables["battery.runtime"], "3600");
+        assert_eq!(variables["ups.status"], "OL CHRG");
+        assert_eq!(variables["input.voltage"], "230");
+        assert_eq!(variables["ups.load"], "21");
+        assert!(!variables.contains_key("output.voltage"));
+    }
+
+    #[test]
+    fn test_parse_truncated_reports() {
+        let variables = parse_reports(&[
+            vec![BATTERY_REPORT, 15, 0x10],
+            vec![STATUS_REPORT, DISCHARGING | BELOW_REMAINING_CAPACITY_LIMIT],
+            vec![OUTPUT_VOLTAGE_REPORT],
+            vec![],
+        ]);
+        assert_eq!(variables["battery.charge"], "15");
+        assert_eq!(variables["ups.status"], "OB DISCHRG LB");
+        assert_eq!(variables.len(), 2);
+    }
+}
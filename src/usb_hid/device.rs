@@ -0,0 +1,77 @@
+// Licensed under the Open Software License version 3.0
+//! Blocking reads of UPSes over hidapi, run with `spawn_blocking`
+use super::{
+    config::{UsbHidDeviceConfig, UsbHidProtocol},
+    cyberpower, megatec,
+};
+use hidapi::{HidApi, HidDevice};
+use std::collections::HashMap;
+
+/// Megatec units answer in 8 byte chunks
+const CHUNK_SIZE: usize = 8;
+const READ_TIMEOUT_MS: i32 = 1000;
+/// Longest `Q1` status is 47 bytes
+const MAX_RESPONSE_SIZE: usize = 64;
+
+fn open(api: &HidApi, config: &UsbHidDeviceConfig) -> Result<HidDevice, String> {
+    let (vendor_id, product_id) = (config.get_vendor_id(), config.get_product_id());
+    let device = match config.get_serial() {
+        Some(serial) => api.open_serial(vendor_id, product_id, &serial),
+        None => api.open(vendor_id, product_id),
+    };
+    device.map_err(|error| error.to_string())
+}
+
+fn query_megatec(device: &HidDevice) -> Result<HashMap<String, String>, String> {
+    // Report id 0 followed by the command padded to a full chunk
+    for command in megatec::STATUS_COMMAND.chunks(CHUNK_SIZE) {
+        let mut report = [0u8; CHUNK_SIZE + 1];
+        report[1..=command.len()].copy_from_slice(command);
+        device.write(&report).map_err(|error| error.to_string())?;
+    }
+    let mut response = Vec::new();
+    while !response.contains(&b'\r') {
+        if response.len() >= MAX_RESPONSE_SIZE {
+            return Err(String::from("response without terminator"));
+        }
+        let mut chunk = [0u8; CHUNK_SIZE];
+        let size = device
+            .read_timeout(&mut chunk, READ_TIMEOUT_MS)
+            .map_err(|error| error.to_string())?;
+        if size == 0 {
+            return Err(String::from("timed out"));
+        }
+        response.extend_from_slice(&chunk[..size]);
+    }
+    let response = String::from_utf8_lossy(&response);
+    megatec::parse_status(&response).ok_or_else(|| format!("invalid response {:?}", response))
+}
+
+fn query_cyberpower(device: &HidDevice) -> Result<HashMap<String, String>, String> {
+    let mut reports = Vec::new();
+    for report_id in cyberpower::REPORTS {
+        let mut report = [0u8; CHUNK_SIZE];
+        report[0] = report_id;
+        match device.get_feature_report(&mut report) {
+            Ok(size) => reports.push(report[..size].to_vec()),
+            Err(error) => tracing::debug!("Failed to get report {:#04x}: {}", report_id, error),
+        }
+    }
+    let variables = cyberpower::parse_reports(&reports);
+    if variables.is_empty() {
+        return Err(String::from("no readable reports"));
+    }
+    Ok(variables)
+}
+
+/// Variables of a single UPS, the device is opened again every poll to survive replugging
+pub fn query_device(
+    api: &HidApi,
+    config: &UsbHidDeviceConfig,
+) -> Result<HashMap<String, String>, String> {
+    let device = open(api, config)?;
+    match config.get_protocol() {
+        UsbHidProtocol::Megatec => query_megatec(&device),
+        UsbHidProtocol::CyberPower => query_cyberpower(&device),
+    }
+}
@@ -0,0 +1,104 @@
+// Licensed under the Open Software License version 3.0
+//! Megatec `Q1` status, ex. `(228.0 195.0 229.0 012 50.0 13.6 25.0 00001001\r`
+use std::collections::HashMap;
+
+pub const STATUS_COMMAND: &[u8] = b"Q1\r";
+
+/// Variables named after their NUT counterparts
+const FIELDS: [&str; 7] = [
+    "input.voltage",
+    "input.voltage.fault",
+    "output.voltage",
+    "ups.load",
+    "input.frequency",
+    "battery.voltage",
+    "ups.temperature",
+];
+
+/// `ups.status` flags from status bits, `b7..b0` from left to right
+fn status_flags(bits: &[u8]) -> Vec<&'static str> {
+    let bit = |index: usize| bits[7 - index] == b'1';
+    let mut flags = Vec::new();
+    flags.push(if bit(7) { "OB" } else { "OL" });
+    if bit(6) {
+        flags.push("LB");
+    }
+    // Line interactive units boost, online units bypass
+    if bit(5) {
+        flags.push(if bit(3) { "BOOST" } else { "BYPASS" });
+    }
+    if bit(2) {
+        flags.push("CAL");
+    }
+    if bit(1) {
+        flags.push("FSD");
+    }
+    flags
+}
+
+/// `None` if response isn't a complete `Q1` status
+pub fn parse_status(response: &str) -> Option<HashMap<String, String>> {
+    let response = response.trim_end_matches(['\r', '\0']).strip_prefix('(')?;
+    let values: Vec<&str> = response.split_whitespace().collect();
+    if values.len() != FIELDS.len() + 1 {
+        return None;
+    }
+    let bits = values[FIELDS.len()].as_bytes();
+    if bits.len() != 8 || !bits.iter().all(|bit| *bit == b'0' || *bit == b'1') {
+        return None;
+    }
+    let mut variables = HashMap::new();
+    for (name, value) in FIELDS.iter().zip(&values) {
+        // Not every unit measures everything, ex. temperature is often --.-
+        let value: f64 = match value.parse() {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+        variables.insert(String::from(*name), value.to_string());
+    }
+    variables.insert(String::from("ups.status"), status_flags(bits).join(" "));
+    if bits[3] == b'1' {
+        variables.insert(String::from("ups.alarm"), String::from("UPS failed"));
+    }
+    let beeper = if bits[7] == b'1' {
+        "enabled"
+    } else {
+        "disabled"
+    };
+    variables.insert(String::from("ups.beeper.status"), String::from(beeper));
+    Some(variables)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_status() {
+        let variables = parse_status("(228.0 195.0 229.0 012 50.0 13.6 --.- 00001001\r").unwrap();
+        assert_eq!(variables["input.voltage"], "228");
+        assert_eq!(variables["ups.load"], "12");
+        assert_eq!(variables["battery.voltage"], "13.6");
+        assert_eq!(variables["ups.status"], "OL");
+        assert_eq!(variables["ups.beeper.status"], "enabled");
+        assert!(!variables.contains_key("ups.temperature"));
+        assert!(!variables.contains_key("ups.alarm"));
+    }
+
+    #[test]
+    fn test_parse_status_on_battery() {
+        let variables = parse_status("(000.0 000.0 229.0 030 50.0 11.2 25.0 11101000\r").unwrap();
+        assert_eq!(variables["ups.status"], "OB LB BOOST");
+        assert_eq!(variables["ups.beeper.status"], "disabled");
+    }
+
+    #[test]
+    fn test_parse_incomplete_status() {
+        assert_eq!(parse_status("(228.0 195.0 229.0 012 50.0"), None);
+        assert_eq!(parse_status("NAK\r"), None);
+        assert_eq!(
+            parse_status("(228.0 195.0 229.0 012 50.0 13.6 25.0 0000100\r"),
+            None
+        );
+    }
+}
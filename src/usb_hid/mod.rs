@@ -0,0 +1,11 @@
+// Licensed under the Open Software License version 3.0
+#[cfg_attr(not(feature = "usb-hid"), allow(dead_code))]
+pub mod config;
+// Only used by the usb-hid feature and tests
+#[cfg_attr(not(feature = "usb-hid"), allow(dead_code))]
+mod cyberpower;
+#[cfg(feature = "usb-hid")]
+mod device;
+#[cfg_attr(not(feature = "usb-hid"), allow(dead_code))]
+mod megatec;
+pub mod sender;
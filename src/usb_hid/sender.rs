@@ -0,0 +1,159 @@
+// Licensed under the Open Software License version 3.0
+use super::config::UsbHidConfig;
+use crate::{nut::sender::UpsUpdate, relations::config::RelationsConfig};
+use tokio::sync::broadcast;
+
+#[cfg(feature = "usb-hid")]
+mod poller {
+    use super::super::{config::UsbHidDeviceConfig, device::query_device};
+    use super::*;
+    use crate::{
+        dedup_log::{info_resolved, warn_deduplicated},
+        hardware::types::{HardwareMetadata, HardwareType, SourceType},
+        introspection,
+        nut::sender::UninterruptiblePowerSupplyData,
+        sampling,
+        scheduler::job::{PollFuture, PollJob},
+    };
+    use hidapi::HidApi;
+    use std::{cmp::max, time::Duration};
+    use tokio::time::sleep;
+
+    const PUBLISHER: &str = "usb_hid";
+
+    fn read_devices(devices: &[UsbHidDeviceConfig]) -> Vec<UninterruptiblePowerSupplyData> {
+        let api = match HidApi::new() {
+            Ok(api) => api,
+            Err(error) => {
+                warn_deduplicated!("usb_hid:api", "Failed to initialize hidapi: {}", error);
+                return Vec::new();
+            }
+        };
+        info_resolved!("usb_hid:api", "Initialized hidapi");
+        let mut upses = Vec::new();
+        for device in devices {
+            let id = device.get_id();
+            let key = format!("usb_hid:{}", id);
+            match query_device(&api, device) {
+                Ok(variables) => {
+                    info_resolved!(key, "Reading {} over USB HID again", id);
+                    let meta = HardwareMetadata::new(
+                        id,
+                        HardwareType::UninterruptiblePowerSupply,
+                        SourceType::UsbHid,
+                    )
+                    .measured_now();
+                    upses.push(UninterruptiblePowerSupplyData {
+                        meta,
+                        variables,
+                        clients: None,
+                    });
+                }
+                Err(error) => {
+                    warn_deduplicated!(key, "Failed to read {} over USB HID: {}", id, error);
+                }
+            }
+        }
+        upses
+    }
+
+    /// State kept between polls of all configured devices
+    pub struct UsbHidPoller {
+        devices: Vec<UsbHidDeviceConfig>,
+        relations_config: RelationsConfig,
+        cooldown: Duration,
+        tx: broadcast::Sender<UpsUpdate>,
+    }
+
+    impl UsbHidPoller {
+        pub fn new(
+            config: &UsbHidConfig,
+            relations_config: RelationsConfig,
+            tx: broadcast::Sender<UpsUpdate>,
+        ) -> Self {
+            Self {
+                devices: config.get_devices(),
+                relations_config,
+                cooldown: max(config.get_cooldown(), Duration::from_secs(1)),
+                tx,
+            }
+        }
+
+        /// Publish all readable UPSes, returns delay until the next poll
+        pub async fn poll_once(&mut self) -> Duration {
+            introspection::mark_iteration("usb_hid");
+            let devices = self.devices.clone();
+            let mut upses = tokio::task::spawn_blocking(move || read_devices(&devices))
+                .await
+                .unwrap_or_default();
+            for ups in &mut upses {
+                self.relations_config.annotate(&mut ups.meta);
+            }
+            tracing::trace!("Sending {:?} to channel", upses);
+            if self.tx.receiver_count() > 0 {
+                self.tx.send(UpsUpdate::new(PUBLISHER, upses)).unwrap();
+                introspection::observe_channel("ups_monitoring", &self.tx);
+            }
+            introspection::mark_idle("usb_hid");
+            self.cooldown
+        }
+    }
+
+    impl PollJob for UsbHidPoller {
+        fn name(&self) -> String {
+            String::from("usb_hid")
+        }
+
+        fn poll(&mut self) -> PollFuture<'_> {
+            Box::pin(self.poll_once())
+        }
+    }
+
+    pub async fn run(
+        mut shutdown_rx: broadcast::Receiver<()>,
+        config: UsbHidConfig,
+        relations_config: RelationsConfig,
+        tx: broadcast::Sender<UpsUpdate>,
+    ) {
+        tracing::debug!("Starting USB HID loop");
+        let mut poller = UsbHidPoller::new(&config, relations_config, tx);
+        let _task = introspection::task_started("usb_hid");
+        loop {
+            let delay = poller.poll_once().await;
+            tokio::select! {
+                _ = shutdown_rx.recv() => {
+                    tracing::trace!("Shutting down USB HID loop");
+                    break;
+                }
+                _ = sleep(sampling::tick::aligned(delay)) => {}
+            }
+        }
+    }
+}
+
+#[cfg(feature = "usb-hid")]
+pub use poller::UsbHidPoller;
+
+pub async fn start_usb_hid_loop(
+    shutdown_rx: broadcast::Receiver<()>,
+    config: UsbHidConfig,
+    relations_config: RelationsConfig,
+    tx: broadcast::Sender<UpsUpdate>,
+) {
+    // Check if module is enabled
+    if !config.is_enabled() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+
+    #[cfg(feature = "usb-hid")]
+    poller::run(shutdown_rx, config, relations_config, tx).await;
+
+    #[cfg(not(feature = "usb-hid"))]
+    {
+        let _ = (shutdown_rx, relations_config, tx);
+        tracing::error!(
+            "USB HID is enabled in config but this binary was built without usb-hid feature"
+        );
+    }
+}
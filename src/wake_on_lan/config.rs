@@ -0,0 +1,55 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WakeOnLanTarget {
+    pub name: String,
+    pub mac_address: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WakeOnLanConfig {
+    enabled: Option<bool>,
+    broadcast_address: Option<String>,
+    targets: Option<Vec<WakeOnLanTarget>>,
+}
+
+impl Default for WakeOnLanConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            broadcast_address: Some(String::from("255.255.255.255:9")),
+            targets: None,
+        }
+    }
+}
+
+impl Example for WakeOnLanConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(false),
+            broadcast_address: Some(String::from("255.255.255.255:9")),
+            targets: Some(vec![WakeOnLanTarget {
+                name: String::from("server1"),
+                mac_address: String::from("00:11:22:33:44:55"),
+            }]),
+        }
+    }
+}
+
+impl WakeOnLanConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_broadcast_address(&self) -> String {
+        self.broadcast_address
+            .clone()
+            .unwrap_or_else(|| String::from("255.255.255.255:9"))
+    }
+
+    pub fn get_targets(&self) -> Vec<WakeOnLanTarget> {
+        self.targets.clone().unwrap_or_default()
+    }
+}
@@ -0,0 +1,8 @@
+// Licensed under the Open Software License version 3.0
+pub mod config;
+// Only used by passive endpoint backends
+#[cfg_attr(
+    not(any(feature = "passive-endpoint", feature = "axum")),
+    allow(dead_code)
+)]
+pub mod packet;
@@ -0,0 +1,107 @@
+// Licensed under the Open Software License version 3.0
+use super::config::{WakeOnLanConfig, WakeOnLanTarget};
+use tokio::net::UdpSocket;
+
+/// Parse MAC address separated with `:` or `-`
+pub fn parse_mac_address(mac_address: &str) -> Option<[u8; 6]> {
+    let octets: Vec<&str> = mac_address.split([':', '-']).collect();
+    if octets.len() != 6 {
+        return None;
+    }
+    let mut parsed = [0u8; 6];
+    for (index, octet) in octets.iter().enumerate() {
+        parsed[index] = u8::from_str_radix(octet, 16).ok()?;
+    }
+    Some(parsed)
+}
+
+/// Build a magic packet: 6 bytes of `0xFF` followed by 16 repetitions of MAC address
+pub fn build_magic_packet(mac_address: &[u8; 6]) -> Vec<u8> {
+    let mut packet = vec![0xFF; 6];
+    for _ in 0..16 {
+        packet.extend_from_slice(mac_address);
+    }
+    packet
+}
+
+pub async fn send_magic_packet(
+    mac_address: &[u8; 6],
+    broadcast_address: &str,
+) -> std::io::Result<()> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    socket.set_broadcast(true)?;
+    socket
+        .send_to(&build_magic_packet(mac_address), broadcast_address)
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WakeOutcome {
+    NoTargets,
+    // No magic packet could be sent
+    Failed,
+    // Names of targets that were sent a magic packet
+    Woken(Vec<String>),
+}
+
+/// Send magic packets to `targets`, skipping invalid MAC addresses
+pub async fn wake_targets(config: &WakeOnLanConfig, targets: Vec<WakeOnLanTarget>) -> WakeOutcome {
+    if targets.is_empty() {
+        return WakeOutcome::NoTargets;
+    }
+    let broadcast_address = config.get_broadcast_address();
+    let mut woken = Vec::new();
+    for target in targets {
+        let mac_address = match parse_mac_address(&target.mac_address) {
+            Some(mac_address) => mac_address,
+            None => {
+                tracing::warn!("Invalid MAC address of WoL target {}", target.name);
+                continue;
+            }
+        };
+        match send_magic_packet(&mac_address, &broadcast_address).await {
+            Ok(_) => woken.push(target.name),
+            Err(error) => tracing::warn!("Failed to wake {}: {}", target.name, error),
+        }
+    }
+    if woken.is_empty() {
+        return WakeOutcome::Failed;
+    }
+    WakeOutcome::Woken(woken)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mac_address() {
+        let expected = [0x00, 0x11, 0x22, 0xAA, 0xbb, 0xFF];
+        assert_eq!(parse_mac_address("00:11:22:aa:BB:ff"), Some(expected));
+        assert_eq!(parse_mac_address("00-11-22-aa-BB-ff"), Some(expected));
+        assert_eq!(parse_mac_address("00:11:22:aa:BB"), None);
+        assert_eq!(parse_mac_address("00:11:22:aa:BB:zz"), None);
+    }
+
+    #[test]
+    fn test_build_magic_packet() {
+        let mac_address = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55];
+        let packet = build_magic_packet(&mac_address);
+        assert_eq!(packet.len(), 102);
+        assert_eq!(&packet[..6], &[0xFF; 6]);
+        assert_eq!(&packet[6..12], &mac_address);
+        assert_eq!(&packet[96..], &mac_address);
+    }
+
+    #[tokio::test]
+    async fn test_send_magic_packet() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = receiver.local_addr().unwrap().to_string();
+        let mac_address = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55];
+        send_magic_packet(&mac_address, &address).await.unwrap();
+        let mut buffer = [0u8; 128];
+        let received = receiver.recv(&mut buffer).await.unwrap();
+        assert_eq!(&buffer[..received], build_magic_packet(&mac_address));
+    }
+}
@@ -0,0 +1,121 @@
+// Licensed under the Open Software License version 3.0
+use crate::introspection::TaskStatus;
+use chrono::{DateTime, Utc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+fn parse_timestamp(timestamp: Option<&str>) -> Option<DateTime<Utc>> {
+    let timestamp = DateTime::parse_from_rfc3339(timestamp?).ok()?;
+    Some(timestamp.with_timezone(&Utc))
+}
+
+/// Running loops whose current iteration started longer than their budget ago, with elapsed time
+///
+/// Loops that finished their poll and wait for the next one aren't overdue, loops that don't mark
+/// the end of a poll are measured from the start of their iteration
+pub fn overdue_tasks(
+    tasks: &BTreeMap<String, TaskStatus>,
+    budgets: &HashMap<String, Duration>,
+    now: DateTime<Utc>,
+) -> BTreeMap<String, Duration> {
+    let mut overdue = BTreeMap::new();
+    for (name, budget) in budgets {
+        let Some(task) = tasks.get(name) else {
+            continue;
+        };
+        if task.running == 0 {
+            continue;
+        }
+        let Some(last_iteration) = parse_timestamp(task.last_iteration.as_deref()) else {
+            continue;
+        };
+        let idle = parse_timestamp(task.last_idle.as_deref())
+            .map_or(false, |last_idle| last_idle >= last_iteration);
+        if idle {
+            continue;
+        }
+        let elapsed = (now - last_iteration).to_std().unwrap_or_default();
+        if elapsed > *budget {
+            overdue.insert(name.clone(), elapsed);
+        }
+    }
+    overdue
+}
+
+/// Growth of heap memory over `baseline` if it exceeds `budget`
+pub fn memory_growth_over_budget(baseline: u64, heap_bytes: u64, budget: u64) -> Option<u64> {
+    let growth = heap_bytes.saturating_sub(baseline);
+    (growth > budget).then_some(growth)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(running: u32, last_iteration: &str, last_idle: Option<&str>) -> TaskStatus {
+        TaskStatus {
+            running,
+            iterations: 1,
+            last_iteration: Some(String::from(last_iteration)),
+            last_idle: last_idle.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_overdue_tasks() {
+        let now = DateTime::parse_from_rfc3339("2023-07-01T12:10:00+00:00")
+            .unwrap()
+            .with_timezone(&Utc);
+        let tasks = BTreeMap::from([
+            (
+                String::from("nut"),
+                task(1, "2023-07-01T12:00:00+00:00", None),
+            ),
+            (
+                String::from("smart"),
+                task(1, "2023-07-01T12:09:00+00:00", None),
+            ),
+            (
+                String::from("hwmon"),
+                task(0, "2023-07-01T11:00:00+00:00", None),
+            ),
+            // Waiting for the next poll since 11:00:05
+            (
+                String::from("ipmi"),
+                task(
+                    1,
+                    "2023-07-01T11:00:00+00:00",
+                    Some("2023-07-01T11:00:05+00:00"),
+                ),
+            ),
+        ]);
+        let budgets = HashMap::from([
+            (String::from("nut"), Duration::from_secs(120)),
+            (String::from("smart"), Duration::from_secs(120)),
+            (String::from("hwmon"), Duration::from_secs(120)),
+            (String::from("ipmi"), Duration::from_secs(120)),
+            (String::from("modbus"), Duration::from_secs(120)),
+        ]);
+        assert_eq!(
+            overdue_tasks(&tasks, &budgets, now),
+            BTreeMap::from([(String::from("nut"), Duration::from_secs(600))])
+        );
+    }
+
+    #[test]
+    fn test_memory_growth_over_budget() {
+        let mib = 1024 * 1024;
+        assert_eq!(
+            memory_growth_over_budget(10 * mib, 20 * mib, 16 * mib),
+            None
+        );
+        assert_eq!(
+            memory_growth_over_budget(10 * mib, 30 * mib, 16 * mib),
+            Some(20 * mib)
+        );
+        // Shrinking below the baseline isn't growth
+        assert_eq!(memory_growth_over_budget(10 * mib, 5 * mib, 0), None);
+    }
+}
@@ -0,0 +1,68 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, time::Duration};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchdogConfig {
+    enabled: Option<bool>,
+    check_interval: Option<Duration>,
+    // Max time since the last iteration of a loop started, keyed by task name of /status/internal
+    iteration_budgets: Option<HashMap<String, Duration>>,
+    // Max growth of heap memory in bytes since the first check, keyed by module name
+    memory_growth_budgets: Option<HashMap<String, u64>>,
+    // Restart a module that exceeds its budget instead of only logging it
+    restart: Option<bool>,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            check_interval: Some(Duration::from_secs(30)),
+            iteration_budgets: None,
+            memory_growth_budgets: None,
+            restart: Some(false),
+        }
+    }
+}
+
+impl Example for WatchdogConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(false),
+            check_interval: Some(Duration::from_secs(30)),
+            iteration_budgets: Some(HashMap::from([
+                (String::from("nut"), Duration::from_secs(120)),
+                (String::from("smart"), Duration::from_secs(900)),
+            ])),
+            memory_growth_budgets: Some(HashMap::from([(
+                String::from("lorawan"),
+                64 * 1024 * 1024,
+            )])),
+            restart: Some(false),
+        }
+    }
+}
+
+impl WatchdogConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_check_interval(&self) -> Duration {
+        self.check_interval.unwrap_or(Duration::from_secs(30))
+    }
+
+    pub fn get_iteration_budgets(&self) -> HashMap<String, Duration> {
+        self.iteration_budgets.clone().unwrap_or_default()
+    }
+
+    pub fn get_memory_growth_budgets(&self) -> HashMap<String, u64> {
+        self.memory_growth_budgets.clone().unwrap_or_default()
+    }
+
+    pub fn get_restart(&self) -> bool {
+        self.restart.unwrap_or_default()
+    }
+}
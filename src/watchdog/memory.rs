@@ -0,0 +1,178 @@
+// Licensed under the Open Software License version 3.0
+//! Heap memory of every supervised module
+//!
+//! With the `module-memory` feature the global allocator prefixes every allocation with the module
+//! whose task was being polled, so memory freed by another module (ex. a sink dropping a snapshot
+//! of a source) is still taken off the module that allocated it. Tasks spawned by a module on
+//! their own and blocking threads aren't attributed to it.
+use std::{
+    cell::Cell,
+    collections::BTreeMap,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    task::{Context, Poll},
+};
+
+// Index 0 collects memory allocated outside of supervised modules
+const MAX_MODULES: usize = 64;
+
+static HEAP_BYTES: [AtomicU64; MAX_MODULES] = [const { AtomicU64::new(0) }; MAX_MODULES];
+// Module at index + 1
+static MODULES: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+thread_local! {
+    // Without a destructor, so it can be read by the allocator at any time
+    static CURRENT: Cell<usize> = const { Cell::new(0) };
+}
+
+fn current_module() -> usize {
+    CURRENT.try_with(Cell::get).unwrap_or(0)
+}
+
+/// Modules over `MAX_MODULES` are left unattributed
+fn module_index(name: &'static str) -> usize {
+    let mut modules = MODULES.lock().unwrap_or_else(|error| error.into_inner());
+    match modules.iter().position(|module| *module == name) {
+        Some(index) => index + 1,
+        None if modules.len() + 1 < MAX_MODULES => {
+            modules.push(name);
+            modules.len()
+        }
+        None => 0,
+    }
+}
+
+/// Heap bytes held by every module that was started, zero without the `module-memory` feature
+pub fn heap_bytes() -> BTreeMap<&'static str, u64> {
+    let modules = MODULES.lock().unwrap_or_else(|error| error.into_inner());
+    modules
+        .iter()
+        .enumerate()
+        .map(|(index, module)| (*module, HEAP_BYTES[index + 1].load(Ordering::Relaxed)))
+        .collect()
+}
+
+/// Restores the module of the outer future, even if the inner one panics
+struct Restore(usize);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let _ = CURRENT.try_with(|current| current.set(self.0));
+    }
+}
+
+/// Future that attributes memory allocated while it's polled to its module
+pub struct Accounted<F> {
+    module: usize,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Accounted<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let _restore = Restore(CURRENT.with(|current| current.replace(self.module)));
+        self.future.as_mut().poll(cx)
+    }
+}
+
+pub fn accounted<F: Future>(module: &'static str, future: F) -> Accounted<F> {
+    Accounted {
+        module: module_index(module),
+        future: Box::pin(future),
+    }
+}
+
+#[cfg(feature = "module-memory")]
+pub use allocator::ModuleAllocator;
+
+#[cfg(feature = "module-memory")]
+mod allocator {
+    use super::{current_module, HEAP_BYTES};
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        mem::size_of,
+        ptr::null_mut,
+        sync::atomic::Ordering,
+    };
+
+    // Room for the module index, allocations aligned to more get a larger prefix
+    const HEADER: usize = 16;
+
+    /// Layout with the prefix and offset of the returned pointer
+    fn with_header(layout: Layout) -> Option<(Layout, usize)> {
+        let offset = layout.align().max(HEADER);
+        let outer = Layout::from_size_align(layout.size().checked_add(offset)?, offset).ok()?;
+        Some((outer, offset))
+    }
+
+    /// Module index is stored right before the returned pointer
+    unsafe fn owner(ptr: *mut u8) -> *mut usize {
+        ptr.sub(size_of::<usize>()) as *mut usize
+    }
+
+    /// System allocator that counts heap bytes of every module
+    pub struct ModuleAllocator;
+
+    // SAFETY: every allocation is made by System with a layout derived only from the requested
+    // one, so dealloc and realloc can derive the same layout and offset again
+    unsafe impl GlobalAlloc for ModuleAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let Some((outer, offset)) = with_header(layout) else {
+                return null_mut();
+            };
+            let base = System.alloc(outer);
+            if base.is_null() {
+                return base;
+            }
+            let ptr = base.add(offset);
+            let module = current_module();
+            owner(ptr).write(module);
+            HEAP_BYTES[module].fetch_add(layout.size() as u64, Ordering::Relaxed);
+            ptr
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            // Can't fail, the same layout was allocated
+            let (outer, offset) = with_header(layout).unwrap();
+            let module = owner(ptr).read();
+            HEAP_BYTES[module].fetch_sub(layout.size() as u64, Ordering::Relaxed);
+            System.dealloc(ptr.sub(offset), outer);
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let (outer, offset) = with_header(layout).unwrap();
+            let Some(new_outer_size) = new_size.checked_add(offset) else {
+                return null_mut();
+            };
+            let base = System.realloc(ptr.sub(offset), outer, new_outer_size);
+            if base.is_null() {
+                return base;
+            }
+            // Memory stays with the module that allocated it first
+            let ptr = base.add(offset);
+            let module = owner(ptr).read();
+            HEAP_BYTES[module].fetch_add(new_size as u64, Ordering::Relaxed);
+            HEAP_BYTES[module].fetch_sub(layout.size() as u64, Ordering::Relaxed);
+            ptr
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_accounted_sets_module() {
+        let module = accounted("test_module", async { current_module() }).await;
+        assert_eq!(module, module_index("test_module"));
+        assert_ne!(module, 0);
+        assert_eq!(current_module(), 0);
+        assert!(heap_bytes().contains_key("test_module"));
+    }
+}
@@ -0,0 +1,6 @@
+// Licensed under the Open Software License version 3.0
+//! Opt-in budgets of module iteration time and memory growth, logged or enforced with a restart
+pub mod budget;
+pub mod config;
+pub mod memory;
+pub mod watcher;
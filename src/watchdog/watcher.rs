@@ -0,0 +1,123 @@
+// Licensed under the Open Software License version 3.0
+use super::{
+    budget::{memory_growth_over_budget, overdue_tasks},
+    config::WatchdogConfig,
+    memory::heap_bytes,
+};
+use crate::{
+    dedup_log::{info_resolved, warn_deduplicated},
+    hot_reload::supervisor::request_module_restart,
+    introspection,
+};
+use std::collections::{BTreeSet, HashMap};
+use tokio::{sync::broadcast, time::sleep};
+
+const MIB: f64 = 1024.0 * 1024.0;
+
+/// Supervised module that runs loops of the task, most are named the same
+fn module_of(task: &str) -> &str {
+    match task {
+        "nut" => "ups_monitoring",
+        task => task,
+    }
+}
+
+/// Modules that exceeded any budget, logged once until they're met again
+fn check_budgets(
+    config: &WatchdogConfig,
+    baselines: &mut HashMap<String, u64>,
+) -> BTreeSet<String> {
+    let budgets = config.get_iteration_budgets();
+    let overdue = overdue_tasks(
+        &introspection::snapshot().tasks,
+        &budgets,
+        chrono::Utc::now(),
+    );
+    for (name, budget) in &budgets {
+        let key = format!("watchdog:{}", name);
+        match overdue.get(name) {
+            Some(elapsed) => warn_deduplicated!(
+                key,
+                "Iteration of {} has been running for {}s, budget is {}s",
+                name,
+                elapsed.as_secs(),
+                budget.as_secs()
+            ),
+            None => info_resolved!(key, "{} is within its iteration budget again", name),
+        }
+    }
+    let mut exceeded: BTreeSet<String> = overdue
+        .keys()
+        .map(|task| String::from(module_of(task)))
+        .collect();
+    let heap_bytes = heap_bytes();
+    for (module, budget) in config.get_memory_growth_budgets() {
+        // Not started yet
+        let Some(bytes) = heap_bytes.get(module.as_str()).copied() else {
+            continue;
+        };
+        let baseline = *baselines.entry(module.clone()).or_insert(bytes);
+        let key = format!("watchdog:memory:{}", module);
+        match memory_growth_over_budget(baseline, bytes, budget) {
+            Some(growth) => {
+                warn_deduplicated!(
+                    key,
+                    "Heap memory of {} grew by {:.1} MiB to {:.1} MiB, budget is {:.1} MiB",
+                    module,
+                    growth as f64 / MIB,
+                    bytes as f64 / MIB,
+                    budget as f64 / MIB
+                );
+                exceeded.insert(module);
+            }
+            None => info_resolved!(
+                key,
+                "Memory growth of {} is within its budget again",
+                module
+            ),
+        }
+    }
+    exceeded
+}
+
+pub async fn start_watchdog_loop(mut shutdown_rx: broadcast::Receiver<()>, config: WatchdogConfig) {
+    // Check if module is enabled
+    if !config.is_enabled() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+    tracing::debug!("Starting watchdog loop");
+    if !cfg!(feature = "module-memory") && !config.get_memory_growth_budgets().is_empty() {
+        tracing::warn!("Memory budgets require building with --features module-memory");
+    }
+    let _task = introspection::task_started("watchdog");
+    // Module name -> heap bytes on the first check, after the module has started
+    let mut baselines = HashMap::new();
+    loop {
+        tokio::select! {
+            _ = shutdown_rx.recv() => {
+                tracing::trace!("Shutting down watchdog loop");
+                break;
+            }
+            _ = sleep(config.get_check_interval()) => {}
+        }
+        introspection::mark_iteration("watchdog");
+        let exceeded = check_budgets(&config, &mut baselines);
+        if config.get_restart() {
+            for module in exceeded {
+                if !request_module_restart(&module) {
+                    warn_deduplicated!(
+                        format!("watchdog:restart:{}", module),
+                        "{} exceeded its budget but can't be restarted on its own",
+                        module
+                    );
+                    continue;
+                }
+                tracing::error!("{} exceeded its budget, restarting it", module);
+                // Memory freed by the restart is measured again
+                baselines.remove(&module);
+            }
+        }
+        introspection::mark_idle("watchdog");
+    }
+}
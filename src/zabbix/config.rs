@@ -0,0 +1,75 @@
+// Licensed under the Open Software License version 3.0
+use crate::config::types::Example;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ZabbixConfig {
+    enabled: Option<bool>,
+    // Zabbix server or proxy trapper, items aren't sent if not set
+    server: Option<String>,
+    // Host items belong to, has to match host name in Zabbix
+    host: Option<String>,
+    // Prepended to every item key, ex. "uds.temperature[<id>]"
+    key_prefix: Option<String>,
+    // Latest items are sent at most this often
+    interval: Option<Duration>,
+    // Also write items in zabbix_sender input format (-T -i <file>)
+    batch_file: Option<String>,
+}
+
+impl Default for ZabbixConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Some(false),
+            server: None,
+            host: Some(String::from("universal-data-source")),
+            key_prefix: Some(String::from("uds")),
+            interval: Some(Duration::from_secs(60)),
+            batch_file: None,
+        }
+    }
+}
+
+impl Example for ZabbixConfig {
+    fn example() -> Self {
+        Self {
+            enabled: Some(false),
+            server: Some(String::from("zabbix.lan:10051")),
+            host: Some(String::from("basement-pi")),
+            key_prefix: Some(String::from("uds")),
+            interval: Some(Duration::from_secs(60)),
+            batch_file: None,
+        }
+    }
+}
+
+impl ZabbixConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or_default()
+    }
+
+    pub fn get_server(&self) -> Option<String> {
+        self.server.clone()
+    }
+
+    pub fn get_host(&self) -> String {
+        self.host
+            .clone()
+            .unwrap_or_else(|| String::from("universal-data-source"))
+    }
+
+    pub fn get_key_prefix(&self) -> String {
+        self.key_prefix
+            .clone()
+            .unwrap_or_else(|| String::from("uds"))
+    }
+
+    pub fn get_interval(&self) -> Duration {
+        self.interval.unwrap_or(Duration::from_secs(60))
+    }
+
+    pub fn get_batch_file(&self) -> Option<String> {
+        self.batch_file.clone()
+    }
+}
@@ -0,0 +1,271 @@
+// Licensed under the Open Software License version 3.0
+//! Flat Zabbix items keyed by hw.id and value name
+use crate::{
+    hardware::{reading::Reading, types::HardwareMetadata},
+    nut::sender::UninterruptiblePowerSupplyData,
+    one_wire::sender::MeasuredTemperature,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Single value of a trapper item
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ZabbixItem {
+    pub host: String,
+    pub key: String,
+    pub value: String,
+    pub clock: i64,
+    pub ns: u32,
+}
+
+/// Key parameters containing separators have to be quoted
+fn quote_parameter(parameter: &str) -> String {
+    let needs_quotes = parameter.contains([',', ']', '"'])
+        || parameter.starts_with(['[', ' '])
+        || parameter.is_empty();
+    if !needs_quotes {
+        return String::from(parameter);
+    }
+    format!("\"{}\"", parameter.replace('"', "\\\""))
+}
+
+pub fn item_key(prefix: &str, category: &str, parameters: &[&str]) -> String {
+    let parameters: Vec<String> = parameters
+        .iter()
+        .map(|parameter| quote_parameter(parameter))
+        .collect();
+    format!("{}.{}[{}]", prefix, category, parameters.join(","))
+}
+
+/// Time of the read, or `now` for computed values
+fn clock(meta: &HardwareMetadata, now: DateTime<Utc>) -> (i64, u32) {
+    let time = meta
+        .measured_at
+        .as_deref()
+        .and_then(|measured_at| DateTime::parse_from_rfc3339(measured_at).ok())
+        .map(|measured_at| measured_at.with_timezone(&Utc))
+        .unwrap_or(now);
+    (time.timestamp(), time.timestamp_subsec_nanos())
+}
+
+/// Build items of a single piece of hardware
+struct ItemBuilder<'a> {
+    host: &'a str,
+    prefix: &'a str,
+    clock: (i64, u32),
+}
+
+impl ItemBuilder<'_> {
+    fn item(&self, category: &str, parameters: &[&str], value: String) -> ZabbixItem {
+        ZabbixItem {
+            host: String::from(self.host),
+            key: item_key(self.prefix, category, parameters),
+            value,
+            clock: self.clock.0,
+            ns: self.clock.1,
+        }
+    }
+}
+
+/// `<prefix>.temperature[<id>]`, sensors with an expected range also get
+/// `<prefix>.temperature_in_range[<id>]` and `<prefix>.temperature_range_violations[<id>]`
+pub fn temperature_items(
+    host: &str,
+    prefix: &str,
+    sensors: &[MeasuredTemperature],
+    now: DateTime<Utc>,
+) -> Vec<ZabbixItem> {
+    sensors
+        .iter()
+        .flat_map(|sensor| {
+            let builder = ItemBuilder {
+                host,
+                prefix,
+                clock: clock(&sensor.meta, now),
+            };
+            let id = [sensor.meta.hw.id.as_str()];
+            let mut items = Vec::new();
+            if let Some(temperature) = sensor.temperature {
+                items.push(builder.item("temperature", &id, temperature.to_string()));
+            }
+            if let Some(quality) = &sensor.quality {
+                items.push(builder.item(
+                    "temperature_in_range",
+                    &id,
+                    (quality.in_range as u8).to_string(),
+                ));
+                items.push(builder.item(
+                    "temperature_range_violations",
+                    &id,
+                    quality.violations.to_string(),
+                ));
+            }
+            items
+        })
+        .collect()
+}
+
+/// `<prefix>.ups[<id>,<variable>]`
+pub fn ups_items(
+    host: &str,
+    prefix: &str,
+    upses: &[UninterruptiblePowerSupplyData],
+    now: DateTime<Utc>,
+) -> Vec<ZabbixItem> {
+    upses
+        .iter()
+        .flat_map(|ups| {
+            let builder = ItemBuilder {
+                host,
+                prefix,
+                clock: clock(&ups.meta, now),
+            };
+            ups.variables
+                .iter()
+                .map(|(variable, value)| {
+                    builder.item("ups", &[&ups.meta.hw.id, variable], value.clone())
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// `<prefix>.reading[<id>,<value name>]`
+pub fn reading_items(
+    host: &str,
+    prefix: &str,
+    readings: &[Reading],
+    now: DateTime<Utc>,
+) -> Vec<ZabbixItem> {
+    readings
+        .iter()
+        .flat_map(|reading| {
+            let builder = ItemBuilder {
+                host,
+                prefix,
+                clock: clock(&reading.meta, now),
+            };
+            reading
+                .values
+                .iter()
+                .map(|(name, value)| {
+                    builder.item("reading", &[&reading.meta.hw.id, name], value.to_string())
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Field of zabbix_sender input, quoted if it contains whitespace or quotes
+fn batch_field(field: &str) -> String {
+    let needs_quotes = field.is_empty()
+        || field.contains(|character: char| character.is_whitespace())
+        || field.contains(['"', '\\']);
+    if !needs_quotes {
+        return String::from(field);
+    }
+    format!(
+        "\"{}\"",
+        field
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+/// Lines of `<host> <key> <timestamp> <value>`, input of `zabbix_sender -T -i <file>`
+pub fn batch_lines(items: &[ZabbixItem]) -> String {
+    items
+        .iter()
+        .map(|item| {
+            format!(
+                "{} {} {} {}\n",
+                batch_field(&item.host),
+                batch_field(&item.key),
+                item.clock,
+                batch_field(&item.value)
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::types::Example, quality::range::ReadingQuality};
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2023-01-01T00:00:00+00:00")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_item_key() {
+        assert_eq!(
+            item_key("uds", "temperature", &["28-00000a0b0c0d"]),
+            "uds.temperature[28-00000a0b0c0d]"
+        );
+        assert_eq!(
+            item_key("uds", "ups", &["[ups1]monitor@localhost:3493", "ups.load"]),
+            r#"uds.ups["[ups1]monitor@localhost:3493",ups.load]"#
+        );
+        assert_eq!(
+            item_key("uds", "reading", &["a,\"b\"", "c"]),
+            r#"uds.reading["a,\"b\"",c]"#
+        );
+    }
+
+    #[test]
+    fn test_items() {
+        let mut sensor = MeasuredTemperature::example();
+        sensor.meta.measured_at = Some(String::from("2023-01-01T00:00:05.5+00:00"));
+        let items = temperature_items("pi", "uds", &[sensor], now());
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].key, "uds.temperature[fake_hw_id]");
+        assert_eq!(items[0].clock, now().timestamp() + 5);
+        assert_eq!(items[0].ns, 500_000_000);
+
+        let mut sensor = MeasuredTemperature::example();
+        sensor.quality = Some(ReadingQuality {
+            in_range: false,
+            violations: 2,
+        });
+        let items = temperature_items("pi", "uds", &[sensor], now());
+        assert_eq!(items[1].key, "uds.temperature_in_range[fake_hw_id]");
+        assert_eq!(items[1].value, "0");
+        assert_eq!(items[2].key, "uds.temperature_range_violations[fake_hw_id]");
+        assert_eq!(items[2].value, "2");
+
+        let items = ups_items(
+            "pi",
+            "uds",
+            &[UninterruptiblePowerSupplyData::example()],
+            now(),
+        );
+        assert!(items
+            .iter()
+            .any(|item| item.key == "uds.ups[fake_hw_id,ups.load]" && item.value == "15"));
+        // Computed values don't have measured_at
+        assert!(items.iter().all(|item| item.clock == now().timestamp()));
+
+        let items = reading_items("pi", "uds", &[Reading::example()], now());
+        assert_eq!(items[0].key, "uds.reading[fake_hw_id,rss_bytes]");
+        assert_eq!(items[0].value, "1024");
+    }
+
+    #[test]
+    fn test_batch_lines() {
+        let item = ZabbixItem {
+            host: String::from("basement pi"),
+            key: String::from(r#"uds.ups["[ups1]a",ups.status]"#),
+            value: String::from("OL CHRG"),
+            clock: 1672531200,
+            ns: 0,
+        };
+        assert_eq!(
+            batch_lines(&[item]),
+            "\"basement pi\" \"uds.ups[\\\"[ups1]a\\\",ups.status]\" 1672531200 \"OL CHRG\"\n"
+        );
+    }
+}
@@ -0,0 +1,155 @@
+// Licensed under the Open Software License version 3.0
+//! Zabbix sender protocol over TCP, same as `zabbix_sender`
+use super::items::ZabbixItem;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    time::timeout,
+};
+
+const HEADER: &[u8; 5] = b"ZBXD\x01";
+/// Protocol signature, flags and 8 bytes of data length
+const HEADER_LENGTH: usize = 13;
+/// Responses are short summaries, anything longer isn't from Zabbix
+const MAX_RESPONSE_LENGTH: u64 = 64 * 1024;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Serialize)]
+struct SenderRequest<'a> {
+    request: &'static str,
+    data: &'a [ZabbixItem],
+}
+
+#[derive(Debug, Deserialize)]
+struct SenderResponse {
+    response: String,
+    info: Option<String>,
+}
+
+pub fn encode_request(items: &[ZabbixItem]) -> Vec<u8> {
+    let request = SenderRequest {
+        request: "sender data",
+        data: items,
+    };
+    let json = serde_json::to_vec(&request).unwrap_or_default();
+    let mut packet = Vec::with_capacity(HEADER_LENGTH + json.len());
+    packet.extend_from_slice(HEADER);
+    packet.extend_from_slice(&(json.len() as u64).to_le_bytes());
+    packet.extend_from_slice(&json);
+    packet
+}
+
+/// Length of data following the header
+pub fn decode_header(header: &[u8; HEADER_LENGTH]) -> Result<u64, String> {
+    if &header[..4] != b"ZBXD" {
+        return Err(String::from("invalid response header"));
+    }
+    let length = u64::from_le_bytes(header[5..].try_into().unwrap());
+    if length > MAX_RESPONSE_LENGTH {
+        return Err(format!("response of {} bytes is too long", length));
+    }
+    Ok(length)
+}
+
+/// `info` of a successful response, ex. "processed: 3; failed: 0; total: 3; ..."
+pub fn decode_response(data: &[u8]) -> Result<String, String> {
+    let response: SenderResponse =
+        serde_json::from_slice(data).map_err(|error| error.to_string())?;
+    let info = response.info.unwrap_or_default();
+    if response.response != "success" {
+        return Err(format!(
+            "server responded with {}: {}",
+            response.response, info
+        ));
+    }
+    Ok(info)
+}
+
+async fn exchange(server: &str, packet: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut stream = TcpStream::connect(server).await?;
+    stream.write_all(packet).await?;
+    let mut header = [0u8; HEADER_LENGTH];
+    stream.read_exact(&mut header).await?;
+    let length = decode_header(&header)
+        .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))?;
+    let mut data = vec![0u8; length as usize];
+    stream.read_exact(&mut data).await?;
+    Ok(data)
+}
+
+/// Send items to a Zabbix server or proxy, returns `info` of its response
+pub async fn send_items(server: &str, items: &[ZabbixItem]) -> Result<String, String> {
+    let packet = encode_request(items);
+    crate::bandwidth::limiter::acquire("zabbix", packet.len()).await;
+    let data = timeout(TIMEOUT, exchange(server, &packet))
+        .await
+        .map_err(|_| String::from("timed out"))?
+        .map_err(|error| error.to_string())?;
+    decode_response(&data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    fn item() -> ZabbixItem {
+        ZabbixItem {
+            host: String::from("pi"),
+            key: String::from("uds.temperature[28-00000a0b0c0d]"),
+            value: String::from("21.5"),
+            clock: 1672531200,
+            ns: 0,
+        }
+    }
+
+    #[test]
+    fn test_encode_request() {
+        let packet = encode_request(&[item()]);
+        assert_eq!(&packet[..5], HEADER);
+        let length = u64::from_le_bytes(packet[5..13].try_into().unwrap());
+        assert_eq!(length as usize, packet.len() - HEADER_LENGTH);
+        let json: serde_json::Value = serde_json::from_slice(&packet[HEADER_LENGTH..]).unwrap();
+        assert_eq!(json["request"], "sender data");
+        assert_eq!(json["data"][0]["key"], "uds.temperature[28-00000a0b0c0d]");
+        assert_eq!(json["data"][0]["clock"], 1672531200);
+    }
+
+    #[test]
+    fn test_decode_response() {
+        let info = decode_response(
+            br#"{"response":"success","info":"processed: 1; failed: 0; total: 1; seconds spent: 0.000055"}"#,
+        );
+        assert!(info.unwrap().starts_with("processed: 1"));
+        assert!(decode_response(br#"{"response":"failed","info":"no host"}"#).is_err());
+        let mut header = [0u8; HEADER_LENGTH];
+        header[..5].copy_from_slice(HEADER);
+        header[5..].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(decode_header(&header).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_send_items() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server = listener.local_addr().unwrap().to_string();
+        let trapper = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut header = [0u8; HEADER_LENGTH];
+            stream.read_exact(&mut header).await.unwrap();
+            let mut data = vec![0u8; decode_header(&header).unwrap() as usize];
+            stream.read_exact(&mut data).await.unwrap();
+            let response = br#"{"response":"success","info":"processed: 1; failed: 0; total: 1"}"#;
+            let mut packet = HEADER.to_vec();
+            packet.extend_from_slice(&(response.len() as u64).to_le_bytes());
+            packet.extend_from_slice(response);
+            stream.write_all(&packet).await.unwrap();
+            data
+        });
+        let info = send_items(&server, &[item()]).await.unwrap();
+        assert_eq!(info, "processed: 1; failed: 0; total: 1");
+        let request: serde_json::Value = serde_json::from_slice(&trapper.await.unwrap()).unwrap();
+        assert_eq!(request["data"][0]["host"], "pi");
+    }
+}
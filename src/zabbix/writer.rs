@@ -0,0 +1,106 @@
+// Licensed under the Open Software License version 3.0
+use super::{
+    config::ZabbixConfig,
+    items::{batch_lines, reading_items, temperature_items, ups_items, ZabbixItem},
+    protocol::send_items,
+};
+use crate::{
+    dedup_log::{info_resolved, warn_deduplicated},
+    hardware::reading::{ReadingsByPublisher, ReadingsUpdate},
+    introspection,
+    nut::sender::{UpsUpdate, UpsesByPublisher},
+    one_wire::sender::MeasuredTemperature,
+    self_metrics::lag::recv_counting_lag,
+};
+use chrono::Utc;
+use std::{cmp::max, time::Duration};
+use tokio::{sync::broadcast, time::interval};
+
+/// Latest data of every category, flushed as items on every interval
+#[derive(Debug, Default)]
+struct Latest {
+    sensors: Vec<MeasuredTemperature>,
+    upses: UpsesByPublisher,
+    readings: ReadingsByPublisher,
+}
+
+impl Latest {
+    fn items(&self, host: &str, prefix: &str) -> Vec<ZabbixItem> {
+        let now = Utc::now();
+        let mut items = temperature_items(host, prefix, &self.sensors, now);
+        items.extend(ups_items(host, prefix, &self.upses.all(), now));
+        items.extend(reading_items(host, prefix, &self.readings.all(), now));
+        items
+    }
+}
+
+/// Replace batch file atomically, so zabbix_sender never reads a partial one
+async fn write_batch_file(path: &str, items: &[ZabbixItem]) -> std::io::Result<()> {
+    let temporary = format!("{}.tmp", path);
+    tokio::fs::write(&temporary, batch_lines(items)).await?;
+    tokio::fs::rename(&temporary, path).await
+}
+
+async fn flush(config: &ZabbixConfig, items: &[ZabbixItem]) {
+    if items.is_empty() {
+        return;
+    }
+    if let Some(server) = config.get_server() {
+        let key = format!("zabbix:{}", server);
+        match send_items(&server, items).await {
+            Ok(info) => {
+                info_resolved!(key, "Zabbix server {} is accepting items again", server);
+                tracing::trace!("Zabbix server {} processed items: {}", server, info);
+            }
+            Err(error) => {
+                warn_deduplicated!(key, "Failed to send items to {}: {}", server, error);
+            }
+        }
+    }
+    if let Some(path) = config.get_batch_file() {
+        let key = format!("zabbix:{}", path);
+        match write_batch_file(&path, items).await {
+            Ok(()) => info_resolved!(key, "Writing {} again", path),
+            Err(error) => warn_deduplicated!(key, "Failed to write {}: {}", path, error),
+        }
+    }
+}
+
+pub async fn start_zabbix_sink_loop(
+    mut shutdown_rx: broadcast::Receiver<()>,
+    config: ZabbixConfig,
+    mut one_wire_rx: broadcast::Receiver<Vec<MeasuredTemperature>>,
+    mut ups_monitoring_rx: broadcast::Receiver<UpsUpdate>,
+    mut readings_rx: broadcast::Receiver<ReadingsUpdate>,
+) {
+    // Check if module is enabled
+    if !config.is_enabled() {
+        tracing::trace!("Module is disabled");
+        return;
+    }
+    if config.get_server().is_none() && config.get_batch_file().is_none() {
+        tracing::error!("Zabbix sink is enabled but neither server nor batch_file is set");
+        return;
+    }
+    let host = config.get_host();
+    let prefix = config.get_key_prefix();
+    let mut latest = Latest::default();
+    let mut flush_interval = interval(max(config.get_interval(), Duration::from_secs(1)));
+    tracing::debug!("Starting Zabbix sink loop");
+    let _task = introspection::task_started("zabbix");
+    loop {
+        tokio::select! {
+            Ok(sensors) = recv_counting_lag(&mut one_wire_rx) => latest.sensors = sensors,
+            Ok(update) = recv_counting_lag(&mut ups_monitoring_rx) => latest.upses.update(update),
+            Ok(update) = recv_counting_lag(&mut readings_rx) => latest.readings.update(update),
+            _ = flush_interval.tick() => {
+                introspection::mark_iteration("zabbix");
+                flush(&config, &latest.items(&host, &prefix)).await;
+            }
+            _ = shutdown_rx.recv() => {
+                tracing::trace!("Shutting down Zabbix sink loop");
+                break;
+            }
+        }
+    }
+}
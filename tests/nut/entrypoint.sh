@@ -0,0 +1,5 @@
+#!/bin/sh
+set -e
+mkdir -p /run/nut /var/run/nut
+upsdrvctl -u root start
+exec upsd -u root -F